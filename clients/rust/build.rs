@@ -0,0 +1,362 @@
+//! Renders the client (`$OUT_DIR/generated.rs`) from the program's Shank annotations, the same ones the IDL is built from
+//! (see `scripts/idl.sh`): instructions from `IdlInstruction`, accounts from `ShankAccount`s, types from `ShankType`s.
+//! Generating at build time keeps the client in step with the program without a checked-in copy to regenerate.
+use std::{env, fs, path::{Path, PathBuf}};
+use proc_macro2::{Ident, Span, TokenStream};
+use quote::{format_ident, quote};
+use syn::{
+    punctuated::Punctuated, Attribute, Expr, ExprLit, Fields, Item, ItemEnum, ItemStruct, Lit, Meta, Token, Type
+};
+
+
+/// Program sources the annotations are read from, relative to this crate.
+const PROGRAM_SOURCES: [&str; 3] = ["../../src/idl.rs", "../../src/state.rs", "../../src/name_service.rs"];
+
+/// Default program id, the one `scripts/idl.sh` writes into the IDL. Overridden with `PROGRAM_ID=<base58>`, or else
+/// with the program's own `ESCROW_LOCALNET_PROGRAM_ID=<base58>` (see `cluster::LOCALNET_PROGRAM_ID`).
+const DEFAULT_PROGRAM_ID: &str = "E6v3tbZyZAthzd5JCPJgd3TmLXL3VirKxib9XHjyKTjL";
+
+/// PDAs of the program, mirroring its `get_*_seeds` helpers: name, then its seeds, either constants or pubkey arguments.
+const PDAS: [(&str, &[Seed]); 6] = [
+    ("config", &[Seed::Constant("config")]),
+    ("counter", &[Seed::Constant("counter")]),
+    ("fee_vault", &[Seed::Constant("fee_vault")]),
+    ("escrow", &[Seed::Constant("escrow"), Seed::Pubkey("payer"), Seed::Pubkey("recipient")]),
+    ("token_escrow", &[Seed::Constant("escrow"), Seed::Pubkey("payer"), Seed::Pubkey("recipient"), Seed::Pubkey("mint")]),
+    ("note", &[Seed::Constant("note"), Seed::Pubkey("escrow")])
+];
+
+enum Seed {
+    Constant(&'static str),
+    Pubkey(&'static str)
+}
+
+struct IdlAccount {
+    index: usize,
+    name: String,
+    writable: bool,
+    signer: bool,
+    optional: bool
+}
+
+
+fn main() {
+    let items: Vec<Item> = PROGRAM_SOURCES.iter()
+        .flat_map(|path| {
+            println!("cargo:rerun-if-changed={}", path);
+            let source: String = fs::read_to_string(path).unwrap_or_else(|e| panic!("can't read {}: {}", path, e));
+            syn::parse_file(&source).unwrap_or_else(|e| panic!("can't parse {}: {}", path, e)).items
+        })
+        .collect();
+    println!("cargo:rerun-if-env-changed=PROGRAM_ID");
+    println!("cargo:rerun-if-env-changed=ESCROW_LOCALNET_PROGRAM_ID");
+
+    let program_id: String = env::var("PROGRAM_ID")
+        .or_else(|_| env::var("ESCROW_LOCALNET_PROGRAM_ID"))
+        .unwrap_or_else(|_| DEFAULT_PROGRAM_ID.to_string());
+    let pdas: Vec<TokenStream> = PDAS.iter().map(|(name, seeds)| render_pda(name, seeds)).collect();
+    let types: Vec<TokenStream> = items.iter().filter_map(render_type).collect();
+    let accounts: Vec<TokenStream> = items.iter().filter_map(|item| render_account(item, &items)).collect();
+    let instructions: Vec<TokenStream> = items.iter()
+        .find_map(|item| match item {
+            Item::Enum(item) if derives(&item.attrs, "ShankInstruction") => Some(item),
+            _ => None
+        })
+        .expect("no `ShankInstruction` enum in the program sources")
+        .variants
+        .iter()
+        .map(render_instruction)
+        .collect();
+
+    let generated: TokenStream = quote! {
+        use borsh::{BorshDeserialize, BorshSerialize};
+        use solana_program::{instruction::{AccountMeta, Instruction}, pubkey::Pubkey};
+
+        /// The escrow program's id.
+        pub const ESCROW_ID: Pubkey = solana_program::pubkey!(#program_id);
+
+        pub mod pdas {
+            use super::*;
+            #(#pdas)*
+        }
+
+        pub mod types {
+            use super::*;
+            #(#types)*
+        }
+
+        pub mod accounts {
+            use super::*;
+            use super::types::*;
+            #(#accounts)*
+        }
+
+        pub mod instructions {
+            use super::*;
+            use super::types::*;
+            #(#instructions)*
+        }
+    };
+
+    let out_path: PathBuf = Path::new(&env::var("OUT_DIR").unwrap()).join("generated.rs");
+    fs::write(out_path, generated.to_string()).unwrap();
+}
+
+/// `find_<name>_pda(<pubkey seeds>) -> (Pubkey, u8)`.
+fn render_pda(name: &str, seeds: &[Seed]) -> TokenStream {
+    let function: Ident = format_ident!("find_{}_pda", name);
+    let arguments: Vec<Ident> = seeds.iter()
+        .filter_map(|seed| match seed {
+            Seed::Pubkey(argument) => Some(format_ident!("{}", argument)),
+            Seed::Constant(_) => None
+        })
+        .collect();
+    let seeds: Vec<TokenStream> = seeds.iter()
+        .map(|seed| match seed {
+            Seed::Constant(constant) => {
+                let constant: syn::LitByteStr = syn::LitByteStr::new(constant.as_bytes(), Span::call_site());
+                quote! { #constant }
+            },
+            Seed::Pubkey(argument) => {
+                let argument: Ident = format_ident!("{}", argument);
+                quote! { #argument.as_ref() }
+            }
+        })
+        .collect();
+
+    quote! {
+        pub fn #function(#(#arguments: &Pubkey),*) -> (Pubkey, u8) {
+            Pubkey::find_program_address(&[#(#seeds),*], &ESCROW_ID)
+        }
+    }
+}
+
+/// `ShankType`s, as borsh types.
+fn render_type(item: &Item) -> Option<TokenStream> {
+    match item {
+        Item::Enum(item) if derives(&item.attrs, "ShankType") => {
+            let ItemEnum { ident, variants, .. } = item;
+            let docs: Vec<&Attribute> = doc_attrs(&item.attrs);
+            let use_discriminant: TokenStream = match variants.iter().any(|variant| variant.discriminant.is_some()) {
+                true => quote! { #[borsh(use_discriminant = true)] },
+                false => quote! {}
+            };
+            let variants = variants.iter().map(|variant| {
+                let docs: Vec<&Attribute> = doc_attrs(&variant.attrs);
+                let name: &Ident = &variant.ident;
+                let discriminant: Option<TokenStream> = variant.discriminant.as_ref().map(|(_, value)| quote! { = #value });
+                quote! { #(#docs)* #name #discriminant }
+            });
+
+            Some(quote! {
+                #(#docs)*
+                #[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+                #use_discriminant
+                pub enum #ident { #(#variants),* }
+            })
+        },
+        Item::Struct(item) if derives(&item.attrs, "ShankType") => {
+            let fields: Vec<TokenStream> = render_fields(item);
+            let docs: Vec<&Attribute> = doc_attrs(&item.attrs);
+            let ident: &Ident = &item.ident;
+
+            Some(quote! {
+                #(#docs)*
+                #[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+                pub struct #ident { #(#fields),* }
+            })
+        },
+        _ => None
+    }
+}
+
+/// `ShankAccount`s, decoded as their discriminator followed by their fields as borsh.
+fn render_account(item: &Item, items: &[Item]) -> Option<TokenStream> {
+    let Item::Struct(item) = item else { return None };
+
+    if !derives(&item.attrs, "ShankAccount") {
+        return None;
+    }
+
+    let ident: &Ident = &item.ident;
+    let docs: Vec<&Attribute> = doc_attrs(&item.attrs);
+    let fields: Vec<TokenStream> = render_fields(item);
+    let discriminator: &Expr = items.iter()
+        .find_map(|item| match item {
+            Item::Impl(item) if matches!(&*item.self_ty, Type::Path(path) if path.path.is_ident(ident)) => {
+                item.items.iter().find_map(|item| match item {
+                    syn::ImplItem::Const(constant) if constant.ident == "DISCRIMINATOR" => Some(&constant.expr),
+                    _ => None
+                })
+            },
+            _ => None
+        })
+        .unwrap_or_else(|| panic!("`{}` has no `DISCRIMINATOR`", ident));
+
+    Some(quote! {
+        #(#docs)*
+        #[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+        pub struct #ident { #(#fields),* }
+
+        impl #ident {
+            pub const DISCRIMINATOR: [u8; 8] = #discriminator;
+
+            /// Decodes the account's data, failing unless it starts with `DISCRIMINATOR`.
+            pub fn from_bytes(data: &[u8]) -> std::io::Result<Self> {
+                match data.split_first_chunk::<8>() {
+                    Some((discriminator, mut fields)) if discriminator == &Self::DISCRIMINATOR => Self::deserialize(&mut fields),
+                    _ => Err(std::io::Error::new(std::io::ErrorKind::InvalidData, concat!("not a `", stringify!(#ident), "`")))
+                }
+            }
+        }
+    })
+}
+
+/// Per instruction, its accounts (`<Name>`), its arguments (`<Name>InstructionArgs`) and a builder of its data,
+/// the discriminator followed by the arguments as borsh.
+fn render_instruction(variant: &syn::Variant) -> TokenStream {
+    let name: &Ident = &variant.ident;
+    let docs: Vec<&Attribute> = doc_attrs(&variant.attrs);
+    let args_name: Ident = format_ident!("{}InstructionArgs", name);
+    let discriminator: &Expr = &variant.discriminant.as_ref().expect("instructions are numbered by tag").1;
+
+    let mut accounts: Vec<IdlAccount> = variant.attrs.iter().filter_map(parse_account).collect();
+    accounts.sort_by_key(|account| account.index);
+
+    let account_fields = accounts.iter().map(|account| {
+        let field: Ident = format_ident!("{}", account.name);
+        match account.optional {
+            true => quote! { pub #field: Option<Pubkey> },
+            false => quote! { pub #field: Pubkey }
+        }
+    });
+    let account_metas = accounts.iter().map(|account| {
+        let field: Ident = format_ident!("{}", account.name);
+        let signer: bool = account.signer;
+        let meta: TokenStream = match account.writable {
+            true => quote! { AccountMeta::new(#field, #signer) },
+            false => quote! { AccountMeta::new_readonly(#field, #signer) }
+        };
+        match account.optional {
+            true => quote! { accounts.extend(self.#field.map(|#field| #meta)); },
+            false => quote! { let #field: Pubkey = self.#field; accounts.push(#meta); }
+        }
+    });
+
+    let (args_struct, args_parameter, args_argument, args_data): (TokenStream, TokenStream, TokenStream, TokenStream) = match &variant.fields {
+        Fields::Named(fields) => {
+            let fields = fields.named.iter().map(|field| {
+                let (ident, ty) = (&field.ident, &field.ty);
+                quote! { pub #ident: #ty }
+            });
+
+            (
+                quote! {
+                    #[derive(BorshSerialize, BorshDeserialize, Clone, Debug, PartialEq, Eq)]
+                    pub struct #args_name { #(#fields),* }
+                },
+                quote! { args: &#args_name, },
+                quote! { args, },
+                quote! { data.extend_from_slice(&borsh::to_vec(args).unwrap()); }
+            )
+        },
+        _ => (quote! {}, quote! {}, quote! {}, quote! {})
+    };
+
+    quote! {
+        #(#docs)*
+        #[derive(Clone, Debug, PartialEq, Eq)]
+        pub struct #name { #(#account_fields),* }
+
+        #args_struct
+
+        impl #name {
+            pub const DISCRIMINATOR: u8 = #discriminator;
+
+            pub fn instruction(&self, #args_parameter) -> Instruction {
+                self.instruction_with_remaining_accounts(#args_argument &[], &[])
+            }
+
+            /// With `data_suffix` (notes, labels... that the IDL leaves out) appended to the data,
+            /// and `remaining_accounts` (the ones optional features expect) to the accounts.
+            pub fn instruction_with_remaining_accounts(&self, #args_parameter data_suffix: &[u8], remaining_accounts: &[AccountMeta]) -> Instruction {
+                let mut accounts: Vec<AccountMeta> = Vec::with_capacity(remaining_accounts.len() + 16);
+                #(#account_metas)*
+                accounts.extend_from_slice(remaining_accounts);
+
+                let mut data: Vec<u8> = vec![Self::DISCRIMINATOR];
+                #args_data
+                data.extend_from_slice(data_suffix);
+
+                Instruction { program_id: ESCROW_ID, accounts, data }
+            }
+        }
+    }
+}
+
+/// `#[cfg_attr(feature = "idl", account(<index>, [writable], [signer], [optional], name = "<name>"))]`
+fn parse_account(attr: &Attribute) -> Option<IdlAccount> {
+    let account: Meta = cfg_attr_metas(attr)?.into_iter().find(|meta| meta.path().is_ident("account"))?;
+    let arguments: Punctuated<Expr, Token![,]> = account.require_list().ok()?
+        .parse_args_with(Punctuated::parse_terminated)
+        .expect("malformed `account` attribute");
+
+    let mut idl_account: IdlAccount = IdlAccount { index: 0, name: String::new(), writable: false, signer: false, optional: false };
+
+    for argument in arguments {
+        match argument {
+            Expr::Lit(ExprLit { lit: Lit::Int(index), .. }) => idl_account.index = index.base10_parse().unwrap(),
+            Expr::Path(path) if path.path.is_ident("writable") => idl_account.writable = true,
+            Expr::Path(path) if path.path.is_ident("signer") => idl_account.signer = true,
+            Expr::Path(path) if path.path.is_ident("optional") => idl_account.optional = true,
+            Expr::Assign(assign) => match (&*assign.left, &*assign.right) {
+                (Expr::Path(path), Expr::Lit(ExprLit { lit: Lit::Str(name), .. })) if path.path.is_ident("name") => {
+                    idl_account.name = name.value();
+                },
+                _ => panic!("unexpected `account` argument")
+            },
+            _ => panic!("unexpected `account` argument")
+        }
+    }
+
+    Some(idl_account)
+}
+
+/// Whether the item derives `shank::<derive>` (behind the `idl` feature).
+fn derives(attrs: &[Attribute], derive: &str) -> bool {
+    attrs.iter()
+        .filter_map(cfg_attr_metas)
+        .flatten()
+        .any(|meta| meta.path().is_ident("derive") && meta.require_list().is_ok_and(|list| {
+            list.tokens.to_string().replace(' ', "") == format!("shank::{}", derive)
+        }))
+}
+
+/// Attributes `#[cfg_attr(feature = "idl", ...)]` applies.
+fn cfg_attr_metas(attr: &Attribute) -> Option<Vec<Meta>> {
+    if !attr.path().is_ident("cfg_attr") {
+        return None;
+    }
+
+    let metas: Punctuated<Meta, Token![,]> = attr.parse_args_with(Punctuated::parse_terminated).ok()?;
+    let mut metas = metas.into_iter();
+
+    match metas.next()? {
+        Meta::NameValue(feature) if feature.path.is_ident("feature") && quote!(#feature).to_string().contains("\"idl\"") => Some(metas.collect()),
+        _ => None
+    }
+}
+
+fn doc_attrs(attrs: &[Attribute]) -> Vec<&Attribute> {
+    attrs.iter().filter(|attr| attr.path().is_ident("doc")).collect()
+}
+
+fn render_fields(item: &ItemStruct) -> Vec<TokenStream> {
+    item.fields.iter()
+        .map(|field| {
+            let docs: Vec<&Attribute> = doc_attrs(&field.attrs);
+            let (ident, ty) = (&field.ident, &field.ty);
+            quote! { #(#docs)* pub #ident: #ty }
+        })
+        .collect()
+}
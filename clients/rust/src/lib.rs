@@ -0,0 +1,3 @@
+//! Typed client of the escrow program: PDAs, instruction builders and account decoders.
+//! `build.rs` generates it from the program's Shank annotations (the ones the IDL is built from), so it can't drift from them.
+include!(concat!(env!("OUT_DIR"), "/generated.rs"));
@@ -1,101 +1,196 @@
 use solana_sdk::{
     hash::Hash, 
-    instruction::{AccountMeta, Instruction}, 
+    account::Account,
+    instruction::Instruction, 
     message::Message, 
     native_token::LAMPORTS_PER_SOL, 
     pubkey::Pubkey, 
-    signature::{Keypair, Signature}, 
+    signature::{read_keypair_file, Keypair, Signature}, 
     signer::Signer, 
-    system_program,
     transaction::Transaction
 };
+use std::str::FromStr;
 use solana_client::nonblocking::rpc_client::RpcClient;
+use base64::{Engine, engine::general_purpose::STANDARD};
+use solana_sdk::commitment_config::CommitmentConfig;
+use escrow::{
+    client::{self, CliConfig, EscrowClient, RetryPolicy},
+    cluster::Cluster,
+    priority_fee::PriorityFeePolicy
+};
+use escrow_client::{
+    accounts::EscrowAccount,
+    instructions::{Close, Initialize, InitializeInstructionArgs},
+    pdas
+};
+
+
+/// Version of the `--output json` records, bumped on any breaking change to their fields.
+const JSON_OUTPUT_VERSION: u32 = 1;
+
+/// `--output text` (default) logs progress, `--output json` prints one JSON record per step on stdout, for scripting.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Output {
+    Text,
+    Json
+}
+
+/// Command line flags, following the solana CLI's offline signing conventions:
+/// - `--sign-only` signs every transaction with the local keys and prints it (base58 & base64) instead of sending it,
+/// - `--blockhash <hash>` signs against a fixed blockhash, so offline and online machines build the same messages,
+/// - `--signer-from-file <path>` (repeatable) adds the signatures of partially-signed transactions previously
+///   printed by `--sign-only` (one base58 or base64 transaction per line) to the matching transactions.
+///
+/// `--priority-fee-cap <micro-lamports>` prices every transaction from the recent prioritization fees
+/// of the accounts it write-locks, up to the cap, and limits it to the compute units its simulation consumed.
+///
+/// Transactions are landed by `client::send_with_retry()`: `--max-attempts <n>` bounds the sends and `--commitment
+/// <processed|confirmed|finalized>` sets the level to wait for. Signatures from `--signer-from-file` or against a
+/// `--blockhash` can't be redone locally, so those transactions aren't re-signed once their blockhash expired.
+struct Options {
+    output: Output,
+    sign_only: bool,
+    blockhash: Option<Hash>,
+    presigned: Vec<Transaction>,
+    priority_fee_cap: Option<u64>,
+    retry: RetryPolicy
+}
+
+impl Options {
+    fn from_args() -> Result<Self, Box<dyn std::error::Error>> {
+        let args: Vec<String> = std::env::args().collect();
+        let value_of = |flag: &str| args.iter().position(|arg| arg == flag).map(|i| args.get(i + 1).map(String::as_str));
+
+        let output: Output = match value_of("--output") {
+            None | Some(Some("text")) => Output::Text,
+            Some(Some("json")) => Output::Json,
+            Some(output) => return Err(format!("unsupported --output: {:?}, expected `text` or `json`", output).into())
+        };
+
+        let blockhash: Option<Hash> = match value_of("--blockhash") {
+            Some(blockhash) => Some(Hash::from_str(blockhash.ok_or("--blockhash expects a value")?)?),
+            None => None
+        };
+
+        let priority_fee_cap: Option<u64> = match value_of("--priority-fee-cap") {
+            Some(cap) => Some(cap.ok_or("--priority-fee-cap expects a value")?.parse()?),
+            None => None
+        };
+
+        let mut retry: RetryPolicy = RetryPolicy::default();
+
+        if let Some(max_attempts) = value_of("--max-attempts") {
+            retry.max_attempts = max_attempts.ok_or("--max-attempts expects a value")?.parse()?;
+        }
+
+        if let Some(commitment) = value_of("--commitment") {
+            retry.commitment = CommitmentConfig::from_str(commitment.ok_or("--commitment expects a value")?)?;
+        }
+
+        let mut presigned: Vec<Transaction> = Vec::new();
+
+        for (i, _) in args.iter().enumerate().filter(|(_, arg)| *arg == "--signer-from-file") {
+            let path: &String = args.get(i + 1).ok_or("--signer-from-file expects a path")?;
+
+            for line in std::fs::read_to_string(path)?.lines().map(str::trim).filter(|line| !line.is_empty()) {
+                presigned.push(decode_transaction(line)?);
+            }
+        }
+
+        retry.resign_on_expiry = presigned.is_empty() && blockhash.is_none();
+
+        Ok(Self {
+            output,
+            sign_only: args.iter().any(|arg| arg == "--sign-only"),
+            blockhash,
+            presigned,
+            priority_fee_cap,
+            retry
+        })
+    }
+}
 
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    dotenvy::dotenv()?;
+    // `.env` is optional, without it the solana CLI's cluster & wallet are used
+    dotenvy::dotenv().ok();
     env_logger::init();
 
+    let options: Options = Options::from_args()?;
+
     log::info!("Running example!");
 
-    let url: String = String::from("http://127.0.0.1:8899");
-    let rpc_client: RpcClient = RpcClient::new(url);
+    let cli_config: CliConfig = CliConfig::load(std::env::var("SOLANA_CONFIG").ok().as_deref())?;
+    let rpc_client: RpcClient = cli_config.rpc_client();
 
     // 1. init payer & recipient
-    let Accounts { payer, recipient } = init_payer_and_recipient(&rpc_client).await?;
+    let Accounts { payer, recipient } = init_payer_and_recipient(&rpc_client, &cli_config).await?;
+    let mut client: EscrowClient = EscrowClient::new(rpc_client, payer.keypair);
+
+    if let Some(cap) = options.priority_fee_cap {
+        client = client.with_priority_fee(PriorityFeePolicy { max_micro_lamports: cap, ..PriorityFeePolicy::default() });
+    }
 
     // 2. derive escrow pda
-    let (escrow_pda, _bump) = Pubkey::find_program_address(
-        &[
-            b"escrow",
-            payer.pkey.as_ref(),
-            recipient.pkey.as_ref()
-        ], 
-        &escrow::ID
-    );
-        
-    // 3. craft init ix & init tx
-    let mut init_ix_payload: Vec<u8> = Vec::with_capacity(9);
-    init_ix_payload.push(0);
-    init_ix_payload.extend_from_slice(&u64::to_le_bytes(101101101));
-
-    let initialize_escrow_ix: Instruction = Instruction::new_with_bytes(
-        escrow::ID, 
-        &init_ix_payload, 
-        vec![
-            AccountMeta::new(payer.pkey, true),
-            AccountMeta::new_readonly(recipient.pkey, false),
-            AccountMeta::new(escrow_pda, false),
-            AccountMeta::new_readonly(system_program::ID, false)
-        ]
-    );
-    let message: Message = Message::new(&[initialize_escrow_ix], Some(&payer.pkey));
+    let escrow_pda: Pubkey = client.escrow_address(&recipient.pkey);
+
+    // 3. craft init ix & init tx with the generated client
+    let (config_pda, _bump) = pdas::find_config_pda();
+    let initialize_escrow_ix: Instruction = Initialize {
+        payer: payer.pkey,
+        recipient: recipient.pkey,
+        escrow: escrow_pda,
+        system_program: solana_sdk::system_program::ID,
+        counter: pdas::find_counter_pda().0,
+        config: config_pda
+    }.instruction(&InitializeInstructionArgs { amount: 101101101 });
+    let instructions: Vec<Instruction> = client.prioritize(&[initialize_escrow_ix], &[]).await?;
+    let message: Message = Message::new(&instructions, Some(&payer.pkey));
     let mut initialize_escrow_tx: Transaction = Transaction::new_unsigned(message);
 
-    // 4. get latest_blockhash
-    let latest_blockhash: Hash = rpc_client.get_latest_blockhash().await?;
+    // 4. get latest_blockhash (unless signing against a given one)
+    let latest_blockhash: Hash = match options.blockhash {
+        Some(blockhash) => blockhash,
+        None => client.rpc.get_latest_blockhash().await?
+    };
 
     // 5. sign init escrow tx & send it
-    initialize_escrow_tx.sign(&[&payer.keypair], latest_blockhash);
-    send_tx_and_print_result(&rpc_client, &initialize_escrow_tx).await?;
+    sign_tx(&mut initialize_escrow_tx, &[&client.payer], latest_blockhash, &options)?;
+    send_tx_and_print_result(&client, &mut initialize_escrow_tx, &options, "initialize", &escrow_pda).await?;
 
     // // 6. craft complete ix & complete tx
-    // let complete_escrow_ix: Instruction = Instruction::new_with_bytes(
-    //     escrow::ID, 
-    //     &[1], 
-    //     vec![
-    //         AccountMeta::new(payer.pkey, true),
-    //         AccountMeta::new(recipient.pkey, false),
-    //         AccountMeta::new(escrow_pda, false)
-    //     ]
-    // );
-    // let message: Message = Message::new(&[complete_escrow_ix], Some(&payer.pkey));
+    // let complete_escrow_ix: Instruction = Complete {
+    //     payer: payer.pkey,
+    //     recipient: recipient.pkey,
+    //     escrow: escrow_pda,
+    //     config: config_pda
+    // }.instruction();
+    // let instructions: Vec<Instruction> = client.prioritize(&[complete_escrow_ix], &[]).await?;
+    // let message: Message = Message::new(&instructions, Some(&payer.pkey));
     // let mut complete_escrow_tx: Transaction = Transaction::new_unsigned(message);
 
     // // 7. sign complete tx & send it
-    // complete_escrow_tx.sign(&[&payer.keypair], latest_blockhash);
-    // send_tx_and_print_result(&rpc_client, &complete_escrow_tx).await?;
+    // sign_tx(&mut complete_escrow_tx, &[&client.payer], latest_blockhash, &options)?;
+    // send_tx_and_print_result(&client, &mut complete_escrow_tx, &options, "complete", &escrow_pda).await?;
 
     // this is an alternative way: 
     // (either complete escrow, or close it. note, that complete escrow also closes EscrowAccount at the end,
     // so we can freely ignore testing this instruction, since they both use the same `EscrowInstruction::close_account()` method)
     // 6. craft close ix & close ix
-    let close_escrow_ix: Instruction = Instruction::new_with_bytes(
-        escrow::ID, 
-        &[2], 
-        vec![
-            AccountMeta::new(payer.pkey, true),
-            AccountMeta::new_readonly(recipient.pkey, false),
-            AccountMeta::new(escrow_pda, false)
-        ]
-    );
-    let message: Message = Message::new(&[close_escrow_ix], Some(&payer.pkey));
+    let close_escrow_ix: Instruction = Close {
+        payer: payer.pkey,
+        recipient: recipient.pkey,
+        escrow: escrow_pda,
+        config: config_pda
+    }.instruction();
+    let instructions: Vec<Instruction> = client.prioritize(&[close_escrow_ix], &[]).await?;
+    let message: Message = Message::new(&instructions, Some(&payer.pkey));
     let mut close_escrow_tx: Transaction = Transaction::new_unsigned(message);
 
     // 7. sign close tx & send it
-    close_escrow_tx.sign(&[&payer.keypair], latest_blockhash);
-    send_tx_and_print_result(&rpc_client, &close_escrow_tx).await?;
+    sign_tx(&mut close_escrow_tx, &[&client.payer], latest_blockhash, &options)?;
+    send_tx_and_print_result(&client, &mut close_escrow_tx, &options, "close", &escrow_pda).await?;
 
     Ok(())
 }
@@ -122,8 +217,15 @@ impl Accounts {
     }
 }
 
-async fn init_payer_and_recipient(rpc_client: &RpcClient) -> Result<Accounts, Box<dyn std::error::Error>> {
-    Ok(if std::env::var("NEW_PAYER_AND_RECIPIENT")?.parse::<bool>()? {
+/// Random airdropped keys with `NEW_PAYER_AND_RECIPIENT=true`, otherwise the payer's `PAYER_SEED_PHRASE` (or else the
+/// solana CLI's wallet) and the recipient's `RECIPIENT_SEED_PHRASE` (or else the `RECIPIENT_KEYPAIR` keypair file).
+async fn init_payer_and_recipient(rpc_client: &RpcClient, cli_config: &CliConfig) -> Result<Accounts, Box<dyn std::error::Error>> {
+    let new_payer_and_recipient: bool = match std::env::var("NEW_PAYER_AND_RECIPIENT") {
+        Ok(value) => value.parse()?,
+        Err(_) => false
+    };
+
+    Ok(if new_payer_and_recipient {
         // request airdrop & wait until balance tops up
         let payer: Keypair = Keypair::new();
         let recipient: Keypair = Keypair::new();
@@ -143,18 +245,169 @@ async fn init_payer_and_recipient(rpc_client: &RpcClient) -> Result<Accounts, Bo
         
         accs
     } else {
-        // use accounts with given seeds
-        let payer: Keypair = Keypair::from_base58_string(&std::env::var("PAYER_SEED_PHRASE")?);
-        let recipient: Keypair = Keypair::from_base58_string(&std::env::var("RECIPIENT_SEED_PHRASE")?);
+        // use accounts with given seeds, or keypair files
+        let payer: Keypair = match std::env::var("PAYER_SEED_PHRASE") {
+            Ok(seed) => Keypair::from_base58_string(&seed),
+            Err(_) => cli_config.keypair()?
+        };
+        let recipient: Keypair = match std::env::var("RECIPIENT_SEED_PHRASE") {
+            Ok(seed) => Keypair::from_base58_string(&seed),
+            Err(_) => read_keypair_file(std::env::var("RECIPIENT_KEYPAIR")?)?
+        };
         Accounts::new(payer, recipient)
     })    
 }
 
-async fn send_tx_and_print_result(rpc_client: &RpcClient, tx: &Transaction) -> solana_rpc_client_api::client_error::Result<()> {
+/// Signs `tx` with the local `signers` and adds the signatures of the matching `--signer-from-file` transaction.
+/// Outside of `--sign-only`, every signature must be present afterwards.
+fn sign_tx(tx: &mut Transaction, signers: &[&Keypair], blockhash: Hash, options: &Options) -> Result<(), Box<dyn std::error::Error>> {
+    tx.try_partial_sign(signers, blockhash)?;
+
+    for presigned in options.presigned.iter().filter(|presigned| presigned.message == tx.message) {
+        for (signature, presigned_signature) in tx.signatures.iter_mut().zip(&presigned.signatures) {
+            if *presigned_signature != Signature::default() {
+                *signature = *presigned_signature;
+            }
+        }
+    }
+
+    if !options.sign_only {
+        if !tx.is_signed() {
+            return Err("transaction is missing signatures, pass them with --signer-from-file".into());
+        }
+
+        tx.verify()?;
+    }
+
+    Ok(())
+}
+
+fn decode_transaction(encoded: &str) -> Result<Transaction, Box<dyn std::error::Error>> {
+    let bytes: Vec<u8> = match bs58::decode(encoded).into_vec() {
+        Ok(bytes) => bytes,
+        Err(_) => STANDARD.decode(encoded)?
+    };
+
+    Ok(bincode::deserialize(&bytes)?)
+}
+
+/// `--sign-only` output: the partially-signed transaction, followed by who signed and who is still missing,
+/// like `solana ... --sign-only` does.
+fn print_signed_tx(tx: &Transaction, output: Output, step: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let bytes: Vec<u8> = bincode::serialize(tx)?;
+    let signers: Vec<(&Pubkey, &Signature)> = tx.message.account_keys.iter().zip(&tx.signatures).collect();
+
+    if output == Output::Text {
+        println!("Step: {}", step);
+        println!("Blockhash: {}", tx.message.recent_blockhash);
+        println!("Transaction (base58): {}", bs58::encode(&bytes).into_string());
+        println!("Transaction (base64): {}", STANDARD.encode(&bytes));
+        println!("Signers (Pubkey=Signature):");
+        for (pubkey, signature) in signers.iter().filter(|(_, signature)| **signature != Signature::default()) {
+            println!("  {}={}", pubkey, signature);
+        }
+        println!("Absent Signers (Pubkey):");
+        for (pubkey, _) in signers.iter().filter(|(_, signature)| **signature == Signature::default()) {
+            println!("  {}", pubkey);
+        }
+        return Ok(());
+    }
+
+    println!(
+        r#"{{"version":{},"step":"{}","blockhash":"{}","transaction":"{}","signers":[{}],"absent_signers":[{}]}}"#,
+        JSON_OUTPUT_VERSION,
+        step,
+        tx.message.recent_blockhash,
+        STANDARD.encode(&bytes),
+        signers.iter()
+            .filter(|(_, signature)| **signature != Signature::default())
+            .map(|(pubkey, signature)| format!(r#""{}={}""#, pubkey, signature))
+            .collect::<Vec<String>>()
+            .join(","),
+        signers.iter()
+            .filter(|(_, signature)| **signature == Signature::default())
+            .map(|(pubkey, _)| format!(r#""{}""#, pubkey))
+            .collect::<Vec<String>>()
+            .join(",")
+    );
+
+    Ok(())
+}
+
+async fn send_tx_and_print_result(
+    client: &EscrowClient,
+    tx: &mut Transaction,
+    options: &Options,
+    step: &str,
+    escrow_pda: &Pubkey
+) -> Result<(), Box<dyn std::error::Error>> {
+    if options.sign_only {
+        return print_signed_tx(tx, options.output, step);
+    }
+
     log::info!("Sending transaction!");
-    match rpc_client.send_and_confirm_transaction(tx).await {
-        Ok(sig) => log::info!("Success! Tx signature: {}", sig),
-        Err(e) => log::error!("Error: {}", e)
+    let rpc_client: &RpcClient = &client.rpc;
+    let result = client::send_with_retry(rpc_client, tx, &[&client.payer], &options.retry).await;
+
+    if options.output == Output::Text {
+        match result {
+            Ok(sig) => log::info!("Success! Tx signature: {}", sig),
+            Err(e) => log::error!("Error: {}", e)
+        };
+        return Ok(());
+    }
+
+    let escrow: Option<Account> = rpc_client.get_account_with_commitment(escrow_pda, rpc_client.commitment()).await?.value;
+    let escrow_json: String = match escrow.as_ref().map(|escrow| EscrowAccount::from_bytes(&escrow.data)) {
+        Some(Ok(state)) => format!(
+            r#"{{"address":"{}","id":{},"created_at":{},"deadline":{},"unlock_at":{},"mint":"{}","reference":"{}","lamports":{}}}"#,
+            escrow_pda,
+            state.id,
+            state.created_at,
+            state.deadline,
+            state.unlock_at,
+            state.mint,
+            state.reference.iter().map(|byte| format!("{:02x}", byte)).collect::<String>(),
+            escrow.map_or(0, |escrow| escrow.lamports)
+        ),
+        // closed (or not created)
+        _ => String::from("null")
     };
+
+    let record: String = match result {
+        Ok(sig) => format!(
+            r#"{{"version":{},"step":"{}","ok":true,"signature":"{}","explorer":"{}","escrow":{}}}"#,
+            JSON_OUTPUT_VERSION,
+            step,
+            sig,
+            Cluster::Localnet.explorer_tx_url(&sig.to_string()),
+            escrow_json
+        ),
+        Err(e) => format!(
+            r#"{{"version":{},"step":"{}","ok":false,"error":{},"escrow":{}}}"#,
+            JSON_OUTPUT_VERSION,
+            step,
+            json_string(&e.to_string()),
+            escrow_json
+        )
+    };
+    println!("{}", record);
+
     Ok(())
+}
+
+fn json_string(s: &str) -> String {
+    let mut json: String = String::from("\"");
+
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            c if c.is_control() => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c)
+        }
+    }
+
+    json.push('"');
+    json
 }
\ No newline at end of file
@@ -0,0 +1,124 @@
+//! Follows an escrow live: `cargo run --example watch -- <escrow> [--cluster <cluster>]`.
+//!
+//! Subscribes to the escrow account and to the logs of transactions mentioning it, and prints every
+//! decoded event and state transition until the escrow is closed (completed, closed or cancelled).
+use std::str::FromStr;
+use futures_util::StreamExt;
+use solana_sdk::{
+    account::Account,
+    pubkey::Pubkey
+};
+use solana_client::nonblocking::{pubsub_client::PubsubClient, rpc_client::RpcClient};
+use solana_rpc_client_api::config::{RpcTransactionLogsConfig, RpcTransactionLogsFilter};
+use solana_program::program_pack::Pack;
+use base64::{Engine, engine::general_purpose::STANDARD};
+use escrow::{cluster::Cluster, events::EscrowEvent, state::{EscrowAccount, EscrowStatus}};
+
+
+/// Parts of `EscrowAccount` a support investigation cares about, printed whenever one of them changes.
+#[derive(Debug, PartialEq, Eq)]
+struct Snapshot {
+    lamports: u64,
+    amount: u64,
+    token_amount: u64,
+    status: EscrowStatus,
+    assignee: Pubkey,
+    has_assignments: bool,
+    depeg_override: bool
+}
+
+impl Snapshot {
+    fn new(account: &Account, escrow: &EscrowAccount) -> Self {
+        Self {
+            lamports: account.lamports,
+            amount: escrow.amount,
+            token_amount: escrow.token_amount,
+            status: escrow.status,
+            assignee: escrow.assignee,
+            has_assignments: escrow.has_assignments,
+            depeg_override: escrow.depeg_override
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args: Vec<String> = std::env::args().collect();
+
+    let escrow_pda: Pubkey = Pubkey::from_str(args.get(1).ok_or("usage: watch <escrow> [--cluster <cluster>]")?)?;
+    let cluster: Cluster = match args.iter().position(|arg| arg == "--cluster") {
+        Some(i) => Cluster::from_str(args.get(i + 1).ok_or("--cluster expects a value")?)?,
+        None => Cluster::Localnet
+    };
+
+    let rpc_client: RpcClient = RpcClient::new(cluster.rpc_url().to_string());
+    let pubsub_client: PubsubClient = PubsubClient::new(cluster.ws_url()).await?;
+
+    // 1. subscribe first, so no transition between the initial fetch and the subscriptions gets lost
+    let (mut account_updates, _account_unsubscribe) = pubsub_client.account_subscribe(&escrow_pda, None).await?;
+    let (mut logs, _logs_unsubscribe) = pubsub_client.logs_subscribe(
+        RpcTransactionLogsFilter::Mentions(vec![escrow_pda.to_string()]),
+        RpcTransactionLogsConfig { commitment: None }
+    ).await?;
+
+    // 2. print the current state
+    let Some(mut snapshot) = fetch_snapshot(&rpc_client, &escrow_pda, cluster).await? else {
+        println!("escrow {} doesn't exist (or was already settled)", escrow_pda);
+        return Ok(());
+    };
+    println!("watching escrow {} on {:?}", escrow_pda, cluster);
+    println!("state: {:?}", snapshot);
+
+    // 3. print events & state transitions until the escrow account is closed
+    loop {
+        tokio::select! {
+            Some(response) = logs.next() => {
+                if let Some(err) = response.value.err {
+                    println!("tx {} failed: {}", response.value.signature, err);
+                    continue;
+                }
+
+                for event in response.value.logs.iter().filter_map(|log| decode_event(log)) {
+                    println!("tx {}: {:?}", response.value.signature, event);
+                }
+            },
+            Some(_) = account_updates.next() => {
+                match fetch_snapshot(&rpc_client, &escrow_pda, cluster).await? {
+                    Some(next) if next != snapshot => {
+                        println!("state: {:?}", next);
+                        snapshot = next;
+                    },
+                    Some(_) => {},
+                    None => {
+                        println!("escrow closed");
+                        return Ok(());
+                    }
+                }
+            },
+            else => return Err("subscription ended".into())
+        }
+    }
+}
+
+async fn fetch_snapshot(
+    rpc_client: &RpcClient,
+    escrow_pda: &Pubkey,
+    cluster: Cluster
+) -> Result<Option<Snapshot>, Box<dyn std::error::Error>> {
+    let account: Option<Account> = rpc_client.get_account_with_commitment(escrow_pda, rpc_client.commitment()).await?.value;
+
+    // closed escrows are zeroed & handed back to the system program
+    Ok(match account {
+        Some(account) if account.owner == cluster.program_id() => {
+            let escrow: EscrowAccount = EscrowAccount::unpack(&account.data)?;
+            Some(Snapshot::new(&account, &escrow))
+        },
+        _ => None
+    })
+}
+
+/// `sol_log_data` shows up as `Program data: <base64>` in the logs.
+fn decode_event(log: &str) -> Option<EscrowEvent> {
+    let data: Vec<u8> = STANDARD.decode(log.strip_prefix("Program data: ")?).ok()?;
+    EscrowEvent::unpack(&data).ok()
+}
@@ -0,0 +1,138 @@
+#![no_main]
+//! Arbitrary instruction data over arbitrary permutations of a pool of accounts, through the program under
+//! `solana-program-test`. Every input must end in the transaction succeeding or failing, never in the program
+//! panicking (libfuzzer aborts on any panic, including those in the bank's tasks).
+//!
+//! The pool holds the parties of an initialized escrow, the program's PDAs around it, the programs it invokes and an
+//! unrelated address. Inputs run one after the other in the same bank, so the pool's accounts are restored before
+//! each of them, keeping crashes reproducible from their input alone.
+use std::cell::RefCell;
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use solana_program_test::{BanksClientError, ProgramTestContext};
+use solana_sdk::{
+    account::AccountSharedData,
+    instruction::{AccountMeta, Instruction},
+    pubkey::Pubkey,
+    signature::Keypair,
+    signer::Signer,
+    system_program,
+    sysvar,
+    transaction::Transaction
+};
+use tokio::runtime::Runtime;
+
+
+/// Accounts an input passes at most, more than any instruction reads.
+const MAX_ACCOUNTS: usize = 32;
+
+#[derive(Arbitrary, Debug)]
+struct FuzzAccount {
+    /// index in the pool, modulo its size
+    index: u8,
+    /// only honoured for the pool's keypairs, the others can't sign
+    is_signer: bool,
+    is_writable: bool
+}
+
+#[derive(Arbitrary, Debug)]
+struct FuzzInstruction {
+    accounts: Vec<FuzzAccount>,
+    data: Vec<u8>
+}
+
+struct Harness {
+    runtime: Runtime,
+    context: ProgramTestContext,
+    /// payer & recipient of the escrow, the context's payer paying the fees
+    signers: Vec<Keypair>,
+    pool: Vec<Pubkey>,
+    /// the pool's accounts the program may write, as they were after the setup
+    pristine: Vec<(Pubkey, AccountSharedData)>
+}
+
+impl Harness {
+    fn new() -> Self {
+        let runtime: Runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().expect("tokio runtime");
+        let mut context: ProgramTestContext = runtime.block_on(escrow::test_utils::program_test().start_with_context());
+
+        let payer: Keypair = runtime.block_on(escrow::test_utils::funded_keypair(&mut context, 10_000_000_000)).expect("funded payer");
+        let recipient: Keypair = runtime.block_on(escrow::test_utils::funded_keypair(&mut context, 10_000_000_000)).expect("funded recipient");
+        let initialize_ix: Instruction = escrow::instruction::initialize(&payer.pubkey(), &recipient.pubkey(), 1_000_000_000);
+        runtime.block_on(escrow::test_utils::send(&mut context, &[initialize_ix.clone()], &[&payer])).expect("escrow initialized");
+
+        let mut state: Vec<Pubkey> = vec![context.payer.pubkey(), payer.pubkey(), recipient.pubkey(), Pubkey::new_unique()];
+        state.extend(initialize_ix.accounts.iter().map(|meta| meta.pubkey));
+        state.extend([
+            Pubkey::find_program_address(&[escrow::get_fee_vault_seeds()], &escrow::ID).0,
+            Pubkey::find_program_address(&[escrow::get_treasury_seeds()], &escrow::ID).0,
+            {
+                let (seed1, seed2) = escrow::get_archive_seeds(&payer.pubkey());
+                Pubkey::find_program_address(&[seed1, seed2], &escrow::ID).0
+            }
+        ]);
+        state.retain(|pubkey| pubkey != &system_program::ID);
+        state.sort();
+        state.dedup();
+
+        let pristine: Vec<(Pubkey, AccountSharedData)> = state
+            .iter()
+            .map(|pubkey| {
+                let account = runtime.block_on(context.banks_client.get_account(*pubkey)).expect("account fetched");
+                (*pubkey, account.map(AccountSharedData::from).unwrap_or_default())
+            })
+            .collect();
+
+        let mut pool: Vec<Pubkey> = state;
+        pool.extend([
+            escrow::ID,
+            system_program::ID,
+            escrow::MEMO_PROGRAM_ID,
+            spl_token::ID,
+            sysvar::instructions::ID,
+            sysvar::clock::ID
+        ]);
+
+        Self { runtime, context, signers: vec![payer, recipient], pool, pristine }
+    }
+
+    fn run(&mut self, input: FuzzInstruction) {
+        for (pubkey, account) in &self.pristine {
+            self.context.set_account(pubkey, account);
+        }
+
+        let fee_payer: Pubkey = self.context.payer.pubkey();
+        let accounts: Vec<AccountMeta> = input.accounts
+            .iter()
+            .take(MAX_ACCOUNTS)
+            .map(|account| {
+                let pubkey: Pubkey = self.pool[account.index as usize % self.pool.len()];
+                let can_sign: bool = pubkey == fee_payer || self.signers.iter().any(|signer| signer.pubkey() == pubkey);
+
+                AccountMeta { pubkey, is_signer: account.is_signer && can_sign, is_writable: account.is_writable }
+            })
+            .collect();
+
+        let mut signers: Vec<&Keypair> = vec![&self.context.payer];
+        signers.extend(self.signers.iter().filter(|signer| accounts.iter().any(|meta| meta.is_signer && meta.pubkey == signer.pubkey())));
+
+        let instruction: Instruction = Instruction::new_with_bytes(escrow::ID, &input.data, accounts);
+        let tx: Transaction = Transaction::new_signed_with_payer(&[instruction], Some(&fee_payer), &signers, self.context.last_blockhash);
+
+        // the transaction failing is fine, the bank failing to answer isn't
+        match self.runtime.block_on(self.context.banks_client.process_transaction(tx)) {
+            Ok(()) | Err(BanksClientError::TransactionError(_)) | Err(BanksClientError::SimulationError { .. }) => {},
+            Err(err) => panic!("banks client failed: {}", err)
+        }
+    }
+}
+
+thread_local! {
+    static HARNESS: RefCell<Option<Harness>> = const { RefCell::new(None) };
+}
+
+fuzz_target!(|input: FuzzInstruction| {
+    HARNESS.with(|harness| {
+        harness.borrow_mut().get_or_insert_with(Harness::new).run(input);
+    });
+});
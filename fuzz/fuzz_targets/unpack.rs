@@ -0,0 +1,10 @@
+#![no_main]
+//! Arbitrary instruction data through `EscrowInstruction::unpack()`, which must reject what it can't parse instead of
+//! panicking.
+use libfuzzer_sys::fuzz_target;
+use escrow::instruction::EscrowInstruction;
+
+
+fuzz_target!(|data: &[u8]| {
+    let _ = EscrowInstruction::unpack(data);
+});
@@ -0,0 +1,11 @@
+#!/usr/bin/env bash
+# Fuzzes a target of fuzz/ (`unpack` or `processor`) for a while, crashes landing in fuzz/artifacts/<target>.
+# Requires a nightly toolchain & cargo-fuzz: cargo install cargo-fuzz
+set -euo pipefail
+
+cd "$(dirname "$0")/.."
+
+TARGET="${1:-unpack}"
+SECONDS_TO_RUN="${2:-300}"
+
+cargo +nightly fuzz run "$TARGET" -- -max_total_time="$SECONDS_TO_RUN"
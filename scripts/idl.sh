@@ -0,0 +1,13 @@
+#!/usr/bin/env bash
+# Generates the program's IDL (idl/escrow.json) from the Shank annotations.
+# Requires shank-cli: cargo install shank-cli
+set -euo pipefail
+
+cd "$(dirname "$0")/.."
+
+PROGRAM_ID="${PROGRAM_ID:-E6v3tbZyZAthzd5JCPJgd3TmLXL3VirKxib9XHjyKTjL}"
+
+# the annotations only exist behind the feature, make sure they still compile
+cargo check --features idl
+
+shank idl --crate-root . --out-dir idl --program-id "$PROGRAM_ID"
@@ -0,0 +1,242 @@
+//! Leading accounts of the instructions, declared once in `instruction_accounts!`: the client builders (`to_account_metas()`,
+//! over `Pubkey`s) and the processor (`parse()`, over `AccountInfo`s) are generated from the same list, so their order
+//! can't drift apart as instructions grow. Optional accounts an instruction takes after these are still read by hand.
+use solana_program::{
+    account_info::{next_account_info, AccountInfo},
+    instruction::AccountMeta,
+    program_error::ProgramError,
+    pubkey::Pubkey
+};
+
+
+/// Each field is an account, flagged `writable` and/or `signer` (or `readonly`), in the order the program reads them.
+macro_rules! instruction_accounts {
+    (@writable writable $($rest:ident)*) => { true };
+    (@writable $flag:ident $($rest:ident)*) => { instruction_accounts!(@writable $($rest)*) };
+    (@writable) => { false };
+    (@signer signer $($rest:ident)*) => { true };
+    (@signer $flag:ident $($rest:ident)*) => { instruction_accounts!(@signer $($rest)*) };
+    (@signer) => { false };
+    ($(
+        $(#[$attr:meta])*
+        pub struct $name:ident {
+            $($(#[$field_attr:meta])* $field:ident: $($flag:ident)+),+ $(,)?
+        }
+    )+) => {$(
+        $(#[$attr])*
+        pub struct $name<T> {
+            $($(#[$field_attr])* pub $field: T),+
+        }
+
+        impl $name<Pubkey> {
+            pub fn to_account_metas(&self) -> Vec<AccountMeta> {
+                vec![$(
+                    AccountMeta {
+                        pubkey: self.$field,
+                        is_signer: instruction_accounts!(@signer $($flag)+),
+                        is_writable: instruction_accounts!(@writable $($flag)+)
+                    }
+                ),+]
+            }
+        }
+
+        impl<'a, 'b> $name<&'b AccountInfo<'a>> {
+            /// Takes the accounts off `accounts_iter`, leaving any optional ones after them.
+            pub fn parse<I: Iterator<Item = &'b AccountInfo<'a>>>(accounts_iter: &mut I) -> Result<Self, ProgramError> {
+                Ok(Self {
+                    $($field: next_account_info(accounts_iter)?),+
+                })
+            }
+        }
+    )+};
+}
+
+
+instruction_accounts! {
+    /// SOL `Initialize` (every tag of it).
+    pub struct InitializeAccounts {
+        payer: writable signer,
+        recipient: readonly,
+        /// escrow PDA
+        escrow: writable,
+        system_program: readonly,
+        /// `EscrowCounter` PDA
+        counter: writable,
+        /// `Config` PDA
+        config: readonly
+    }
+
+    pub struct CompleteAccounts {
+        payer: writable signer,
+        recipient: writable,
+        escrow: writable,
+        config: readonly
+    }
+
+    pub struct ClaimAccounts {
+        recipient: writable signer,
+        payer: writable,
+        escrow: writable,
+        config: readonly
+    }
+
+    pub struct CloseAccounts {
+        payer: writable signer,
+        /// only used to check the escrow PDA
+        recipient: readonly,
+        escrow: writable,
+        config: readonly
+    }
+
+    pub struct DepositAccounts {
+        payer: writable signer,
+        recipient: readonly,
+        escrow: writable,
+        system_program: readonly
+    }
+
+    pub struct FundAccounts {
+        payer: writable signer,
+        recipient: readonly,
+        escrow: writable,
+        system_program: readonly,
+        config: readonly
+    }
+
+    /// Sent by anyone, the rent still going to the payer.
+    pub struct CloseExpiredReservationAccounts {
+        payer: writable,
+        recipient: readonly,
+        escrow: writable
+    }
+
+    pub struct WithdrawVestedAccounts {
+        recipient: writable signer,
+        payer: writable,
+        escrow: writable,
+        config: readonly
+    }
+
+    pub struct WithdrawVestedShareAccounts {
+        /// the recipient or one of the splits
+        beneficiary: writable signer,
+        payer: writable,
+        /// only used to check the escrow PDA
+        recipient: readonly,
+        escrow: writable,
+        config: readonly,
+        /// `EscrowAssignments` PDA, weighing the beneficiaries
+        assignments: writable,
+        /// `VestingShare` PDA of the beneficiary
+        vesting_share: writable
+    }
+
+    pub struct TouchAccounts {
+        escrow: readonly
+    }
+
+    pub struct QuarantineAccounts {
+        escrow: writable
+    }
+
+    pub struct IndexDeadlineAccounts {
+        signer: writable signer,
+        escrow: readonly,
+        deadline_bucket: writable,
+        system_program: readonly
+    }
+
+    pub struct ExportStateAccounts {
+        admin: writable signer,
+        config: readonly,
+        escrow: writable,
+        /// `EscrowExport` PDA
+        export: writable,
+        system_program: readonly
+    }
+
+    pub struct ImportStateAccounts {
+        admin: writable signer,
+        config: readonly,
+        /// `EscrowExport` PDA, owned by the program the escrow was exported from
+        export: readonly,
+        import_marker: writable,
+        escrow: writable,
+        system_program: readonly
+    }
+
+    pub struct NominateAdminAccounts {
+        admin: readonly signer,
+        config: writable
+    }
+
+    pub struct AcceptAdminAccounts {
+        nominee: readonly signer,
+        config: writable
+    }
+
+    pub struct SetFeaturesAccounts {
+        admin: readonly signer,
+        config: writable
+    }
+
+    /// Followed by the venue's stake pool unless the venue is being unset.
+    pub struct SetYieldVenueAccounts {
+        admin: readonly signer,
+        config: writable
+    }
+
+    pub struct SweepFeesAccounts {
+        admin: readonly signer,
+        config: writable,
+        fee_vault: writable,
+        /// treasury PDA, system-owned
+        treasury: writable,
+        /// the treasury's token account of the pool mint
+        pool_tokens: writable,
+        pool_program: readonly,
+        pool: writable,
+        withdraw_authority: readonly,
+        reserve_stake: writable,
+        manager_fee: writable,
+        pool_mint: writable,
+        system_program: readonly,
+        token_program: readonly
+    }
+
+    pub struct RedeemTreasuryAccounts {
+        admin: readonly signer,
+        config: writable,
+        fee_vault: writable,
+        treasury: writable,
+        pool_tokens: writable,
+        pool_program: readonly,
+        pool: writable,
+        withdraw_authority: readonly,
+        reserve_stake: writable,
+        manager_fee: writable,
+        pool_mint: writable,
+        clock: readonly,
+        stake_history: readonly,
+        stake_program: readonly,
+        system_program: readonly,
+        token_program: readonly
+    }
+
+    pub struct AcknowledgeReceiptAccounts {
+        recipient: readonly signer,
+        payer: readonly,
+        /// the completed (closed) escrow
+        escrow: readonly,
+        archive: writable
+    }
+
+    /// Followed by the vote account unless the stake settlement is being unset.
+    pub struct SetStakeSettlementAccounts {
+        /// the recipient, or the assignee of the claim
+        holder: readonly signer,
+        payer: readonly,
+        recipient: readonly,
+        escrow: writable
+    }
+}
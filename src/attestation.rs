@@ -0,0 +1,69 @@
+use solana_program::{
+    pubkey,
+    pubkey::Pubkey,
+    account_info::AccountInfo,
+    program_error::ProgramError
+};
+
+
+/// Solana Attestation Service program, owner of credentials, schemas and attestations.
+pub const ATTESTATION_SERVICE_PROGRAM_ID: Pubkey = pubkey!("22zoJMtdu4tQc2PzL74ZUT7FrwgB1Udec8DdW4yw4BdG");
+
+const ATTESTATION_DISCRIMINATOR: u8 = 2;
+
+
+/// The parts of an attestation a KYC gate cares about.
+pub struct Attestation {
+    /// subject of the attestation (the attested wallet)
+    pub nonce: Pubkey,
+    /// issuer
+    pub credential: Pubkey,
+    pub schema: Pubkey,
+    /// unix timestamp, 0 if it never expires
+    pub expiry: i64
+}
+
+impl Attestation {
+    /// Reads an attestation account and rejects it if it has expired at `now`.
+    pub fn load(attestation_account: &AccountInfo, now: i64) -> Result<Self, ProgramError> {
+        if attestation_account.owner != &ATTESTATION_SERVICE_PROGRAM_ID {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        let attestation: Self = Self::unpack(&attestation_account.data.borrow())?;
+
+        if attestation.expiry != 0 && attestation.expiry <= now {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        Ok(attestation)
+    }
+
+    /// Layout: discriminator (1) + nonce (32) + credential (32) + schema (32) + data (u32 length + bytes)
+    /// + signer (32) + expiry (8) + ...
+    fn unpack(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.first() != Some(&ATTESTATION_DISCRIMINATOR) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let read_pubkey = |offset: usize| -> Result<Pubkey, ProgramError> {
+            Ok(Pubkey::new_from_array(src.get(offset..offset + 32).and_then(|slice| slice.try_into().ok()).ok_or(ProgramError::InvalidAccountData)?))
+        };
+
+        let data_len: usize = src.get(97..101)
+            .and_then(|slice| slice.try_into().ok())
+            .map(u32::from_le_bytes)
+            .ok_or(ProgramError::InvalidAccountData)? as usize;
+        let expiry_offset: usize = 101 + data_len + 32;
+
+        Ok(Self {
+            nonce: read_pubkey(1)?,
+            credential: read_pubkey(33)?,
+            schema: read_pubkey(65)?,
+            expiry: src.get(expiry_offset..expiry_offset + 8)
+                .and_then(|slice| slice.try_into().ok())
+                .map(i64::from_le_bytes)
+                .ok_or(ProgramError::InvalidAccountData)?
+        })
+    }
+}
@@ -0,0 +1,116 @@
+//! Off-chain settlement authorizations, so a relayer can settle escrows their parties never sign a transaction for
+//! (see `BatchSettle`).
+//!
+//! The payer signs `settlement_message()` of the escrow with its wallet key, off-chain. The relayer puts the signatures
+//! into an ed25519 program instruction (`ed25519_instruction()`) ahead of `BatchSettle`: the runtime verifies them
+//! before the program runs, which then only has to find each escrow's message among them (`verified_messages()`).
+use solana_program::{
+    account_info::AccountInfo,
+    ed25519_program,
+    instruction::Instruction,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    sysvar
+};
+
+
+/// Tells settlement authorizations apart from anything else the payer's key might sign.
+pub const SETTLEMENT_PREFIX: &[u8; 16] = b"escrow-settle-v1";
+
+/// prefix (16) + escrow (32) + id (8)
+pub const SETTLEMENT_MESSAGE_LEN: usize = 56;
+
+/// num_signatures (1) + padding (1)
+const ED25519_HEADER_LEN: usize = 2;
+/// signature offset, signature instruction index, public key offset, public key instruction index,
+/// message offset, message size, message instruction index (2 each)
+const ED25519_OFFSETS_LEN: usize = 14;
+/// instruction index meaning "this instruction", where `ed25519_instruction()` puts everything
+const CURRENT_INSTRUCTION: u16 = u16::MAX;
+
+
+/// What the payer signs to let its escrow be completed by someone else. Bound to the escrow's `id`, so it can't be
+/// replayed against a later escrow at the same address.
+pub fn settlement_message(escrow_pda: &Pubkey, id: u64) -> [u8; SETTLEMENT_MESSAGE_LEN] {
+    let mut message: [u8; SETTLEMENT_MESSAGE_LEN] = [0; SETTLEMENT_MESSAGE_LEN];
+    message[..16].copy_from_slice(SETTLEMENT_PREFIX);
+    message[16..48].copy_from_slice(escrow_pda.as_ref());
+    message[48..].copy_from_slice(&id.to_le_bytes());
+    message
+}
+
+/// Ed25519 program instruction verifying every `(signer, signature, message)`, all of them carried in its own data.
+pub fn ed25519_instruction(authorizations: &[(Pubkey, [u8; 64], Vec<u8>)]) -> Instruction {
+    let mut offsets: Vec<u8> = Vec::with_capacity(ED25519_HEADER_LEN + authorizations.len() * ED25519_OFFSETS_LEN);
+    let mut payloads: Vec<u8> = Vec::new();
+    let payloads_start: usize = ED25519_HEADER_LEN + authorizations.len() * ED25519_OFFSETS_LEN;
+
+    offsets.push(authorizations.len() as u8);
+    offsets.push(0);
+
+    for (signer, signature, message) in authorizations {
+        let signature_offset: usize = payloads_start + payloads.len();
+        let public_key_offset: usize = signature_offset + 64;
+        let message_offset: usize = public_key_offset + 32;
+
+        for field in [
+            signature_offset as u16,
+            CURRENT_INSTRUCTION,
+            public_key_offset as u16,
+            CURRENT_INSTRUCTION,
+            message_offset as u16,
+            message.len() as u16,
+            CURRENT_INSTRUCTION
+        ] {
+            offsets.extend_from_slice(&field.to_le_bytes());
+        }
+
+        payloads.extend_from_slice(signature);
+        payloads.extend_from_slice(signer.as_ref());
+        payloads.extend_from_slice(message);
+    }
+
+    offsets.extend_from_slice(&payloads);
+
+    Instruction::new_with_bytes(ed25519_program::ID, &offsets, vec![])
+}
+
+/// Every `(signer, message)` the ed25519 program instructions of the transaction verified, read from the Instructions
+/// sysvar. Signatures whose key or message sit in another instruction are left out, they're not `ed25519_instruction()`'s.
+pub fn verified_messages(instructions_sysvar_account: &AccountInfo) -> Result<Vec<(Pubkey, Vec<u8>)>, ProgramError> {
+    if !sysvar::instructions::check_id(instructions_sysvar_account.key) {
+        return Err(ProgramError::UnsupportedSysvar);
+    }
+
+    let mut messages: Vec<(Pubkey, Vec<u8>)> = Vec::new();
+
+    let mut index: usize = 0;
+    while let Ok(instruction) = sysvar::instructions::load_instruction_at_checked(index, instructions_sysvar_account) {
+        index += 1;
+
+        if instruction.program_id != ed25519_program::ID {
+            continue;
+        }
+
+        let data: &[u8] = &instruction.data;
+        let count: usize = *data.first().ok_or(ProgramError::InvalidInstructionData)? as usize;
+
+        for i in 0..count {
+            let offsets: &[u8] = data.get(ED25519_HEADER_LEN + i * ED25519_OFFSETS_LEN..ED25519_HEADER_LEN + (i + 1) * ED25519_OFFSETS_LEN)
+                .ok_or(ProgramError::InvalidInstructionData)?;
+            let field = |n: usize| u16::from_le_bytes([offsets[n * 2], offsets[n * 2 + 1]]);
+
+            if [field(1), field(3), field(6)] != [CURRENT_INSTRUCTION; 3] {
+                continue;
+            }
+
+            let (public_key_offset, message_offset, message_size) = (field(2) as usize, field(4) as usize, field(5) as usize);
+            let signer: &[u8] = data.get(public_key_offset..public_key_offset + 32).ok_or(ProgramError::InvalidInstructionData)?;
+            let message: &[u8] = data.get(message_offset..message_offset + message_size).ok_or(ProgramError::InvalidInstructionData)?;
+
+            messages.push((Pubkey::new_from_array(signer.try_into().map_err(|_| ProgramError::InvalidInstructionData)?), message.to_vec()));
+        }
+    }
+
+    Ok(messages)
+}
@@ -0,0 +1,154 @@
+//! Operates SOL escrows from a terminal, over `escrow::client` (`cargo run --features cli --bin escrow-cli -- ...`):
+//!
+//! ```text
+//! escrow-cli [--url <cluster|url>] [--keypair <path>] init --recipient <pubkey> --amount <lamports>
+//! escrow-cli complete --recipient <pubkey>
+//! escrow-cli close --recipient <pubkey>
+//! escrow-cli show --recipient <pubkey> [--payer <pubkey>]
+//! escrow-cli list [--payer <pubkey> | --recipient <pubkey>]
+//! ```
+//!
+//! `--url` takes a cluster name (as `Cluster::from_str()` accepts it) or an RPC URL, `--keypair` the payer's keypair
+//! file, both defaulting to the solana CLI config's (`--config`). The program id is the one this binary was built for (see `cluster`).
+use clap::{Parser, Subcommand};
+use escrow::{
+    client::{self, CliConfig, EscrowClient},
+    cluster::Cluster,
+    state::EscrowAccount
+};
+use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_sdk::{
+    pubkey::Pubkey,
+    signature::{Keypair, Signature},
+    signer::Signer
+};
+
+
+#[derive(Parser)]
+#[command(name = "escrow-cli", about = "Operates SOL escrows of the escrow program")]
+struct Cli {
+    /// solana CLI config file, `~/.config/solana/cli/config.yml` by default
+    #[arg(short = 'C', long, global = true)]
+    config: Option<String>,
+    /// cluster (`mainnet-beta`, `devnet`, `testnet`, `localhost`) or RPC URL, the CLI config's by default
+    #[arg(short, long, global = true)]
+    url: Option<String>,
+    /// payer's keypair file, the CLI config's by default
+    #[arg(short, long, global = true)]
+    keypair: Option<String>,
+    #[command(subcommand)]
+    command: Command
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Locks `amount` lamports for the recipient
+    Init {
+        #[arg(long)]
+        recipient: Pubkey,
+        #[arg(long)]
+        amount: u64
+    },
+    /// Releases the escrow to the recipient
+    Complete {
+        #[arg(long)]
+        recipient: Pubkey
+    },
+    /// Refunds the escrow to the payer
+    Close {
+        #[arg(long)]
+        recipient: Pubkey
+    },
+    /// Prints the escrow between the payer and the recipient
+    Show {
+        #[arg(long)]
+        recipient: Pubkey,
+        /// the keypair's pubkey by default
+        #[arg(long)]
+        payer: Option<Pubkey>
+    },
+    /// Lists the escrows of a payer (the keypair's by default) or of a recipient
+    List {
+        #[arg(long, conflicts_with = "recipient")]
+        payer: Option<Pubkey>,
+        #[arg(long)]
+        recipient: Option<Pubkey>
+    }
+}
+
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let cli: Cli = Cli::parse();
+
+    let mut config: CliConfig = CliConfig::load(cli.config.as_deref())?;
+
+    if let Some(url) = &cli.url {
+        config.json_rpc_url = url.parse::<Cluster>().map(|cluster| cluster.rpc_url().to_string()).unwrap_or_else(|_| url.clone());
+    }
+    if let Some(keypair) = &cli.keypair {
+        config.keypair_path = keypair.clone();
+    }
+
+    let rpc: RpcClient = config.rpc_client();
+    let payer: Keypair = config.keypair()?;
+
+    match cli.command {
+        Command::Init { recipient, amount } => {
+            let client: EscrowClient = EscrowClient::new(rpc, payer);
+            let signature: Signature = client.initialize(&recipient, amount).await?;
+            print_signature(&client, &recipient, &signature);
+        },
+        Command::Complete { recipient } => {
+            let client: EscrowClient = EscrowClient::new(rpc, payer);
+            let signature: Signature = client.complete(&recipient).await?;
+            print_signature(&client, &recipient, &signature);
+        },
+        Command::Close { recipient } => {
+            let client: EscrowClient = EscrowClient::new(rpc, payer);
+            let signature: Signature = client.close(&recipient).await?;
+            print_signature(&client, &recipient, &signature);
+        },
+        Command::Show { recipient, payer: payer_pkey } => {
+            let payer_pkey: Pubkey = payer_pkey.unwrap_or_else(|| payer.pubkey());
+            let escrow: EscrowAccount = EscrowAccount::fetch(&rpc, &payer_pkey, &recipient).await?;
+            print_escrow(&client::escrow_address(&payer_pkey, &recipient), &escrow);
+        },
+        Command::List { payer: payer_pkey, recipient } => {
+            let escrows: Vec<(Pubkey, EscrowAccount)> = match recipient {
+                Some(recipient) => client::list_escrows_by_recipient(&rpc, &recipient).await?,
+                None => client::list_escrows_by_payer(&rpc, &payer_pkey.unwrap_or_else(|| payer.pubkey())).await?
+            };
+
+            for (escrow_pda, escrow) in &escrows {
+                print_escrow(escrow_pda, escrow);
+                println!();
+            }
+            println!("{} escrow(s)", escrows.len());
+        }
+    }
+
+    Ok(())
+}
+
+fn print_signature(client: &EscrowClient, recipient_pkey: &Pubkey, signature: &Signature) {
+    println!("escrow:    {}", client.escrow_address(recipient_pkey));
+    println!("signature: {}", signature);
+}
+
+fn print_escrow(escrow_pda: &Pubkey, escrow: &EscrowAccount) {
+    println!("escrow:    {}", escrow_pda);
+    println!("id:        {}", escrow.id);
+    println!("status:    {:?}", escrow.status);
+    println!("payer:     {}", escrow.payer);
+    println!("recipient: {}", escrow.recipient);
+    println!("amount:    {} lamports", escrow.amount);
+
+    if escrow.deadline != 0 {
+        println!("deadline:  {}", escrow.deadline);
+    }
+
+    if escrow.is_vesting() {
+        println!("vesting:   {} to {}, {} withdrawn", escrow.vesting_start, escrow.vesting_end, escrow.withdrawn);
+    }
+}
@@ -0,0 +1,331 @@
+//! Replays a transaction of the escrow program locally against this crate's processor, printing the full decision
+//! trace (program logs, CPIs, decoded events) and how the outcome compares with the ledger's, for disputed settlements
+//! and other incidents (`cargo run --features cli,replay --bin escrow-replay -- ...`):
+//!
+//! ```text
+//! escrow-replay [--url <cluster|url>] <signature> [--max-history <n>]
+//! ```
+//!
+//! RPC nodes don't serve past account states, so they're rebuilt: accounts of the program the transaction writes
+//! (escrows, ...) by replaying their earlier transactions from their creation on (at most `--max-history` of them),
+//! every other account from its current state, with the lamports and token amounts the ledger recorded right before
+//! each transaction. The replay uses the clock of the transaction's slot & block time.
+use std::collections::{HashMap, HashSet};
+use clap::Parser;
+use escrow::{
+    client::CliConfig,
+    cluster::Cluster,
+    replay::{ReplayAccount, Replayer}
+};
+use solana_client::{
+    nonblocking::rpc_client::RpcClient,
+    rpc_client::GetConfirmedSignaturesForAddress2Config,
+    rpc_config::RpcTransactionConfig
+};
+use solana_sdk::{
+    account::Account,
+    clock::Clock,
+    epoch_schedule::EpochSchedule,
+    instruction::{AccountMeta, Instruction},
+    message::VersionedMessage,
+    program_error::ProgramError,
+    pubkey::Pubkey,
+    signature::Signature,
+    transaction::{TransactionError, VersionedTransaction}
+};
+use solana_transaction_status_client_types::{UiLoadedAddresses, UiTransactionEncoding};
+
+
+/// Signatures `getSignaturesForAddress` returns at most per call.
+const SIGNATURES_PAGE: usize = 1_000;
+/// `getMultipleAccounts` limit.
+const ACCOUNTS_PAGE: usize = 100;
+/// SPL token account `amount` (after mint & owner).
+const TOKEN_AMOUNT_OFFSET: usize = 64;
+
+
+#[derive(Parser)]
+#[command(name = "escrow-replay", about = "Replays a transaction of the escrow program locally, printing its decision trace")]
+struct Cli {
+    /// solana CLI config file, `~/.config/solana/cli/config.yml` by default
+    #[arg(short = 'C', long)]
+    config: Option<String>,
+    /// cluster (`mainnet-beta`, `devnet`, `testnet`, `localhost`) or RPC URL, the CLI config's by default
+    #[arg(short, long)]
+    url: Option<String>,
+    /// transaction to replay
+    signature: Signature,
+    /// earlier transactions replayed at most to rebuild the state of the program's accounts
+    #[arg(long, default_value_t = 100)]
+    max_history: usize
+}
+
+/// A transaction as the ledger recorded it.
+struct LedgerTransaction {
+    signature: Signature,
+    slot: u64,
+    block_time: Option<i64>,
+    keys: Vec<Pubkey>,
+    writable: Vec<bool>,
+    instructions: Vec<Instruction>,
+    fee: u64,
+    pre_balances: Vec<u64>,
+    post_balances: Vec<u64>,
+    /// `(account index, amount)` of the token accounts
+    pre_token_amounts: Vec<(usize, u64)>,
+    err: Option<TransactionError>
+}
+
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let cli: Cli = Cli::parse();
+
+    let mut config: CliConfig = CliConfig::load(cli.config.as_deref())?;
+
+    if let Some(url) = &cli.url {
+        config.json_rpc_url = url.parse::<Cluster>().map(|cluster| cluster.rpc_url().to_string()).unwrap_or_else(|_| url.clone());
+    }
+
+    let rpc: RpcClient = config.rpc_client();
+
+    let target: LedgerTransaction = fetch_transaction(&rpc, &cli.signature).await?;
+    let schedule: EpochSchedule = rpc.get_epoch_schedule().await?;
+    let mut current: HashMap<Pubkey, Option<Account>> = HashMap::new();
+    fetch_accounts(&rpc, &target.keys, &mut current).await?;
+
+    // 1. the program's accounts the transaction writes (or that are gone by now) are rebuilt from their history
+    let tracked: HashSet<Pubkey> = target.keys.iter().zip(&target.writable)
+        .filter(|(key, writable)| **writable && current[*key].as_ref().is_none_or(|account| account.owner == escrow::ID))
+        .map(|(key, _)| *key)
+        .collect();
+
+    let history: Vec<(u64, Signature)> = fetch_history(&rpc, &tracked, &target.signature, cli.max_history).await?;
+    let mut replayer: Replayer = Replayer::new(HashMap::new(), clock(&schedule, target.slot, target.block_time));
+
+    for (_, signature) in &history {
+        let transaction: LedgerTransaction = fetch_transaction(&rpc, signature).await?;
+        fetch_accounts(&rpc, &transaction.keys, &mut current).await?;
+
+        println!("--- rebuilding state: {} (slot {})", transaction.signature, transaction.slot);
+        load_accounts(&mut replayer, &transaction, &current, &tracked);
+        replayer.clock = clock(&schedule, transaction.slot, transaction.block_time);
+
+        if let Err((index, err)) = replayer.replay(&transaction.instructions) {
+            println!("warning: instruction {} failed in the replay but succeeded on-chain: {}", index, err);
+        }
+    }
+
+    // 2. the transaction itself
+    println!();
+    println!("=== replaying {} (slot {}, block time {:?})", target.signature, target.slot, target.block_time);
+
+    load_accounts(&mut replayer, &target, &current, &tracked);
+    replayer.clock = clock(&schedule, target.slot, target.block_time);
+
+    for (index, key) in target.keys.iter().enumerate() {
+        let lamports: u64 = replayer.accounts.get(key).map_or(0, |account| account.lamports);
+
+        if tracked.contains(key) && lamports != target.pre_balances[index] {
+            println!("warning: {} rebuilt with {} lamports, the ledger had {} before the transaction", key, lamports, target.pre_balances[index]);
+        }
+    }
+
+    let outcome: Result<(), (usize, ProgramError)> = replayer.replay(&target.instructions);
+
+    // 3. the replay against the ledger
+    println!();
+    println!("=== outcome");
+
+    let mut matches: bool = outcome.is_ok() == target.err.is_none();
+
+    match &outcome {
+        Ok(()) => println!("replay:  success"),
+        Err((index, err)) => println!("replay:  instruction {} failed: {}", index, err)
+    }
+    match &target.err {
+        None => println!("ledger:  success"),
+        Some(err) => println!("ledger:  {}", err)
+    }
+
+    println!();
+    println!("{:<44} {:>16} {:>16} {:>16}", "account", "pre", "replayed post", "ledger post");
+
+    for (index, key) in target.keys.iter().enumerate() {
+        // the replay doesn't charge the fee
+        let ledger_post: u64 = target.post_balances[index] + if index == 0 { target.fee } else { 0 };
+        let replayed_post: u64 = replayer.accounts.get(key).map_or(0, |account| account.lamports);
+        let marker: &str = if replayed_post == ledger_post { "" } else { "  <- differs" };
+
+        matches &= replayed_post == ledger_post;
+        println!("{:<44} {:>16} {:>16} {:>16}{}", key, target.pre_balances[index], replayed_post, ledger_post, marker);
+    }
+
+    println!();
+    println!("{}", if matches { "the replay matches the ledger" } else { "the replay DIFFERS from the ledger" });
+
+    Ok(())
+}
+
+async fn fetch_transaction(rpc: &RpcClient, signature: &Signature) -> Result<LedgerTransaction, Box<dyn std::error::Error>> {
+    let confirmed = rpc.get_transaction_with_config(
+        signature,
+        RpcTransactionConfig {
+            encoding: Some(UiTransactionEncoding::Base64),
+            commitment: Some(rpc.commitment()),
+            max_supported_transaction_version: Some(0)
+        }
+    ).await?;
+
+    let transaction: VersionedTransaction = confirmed.transaction.transaction.decode().ok_or("transaction couldn't be decoded")?;
+    let meta = confirmed.transaction.meta.ok_or("transaction has no status meta")?;
+    let message: &VersionedMessage = &transaction.message;
+
+    let mut keys: Vec<Pubkey> = message.static_account_keys().to_vec();
+    if let Some(loaded) = Option::<UiLoadedAddresses>::from(meta.loaded_addresses.clone()) {
+        for address in loaded.writable.iter().chain(&loaded.readonly) {
+            keys.push(address.parse()?);
+        }
+    }
+
+    let writable: Vec<bool> = (0..keys.len()).map(|index| message.is_maybe_writable(index, None)).collect();
+    let instructions: Vec<Instruction> = message.instructions().iter().map(|compiled| Instruction {
+        program_id: keys[compiled.program_id_index as usize],
+        accounts: compiled.accounts.iter().map(|&index| AccountMeta {
+            pubkey: keys[index as usize],
+            is_signer: message.is_signer(index as usize),
+            is_writable: writable[index as usize]
+        }).collect(),
+        data: compiled.data.clone()
+    }).collect();
+
+    let pre_token_amounts: Vec<(usize, u64)> = Option::<Vec<_>>::from(meta.pre_token_balances.clone())
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|balance| Some((balance.account_index as usize, balance.ui_token_amount.amount.parse().ok()?)))
+        .collect();
+
+    Ok(LedgerTransaction {
+        signature: *signature,
+        slot: confirmed.slot,
+        block_time: confirmed.block_time,
+        keys,
+        writable,
+        instructions,
+        fee: meta.fee,
+        pre_balances: meta.pre_balances,
+        post_balances: meta.post_balances,
+        pre_token_amounts,
+        err: meta.err
+    })
+}
+
+/// Fetches the current state of the `keys` missing from `current`.
+async fn fetch_accounts(rpc: &RpcClient, keys: &[Pubkey], current: &mut HashMap<Pubkey, Option<Account>>) -> Result<(), Box<dyn std::error::Error>> {
+    let missing: Vec<Pubkey> = keys.iter().filter(|key| !current.contains_key(key)).copied().collect::<HashSet<_>>().into_iter().collect();
+
+    for chunk in missing.chunks(ACCOUNTS_PAGE) {
+        for (key, account) in chunk.iter().zip(rpc.get_multiple_accounts(chunk).await?) {
+            current.insert(*key, account);
+        }
+    }
+
+    Ok(())
+}
+
+/// Successful transactions before `before` touching any of `tracked`, oldest first. Transactions of the same slot
+/// keep the order the ledger lists them in for each account.
+async fn fetch_history(
+    rpc: &RpcClient,
+    tracked: &HashSet<Pubkey>,
+    before: &Signature,
+    max_history: usize
+) -> Result<Vec<(u64, Signature)>, Box<dyn std::error::Error>> {
+    let mut history: Vec<(u64, Signature)> = Vec::new();
+
+    for key in tracked {
+        let mut account_history: Vec<(u64, Signature)> = Vec::new();
+        let mut page_before: Signature = *before;
+
+        loop {
+            let page = rpc.get_signatures_for_address_with_config(
+                key,
+                GetConfirmedSignaturesForAddress2Config {
+                    before: Some(page_before),
+                    until: None,
+                    limit: Some(SIGNATURES_PAGE),
+                    commitment: Some(rpc.commitment())
+                }
+            ).await?;
+
+            let exhausted: bool = page.len() < SIGNATURES_PAGE;
+
+            for status in page {
+                page_before = status.signature.parse()?;
+
+                if status.err.is_none() {
+                    account_history.push((status.slot, page_before));
+                }
+            }
+
+            if account_history.len() >= max_history {
+                account_history.truncate(max_history);
+                println!("warning: history of {} truncated at {} transactions, its rebuilt state may differ from the ledger's", key, max_history);
+                break;
+            }
+
+            if exhausted {
+                break;
+            }
+        }
+
+        account_history.reverse();
+        history.extend(account_history);
+    }
+
+    // oldest first, each account's history staying in order within a slot
+    history.sort_by_key(|(slot, _)| *slot);
+    let mut seen: HashSet<Signature> = HashSet::new();
+    history.retain(|(_, signature)| seen.insert(*signature));
+
+    Ok(history)
+}
+
+/// Loads the accounts of `transaction` that aren't rebuilt from history, as they were right before it.
+fn load_accounts(replayer: &mut Replayer, transaction: &LedgerTransaction, current: &HashMap<Pubkey, Option<Account>>, tracked: &HashSet<Pubkey>) {
+    for (index, key) in transaction.keys.iter().enumerate() {
+        if tracked.contains(key) {
+            continue;
+        }
+
+        let mut account: ReplayAccount = current.get(key).cloned().flatten().map(|account| ReplayAccount {
+            lamports: account.lamports,
+            data: account.data,
+            owner: account.owner,
+            executable: account.executable
+        }).unwrap_or_default();
+
+        account.lamports = transaction.pre_balances[index];
+
+        if let Some((_, amount)) = transaction.pre_token_amounts.iter().find(|(account_index, _)| *account_index == index) {
+            if let Some(slice) = account.data.get_mut(TOKEN_AMOUNT_OFFSET..TOKEN_AMOUNT_OFFSET + 8) {
+                slice.copy_from_slice(&amount.to_le_bytes());
+            }
+        }
+
+        replayer.accounts.insert(*key, account);
+    }
+}
+
+/// The clock of `slot`, the epoch's start being unknown to RPC nodes (set to the block time).
+fn clock(schedule: &EpochSchedule, slot: u64, block_time: Option<i64>) -> Clock {
+    let unix_timestamp: i64 = block_time.unwrap_or_default();
+
+    Clock {
+        slot,
+        epoch_start_timestamp: unix_timestamp,
+        epoch: schedule.get_epoch(slot),
+        leader_schedule_epoch: schedule.get_leader_schedule_epoch(slot),
+        unix_timestamp
+    }
+}
@@ -0,0 +1,232 @@
+//! Jito bundle submission (behind the `bundle` feature), for settling escrows atomically with other transactions.
+//!
+//! A bundle is up to `MAX_BUNDLE_TRANSACTIONS` transactions a block engine forwards to Jito validators, executed
+//! in order and all-or-nothing within one slot. Validators only pick it up with a tip: a transfer to one of the
+//! `TIP_ACCOUNTS`, made in the bundle's last transaction so it's only paid if everything before it landed.
+//! `EscrowClient::complete_bundled()` & `EscrowClient::close_bundled()` settle an escrow after transactions signed
+//! by others (e.g. the recipient's side of a trade), with the payer paying the tip.
+use serde::Deserialize;
+use serde_json::{json, Value};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use solana_program::{instruction::Instruction, pubkey, pubkey::Pubkey, system_instruction};
+use solana_sdk::{hash::Hash, signer::Signer, transaction::Transaction};
+use crate::{client::{self, EscrowClient}, instruction::EscrowInstruction};
+
+
+/// Block engine of mainnet-beta, routing to the closest region.
+pub const MAINNET_BLOCK_ENGINE_URL: &str = "https://mainnet.block-engine.jito.wtf";
+
+/// Transactions a bundle holds at most.
+pub const MAX_BUNDLE_TRANSACTIONS: usize = 5;
+
+/// Tip accounts of the Jito tip payment program on mainnet-beta (`BlockEngine::tip_accounts()` fetches them).
+pub const TIP_ACCOUNTS: [Pubkey; 8] = [
+    pubkey!("96gYZGLnJYVFmbjzopPSU6QiEV5fGqZNyN9nmNhvrZU5"),
+    pubkey!("HFqU5x63VTqvQss8hp11i4wVV8bD44PvwucfZ2bU7gRe"),
+    pubkey!("Cw8CFyM9FkoMi7K7Crf6HNQqf4uEMzpKw6QNghXLvLkY"),
+    pubkey!("ADaUMid9yfUytqMBgopwjb2DTLSokTSzL1zt6iGPaS49"),
+    pubkey!("DfXygSm4jCyNCybVYYK6DwvWqjKee8pbDmJGcLWNDXjh"),
+    pubkey!("ADuUkR4vqLUMWXxW9gh6D6L8pMSawimctcNZ5pGwDcEt"),
+    pubkey!("DttWaMuVvTiduZRnguLF7jNxTgiMBZ1hyAumKUiL2KRL"),
+    pubkey!("3AVi9Tg9Uo68tJfuvoKvqKNWKkC5wPdSSdeBnizKZ6jT")
+];
+
+
+#[derive(Debug)]
+pub enum Error {
+    /// the block engine couldn't be reached, or its response read
+    Http(reqwest::Error),
+    /// the block engine rejected the request
+    BlockEngine(String),
+    /// the bundle holds no transaction, or more than `MAX_BUNDLE_TRANSACTIONS`
+    InvalidBundle(usize),
+    /// the payer's transaction couldn't be built or signed
+    Client(client::Error)
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Http(e) => write!(f, "http error: {}", e),
+            Self::BlockEngine(e) => write!(f, "block engine error: {}", e),
+            Self::InvalidBundle(len) => write!(f, "bundles hold 1 to {} transactions, not {}", MAX_BUNDLE_TRANSACTIONS, len),
+            Self::Client(e) => write!(f, "{}", e)
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Self {
+        Self::Http(e)
+    }
+}
+
+impl From<client::Error> for Error {
+    fn from(e: client::Error) -> Self {
+        Self::Client(e)
+    }
+}
+
+
+/// Status of a bundle submitted in the last 5 minutes, see `BlockEngine::bundle_status()`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+pub enum BundleStatus {
+    /// unknown to the block engine, or older than 5 minutes
+    Invalid,
+    /// not landed yet
+    Pending,
+    /// all the regions' auctions failed, e.g. outbid by other bundles
+    Failed,
+    /// landed, which doesn't mean it's confirmed yet
+    Landed
+}
+
+#[derive(Deserialize)]
+struct JsonRpcResponse {
+    result: Option<Value>,
+    error: Option<Value>
+}
+
+#[derive(Deserialize)]
+struct InflightBundleStatuses {
+    value: Vec<InflightBundleStatus>
+}
+
+#[derive(Deserialize)]
+struct InflightBundleStatus {
+    status: BundleStatus
+}
+
+
+/// Transfer of `lamports` from `payer_pkey` to `tip_account`, to end the bundle's last transaction with.
+pub fn tip_instruction(payer_pkey: &Pubkey, tip_account: &Pubkey, lamports: u64) -> Instruction {
+    system_instruction::transfer(payer_pkey, tip_account, lamports)
+}
+
+/// One of the `TIP_ACCOUNTS`, picked from `blockhash` so concurrent bundles don't all write-lock the same one.
+pub fn tip_account(blockhash: &Hash) -> Pubkey {
+    TIP_ACCOUNTS[blockhash.as_ref()[0] as usize % TIP_ACCOUNTS.len()]
+}
+
+
+/// JSON-RPC client of a block engine's bundle API.
+pub struct BlockEngine {
+    url: String,
+    http: reqwest::Client
+}
+
+impl BlockEngine {
+    /// Block engine at `url`, e.g. `MAINNET_BLOCK_ENGINE_URL` or a regional one (`https://ny.mainnet.block-engine.jito.wtf`).
+    pub fn new(url: &str) -> Self {
+        Self { url: url.trim_end_matches('/').to_string(), http: reqwest::Client::new() }
+    }
+
+    pub fn mainnet() -> Self {
+        Self::new(MAINNET_BLOCK_ENGINE_URL)
+    }
+
+    /// Submits `transactions` (signed) as one bundle, returning its id.
+    pub async fn send_bundle(&self, transactions: &[Transaction]) -> Result<String, Error> {
+        if transactions.is_empty() || transactions.len() > MAX_BUNDLE_TRANSACTIONS {
+            return Err(Error::InvalidBundle(transactions.len()));
+        }
+
+        let encoded: Vec<String> = transactions
+            .iter()
+            .map(|tx| STANDARD.encode(bincode::serialize(tx).expect("transactions always serialize")))
+            .collect();
+
+        let result: Value = self.request("sendBundle", json!([encoded, { "encoding": "base64" }])).await?;
+        result.as_str().map(str::to_string).ok_or_else(|| Error::BlockEngine(format!("unexpected bundle id {}", result)))
+    }
+
+    /// Status of the bundle `bundle_id`, `BundleStatus::Invalid` once it's older than 5 minutes.
+    pub async fn bundle_status(&self, bundle_id: &str) -> Result<BundleStatus, Error> {
+        let result: Value = self.request("getInflightBundleStatuses", json!([[bundle_id]])).await?;
+        let statuses: InflightBundleStatuses = serde_json::from_value(result)
+            .map_err(|e| Error::BlockEngine(e.to_string()))?;
+
+        Ok(statuses.value.first().map_or(BundleStatus::Invalid, |status| status.status))
+    }
+
+    /// Tip accounts as the block engine reports them, in case they differ from `TIP_ACCOUNTS`.
+    pub async fn tip_accounts(&self) -> Result<Vec<Pubkey>, Error> {
+        let result: Value = self.request("getTipAccounts", json!([])).await?;
+        let accounts: Vec<String> = serde_json::from_value(result)
+            .map_err(|e| Error::BlockEngine(e.to_string()))?;
+
+        accounts
+            .iter()
+            .map(|account| account.parse().map_err(|_| Error::BlockEngine(format!("invalid tip account {}", account))))
+            .collect()
+    }
+
+    async fn request(&self, method: &str, params: Value) -> Result<Value, Error> {
+        let response: JsonRpcResponse = self.http
+            .post(format!("{}/api/v1/bundles", self.url))
+            .json(&json!({ "jsonrpc": "2.0", "id": 1, "method": method, "params": params }))
+            .send()
+            .await?
+            .json()
+            .await?;
+
+        match (response.result, response.error) {
+            (_, Some(error)) => Err(Error::BlockEngine(error.to_string())),
+            (Some(result), None) => Ok(result),
+            (None, None) => Err(Error::BlockEngine(format!("empty response to {}", method)))
+        }
+    }
+}
+
+
+impl EscrowClient {
+    /// Bundles `transactions` (signed by others) with a last one running `instructions` and tipping `tip_lamports`,
+    /// paid & signed by `payer`: either all of them land, in this order, or none.
+    /// Returns the bundle id, see `BlockEngine::bundle_status()`.
+    pub async fn send_bundle(
+        &self,
+        engine: &BlockEngine,
+        transactions: &[Transaction],
+        instructions: &[Instruction],
+        tip_lamports: u64
+    ) -> Result<String, Error> {
+        let blockhash: Hash = self.rpc.get_latest_blockhash().await.map_err(client::Error::from)?;
+
+        let mut instructions: Vec<Instruction> = instructions.to_vec();
+        instructions.push(tip_instruction(&self.payer.pubkey(), &tip_account(&blockhash), tip_lamports));
+
+        let mut tx: Transaction = Transaction::new_with_payer(&instructions, Some(&self.payer.pubkey()));
+        tx.try_sign(&[&self.payer], blockhash).map_err(client::Error::from)?;
+
+        let mut bundle: Vec<Transaction> = transactions.to_vec();
+        bundle.push(tx);
+
+        engine.send_bundle(&bundle).await
+    }
+
+    /// Releases the escrow to `recipient_pkey` after `transactions` land, atomically with them (see `send_bundle()`).
+    pub async fn complete_bundled(
+        &self,
+        engine: &BlockEngine,
+        recipient_pkey: &Pubkey,
+        transactions: &[Transaction],
+        tip_lamports: u64
+    ) -> Result<String, Error> {
+        let instructions: [Instruction; 1] = [EscrowInstruction::complete(&self.payer.pubkey(), recipient_pkey)];
+        self.send_bundle(engine, transactions, &instructions, tip_lamports).await
+    }
+
+    /// Refunds the escrow to `recipient_pkey` to the payer after `transactions` land, as `complete_bundled()`.
+    pub async fn close_bundled(
+        &self,
+        engine: &BlockEngine,
+        recipient_pkey: &Pubkey,
+        transactions: &[Transaction],
+        tip_lamports: u64
+    ) -> Result<String, Error> {
+        let instructions: [Instruction; 1] = [EscrowInstruction::close(&self.payer.pubkey(), recipient_pkey)];
+        self.send_bundle(engine, transactions, &instructions, tip_lamports).await
+    }
+}
@@ -0,0 +1,84 @@
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use solana_program::pubkey::Pubkey;
+use super::cluster::Cluster;
+
+
+/// Scheme of claim deep links, e.g. `solana-escrow:claim?p=<payload>`.
+pub const CLAIM_URI_PREFIX: &str = "solana-escrow:claim?p=";
+
+const CLAIM_PAYLOAD_VERSION: u8 = 1;
+
+
+/// Everything a wallet needs to claim an escrow, shared as a QR code or deep link.
+///
+/// Payload layout (before url-safe base64 without padding):
+/// version (1) + cluster (1) + escrow (32) + optional secret (32).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ClaimRequest {
+    pub escrow: Pubkey,
+    pub secret: Option<[u8; 32]>,
+    pub cluster: Cluster
+}
+
+impl ClaimRequest {
+    pub fn encode(&self) -> String {
+        let mut data: Vec<u8> = Vec::with_capacity(66);
+        data.push(CLAIM_PAYLOAD_VERSION);
+        data.push(Self::cluster_to_u8(self.cluster));
+        data.extend_from_slice(self.escrow.as_ref());
+
+        if let Some(secret) = self.secret {
+            data.extend_from_slice(&secret);
+        }
+
+        URL_SAFE_NO_PAD.encode(data)
+    }
+
+    pub fn decode(payload: &str) -> Result<Self, String> {
+        let data: Vec<u8> = URL_SAFE_NO_PAD.decode(payload).map_err(|e| format!("invalid claim payload: {}", e))?;
+
+        let (header, rest) = data.split_at_checked(2).ok_or("claim payload too short")?;
+
+        if header[0] != CLAIM_PAYLOAD_VERSION {
+            return Err(format!("unsupported claim payload version: {}", header[0]));
+        }
+
+        let (escrow, secret) = rest.split_at_checked(32).ok_or("claim payload too short")?;
+
+        Ok(Self {
+            escrow: Pubkey::new_from_array(escrow.try_into().map_err(|_| "claim payload too short")?),
+            secret: match secret {
+                [] => None,
+                secret => Some(secret.try_into().map_err(|_| "invalid claim secret length")?)
+            },
+            cluster: Self::cluster_from_u8(header[1])?
+        })
+    }
+
+    pub fn to_uri(&self) -> String {
+        format!("{}{}", CLAIM_URI_PREFIX, self.encode())
+    }
+
+    pub fn from_uri(uri: &str) -> Result<Self, String> {
+        Self::decode(uri.strip_prefix(CLAIM_URI_PREFIX).ok_or("not an escrow claim link")?)
+    }
+
+    fn cluster_to_u8(cluster: Cluster) -> u8 {
+        match cluster {
+            Cluster::Mainnet => 0,
+            Cluster::Devnet => 1,
+            Cluster::Testnet => 2,
+            Cluster::Localnet => 3
+        }
+    }
+
+    fn cluster_from_u8(cluster: u8) -> Result<Cluster, String> {
+        Ok(match cluster {
+            0 => Cluster::Mainnet,
+            1 => Cluster::Devnet,
+            2 => Cluster::Testnet,
+            3 => Cluster::Localnet,
+            _ => return Err(format!("unknown cluster in claim payload: {}", cluster))
+        })
+    }
+}
@@ -0,0 +1,609 @@
+//! Off-chain client for SOL escrows, over a nonblocking `RpcClient` (behind the `client` feature, never enabled for the on-chain build).
+//!
+//! `EscrowClient` builds, signs & sends the same instructions as `examples/escrow.rs`, with `payer` signing and paying for every transaction.
+//! `list_escrows_by_payer()` & `list_escrows_by_recipient()` find escrows through `getProgramAccounts`,
+//! `subscribe_escrow()` follows one live through `accountSubscribe`.
+//! `CliConfig` picks up the cluster & wallet of the solana CLI, so clients work with existing wallets out of the box.
+//! With a `PriorityFeePolicy` (`EscrowClient::with_priority_fee()`), every transaction is priced from the recent fees
+//! and limited to the compute units its simulation consumed, see `priority_fee`.
+//! Large account lists (batches, multi-recipient escrows) go through v0 transactions (`EscrowClient::send_versioned()`)
+//! resolving them via address lookup tables, created & extended by `EscrowClient::create_lookup_table()`.
+//! Transactions signed offline or hours apart are built against a durable nonce (`nonce_transaction()`), so their
+//! signatures don't expire with the blockhash.
+//! `send_with_retry()` lands a transaction under a `RetryPolicy`: resending with exponential backoff, re-signing
+//! against a fresh blockhash once the previous one expired, until it reaches the policy's commitment.
+//! With the `bundle` feature, escrows are also settled in Jito bundles, atomically with other transactions, see `bundle`.
+use futures_util::{StreamExt, stream::BoxStream};
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::{
+    client_error::ClientError,
+    nonblocking::{
+        pubsub_client::{PubsubClient, PubsubClientError, UnsubscribeFn},
+        rpc_client::RpcClient
+    },
+    rpc_config::{RpcAccountInfoConfig, RpcProgramAccountsConfig, RpcSendTransactionConfig},
+    rpc_filter::{Memcmp, RpcFilterType}
+};
+use solana_program::{instruction::Instruction, program_error::ProgramError, program_pack::Pack, pubkey::Pubkey, system_instruction, system_program};
+use std::time::{Duration, Instant};
+use solana_sdk::{
+    account::Account,
+    address_lookup_table::{self, state::AddressLookupTable},
+    commitment_config::CommitmentConfig,
+    hash::Hash,
+    message::{v0, AddressLookupTableAccount, CompileError, Message, VersionedMessage},
+    signature::{read_keypair_file, Keypair, Signature},
+    signer::{Signer, SignerError},
+    transaction::{Transaction, TransactionError, VersionedTransaction}
+};
+use crate::{
+    cluster::Cluster,
+    instruction::EscrowInstruction,
+    priority_fee::{self, PriorityFeePolicy},
+    state::EscrowAccount
+};
+
+
+#[derive(Debug)]
+pub enum Error {
+    /// the request or the transaction failed
+    Rpc(ClientError),
+    /// the subscription failed
+    Pubsub(PubsubClientError),
+    /// the account isn't an escrow
+    Program(ProgramError),
+    /// no account at this escrow address: never created, or already settled
+    AccountNotFound(Pubkey),
+    /// the solana CLI config or a keypair file couldn't be read
+    Config(String),
+    /// the transaction failed in simulation, so it wasn't sent
+    Simulation(TransactionError),
+    /// the v0 message couldn't be compiled against its lookup tables
+    Compile(CompileError),
+    /// the versioned transaction couldn't be signed
+    Signer(SignerError),
+    /// the transaction landed, and failed
+    Transaction(TransactionError),
+    /// the transaction didn't reach the commitment within the `RetryPolicy`'s attempts
+    NotConfirmed(Signature)
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Rpc(e) => write!(f, "rpc error: {}", e),
+            Self::Pubsub(e) => write!(f, "pubsub error: {}", e),
+            Self::Program(e) => write!(f, "invalid escrow account: {}", e),
+            Self::AccountNotFound(escrow_pda) => write!(f, "escrow {} not found", escrow_pda),
+            Self::Config(e) => write!(f, "config error: {}", e),
+            Self::Simulation(e) => write!(f, "simulation failed: {}", e),
+            Self::Compile(e) => write!(f, "can't compile v0 message: {}", e),
+            Self::Signer(e) => write!(f, "can't sign transaction: {}", e),
+            Self::Transaction(e) => write!(f, "transaction failed: {}", e),
+            Self::NotConfirmed(signature) => write!(f, "transaction {} not confirmed", signature)
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<ClientError> for Error {
+    fn from(e: ClientError) -> Self {
+        Self::Rpc(e)
+    }
+}
+
+impl From<PubsubClientError> for Error {
+    fn from(e: PubsubClientError) -> Self {
+        Self::Pubsub(e)
+    }
+}
+
+impl From<ProgramError> for Error {
+    fn from(e: ProgramError) -> Self {
+        Self::Program(e)
+    }
+}
+
+impl From<CompileError> for Error {
+    fn from(e: CompileError) -> Self {
+        Self::Compile(e)
+    }
+}
+
+impl From<SignerError> for Error {
+    fn from(e: SignerError) -> Self {
+        Self::Signer(e)
+    }
+}
+
+
+/// Cluster & wallet of the solana CLI (what `solana config get` shows).
+pub struct CliConfig {
+    pub json_rpc_url: String,
+    pub websocket_url: String,
+    /// the wallet's keypair file
+    pub keypair_path: String,
+    pub commitment: CommitmentConfig
+}
+
+impl CliConfig {
+    /// Reads the solana CLI's `config.yml` at `config_path`, or at its default location (`~/.config/solana/cli/config.yml`).
+    /// Without a config there, falls back to the cluster this crate was built for and `~/.config/solana/id.json`.
+    pub fn load(config_path: Option<&str>) -> Result<Self, Error> {
+        let config: solana_cli_config::Config = match (config_path, solana_cli_config::CONFIG_FILE.as_ref()) {
+            (Some(path), _) => solana_cli_config::Config::load(path).map_err(|e| Error::Config(format!("{}: {}", path, e)))?,
+            (None, Some(path)) if std::path::Path::new(path).exists() => {
+                solana_cli_config::Config::load(path).map_err(|e| Error::Config(format!("{}: {}", path, e)))?
+            },
+            (None, _) => solana_cli_config::Config {
+                json_rpc_url: Cluster::current().rpc_url().to_string(),
+                websocket_url: Cluster::current().ws_url().to_string(),
+                ..solana_cli_config::Config::default()
+            }
+        };
+
+        let websocket_url: String = match config.websocket_url.is_empty() {
+            true => solana_cli_config::Config::compute_websocket_url(&config.json_rpc_url),
+            false => config.websocket_url
+        };
+        let commitment: CommitmentConfig = config.commitment.parse()
+            .map_err(|_| Error::Config(format!("unsupported commitment: {}", config.commitment)))?;
+
+        Ok(Self { json_rpc_url: config.json_rpc_url, websocket_url, keypair_path: config.keypair_path, commitment })
+    }
+
+    /// The wallet, read from `keypair_path`.
+    pub fn keypair(&self) -> Result<Keypair, Error> {
+        read_keypair_file(&self.keypair_path).map_err(|e| Error::Config(format!("{}: {}", self.keypair_path, e)))
+    }
+
+    pub fn rpc_client(&self) -> RpcClient {
+        RpcClient::new_with_commitment(self.json_rpc_url.clone(), self.commitment)
+    }
+}
+
+
+/// Addresses a single `ExtendLookupTable` adds, keeping its transaction within the packet size.
+pub const LOOKUP_TABLE_EXTEND_CHUNK: usize = 30;
+
+/// Fetches the address lookup table at `table_pkey`, in the form v0 messages are compiled against.
+pub async fn fetch_lookup_table(rpc: &RpcClient, table_pkey: &Pubkey) -> Result<AddressLookupTableAccount, Error> {
+    let account: Account = rpc.get_account_with_commitment(table_pkey, rpc.commitment()).await?.value
+        .ok_or(Error::AccountNotFound(*table_pkey))?;
+    let table: AddressLookupTable = AddressLookupTable::deserialize(&account.data).map_err(|_| ProgramError::InvalidAccountData)?;
+
+    Ok(AddressLookupTableAccount { key: *table_pkey, addresses: table.addresses.to_vec() })
+}
+
+/// Accounts of `instructions` a lookup table can stand in for: all of them but the signers, which v0 messages
+/// always carry inline, deduplicated in order of appearance.
+pub fn lookup_table_addresses(instructions: &[Instruction]) -> Vec<Pubkey> {
+    let signers: Vec<Pubkey> = instructions.iter().flat_map(|ix| &ix.accounts).filter(|account| account.is_signer).map(|account| account.pubkey).collect();
+    let mut addresses: Vec<Pubkey> = Vec::new();
+
+    for pubkey in instructions.iter().flat_map(|ix| ix.accounts.iter().map(|account| account.pubkey).chain([ix.program_id])) {
+        if !signers.contains(&pubkey) && !addresses.contains(&pubkey) {
+            addresses.push(pubkey);
+        }
+    }
+
+    addresses
+}
+
+/// A nonce account's current nonce, which transactions use in place of a recent blockhash.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DurableNonce {
+    /// the nonce account
+    pub account: Pubkey,
+    /// signs the `AdvanceNonceAccount` of every transaction using the nonce
+    pub authority: Pubkey,
+    pub blockhash: Hash
+}
+
+impl DurableNonce {
+    /// version (4) + state (4) + authority (32) + durable nonce (32) + lamports per signature (8)
+    const LEN: usize = 80;
+
+    fn unpack(account: &Pubkey, data: &[u8]) -> Result<Self, ProgramError> {
+        // `State::Initialized`, of either the legacy or the current `Versions`
+        if data.len() != Self::LEN || data[4..8] != 1u32.to_le_bytes() {
+            return Err(ProgramError::UninitializedAccount);
+        }
+
+        Ok(Self {
+            account: *account,
+            authority: Pubkey::new_from_array(data[8..40].try_into().map_err(|_| ProgramError::InvalidAccountData)?),
+            blockhash: Hash::new_from_array(data[40..72].try_into().map_err(|_| ProgramError::InvalidAccountData)?)
+        })
+    }
+}
+
+/// Reads the nonce account at `nonce_pkey`. Fetch it again for each transaction: using the nonce advances it.
+pub async fn fetch_durable_nonce(rpc: &RpcClient, nonce_pkey: &Pubkey) -> Result<DurableNonce, Error> {
+    let account: Account = rpc.get_account_with_commitment(nonce_pkey, rpc.commitment()).await?.value
+        .ok_or(Error::AccountNotFound(*nonce_pkey))?;
+
+    if account.owner != system_program::ID {
+        return Err(ProgramError::IncorrectProgramId.into());
+    }
+
+    Ok(DurableNonce::unpack(nonce_pkey, &account.data)?)
+}
+
+/// Unsigned transaction of `instructions` against `nonce`: its blockhash is the nonce, and it starts with the
+/// `AdvanceNonceAccount` the runtime requires, so it stays valid until the nonce is used. Every signer (the nonce
+/// authority included) adds its signature with `Transaction::partial_sign()`, offline and in any order,
+/// before anyone submits it.
+pub fn nonce_transaction(instructions: &[Instruction], fee_payer_pkey: &Pubkey, nonce: &DurableNonce) -> Transaction {
+    let mut message: Message = Message::new_with_nonce(instructions.to_vec(), Some(fee_payer_pkey), &nonce.account, &nonce.authority);
+    message.recent_blockhash = nonce.blockhash;
+
+    Transaction::new_unsigned(message)
+}
+
+/// The `AdvanceNonceAccount` `nonce_transaction()` puts first, for transactions assembled by hand.
+pub fn advance_nonce_instruction(nonce: &DurableNonce) -> Instruction {
+    system_instruction::advance_nonce_account(&nonce.account, &nonce.authority)
+}
+
+/// How `send_with_retry()` lands a transaction.
+#[derive(Clone, Copy, Debug)]
+pub struct RetryPolicy {
+    /// sends of the transaction at most, re-signed ones included
+    pub max_attempts: u32,
+    /// wait for the transaction to land after the first send, doubled after every send up to `max_backoff`
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    /// level the transaction must reach, which its blockhash is fetched at too
+    pub commitment: CommitmentConfig,
+    /// re-signs the transaction against a fresh blockhash once its own expired. Off for transactions holding
+    /// signatures of others (or signed against a given `--blockhash`), which then fail with `NotConfirmed`.
+    pub resign_on_expiry: bool,
+    /// skips the preflight simulation of the first send (later ones always skip it)
+    pub skip_preflight: bool
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_backoff: Duration::from_secs(2),
+            max_backoff: Duration::from_secs(16),
+            commitment: CommitmentConfig::confirmed(),
+            resign_on_expiry: true,
+            skip_preflight: false
+        }
+    }
+}
+
+/// Signature statuses are polled this often while waiting for a transaction to land.
+const STATUS_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Sends `tx` until it reaches `policy.commitment`, signing it with `signers` first if it isn't. Each attempt sends
+/// it (the RPC node not retrying on its own) and waits for it with exponential backoff; once its blockhash expired,
+/// it can't land anymore and is re-signed with `signers` against a fresh one (see `RetryPolicy::resign_on_expiry`).
+/// A transaction failing its preflight simulation or landing with an error isn't retried.
+/// Not for durable nonce transactions (see `nonce_transaction()`), whose blockhash never reads as valid.
+pub async fn send_with_retry(rpc: &RpcClient, tx: &mut Transaction, signers: &[&Keypair], policy: &RetryPolicy) -> Result<Signature, Error> {
+    let mut backoff: Duration = policy.initial_backoff;
+
+    for attempt in 0..policy.max_attempts {
+        if !tx.is_signed() || !rpc.is_blockhash_valid(&tx.message.recent_blockhash, CommitmentConfig::processed()).await? {
+            // the previous signature may still have landed right before its blockhash expired
+            if tx.is_signed() {
+                if let Some(signature) = landed(rpc, &tx.signatures[0], policy).await? {
+                    return Ok(signature);
+                }
+            }
+
+            if tx.is_signed() && !policy.resign_on_expiry {
+                return Err(Error::NotConfirmed(tx.signatures[0]));
+            }
+
+            let (blockhash, _) = rpc.get_latest_blockhash_with_commitment(policy.commitment).await?;
+            tx.try_sign(signers, blockhash)?;
+        }
+
+        let config: RpcSendTransactionConfig = RpcSendTransactionConfig {
+            skip_preflight: policy.skip_preflight || attempt > 0,
+            preflight_commitment: Some(policy.commitment.commitment),
+            max_retries: Some(0),
+            ..RpcSendTransactionConfig::default()
+        };
+
+        if let Err(e) = rpc.send_transaction_with_config(tx, config).await {
+            // rejected by its simulation, sending it again wouldn't change that
+            if e.get_transaction_error().is_some() {
+                return Err(e.into());
+            }
+        }
+
+        let deadline: Instant = Instant::now() + backoff;
+        while Instant::now() < deadline {
+            if let Some(signature) = landed(rpc, &tx.signatures[0], policy).await? {
+                return Ok(signature);
+            }
+            tokio::time::sleep(STATUS_POLL_INTERVAL).await;
+        }
+
+        backoff = (backoff * 2).min(policy.max_backoff);
+    }
+
+    Err(Error::NotConfirmed(tx.signatures[0]))
+}
+
+/// `signature` once it reached `policy.commitment`, failing if it landed with an error.
+async fn landed(rpc: &RpcClient, signature: &Signature, policy: &RetryPolicy) -> Result<Option<Signature>, Error> {
+    match rpc.get_signature_status_with_commitment(signature, policy.commitment).await? {
+        Some(Ok(())) => Ok(Some(*signature)),
+        Some(Err(e)) => Err(Error::Transaction(e)),
+        None => Ok(None)
+    }
+}
+
+/// Escrow PDA between `payer_pkey` and `recipient_pkey`.
+pub fn escrow_address(payer_pkey: &Pubkey, recipient_pkey: &Pubkey) -> Pubkey {
+    let (seed1, seed2, seed3) = crate::get_escrow_seeds(payer_pkey, recipient_pkey);
+    Pubkey::find_program_address(&[seed1, seed2, seed3], &crate::ID).0
+}
+
+/// Fetches the account at `escrow_pda` and unpacks it, after checking this program owns it. `None` if there's no account.
+async fn fetch_escrow(rpc: &RpcClient, escrow_pda: &Pubkey) -> Result<Option<EscrowAccount>, Error> {
+    match rpc.get_account_with_commitment(escrow_pda, rpc.commitment()).await?.value {
+        Some(account) => decode_escrow(&account),
+        None => Ok(None)
+    }
+}
+
+/// Unpacks an escrow account, `None` once it's closed (zeroed & handed back to the system program).
+fn decode_escrow(account: &Account) -> Result<Option<EscrowAccount>, Error> {
+    if account.lamports == 0 {
+        return Ok(None);
+    }
+
+    if account.owner != crate::ID {
+        return Err(ProgramError::IncorrectProgramId.into());
+    }
+
+    Ok(Some(EscrowAccount::unpack(&account.data)?))
+}
+
+/// Follows the escrow between `payer_pkey` and `recipient_pkey` live: every change of the account, decoded, `None` once
+/// the escrow is settled (completed, closed or cancelled) and its account closed. Stops with the returned `UnsubscribeFn`.
+pub async fn subscribe_escrow<'a>(
+    pubsub: &'a PubsubClient,
+    payer_pkey: &Pubkey,
+    recipient_pkey: &Pubkey
+) -> Result<(BoxStream<'a, Result<Option<EscrowAccount>, Error>>, UnsubscribeFn), Error> {
+    let config: RpcAccountInfoConfig = RpcAccountInfoConfig {
+        encoding: Some(UiAccountEncoding::Base64),
+        ..RpcAccountInfoConfig::default()
+    };
+    let (updates, unsubscribe) = pubsub.account_subscribe(&escrow_address(payer_pkey, recipient_pkey), Some(config)).await?;
+
+    let escrow_updates = updates.map(|response| -> Result<Option<EscrowAccount>, Error> {
+        let account: Account = response.value.decode().ok_or(ProgramError::InvalidAccountData)?;
+        decode_escrow(&account)
+    });
+
+    Ok((escrow_updates.boxed(), unsubscribe))
+}
+
+/// Every escrow `payer_pkey` pays for, with its address.
+pub async fn list_escrows_by_payer(rpc: &RpcClient, payer_pkey: &Pubkey) -> Result<Vec<(Pubkey, EscrowAccount)>, Error> {
+    list_escrows(rpc, EscrowAccount::PAYER_OFFSET, payer_pkey).await
+}
+
+/// Every escrow `recipient_pkey` is the recipient of, with its address.
+pub async fn list_escrows_by_recipient(rpc: &RpcClient, recipient_pkey: &Pubkey) -> Result<Vec<(Pubkey, EscrowAccount)>, Error> {
+    list_escrows(rpc, EscrowAccount::RECIPIENT_OFFSET, recipient_pkey).await
+}
+
+/// Escrows (told apart from the program's other accounts by their size & discriminator) holding `pkey` at `offset`.
+async fn list_escrows(rpc: &RpcClient, offset: usize, pkey: &Pubkey) -> Result<Vec<(Pubkey, EscrowAccount)>, Error> {
+    let config: RpcProgramAccountsConfig = RpcProgramAccountsConfig {
+        filters: Some(vec![
+            RpcFilterType::DataSize(EscrowAccount::LEN as u64),
+            RpcFilterType::Memcmp(Memcmp::new_base58_encoded(0, &EscrowAccount::DISCRIMINATOR)),
+            RpcFilterType::Memcmp(Memcmp::new_base58_encoded(offset, pkey.as_ref()))
+        ]),
+        // escrows are too large for the default base58 encoding
+        account_config: RpcAccountInfoConfig {
+            encoding: Some(UiAccountEncoding::Base64),
+            ..RpcAccountInfoConfig::default()
+        },
+        ..RpcProgramAccountsConfig::default()
+    };
+
+    rpc.get_program_accounts_with_config(&crate::ID, config).await?
+        .into_iter()
+        .map(|(escrow_pda, account)| Ok((escrow_pda, EscrowAccount::unpack(&account.data)?)))
+        .collect()
+}
+
+
+impl EscrowAccount {
+    /// Fetches and decodes the escrow between `payer_pkey` and `recipient_pkey`.
+    pub async fn fetch(rpc: &RpcClient, payer_pkey: &Pubkey, recipient_pkey: &Pubkey) -> Result<Self, Error> {
+        let escrow_pda: Pubkey = escrow_address(payer_pkey, recipient_pkey);
+
+        fetch_escrow(rpc, &escrow_pda).await?.ok_or(Error::AccountNotFound(escrow_pda))
+    }
+}
+
+
+pub struct EscrowClient {
+    pub rpc: RpcClient,
+    /// payer of the escrows, signs every transaction
+    pub payer: Keypair,
+    /// prices & limits the compute units of every transaction when set, see `prioritize()`
+    pub priority_fee: Option<PriorityFeePolicy>,
+    /// lands legacy transactions with `send_with_retry()` when set, instead of `send_and_confirm_transaction()`
+    pub retry: Option<RetryPolicy>
+}
+
+impl EscrowClient {
+    pub fn new(rpc: RpcClient, payer: Keypair) -> Self {
+        Self { rpc, payer, priority_fee: None, retry: None }
+    }
+
+    pub fn with_priority_fee(mut self, policy: PriorityFeePolicy) -> Self {
+        self.priority_fee = Some(policy);
+        self
+    }
+
+    pub fn with_retry(mut self, policy: RetryPolicy) -> Self {
+        self.retry = Some(policy);
+        self
+    }
+
+    /// Client over the cluster of the solana CLI's config, its wallet being the payer.
+    pub fn from_cli_config(config: &CliConfig) -> Result<Self, Error> {
+        Ok(Self::new(config.rpc_client(), config.keypair()?))
+    }
+
+    /// Escrow PDA between `payer` and `recipient_pkey`.
+    pub fn escrow_address(&self, recipient_pkey: &Pubkey) -> Pubkey {
+        escrow_address(&self.payer.pubkey(), recipient_pkey)
+    }
+
+    /// Locks `amount` lamports for `recipient_pkey`, see `instruction::initialize`.
+    pub async fn initialize(&self, recipient_pkey: &Pubkey, amount: u64) -> Result<Signature, Error> {
+        self.send(&[EscrowInstruction::initialize(&self.payer.pubkey(), recipient_pkey, amount)]).await
+    }
+
+    /// Releases the escrow to `recipient_pkey`, see `instruction::complete`.
+    pub async fn complete(&self, recipient_pkey: &Pubkey) -> Result<Signature, Error> {
+        self.send(&[EscrowInstruction::complete(&self.payer.pubkey(), recipient_pkey)]).await
+    }
+
+    /// Refunds the escrow to the payer, see `instruction::close`.
+    pub async fn close(&self, recipient_pkey: &Pubkey) -> Result<Signature, Error> {
+        self.send(&[EscrowInstruction::close(&self.payer.pubkey(), recipient_pkey)]).await
+    }
+
+    /// `Complete` of the escrow to `recipient_pkey` against `nonce`, paid & signed by `payer` (see `nonce_transaction()`).
+    /// Still lacks the nonce authority's signature if that's someone else.
+    pub fn complete_with_nonce(&self, recipient_pkey: &Pubkey, nonce: &DurableNonce) -> Result<Transaction, Error> {
+        self.sign_with_nonce(&[EscrowInstruction::complete(&self.payer.pubkey(), recipient_pkey)], nonce)
+    }
+
+    /// `Close` of the escrow to `recipient_pkey` against `nonce`, as `complete_with_nonce()`.
+    pub fn close_with_nonce(&self, recipient_pkey: &Pubkey, nonce: &DurableNonce) -> Result<Transaction, Error> {
+        self.sign_with_nonce(&[EscrowInstruction::close(&self.payer.pubkey(), recipient_pkey)], nonce)
+    }
+
+    fn sign_with_nonce(&self, instructions: &[Instruction], nonce: &DurableNonce) -> Result<Transaction, Error> {
+        let mut tx: Transaction = nonce_transaction(instructions, &self.payer.pubkey(), nonce);
+        tx.try_partial_sign(&[&self.payer], nonce.blockhash)?;
+
+        Ok(tx)
+    }
+
+    /// State of the escrow at `escrow_pda`, `None` if it's closed (or was never created).
+    pub async fn fetch_escrow(&self, escrow_pda: &Pubkey) -> Result<Option<EscrowAccount>, Error> {
+        fetch_escrow(&self.rpc, escrow_pda).await
+    }
+
+    /// `instructions` preceded by the compute budget instructions, with a `priority_fee` policy: the price it derives from
+    /// the recent fees of the write-locked accounts, and a limit just above the units a simulation consumed
+    /// (none if the node doesn't report them). `instructions` as they are without a policy.
+    /// `lookup_tables` are the ones the transaction will be compiled against, empty for legacy transactions.
+    pub async fn prioritize(&self, instructions: &[Instruction], lookup_tables: &[AddressLookupTableAccount]) -> Result<Vec<Instruction>, Error> {
+        let Some(policy) = self.priority_fee else {
+            return Ok(instructions.to_vec());
+        };
+
+        let recent_fees: Vec<u64> = self.rpc.get_recent_prioritization_fees(&priority_fee::fee_accounts(instructions)).await?
+            .iter()
+            .map(|fee| fee.prioritization_fee)
+            .collect();
+        let micro_lamports: u64 = policy.compute_unit_price(&recent_fees);
+
+        let simulated: Vec<Instruction> = priority_fee::with_priority_fee(instructions, Some(priority_fee::MAX_COMPUTE_UNIT_LIMIT), micro_lamports);
+        let compute_unit_limit: Option<u32> = self.simulate_compute_units(&simulated, lookup_tables).await?.map(priority_fee::compute_unit_limit);
+
+        Ok(priority_fee::with_priority_fee(instructions, compute_unit_limit, micro_lamports))
+    }
+
+    /// Compute units a v0 transaction of `instructions` consumes, `None` if the node doesn't report them.
+    /// Simulated unsigned (signatures aren't verified), so it doesn't matter who else has to sign.
+    pub async fn simulate_compute_units(
+        &self,
+        instructions: &[Instruction],
+        lookup_tables: &[AddressLookupTableAccount]
+    ) -> Result<Option<u64>, Error> {
+        let blockhash: Hash = self.rpc.get_latest_blockhash().await?;
+        let message: v0::Message = v0::Message::try_compile(&self.payer.pubkey(), instructions, lookup_tables, blockhash)?;
+        let tx: VersionedTransaction = VersionedTransaction {
+            signatures: vec![Signature::default(); message.header.num_required_signatures as usize],
+            message: VersionedMessage::V0(message)
+        };
+        let simulation = self.rpc.simulate_transaction(&tx).await?.value;
+
+        if let Some(e) = simulation.err {
+            return Err(Error::Simulation(e));
+        }
+
+        Ok(simulation.units_consumed)
+    }
+
+    /// Creates an address lookup table with `payer` as its authority, holding `addresses` (see `lookup_table_addresses()`).
+    /// v0 transactions can use it from the slot after its last extension on.
+    pub async fn create_lookup_table(&self, addresses: &[Pubkey]) -> Result<Pubkey, Error> {
+        let recent_slot: u64 = self.rpc.get_slot_with_commitment(CommitmentConfig::finalized()).await?;
+        let (create_ix, table_pkey) = address_lookup_table::instruction::create_lookup_table(self.payer.pubkey(), self.payer.pubkey(), recent_slot);
+
+        self.send(&[create_ix]).await?;
+        self.extend_lookup_table(&table_pkey, addresses).await?;
+
+        Ok(table_pkey)
+    }
+
+    /// Appends `addresses` to the lookup table at `table_pkey` (whose authority is `payer`), `LOOKUP_TABLE_EXTEND_CHUNK`
+    /// per transaction.
+    pub async fn extend_lookup_table(&self, table_pkey: &Pubkey, addresses: &[Pubkey]) -> Result<(), Error> {
+        for chunk in addresses.chunks(LOOKUP_TABLE_EXTEND_CHUNK) {
+            let extend_ix: Instruction = address_lookup_table::instruction::extend_lookup_table(
+                *table_pkey,
+                self.payer.pubkey(),
+                Some(self.payer.pubkey()),
+                chunk.to_vec()
+            );
+            self.send(&[extend_ix]).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Sends `instructions` (prioritized as `send()` does) in a v0 transaction signed by `payer` & `signers`, looking its
+    /// accounts up in `lookup_tables` (see `fetch_lookup_table()`).
+    pub async fn send_versioned(
+        &self,
+        instructions: &[Instruction],
+        lookup_tables: &[AddressLookupTableAccount],
+        signers: &[&Keypair]
+    ) -> Result<Signature, Error> {
+        let instructions: Vec<Instruction> = self.prioritize(instructions, lookup_tables).await?;
+        let blockhash: Hash = self.rpc.get_latest_blockhash().await?;
+
+        let message: v0::Message = v0::Message::try_compile(&self.payer.pubkey(), &instructions, lookup_tables, blockhash)?;
+        let mut all_signers: Vec<&Keypair> = vec![&self.payer];
+        all_signers.extend_from_slice(signers);
+        let tx: VersionedTransaction = VersionedTransaction::try_new(VersionedMessage::V0(message), &all_signers)?;
+
+        Ok(self.rpc.send_and_confirm_transaction(&tx).await?)
+    }
+
+    async fn send(&self, instructions: &[Instruction]) -> Result<Signature, Error> {
+        let instructions: Vec<Instruction> = self.prioritize(instructions, &[]).await?;
+        let blockhash: Hash = self.rpc.get_latest_blockhash().await?;
+        let mut tx: Transaction = Transaction::new_signed_with_payer(&instructions, Some(&self.payer.pubkey()), &[&self.payer], blockhash);
+
+        match &self.retry {
+            Some(policy) => send_with_retry(&self.rpc, &mut tx, &[&self.payer], policy).await,
+            None => Ok(self.rpc.send_and_confirm_transaction(&tx).await?)
+        }
+    }
+}
@@ -0,0 +1,111 @@
+use std::str::FromStr;
+use solana_program::{
+    pubkey,
+    pubkey::Pubkey
+};
+
+
+// The program is deployed from the same program keypair on every public cluster, so these currently coincide.
+pub const MAINNET_PROGRAM_ID: Pubkey = pubkey!("E6v3tbZyZAthzd5JCPJgd3TmLXL3VirKxib9XHjyKTjL");
+pub const DEVNET_PROGRAM_ID: Pubkey = pubkey!("E6v3tbZyZAthzd5JCPJgd3TmLXL3VirKxib9XHjyKTjL");
+pub const TESTNET_PROGRAM_ID: Pubkey = pubkey!("E6v3tbZyZAthzd5JCPJgd3TmLXL3VirKxib9XHjyKTjL");
+
+/// Local validators often run a freshly generated program keypair,
+/// so this one can be overridden at build time with `ESCROW_LOCALNET_PROGRAM_ID=<base58>`.
+pub const LOCALNET_PROGRAM_ID: Pubkey = match option_env!("ESCROW_LOCALNET_PROGRAM_ID") {
+    Some(program_id) => Pubkey::from_str_const(program_id),
+    None => pubkey!("E6v3tbZyZAthzd5JCPJgd3TmLXL3VirKxib9XHjyKTjL")
+};
+
+#[cfg(any(
+    all(feature = "mainnet", feature = "devnet"),
+    all(feature = "mainnet", feature = "testnet"),
+    all(feature = "devnet", feature = "testnet")
+))]
+compile_error!("at most one of the `mainnet`, `devnet` and `testnet` features can be enabled");
+
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Cluster {
+    Mainnet,
+    Devnet,
+    Testnet,
+    Localnet
+}
+
+impl Cluster {
+    /// Cluster selected by cargo features at build time, `Localnet` if none is enabled.
+    /// This is what the on-chain `crate::ID` is derived from.
+    pub const fn current() -> Self {
+        if cfg!(feature = "mainnet") {
+            Self::Mainnet
+        } else if cfg!(feature = "devnet") {
+            Self::Devnet
+        } else if cfg!(feature = "testnet") {
+            Self::Testnet
+        } else {
+            Self::Localnet
+        }
+    }
+
+    pub const fn program_id(&self) -> Pubkey {
+        match self {
+            Self::Mainnet => MAINNET_PROGRAM_ID,
+            Self::Devnet => DEVNET_PROGRAM_ID,
+            Self::Testnet => TESTNET_PROGRAM_ID,
+            Self::Localnet => LOCALNET_PROGRAM_ID
+        }
+    }
+
+    pub fn rpc_url(&self) -> &'static str {
+        match self {
+            Self::Mainnet => "https://api.mainnet-beta.solana.com",
+            Self::Devnet => "https://api.devnet.solana.com",
+            Self::Testnet => "https://api.testnet.solana.com",
+            Self::Localnet => "http://127.0.0.1:8899"
+        }
+    }
+
+    /// PubSub (websocket) endpoint matching `rpc_url()`.
+    pub fn ws_url(&self) -> &'static str {
+        match self {
+            Self::Mainnet => "wss://api.mainnet-beta.solana.com",
+            Self::Devnet => "wss://api.devnet.solana.com",
+            Self::Testnet => "wss://api.testnet.solana.com",
+            Self::Localnet => "ws://127.0.0.1:8900"
+        }
+    }
+
+    /// Solana Explorer link of a transaction on this cluster (localnet links point the explorer at the local RPC).
+    pub fn explorer_tx_url(&self, signature: &str) -> String {
+        let base: String = format!("https://explorer.solana.com/tx/{}", signature);
+
+        match self {
+            Self::Mainnet => base,
+            Self::Devnet => format!("{}?cluster=devnet", base),
+            Self::Testnet => format!("{}?cluster=testnet", base),
+            Self::Localnet => format!("{}?cluster=custom&customUrl=http%3A%2F%2F127.0.0.1%3A8899", base)
+        }
+    }
+}
+
+impl FromStr for Cluster {
+    type Err = String;
+
+    /// Accepts the names used by the solana CLI (`mainnet-beta`, `devnet`, `testnet`, `localhost`) plus short forms.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "mainnet" | "mainnet-beta" | "m" => Self::Mainnet,
+            "devnet" | "d" => Self::Devnet,
+            "testnet" | "t" => Self::Testnet,
+            "localnet" | "localhost" | "l" => Self::Localnet,
+            _ => return Err(format!("unknown cluster: {}", s))
+        })
+    }
+}
+
+/// Program id of the escrow deployment on `cluster`, independent of the features this crate was built with,
+/// so one client build can target any cluster.
+pub fn id_for_cluster(cluster: Cluster) -> Pubkey {
+    cluster.program_id()
+}
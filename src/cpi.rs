@@ -0,0 +1,119 @@
+use solana_program::{
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    instruction::{AccountMeta, Instruction},
+    program::invoke_signed,
+    program_error::ProgramError
+};
+use crate::accounts::{self, CompleteAccounts, CloseAccounts};
+
+
+/// Accounts of an `Initialize` made by another program, e.g. opening an escrow on behalf of its user.
+/// `payer` may be a PDA of the calling program (signing through `signers_seeds`), as long as it's owned by the system program.
+pub struct InitializeAccounts<'a, 'b> {
+    pub escrow_program: &'b AccountInfo<'a>,
+    pub payer: &'b AccountInfo<'a>,
+    pub recipient: &'b AccountInfo<'a>,
+    pub escrow: &'b AccountInfo<'a>,
+    pub system_program: &'b AccountInfo<'a>,
+    /// `EscrowCounter` PDA (seeds: `"counter"`)
+    pub counter: &'b AccountInfo<'a>,
+    /// `Config` PDA (seeds: `"config"`, or `"config"` & the namespace)
+    pub config: &'b AccountInfo<'a>
+}
+
+/// Accounts of a SOL `Complete` or `Close` made by another program.
+pub struct SettleAccounts<'a, 'b> {
+    pub escrow_program: &'b AccountInfo<'a>,
+    pub payer: &'b AccountInfo<'a>,
+    pub recipient: &'b AccountInfo<'a>,
+    pub escrow: &'b AccountInfo<'a>,
+    pub config: &'b AccountInfo<'a>
+}
+
+
+/// Opens a SOL escrow of `amount` lamports without a note, see `instruction::initialize`.
+pub fn initialize(accounts: InitializeAccounts, amount: u64, signers_seeds: &[&[&[u8]]]) -> ProgramResult {
+    check_escrow_program(accounts.escrow_program)?;
+
+    let mut data: Vec<u8> = Vec::with_capacity(9);
+    data.push(0);
+    data.extend_from_slice(&amount.to_le_bytes());
+
+    let ix: Instruction = Instruction::new_with_bytes(
+        *accounts.escrow_program.key,
+        &data,
+        accounts::InitializeAccounts {
+            payer: *accounts.payer.key,
+            recipient: *accounts.recipient.key,
+            escrow: *accounts.escrow.key,
+            system_program: *accounts.system_program.key,
+            counter: *accounts.counter.key,
+            config: *accounts.config.key
+        }.to_account_metas()
+    );
+
+    invoke_signed(
+        &ix,
+        &[
+            accounts.payer.clone(),
+            accounts.recipient.clone(),
+            accounts.escrow.clone(),
+            accounts.system_program.clone(),
+            accounts.counter.clone(),
+            accounts.config.clone(),
+            accounts.escrow_program.clone()
+        ],
+        signers_seeds
+    )
+}
+
+/// Releases a SOL escrow to its recipient, see `instruction::complete`.
+pub fn complete(accounts: SettleAccounts, signers_seeds: &[&[&[u8]]]) -> ProgramResult {
+    let account_metas: Vec<AccountMeta> = CompleteAccounts {
+        payer: *accounts.payer.key,
+        recipient: *accounts.recipient.key,
+        escrow: *accounts.escrow.key,
+        config: *accounts.config.key
+    }.to_account_metas();
+
+    settle(accounts, 1, account_metas, signers_seeds)
+}
+
+/// Refunds a SOL escrow to its payer, see `instruction::close`.
+pub fn close(accounts: SettleAccounts, signers_seeds: &[&[&[u8]]]) -> ProgramResult {
+    let account_metas: Vec<AccountMeta> = CloseAccounts {
+        payer: *accounts.payer.key,
+        recipient: *accounts.recipient.key,
+        escrow: *accounts.escrow.key,
+        config: *accounts.config.key
+    }.to_account_metas();
+
+    settle(accounts, 2, account_metas, signers_seeds)
+}
+
+fn settle(accounts: SettleAccounts, tag: u8, account_metas: Vec<AccountMeta>, signers_seeds: &[&[&[u8]]]) -> ProgramResult {
+    check_escrow_program(accounts.escrow_program)?;
+
+    let ix: Instruction = Instruction::new_with_bytes(*accounts.escrow_program.key, &[tag], account_metas);
+
+    invoke_signed(
+        &ix,
+        &[
+            accounts.payer.clone(),
+            accounts.recipient.clone(),
+            accounts.escrow.clone(),
+            accounts.config.clone(),
+            accounts.escrow_program.clone()
+        ],
+        signers_seeds
+    )
+}
+
+fn check_escrow_program(escrow_program: &AccountInfo) -> Result<(), ProgramError> {
+    if escrow_program.key != &crate::ID {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    Ok(())
+}
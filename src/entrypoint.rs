@@ -2,12 +2,14 @@
 use solana_program::{
     pubkey::Pubkey,
     account_info::AccountInfo,
-    entrypoint::{entrypoint, ProgramResult}
+    entrypoint::ProgramResult
 };
 use super::processor::Processor;
 
 
-entrypoint!(process_instruction);
+// depending programs bring their own entrypoint (`no-entrypoint`), `process_instruction` stays available to them & to tests
+#[cfg(not(feature = "no-entrypoint"))]
+solana_program::entrypoint!(process_instruction);
 
 pub fn process_instruction(
     program_id: &Pubkey,
@@ -0,0 +1,90 @@
+use solana_program::program_error::ProgramError;
+
+
+/// Program specific failures, surfaced to clients as `ProgramError::Custom(<discriminant>)`.
+/// Discriminants are part of the program's interface: new variants are appended, never renumbered.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EscrowError {
+    /// `Complete` before the escrow's `unlock_at`
+    StillLocked = 0,
+    /// the escrowed stablecoin is off its peg beyond the escrow's `max_depeg_bps`, see `SetDepegGuard`
+    Depegged = 1,
+    /// the instruction would move the escrow to a status it can't reach from its current one, see `EscrowStatus`
+    InvalidTransition = 2,
+    /// missing preimage, or one not hashing to the hashlock of a hash time-locked escrow
+    InvalidPreimage = 3,
+    /// a PDA (escrow, config, vault, ...) isn't the one derived from the instruction's other accounts
+    PdaMismatch = 4,
+    /// the PDA the instruction would create already exists
+    AlreadyInitialized = 5,
+    /// the amount is zero, doesn't cover the escrow's rent, or is below `Config::min_amount`
+    AmountTooSmall = 6,
+    /// the amount exceeds `Config::max_amount`
+    AmountTooLarge = 7,
+    /// `Close` before the escrow's deadline
+    NotExpired = 8,
+    /// settling after the escrow's deadline (without a late fee)
+    Expired = 9,
+    /// the instruction is paused in the `Config`, see `SetPaused`
+    Paused = 10,
+    /// the signer isn't the `Config`'s admin
+    NotAdmin = 11,
+    /// the transaction calls a program outside `ISOLATED_SETTLEMENT_PROGRAMS`, see `RequireIsolatedSettlement`
+    ForeignInstruction = 12,
+    /// the escrow was found inconsistent and quarantined, only the admin can move it (see `Quarantine`)
+    Quarantined = 13,
+    /// the instruction's family isn't enabled in the `Config` yet, see `SetFeatures`
+    FeatureDisabled = 14,
+    /// the yield venue can't change while swept fees are still deposited in it, see `RedeemTreasury`
+    PrincipalOutstanding = 15,
+    /// the instruction data ends before the instruction's last field, or is empty
+    InstructionDataTooShort = 16,
+    /// bytes are left in the instruction data after the instruction's last field
+    InstructionDataTooLong = 17,
+    /// the instruction data's tag (or discriminator) isn't one of the program's instructions
+    UnknownInstruction = 18
+}
+
+impl From<EscrowError> for ProgramError {
+    fn from(e: EscrowError) -> Self {
+        ProgramError::Custom(e as u32)
+    }
+}
+
+impl TryFrom<u32> for EscrowError {
+    type Error = ProgramError;
+
+    /// Decodes the code of a `ProgramError::Custom` returned by the program.
+    fn try_from(code: u32) -> Result<Self, Self::Error> {
+        Ok(match code {
+            0 => Self::StillLocked,
+            1 => Self::Depegged,
+            2 => Self::InvalidTransition,
+            3 => Self::InvalidPreimage,
+            4 => Self::PdaMismatch,
+            5 => Self::AlreadyInitialized,
+            6 => Self::AmountTooSmall,
+            7 => Self::AmountTooLarge,
+            8 => Self::NotExpired,
+            9 => Self::Expired,
+            10 => Self::Paused,
+            11 => Self::NotAdmin,
+            12 => Self::ForeignInstruction,
+            13 => Self::Quarantined,
+            14 => Self::FeatureDisabled,
+            15 => Self::PrincipalOutstanding,
+            16 => Self::InstructionDataTooShort,
+            17 => Self::InstructionDataTooLong,
+            18 => Self::UnknownInstruction,
+            _ => return Err(ProgramError::Custom(code))
+        })
+    }
+}
+
+// deprecated upstream in favor of `num_traits::FromPrimitive`, still what explorers & older clients decode errors with
+#[allow(deprecated)]
+impl<T> solana_program::decode_error::DecodeError<T> for EscrowError {
+    fn type_of() -> &'static str {
+        "EscrowError"
+    }
+}
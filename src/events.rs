@@ -0,0 +1,461 @@
+use solana_program::{
+    log::sol_log_data,
+    pubkey::Pubkey,
+    program_error::ProgramError
+};
+use super::state::{EscrowStatus, Inconsistency};
+
+
+/// Events are emitted with `sol_log_data`, so they show up as `Program data: <base64>` in the transaction logs.
+/// Layout of escrow events: tag (1) + id (8) + escrow (32) + callback (32) + seq (8) + variant specific fields.
+/// Admin events: tag (1) + variant specific fields.
+///
+/// `callback` is the integrator's webhook digest registered on the escrow (zeroed if none),
+/// so notifications can be routed purely from on-chain data.
+/// `seq` numbers an escrow's events 1, 2, 3... in emission order (see `EscrowAccount::event_seq`),
+/// so indexers can order them and tell a gap in the sequence from a quiet escrow.
+#[derive(Debug)]
+pub enum EscrowEvent {
+    Initialized { id: u64, escrow: Pubkey, callback: [u8; 32], seq: u64, amount: u64 },
+    Completed { id: u64, escrow: Pubkey, callback: [u8; 32], seq: u64, amount: u64 },
+    Closed { id: u64, escrow: Pubkey, callback: [u8; 32], seq: u64 },
+    CallbackRegistered { id: u64, escrow: Pubkey, callback: [u8; 32], seq: u64 },
+    MintBlocked { mint: Pubkey },
+    MintUnblocked { mint: Pubkey },
+    PauseUpdated { paused: u8 },
+    ProtocolFeeUpdated { fee_bps: u16 },
+    FeaturesUpdated { features: u8 },
+    /// `amount` lamports of the fee vault were swept into the yield venue, `principal` being outstanding in total
+    TreasurySwept { amount: u64, principal: u64 },
+    /// `lamports` were redeemed from the yield venue back into the fee vault: `principal` of the swept principal,
+    /// `yield_amount` on top of it, and `written_off` principal the venue lost
+    TreasuryRedeemed { lamports: u64, principal: u64, yield_amount: u64, written_off: u64 },
+    /// `from` is the previous holder of the claim (the recipient or an earlier assignee)
+    ClaimAssigned { id: u64, escrow: Pubkey, callback: [u8; 32], seq: u64, from: Pubkey, assignee: Pubkey },
+    ClaimShareAssigned { id: u64, escrow: Pubkey, callback: [u8; 32], seq: u64, assignee: Pubkey, bps: u16 },
+    DisputeRaised { id: u64, escrow: Pubkey, callback: [u8; 32], seq: u64, by: Pubkey },
+    DisputeResolved { id: u64, escrow: Pubkey, callback: [u8; 32], seq: u64, payer_amount: u64, recipient_amount: u64 },
+    /// `Reconcile` found the vault holding `actual` tokens instead of the recorded `expected`, and adopted `actual`
+    VaultDiscrepancy { id: u64, escrow: Pubkey, callback: [u8; 32], seq: u64, expected: u64, actual: u64 },
+    /// the payer topped up the escrow with `amount` lamports
+    Deposited { id: u64, escrow: Pubkey, callback: [u8; 32], seq: u64, amount: u64 },
+    /// the recipient withdrew `amount` lamports of a vesting escrow, `withdrawn` in total so far
+    VestedWithdrawn { id: u64, escrow: Pubkey, callback: [u8; 32], seq: u64, amount: u64, withdrawn: u64 },
+    /// `amount` lamports of an escrow capped by `SetMaxPayout` were released, `withdrawn` in total so far
+    Drawn { id: u64, escrow: Pubkey, callback: [u8; 32], seq: u64, amount: u64, withdrawn: u64 },
+    /// a `fee` lamports protocol fee was charged, `by_payer` on top of the escrowed amount, otherwise off the payout
+    FeeCharged { id: u64, escrow: Pubkey, callback: [u8; 32], seq: u64, fee: u64, by_payer: bool },
+    /// what a `Complete` moved where, computed on-chain, emitted right before `Completed`
+    Settled { id: u64, escrow: Pubkey, callback: [u8; 32], seq: u64, breakdown: SettlementBreakdown },
+    /// `Touch` heartbeat, the escrow's current state. Nothing changed, so `seq` is the one of the escrow's last event:
+    /// an indexer that hasn't seen it yet has missed events
+    Touched {
+        id: u64,
+        escrow: Pubkey,
+        callback: [u8; 32],
+        seq: u64,
+        status: EscrowStatus,
+        amount: u64,
+        token_amount: u64,
+        withdrawn: u64,
+        deadline: i64
+    },
+    /// `Quarantine` took the escrow out of the normal flows, for the admin to recover it
+    Quarantined { id: u64, escrow: Pubkey, callback: [u8; 32], seq: u64, reason: Inconsistency },
+    /// `beneficiary` withdrew `amount` lamports of a team vesting escrow, `withdrawn` of its allocation in total so far
+    VestedShareWithdrawn { id: u64, escrow: Pubkey, callback: [u8; 32], seq: u64, beneficiary: Pubkey, amount: u64, withdrawn: u64 },
+    /// `by` (the recipient, or the assignee of the claim) acknowledged receiving the payout of the completed escrow
+    /// (`AcknowledgeReceipt`). The escrow is closed by then and its events over, so `seq` is 0
+    ReceiptAcknowledged { id: u64, escrow: Pubkey, callback: [u8; 32], seq: u64, by: Pubkey, acknowledged_at: i64 },
+    /// `BatchClose` left the pair of `escrow` open, failing the `Close` checks with `error` (a `ProgramError` as `u64`).
+    /// The pair may not be an escrow at all, so this is an admin-style event
+    CloseSkipped { escrow: Pubkey, error: u64 }
+}
+
+impl EscrowEvent {
+    const HEADER_LEN: usize = 81;
+
+    pub fn emit(&self) {
+        sol_log_data(&[&self.pack()]);
+    }
+
+    pub fn pack(&self) -> Vec<u8> {
+        let mut data: Vec<u8> = Vec::with_capacity(Self::HEADER_LEN + 64);
+
+        match self {
+            Self::Initialized { id, escrow, callback, seq, amount } => {
+                Self::pack_header(&mut data, 0, *id, escrow, callback, *seq);
+                data.extend_from_slice(&amount.to_le_bytes());
+            },
+            Self::Completed { id, escrow, callback, seq, amount } => {
+                Self::pack_header(&mut data, 1, *id, escrow, callback, *seq);
+                data.extend_from_slice(&amount.to_le_bytes());
+            },
+            Self::Closed { id, escrow, callback, seq } => Self::pack_header(&mut data, 2, *id, escrow, callback, *seq),
+            Self::CallbackRegistered { id, escrow, callback, seq } => Self::pack_header(&mut data, 3, *id, escrow, callback, *seq),
+            Self::MintBlocked { mint } => {
+                data.push(4);
+                data.extend_from_slice(mint.as_ref());
+            },
+            Self::MintUnblocked { mint } => {
+                data.push(5);
+                data.extend_from_slice(mint.as_ref());
+            },
+            Self::PauseUpdated { paused } => {
+                data.push(6);
+                data.push(*paused);
+            },
+            Self::ProtocolFeeUpdated { fee_bps } => {
+                data.push(14);
+                data.extend_from_slice(&fee_bps.to_le_bytes());
+            },
+            Self::FeaturesUpdated { features } => {
+                data.push(21);
+                data.push(*features);
+            },
+            Self::TreasurySwept { amount, principal } => {
+                data.push(22);
+                data.extend_from_slice(&amount.to_le_bytes());
+                data.extend_from_slice(&principal.to_le_bytes());
+            },
+            Self::TreasuryRedeemed { lamports, principal, yield_amount, written_off } => {
+                data.push(23);
+                data.extend_from_slice(&lamports.to_le_bytes());
+                data.extend_from_slice(&principal.to_le_bytes());
+                data.extend_from_slice(&yield_amount.to_le_bytes());
+                data.extend_from_slice(&written_off.to_le_bytes());
+            },
+            Self::ClaimAssigned { id, escrow, callback, seq, from, assignee } => {
+                Self::pack_header(&mut data, 7, *id, escrow, callback, *seq);
+                data.extend_from_slice(from.as_ref());
+                data.extend_from_slice(assignee.as_ref());
+            },
+            Self::ClaimShareAssigned { id, escrow, callback, seq, assignee, bps } => {
+                Self::pack_header(&mut data, 8, *id, escrow, callback, *seq);
+                data.extend_from_slice(assignee.as_ref());
+                data.extend_from_slice(&bps.to_le_bytes());
+            },
+            Self::DisputeRaised { id, escrow, callback, seq, by } => {
+                Self::pack_header(&mut data, 9, *id, escrow, callback, *seq);
+                data.extend_from_slice(by.as_ref());
+            },
+            Self::DisputeResolved { id, escrow, callback, seq, payer_amount, recipient_amount } => {
+                Self::pack_header(&mut data, 10, *id, escrow, callback, *seq);
+                data.extend_from_slice(&payer_amount.to_le_bytes());
+                data.extend_from_slice(&recipient_amount.to_le_bytes());
+            },
+            Self::VaultDiscrepancy { id, escrow, callback, seq, expected, actual } => {
+                Self::pack_header(&mut data, 11, *id, escrow, callback, *seq);
+                data.extend_from_slice(&expected.to_le_bytes());
+                data.extend_from_slice(&actual.to_le_bytes());
+            },
+            Self::Deposited { id, escrow, callback, seq, amount } => {
+                Self::pack_header(&mut data, 12, *id, escrow, callback, *seq);
+                data.extend_from_slice(&amount.to_le_bytes());
+            },
+            Self::VestedWithdrawn { id, escrow, callback, seq, amount, withdrawn } => {
+                Self::pack_header(&mut data, 13, *id, escrow, callback, *seq);
+                data.extend_from_slice(&amount.to_le_bytes());
+                data.extend_from_slice(&withdrawn.to_le_bytes());
+            },
+            Self::Drawn { id, escrow, callback, seq, amount, withdrawn } => {
+                Self::pack_header(&mut data, 15, *id, escrow, callback, *seq);
+                data.extend_from_slice(&amount.to_le_bytes());
+                data.extend_from_slice(&withdrawn.to_le_bytes());
+            },
+            Self::FeeCharged { id, escrow, callback, seq, fee, by_payer } => {
+                Self::pack_header(&mut data, 16, *id, escrow, callback, *seq);
+                data.extend_from_slice(&fee.to_le_bytes());
+                data.push(*by_payer as u8);
+            },
+            Self::Touched { id, escrow, callback, seq, status, amount, token_amount, withdrawn, deadline } => {
+                Self::pack_header(&mut data, 17, *id, escrow, callback, *seq);
+                data.push(*status as u8);
+                data.extend_from_slice(&amount.to_le_bytes());
+                data.extend_from_slice(&token_amount.to_le_bytes());
+                data.extend_from_slice(&withdrawn.to_le_bytes());
+                data.extend_from_slice(&deadline.to_le_bytes());
+            },
+            Self::Settled { id, escrow, callback, seq, breakdown } => {
+                Self::pack_header(&mut data, 18, *id, escrow, callback, *seq);
+                data.extend_from_slice(&breakdown.principal.to_le_bytes());
+                data.extend_from_slice(&breakdown.protocol_fee.to_le_bytes());
+                data.extend_from_slice(&breakdown.late_fee.to_le_bytes());
+                data.extend_from_slice(&breakdown.payout.to_le_bytes());
+                data.extend_from_slice(&breakdown.shares.to_le_bytes());
+                data.extend_from_slice(&breakdown.arbiter.to_le_bytes());
+                data.extend_from_slice(&breakdown.rent_refund.to_le_bytes());
+            },
+            Self::Quarantined { id, escrow, callback, seq, reason } => {
+                Self::pack_header(&mut data, 19, *id, escrow, callback, *seq);
+                data.push(*reason as u8);
+            },
+            Self::VestedShareWithdrawn { id, escrow, callback, seq, beneficiary, amount, withdrawn } => {
+                Self::pack_header(&mut data, 20, *id, escrow, callback, *seq);
+                data.extend_from_slice(beneficiary.as_ref());
+                data.extend_from_slice(&amount.to_le_bytes());
+                data.extend_from_slice(&withdrawn.to_le_bytes());
+            },
+            Self::ReceiptAcknowledged { id, escrow, callback, seq, by, acknowledged_at } => {
+                Self::pack_header(&mut data, 24, *id, escrow, callback, *seq);
+                data.extend_from_slice(by.as_ref());
+                data.extend_from_slice(&acknowledged_at.to_le_bytes());
+            },
+            Self::CloseSkipped { escrow, error } => {
+                data.push(25);
+                data.extend_from_slice(escrow.as_ref());
+                data.extend_from_slice(&error.to_le_bytes());
+            }
+        };
+
+        data
+    }
+
+    /// Decodes an event from the (base64-decoded) `Program data:` log line, for indexers and clients.
+    pub fn unpack(data: &[u8]) -> Result<Self, ProgramError> {
+        let (tag, rest) = data.split_first().ok_or(ProgramError::InvalidAccountData)?;
+
+        match tag {
+            4 => return Ok(Self::MintBlocked { mint: Pubkey::new_from_array(Self::read_array(rest, 0)?) }),
+            5 => return Ok(Self::MintUnblocked { mint: Pubkey::new_from_array(Self::read_array(rest, 0)?) }),
+            6 => return Ok(Self::PauseUpdated { paused: *rest.first().ok_or(ProgramError::InvalidAccountData)? }),
+            14 => return Ok(Self::ProtocolFeeUpdated {
+                fee_bps: rest.get(..2)
+                    .and_then(|slice| slice.try_into().ok())
+                    .map(u16::from_le_bytes)
+                    .ok_or(ProgramError::InvalidAccountData)?
+            }),
+            21 => return Ok(Self::FeaturesUpdated { features: *rest.first().ok_or(ProgramError::InvalidAccountData)? }),
+            22 => return Ok(Self::TreasurySwept { amount: Self::read_u64(rest, 0)?, principal: Self::read_u64(rest, 8)? }),
+            23 => return Ok(Self::TreasuryRedeemed {
+                lamports: Self::read_u64(rest, 0)?,
+                principal: Self::read_u64(rest, 8)?,
+                yield_amount: Self::read_u64(rest, 16)?,
+                written_off: Self::read_u64(rest, 24)?
+            }),
+            25 => return Ok(Self::CloseSkipped { escrow: Pubkey::new_from_array(Self::read_array(rest, 0)?), error: Self::read_u64(rest, 32)? }),
+            _ => {}
+        };
+
+        let id: u64 = Self::read_u64(rest, 0)?;
+        let escrow: Pubkey = Pubkey::new_from_array(Self::read_array(rest, 8)?);
+        let callback: [u8; 32] = Self::read_array(rest, 40)?;
+        let seq: u64 = Self::read_u64(rest, 72)?;
+
+        Ok(match tag {
+            0 => Self::Initialized { id, escrow, callback, seq, amount: Self::read_u64(rest, 80)? },
+            1 => Self::Completed { id, escrow, callback, seq, amount: Self::read_u64(rest, 80)? },
+            2 => Self::Closed { id, escrow, callback, seq },
+            3 => Self::CallbackRegistered { id, escrow, callback, seq },
+            7 => Self::ClaimAssigned {
+                id,
+                escrow,
+                callback,
+                seq,
+                from: Pubkey::new_from_array(Self::read_array(rest, 80)?),
+                assignee: Pubkey::new_from_array(Self::read_array(rest, 112)?)
+            },
+            8 => Self::ClaimShareAssigned {
+                id,
+                escrow,
+                callback,
+                seq,
+                assignee: Pubkey::new_from_array(Self::read_array(rest, 80)?),
+                bps: rest.get(112..114)
+                    .and_then(|slice| slice.try_into().ok())
+                    .map(u16::from_le_bytes)
+                    .ok_or(ProgramError::InvalidAccountData)?
+            },
+            9 => Self::DisputeRaised { id, escrow, callback, seq, by: Pubkey::new_from_array(Self::read_array(rest, 80)?) },
+            10 => Self::DisputeResolved {
+                id,
+                escrow,
+                callback,
+                seq,
+                payer_amount: Self::read_u64(rest, 80)?,
+                recipient_amount: Self::read_u64(rest, 88)?
+            },
+            11 => Self::VaultDiscrepancy {
+                id,
+                escrow,
+                callback,
+                seq,
+                expected: Self::read_u64(rest, 80)?,
+                actual: Self::read_u64(rest, 88)?
+            },
+            12 => Self::Deposited { id, escrow, callback, seq, amount: Self::read_u64(rest, 80)? },
+            13 => Self::VestedWithdrawn {
+                id,
+                escrow,
+                callback,
+                seq,
+                amount: Self::read_u64(rest, 80)?,
+                withdrawn: Self::read_u64(rest, 88)?
+            },
+            15 => Self::Drawn {
+                id,
+                escrow,
+                callback,
+                seq,
+                amount: Self::read_u64(rest, 80)?,
+                withdrawn: Self::read_u64(rest, 88)?
+            },
+            16 => Self::FeeCharged {
+                id,
+                escrow,
+                callback,
+                seq,
+                fee: Self::read_u64(rest, 80)?,
+                by_payer: *rest.get(88).ok_or(ProgramError::InvalidAccountData)? == 1
+            },
+            17 => Self::Touched {
+                id,
+                escrow,
+                callback,
+                seq,
+                status: EscrowStatus::try_from(*rest.get(80).ok_or(ProgramError::InvalidAccountData)?)?,
+                amount: Self::read_u64(rest, 81)?,
+                token_amount: Self::read_u64(rest, 89)?,
+                withdrawn: Self::read_u64(rest, 97)?,
+                deadline: Self::read_u64(rest, 105)? as i64
+            },
+            18 => Self::Settled {
+                id,
+                escrow,
+                callback,
+                seq,
+                breakdown: SettlementBreakdown {
+                    principal: Self::read_u64(rest, 80)?,
+                    protocol_fee: Self::read_u64(rest, 88)?,
+                    late_fee: Self::read_u64(rest, 96)?,
+                    payout: Self::read_u64(rest, 104)?,
+                    shares: Self::read_u64(rest, 112)?,
+                    arbiter: Self::read_u64(rest, 120)?,
+                    rent_refund: Self::read_u64(rest, 128)?
+                }
+            },
+            19 => Self::Quarantined {
+                id,
+                escrow,
+                callback,
+                seq,
+                reason: Inconsistency::try_from(*rest.get(80).ok_or(ProgramError::InvalidAccountData)?)?
+            },
+            20 => Self::VestedShareWithdrawn {
+                id,
+                escrow,
+                callback,
+                seq,
+                beneficiary: Pubkey::new_from_array(Self::read_array(rest, 80)?),
+                amount: Self::read_u64(rest, 112)?,
+                withdrawn: Self::read_u64(rest, 120)?
+            },
+            24 => Self::ReceiptAcknowledged {
+                id,
+                escrow,
+                callback,
+                seq,
+                by: Pubkey::new_from_array(Self::read_array(rest, 80)?),
+                acknowledged_at: Self::read_u64(rest, 112)? as i64
+            },
+            _ => return Err(ProgramError::InvalidAccountData)
+        })
+    }
+
+    /// `(id, seq)` of an escrow event, `None` for admin events.
+    pub fn sequence(&self) -> Option<(u64, u64)> {
+        match self {
+            Self::Initialized { id, seq, .. }
+            | Self::Completed { id, seq, .. }
+            | Self::Closed { id, seq, .. }
+            | Self::CallbackRegistered { id, seq, .. }
+            | Self::ClaimAssigned { id, seq, .. }
+            | Self::ClaimShareAssigned { id, seq, .. }
+            | Self::DisputeRaised { id, seq, .. }
+            | Self::DisputeResolved { id, seq, .. }
+            | Self::VaultDiscrepancy { id, seq, .. }
+            | Self::Deposited { id, seq, .. }
+            | Self::VestedWithdrawn { id, seq, .. }
+            | Self::Drawn { id, seq, .. }
+            | Self::FeeCharged { id, seq, .. }
+            | Self::Settled { id, seq, .. }
+            | Self::Touched { id, seq, .. }
+            | Self::Quarantined { id, seq, .. }
+            | Self::VestedShareWithdrawn { id, seq, .. }
+            | Self::ReceiptAcknowledged { id, seq, .. } => Some((*id, *seq)),
+            Self::MintBlocked { .. }
+            | Self::MintUnblocked { .. }
+            | Self::PauseUpdated { .. }
+            | Self::ProtocolFeeUpdated { .. }
+            | Self::FeaturesUpdated { .. }
+            | Self::TreasurySwept { .. }
+            | Self::TreasuryRedeemed { .. }
+            | Self::CloseSkipped { .. } => None
+        }
+    }
+
+    fn pack_header(dst: &mut Vec<u8>, tag: u8, id: u64, escrow: &Pubkey, callback: &[u8; 32], seq: u64) {
+        dst.push(tag);
+        dst.extend_from_slice(&id.to_le_bytes());
+        dst.extend_from_slice(escrow.as_ref());
+        dst.extend_from_slice(callback);
+        dst.extend_from_slice(&seq.to_le_bytes());
+    }
+
+    fn read_u64(src: &[u8], offset: usize) -> Result<u64, ProgramError> {
+        src.get(offset..offset + 8)
+            .and_then(|slice| slice.try_into().ok())
+            .map(u64::from_le_bytes)
+            .ok_or(ProgramError::InvalidAccountData)
+    }
+
+    fn read_array(src: &[u8], offset: usize) -> Result<[u8; 32], ProgramError> {
+        src.get(offset..offset + 32)
+            .and_then(|slice| slice.try_into().ok())
+            .ok_or(ProgramError::InvalidAccountData)
+    }
+}
+
+
+/// Amounts of a `Complete` (the `Settled` event): lamports for SOL escrows, tokens for token escrows (`rent_refund` aside),
+/// so explorers, accounting & disputes all work from the numbers the program actually used.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SettlementBreakdown {
+    /// escrowed amount released
+    pub principal: u64,
+    /// protocol fee charged into the fee vault, off the principal or on top of it (`FeeCharged::by_payer`)
+    pub protocol_fee: u64,
+    /// late fee paid by the payer on top of the principal
+    pub late_fee: u64,
+    /// what the recipient (or its assignee) received
+    pub payout: u64,
+    /// what the claim shares (split recipients, e.g. a referrer) received, 0 without any
+    pub shares: u64,
+    /// what the arbiter received, 0 as long as arbiters charge no fee
+    pub arbiter: u64,
+    /// lamports of the closed escrow account returned to the payer
+    pub rent_refund: u64
+}
+
+/// Settlement receipt written as an SPL memo on `Complete`, for accounting systems that already ingest memos.
+pub struct SettlementReceipt<'a> {
+    pub id: u64,
+    pub escrow: &'a Pubkey,
+    pub payer: &'a Pubkey,
+    pub recipient: &'a Pubkey,
+    pub amount: u64
+}
+
+impl SettlementReceipt<'_> {
+    /// Canonical JSON: fixed key order, no whitespace, amounts in lamports.
+    pub fn to_json(&self) -> String {
+        format!(
+            r#"{{"type":"escrow_settlement","id":{},"escrow":"{}","payer":"{}","recipient":"{}","amount":{}}}"#,
+            self.id,
+            self.escrow,
+            self.payer,
+            self.recipient,
+            self.amount
+        )
+    }
+}
@@ -0,0 +1,93 @@
+use solana_program::{
+    pubkey,
+    pubkey::Pubkey,
+    account_info::AccountInfo,
+    program_error::ProgramError
+};
+
+
+/// SPL Governance (Realms) program, owner of realms, governances and proposals.
+pub const SPL_GOVERNANCE_PROGRAM_ID: Pubkey = pubkey!("GovER5Lthms3bLBqWub97yVrMmEogzX7xNjdXpPPCVZw");
+
+// `GovernanceAccountType`, the first byte of every governance account
+const REALM_V1: u8 = 1;
+const GOVERNANCE_V1: u8 = 3;
+const PROGRAM_GOVERNANCE_V1: u8 = 4;
+const PROPOSAL_V1: u8 = 5;
+const MINT_GOVERNANCE_V1: u8 = 9;
+const TOKEN_GOVERNANCE_V1: u8 = 10;
+const PROPOSAL_V2: u8 = 14;
+const REALM_V2: u8 = 16;
+const GOVERNANCE_V2: u8 = 18;
+const PROGRAM_GOVERNANCE_V2: u8 = 19;
+const MINT_GOVERNANCE_V2: u8 = 20;
+const TOKEN_GOVERNANCE_V2: u8 = 21;
+
+
+/// Checks that `realm_account` is a realm of the SPL Governance program.
+pub fn check_realm(realm_account: &AccountInfo) -> Result<(), ProgramError> {
+    if realm_account.owner != &SPL_GOVERNANCE_PROGRAM_ID {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    match realm_account.data.borrow().first() {
+        Some(&REALM_V1 | &REALM_V2) => Ok(()),
+        _ => Err(ProgramError::InvalidAccountData)
+    }
+}
+
+/// Realm a governance account belongs to. Layout: account_type (1) + realm (32) + ...
+pub fn governance_realm(governance_account: &AccountInfo) -> Result<Pubkey, ProgramError> {
+    if governance_account.owner != &SPL_GOVERNANCE_PROGRAM_ID {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let data = governance_account.data.borrow();
+
+    match data.first() {
+        Some(
+            &GOVERNANCE_V1 | &PROGRAM_GOVERNANCE_V1 | &MINT_GOVERNANCE_V1 | &TOKEN_GOVERNANCE_V1
+            | &GOVERNANCE_V2 | &PROGRAM_GOVERNANCE_V2 | &MINT_GOVERNANCE_V2 | &TOKEN_GOVERNANCE_V2
+        ) => {},
+        _ => return Err(ProgramError::InvalidAccountData)
+    };
+
+    Ok(Pubkey::new_from_array(data.get(1..33).and_then(|slice| slice.try_into().ok()).ok_or(ProgramError::InvalidAccountData)?))
+}
+
+
+/// The parts of a proposal (V1 or V2) a veto cares about.
+pub struct Proposal {
+    pub governance: Pubkey,
+    pub state: u8
+}
+
+impl Proposal {
+    // `ProposalState`
+    pub const SUCCEEDED: u8 = 3;
+    pub const EXECUTING: u8 = 4;
+    pub const COMPLETED: u8 = 5;
+
+    /// Layout: account_type (1) + governance (32) + governing_token_mint (32) + state (1) + ...
+    pub fn load(proposal_account: &AccountInfo) -> Result<Self, ProgramError> {
+        if proposal_account.owner != &SPL_GOVERNANCE_PROGRAM_ID {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        let data = proposal_account.data.borrow();
+
+        if !matches!(data.first(), Some(&PROPOSAL_V1 | &PROPOSAL_V2)) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        Ok(Self {
+            governance: Pubkey::new_from_array(data.get(1..33).and_then(|slice| slice.try_into().ok()).ok_or(ProgramError::InvalidAccountData)?),
+            state: *data.get(65).ok_or(ProgramError::InvalidAccountData)?
+        })
+    }
+
+    /// The vote passed: the proposal may be executing its transactions (still `Succeeded` while running the first one) or be done.
+    pub fn is_passed(&self) -> bool {
+        matches!(self.state, Self::SUCCEEDED | Self::EXECUTING | Self::COMPLETED)
+    }
+}
@@ -0,0 +1,467 @@
+//! Wire format of every instruction tag, as the IDL (and the clients generated from it, see `clients/rust`) describes it.
+//!
+//! `EscrowInstruction` is what the program acts on, several tags sharing a variant and optional fields being left out
+//! of the data rather than flagged. An IDL can't describe that, so it is built from `IdlInstruction` instead: one variant
+//! per tag, in tag order, whose fields laid out as borsh (the variant's index as a u8, then its fields) are exactly
+//! what `EscrowInstruction::unpack` reads for that tag. Optional fields are sent zeroed when unused, and variable-length
+//! fields ending the data (notes, labels, ...) aren't described at all: clients append them to the generated data.
+
+use solana_program::pubkey::Pubkey;
+use super::state::MilestoneTemplate;
+use super::name_service::RecipientResolution;
+
+
+#[repr(u8)]
+#[cfg_attr(feature = "idl", derive(shank::ShankInstruction))]
+#[cfg_attr(test, derive(borsh::BorshSerialize), borsh(use_discriminant = true))]
+pub enum IdlInstruction {
+    /// Trailing bytes (if any) are the note, see `EscrowInstruction::Initialize`.
+    #[cfg_attr(feature = "idl", account(0, writable, signer, name = "payer"))]
+    #[cfg_attr(feature = "idl", account(1, name = "recipient"))]
+    #[cfg_attr(feature = "idl", account(2, writable, name = "escrow"))]
+    #[cfg_attr(feature = "idl", account(3, name = "system_program"))]
+    #[cfg_attr(feature = "idl", account(4, writable, name = "counter"))]
+    #[cfg_attr(feature = "idl", account(5, name = "config"))]
+    Initialize { amount: u64 } = 0,
+    /// Trailing bytes (if any) are the note, see `EscrowInstruction::Complete`.
+    #[cfg_attr(feature = "idl", account(0, writable, signer, name = "payer"))]
+    #[cfg_attr(feature = "idl", account(1, writable, name = "recipient"))]
+    #[cfg_attr(feature = "idl", account(2, writable, name = "escrow"))]
+    #[cfg_attr(feature = "idl", account(3, name = "config"))]
+    Complete = 1,
+    #[cfg_attr(feature = "idl", account(0, writable, signer, name = "payer"))]
+    #[cfg_attr(feature = "idl", account(1, name = "recipient"))]
+    #[cfg_attr(feature = "idl", account(2, writable, name = "escrow"))]
+    #[cfg_attr(feature = "idl", account(3, name = "config"))]
+    Close = 2,
+    /// The default `mint` closes the note of a SOL escrow.
+    #[cfg_attr(feature = "idl", account(0, signer, name = "authority"))]
+    #[cfg_attr(feature = "idl", account(1, writable, name = "payer"))]
+    #[cfg_attr(feature = "idl", account(2, name = "recipient"))]
+    #[cfg_attr(feature = "idl", account(3, writable, name = "note"))]
+    CloseNote { mint: Pubkey } = 3,
+    #[cfg_attr(feature = "idl", account(0, writable, signer, name = "payer"))]
+    #[cfg_attr(feature = "idl", account(1, name = "config"))]
+    BatchClose = 4,
+    #[cfg_attr(feature = "idl", account(0, signer, name = "payer"))]
+    #[cfg_attr(feature = "idl", account(1, name = "recipient"))]
+    #[cfg_attr(feature = "idl", account(2, writable, name = "escrow"))]
+    RegisterCallback { callback: [u8; 32] } = 5,
+    /// The default `namespace` creates the global `Config`.
+    #[cfg_attr(feature = "idl", account(0, writable, signer, name = "admin"))]
+    #[cfg_attr(feature = "idl", account(1, writable, name = "config"))]
+    #[cfg_attr(feature = "idl", account(2, name = "system_program"))]
+    #[cfg_attr(feature = "idl", account(3, optional, name = "program_data"))]
+    InitializeConfig { namespace: Pubkey } = 6,
+    #[cfg_attr(feature = "idl", account(0, writable, signer, name = "admin"))]
+    #[cfg_attr(feature = "idl", account(1, name = "config"))]
+    #[cfg_attr(feature = "idl", account(2, name = "mint"))]
+    #[cfg_attr(feature = "idl", account(3, writable, name = "blocked_mint"))]
+    #[cfg_attr(feature = "idl", account(4, name = "system_program"))]
+    BlockMint = 7,
+    #[cfg_attr(feature = "idl", account(0, writable, signer, name = "admin"))]
+    #[cfg_attr(feature = "idl", account(1, name = "config"))]
+    #[cfg_attr(feature = "idl", account(2, name = "mint"))]
+    #[cfg_attr(feature = "idl", account(3, writable, name = "blocked_mint"))]
+    UnblockMint = 8,
+    /// `max_divergence_bps` is 0 without a secondary price feed.
+    #[cfg_attr(feature = "idl", account(0, signer, name = "payer"))]
+    #[cfg_attr(feature = "idl", account(1, name = "recipient"))]
+    #[cfg_attr(feature = "idl", account(2, writable, name = "escrow"))]
+    #[cfg_attr(feature = "idl", account(3, name = "price_feed"))]
+    #[cfg_attr(feature = "idl", account(4, optional, name = "secondary_price_feed"))]
+    #[cfg_attr(feature = "idl", account(5, name = "config"))]
+    SetUsdTerms { usd_amount: u64, max_divergence_bps: u16 } = 9,
+    #[cfg_attr(feature = "idl", account(0, writable, signer, name = "payer"))]
+    #[cfg_attr(feature = "idl", account(1, writable, name = "recipient"))]
+    #[cfg_attr(feature = "idl", account(2, writable, name = "escrow"))]
+    #[cfg_attr(feature = "idl", account(3, name = "config"))]
+    #[cfg_attr(feature = "idl", account(4, name = "price_feed"))]
+    #[cfg_attr(feature = "idl", account(5, optional, name = "secondary_price_feed"))]
+    CompleteUsd { min_amount: u64, max_amount: u64 } = 10,
+    /// Trailing bytes are the utf-8 label.
+    #[cfg_attr(feature = "idl", account(0, writable, signer, name = "payer"))]
+    #[cfg_attr(feature = "idl", account(1, name = "recipient"))]
+    #[cfg_attr(feature = "idl", account(2, name = "escrow"))]
+    #[cfg_attr(feature = "idl", account(3, writable, name = "display"))]
+    #[cfg_attr(feature = "idl", account(4, name = "system_program"))]
+    SetDisplayMetadata { icon_hash: [u8; 32] } = 11,
+    #[cfg_attr(feature = "idl", account(0, writable, signer, name = "payer"))]
+    #[cfg_attr(feature = "idl", account(1, name = "recipient"))]
+    #[cfg_attr(feature = "idl", account(2, writable, name = "display"))]
+    CloseDisplayMetadata = 12,
+    #[cfg_attr(feature = "idl", account(0, signer, name = "admin"))]
+    #[cfg_attr(feature = "idl", account(1, writable, name = "config"))]
+    SetPaused { paused: u8 } = 13,
+    /// `reference` is zeroed without one.
+    #[cfg_attr(feature = "idl", account(0, writable, signer, name = "payer"))]
+    #[cfg_attr(feature = "idl", account(1, name = "recipient"))]
+    #[cfg_attr(feature = "idl", account(2, writable, name = "escrow"))]
+    #[cfg_attr(feature = "idl", account(3, name = "mint"))]
+    #[cfg_attr(feature = "idl", account(4, writable, name = "vault"))]
+    #[cfg_attr(feature = "idl", account(5, writable, name = "payer_token"))]
+    #[cfg_attr(feature = "idl", account(6, name = "token_program"))]
+    #[cfg_attr(feature = "idl", account(7, name = "system_program"))]
+    #[cfg_attr(feature = "idl", account(8, writable, name = "counter"))]
+    #[cfg_attr(feature = "idl", account(9, name = "config"))]
+    #[cfg_attr(feature = "idl", account(10, name = "blocked_mint"))]
+    InitializeToken { amount: u64, reference: [u8; 16] } = 14,
+    /// `reference` is zeroed without one.
+    #[cfg_attr(feature = "idl", account(0, writable, signer, name = "payer"))]
+    #[cfg_attr(feature = "idl", account(1, name = "recipient"))]
+    #[cfg_attr(feature = "idl", account(2, writable, name = "escrow"))]
+    #[cfg_attr(feature = "idl", account(3, name = "mint"))]
+    #[cfg_attr(feature = "idl", account(4, writable, name = "vault"))]
+    #[cfg_attr(feature = "idl", account(5, writable, name = "payer_token"))]
+    #[cfg_attr(feature = "idl", account(6, name = "token_program"))]
+    #[cfg_attr(feature = "idl", account(7, name = "system_program"))]
+    #[cfg_attr(feature = "idl", account(8, writable, name = "counter"))]
+    #[cfg_attr(feature = "idl", account(9, name = "config"))]
+    #[cfg_attr(feature = "idl", account(10, name = "blocked_mint"))]
+    InitializeNft { reference: [u8; 16] } = 15,
+    #[cfg_attr(feature = "idl", account(0, writable, signer, name = "payer"))]
+    #[cfg_attr(feature = "idl", account(1, name = "recipient"))]
+    #[cfg_attr(feature = "idl", account(2, writable, name = "escrow"))]
+    #[cfg_attr(feature = "idl", account(3, name = "system_program"))]
+    #[cfg_attr(feature = "idl", account(4, writable, name = "counter"))]
+    #[cfg_attr(feature = "idl", account(5, name = "config"))]
+    #[cfg_attr(feature = "idl", account(6, writable, name = "milestones"))]
+    InitializeFromTemplate { amount: u64, template: MilestoneTemplate } = 16,
+    #[cfg_attr(feature = "idl", account(0, writable, signer, name = "payer"))]
+    #[cfg_attr(feature = "idl", account(1, writable, name = "recipient"))]
+    #[cfg_attr(feature = "idl", account(2, writable, name = "escrow"))]
+    #[cfg_attr(feature = "idl", account(3, name = "config"))]
+    #[cfg_attr(feature = "idl", account(4, writable, name = "milestones"))]
+    ApproveMilestone { index: u8 } = 17,
+    /// Trailing bytes (if any) are the note, see `EscrowInstruction::Initialize`.
+    #[cfg_attr(feature = "idl", account(0, writable, signer, name = "payer"))]
+    #[cfg_attr(feature = "idl", account(1, name = "recipient"))]
+    #[cfg_attr(feature = "idl", account(2, writable, name = "escrow"))]
+    #[cfg_attr(feature = "idl", account(3, name = "system_program"))]
+    #[cfg_attr(feature = "idl", account(4, writable, name = "counter"))]
+    #[cfg_attr(feature = "idl", account(5, name = "config"))]
+    InitializeWithDeadline { amount: u64, deadline: i64 } = 18,
+    #[cfg_attr(feature = "idl", account(0, signer, name = "payer"))]
+    #[cfg_attr(feature = "idl", account(1, name = "recipient"))]
+    #[cfg_attr(feature = "idl", account(2, writable, name = "escrow"))]
+    SetLateFee { bps_per_day: u16, cap_bps: u16 } = 19,
+    #[cfg_attr(feature = "idl", account(0, signer, name = "payer"))]
+    #[cfg_attr(feature = "idl", account(1, name = "recipient"))]
+    #[cfg_attr(feature = "idl", account(2, writable, name = "escrow"))]
+    SetUnlockAt { unlock_at: i64 } = 20,
+    #[cfg_attr(feature = "idl", account(0, signer, name = "holder"))]
+    #[cfg_attr(feature = "idl", account(1, name = "payer"))]
+    #[cfg_attr(feature = "idl", account(2, name = "recipient"))]
+    #[cfg_attr(feature = "idl", account(3, writable, name = "escrow"))]
+    #[cfg_attr(feature = "idl", account(4, name = "assignee"))]
+    AssignClaim = 21,
+    /// `preimage` is only checked for hash time-locked escrows, others may zero it.
+    #[cfg_attr(feature = "idl", account(0, writable, signer, name = "recipient"))]
+    #[cfg_attr(feature = "idl", account(1, writable, name = "payer"))]
+    #[cfg_attr(feature = "idl", account(2, writable, name = "escrow"))]
+    #[cfg_attr(feature = "idl", account(3, name = "config"))]
+    Claim { preimage: [u8; 32] } = 22,
+    #[cfg_attr(feature = "idl", account(0, signer, name = "recipient"))]
+    #[cfg_attr(feature = "idl", account(1, name = "payer"))]
+    #[cfg_attr(feature = "idl", account(2, writable, name = "escrow"))]
+    Accept = 23,
+    #[cfg_attr(feature = "idl", account(0, writable, signer, name = "payer"))]
+    #[cfg_attr(feature = "idl", account(1, writable, signer, name = "recipient"))]
+    #[cfg_attr(feature = "idl", account(2, writable, name = "escrow"))]
+    #[cfg_attr(feature = "idl", account(3, name = "config"))]
+    CancelMutual { payer_bps: u16, recipient_bps: u16 } = 24,
+    #[cfg_attr(feature = "idl", account(0, writable, signer, name = "recipient"))]
+    #[cfg_attr(feature = "idl", account(1, name = "payer"))]
+    #[cfg_attr(feature = "idl", account(2, writable, name = "escrow"))]
+    #[cfg_attr(feature = "idl", account(3, writable, name = "assignments"))]
+    #[cfg_attr(feature = "idl", account(4, name = "assignee"))]
+    #[cfg_attr(feature = "idl", account(5, name = "system_program"))]
+    AssignClaimShare { bps: u16, from_payout: bool } = 25,
+    #[cfg_attr(feature = "idl", account(0, signer, name = "signer"))]
+    #[cfg_attr(feature = "idl", account(1, name = "payer"))]
+    #[cfg_attr(feature = "idl", account(2, name = "recipient"))]
+    #[cfg_attr(feature = "idl", account(3, writable, name = "escrow"))]
+    RaiseDispute = 26,
+    #[cfg_attr(feature = "idl", account(0, signer, name = "arbiter"))]
+    #[cfg_attr(feature = "idl", account(1, writable, name = "payer"))]
+    #[cfg_attr(feature = "idl", account(2, writable, name = "recipient"))]
+    #[cfg_attr(feature = "idl", account(3, writable, name = "escrow"))]
+    ResolveDispute { payer_bps: u16, recipient_bps: u16 } = 27,
+    #[cfg_attr(feature = "idl", account(0, signer, name = "payer"))]
+    #[cfg_attr(feature = "idl", account(1, name = "recipient"))]
+    #[cfg_attr(feature = "idl", account(2, writable, name = "escrow"))]
+    #[cfg_attr(feature = "idl", account(3, name = "price_feed"))]
+    #[cfg_attr(feature = "idl", account(4, name = "config"))]
+    SetDepegGuard { max_depeg_bps: u16 } = 28,
+    #[cfg_attr(feature = "idl", account(0, name = "payer"))]
+    #[cfg_attr(feature = "idl", account(1, name = "recipient"))]
+    #[cfg_attr(feature = "idl", account(2, writable, name = "escrow"))]
+    #[cfg_attr(feature = "idl", account(3, optional, name = "arbiter"))]
+    OverrideDepegFreeze = 29,
+    #[cfg_attr(feature = "idl", account(0, writable, name = "escrow"))]
+    #[cfg_attr(feature = "idl", account(1, name = "vault"))]
+    #[cfg_attr(feature = "idl", account(2, optional, signer, name = "authority"))]
+    #[cfg_attr(feature = "idl", account(3, optional, name = "config"))]
+    Reconcile = 30,
+    /// `deadline` is 0 without one. Trailing bytes (if any) are the note, see `EscrowInstruction::Initialize`.
+    #[cfg_attr(feature = "idl", account(0, writable, signer, name = "payer"))]
+    #[cfg_attr(feature = "idl", account(1, name = "recipient"))]
+    #[cfg_attr(feature = "idl", account(2, writable, name = "escrow"))]
+    #[cfg_attr(feature = "idl", account(3, name = "system_program"))]
+    #[cfg_attr(feature = "idl", account(4, writable, name = "counter"))]
+    #[cfg_attr(feature = "idl", account(5, name = "config"))]
+    InitializeWithReference { amount: u64, deadline: i64, reference: [u8; 16] } = 31,
+    #[cfg_attr(feature = "idl", account(0, writable, signer, name = "payer"))]
+    #[cfg_attr(feature = "idl", account(1, name = "recipient"))]
+    #[cfg_attr(feature = "idl", account(2, writable, name = "escrow"))]
+    #[cfg_attr(feature = "idl", account(3, name = "system_program"))]
+    Deposit { amount: u64 } = 32,
+    /// Trailing bytes (if any) are the note, see `EscrowInstruction::Initialize`.
+    #[cfg_attr(feature = "idl", account(0, writable, signer, name = "payer"))]
+    #[cfg_attr(feature = "idl", account(1, name = "recipient"))]
+    #[cfg_attr(feature = "idl", account(2, writable, name = "escrow"))]
+    #[cfg_attr(feature = "idl", account(3, name = "system_program"))]
+    #[cfg_attr(feature = "idl", account(4, writable, name = "counter"))]
+    #[cfg_attr(feature = "idl", account(5, name = "config"))]
+    InitializeHashlocked { amount: u64, deadline: i64, hashlock: [u8; 32] } = 33,
+    /// Trailing bytes (if any) are the note, see `EscrowInstruction::Complete`.
+    #[cfg_attr(feature = "idl", account(0, writable, signer, name = "payer"))]
+    #[cfg_attr(feature = "idl", account(1, writable, name = "recipient"))]
+    #[cfg_attr(feature = "idl", account(2, writable, name = "escrow"))]
+    #[cfg_attr(feature = "idl", account(3, name = "config"))]
+    CompleteWithPreimage { preimage: [u8; 32] } = 34,
+    #[cfg_attr(feature = "idl", account(0, writable, signer, name = "payer"))]
+    #[cfg_attr(feature = "idl", account(1, name = "recipient"))]
+    #[cfg_attr(feature = "idl", account(2, writable, name = "leg_a"))]
+    #[cfg_attr(feature = "idl", account(3, name = "leg_b"))]
+    #[cfg_attr(feature = "idl", account(4, writable, name = "swap"))]
+    #[cfg_attr(feature = "idl", account(5, name = "system_program"))]
+    #[cfg_attr(feature = "idl", account(6, name = "config"))]
+    OpenSwap { deadline: i64 } = 35,
+    #[cfg_attr(feature = "idl", account(0, signer, name = "payer"))]
+    #[cfg_attr(feature = "idl", account(1, name = "recipient"))]
+    #[cfg_attr(feature = "idl", account(2, writable, name = "leg_b"))]
+    #[cfg_attr(feature = "idl", account(3, name = "leg_a"))]
+    #[cfg_attr(feature = "idl", account(4, writable, name = "swap"))]
+    JoinSwap = 36,
+    #[cfg_attr(feature = "idl", account(0, signer, name = "signer"))]
+    #[cfg_attr(feature = "idl", account(1, writable, name = "leg_a_payer"))]
+    #[cfg_attr(feature = "idl", account(2, writable, name = "leg_a_recipient"))]
+    #[cfg_attr(feature = "idl", account(3, writable, name = "leg_a"))]
+    #[cfg_attr(feature = "idl", account(4, writable, name = "leg_b"))]
+    #[cfg_attr(feature = "idl", account(5, writable, name = "swap"))]
+    #[cfg_attr(feature = "idl", account(6, name = "config"))]
+    Settle = 37,
+    #[cfg_attr(feature = "idl", account(0, writable, signer, name = "payer"))]
+    #[cfg_attr(feature = "idl", account(1, name = "recipient"))]
+    #[cfg_attr(feature = "idl", account(2, writable, name = "escrow"))]
+    #[cfg_attr(feature = "idl", account(3, name = "system_program"))]
+    #[cfg_attr(feature = "idl", account(4, writable, name = "counter"))]
+    #[cfg_attr(feature = "idl", account(5, name = "config"))]
+    Reserve { amount: u64, reserved_until: i64 } = 38,
+    #[cfg_attr(feature = "idl", account(0, writable, signer, name = "payer"))]
+    #[cfg_attr(feature = "idl", account(1, name = "recipient"))]
+    #[cfg_attr(feature = "idl", account(2, writable, name = "escrow"))]
+    #[cfg_attr(feature = "idl", account(3, name = "system_program"))]
+    #[cfg_attr(feature = "idl", account(4, name = "config"))]
+    Fund = 39,
+    #[cfg_attr(feature = "idl", account(0, writable, name = "payer"))]
+    #[cfg_attr(feature = "idl", account(1, name = "recipient"))]
+    #[cfg_attr(feature = "idl", account(2, writable, name = "escrow"))]
+    CloseExpiredReservation = 40,
+    /// Trailing bytes (if any) are the note, see `EscrowInstruction::Initialize`.
+    #[cfg_attr(feature = "idl", account(0, writable, signer, name = "payer"))]
+    #[cfg_attr(feature = "idl", account(1, name = "recipient"))]
+    #[cfg_attr(feature = "idl", account(2, writable, name = "escrow"))]
+    #[cfg_attr(feature = "idl", account(3, name = "system_program"))]
+    #[cfg_attr(feature = "idl", account(4, writable, name = "counter"))]
+    #[cfg_attr(feature = "idl", account(5, name = "config"))]
+    InitializeVesting { amount: u64, start: i64, end: i64 } = 41,
+    #[cfg_attr(feature = "idl", account(0, writable, signer, name = "recipient"))]
+    #[cfg_attr(feature = "idl", account(1, writable, name = "payer"))]
+    #[cfg_attr(feature = "idl", account(2, writable, name = "escrow"))]
+    #[cfg_attr(feature = "idl", account(3, name = "config"))]
+    WithdrawVested = 42,
+    /// Followed by `count` milestones, each an amount (u64) and a deadline (i64).
+    #[cfg_attr(feature = "idl", account(0, writable, signer, name = "payer"))]
+    #[cfg_attr(feature = "idl", account(1, name = "recipient"))]
+    #[cfg_attr(feature = "idl", account(2, writable, name = "escrow"))]
+    #[cfg_attr(feature = "idl", account(3, name = "system_program"))]
+    #[cfg_attr(feature = "idl", account(4, writable, name = "counter"))]
+    #[cfg_attr(feature = "idl", account(5, name = "config"))]
+    #[cfg_attr(feature = "idl", account(6, writable, name = "milestones"))]
+    InitializeMilestones { count: u8 } = 43,
+    #[cfg_attr(feature = "idl", account(0, writable, signer, name = "party_a"))]
+    #[cfg_attr(feature = "idl", account(1, writable, signer, name = "party_b"))]
+    #[cfg_attr(feature = "idl", account(2, writable, name = "escrow_a"))]
+    #[cfg_attr(feature = "idl", account(3, writable, name = "escrow_b"))]
+    #[cfg_attr(feature = "idl", account(4, name = "config"))]
+    SettleSwap { amount_a: u64, amount_b: u64 } = 44,
+    /// Followed by `count` splits, each a recipient (32) and its share in basis points (u16), then the note.
+    #[cfg_attr(feature = "idl", account(0, writable, signer, name = "payer"))]
+    #[cfg_attr(feature = "idl", account(1, name = "recipient"))]
+    #[cfg_attr(feature = "idl", account(2, writable, name = "escrow"))]
+    #[cfg_attr(feature = "idl", account(3, name = "system_program"))]
+    #[cfg_attr(feature = "idl", account(4, writable, name = "counter"))]
+    #[cfg_attr(feature = "idl", account(5, name = "config"))]
+    #[cfg_attr(feature = "idl", account(6, writable, name = "assignments"))]
+    InitializeSplit { amount: u64, count: u8 } = 45,
+    #[cfg_attr(feature = "idl", account(0, signer, name = "payer"))]
+    #[cfg_attr(feature = "idl", account(1, name = "recipient"))]
+    #[cfg_attr(feature = "idl", account(2, writable, name = "escrow"))]
+    #[cfg_attr(feature = "idl", account(3, name = "realm"))]
+    SetGovernanceVeto { veto_until: i64 } = 46,
+    #[cfg_attr(feature = "idl", account(0, signer, name = "governance"))]
+    #[cfg_attr(feature = "idl", account(1, name = "proposal"))]
+    #[cfg_attr(feature = "idl", account(2, writable, name = "payer"))]
+    #[cfg_attr(feature = "idl", account(3, name = "recipient"))]
+    #[cfg_attr(feature = "idl", account(4, writable, name = "escrow"))]
+    #[cfg_attr(feature = "idl", account(5, name = "config"))]
+    GovernanceVeto = 47,
+    #[cfg_attr(feature = "idl", account(0, writable, signer, name = "admin"))]
+    #[cfg_attr(feature = "idl", account(1, writable, name = "config"))]
+    #[cfg_attr(feature = "idl", account(2, writable, name = "fee_vault"))]
+    #[cfg_attr(feature = "idl", account(3, name = "system_program"))]
+    SetProtocolFee { fee_bps: u16 } = 48,
+    #[cfg_attr(feature = "idl", account(0, signer, name = "admin"))]
+    #[cfg_attr(feature = "idl", account(1, name = "config"))]
+    #[cfg_attr(feature = "idl", account(2, writable, name = "fee_vault"))]
+    #[cfg_attr(feature = "idl", account(3, writable, name = "destination"))]
+    WithdrawFees { amount: u64 } = 49,
+    #[cfg_attr(feature = "idl", account(0, signer, name = "admin"))]
+    #[cfg_attr(feature = "idl", account(1, writable, name = "config"))]
+    SetAttestationIssuer { issuer: Pubkey } = 50,
+    #[cfg_attr(feature = "idl", account(0, signer, name = "payer"))]
+    #[cfg_attr(feature = "idl", account(1, name = "recipient"))]
+    #[cfg_attr(feature = "idl", account(2, writable, name = "escrow"))]
+    RequireAttestation = 51,
+    #[cfg_attr(feature = "idl", account(0, signer, name = "admin"))]
+    #[cfg_attr(feature = "idl", account(1, writable, name = "config"))]
+    UpdateConfig { min_amount: u64, max_amount: u64 } = 52,
+    #[cfg_attr(feature = "idl", account(0, signer, name = "payer"))]
+    #[cfg_attr(feature = "idl", account(1, name = "recipient"))]
+    #[cfg_attr(feature = "idl", account(2, writable, name = "escrow"))]
+    SetMaxPayout { max_payout: u64 } = 53,
+    #[cfg_attr(feature = "idl", account(0, signer, name = "admin"))]
+    #[cfg_attr(feature = "idl", account(1, writable, name = "config"))]
+    NominateAdmin { admin: Pubkey } = 54,
+    #[cfg_attr(feature = "idl", account(0, signer, name = "nominee"))]
+    #[cfg_attr(feature = "idl", account(1, writable, name = "config"))]
+    AcceptAdmin = 55,
+    /// Trailing bytes (if any) are the note, see `EscrowInstruction::Initialize`.
+    #[cfg_attr(feature = "idl", account(0, writable, signer, name = "payer"))]
+    #[cfg_attr(feature = "idl", account(1, name = "recipient"))]
+    #[cfg_attr(feature = "idl", account(2, writable, name = "escrow"))]
+    #[cfg_attr(feature = "idl", account(3, name = "system_program"))]
+    #[cfg_attr(feature = "idl", account(4, writable, name = "counter"))]
+    #[cfg_attr(feature = "idl", account(5, name = "config"))]
+    InitializePayerPaysFee { amount: u64 } = 56,
+    #[cfg_attr(feature = "idl", account(0, name = "escrow"))]
+    Touch = 57,
+    #[cfg_attr(feature = "idl", account(0, writable, signer, name = "signer"))]
+    #[cfg_attr(feature = "idl", account(1, name = "escrow"))]
+    #[cfg_attr(feature = "idl", account(2, writable, name = "deadline_bucket"))]
+    #[cfg_attr(feature = "idl", account(3, name = "system_program"))]
+    IndexDeadline = 58,
+    #[cfg_attr(feature = "idl", account(0, writable, signer, name = "signer"))]
+    #[cfg_attr(feature = "idl", account(1, writable, name = "deadline_bucket"))]
+    PruneDeadlines = 59,
+    #[cfg_attr(feature = "idl", account(0, signer, name = "payer"))]
+    #[cfg_attr(feature = "idl", account(1, name = "recipient"))]
+    #[cfg_attr(feature = "idl", account(2, writable, name = "escrow"))]
+    RequireIsolatedSettlement = 60,
+    #[cfg_attr(feature = "idl", account(0, writable, signer, name = "admin"))]
+    #[cfg_attr(feature = "idl", account(1, name = "config"))]
+    #[cfg_attr(feature = "idl", account(2, writable, name = "escrow"))]
+    #[cfg_attr(feature = "idl", account(3, writable, name = "export"))]
+    #[cfg_attr(feature = "idl", account(4, name = "system_program"))]
+    ExportState = 61,
+    /// Trailing bytes are the exported state.
+    #[cfg_attr(feature = "idl", account(0, writable, signer, name = "admin"))]
+    #[cfg_attr(feature = "idl", account(1, name = "config"))]
+    #[cfg_attr(feature = "idl", account(2, name = "export"))]
+    #[cfg_attr(feature = "idl", account(3, writable, name = "import_marker"))]
+    #[cfg_attr(feature = "idl", account(4, writable, name = "escrow"))]
+    #[cfg_attr(feature = "idl", account(5, name = "system_program"))]
+    ImportState = 62,
+    #[cfg_attr(feature = "idl", account(0, writable, name = "escrow"))]
+    Quarantine = 63,
+    /// Followed by `count` splits like `InitializeSplit`, then the note. The `VestingShare` PDAs of the recipient and of
+    /// every split follow `assignments`.
+    #[cfg_attr(feature = "idl", account(0, writable, signer, name = "payer"))]
+    #[cfg_attr(feature = "idl", account(1, name = "recipient"))]
+    #[cfg_attr(feature = "idl", account(2, writable, name = "escrow"))]
+    #[cfg_attr(feature = "idl", account(3, name = "system_program"))]
+    #[cfg_attr(feature = "idl", account(4, writable, name = "counter"))]
+    #[cfg_attr(feature = "idl", account(5, name = "config"))]
+    #[cfg_attr(feature = "idl", account(6, writable, name = "assignments"))]
+    InitializeTeamVesting { amount: u64, start: i64, end: i64, count: u8 } = 64,
+    #[cfg_attr(feature = "idl", account(0, writable, signer, name = "beneficiary"))]
+    #[cfg_attr(feature = "idl", account(1, writable, name = "payer"))]
+    #[cfg_attr(feature = "idl", account(2, name = "recipient"))]
+    #[cfg_attr(feature = "idl", account(3, writable, name = "escrow"))]
+    #[cfg_attr(feature = "idl", account(4, name = "config"))]
+    #[cfg_attr(feature = "idl", account(5, writable, name = "assignments"))]
+    #[cfg_attr(feature = "idl", account(6, writable, name = "vesting_share"))]
+    WithdrawVestedShare = 65,
+    /// Trailing bytes (if any) are the note, see `EscrowInstruction::Initialize`.
+    #[cfg_attr(feature = "idl", account(0, writable, signer, name = "payer"))]
+    #[cfg_attr(feature = "idl", account(1, name = "domain"))]
+    #[cfg_attr(feature = "idl", account(2, writable, name = "escrow"))]
+    #[cfg_attr(feature = "idl", account(3, name = "system_program"))]
+    #[cfg_attr(feature = "idl", account(4, writable, name = "counter"))]
+    #[cfg_attr(feature = "idl", account(5, name = "config"))]
+    InitializeToDomain { amount: u64, resolution: RecipientResolution } = 66,
+    #[cfg_attr(feature = "idl", account(0, signer, name = "admin"))]
+    #[cfg_attr(feature = "idl", account(1, writable, name = "config"))]
+    SetFeatures { features: u8 } = 67,
+    #[cfg_attr(feature = "idl", account(0, signer, name = "relayer"))]
+    #[cfg_attr(feature = "idl", account(1, name = "config"))]
+    #[cfg_attr(feature = "idl", account(2, name = "instructions_sysvar"))]
+    BatchSettle = 68,
+    #[cfg_attr(feature = "idl", account(0, signer, name = "admin"))]
+    #[cfg_attr(feature = "idl", account(1, writable, name = "config"))]
+    SetYieldVenue { venue: Pubkey } = 69,
+    #[cfg_attr(feature = "idl", account(0, signer, name = "admin"))]
+    #[cfg_attr(feature = "idl", account(1, writable, name = "config"))]
+    #[cfg_attr(feature = "idl", account(2, writable, name = "fee_vault"))]
+    #[cfg_attr(feature = "idl", account(3, writable, name = "treasury"))]
+    #[cfg_attr(feature = "idl", account(4, writable, name = "pool_tokens"))]
+    #[cfg_attr(feature = "idl", account(5, name = "pool_program"))]
+    #[cfg_attr(feature = "idl", account(6, writable, name = "pool"))]
+    #[cfg_attr(feature = "idl", account(7, name = "withdraw_authority"))]
+    #[cfg_attr(feature = "idl", account(8, writable, name = "reserve_stake"))]
+    #[cfg_attr(feature = "idl", account(9, writable, name = "manager_fee"))]
+    #[cfg_attr(feature = "idl", account(10, writable, name = "pool_mint"))]
+    #[cfg_attr(feature = "idl", account(11, name = "system_program"))]
+    #[cfg_attr(feature = "idl", account(12, name = "token_program"))]
+    SweepFees { amount: u64 } = 70,
+    #[cfg_attr(feature = "idl", account(0, signer, name = "admin"))]
+    #[cfg_attr(feature = "idl", account(1, writable, name = "config"))]
+    #[cfg_attr(feature = "idl", account(2, writable, name = "fee_vault"))]
+    #[cfg_attr(feature = "idl", account(3, writable, name = "treasury"))]
+    #[cfg_attr(feature = "idl", account(4, writable, name = "pool_tokens"))]
+    #[cfg_attr(feature = "idl", account(5, name = "pool_program"))]
+    #[cfg_attr(feature = "idl", account(6, writable, name = "pool"))]
+    #[cfg_attr(feature = "idl", account(7, name = "withdraw_authority"))]
+    #[cfg_attr(feature = "idl", account(8, writable, name = "reserve_stake"))]
+    #[cfg_attr(feature = "idl", account(9, writable, name = "manager_fee"))]
+    #[cfg_attr(feature = "idl", account(10, writable, name = "pool_mint"))]
+    #[cfg_attr(feature = "idl", account(11, name = "clock"))]
+    #[cfg_attr(feature = "idl", account(12, name = "stake_history"))]
+    #[cfg_attr(feature = "idl", account(13, name = "stake_program"))]
+    #[cfg_attr(feature = "idl", account(14, name = "system_program"))]
+    #[cfg_attr(feature = "idl", account(15, name = "token_program"))]
+    RedeemTreasury { pool_tokens: u64 } = 71,
+    /// Trailing bytes are the acknowledged state.
+    #[cfg_attr(feature = "idl", account(0, signer, name = "recipient"))]
+    #[cfg_attr(feature = "idl", account(1, name = "payer"))]
+    #[cfg_attr(feature = "idl", account(2, name = "escrow"))]
+    #[cfg_attr(feature = "idl", account(3, writable, name = "archive"))]
+    AcknowledgeReceipt { record: u32, lamports: u64 } = 72,
+    #[cfg_attr(feature = "idl", account(0, signer, name = "holder"))]
+    #[cfg_attr(feature = "idl", account(1, name = "payer"))]
+    #[cfg_attr(feature = "idl", account(2, name = "recipient"))]
+    #[cfg_attr(feature = "idl", account(3, writable, name = "escrow"))]
+    SetStakeSettlement { vote: Pubkey } = 73
+}
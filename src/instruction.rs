@@ -1,26 +1,1701 @@
-use solana_program::program_error::ProgramError;
+use std::cmp::Ordering;
+use solana_program::{
+    hash::Hash,
+    pubkey::Pubkey,
+    system_program,
+    message::Message,
+    instruction::{Instruction, AccountMeta},
+    program_error::ProgramError
+};
+use super::error::EscrowError;
+use super::state::{ClaimShare, DeadlineBucket, Milestone, MilestoneTemplate};
+use super::name_service::RecipientResolution;
+use super::accounts::{
+    InitializeAccounts, CompleteAccounts, ClaimAccounts, CloseAccounts, DepositAccounts, FundAccounts, CloseExpiredReservationAccounts,
+    WithdrawVestedAccounts, WithdrawVestedShareAccounts, TouchAccounts, QuarantineAccounts, IndexDeadlineAccounts, ExportStateAccounts, ImportStateAccounts,
+    NominateAdminAccounts, AcceptAdminAccounts, SetFeaturesAccounts, SetYieldVenueAccounts, SweepFeesAccounts, RedeemTreasuryAccounts,
+    AcknowledgeReceiptAccounts, SetStakeSettlementAccounts
+};
+use super::treasury::StakePool;
 
 
+/// `Initialize`, `Complete`, `CompleteUsd`, `Close` and `BatchClose` take the `Config` PDA (which may not exist yet)
+/// so the processor can honour `Config::paused`.
+///
+/// Once the claim is assigned (`AssignClaim`), `Complete`, `CompleteUsd`, `ApproveMilestone` and `Claim` pay out to the assignee,
+/// who is then expected (writable) right after `config`.
+///
+/// Variants are numbered by their tag (the first byte of the data, unless length-prefixed, see `split_tag`). The IDL
+/// describes every tag on its own, see `idl::IdlInstruction`; the accounts optional features append are only described here.
+#[repr(u8)]
 pub enum EscrowInstruction {
-    Initialize { amount: u64 },
-    Complete,
-    Close
+    /// Trailing bytes after `amount` (if any) are stored as an encrypted note for the recipient.
+    /// An extra account after `config` (after the note account, with a note) becomes the escrow's arbiter.
+    /// With tag 18 instead of 0, `amount` is followed by a `deadline` (i64 unix timestamp, before the note):
+    /// `Complete` then only succeeds before it and `Close` only after it.
+    /// With tag 31, `amount` is followed by a `deadline` (0 if none) and a 16-byte external `reference`
+    /// (e.g. the merchant's order id, see `EscrowAccount::REFERENCE_OFFSET`), then the note.
+    /// With tag 33 (hash time-locked), `amount` is followed by a `deadline` (required, the refund fall-back) and
+    /// the SHA-256 `hashlock` of a 32-byte secret, then the note: the escrow can then only be released with the secret.
+    /// With tag 41 (vesting), `amount` is followed by the `vesting` start & end (i64 unix timestamps), then the note:
+    /// `amount` then vests linearly in between, and the recipient withdraws it at will (`WithdrawVested`).
+    /// With tag 45 (split payout), `amount` is followed by a count (1) and that many `splits`, each a recipient (32)
+    /// and its share in basis points (2), then the note: `Complete` pays every split its share (like sold `AssignClaimShare`s),
+    /// the recipient keeping the rest. The `EscrowAssignments` PDA, paid by the payer, is then expected right after `config`.
+    /// With tag 56 (payer-borne fee), `amount` is followed by the note, and the payer bears the protocol fee: `Complete`
+    /// charges it on top, out of the payer's wallet, instead of deducting it from the recipient's payout, and then
+    /// expects the system program right after the fee vault.
+    /// With tag 64 (team vesting), `amount` is followed by the `vesting` start & end, then by the `splits` like with
+    /// tag 45 (the recipient keeping the rest, at least 1 bps), then the note: `amount` then vests linearly to every
+    /// beneficiary at its weight, each withdrawing its own part at will (`WithdrawVestedShare`). The `EscrowAssignments`
+    /// PDA is expected right after `config`, followed by the `VestingShare` PDA of the recipient and of every split,
+    /// in order, all paid by the payer.
+    /// With tag 66 (SNS recipient), the recipient account is the name account of a `.sol` domain (see
+    /// `name_service::domain_address`), the escrow PDA being derived from it, and `amount` is followed by its
+    /// `RecipientResolution` (1), then the note: the domain's owner is either pinned as the escrow's assignee right away,
+    /// or looked up at settlement. Either way, the owner is then expected (writable) right after `config`, like an
+    /// assignee, and signs `Claim` in the domain's stead.
+    Initialize {
+        amount: u64,
+        deadline: Option<i64>,
+        reference: Option<[u8; 16]>,
+        hashlock: Option<[u8; 32]>,
+        vesting: Option<(i64, i64)>,
+        splits: Option<Vec<ClaimShare>>,
+        payer_pays_fee: bool,
+        domain: Option<RecipientResolution>,
+        note: Option<Vec<u8>>
+    } = 0,
+    /// Trailing bytes after the tag (if any) are stored as an encrypted note for the recipient.
+    /// Passing the SPL Memo program as the last account additionally writes a JSON settlement receipt.
+    /// Passing the payer's `EscrowArchive` PDA and the system program (before the memo program) archives the escrow.
+    /// Hash time-locked escrows are completed with tag 34 instead of 1, the `preimage` preceding the note.
+    Complete { preimage: Option<[u8; 32]>, note: Option<Vec<u8>> } = 1,
+    /// Passing the payer's `EscrowArchive` PDA and the system program as the last accounts archives the escrow.
+    Close = 2,
+    /// Signed by the payer or the recipient, the rent going back to the payer. Notes of token escrows are given
+    /// the escrow's `mint` (32 bytes of data, the default pubkey meaning none), the escrow PDA the note hangs off being
+    /// derived from it.
+    CloseNote { mint: Option<Pubkey> } = 3,
+    /// Refunds many plain SOL escrows of the payer at once. Remaining accounts are `(recipient, escrow)` pairs; escrows
+    /// failing the `Close` checks are skipped, each with a `CloseSkipped` event carrying its error. Token, milestone and
+    /// assigned escrows need more accounts than a pair carries, so they're skipped with `InvalidArgument`, to be closed
+    /// one by one through `Close`.
+    BatchClose = 4,
+    /// Sets the integrator's webhook digest that every event of the escrow carries. Signed by the payer.
+    RegisterCallback { callback: [u8; 32] } = 5,
+    /// Creates the global `Config`, making the signer its admin. Signed by the program's upgrade authority,
+    /// whose `ProgramData` account (see `crate::get_program_data_address()`) comes last. Meant to be called right after deployment.
+    /// With a `namespace` (any pubkey but the default one, passed as 32 bytes of data), creates that namespace's `Config`
+    /// (seeds: `"config"`, namespace) instead, letting another operator run its own admin, pause flags, fees, issuer
+    /// and fee vault (seeds: `"fee_vault"`, namespace) on this deployment. Escrows are bound to the `Config` passed
+    /// when creating them, which every later instruction taking a `Config` must then be given.
+    /// Accounts: admin, config, system program, [program data, for the global `Config`].
+    InitializeConfig { namespace: Pubkey } = 6,
+    /// Global `Config` admin only, the blocklist applying to every namespace. Prevents the mint from being used in token escrows.
+    BlockMint = 7,
+    /// Global `Config` admin only. Lifts a `BlockMint`, returning the marker's rent to the admin.
+    UnblockMint = 8,
+    /// Makes the escrow USD-denominated: the recipient is paid `usd_amount` worth of SOL, priced by the given oracle.
+    /// Signed by the payer. With `max_divergence_bps` (left out or 0 if none), a second price account is expected and settlement
+    /// is rejected whenever the two sources disagree by more than that (both must be readable by `OraclePrice::load_sol_usd()`).
+    /// Only while the escrow is `Pending`, the recipient accepting it at the terms it has then.
+    /// The escrow's `config` comes last, which must have `Config::FEATURE_ORACLES` enabled.
+    SetUsdTerms { usd_amount: u64, max_divergence_bps: Option<u16> } = 9,
+    /// Settles a USD-denominated escrow. Fails unless the oracle-derived lamports fall within `[min_amount, max_amount]`.
+    /// While a protocol fee is set, the fee vault follows the price feeds and the fee is charged on those lamports.
+    CompleteUsd { min_amount: u64, max_amount: u64 } = 10,
+    /// Creates or updates the escrow's `EscrowDisplay`. Signed by the payer.
+    /// Data: icon_hash (32) + utf-8 label (rest, at most 32 bytes).
+    SetDisplayMetadata { label: String, icon_hash: [u8; 32] } = 11,
+    /// Closes the escrow's `EscrowDisplay`, returning its rent to the payer. Signed by the payer.
+    CloseDisplayMetadata = 12,
+    /// Admin only. Replaces `Config::paused` with the given bitmask of `Config::PAUSE_*` flags, e.g. pausing only
+    /// `PAUSE_INITIALIZE` during an incident stops new escrows while payers can still `Close` theirs.
+    /// Paused instructions fail with `EscrowError::Paused` right after their accounts are read, before touching any
+    /// escrow; `Config::PAUSE_ALL` halts every instruction creating an escrow or moving escrowed funds, while disputes,
+    /// metadata, `Deposit` top-ups and the admin's own instructions stay live. `0` unpauses everything.
+    SetPaused { paused: u8 } = 13,
+    /// Locks `amount` tokens of an SPL Token or Token-2022 mint in the escrow's vault.
+    /// The escrow is derived by `get_token_escrow_seeds()`.
+    /// Token escrows settle through the regular `Complete` and `Close`, which then expect
+    /// `[mint, vault, destination token account, token program]` right after `config`
+    /// (the mint has to be writable for Token-2022 mints with transfer fees).
+    /// An optional 16-byte external reference may follow `amount`, as in `Initialize`.
+    /// Requires `Config::FEATURE_TOKENS`, as does `InitializeNft`.
+    InitializeToken { amount: u64, reference: Option<[u8; 16]> } = 14,
+    /// Same accounts (and optional reference) as `InitializeToken`, but locks a single NFT (supply 1, 0 decimals).
+    /// `Complete` and `Close` then only release it to the recipient's and payer's associated token account respectively.
+    InitializeNft { reference: Option<[u8; 16]> } = 15,
+    /// `Initialize` (same accounts, followed by the escrow's `EscrowMilestones` PDA) splitting `amount` into
+    /// the milestones of a `MilestoneTemplate`. Such escrows are released through `ApproveMilestone` instead of `Complete`,
+    /// and `Close` (refunding whatever is left) then expects the `EscrowMilestones` PDA right after `config`.
+    InitializeFromTemplate { amount: u64, template: MilestoneTemplate } = 16,
+    /// Releases one milestone to the recipient (less the protocol fee), signed by the payer. Releasing the last one
+    /// closes the escrow.
+    /// Accounts: payer, recipient, escrow, config, `EscrowMilestones` PDA, [fee vault].
+    ApproveMilestone { index: u8 } = 17,
+    /// Lets the payer still `Complete` a SOL escrow after its deadline, paying the recipient `bps_per_day` of the payout
+    /// per full day late (at most `cap_bps`) out of their own wallet. Signed by the payer, can only be set once.
+    /// `Complete` then expects the system program right after `config`.
+    SetLateFee { bps_per_day: u16, cap_bps: u16 } = 19,
+    /// Locks the funds until `unlock_at`: releasing them to the recipient fails with `EscrowError::StillLocked` before that.
+    /// Signed by the payer, can only be set once and has to come before the deadline (if any).
+    /// Accounts: payer, recipient, escrow.
+    SetUnlockAt { unlock_at: i64 } = 20,
+    /// Sells the claim on the payout to a third party, e.g. to finance the receivable.
+    /// Signed by the current holder of the claim: the recipient, or the assignee of an earlier assignment.
+    /// Accounts: holder, payer, recipient, escrow, new assignee.
+    AssignClaim = 21,
+    /// Settles the escrow like `Complete`, but signed by the holder of the claim (the recipient, or the assignee)
+    /// who pulls the funds, so the payer doesn't have to be online. Rent still goes back to the payer.
+    /// Accounts: recipient, payer, escrow, config, [assignee], [token accounts as in `Complete`], [fee vault as in `Complete`].
+    /// Hash time-locked escrows expect the 32-byte `preimage` of their hashlock as instruction data.
+    /// When the payer bears the protocol fee, they have to co-sign and the system program follows the fee vault.
+    /// Vesting escrows can't be claimed, they pay out only through `WithdrawVested`/`WithdrawVestedShare`.
+    Claim { preimage: Option<[u8; 32]> } = 22,
+    /// Signed by the recipient. From then on `Close` fails unless the escrow has a deadline that has passed,
+    /// the parties cancel through `CancelMutual` instead. SOL escrows only.
+    /// Accounts: recipient, payer, escrow.
+    Accept = 23,
+    /// Cancels the escrow with both parties' consent, splitting the remaining funds by the negotiated shares
+    /// (which must add up to 10000 bps). Signed by the payer and the holder of the claim. No protocol fee is charged,
+    /// the escrow being cancelled rather than settled.
+    /// Accounts: payer, recipient, escrow, config, [assignee], [`EscrowMilestones` PDA for milestone escrows].
+    CancelMutual { payer_bps: u16, recipient_bps: u16 } = 24,
+    /// Sells `bps` of the future payout of a SOL escrow, signed (and the share's rent paid) by the recipient.
+    /// Can't be combined with `AssignClaim`. `Complete` then expects the `EscrowAssignments` PDA followed by every assignee
+    /// (writable, in assignment order) right after `config`, and `Close` the `EscrowAssignments` PDA right after `config`
+    /// with the recipient writable to get its rent back. Such escrows can't be settled through `Claim` or `CancelMutual`.
+    /// With `from_payout` (a trailing `1` byte), the rent is taken off the escrowed amount instead, so a recipient
+    /// holding no SOL can sell shares: it's moved back into the escrow before it's settled or refunded,
+    /// so the payer and the assignees are unaffected and the recipient's payout shrinks by the rent until then.
+    /// Accounts: recipient, payer, escrow, `EscrowAssignments` PDA, assignee, system program.
+    AssignClaimShare { bps: u16, from_payout: bool } = 25,
+    /// Freezes settlement of an escrow with an arbiter until `ResolveDispute`. Signed by the payer or the recipient.
+    /// Accounts: signer, payer, recipient, escrow.
+    RaiseDispute = 26,
+    /// Arbiter only. Settles a disputed SOL escrow by splitting the remaining funds (the shares must add up to 10000 bps),
+    /// the recipient's part being paid out like in `Complete`, though free of the protocol fee (rulings don't take
+    /// the config). The rent goes back to the payer.
+    /// Accounts: arbiter, payer, recipient, escrow, [assignee or `EscrowAssignments` PDA and assignees], [`EscrowMilestones` PDA].
+    ResolveDispute { payer_bps: u16, recipient_bps: u16 } = 27,
+    /// Freezes `Complete`, `Claim` and `Close` of a stablecoin escrow whenever the given oracle (readable by `OraclePrice::load()`)
+    /// puts the token further than `max_depeg_bps` from $1, failing with `EscrowError::Depegged`.
+    /// Those then expect the oracle right after the token accounts. Signed by the payer, can only be set once.
+    /// Requires `Config::FEATURE_ORACLES` in the escrow's config.
+    /// Accounts: payer, recipient, escrow, price feed, config.
+    SetDepegGuard { max_depeg_bps: u16 } = 28,
+    /// Lifts the depeg freeze for good. Signed by the arbiter, or by both parties.
+    /// Accounts: payer, recipient, escrow, [arbiter].
+    OverrideDepegFreeze = 29,
+    /// Compares a token escrow's vault with `EscrowAccount::token_amount` and, on drift, emits `VaultDiscrepancy`
+    /// and records the actual balance. Permissionless for a surplus (e.g. tokens sent to the vault directly),
+    /// while a shortfall is only written off when signed by the payer or the admin of the escrow's config.
+    /// Accounts: escrow, vault, [authority, config] (for a shortfall).
+    Reconcile = 30,
+    /// Tops up a SOL escrow with `amount` lamports from the payer (milestone escrows keep their schedule, so they can't be topped up).
+    /// Accounts: payer, recipient, escrow, system program.
+    Deposit { amount: u64 } = 32,
+    /// Offers a swap of the escrow (leg A) against `leg_b`, the escrow its recipient is expected to fund for its payer
+    /// (SOL or token, which may not exist yet). Leg A is locked until `Settle`, which refunds it once `deadline` passed
+    /// without `JoinSwap`. Legs can't be USD-denominated, hash time-locked, milestone, assigned or depeg guarded escrows.
+    /// Signed by leg A's payer, who pays the `EscrowSwap` rent. Requires `Config::FEATURE_SWAPS` in leg A's config.
+    /// Accounts: payer, recipient, leg A escrow, leg B escrow, `EscrowSwap` PDA, system program, config.
+    OpenSwap { deadline: i64 } = 35,
+    /// Locks leg B into the swap. Signed by leg B's payer (leg A's recipient).
+    /// Accounts: payer, recipient, leg B escrow, leg A escrow, `EscrowSwap` PDA.
+    JoinSwap = 36,
+    /// Swaps both legs at once when both joined (each paid out to its recipient), or refunds leg A after the deadline.
+    /// Signed by either party. Each token leg carries its token accounts (as in `Complete`, the destination
+    /// being owned by whoever receives the leg) after `config`, leg A's first; leg B's are only expected when swapping.
+    /// Swapped SOL legs are charged the protocol fee: while it's set, the fee vault follows the token accounts.
+    /// Refunds are free of it.
+    /// Accounts: signer, leg A payer, leg A recipient, leg A escrow, leg B escrow, `EscrowSwap` PDA, config.
+    Settle = 37,
+    /// Reserves the SOL escrow's address (and id) for `amount`, paying only its rent, so UIs can show it right away.
+    /// It's activated by `Fund` until `reserved_until`, after which anyone can close it (`CloseExpiredReservation`).
+    /// Same accounts as `Initialize`.
+    Reserve { amount: u64, reserved_until: i64 } = 38,
+    /// Locks the reserved amount, turning the reservation into a regular escrow. Signed by the payer.
+    /// Accounts: payer, recipient, escrow, system program, config.
+    Fund = 39,
+    /// Permissionless. Closes an expired, never funded reservation, returning its rent to the payer.
+    /// Accounts: payer, recipient, escrow.
+    CloseExpiredReservation = 40,
+    /// Pays the recipient whatever has vested since their last withdrawal (less the protocol fee), closing the escrow
+    /// once it has fully vested (its rent going back to the payer). Signed by the recipient.
+    /// Accounts: recipient, payer, escrow, config, [fee vault].
+    WithdrawVested = 42,
+    /// `InitializeFromTemplate` with a custom schedule: the escrow locks the sum of the milestones' amounts.
+    /// Data: milestone count (1) + `amount` (8) & `deadline` (8) per milestone.
+    InitializeMilestones { milestones: Vec<Milestone> } = 43,
+    /// Completes two mirrored, unlinked escrows (A→B and B→A) at once, provided they still lock exactly `amount_a`
+    /// and `amount_b` (lamports, or tokens for token escrows). Signed by both parties, each releasing their own leg.
+    /// Token legs carry their token accounts after `config` (leg A's first, each destination owned by that leg's recipient),
+    /// followed by the fee vault while a protocol fee is set and a leg locks SOL.
+    /// Accounts: party A, party B, escrow A→B, escrow B→A, config.
+    SettleSwap { amount_a: u64, amount_b: u64 } = 44,
+    /// Lets the given SPL Governance realm recall the escrow until `veto_until` (`GovernanceVeto`), for DAO-funded grants.
+    /// Signed by the payer, before the recipient accepts. Can only be set once, on plain SOL escrows
+    /// (no milestones, shares or swap).
+    /// Accounts: payer, recipient, escrow, realm.
+    SetGovernanceVeto { veto_until: i64 } = 46,
+    /// Force-refunds the escrow to the payer. Signed by a governance of the escrow's `veto_realm`, i.e. executed by one of its
+    /// proposals, which must have passed.
+    /// Accounts: governance, proposal, payer, recipient, escrow, config.
+    GovernanceVeto = 47,
+    /// Admin only. Sets `Config::fee_bps` (at most `Config::MAX_FEE_BPS`), creating the fee vault PDA (seeds: `"fee_vault"`)
+    /// on first use. While it's set, every SOL payout (`Complete`, `Claim`, `CompleteUsd`, `ApproveMilestone`,
+    /// `WithdrawVested`, `WithdrawVestedShare`, `Settle`, `SettleSwap` and `BatchSettle`) expects the fee vault (writable):
+    /// in `Complete`s after the accounts of the escrow's optional features (assignee, shares, late fee) and before
+    /// the note, archive and memo accounts. Cancellations, refunds and `ResolveDispute` rulings are exempt.
+    /// Accounts: admin, config, fee vault, system program.
+    SetProtocolFee { fee_bps: u16 } = 48,
+    /// Admin only. Moves `amount` collected lamports out of the fee vault (which keeps its rent).
+    /// Accounts: admin, config, fee vault, destination.
+    WithdrawFees { amount: u64 } = 49,
+    /// Admin only. Sets the Solana Attestation Service credential (`Config::attestation_issuer`) whose attestations
+    /// `RequireAttestation` escrows accept, the default pubkey disabling them.
+    /// Accounts: admin, config.
+    SetAttestationIssuer { issuer: Pubkey } = 50,
+    /// Makes `Complete` and `Claim` require an unexpired attestation of the recipient (its subject being the recipient)
+    /// by `Config::attestation_issuer`, expected (read-only) right before the note, archive and memo accounts in `Complete`
+    /// and last in `Claim`. Signed by the payer, before the recipient accepts. Can't be undone.
+    /// Accounts: payer, recipient, escrow.
+    RequireAttestation = 51,
+    /// Admin only. Replaces the `Config`'s limits on the amount SOL escrows lock at initialization
+    /// (`max_amount` 0 meaning unlimited). Fees are set by `SetProtocolFee`, pause flags by `SetPaused`,
+    /// the admin is handed over through `NominateAdmin` & `AcceptAdmin`.
+    /// Accounts: admin, config.
+    UpdateConfig { min_amount: u64, max_amount: u64 } = 52,
+    /// Caps what a single `Complete` or `Claim` of a SOL escrow releases at `max_payout` lamports, so large escrows
+    /// are drawn down over several calls: each one short of the last only pays out the cap (late & protocol fees
+    /// included, charged per draw), leaving the escrow open. Only the final draw settles it, taking the note, archive
+    /// & memo accounts. Signed by the payer, before the recipient accepts. Not for escrows with claim shares or vesting.
+    /// Accounts: payer, recipient, escrow.
+    SetMaxPayout { max_payout: u64 } = 53,
+    /// Admin only. Nominates `admin` (`Config::pending_admin`) to take the `Config` over, which only happens once
+    /// the nominee signs `AcceptAdmin`, so a mistyped pubkey can't lock the admin out. The default pubkey
+    /// withdraws the nomination.
+    /// Accounts: admin, config.
+    NominateAdmin { admin: Pubkey } = 54,
+    /// Makes the signer, nominated by `NominateAdmin`, the `Config`'s admin.
+    /// Accounts: nominee, config.
+    AcceptAdmin = 55,
+    /// Permissionless. Re-emits the escrow's current state as a `Touched` event without changing it, so downstream
+    /// systems can re-sync after an outage without re-scanning accounts.
+    /// Accounts: escrow.
+    Touch = 57,
+    /// Permissionless. Lists the escrow in the `DeadlineBucket` of its deadline's day, created or grown at the signer's
+    /// expense. Fails for escrows without a deadline and escrows already listed there.
+    /// Accounts: signer, escrow, deadline bucket, system program.
+    IndexDeadline = 58,
+    /// Permissionless. Drops the given escrows from a `DeadlineBucket` once they're settled (closed) or their deadline
+    /// moved to another day, shrinking the bucket or closing it once empty. The freed rent goes to the signer.
+    /// Accounts: signer, deadline bucket, then the escrows to drop.
+    PruneDeadlines = 59,
+    /// Makes `Complete` and `Claim` fail in transactions with instructions of programs other than this one, the system,
+    /// compute budget & memo programs, so the settlement can't be sandwiched between other protocols' instructions.
+    /// The Instructions sysvar is expected (read-only) right after the attestation, before the note, archive and memo
+    /// accounts. Signed by the payer, before the recipient accepts. Can't be undone.
+    /// Accounts: payer, recipient, escrow.
+    RequireIsolatedSettlement = 60,
+    /// Admin only. Moves a SOL escrow out ahead of a layout migration or a re-deployment: records the hash of its data
+    /// in an `EscrowExport` PDA (seeds: `"export"`, escrow) and closes it, handing the escrowed lamports & rent to the
+    /// admin, who locks them again through `ImportState`. Escrows with a vault, milestones, claim shares or a swap
+    /// can't be exported.
+    /// Accounts: admin, config, escrow, export, system program.
+    ExportState = 61,
+    /// Admin only. Recreates an exported escrow (at its PDA of this program, in the namespace of `config`) from `data`,
+    /// the escrow's data as exported, which has to match the hash of the `EscrowExport` (owned by the program it was
+    /// exported from). The admin funds the escrowed lamports & the rent, and creates an import marker PDA
+    /// (seeds: `"import"`, exported escrow) so the escrow can't be imported twice.
+    /// Accounts: admin, config, export, import marker, escrow, system program.
+    ImportState { data: Vec<u8> } = 62,
+    /// Permissionless. Takes an escrow found inconsistent out of the normal flows instead of leaving it failing opaquely:
+    /// a foreign discriminator, a status that's never stored, more withdrawn than escrowed (see
+    /// `EscrowAccount::unpack_inconsistent`) or, for SOL escrows, lamports below the rent-exempt minimum. The escrow is
+    /// repacked as `Quarantined`, which every other flow rejects with `EscrowError::Quarantined`, and a `Quarantined`
+    /// event is emitted. SOL escrows are then recovered by the admin through `ExportState`. Fails for consistent escrows.
+    /// Accounts: escrow.
+    Quarantine = 63,
+    /// Pays the signing beneficiary of a team vesting escrow (tag 64 `Initialize`) what vested of its weight since its
+    /// last withdrawal, tracked in its `VestingShare` PDA (seeds: `"vesting_share"`, escrow, beneficiary). A share fully
+    /// withdrawn is closed, and the escrow (with its `EscrowAssignments`) once every share is, their rent going back
+    /// to the payer. Each withdrawal is charged the protocol fee.
+    /// Accounts: beneficiary, payer, recipient, escrow, config, assignments, vesting share, [fee vault].
+    WithdrawVestedShare = 65,
+    /// Admin only. Replaces `Config::features` with the given bitmask of `Config::FEATURE_*` families, so newer
+    /// instruction families can be rolled out cluster by cluster (e.g. on devnet first, on mainnet once tested).
+    /// Disabled families fail with `FeatureDisabled` wherever they start (escrows already using them still settle).
+    /// Accounts: admin, config.
+    SetFeatures { features: u8 } = 67,
+    /// Relayer-submitted `Complete` of many SOL escrows at once, each authorized by its payer signing
+    /// `authorization::settlement_message()` off-chain, verified by an ed25519 program instruction earlier in the
+    /// transaction (`authorization::ed25519_instruction()`). The relayer signs & pays for the transaction only.
+    /// Remaining accounts are `(payer, recipient, escrow)` triples, followed by the fee vault while a protocol fee is set.
+    /// Escrows settle as a plain `Complete` would without further accounts (so no token, late fee, payer-paid fee,
+    /// assigned or hash time-locked escrows). Late fee and payer-paid fee escrows, which charge the payer's wallet, fail
+    /// with `InvalidArgument`. The batch fails as a whole, relayers simulate it first.
+    BatchSettle = 68,
+    /// Admin only. Sets the stake pool idle fees are swept into (`Config::yield_venue`), which must be owned by one of
+    /// `treasury::YIELD_VENUE_PROGRAMS`, the default pubkey unsetting it. Fails while swept principal is outstanding.
+    /// Accounts: admin, config, stake pool (unless unsetting).
+    SetYieldVenue { venue: Pubkey } = 69,
+    /// Admin only. Deposits `amount` collected lamports of the fee vault (which keeps its rent) into the yield venue
+    /// through the treasury PDA (seeds: `"treasury"`), which receives the pool tokens in its token account of the pool
+    /// mint, adding them to `Config::swept_principal`.
+    /// Accounts: admin, config, fee vault, treasury, treasury's pool tokens, stake pool program, stake pool,
+    /// withdraw authority, reserve stake, manager fee account, pool mint, system program, token program.
+    SweepFees { amount: u64 } = 70,
+    /// Admin only. Redeems `pool_tokens` of the treasury from the yield venue back into the fee vault, where
+    /// `WithdrawFees` takes them out. The lamports received repay `Config::swept_principal` first, the rest adding to
+    /// `Config::realized_yield`. Redeeming the last pool tokens writes off whatever principal is left unpaid.
+    /// Accounts: admin, config, fee vault, treasury, treasury's pool tokens, stake pool program, stake pool,
+    /// withdraw authority, reserve stake, manager fee account, pool mint, clock sysvar, stake history sysvar,
+    /// stake program, system program, token program.
+    RedeemTreasury { pool_tokens: u64 } = 71,
+    /// Signed by the recipient of a completed escrow (or the assignee of its claim) once the payout arrived: stamps
+    /// `ArchivedEscrow::acknowledged_at` of the escrow's `record` in the payer's `EscrowArchive` and emits
+    /// `ReceiptAcknowledged`, the payer's on-chain delivery confirmation. The escrow being closed, `lamports` and `state`
+    /// are what it held when it was archived (its data in either layout), matched against the record's `state_hash`.
+    /// Only escrows archived on `Complete` can be acknowledged, once.
+    /// Accounts: recipient, payer, escrow, archive.
+    AcknowledgeReceipt { record: u32, lamports: u64, state: Vec<u8> } = 72,
+    /// Signed by the holder of the claim (the recipient, or the assignee): `Complete` then pays them into a new stake
+    /// account delegated to `vote` instead of their wallet (see `staking`), the default pubkey unsetting it. Only SOL
+    /// escrows settled in one go by `Complete` can be staked (no USD, milestone, team vesting, capped, swapped or SNS
+    /// resolved escrows), and `AssignClaim` unsets it, the new holder picking for themselves.
+    /// `Complete` then expects the stake PDA (seeds: `"stake"`, escrow id), the vote account, the clock, stake history &
+    /// rent sysvars, the stake config, stake & system programs right after the Instructions sysvar (if any), before
+    /// the note, archive and memo accounts. The stake account's rent-exempt reserve comes out of the payout.
+    /// Accounts: holder, payer, recipient, escrow, vote account (unless unsetting).
+    SetStakeSettlement { vote: Pubkey } = 73
 }
 
+/// First byte of the length-prefixed layout (`EXTENDED_TAG`, tag as u16, payload length as u16, payload), which
+/// instructions tagged from `DISCRIMINATOR_TAG` on are sent in, see `EscrowInstruction::split_tag`.
+pub const EXTENDED_TAG: u8 = u8::MAX;
+
+/// First byte of the discriminator layout (`DISCRIMINATOR_TAG`, 8-byte discriminator, payload), so a 1-byte tag
+/// followed by its payload can never be mistaken for a discriminator, see `EscrowInstruction::split_tag`.
+pub const DISCRIMINATOR_TAG: u8 = u8::MAX - 1;
+
+/// Anchor-style instruction discriminators (`sha256("global:<name>")[..8]`) by tag, accepted after `DISCRIMINATOR_TAG`
+/// in place of the 1-byte tag (see `with_discriminator`). Each name is the snake-case name of the instruction, tags
+/// sharing a variant being named after their builder.
+const DISCRIMINATORS: [[u8; 8]; 74] = [
+    [0xaf, 0xaf, 0x6d, 0x1f, 0x0d, 0x98, 0x9b, 0xed], // 0: initialize
+    [0x00, 0x4d, 0xe0, 0x93, 0x88, 0x19, 0x58, 0x4c], // 1: complete
+    [0x62, 0xa5, 0xc9, 0xb1, 0x6c, 0x41, 0xce, 0x60], // 2: close
+    [0xbf, 0x4c, 0x5e, 0x10, 0x84, 0xbc, 0xe5, 0x5f], // 3: close_note
+    [0xa9, 0xce, 0x39, 0x45, 0x83, 0x81, 0x0d, 0x42], // 4: batch_close
+    [0xec, 0x79, 0xa0, 0xc4, 0xa2, 0x23, 0xd5, 0x2d], // 5: register_callback
+    [0xd0, 0x7f, 0x15, 0x01, 0xc2, 0xbe, 0xc4, 0x46], // 6: initialize_config
+    [0x86, 0x10, 0xda, 0x91, 0x65, 0x70, 0x57, 0xef], // 7: block_mint
+    [0xad, 0x59, 0x82, 0xd0, 0x76, 0xd4, 0xda, 0xe0], // 8: unblock_mint
+    [0x45, 0x66, 0x07, 0xe6, 0xcb, 0x20, 0x65, 0x28], // 9: set_usd_terms
+    [0xa8, 0x9f, 0xf9, 0xfd, 0x4b, 0x69, 0x75, 0x40], // 10: complete_usd
+    [0x66, 0x96, 0x32, 0xf1, 0xe0, 0x44, 0x53, 0x3f], // 11: set_display_metadata
+    [0xf4, 0x2c, 0x08, 0xbc, 0x3e, 0x02, 0x91, 0x3c], // 12: close_display_metadata
+    [0x5b, 0x3c, 0x7d, 0xc0, 0xb0, 0xe1, 0xa6, 0xda], // 13: set_paused
+    [0x26, 0xd1, 0x96, 0x32, 0xbe, 0x75, 0x10, 0x36], // 14: initialize_token
+    [0xc8, 0x10, 0xda, 0x35, 0x1b, 0xa4, 0x99, 0x98], // 15: initialize_nft
+    [0xcc, 0x90, 0x40, 0xb0, 0x17, 0xa8, 0xb7, 0xab], // 16: initialize_from_template
+    [0x91, 0x55, 0x5c, 0x3c, 0x32, 0x82, 0xdb, 0x6a], // 17: approve_milestone
+    [0x41, 0xc7, 0x03, 0xc4, 0x46, 0x84, 0x45, 0x4a], // 18: initialize_with_deadline
+    [0xa5, 0x28, 0x96, 0xc6, 0xde, 0x3d, 0x3c, 0x93], // 19: set_late_fee
+    [0xc2, 0xab, 0x40, 0x64, 0x71, 0xf9, 0x81, 0xeb], // 20: set_unlock_at
+    [0xa6, 0xdb, 0x58, 0x04, 0xd5, 0x4c, 0x86, 0x80], // 21: assign_claim
+    [0x3e, 0xc6, 0xd6, 0xc1, 0xd5, 0x9f, 0x6c, 0xd2], // 22: claim
+    [0x41, 0x96, 0x46, 0xd8, 0x85, 0x06, 0x6b, 0x04], // 23: accept
+    [0xa2, 0xff, 0xd6, 0x61, 0x03, 0x56, 0x95, 0x4c], // 24: cancel_mutual
+    [0x46, 0x4f, 0xe2, 0xf2, 0x3a, 0x46, 0xf5, 0xbe], // 25: assign_claim_share
+    [0x29, 0xf3, 0x01, 0x33, 0x96, 0x5f, 0xf6, 0x49], // 26: raise_dispute
+    [0xe7, 0x06, 0xca, 0x06, 0x60, 0x67, 0x0c, 0xe6], // 27: resolve_dispute
+    [0x95, 0xaa, 0xdc, 0xc3, 0xdf, 0x22, 0x33, 0xc0], // 28: set_depeg_guard
+    [0x56, 0xca, 0xc6, 0x6b, 0x3c, 0x89, 0x61, 0x69], // 29: override_depeg_freeze
+    [0x3d, 0x18, 0xc5, 0xb4, 0xc3, 0xa9, 0x8a, 0x69], // 30: reconcile
+    [0xa0, 0x9c, 0x41, 0x0a, 0x7f, 0x8a, 0xf1, 0xfe], // 31: initialize_with_reference
+    [0xf2, 0x23, 0xc6, 0x89, 0x52, 0xe1, 0xf2, 0xb6], // 32: deposit
+    [0x5d, 0xa6, 0xa7, 0x2b, 0x45, 0x6a, 0x72, 0x38], // 33: initialize_hashlocked
+    [0x9b, 0x34, 0x84, 0xb4, 0xac, 0x4e, 0x21, 0x27], // 34: complete_with_preimage
+    [0x6d, 0x6d, 0x15, 0x84, 0xc9, 0x4c, 0x43, 0x71], // 35: open_swap
+    [0x1b, 0xc1, 0xf4, 0x38, 0x37, 0x51, 0x45, 0xfb], // 36: join_swap
+    [0xaf, 0x2a, 0xb9, 0x57, 0x90, 0x83, 0x66, 0xd4], // 37: settle
+    [0x5c, 0x63, 0xf4, 0xd1, 0x1c, 0x41, 0xd5, 0x9d], // 38: reserve
+    [0xda, 0xbc, 0x6f, 0xdd, 0x98, 0x71, 0xae, 0x07], // 39: fund
+    [0xb1, 0xb4, 0x23, 0x2d, 0x01, 0x14, 0x41, 0xd2], // 40: close_expired_reservation
+    [0x05, 0x1d, 0xf5, 0xed, 0x32, 0xf2, 0x23, 0x0d], // 41: initialize_vesting
+    [0x68, 0xbc, 0x34, 0xc2, 0x23, 0xea, 0x5f, 0x95], // 42: withdraw_vested
+    [0x89, 0xc3, 0x40, 0x57, 0xfb, 0xb6, 0xf9, 0x2c], // 43: initialize_milestones
+    [0x03, 0x82, 0x85, 0xb4, 0xfb, 0x57, 0xf2, 0xfa], // 44: settle_swap
+    [0x35, 0x11, 0x5c, 0x09, 0x54, 0x97, 0xad, 0x4e], // 45: initialize_split
+    [0xac, 0xa9, 0xb0, 0x51, 0x67, 0xdc, 0x88, 0xc4], // 46: set_governance_veto
+    [0xc0, 0xf8, 0xe6, 0x18, 0x31, 0x9e, 0x6b, 0xbb], // 47: governance_veto
+    [0xad, 0xef, 0x53, 0xf2, 0x88, 0x2b, 0x90, 0xd9], // 48: set_protocol_fee
+    [0xc6, 0xd4, 0xab, 0x6d, 0x90, 0xd7, 0xae, 0x59], // 49: withdraw_fees
+    [0xd3, 0xad, 0xc3, 0x74, 0xe3, 0x83, 0xa5, 0x38], // 50: set_attestation_issuer
+    [0x7b, 0x85, 0x8a, 0xbf, 0xca, 0x07, 0x1e, 0x92], // 51: require_attestation
+    [0x1d, 0x9e, 0xfc, 0xbf, 0x0a, 0x53, 0xdb, 0x63], // 52: update_config
+    [0xb4, 0xf0, 0x7d, 0x11, 0x64, 0x94, 0x43, 0xb3], // 53: set_max_payout
+    [0x86, 0x0b, 0x1f, 0xf4, 0x14, 0x4d, 0x8a, 0x79], // 54: nominate_admin
+    [0x70, 0x2a, 0x2d, 0x5a, 0x74, 0xb5, 0x0d, 0xaa], // 55: accept_admin
+    [0x57, 0x83, 0x9c, 0x22, 0xfc, 0xc3, 0x2e, 0x80], // 56: initialize_payer_pays_fee
+    [0x50, 0x9d, 0x6a, 0xdc, 0xb5, 0x48, 0x65, 0x51], // 57: touch
+    [0xf0, 0xf8, 0x17, 0x30, 0xda, 0x79, 0x46, 0x6a], // 58: index_deadline
+    [0x47, 0xc3, 0x2c, 0x62, 0xea, 0x8b, 0x6d, 0x2b], // 59: prune_deadlines
+    [0xbf, 0x4c, 0xe6, 0x03, 0x3d, 0x80, 0xea, 0xfc], // 60: require_isolated_settlement
+    [0x7c, 0x9e, 0xa8, 0xe0, 0x3a, 0x12, 0x40, 0x60], // 61: export_state
+    [0xc7, 0x2c, 0x06, 0x67, 0x8b, 0x83, 0x9f, 0xec], // 62: import_state
+    [0xd5, 0x32, 0xc0, 0xdc, 0xf8, 0x81, 0x39, 0x43], // 63: quarantine
+    [0xf7, 0x4d, 0x41, 0x35, 0xbf, 0x07, 0x8d, 0xc3], // 64: initialize_team_vesting
+    [0x9b, 0x1e, 0x61, 0x61, 0x94, 0x12, 0x9b, 0x74], // 65: withdraw_vested_share
+    [0x33, 0xc0, 0x8c, 0x4d, 0xcd, 0x41, 0x43, 0x1e], // 66: initialize_to_domain
+    [0x9c, 0xef, 0x5a, 0xec, 0x02, 0x2d, 0x29, 0xcb], // 67: set_features
+    [0xb0, 0xa0, 0x2c, 0x54, 0x44, 0xd3, 0xc9, 0xda], // 68: batch_settle
+    [0x4a, 0xb8, 0xac, 0x9c, 0xa0, 0x63, 0x91, 0x1f], // 69: set_yield_venue
+    [0xaf, 0xe1, 0x62, 0x47, 0x76, 0x42, 0x22, 0x94], // 70: sweep_fees
+    [0x10, 0x97, 0x98, 0xe0, 0x1b, 0x5b, 0xb9, 0xcd], // 71: redeem_treasury
+    [0x0d, 0xe1, 0xe4, 0x51, 0x17, 0x8a, 0xc9, 0xb1], // 72: acknowledge_receipt
+    [0x81, 0x90, 0xa4, 0xa2, 0x1d, 0x04, 0xdf, 0xa9]  // 73: set_stake_settlement
+];
+
 impl EscrowInstruction {
+    /// Data starts with the 1-byte tag, the discriminator header or the length-prefixed header of the instruction
+    /// (see `split_tag`), followed by its fields. Data ending before the last field fails with `InstructionDataTooShort`,
+    /// bytes left after it with `InstructionDataTooLong`, except for the variable-length fields ending the data
+    /// (notes, `ImportState`'s data...).
     pub fn unpack(data: &[u8]) -> Result<Self, ProgramError> {
-        let (instr_type, rest) = data.split_at(1);
-        
-        Ok(match instr_type[0] {
-            0 => {
-                let amount: u64 = u64::from_le_bytes(
-                    rest.try_into().map_err(|_| ProgramError::InvalidInstructionData)?
-                );
-                Self::Initialize { amount }
-            },
-            1 => EscrowInstruction::Complete,
+        let (tag, payload): (u16, &[u8]) = Self::split_tag(data)?;
+        let mut payload: Payload = Payload { data: payload };
+
+        let instruction: Self = match tag {
+            0 => Self::Initialize {
+                amount: payload.u64()?,
+                deadline: None,
+                reference: None,
+                hashlock: None,
+                vesting: None,
+                splits: None,
+                payer_pays_fee: false,
+                domain: None,
+                note: payload.note()
+            },
+            1 => Self::Complete { preimage: None, note: payload.note() },
             2 => EscrowInstruction::Close,
-            _ => return Err(ProgramError::InvalidInstructionData)
-        })
-    } 
-}
\ No newline at end of file
+            3 => Self::CloseNote {
+                mint: payload.optional_bytes()?.map(Pubkey::new_from_array).filter(|mint| mint != &Pubkey::default())
+            },
+            4 => EscrowInstruction::BatchClose,
+            5 => Self::RegisterCallback { callback: payload.bytes()? },
+            6 => Self::InitializeConfig {
+                namespace: payload.optional_bytes()?.map(Pubkey::new_from_array).unwrap_or_default()
+            },
+            7 => EscrowInstruction::BlockMint,
+            8 => EscrowInstruction::UnblockMint,
+            9 => Self::SetUsdTerms {
+                usd_amount: payload.u64()?,
+                max_divergence_bps: payload.optional_bytes()?.map(u16::from_le_bytes).filter(|bps| *bps != 0)
+            },
+            10 => Self::CompleteUsd {
+                min_amount: payload.u64()?,
+                max_amount: payload.u64()?
+            },
+            11 => {
+                let icon_hash: [u8; 32] = payload.bytes()?;
+                Self::SetDisplayMetadata {
+                    label: String::from_utf8(payload.rest().to_vec()).map_err(|_| ProgramError::InvalidInstructionData)?,
+                    icon_hash
+                }
+            },
+            12 => EscrowInstruction::CloseDisplayMetadata,
+            13 => Self::SetPaused { paused: payload.u8()? },
+            14 => Self::InitializeToken {
+                amount: payload.u64()?,
+                reference: payload.optional_bytes()?
+            },
+            15 => Self::InitializeNft { reference: payload.optional_bytes()? },
+            16 => Self::InitializeFromTemplate {
+                amount: payload.u64()?,
+                template: MilestoneTemplate::try_from(payload.u8()?)?
+            },
+            17 => Self::ApproveMilestone { index: payload.u8()? },
+            18 => Self::Initialize {
+                amount: payload.u64()?,
+                deadline: Some(payload.i64()?),
+                reference: None,
+                hashlock: None,
+                vesting: None,
+                splits: None,
+                payer_pays_fee: false,
+                domain: None,
+                note: payload.note()
+            },
+            19 => Self::SetLateFee {
+                bps_per_day: payload.u16()?,
+                cap_bps: payload.u16()?
+            },
+            20 => Self::SetUnlockAt { unlock_at: payload.i64()? },
+            21 => EscrowInstruction::AssignClaim,
+            22 => Self::Claim { preimage: payload.optional_bytes()? },
+            23 => EscrowInstruction::Accept,
+            24 => Self::CancelMutual {
+                payer_bps: payload.u16()?,
+                recipient_bps: payload.u16()?
+            },
+            25 => Self::AssignClaimShare {
+                bps: payload.u16()?,
+                from_payout: match payload.optional_bytes()? {
+                    None | Some([0]) => false,
+                    Some([1]) => true,
+                    Some(_) => return Err(ProgramError::InvalidInstructionData)
+                }
+            },
+            26 => EscrowInstruction::RaiseDispute,
+            27 => Self::ResolveDispute {
+                payer_bps: payload.u16()?,
+                recipient_bps: payload.u16()?
+            },
+            28 => Self::SetDepegGuard { max_depeg_bps: payload.u16()? },
+            29 => EscrowInstruction::OverrideDepegFreeze,
+            30 => EscrowInstruction::Reconcile,
+            31 => {
+                let (amount, deadline): (u64, i64) = (payload.u64()?, payload.i64()?);
+                Self::Initialize {
+                    amount,
+                    deadline: if deadline == 0 { None } else { Some(deadline) },
+                    reference: Some(payload.bytes()?),
+                    hashlock: None,
+                    vesting: None,
+                    splits: None,
+                    payer_pays_fee: false,
+                    domain: None,
+                    note: payload.note()
+                }
+            },
+            32 => Self::Deposit { amount: payload.u64()? },
+            33 => {
+                let (amount, deadline): (u64, i64) = (payload.u64()?, payload.i64()?);
+
+                // the deadline is what lets the payer get a refund if the secret is never revealed
+                if deadline == 0 {
+                    return Err(ProgramError::InvalidInstructionData);
+                }
+
+                Self::Initialize {
+                    amount,
+                    deadline: Some(deadline),
+                    reference: None,
+                    hashlock: Some(payload.bytes()?),
+                    vesting: None,
+                    splits: None,
+                    payer_pays_fee: false,
+                    domain: None,
+                    note: payload.note()
+                }
+            },
+            34 => Self::Complete {
+                preimage: Some(payload.bytes()?),
+                note: payload.note()
+            },
+            35 => Self::OpenSwap { deadline: payload.i64()? },
+            36 => EscrowInstruction::JoinSwap,
+            37 => EscrowInstruction::Settle,
+            38 => Self::Reserve {
+                amount: payload.u64()?,
+                reserved_until: payload.i64()?
+            },
+            39 => EscrowInstruction::Fund,
+            40 => EscrowInstruction::CloseExpiredReservation,
+            41 => {
+                let (amount, start, end): (u64, i64, i64) = (payload.u64()?, payload.i64()?, payload.i64()?);
+
+                if start >= end {
+                    return Err(ProgramError::InvalidInstructionData);
+                }
+
+                Self::Initialize {
+                    amount,
+                    deadline: None,
+                    reference: None,
+                    hashlock: None,
+                    vesting: Some((start, end)),
+                    splits: None,
+                    payer_pays_fee: false,
+                    domain: None,
+                    note: payload.note()
+                }
+            },
+            42 => EscrowInstruction::WithdrawVested,
+            43 => {
+                let count: u8 = payload.u8()?;
+                let milestones: Vec<Milestone> = (0..count)
+                    .map(|_| Ok(Milestone { amount: payload.u64()?, deadline: payload.i64()?, released: false }))
+                    .collect::<Result<_, ProgramError>>()?;
+
+                Self::InitializeMilestones { milestones }
+            },
+            44 => Self::SettleSwap {
+                amount_a: payload.u64()?,
+                amount_b: payload.u64()?
+            },
+            45 => Self::Initialize {
+                amount: payload.u64()?,
+                deadline: None,
+                reference: None,
+                hashlock: None,
+                vesting: None,
+                splits: Some(payload.claim_shares()?),
+                payer_pays_fee: false,
+                domain: None,
+                note: payload.note()
+            },
+            46 => Self::SetGovernanceVeto { veto_until: payload.i64()? },
+            47 => EscrowInstruction::GovernanceVeto,
+            48 => Self::SetProtocolFee { fee_bps: payload.u16()? },
+            49 => Self::WithdrawFees { amount: payload.u64()? },
+            50 => Self::SetAttestationIssuer { issuer: payload.pubkey()? },
+            51 => EscrowInstruction::RequireAttestation,
+            52 => Self::UpdateConfig { min_amount: payload.u64()?, max_amount: payload.u64()? },
+            53 => Self::SetMaxPayout { max_payout: payload.u64()? },
+            54 => Self::NominateAdmin { admin: payload.pubkey()? },
+            55 => EscrowInstruction::AcceptAdmin,
+            56 => Self::Initialize {
+                amount: payload.u64()?,
+                deadline: None,
+                reference: None,
+                hashlock: None,
+                vesting: None,
+                splits: None,
+                payer_pays_fee: true,
+                domain: None,
+                note: payload.note()
+            },
+            57 => EscrowInstruction::Touch,
+            58 => EscrowInstruction::IndexDeadline,
+            59 => EscrowInstruction::PruneDeadlines,
+            60 => EscrowInstruction::RequireIsolatedSettlement,
+            61 => EscrowInstruction::ExportState,
+            62 => Self::ImportState { data: payload.rest().to_vec() },
+            63 => EscrowInstruction::Quarantine,
+            64 => {
+                let (amount, start, end): (u64, i64, i64) = (payload.u64()?, payload.i64()?, payload.i64()?);
+                let splits: Vec<ClaimShare> = payload.claim_shares()?;
+
+                if start >= end {
+                    return Err(ProgramError::InvalidInstructionData);
+                }
+
+                Self::Initialize {
+                    amount,
+                    deadline: None,
+                    reference: None,
+                    hashlock: None,
+                    vesting: Some((start, end)),
+                    splits: Some(splits),
+                    payer_pays_fee: false,
+                    domain: None,
+                    note: payload.note()
+                }
+            },
+            65 => EscrowInstruction::WithdrawVestedShare,
+            66 => Self::Initialize {
+                amount: payload.u64()?,
+                deadline: None,
+                reference: None,
+                hashlock: None,
+                vesting: None,
+                splits: None,
+                payer_pays_fee: false,
+                domain: Some(RecipientResolution::try_from(payload.u8()?)?),
+                note: payload.note()
+            },
+            67 => Self::SetFeatures { features: payload.u8()? },
+            68 => Self::BatchSettle,
+            69 => Self::SetYieldVenue { venue: payload.pubkey()? },
+            70 => Self::SweepFees { amount: payload.u64()? },
+            71 => Self::RedeemTreasury { pool_tokens: payload.u64()? },
+            72 => Self::AcknowledgeReceipt {
+                record: u32::from_le_bytes(payload.bytes()?),
+                lamports: payload.u64()?,
+                state: payload.rest().to_vec()
+            },
+            73 => Self::SetStakeSettlement { vote: payload.pubkey()? },
+            _ => return Err(EscrowError::UnknownInstruction.into())
+        };
+
+        payload.finish()?;
+        Ok(instruction)
+    }
+
+    /// Splits `data` into the instruction's tag & payload. The tag is either:
+    /// - the first byte, for tags below `DISCRIMINATOR_TAG`
+    /// - after `DISCRIMINATOR_TAG`, the instruction's 8-byte discriminator (see `with_discriminator`)
+    /// - or, after `EXTENDED_TAG`, a u16 followed by the payload's length as a u16 (see `with_length_prefix`).
+    ///   Every tag can be sent that way, the ones from `DISCRIMINATOR_TAG` on only that way. The length tells where the
+    ///   payload ends without knowing the instruction, so parsers can skip the ones they don't know.
+    pub fn split_tag(data: &[u8]) -> Result<(u16, &[u8]), ProgramError> {
+        match data.split_first() {
+            None => Err(EscrowError::InstructionDataTooShort.into()),
+            Some((&DISCRIMINATOR_TAG, header)) => {
+                let mut header: Payload = Payload { data: header };
+                let discriminator: [u8; 8] = header.bytes()?;
+                let tag: usize = DISCRIMINATORS.iter()
+                    .position(|known| known == &discriminator)
+                    .ok_or(EscrowError::UnknownInstruction)?;
+
+                Ok((tag as u16, header.rest()))
+            },
+            Some((&EXTENDED_TAG, header)) => {
+                let mut header: Payload = Payload { data: header };
+                let (tag, len): (u16, u16) = (header.u16()?, header.u16()?);
+                let payload: &[u8] = header.rest();
+
+                match payload.len().cmp(&(len as usize)) {
+                    Ordering::Less => Err(EscrowError::InstructionDataTooShort.into()),
+                    Ordering::Greater => Err(EscrowError::InstructionDataTooLong.into()),
+                    Ordering::Equal => Ok((tag, payload))
+                }
+            },
+            Some((&tag, payload)) => Ok((tag as u16, payload))
+        }
+    }
+}
+
+/// Reader of an instruction's payload, field by field, failing with `InstructionDataTooShort` past its end.
+struct Payload<'a> {
+    data: &'a [u8]
+}
+
+impl<'a> Payload<'a> {
+    fn bytes<const N: usize>(&mut self) -> Result<[u8; N], ProgramError> {
+        let (bytes, rest) = self.data.split_first_chunk::<N>().ok_or(EscrowError::InstructionDataTooShort)?;
+        self.data = rest;
+        Ok(*bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, ProgramError> {
+        self.bytes().map(u8::from_le_bytes)
+    }
+
+    fn u16(&mut self) -> Result<u16, ProgramError> {
+        self.bytes().map(u16::from_le_bytes)
+    }
+
+    fn u64(&mut self) -> Result<u64, ProgramError> {
+        self.bytes().map(u64::from_le_bytes)
+    }
+
+    fn i64(&mut self) -> Result<i64, ProgramError> {
+        self.bytes().map(i64::from_le_bytes)
+    }
+
+    fn pubkey(&mut self) -> Result<Pubkey, ProgramError> {
+        self.bytes().map(Pubkey::new_from_array)
+    }
+
+    /// Fixed-size last field that may be left out entirely.
+    fn optional_bytes<const N: usize>(&mut self) -> Result<Option<[u8; N]>, ProgramError> {
+        match self.data {
+            [] => Ok(None),
+            _ => self.bytes().map(Some)
+        }
+    }
+
+    /// Count-prefixed `ClaimShare`s, e.g. the splits of `Initialize`.
+    fn claim_shares(&mut self) -> Result<Vec<ClaimShare>, ProgramError> {
+        let count: u8 = self.u8()?;
+
+        (0..count)
+            .map(|_| Ok(ClaimShare { assignee: self.pubkey()?, bps: self.u16()? }))
+            .collect()
+    }
+
+    /// The rest of the payload, as a variable-length last field.
+    fn rest(&mut self) -> &'a [u8] {
+        std::mem::take(&mut self.data)
+    }
+
+    fn note(&mut self) -> Option<Vec<u8>> {
+        match self.rest() {
+            [] => None,
+            note => Some(note.to_vec())
+        }
+    }
+
+    /// Checks that the last field was read, with nothing left after it.
+    fn finish(self) -> Result<(), ProgramError> {
+        match self.data {
+            [] => Ok(()),
+            _ => Err(EscrowError::InstructionDataTooLong.into())
+        }
+    }
+}
+
+/// Client-side constructors of the everyday instructions: data and accounts in the order the program expects them.
+/// The module level builders below cover the rest.
+impl EscrowInstruction {
+    /// See [`initialize`].
+    pub fn initialize(payer_pkey: &Pubkey, recipient_pkey: &Pubkey, amount: u64) -> Instruction {
+        initialize(payer_pkey, recipient_pkey, amount)
+    }
+
+    /// See [`complete`].
+    pub fn complete(payer_pkey: &Pubkey, recipient_pkey: &Pubkey) -> Instruction {
+        complete(payer_pkey, recipient_pkey)
+    }
+
+    /// See [`close`].
+    pub fn close(payer_pkey: &Pubkey, recipient_pkey: &Pubkey) -> Instruction {
+        close(payer_pkey, recipient_pkey)
+    }
+}
+
+
+/// Builds a SOL `Initialize` without a note.
+pub fn initialize(payer_pkey: &Pubkey, recipient_pkey: &Pubkey, amount: u64) -> Instruction {
+    let mut data: Vec<u8> = Vec::with_capacity(9);
+    data.push(0);
+    data.extend_from_slice(&amount.to_le_bytes());
+
+    Instruction::new_with_bytes(
+        crate::ID,
+        &data,
+        InitializeAccounts {
+            payer: *payer_pkey,
+            recipient: *recipient_pkey,
+            escrow: find_escrow_pda(payer_pkey, recipient_pkey),
+            system_program: system_program::ID,
+            counter: Pubkey::find_program_address(&[crate::get_counter_seeds()], &crate::ID).0,
+            config: find_config_pda()
+        }.to_account_metas()
+    )
+}
+
+/// Builds a SOL `Complete` without a note, archive or memo.
+pub fn complete(payer_pkey: &Pubkey, recipient_pkey: &Pubkey) -> Instruction {
+    Instruction::new_with_bytes(
+        crate::ID,
+        &[1],
+        CompleteAccounts {
+            payer: *payer_pkey,
+            recipient: *recipient_pkey,
+            escrow: find_escrow_pda(payer_pkey, recipient_pkey),
+            config: find_config_pda()
+        }.to_account_metas()
+    )
+}
+
+/// Builds a SOL `Claim` of an escrow whose claim wasn't assigned, signed by the recipient.
+pub fn claim(recipient_pkey: &Pubkey, payer_pkey: &Pubkey) -> Instruction {
+    Instruction::new_with_bytes(
+        crate::ID,
+        &[22],
+        ClaimAccounts {
+            recipient: *recipient_pkey,
+            payer: *payer_pkey,
+            escrow: find_escrow_pda(payer_pkey, recipient_pkey),
+            config: find_config_pda()
+        }.to_account_metas()
+    )
+}
+
+/// Builds a SOL `Close` without an archive.
+pub fn close(payer_pkey: &Pubkey, recipient_pkey: &Pubkey) -> Instruction {
+    Instruction::new_with_bytes(
+        crate::ID,
+        &[2],
+        CloseAccounts {
+            payer: *payer_pkey,
+            recipient: *recipient_pkey,
+            escrow: find_escrow_pda(payer_pkey, recipient_pkey),
+            config: find_config_pda()
+        }.to_account_metas()
+    )
+}
+
+/// Builds a `Touch` of any escrow, SOL or token, signed by whoever pays the transaction fee.
+pub fn touch(escrow_pkey: &Pubkey) -> Instruction {
+    Instruction::new_with_bytes(crate::ID, &[57], TouchAccounts { escrow: *escrow_pkey }.to_account_metas())
+}
+
+/// Builds a `Quarantine` of any escrow, signed by whoever pays the transaction fee.
+pub fn quarantine(escrow_pkey: &Pubkey) -> Instruction {
+    Instruction::new_with_bytes(crate::ID, &[63], QuarantineAccounts { escrow: *escrow_pkey }.to_account_metas())
+}
+
+/// Builds an `IndexDeadline` of the escrow at `escrow_pkey`, whose deadline is `deadline`.
+pub fn index_deadline(signer_pkey: &Pubkey, escrow_pkey: &Pubkey, deadline: i64) -> Instruction {
+    Instruction::new_with_bytes(
+        crate::ID,
+        &[58],
+        IndexDeadlineAccounts {
+            signer: *signer_pkey,
+            escrow: *escrow_pkey,
+            deadline_bucket: find_deadline_bucket_pda(DeadlineBucket::day_of(deadline)),
+            system_program: system_program::ID
+        }.to_account_metas()
+    )
+}
+
+/// Builds an `ExportState` of the SOL escrow at `escrow_pkey`, in the global namespace.
+pub fn export_state(admin_pkey: &Pubkey, escrow_pkey: &Pubkey) -> Instruction {
+    let (seed1, seed2) = crate::get_export_seeds(escrow_pkey);
+
+    Instruction::new_with_bytes(
+        crate::ID,
+        &[61],
+        ExportStateAccounts {
+            admin: *admin_pkey,
+            config: find_config_pda(),
+            escrow: *escrow_pkey,
+            export: Pubkey::find_program_address(&[seed1, seed2], &crate::ID).0,
+            system_program: system_program::ID
+        }.to_account_metas()
+    )
+}
+
+/// Builds an `AcknowledgeReceipt` of the escrow at `escrow_pkey`, archived as `record` of the payer's archive
+/// holding `lamports` and `state` (e.g. as an indexer last saw it, before `Complete`).
+pub fn acknowledge_receipt(
+    recipient_pkey: &Pubkey,
+    payer_pkey: &Pubkey,
+    escrow_pkey: &Pubkey,
+    record: u32,
+    lamports: u64,
+    state: &[u8]
+) -> Instruction {
+    let (seed1, seed2) = crate::get_archive_seeds(payer_pkey);
+
+    let mut data: Vec<u8> = Vec::with_capacity(13 + state.len());
+    data.push(72);
+    data.extend_from_slice(&record.to_le_bytes());
+    data.extend_from_slice(&lamports.to_le_bytes());
+    data.extend_from_slice(state);
+
+    Instruction::new_with_bytes(
+        crate::ID,
+        &data,
+        AcknowledgeReceiptAccounts {
+            recipient: *recipient_pkey,
+            payer: *payer_pkey,
+            escrow: *escrow_pkey,
+            archive: Pubkey::find_program_address(&[seed1, seed2], &crate::ID).0
+        }.to_account_metas()
+    )
+}
+
+/// Builds a `SetStakeSettlement` of the escrow between `payer_pkey` and `recipient_pkey` to `vote_pkey`, signed by
+/// `holder_pkey` (the recipient, or the assignee), `Pubkey::default()` unsetting it.
+pub fn set_stake_settlement(holder_pkey: &Pubkey, payer_pkey: &Pubkey, recipient_pkey: &Pubkey, vote_pkey: &Pubkey) -> Instruction {
+    let mut accounts: Vec<AccountMeta> = SetStakeSettlementAccounts {
+        holder: *holder_pkey,
+        payer: *payer_pkey,
+        recipient: *recipient_pkey,
+        escrow: find_escrow_pda(payer_pkey, recipient_pkey)
+    }.to_account_metas();
+
+    if vote_pkey != &Pubkey::default() {
+        accounts.push(AccountMeta::new_readonly(*vote_pkey, false));
+    }
+
+    let mut data: Vec<u8> = Vec::with_capacity(33);
+    data.push(73);
+    data.extend_from_slice(vote_pkey.as_ref());
+
+    Instruction::new_with_bytes(crate::ID, &data, accounts)
+}
+
+/// Builds a `Complete` of a SOL escrow whose claim wasn't assigned, paying the recipient into the stake PDA of the
+/// escrow `escrow_id`, delegated to `vote_pkey` (as set by `SetStakeSettlement`).
+pub fn complete_to_stake(payer_pkey: &Pubkey, recipient_pkey: &Pubkey, escrow_id: u64, vote_pkey: &Pubkey) -> Instruction {
+    let mut ix: Instruction = complete(payer_pkey, recipient_pkey);
+    ix.accounts.extend([
+        AccountMeta::new(find_stake_pda(escrow_id), false),
+        AccountMeta::new_readonly(*vote_pkey, false),
+        AccountMeta::new_readonly(solana_program::sysvar::clock::ID, false),
+        AccountMeta::new_readonly(solana_program::sysvar::stake_history::ID, false),
+        AccountMeta::new_readonly(solana_program::sysvar::rent::ID, false),
+        AccountMeta::new_readonly(crate::staking::STAKE_CONFIG_ID, false),
+        AccountMeta::new_readonly(crate::staking::STAKE_PROGRAM_ID, false),
+        AccountMeta::new_readonly(system_program::ID, false)
+    ]);
+    ix
+}
+
+/// Builds an `ImportState` of `data`, exported by `source_program_id` (this program for layout migrations) from the escrow
+/// at `exported_escrow_pkey`, into the global namespace. `payer_pkey` & `recipient_pkey` are the parties stored in `data`.
+pub fn import_state(
+    admin_pkey: &Pubkey,
+    source_program_id: &Pubkey,
+    exported_escrow_pkey: &Pubkey,
+    payer_pkey: &Pubkey,
+    recipient_pkey: &Pubkey,
+    data: &[u8]
+) -> Instruction {
+    let (export_seed1, export_seed2) = crate::get_export_seeds(exported_escrow_pkey);
+    let (import_seed1, import_seed2) = crate::get_import_seeds(exported_escrow_pkey);
+
+    let mut ix_data: Vec<u8> = Vec::with_capacity(1 + data.len());
+    ix_data.push(62);
+    ix_data.extend_from_slice(data);
+
+    Instruction::new_with_bytes(
+        crate::ID,
+        &ix_data,
+        ImportStateAccounts {
+            admin: *admin_pkey,
+            config: find_config_pda(),
+            export: Pubkey::find_program_address(&[export_seed1, export_seed2], source_program_id).0,
+            import_marker: Pubkey::find_program_address(&[import_seed1, import_seed2], &crate::ID).0,
+            escrow: find_escrow_pda(payer_pkey, recipient_pkey),
+            system_program: system_program::ID
+        }.to_account_metas()
+    )
+}
+
+/// Builds a `PruneDeadlines` dropping `escrow_pkeys` from the bucket of `day` (see `DeadlineBucket::day_of`).
+pub fn prune_deadlines(signer_pkey: &Pubkey, day: i64, escrow_pkeys: &[Pubkey]) -> Instruction {
+    let mut accounts: Vec<AccountMeta> = vec![
+        AccountMeta::new(*signer_pkey, true),
+        AccountMeta::new(find_deadline_bucket_pda(day), false)
+    ];
+    accounts.extend(escrow_pkeys.iter().map(|escrow_pkey| AccountMeta::new_readonly(*escrow_pkey, false)));
+
+    Instruction::new_with_bytes(crate::ID, &[59], accounts)
+}
+
+/// Builds a `Deposit` topping up a SOL escrow with `amount` lamports.
+pub fn deposit(payer_pkey: &Pubkey, recipient_pkey: &Pubkey, amount: u64) -> Instruction {
+    let mut data: Vec<u8> = Vec::with_capacity(9);
+    data.push(32);
+    data.extend_from_slice(&amount.to_le_bytes());
+
+    Instruction::new_with_bytes(
+        crate::ID,
+        &data,
+        DepositAccounts {
+            payer: *payer_pkey,
+            recipient: *recipient_pkey,
+            escrow: find_escrow_pda(payer_pkey, recipient_pkey),
+            system_program: system_program::ID
+        }.to_account_metas()
+    )
+}
+
+/// Builds a `Reserve` of `amount`, to be funded before `reserved_until`.
+pub fn reserve(payer_pkey: &Pubkey, recipient_pkey: &Pubkey, amount: u64, reserved_until: i64) -> Instruction {
+    let mut ix: Instruction = initialize(payer_pkey, recipient_pkey, amount);
+    ix.data[0] = 38;
+    ix.data.extend_from_slice(&reserved_until.to_le_bytes());
+    ix
+}
+
+pub fn fund(payer_pkey: &Pubkey, recipient_pkey: &Pubkey) -> Instruction {
+    Instruction::new_with_bytes(
+        crate::ID,
+        &[39],
+        FundAccounts {
+            payer: *payer_pkey,
+            recipient: *recipient_pkey,
+            escrow: find_escrow_pda(payer_pkey, recipient_pkey),
+            system_program: system_program::ID,
+            config: find_config_pda()
+        }.to_account_metas()
+    )
+}
+
+/// Builds a `CloseExpiredReservation`, which anyone may send (the rent still goes to the payer).
+pub fn close_expired_reservation(payer_pkey: &Pubkey, recipient_pkey: &Pubkey) -> Instruction {
+    Instruction::new_with_bytes(
+        crate::ID,
+        &[40],
+        CloseExpiredReservationAccounts {
+            payer: *payer_pkey,
+            recipient: *recipient_pkey,
+            escrow: find_escrow_pda(payer_pkey, recipient_pkey)
+        }.to_account_metas()
+    )
+}
+
+/// Builds a vesting `Initialize` of `amount`, vesting linearly from `start` to `end`.
+pub fn initialize_vesting(payer_pkey: &Pubkey, recipient_pkey: &Pubkey, amount: u64, start: i64, end: i64) -> Instruction {
+    let mut ix: Instruction = initialize(payer_pkey, recipient_pkey, amount);
+    ix.data[0] = 41;
+    ix.data.extend_from_slice(&start.to_le_bytes());
+    ix.data.extend_from_slice(&end.to_le_bytes());
+    ix
+}
+
+/// Builds an `Initialize` whose protocol fee the payer bears on top of `amount`.
+pub fn initialize_payer_pays_fee(payer_pkey: &Pubkey, recipient_pkey: &Pubkey, amount: u64) -> Instruction {
+    let mut ix: Instruction = initialize(payer_pkey, recipient_pkey, amount);
+    ix.data[0] = 56;
+    ix
+}
+
+pub fn withdraw_vested(payer_pkey: &Pubkey, recipient_pkey: &Pubkey) -> Instruction {
+    Instruction::new_with_bytes(
+        crate::ID,
+        &[42],
+        WithdrawVestedAccounts {
+            recipient: *recipient_pkey,
+            payer: *payer_pkey,
+            escrow: find_escrow_pda(payer_pkey, recipient_pkey),
+            config: find_config_pda()
+        }.to_account_metas()
+    )
+}
+
+/// Builds an `InitializeMilestones` from `(amount, deadline)` pairs.
+pub fn initialize_milestones(payer_pkey: &Pubkey, recipient_pkey: &Pubkey, milestones: &[(u64, i64)]) -> Instruction {
+    let mut ix: Instruction = initialize(payer_pkey, recipient_pkey, 0);
+
+    ix.data = Vec::with_capacity(2 + milestones.len() * 16);
+    ix.data.push(43);
+    ix.data.push(milestones.len() as u8);
+    for (amount, deadline) in milestones {
+        ix.data.extend_from_slice(&amount.to_le_bytes());
+        ix.data.extend_from_slice(&deadline.to_le_bytes());
+    }
+
+    ix.accounts.push(AccountMeta::new(find_milestones_pda(&find_escrow_pda(payer_pkey, recipient_pkey)), false));
+    ix
+}
+
+pub fn approve_milestone(payer_pkey: &Pubkey, recipient_pkey: &Pubkey, index: u8) -> Instruction {
+    let escrow_pda: Pubkey = find_escrow_pda(payer_pkey, recipient_pkey);
+
+    Instruction::new_with_bytes(
+        crate::ID,
+        &[17, index],
+        vec![
+            AccountMeta::new(*payer_pkey, true),
+            AccountMeta::new(*recipient_pkey, false),
+            AccountMeta::new(escrow_pda, false),
+            AccountMeta::new_readonly(find_config_pda(), false),
+            AccountMeta::new(find_milestones_pda(&escrow_pda), false)
+        ]
+    )
+}
+
+/// Builds a split payout `Initialize` of `amount`, `splits` being `(recipient, bps)` pairs on top of the main recipient.
+pub fn initialize_split(payer_pkey: &Pubkey, recipient_pkey: &Pubkey, amount: u64, splits: &[(Pubkey, u16)]) -> Instruction {
+    let mut ix: Instruction = initialize(payer_pkey, recipient_pkey, amount);
+
+    ix.data[0] = 45;
+    ix.data.push(splits.len() as u8);
+    for (split_recipient_pkey, bps) in splits {
+        ix.data.extend_from_slice(split_recipient_pkey.as_ref());
+        ix.data.extend_from_slice(&bps.to_le_bytes());
+    }
+
+    ix.accounts.push(AccountMeta::new(find_assignments_pda(&find_escrow_pda(payer_pkey, recipient_pkey)), false));
+    ix
+}
+
+/// Builds a team vesting `Initialize` of `amount`, vesting linearly from `start` to `end` to the beneficiaries
+/// `splits` (`(beneficiary, bps)` pairs) and to the recipient, who keeps the rest.
+pub fn initialize_team_vesting(
+    payer_pkey: &Pubkey,
+    recipient_pkey: &Pubkey,
+    amount: u64,
+    start: i64,
+    end: i64,
+    splits: &[(Pubkey, u16)]
+) -> Instruction {
+    let mut ix: Instruction = initialize_vesting(payer_pkey, recipient_pkey, amount, start, end);
+    let escrow_pda: Pubkey = find_escrow_pda(payer_pkey, recipient_pkey);
+
+    ix.data[0] = 64;
+    ix.data.push(splits.len() as u8);
+    for (beneficiary_pkey, bps) in splits {
+        ix.data.extend_from_slice(beneficiary_pkey.as_ref());
+        ix.data.extend_from_slice(&bps.to_le_bytes());
+    }
+
+    ix.accounts.push(AccountMeta::new(find_assignments_pda(&escrow_pda), false));
+    ix.accounts.push(AccountMeta::new(find_vesting_share_pda(&escrow_pda, recipient_pkey), false));
+    for (beneficiary_pkey, _bps) in splits {
+        ix.accounts.push(AccountMeta::new(find_vesting_share_pda(&escrow_pda, beneficiary_pkey), false));
+    }
+    ix
+}
+
+/// Builds a `WithdrawVestedShare` of `beneficiary_pkey` (the recipient or one of the splits) out of a team vesting escrow.
+pub fn withdraw_vested_share(payer_pkey: &Pubkey, recipient_pkey: &Pubkey, beneficiary_pkey: &Pubkey) -> Instruction {
+    let escrow_pda: Pubkey = find_escrow_pda(payer_pkey, recipient_pkey);
+
+    Instruction::new_with_bytes(
+        crate::ID,
+        &[65],
+        WithdrawVestedShareAccounts {
+            beneficiary: *beneficiary_pkey,
+            payer: *payer_pkey,
+            recipient: *recipient_pkey,
+            escrow: escrow_pda,
+            config: find_config_pda(),
+            assignments: find_assignments_pda(&escrow_pda),
+            vesting_share: find_vesting_share_pda(&escrow_pda, beneficiary_pkey)
+        }.to_account_metas()
+    )
+}
+
+/// Builds an `Initialize` of `amount` for whoever owns the `.sol` domain whose name account is `domain_pkey`
+/// (see `name_service::domain_address`), resolved per `resolution`.
+pub fn initialize_to_domain(payer_pkey: &Pubkey, domain_pkey: &Pubkey, amount: u64, resolution: RecipientResolution) -> Instruction {
+    let mut ix: Instruction = initialize(payer_pkey, domain_pkey, amount);
+    ix.data[0] = 66;
+    ix.data.push(resolution as u8);
+    ix
+}
+
+/// Builds a `Complete` of an escrow to a domain, paying `owner_pkey` (pinned at `Initialize`, or the domain's current owner).
+pub fn complete_to_domain(payer_pkey: &Pubkey, domain_pkey: &Pubkey, owner_pkey: &Pubkey) -> Instruction {
+    let mut ix: Instruction = complete(payer_pkey, domain_pkey);
+    ix.accounts.push(AccountMeta::new(*owner_pkey, false));
+    ix
+}
+
+/// Builds a `Claim` of an escrow to a domain, signed by `owner_pkey` (pinned at `Initialize`, or the domain's current owner).
+pub fn claim_to_domain(owner_pkey: &Pubkey, payer_pkey: &Pubkey, domain_pkey: &Pubkey) -> Instruction {
+    let mut ix: Instruction = claim(domain_pkey, payer_pkey);
+    ix.accounts[0].is_signer = false;
+    ix.accounts.push(AccountMeta::new(*owner_pkey, true));
+    ix
+}
+
+/// Builds a `BatchSettle` by `relayer_pkey` of the escrows between each `(payer, recipient)` pair, to send after
+/// `authorization::ed25519_instruction()` of their payers' authorizations. Push the fee vault while a protocol fee is set.
+pub fn batch_settle(relayer_pkey: &Pubkey, escrows: &[(Pubkey, Pubkey)]) -> Instruction {
+    let mut accounts: Vec<AccountMeta> = vec![
+        AccountMeta::new_readonly(*relayer_pkey, true),
+        AccountMeta::new_readonly(find_config_pda(), false),
+        AccountMeta::new_readonly(solana_program::sysvar::instructions::ID, false)
+    ];
+
+    for (payer_pkey, recipient_pkey) in escrows {
+        accounts.push(AccountMeta::new(*payer_pkey, false));
+        accounts.push(AccountMeta::new(*recipient_pkey, false));
+        accounts.push(AccountMeta::new(find_escrow_pda(payer_pkey, recipient_pkey), false));
+    }
+
+    Instruction::new_with_bytes(crate::ID, &[68], accounts)
+}
+
+/// Builds a `SettleSwap` of two SOL escrows, signed by both parties.
+pub fn settle_swap(party_a_pkey: &Pubkey, party_b_pkey: &Pubkey, amount_a: u64, amount_b: u64) -> Instruction {
+    let mut data: Vec<u8> = Vec::with_capacity(17);
+    data.push(44);
+    data.extend_from_slice(&amount_a.to_le_bytes());
+    data.extend_from_slice(&amount_b.to_le_bytes());
+
+    Instruction::new_with_bytes(
+        crate::ID,
+        &data,
+        vec![
+            AccountMeta::new(*party_a_pkey, true),
+            AccountMeta::new(*party_b_pkey, true),
+            AccountMeta::new(find_escrow_pda(party_a_pkey, party_b_pkey), false),
+            AccountMeta::new(find_escrow_pda(party_b_pkey, party_a_pkey), false),
+            AccountMeta::new_readonly(find_config_pda(), false)
+        ]
+    )
+}
+
+/// Builds an `InitializeConfig` of the global `Config` (default `namespace`) or of a namespace's.
+pub fn initialize_config(admin_pkey: &Pubkey, namespace: &Pubkey) -> Instruction {
+    let mut data: Vec<u8> = Vec::with_capacity(33);
+    data.push(6);
+
+    let mut accounts: Vec<AccountMeta> = vec![
+        AccountMeta::new(*admin_pkey, true),
+        AccountMeta::new(find_namespace_pda(crate::get_namespace_config_seeds(namespace)), false),
+        AccountMeta::new_readonly(system_program::ID, false)
+    ];
+
+    match namespace == &Pubkey::default() {
+        true => accounts.push(AccountMeta::new_readonly(crate::get_program_data_address(&crate::ID), false)),
+        false => data.extend_from_slice(namespace.as_ref())
+    }
+
+    Instruction::new_with_bytes(crate::ID, &data, accounts)
+}
+
+/// Swaps the 1-byte tag of an instruction built by this module for its Anchor-style 8-byte discriminator (led by
+/// `DISCRIMINATOR_TAG`), for clients & explorers that identify instructions that way.
+pub fn with_discriminator(mut instruction: Instruction) -> Instruction {
+    let tag: usize = instruction.data[0] as usize;
+    instruction.data.splice(..1, [[DISCRIMINATOR_TAG].as_slice(), &DISCRIMINATORS[tag]].concat());
+    instruction
+}
+
+/// Moves the 1-byte tag of an instruction built by this module into the length-prefixed layout (see `EXTENDED_TAG`).
+pub fn with_length_prefix(mut instruction: Instruction) -> Instruction {
+    let tag: u16 = instruction.data[0] as u16;
+    let len: u16 = (instruction.data.len() - 1) as u16;
+    instruction.data.splice(..1, [[EXTENDED_TAG].as_slice(), &tag.to_le_bytes(), &len.to_le_bytes()].concat());
+    instruction
+}
+
+/// Points an instruction built by this module at `namespace`'s `Config` and fee vault instead of the global ones.
+pub fn in_namespace(mut instruction: Instruction, namespace: &Pubkey) -> Instruction {
+    let (global_config_pda, global_fee_vault_pda): (Pubkey, Pubkey) = (find_config_pda(), find_fee_vault_pda());
+    let config_pda: Pubkey = find_namespace_pda(crate::get_namespace_config_seeds(namespace));
+    let fee_vault_pda: Pubkey = find_namespace_pda(crate::get_namespace_fee_vault_seeds(namespace));
+
+    for account_meta in instruction.accounts.iter_mut() {
+        if account_meta.pubkey == global_config_pda {
+            account_meta.pubkey = config_pda;
+        } else if account_meta.pubkey == global_fee_vault_pda {
+            account_meta.pubkey = fee_vault_pda;
+        }
+    }
+
+    instruction
+}
+
+pub fn set_protocol_fee(admin_pkey: &Pubkey, fee_bps: u16) -> Instruction {
+    let mut data: Vec<u8> = Vec::with_capacity(3);
+    data.push(48);
+    data.extend_from_slice(&fee_bps.to_le_bytes());
+
+    Instruction::new_with_bytes(
+        crate::ID,
+        &data,
+        vec![
+            AccountMeta::new(*admin_pkey, true),
+            AccountMeta::new(find_config_pda(), false),
+            AccountMeta::new(find_fee_vault_pda(), false),
+            AccountMeta::new_readonly(system_program::ID, false)
+        ]
+    )
+}
+
+pub fn withdraw_fees(admin_pkey: &Pubkey, destination_pkey: &Pubkey, amount: u64) -> Instruction {
+    let mut data: Vec<u8> = Vec::with_capacity(9);
+    data.push(49);
+    data.extend_from_slice(&amount.to_le_bytes());
+
+    Instruction::new_with_bytes(
+        crate::ID,
+        &data,
+        vec![
+            AccountMeta::new_readonly(*admin_pkey, true),
+            AccountMeta::new_readonly(find_config_pda(), false),
+            AccountMeta::new(find_fee_vault_pda(), false),
+            AccountMeta::new(*destination_pkey, false)
+        ]
+    )
+}
+
+pub fn update_config(admin_pkey: &Pubkey, min_amount: u64, max_amount: u64) -> Instruction {
+    let mut data: Vec<u8> = Vec::with_capacity(17);
+    data.push(52);
+    data.extend_from_slice(&min_amount.to_le_bytes());
+    data.extend_from_slice(&max_amount.to_le_bytes());
+
+    Instruction::new_with_bytes(
+        crate::ID,
+        &data,
+        vec![
+            AccountMeta::new_readonly(*admin_pkey, true),
+            AccountMeta::new(find_config_pda(), false)
+        ]
+    )
+}
+
+/// Builds an `AssignClaimShare` of `bps` of the payout to `assignee_pkey`, see `EscrowInstruction::AssignClaimShare`.
+pub fn assign_claim_share(
+    recipient_pkey: &Pubkey,
+    payer_pkey: &Pubkey,
+    assignee_pkey: &Pubkey,
+    bps: u16,
+    from_payout: bool
+) -> Instruction {
+    let escrow_pda: Pubkey = find_escrow_pda(payer_pkey, recipient_pkey);
+    let mut data: Vec<u8> = Vec::with_capacity(4);
+    data.push(25);
+    data.extend_from_slice(&bps.to_le_bytes());
+
+    if from_payout {
+        data.push(1);
+    }
+
+    Instruction::new_with_bytes(
+        crate::ID,
+        &data,
+        vec![
+            AccountMeta::new(*recipient_pkey, true),
+            AccountMeta::new_readonly(*payer_pkey, false),
+            AccountMeta::new(escrow_pda, false),
+            AccountMeta::new(find_assignments_pda(&escrow_pda), false),
+            AccountMeta::new_readonly(*assignee_pkey, false),
+            AccountMeta::new_readonly(system_program::ID, false)
+        ]
+    )
+}
+
+pub fn nominate_admin(admin_pkey: &Pubkey, nominee_pkey: &Pubkey) -> Instruction {
+    let mut data: Vec<u8> = Vec::with_capacity(33);
+    data.push(54);
+    data.extend_from_slice(nominee_pkey.as_ref());
+
+    Instruction::new_with_bytes(
+        crate::ID,
+        &data,
+        NominateAdminAccounts { admin: *admin_pkey, config: find_config_pda() }.to_account_metas()
+    )
+}
+
+pub fn set_features(admin_pkey: &Pubkey, features: u8) -> Instruction {
+    Instruction::new_with_bytes(
+        crate::ID,
+        &[67, features],
+        SetFeaturesAccounts { admin: *admin_pkey, config: find_config_pda() }.to_account_metas()
+    )
+}
+
+/// Builds a `SetYieldVenue` to the stake pool `venue_pkey`, or unsetting it with the default pubkey.
+pub fn set_yield_venue(admin_pkey: &Pubkey, venue_pkey: &Pubkey) -> Instruction {
+    let mut data: Vec<u8> = Vec::with_capacity(33);
+    data.push(69);
+    data.extend_from_slice(venue_pkey.as_ref());
+
+    let mut accounts: Vec<AccountMeta> = SetYieldVenueAccounts { admin: *admin_pkey, config: find_config_pda() }.to_account_metas();
+    if venue_pkey != &Pubkey::default() {
+        accounts.push(AccountMeta::new_readonly(*venue_pkey, false));
+    }
+
+    Instruction::new_with_bytes(crate::ID, &data, accounts)
+}
+
+/// Builds a `SweepFees` of `amount` lamports into `pool`. The treasury's associated token account of the pool mint
+/// must exist beforehand (seeds of the treasury PDA: `"treasury"`).
+pub fn sweep_fees(admin_pkey: &Pubkey, pool: &StakePool, amount: u64) -> Instruction {
+    let mut data: Vec<u8> = Vec::with_capacity(9);
+    data.push(70);
+    data.extend_from_slice(&amount.to_le_bytes());
+
+    let treasury_pda: Pubkey = find_treasury_pda();
+
+    Instruction::new_with_bytes(
+        crate::ID,
+        &data,
+        SweepFeesAccounts {
+            admin: *admin_pkey,
+            config: find_config_pda(),
+            fee_vault: find_fee_vault_pda(),
+            treasury: treasury_pda,
+            pool_tokens: find_treasury_pool_tokens(&treasury_pda, pool),
+            pool_program: pool.program,
+            pool: pool.pool,
+            withdraw_authority: pool.withdraw_authority,
+            reserve_stake: pool.reserve_stake,
+            manager_fee: pool.manager_fee,
+            pool_mint: pool.pool_mint,
+            system_program: system_program::ID,
+            token_program: pool.token_program
+        }.to_account_metas()
+    )
+}
+
+/// Builds a `RedeemTreasury` of `pool_tokens` of the treasury from `pool`.
+pub fn redeem_treasury(admin_pkey: &Pubkey, pool: &StakePool, pool_tokens: u64) -> Instruction {
+    let mut data: Vec<u8> = Vec::with_capacity(9);
+    data.push(71);
+    data.extend_from_slice(&pool_tokens.to_le_bytes());
+
+    let treasury_pda: Pubkey = find_treasury_pda();
+
+    Instruction::new_with_bytes(
+        crate::ID,
+        &data,
+        RedeemTreasuryAccounts {
+            admin: *admin_pkey,
+            config: find_config_pda(),
+            fee_vault: find_fee_vault_pda(),
+            treasury: treasury_pda,
+            pool_tokens: find_treasury_pool_tokens(&treasury_pda, pool),
+            pool_program: pool.program,
+            pool: pool.pool,
+            withdraw_authority: pool.withdraw_authority,
+            reserve_stake: pool.reserve_stake,
+            manager_fee: pool.manager_fee,
+            pool_mint: pool.pool_mint,
+            clock: solana_program::sysvar::clock::ID,
+            stake_history: solana_program::sysvar::stake_history::ID,
+            stake_program: solana_program::stake::program::ID,
+            system_program: system_program::ID,
+            token_program: pool.token_program
+        }.to_account_metas()
+    )
+}
+
+pub fn accept_admin(nominee_pkey: &Pubkey) -> Instruction {
+    Instruction::new_with_bytes(
+        crate::ID,
+        &[55],
+        AcceptAdminAccounts { nominee: *nominee_pkey, config: find_config_pda() }.to_account_metas()
+    )
+}
+
+pub fn set_max_payout(payer_pkey: &Pubkey, recipient_pkey: &Pubkey, max_payout: u64) -> Instruction {
+    let mut data: Vec<u8> = Vec::with_capacity(9);
+    data.push(53);
+    data.extend_from_slice(&max_payout.to_le_bytes());
+
+    Instruction::new_with_bytes(
+        crate::ID,
+        &data,
+        vec![
+            AccountMeta::new_readonly(*payer_pkey, true),
+            AccountMeta::new_readonly(*recipient_pkey, false),
+            AccountMeta::new(find_escrow_pda(payer_pkey, recipient_pkey), false)
+        ]
+    )
+}
+
+pub fn set_attestation_issuer(admin_pkey: &Pubkey, issuer_pkey: &Pubkey) -> Instruction {
+    let mut data: Vec<u8> = Vec::with_capacity(33);
+    data.push(50);
+    data.extend_from_slice(issuer_pkey.as_ref());
+
+    Instruction::new_with_bytes(
+        crate::ID,
+        &data,
+        vec![
+            AccountMeta::new_readonly(*admin_pkey, true),
+            AccountMeta::new(find_config_pda(), false)
+        ]
+    )
+}
+
+pub fn require_attestation(payer_pkey: &Pubkey, recipient_pkey: &Pubkey) -> Instruction {
+    Instruction::new_with_bytes(
+        crate::ID,
+        &[51],
+        vec![
+            AccountMeta::new_readonly(*payer_pkey, true),
+            AccountMeta::new_readonly(*recipient_pkey, false),
+            AccountMeta::new(find_escrow_pda(payer_pkey, recipient_pkey), false)
+        ]
+    )
+}
+
+/// Builds a `RequireIsolatedSettlement` for the escrow between `payer_pkey` and `recipient_pkey`.
+pub fn require_isolated_settlement(payer_pkey: &Pubkey, recipient_pkey: &Pubkey) -> Instruction {
+    let mut ix: Instruction = require_attestation(payer_pkey, recipient_pkey);
+    ix.data[0] = 60;
+    ix
+}
+
+/// Builds a `CloseNote` of a SOL escrow signed by `authority_pkey` (the payer or the recipient).
+/// The rent always goes back to the payer, so the authority's account is read-only.
+pub fn close_note(authority_pkey: &Pubkey, payer_pkey: &Pubkey, recipient_pkey: &Pubkey) -> Instruction {
+    close_note_of(authority_pkey, payer_pkey, recipient_pkey, &find_escrow_pda(payer_pkey, recipient_pkey), vec![3])
+}
+
+/// Builds a `CloseNote` of the `mint` token escrow, see [`close_note`].
+pub fn close_token_note(authority_pkey: &Pubkey, payer_pkey: &Pubkey, recipient_pkey: &Pubkey, mint_pkey: &Pubkey) -> Instruction {
+    let mut data: Vec<u8> = Vec::with_capacity(33);
+    data.push(3);
+    data.extend_from_slice(mint_pkey.as_ref());
+
+    close_note_of(authority_pkey, payer_pkey, recipient_pkey, &find_token_escrow_pda(payer_pkey, recipient_pkey, mint_pkey), data)
+}
+
+fn close_note_of(authority_pkey: &Pubkey, payer_pkey: &Pubkey, recipient_pkey: &Pubkey, escrow_pda: &Pubkey, data: Vec<u8>) -> Instruction {
+    let (seed1, seed2) = crate::get_note_seeds(escrow_pda);
+    let (note_pda, _bump) = Pubkey::find_program_address(&[seed1, seed2], &crate::ID);
+
+    Instruction::new_with_bytes(
+        crate::ID,
+        &data,
+        vec![
+            AccountMeta::new_readonly(*authority_pkey, true),
+            AccountMeta::new(*payer_pkey, false),
+            AccountMeta::new_readonly(*recipient_pkey, false),
+            AccountMeta::new(note_pda, false)
+        ]
+    )
+}
+
+/// Message of a relayer-sponsored (gasless) transaction: `relayer_pkey` is the fee payer,
+/// so the remaining signers, e.g. a recipient holding no SOL, only sign as authorities.
+///
+/// The relayer and the authorities sign the resulting transaction independently (`Transaction::partial_sign()`),
+/// in any order, before the relayer submits it.
+pub fn relayed_message(instructions: &[Instruction], relayer_pkey: &Pubkey, recent_blockhash: &Hash) -> Message {
+    Message::new_with_blockhash(instructions, Some(relayer_pkey), recent_blockhash)
+}
+
+/// Message for previewing `instructions` (e.g. in a wallet UI) through `simulateTransaction` with `sigVerify: false`
+/// and `replaceRecentBlockhash: true`: sent with zeroed signatures (`Transaction::new_unsigned()`), nobody is asked to sign
+/// and no blockhash has to be fetched.
+///
+/// The signer flags are kept on purpose: the program checks `is_signer`, so an instruction whose signers were turned
+/// into non-signers would simulate as a `MissingRequiredSignature` failure instead of previewing its effects.
+pub fn simulation_message(instructions: &[Instruction], fee_payer_pkey: &Pubkey) -> Message {
+    Message::new(instructions, Some(fee_payer_pkey))
+}
+
+fn find_escrow_pda(payer_pkey: &Pubkey, recipient_pkey: &Pubkey) -> Pubkey {
+    let (seed1, seed2, seed3) = crate::get_escrow_seeds(payer_pkey, recipient_pkey);
+    Pubkey::find_program_address(&[seed1, seed2, seed3], &crate::ID).0
+}
+
+fn find_token_escrow_pda(payer_pkey: &Pubkey, recipient_pkey: &Pubkey, mint_pkey: &Pubkey) -> Pubkey {
+    let (seed1, seed2, seed3, seed4) = crate::get_token_escrow_seeds(payer_pkey, recipient_pkey, mint_pkey);
+    Pubkey::find_program_address(&[seed1, seed2, seed3, seed4], &crate::ID).0
+}
+
+/// Stake account `Complete` pays the escrow `escrow_id` into, see `SetStakeSettlement`.
+pub fn find_stake_pda(escrow_id: u64) -> Pubkey {
+    let escrow_id: [u8; 8] = escrow_id.to_le_bytes();
+    let (seed1, seed2) = crate::get_stake_seeds(&escrow_id);
+    Pubkey::find_program_address(&[seed1, seed2], &crate::ID).0
+}
+
+fn find_config_pda() -> Pubkey {
+    Pubkey::find_program_address(&[crate::get_config_seeds()], &crate::ID).0
+}
+
+fn find_namespace_pda(seeds: Vec<&[u8]>) -> Pubkey {
+    Pubkey::find_program_address(&seeds, &crate::ID).0
+}
+
+fn find_fee_vault_pda() -> Pubkey {
+    Pubkey::find_program_address(&[crate::get_fee_vault_seeds()], &crate::ID).0
+}
+
+fn find_treasury_pda() -> Pubkey {
+    Pubkey::find_program_address(&[crate::get_treasury_seeds()], &crate::ID).0
+}
+
+fn find_treasury_pool_tokens(treasury_pda: &Pubkey, pool: &StakePool) -> Pubkey {
+    spl_associated_token_account::get_associated_token_address_with_program_id(treasury_pda, &pool.pool_mint, &pool.token_program)
+}
+
+fn find_deadline_bucket_pda(day: i64) -> Pubkey {
+    let day: [u8; 8] = day.to_le_bytes();
+    let (seed1, seed2) = crate::get_deadline_bucket_seeds(&day);
+    Pubkey::find_program_address(&[seed1, seed2], &crate::ID).0
+}
+
+fn find_assignments_pda(escrow_pda: &Pubkey) -> Pubkey {
+    let (seed1, seed2) = crate::get_assignments_seeds(escrow_pda);
+    Pubkey::find_program_address(&[seed1, seed2], &crate::ID).0
+}
+
+fn find_vesting_share_pda(escrow_pda: &Pubkey, beneficiary_pkey: &Pubkey) -> Pubkey {
+    let (seed1, seed2, seed3) = crate::get_vesting_share_seeds(escrow_pda, beneficiary_pkey);
+    Pubkey::find_program_address(&[seed1, seed2, seed3], &crate::ID).0
+}
+
+fn find_milestones_pda(escrow_pda: &Pubkey) -> Pubkey {
+    let (seed1, seed2) = crate::get_milestones_seeds(escrow_pda);
+    Pubkey::find_program_address(&[seed1, seed2], &crate::ID).0
+}
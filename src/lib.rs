@@ -1,15 +1,68 @@
 pub mod state;
+pub mod error;
+pub mod cluster;
+pub mod claim;
+pub mod solana_pay;
+pub mod events;
+pub mod oracle;
+pub mod governance;
+pub mod attestation;
+pub mod authorization;
+pub mod name_service;
+pub mod treasury;
+pub mod staking;
+pub mod priority_fee;
+pub mod rent;
 pub mod processor;
 pub mod entrypoint;
+pub mod accounts;
 pub mod instruction;
+#[cfg(any(test, feature = "idl"))]
+pub mod idl;
+pub mod cpi;
+#[cfg(feature = "client")]
+pub mod client;
+#[cfg(feature = "bundle")]
+pub mod bundle;
+#[cfg(feature = "types")]
+pub mod types;
+#[cfg(feature = "replay")]
+pub mod replay;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod model;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod test_utils;
 
 use solana_program::{
-    declare_id,
+    pubkey,
     pubkey::Pubkey,
+    account_info::AccountInfo,
+    bpf_loader_upgradeable,
     program_error::ProgramError
 };
+use error::EscrowError;
 
-declare_id!("E6v3tbZyZAthzd5JCPJgd3TmLXL3VirKxib9XHjyKTjL");
+/// Program id of the cluster selected at build time (see `cluster::Cluster::current()`).
+pub const ID: Pubkey = cluster::Cluster::current().program_id();
+
+pub fn id() -> Pubkey {
+    ID
+}
+
+pub fn check_id(id: &Pubkey) -> bool {
+    id == &ID
+}
+
+/// SPL Memo program (v2), used for settlement receipts.
+pub const MEMO_PROGRAM_ID: Pubkey = pubkey!("MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr");
+
+/// The only programs a transaction settling a `RequireIsolatedSettlement` escrow may call.
+pub const ISOLATED_SETTLEMENT_PROGRAMS: [Pubkey; 4] = [
+    ID,
+    solana_program::system_program::ID,
+    priority_fee::COMPUTE_BUDGET_PROGRAM_ID,
+    MEMO_PROGRAM_ID
+];
 
 pub fn get_escrow_seeds<'a>(payer_pkey: &'a Pubkey, recipient_pkey: &'a Pubkey) -> (&'a [u8], &'a [u8], &'a [u8]) {
     (
@@ -32,26 +85,304 @@ pub fn check_provided_pda(
     )?;
 
     if escrow_pda != &expected_pda {
-        return Err(ProgramError::InvalidInstructionData);
+        return Err(EscrowError::PdaMismatch.into());
     }
     
     Ok(())
 }
 
+pub fn get_counter_seeds() -> &'static [u8] {
+    b"counter"
+}
+
+pub fn get_config_seeds() -> &'static [u8] {
+    b"config"
+}
+
+pub fn get_fee_vault_seeds() -> &'static [u8] {
+    b"fee_vault"
+}
+
+pub fn get_treasury_seeds() -> &'static [u8] {
+    b"treasury"
+}
+
+/// Seeds of a namespace's `Config`, letting independent operators run isolated configurations on this deployment.
+/// The default namespace is the global `Config`, which keeps the plain `"config"` seed.
+pub fn get_namespace_config_seeds(namespace: &Pubkey) -> Vec<&[u8]> {
+    match namespace == &Pubkey::default() {
+        true => vec![get_config_seeds()],
+        false => vec![get_config_seeds(), namespace.as_ref()]
+    }
+}
+
+/// Seeds of a namespace's fee vault, see `get_namespace_config_seeds()`.
+pub fn get_namespace_fee_vault_seeds(namespace: &Pubkey) -> Vec<&[u8]> {
+    match namespace == &Pubkey::default() {
+        true => vec![get_fee_vault_seeds()],
+        false => vec![get_fee_vault_seeds(), namespace.as_ref()]
+    }
+}
+
+/// Seeds of a namespace's treasury, see `get_namespace_config_seeds()` and `treasury`.
+pub fn get_namespace_treasury_seeds(namespace: &Pubkey) -> Vec<&[u8]> {
+    match namespace == &Pubkey::default() {
+        true => vec![get_treasury_seeds()],
+        false => vec![get_treasury_seeds(), namespace.as_ref()]
+    }
+}
+
+pub fn check_provided_config_pda(config_pda: &Pubkey, namespace: &Pubkey, bump: u8) -> Result<(), ProgramError> {
+    let expected_pda: Pubkey = Pubkey::create_program_address(
+        &[&get_namespace_config_seeds(namespace)[..], &[&[bump]]].concat(),
+        &crate::ID
+    )?;
+
+    if config_pda != &expected_pda {
+        return Err(EscrowError::PdaMismatch.into());
+    }
+
+    Ok(())
+}
+
+/// Address of the upgradeable loader's `ProgramData` account of `program_id`, which holds its upgrade authority.
+pub fn get_program_data_address(program_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[program_id.as_ref()], &bpf_loader_upgradeable::ID).0
+}
+
+/// Fails unless `program_data_account` is `program_id`'s `ProgramData` and `authority_pkey` its upgrade authority.
+/// Layout (`UpgradeableLoaderState::ProgramData`, bincode): variant (u32, 3) + slot (8) + upgrade authority (`Option<Pubkey>`).
+pub fn check_upgrade_authority(program_id: &Pubkey, program_data_account: &AccountInfo, authority_pkey: &Pubkey) -> Result<(), ProgramError> {
+    if program_data_account.key != &get_program_data_address(program_id) {
+        return Err(EscrowError::PdaMismatch.into());
+    }
+
+    if program_data_account.owner != &bpf_loader_upgradeable::ID {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    let data = program_data_account.data.borrow();
+
+    if data.get(..4) != Some(&3u32.to_le_bytes()[..]) {
+        return Err(ProgramError::InvalidAccountData);
+    }
+
+    // immutable programs (no upgrade authority) have no one to hand the admin role to
+    if data.get(12) != Some(&1) || data.get(13..45) != Some(authority_pkey.as_ref()) {
+        return Err(EscrowError::NotAdmin.into());
+    }
+
+    Ok(())
+}
+
+pub fn get_blocked_mint_seeds(mint_pkey: &Pubkey) -> (&[u8], &[u8]) {
+    (
+        b"blocked_mint",
+        mint_pkey.as_ref()
+    )
+}
+
+/// Fails if the admin blocked `mint_pkey`. `blocked_mint_account` must be the mint's `BlockedMint` PDA,
+/// which simply doesn't exist for allowed mints.
+pub fn check_mint_not_blocked(mint_pkey: &Pubkey, blocked_mint_account: &AccountInfo) -> Result<(), ProgramError> {
+    let (seed1, seed2) = get_blocked_mint_seeds(mint_pkey);
+    let (expected_pda, _bump) = Pubkey::find_program_address(&[seed1, seed2], &crate::ID);
+
+    if blocked_mint_account.key != &expected_pda {
+        return Err(EscrowError::PdaMismatch.into());
+    }
+
+    if blocked_mint_account.owner == &crate::ID && blocked_mint_account.lamports() != 0 {
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    Ok(())
+}
+
+/// Seeds of token escrows. Unlike `get_escrow_seeds()` (which SOL escrows keep using), the mint is part of the seeds,
+/// so a payer can escrow different tokens to the same recipient at the same time.
+pub fn get_token_escrow_seeds<'a>(
+    payer_pkey: &'a Pubkey,
+    recipient_pkey: &'a Pubkey,
+    mint_pkey: &'a Pubkey
+) -> (&'a [u8], &'a [u8], &'a [u8], &'a [u8]) {
+    (
+        b"escrow",
+        payer_pkey.as_ref(),
+        recipient_pkey.as_ref(),
+        mint_pkey.as_ref()
+    )
+}
+
+pub fn check_provided_token_pda(
+    payer_pkey: &Pubkey,
+    recipient_pkey: &Pubkey,
+    mint_pkey: &Pubkey,
+    escrow_pda: &Pubkey,
+    bump: u8
+) -> Result<(), ProgramError> {
+    let (seed1, seed2, seed3, seed4) = get_token_escrow_seeds(payer_pkey, recipient_pkey, mint_pkey);
+    let expected_pda: Pubkey = Pubkey::create_program_address(
+        &[seed1, seed2, seed3, seed4, &[bump]],
+        &crate::ID
+    )?;
+
+    if escrow_pda != &expected_pda {
+        return Err(EscrowError::PdaMismatch.into());
+    }
+
+    Ok(())
+}
+
+pub fn get_vault_seeds(escrow_pda: &Pubkey) -> (&[u8], &[u8]) {
+    (
+        b"vault",
+        escrow_pda.as_ref()
+    )
+}
+
+pub fn get_note_seeds(escrow_pda: &Pubkey) -> (&[u8], &[u8]) {
+    (
+        b"note",
+        escrow_pda.as_ref()
+    )
+}
+
+pub fn get_milestones_seeds(escrow_pda: &Pubkey) -> (&[u8], &[u8]) {
+    (
+        b"milestones",
+        escrow_pda.as_ref()
+    )
+}
+
+pub fn get_assignments_seeds(escrow_pda: &Pubkey) -> (&[u8], &[u8]) {
+    (
+        b"assignments",
+        escrow_pda.as_ref()
+    )
+}
+
+/// Seeds of the `VestingShare` of `beneficiary_pkey` in a team vesting escrow.
+pub fn get_vesting_share_seeds<'a>(escrow_pda: &'a Pubkey, beneficiary_pkey: &'a Pubkey) -> (&'a [u8], &'a [u8], &'a [u8]) {
+    (
+        b"vesting_share",
+        escrow_pda.as_ref(),
+        beneficiary_pkey.as_ref()
+    )
+}
+
+pub fn get_swap_seeds(leg_a_pda: &Pubkey) -> (&[u8], &[u8]) {
+    (
+        b"swap",
+        leg_a_pda.as_ref()
+    )
+}
+
+pub fn get_export_seeds(escrow_pkey: &Pubkey) -> (&[u8], &[u8]) {
+    (
+        b"export",
+        escrow_pkey.as_ref()
+    )
+}
+
+/// `escrow_pkey` being the exported escrow (of the program it was exported from).
+pub fn get_import_seeds(escrow_pkey: &Pubkey) -> (&[u8], &[u8]) {
+    (
+        b"import",
+        escrow_pkey.as_ref()
+    )
+}
+
+/// `day` as returned by `DeadlineBucket::day_of`, in little-endian bytes.
+pub fn get_deadline_bucket_seeds(day: &[u8; 8]) -> (&[u8], &[u8]) {
+    (
+        b"deadlines",
+        day
+    )
+}
+
+pub fn get_archive_seeds(payer_pkey: &Pubkey) -> (&[u8], &[u8]) {
+    (
+        b"archive",
+        payer_pkey.as_ref()
+    )
+}
+
+pub fn get_display_seeds(escrow_pda: &Pubkey) -> (&[u8], &[u8]) {
+    (
+        b"display",
+        escrow_pda.as_ref()
+    )
+}
+
+/// `escrow_id` as in `EscrowAccount::id`, in little-endian bytes.
+pub fn get_stake_seeds(escrow_id: &[u8; 8]) -> (&[u8], &[u8]) {
+    (
+        b"stake",
+        escrow_id
+    )
+}
+
+pub fn check_provided_note_pda(
+    escrow_pda: &Pubkey,
+    note_pda: &Pubkey,
+    bump: u8
+) -> Result<(), ProgramError> {
+    let (seed1, seed2) = get_note_seeds(escrow_pda);
+    let expected_pda: Pubkey = Pubkey::create_program_address(
+        &[seed1, seed2, &[bump]],
+        &crate::ID
+    )?;
+
+    if note_pda != &expected_pda {
+        return Err(EscrowError::PdaMismatch.into());
+    }
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
-    use solana_program_test::{BanksClient, ProgramTest, processor};
+    use solana_program_test::{BanksClient, ProgramTest, ProgramTestContext, processor};
+    use solana_program::{account_info::AccountInfo, program_error::ProgramError, program_pack::Pack};
+    use crate::error::EscrowError;
+    use crate::instruction::EscrowInstruction;
+    use crate::events::{EscrowEvent, SettlementBreakdown};
+    use crate::state::{
+        ArchivedEscrow, BlockedMint, ClaimShare, Config, DeadlineBucket, EscrowAccount, EscrowArchive, EscrowAssignments, EscrowDisplay, EscrowExport,
+        EscrowStatus, EscrowSwap, EscrowCounter, EscrowMilestones, EscrowNote, Milestone, MilestoneTemplate, VestingShare, LayoutVersion, ImpliedFields
+    };
+    use crate::oracle::{OraclePrice, SettlementBounds, PYTH_RECEIVER_PROGRAM_ID, SOL_USD_FEED_ID};
+    use crate::governance::{Proposal, SPL_GOVERNANCE_PROGRAM_ID};
+    use crate::attestation::ATTESTATION_SERVICE_PROGRAM_ID;
+    use crate::authorization;
+    use crate::name_service::{self, RecipientResolution, NAME_SERVICE_PROGRAM_ID, SOL_TLD_AUTHORITY};
+    use crate::claim::ClaimRequest;
+    use crate::solana_pay;
+    use crate::priority_fee::{self, PriorityFeePolicy};
+    use crate::staking;
+    use crate::treasury::{StakePool, SPL_STAKE_POOL_PROGRAM_ID};
+    use solana_vote_interface::state::{VoteInit, VoteState, VoteStateVersions};
+    use base64::Engine;
+    use proptest::prelude::*;
+    use crate::cluster::Cluster;
+    use crate::test_utils::{self, Attack};
     use solana_sdk::{
+        account::Account,
+        clock::Clock,
+        rent::Rent,
         hash::Hash,
         system_program,
+        system_instruction,
         pubkey::Pubkey,
         signer::{
             Signer,
             keypair::Keypair
         },
         message::Message,
-        transaction::Transaction,
-        instruction::{Instruction, AccountMeta}
+        transaction::{Transaction, TransactionError},
+        instruction::{Instruction, AccountMeta, InstructionError},
+        system_instruction::SystemError
     };
 
     #[tokio::test]
@@ -101,21 +432,79 @@ mod tests {
 
         // 3. complete escrow
         // 3.1 craft ix & tx
+        let complete_escrow_ix: Instruction = EscrowInstruction::complete(&payer_pkey, &recipient_pkey);
+        let message: Message = Message::new(&[complete_escrow_ix], Some(&payer_pkey));
+        let mut complete_escrow_tx: Transaction = Transaction::new_unsigned(message);
+
+        // 3.2 sign complete escrow tx & send it
+        complete_escrow_tx.sign(&[&payer], latest_blockhash);
+        banks_client.process_transaction(complete_escrow_tx).await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_complete_escrow_with_memo_receipt() -> Result<(), Box<dyn std::error::Error>> {
+        let program_test: ProgramTest = ProgramTest::new(
+            "escrow",
+            crate::ID,
+            processor!(super::entrypoint::process_instruction)
+        );
+
+        // 1.1 init Client, payer, get latest blockhash
+        let (banks_client, payer, latest_blockhash) = program_test.start().await;
+        let payer_pkey: Pubkey = payer.pubkey();
+
+        // 1.2 init recipient & derive escrow PDA
+        let recipient: Keypair = Keypair::new();
+        let recipient_pkey: Pubkey = recipient.pubkey();
+
+        let (escrow_pda, _bump) = derive_escrow_pda(&payer_pkey, &recipient_pkey);
+
+        // 2. init escrow
+        init_escrow(&banks_client, &payer, &payer_pkey, &recipient_pkey, &escrow_pda, latest_blockhash).await?;
+
+        // 3. complete escrow, passing the memo program to get a receipt
         let complete_escrow_ix: Instruction = Instruction::new_with_bytes(
-            crate::ID, 
-            &[1], 
+            crate::ID,
+            &[1],
             vec![
                 AccountMeta::new(payer_pkey, true),
                 AccountMeta::new(recipient_pkey, false),
-                AccountMeta::new(escrow_pda, false)
+                AccountMeta::new(escrow_pda, false),
+                AccountMeta::new_readonly(derive_config_pda(), false),
+                AccountMeta::new_readonly(crate::MEMO_PROGRAM_ID, false)
             ]
         );
         let message: Message = Message::new(&[complete_escrow_ix], Some(&payer_pkey));
         let mut complete_escrow_tx: Transaction = Transaction::new_unsigned(message);
-
-        // 3.2 sign complete escrow tx & send it
         complete_escrow_tx.sign(&[&payer], latest_blockhash);
-        banks_client.process_transaction(complete_escrow_tx).await?;
+
+        let result = banks_client.process_transaction_with_metadata(complete_escrow_tx).await?;
+        result.result?;
+
+        let logs: Vec<String> = result.metadata.ok_or("missing metadata")?.log_messages;
+        assert!(logs.iter().any(|log| log.contains("escrow_settlement")));
+
+        // 4. the archive goes before the memo program, either way round it's rejected instead of skipped
+        let (archive_pda, _bump) = Pubkey::find_program_address(&[b"archive", payer_pkey.as_ref()], &crate::ID);
+        let recipient_pkey: Pubkey = Keypair::new().pubkey();
+        let (escrow_pda, _bump) = derive_escrow_pda(&payer_pkey, &recipient_pkey);
+        init_escrow(&banks_client, &payer, &payer_pkey, &recipient_pkey, &escrow_pda, latest_blockhash).await?;
+
+        let mut complete_escrow_ix: Instruction = EscrowInstruction::complete(&payer_pkey, &recipient_pkey);
+        complete_escrow_ix.accounts.push(AccountMeta::new_readonly(crate::MEMO_PROGRAM_ID, false));
+        complete_escrow_ix.accounts.push(AccountMeta::new(archive_pda, false));
+        complete_escrow_ix.accounts.push(AccountMeta::new_readonly(system_program::ID, false));
+        let error: InstructionError = instruction_error(&banks_client, &[complete_escrow_ix], &[&payer]).await?;
+        assert_eq!(error, InstructionError::InvalidArgument);
+
+        let mut complete_escrow_ix: Instruction = EscrowInstruction::complete(&payer_pkey, &recipient_pkey);
+        complete_escrow_ix.accounts.push(AccountMeta::new_readonly(system_program::ID, false));
+        complete_escrow_ix.accounts.push(AccountMeta::new(archive_pda, false));
+        let error: InstructionError = instruction_error(&banks_client, &[complete_escrow_ix], &[&payer]).await?;
+        assert_eq!(error, InstructionError::Custom(EscrowError::PdaMismatch as u32));
+        assert!(banks_client.get_account(escrow_pda).await?.is_some());
 
         Ok(())
     }
@@ -142,15 +531,7 @@ mod tests {
         init_escrow(&banks_client, &payer, &payer_pkey, &recipient_pkey, &escrow_pda, latest_blockhash).await?;
 
         // 3. close escrow
-        let close_escrow_ix: Instruction = Instruction::new_with_bytes(
-            crate::ID, 
-            &[2], 
-            vec![
-                AccountMeta::new(payer_pkey, true),
-                AccountMeta::new_readonly(recipient_pkey, false),
-                AccountMeta::new(escrow_pda, false)
-            ]
-        );
+        let close_escrow_ix: Instruction = EscrowInstruction::close(&payer_pkey, &recipient_pkey);
         let message: Message = Message::new(&[close_escrow_ix], Some(&payer_pkey));
         let mut close_escrow_tx: Transaction = Transaction::new_unsigned(message);
 
@@ -161,39 +542,5095 @@ mod tests {
         Ok(())
     }
 
-    async fn init_escrow(
-        banks_client: &BanksClient,
-        payer: &Keypair,
-        payer_pkey: &Pubkey,
-        recipient_pkey: &Pubkey,
-        escrow_pda: &Pubkey,
-        latest_blockhash: Hash
-    ) -> Result<(), Box<dyn std::error::Error>> {        
-        // craft init ix & init tx
-        let mut init_ix_payload: Vec<u8> = Vec::with_capacity(9);
-        init_ix_payload.push(0);
-        init_ix_payload.extend_from_slice(&u64::to_le_bytes(101101101));
+    #[tokio::test]
+    async fn test_close_escrows_into_archive() -> Result<(), Box<dyn std::error::Error>> {
+        let program_test: ProgramTest = ProgramTest::new(
+            "escrow",
+            crate::ID,
+            processor!(super::entrypoint::process_instruction)
+        );
 
-        let initialize_escrow_ix: Instruction = Instruction::new_with_bytes(
-            crate::ID, 
-            &init_ix_payload, 
-            vec![
-                AccountMeta::new(*payer_pkey, true),
-                AccountMeta::new_readonly(*recipient_pkey, false),
-                AccountMeta::new(*escrow_pda, false),
-                AccountMeta::new_readonly(system_program::ID, false)
-            ]
+        // 1. init Client, payer, get latest blockhash & derive archive PDA
+        let (banks_client, payer, latest_blockhash) = program_test.start().await;
+        let payer_pkey: Pubkey = payer.pubkey();
+
+        let (archive_pda, _bump) = Pubkey::find_program_address(&[b"archive", payer_pkey.as_ref()], &crate::ID);
+
+        // 2. init & close two escrows, the first close creates the archive and the second one grows it
+        for _ in 0..2 {
+            let recipient_pkey: Pubkey = Keypair::new().pubkey();
+            let (escrow_pda, _bump) = derive_escrow_pda(&payer_pkey, &recipient_pkey);
+
+            init_escrow(&banks_client, &payer, &payer_pkey, &recipient_pkey, &escrow_pda, latest_blockhash).await?;
+
+            let close_escrow_ix: Instruction = Instruction::new_with_bytes(
+                crate::ID,
+                &[2],
+                vec![
+                    AccountMeta::new(payer_pkey, true),
+                    AccountMeta::new_readonly(recipient_pkey, false),
+                    AccountMeta::new(escrow_pda, false),
+                    AccountMeta::new_readonly(derive_config_pda(), false),
+                    AccountMeta::new(archive_pda, false),
+                    AccountMeta::new_readonly(system_program::ID, false)
+                ]
+            );
+            let message: Message = Message::new(&[close_escrow_ix], Some(&payer_pkey));
+            let mut close_escrow_tx: Transaction = Transaction::new_unsigned(message);
+            close_escrow_tx.sign(&[&payer], latest_blockhash);
+            banks_client.process_transaction(close_escrow_tx).await?;
+
+            assert!(banks_client.get_account(escrow_pda).await?.is_none());
+        }
+
+        // 3. both records are there
+        let archive_account = banks_client.get_account(archive_pda).await?.ok_or("archive wasn't created")?;
+        let records: Vec<ArchivedEscrow> = EscrowArchive::records(&archive_account.data)?;
+
+        assert_eq!(records.len(), 2);
+        assert!(records.iter().all(|record| record.outcome == ArchivedEscrow::OUTCOME_CLOSED));
+        assert!(records.iter().all(|record| record.created_at <= record.closed_at));
+        assert_ne!(records[0].state_hash, records[1].state_hash);
+
+        // 4. a misordered account list fails, rather than the escrow being closed unarchived
+        let recipient_pkey: Pubkey = Keypair::new().pubkey();
+        let (escrow_pda, _bump) = derive_escrow_pda(&payer_pkey, &recipient_pkey);
+        init_escrow(&banks_client, &payer, &payer_pkey, &recipient_pkey, &escrow_pda, latest_blockhash).await?;
+
+        let mut close_escrow_ix: Instruction = crate::instruction::close(&payer_pkey, &recipient_pkey);
+        close_escrow_ix.accounts.push(AccountMeta::new_readonly(system_program::ID, false));
+        close_escrow_ix.accounts.push(AccountMeta::new(archive_pda, false));
+        let error: InstructionError = instruction_error(&banks_client, &[close_escrow_ix], &[&payer]).await?;
+        assert_eq!(error, InstructionError::Custom(EscrowError::PdaMismatch as u32));
+        assert!(banks_client.get_account(escrow_pda).await?.is_some());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_recipient_acknowledges_receipt_in_archive() -> Result<(), Box<dyn std::error::Error>> {
+        let program_test: ProgramTest = ProgramTest::new(
+            "escrow",
+            crate::ID,
+            processor!(super::entrypoint::process_instruction)
         );
-        let message: Message = Message::new(&[initialize_escrow_ix], Some(&payer_pkey));
-        let mut initialize_escrow_tx: Transaction = Transaction::new_unsigned(message);
 
-        // sign init escrow tx & send it
-        initialize_escrow_tx.sign(&[&payer], latest_blockhash);
-        banks_client.process_transaction(initialize_escrow_tx).await?;
+        let (banks_client, payer, latest_blockhash) = program_test.start().await;
+        let payer_pkey: Pubkey = payer.pubkey();
+        let recipient: Keypair = Keypair::new();
+        let recipient_pkey: Pubkey = recipient.pubkey();
+        let (escrow_pda, _bump) = derive_escrow_pda(&payer_pkey, &recipient_pkey);
+        let (archive_pda, _bump) = Pubkey::find_program_address(&[b"archive", payer_pkey.as_ref()], &crate::ID);
+
+        // 1. complete an escrow into the archive, keeping its last state as an indexer would
+        init_escrow(&banks_client, &payer, &payer_pkey, &recipient_pkey, &escrow_pda, latest_blockhash).await?;
+        let escrow_account: Account = banks_client.get_account(escrow_pda).await?.ok_or("escrow wasn't created")?;
+
+        let mut complete_ix: Instruction = EscrowInstruction::complete(&payer_pkey, &recipient_pkey);
+        complete_ix.accounts.push(AccountMeta::new(archive_pda, false));
+        complete_ix.accounts.push(AccountMeta::new_readonly(system_program::ID, false));
+        let tx: Transaction = Transaction::new_signed_with_payer(&[complete_ix], Some(&payer_pkey), &[&payer], latest_blockhash);
+        banks_client.process_transaction(tx).await?;
+
+        let acknowledge = |signer: &Keypair, lamports: u64| {
+            let ix: Instruction = crate::instruction::acknowledge_receipt(&signer.pubkey(), &payer_pkey, &escrow_pda, 0, lamports, &escrow_account.data);
+            Transaction::new_signed_with_payer(&[ix], Some(&payer_pkey), &[&payer, signer], latest_blockhash)
+        };
+
+        // 2. neither the payer nor a state that wasn't archived acknowledges it
+        assert!(banks_client.process_transaction(acknowledge(&payer, escrow_account.lamports)).await.is_err());
+        assert!(banks_client.process_transaction(acknowledge(&recipient, escrow_account.lamports + 1)).await.is_err());
+
+        // 3. the recipient does, once
+        banks_client.process_transaction(acknowledge(&recipient, escrow_account.lamports)).await?;
+
+        let archive_account: Account = banks_client.get_account(archive_pda).await?.ok_or("archive wasn't created")?;
+        let records: Vec<ArchivedEscrow> = EscrowArchive::records(&archive_account.data)?;
+        assert_eq!(records[0].outcome, ArchivedEscrow::OUTCOME_COMPLETED);
+        assert!(records[0].acknowledged_at >= records[0].closed_at);
+
+        let new_blockhash: Hash = banks_client.get_new_latest_blockhash(&latest_blockhash).await?;
+        let ix: Instruction = crate::instruction::acknowledge_receipt(&recipient_pkey, &payer_pkey, &escrow_pda, 0, escrow_account.lamports, &escrow_account.data);
+        let tx: Transaction = Transaction::new_signed_with_payer(&[ix], Some(&payer_pkey), &[&payer, &recipient], new_blockhash);
+        assert!(banks_client.process_transaction(tx).await.is_err());
 
         Ok(())
     }
-    
+
+    #[tokio::test]
+    async fn test_complete_settles_into_stake_account() -> Result<(), Box<dyn std::error::Error>> {
+        let mut program_test: ProgramTest = ProgramTest::new(
+            "escrow",
+            crate::ID,
+            processor!(super::entrypoint::process_instruction)
+        );
+
+        let vote_pkey: Pubkey = Pubkey::new_unique();
+        let vote_init: VoteInit = VoteInit {
+            node_pubkey: Pubkey::new_unique(),
+            authorized_voter: Pubkey::new_unique(),
+            authorized_withdrawer: Pubkey::new_unique(),
+            commission: 0
+        };
+        let mut vote_data: Vec<u8> = vec![0; VoteState::size_of()];
+        VoteState::serialize(&VoteStateVersions::new_current(VoteState::new(&vote_init, &Clock::default())), &mut vote_data)?;
+        program_test.add_account(vote_pkey, Account {
+            lamports: Rent::default().minimum_balance(vote_data.len()),
+            data: vote_data,
+            owner: staking::VOTE_PROGRAM_ID,
+            executable: false,
+            rent_epoch: 0
+        });
+
+        let (banks_client, payer, _latest_blockhash) = program_test.start().await;
+        let payer_pkey: Pubkey = payer.pubkey();
+        let recipient: Keypair = Keypair::new();
+        let recipient_pkey: Pubkey = recipient.pubkey();
+        let (escrow_pda, _bump) = derive_escrow_pda(&payer_pkey, &recipient_pkey);
+        let amount: u64 = 5_000_000_000;
+
+        send_signed(&banks_client, &[EscrowInstruction::initialize(&payer_pkey, &recipient_pkey, amount)], &[&payer]).await?;
+        let escrow_id: u64 = EscrowAccount::unpack(&banks_client.get_account(escrow_pda).await?.ok_or("escrow wasn't created")?.data)?.id;
+
+        // 1. only the recipient picks where the payout is staked, and only with a vote account
+        let ix: Instruction = crate::instruction::set_stake_settlement(&payer_pkey, &payer_pkey, &recipient_pkey, &vote_pkey);
+        assert_eq!(instruction_error(&banks_client, &[ix], &[&payer]).await?, InstructionError::MissingRequiredSignature);
+
+        let ix: Instruction = crate::instruction::set_stake_settlement(&recipient_pkey, &payer_pkey, &recipient_pkey, &Pubkey::new_unique());
+        assert_eq!(instruction_error(&banks_client, &[ix], &[&payer, &recipient]).await?, InstructionError::IncorrectProgramId);
+
+        let ix: Instruction = crate::instruction::set_stake_settlement(&recipient_pkey, &payer_pkey, &recipient_pkey, &vote_pkey);
+        send_signed(&banks_client, &[ix], &[&payer, &recipient]).await?;
+
+        // 2. `Complete` then requires the stake accounts
+        let ix: Instruction = EscrowInstruction::complete(&payer_pkey, &recipient_pkey);
+        assert_eq!(instruction_error(&banks_client, &[ix], &[&payer]).await?, InstructionError::NotEnoughAccountKeys);
+
+        // 3. and pays the recipient into a stake account delegated to the vote account, which the recipient controls
+        send_signed(&banks_client, &[crate::instruction::complete_to_stake(&payer_pkey, &recipient_pkey, escrow_id, &vote_pkey)], &[&payer]).await?;
+
+        let stake_account: Account = banks_client.get_account(crate::instruction::find_stake_pda(escrow_id)).await?.ok_or("stake account wasn't created")?;
+        assert_eq!(stake_account.owner, staking::STAKE_PROGRAM_ID);
+        assert_eq!(stake_account.lamports, amount);
+        // `StakeStateV2::Stake`: tag, rent-exempt reserve, staker, withdrawer, lockup, then the delegation's voter
+        assert_eq!(stake_account.data[..4], 2u32.to_le_bytes());
+        assert_eq!(&stake_account.data[12..44], recipient_pkey.as_ref());
+        assert_eq!(&stake_account.data[44..76], recipient_pkey.as_ref());
+        assert_eq!(&stake_account.data[124..156], vote_pkey.as_ref());
+
+        assert!(banks_client.get_account(recipient_pkey).await?.is_none());
+        assert!(banks_client.get_account(escrow_pda).await?.is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_wrong_pda_is_rejected() -> Result<(), Box<dyn std::error::Error>> {
+        let (banks_client, fee_payer, payer, recipient_pkey) = start_with_escrow().await?;
+
+        // 1. initializing at an address that isn't the pair's PDA
+        let mut initialize_ix: Instruction = EscrowInstruction::initialize(&payer.pubkey(), &Pubkey::new_unique(), 101101101);
+        initialize_ix.accounts[2].pubkey = Pubkey::new_unique();
+        let error = instruction_error(&banks_client, &[initialize_ix], &[&fee_payer, &payer]).await?;
+        assert_eq!(error, InstructionError::Custom(EscrowError::PdaMismatch as u32));
+
+        // 2. completing the pair through the escrow of another pair of the same payer
+        let other_recipient_pkey: Pubkey = Pubkey::new_unique();
+        send_signed(&banks_client, &[EscrowInstruction::initialize(&payer.pubkey(), &other_recipient_pkey, 101101101)], &[&fee_payer, &payer]).await?;
+
+        let mut complete_ix: Instruction = EscrowInstruction::complete(&payer.pubkey(), &recipient_pkey);
+        complete_ix.accounts[2].pubkey = derive_escrow_pda(&payer.pubkey(), &other_recipient_pkey).0;
+        let error = instruction_error(&banks_client, &[complete_ix], &[&fee_payer, &payer]).await?;
+        assert_eq!(error, InstructionError::InvalidArgument);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_missing_payer_signature_is_rejected() -> Result<(), Box<dyn std::error::Error>> {
+        let (banks_client, fee_payer, payer, recipient_pkey) = start_with_escrow().await?;
+
+        // the fee payer signs, the escrow's payer doesn't
+        for mut ix in [EscrowInstruction::complete(&payer.pubkey(), &recipient_pkey), EscrowInstruction::close(&payer.pubkey(), &recipient_pkey)] {
+            ix.accounts[0].is_signer = false;
+            let error = instruction_error(&banks_client, &[ix], &[&fee_payer]).await?;
+            assert_eq!(error, InstructionError::MissingRequiredSignature);
+        }
+
+        assert!(banks_client.get_account(derive_escrow_pda(&payer.pubkey(), &recipient_pkey).0).await?.is_some());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_escrow_owned_by_another_program_is_rejected() -> Result<(), Box<dyn std::error::Error>> {
+        let mut program_test: ProgramTest = ProgramTest::new(
+            "escrow",
+            crate::ID,
+            processor!(super::entrypoint::process_instruction)
+        );
+
+        // a well-formed escrow at the right PDA, owned by someone else
+        let payer: Keypair = Keypair::new();
+        let recipient_pkey: Pubkey = Pubkey::new_unique();
+        let (escrow_pda, bump) = derive_escrow_pda(&payer.pubkey(), &recipient_pkey);
+
+        let mut escrow_instance: EscrowAccount = EscrowAccount::new(bump, 0, 0, payer.pubkey(), recipient_pkey, 101101101);
+        escrow_instance.config = derive_config_pda();
+        let mut data: Vec<u8> = vec![0; EscrowAccount::LEN];
+        escrow_instance.pack_into_slice(&mut data);
+
+        program_test.add_account(payer.pubkey(), Account { lamports: 10_000_000_000, data: Vec::new(), owner: system_program::ID, executable: false, rent_epoch: 0 });
+        program_test.add_account(escrow_pda, Account { lamports: 1_000_000_000, data, owner: Pubkey::new_unique(), executable: false, rent_epoch: 0 });
+
+        let (banks_client, fee_payer, _latest_blockhash) = program_test.start().await;
+
+        for ix in [EscrowInstruction::complete(&payer.pubkey(), &recipient_pkey), EscrowInstruction::close(&payer.pubkey(), &recipient_pkey)] {
+            let error = instruction_error(&banks_client, &[ix], &[&fee_payer, &payer]).await?;
+            assert_eq!(error, InstructionError::IncorrectProgramId);
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_double_initialize_is_rejected() -> Result<(), Box<dyn std::error::Error>> {
+        let (banks_client, fee_payer, payer, recipient_pkey) = start_with_escrow().await?;
+
+        // creating the escrow account again fails in the system program (`AccountAlreadyInUse`)
+        let initialize_ix: Instruction = EscrowInstruction::initialize(&payer.pubkey(), &recipient_pkey, 202202202);
+        let error = instruction_error(&banks_client, &[initialize_ix], &[&fee_payer, &payer]).await?;
+        assert_eq!(error, InstructionError::Custom(SystemError::AccountAlreadyInUse as u32));
+
+        // and the escrow keeps its original amount
+        let escrow_account: Account = banks_client.get_account(derive_escrow_pda(&payer.pubkey(), &recipient_pkey).0).await?.ok_or("escrow is gone")?;
+        assert_eq!(EscrowAccount::unpack(&escrow_account.data)?.amount, 101101101);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_prefunded_pdas_are_still_created() -> Result<(), Box<dyn std::error::Error>> {
+        let mut context = test_utils::program_test().start_with_context().await;
+
+        let admin: Keypair = test_utils::funded_keypair(&mut context, 1_000_000_000).await?;
+        test_utils::set_upgrade_authority(&mut context, &admin.pubkey());
+        let payer: Keypair = test_utils::funded_keypair(&mut context, 10_000_000_000).await?;
+        let recipient_pkey: Pubkey = Keypair::new().pubkey();
+        let (escrow_pda, _bump) = derive_escrow_pda(&payer.pubkey(), &recipient_pkey);
+        let fee_vault_pda: Pubkey = Pubkey::find_program_address(&[b"fee_vault"], &crate::ID).0;
+        let amount: u64 = 1_000_000_000;
+
+        // 1. someone sends lamports to the (global) counter, config, fee vault & escrow addresses before they're created
+        let donation: u64 = context.banks_client.get_rent().await?.minimum_balance(0);
+        let donations: Vec<Instruction> = [derive_counter_pda(), derive_config_pda(), fee_vault_pda, escrow_pda].iter()
+            .map(|pda| system_instruction::transfer(&context.payer.pubkey(), pda, donation))
+            .collect();
+        test_utils::send(&mut context, &donations, &[]).await?;
+
+        // 2. the config, its fee vault, the escrow & the counter are created all the same
+        test_utils::send(
+            &mut context,
+            &[
+                crate::instruction::initialize_config(&admin.pubkey(), &Pubkey::default()),
+                crate::instruction::set_protocol_fee(&admin.pubkey(), 100)
+            ],
+            &[&admin]
+        ).await?;
+        test_utils::send(&mut context, &[crate::instruction::initialize(&payer.pubkey(), &recipient_pkey, amount)], &[&payer]).await?;
+
+        for pda in [derive_counter_pda(), derive_config_pda(), fee_vault_pda, escrow_pda] {
+            assert_eq!(context.banks_client.get_account(pda).await?.ok_or("account wasn't created")?.owner, crate::ID);
+        }
+
+        let escrow: EscrowAccount = EscrowAccount::unpack(&context.banks_client.get_account(escrow_pda).await?.ok_or("escrow wasn't created")?.data)?;
+        assert_eq!((escrow.id, escrow.amount), (0, amount));
+
+        let counter: EscrowCounter = EscrowCounter::unpack(&context.banks_client.get_account(derive_counter_pda()).await?.ok_or("counter wasn't created")?.data)?;
+        assert_eq!(counter.next_id, 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_complete_after_close_is_rejected() -> Result<(), Box<dyn std::error::Error>> {
+        let (banks_client, fee_payer, payer, recipient_pkey) = start_with_escrow().await?;
+
+        send_signed(&banks_client, &[EscrowInstruction::close(&payer.pubkey(), &recipient_pkey)], &[&fee_payer, &payer]).await?;
+
+        // the escrow address is a plain system account again
+        let complete_ix: Instruction = EscrowInstruction::complete(&payer.pubkey(), &recipient_pkey);
+        let error = instruction_error(&banks_client, &[complete_ix], &[&fee_payer, &payer]).await?;
+        assert_eq!(error, InstructionError::IncorrectProgramId);
+        assert_eq!(banks_client.get_balance(recipient_pkey).await?, 0);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_truncated_instruction_data_is_rejected() -> Result<(), Box<dyn std::error::Error>> {
+        let (banks_client, fee_payer, payer, recipient_pkey) = start_with_escrow().await?;
+        let other_recipient_pkey: Pubkey = Pubkey::new_unique();
+
+        let initialize_ix: Instruction = EscrowInstruction::initialize(&payer.pubkey(), &other_recipient_pkey, 101101101);
+        let complete_ix: Instruction = EscrowInstruction::complete(&payer.pubkey(), &recipient_pkey);
+
+        // no tag at all, an amount cut short, an 8-byte discriminator without the amount, and a length-prefixed
+        // header announcing more than follows
+        let discriminator_only: Vec<u8> = crate::instruction::with_discriminator(initialize_ix.clone()).data[..9].to_vec();
+        let mut short_frame: Vec<u8> = crate::instruction::with_length_prefix(initialize_ix.clone()).data;
+        short_frame.pop();
+        let truncated: [(&Instruction, Vec<u8>); 4] = [
+            (&complete_ix, Vec::new()),
+            (&initialize_ix, initialize_ix.data[..5].to_vec()),
+            (&initialize_ix, discriminator_only),
+            (&initialize_ix, short_frame)
+        ];
+
+        for (ix, data) in truncated {
+            let ix: Instruction = Instruction { data, ..ix.clone() };
+            let error = instruction_error(&banks_client, &[ix], &[&fee_payer, &payer]).await?;
+            assert_eq!(error, InstructionError::Custom(EscrowError::InstructionDataTooShort as u32));
+        }
+
+        // bytes after the last field of an instruction without a trailing note, and tags the program doesn't know
+        let close_ix: Instruction = EscrowInstruction::close(&payer.pubkey(), &recipient_pkey);
+        let rejected: [(Instruction, EscrowError); 3] = [
+            (Instruction { data: vec![2, 0], ..close_ix.clone() }, EscrowError::InstructionDataTooLong),
+            (Instruction { data: vec![74], ..close_ix.clone() }, EscrowError::UnknownInstruction),
+            (Instruction { data: vec![crate::instruction::EXTENDED_TAG, 0, 1, 0, 0], ..close_ix }, EscrowError::UnknownInstruction)
+        ];
+
+        for (ix, expected) in rejected {
+            let error = instruction_error(&banks_client, &[ix], &[&fee_payer, &payer]).await?;
+            assert_eq!(error, InstructionError::Custom(expected as u32));
+        }
+
+        // while the length-prefixed layout settles like the others
+        let complete_ix: Instruction = crate::instruction::with_length_prefix(complete_ix);
+        send_signed(&banks_client, &[complete_ix], &[&fee_payer, &payer]).await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_milestone_template_releases_in_tranches() -> Result<(), Box<dyn std::error::Error>> {
+        let program_test: ProgramTest = ProgramTest::new(
+            "escrow",
+            crate::ID,
+            processor!(super::entrypoint::process_instruction)
+        );
+
+        // 1. init Client, payer, get latest blockhash & derive PDAs
+        let (banks_client, payer, latest_blockhash) = program_test.start().await;
+        let payer_pkey: Pubkey = payer.pubkey();
+        let recipient_pkey: Pubkey = Keypair::new().pubkey();
+
+        let (escrow_pda, _bump) = derive_escrow_pda(&payer_pkey, &recipient_pkey);
+        let (milestones_pda, _bump) = Pubkey::find_program_address(&[b"milestones", escrow_pda.as_ref()], &crate::ID);
+
+        // 2. init escrow from the 50/30/20 template
+        let amount: u64 = 1_000_000_000;
+        let mut init_ix_payload: Vec<u8> = Vec::with_capacity(10);
+        init_ix_payload.push(16);
+        init_ix_payload.extend_from_slice(&amount.to_le_bytes());
+        init_ix_payload.push(MilestoneTemplate::Freelance50_30_20 as u8);
+
+        let init_ix: Instruction = Instruction::new_with_bytes(
+            crate::ID,
+            &init_ix_payload,
+            vec![
+                AccountMeta::new(payer_pkey, true),
+                AccountMeta::new_readonly(recipient_pkey, false),
+                AccountMeta::new(escrow_pda, false),
+                AccountMeta::new_readonly(system_program::ID, false),
+                AccountMeta::new(derive_counter_pda(), false),
+                AccountMeta::new_readonly(derive_config_pda(), false),
+                AccountMeta::new(milestones_pda, false)
+            ]
+        );
+        let mut init_tx: Transaction = Transaction::new_with_payer(&[init_ix], Some(&payer_pkey));
+        init_tx.sign(&[&payer], latest_blockhash);
+        banks_client.process_transaction(init_tx).await?;
+
+        let milestones_account = banks_client.get_account(milestones_pda).await?.ok_or("milestones weren't created")?;
+        let milestones: EscrowMilestones = EscrowMilestones::unpack(&milestones_account.data)?;
+        let amounts: Vec<u64> = milestones.milestones.iter().map(|milestone| milestone.amount).collect();
+        assert_eq!(amounts, vec![500_000_000, 300_000_000, 200_000_000]);
+
+        // 3. milestone escrows can't be completed at once
+        let complete_ix: Instruction = Instruction::new_with_bytes(
+            crate::ID,
+            &[1],
+            vec![
+                AccountMeta::new(payer_pkey, true),
+                AccountMeta::new(recipient_pkey, false),
+                AccountMeta::new(escrow_pda, false),
+                AccountMeta::new_readonly(derive_config_pda(), false)
+            ]
+        );
+        let mut complete_tx: Transaction = Transaction::new_with_payer(&[complete_ix], Some(&payer_pkey));
+        complete_tx.sign(&[&payer], latest_blockhash);
+        assert!(banks_client.process_transaction(complete_tx).await.is_err());
+
+        // 4. approve the milestones in any order, the last one closes the escrow
+        let mut released: u64 = 0;
+        for index in [1u8, 0, 2] {
+            let approve_ix: Instruction = Instruction::new_with_bytes(
+                crate::ID,
+                &[17, index],
+                vec![
+                    AccountMeta::new(payer_pkey, true),
+                    AccountMeta::new(recipient_pkey, false),
+                    AccountMeta::new(escrow_pda, false),
+                    AccountMeta::new_readonly(derive_config_pda(), false),
+                    AccountMeta::new(milestones_pda, false)
+                ]
+            );
+            let mut approve_tx: Transaction = Transaction::new_with_payer(&[approve_ix], Some(&payer_pkey));
+            approve_tx.sign(&[&payer], latest_blockhash);
+            banks_client.process_transaction(approve_tx).await?;
+
+            released += amounts[index as usize];
+            assert_eq!(banks_client.get_balance(recipient_pkey).await?, released);
+        }
+
+        assert!(banks_client.get_account(escrow_pda).await?.is_none());
+        assert!(banks_client.get_account(milestones_pda).await?.is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_custom_milestones_release_one_tranche_at_a_time() -> Result<(), Box<dyn std::error::Error>> {
+        let mut context = test_utils::program_test().start_with_context().await;
+
+        let payer: Keypair = test_utils::funded_keypair(&mut context, 10_000_000_000).await?;
+        let recipient_pkey: Pubkey = Keypair::new().pubkey();
+        let (escrow_pda, _bump) = derive_escrow_pda(&payer.pubkey(), &recipient_pkey);
+        let (milestones_pda, _bump) = Pubkey::find_program_address(&[b"milestones", escrow_pda.as_ref()], &crate::ID);
+
+        let now: i64 = context.banks_client.get_sysvar::<Clock>().await?.unix_timestamp;
+        let schedule: [(u64, i64); 4] = [(100_000_000, now + 10), (250_000_000, now + 20), (650_000_000, now + 30), (5_000_000, now + 40)];
+
+        // 1. empty tranches & past deadlines are rejected
+        let zero_amount_ix: Instruction = crate::instruction::initialize_milestones(&payer.pubkey(), &recipient_pkey, &[(0, now + 10)]);
+        assert!(test_utils::send(&mut context, &[zero_amount_ix], &[&payer]).await.is_err());
+        let past_deadline_ix: Instruction = crate::instruction::initialize_milestones(&payer.pubkey(), &recipient_pkey, &[(100_000_000, now)]);
+        assert!(test_utils::send(&mut context, &[past_deadline_ix], &[&payer]).await.is_err());
+
+        // 2. the escrow locks the sum of the tranches
+        let init_ix: Instruction = crate::instruction::initialize_milestones(&payer.pubkey(), &recipient_pkey, &schedule);
+        test_utils::send(&mut context, &[init_ix], &[&payer]).await?;
+
+        let escrow: EscrowAccount = EscrowAccount::unpack(&context.banks_client.get_account(escrow_pda).await?.ok_or("escrow wasn't created")?.data)?;
+        assert!(escrow.has_milestones);
+        assert_eq!(escrow.amount, 1_005_000_000);
+
+        let milestones: EscrowMilestones = EscrowMilestones::unpack(&context.banks_client.get_account(milestones_pda).await?.ok_or("milestones weren't created")?.data)?;
+        let stored: Vec<(u64, i64)> = milestones.milestones.iter().map(|milestone| (milestone.amount, milestone.deadline)).collect();
+        assert_eq!(stored, schedule.to_vec());
+
+        // 3. each approval releases only its tranche, once
+        test_utils::send(&mut context, &[crate::instruction::approve_milestone(&payer.pubkey(), &recipient_pkey, 2)], &[&payer]).await?;
+        assert_eq!(context.banks_client.get_balance(recipient_pkey).await?, 650_000_000);
+        assert!(test_utils::send(&mut context, &[crate::instruction::approve_milestone(&payer.pubkey(), &recipient_pkey, 2)], &[&payer]).await.is_err());
+        assert!(test_utils::send(&mut context, &[crate::instruction::approve_milestone(&payer.pubkey(), &recipient_pkey, 4)], &[&payer]).await.is_err());
+
+        test_utils::send(&mut context, &[crate::instruction::approve_milestone(&payer.pubkey(), &recipient_pkey, 0)], &[&payer]).await?;
+        assert_eq!(context.banks_client.get_balance(recipient_pkey).await?, 750_000_000);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_init_escrow_with_note_and_close_note()-> Result<(), Box<dyn std::error::Error>> {
+        use escrow_client::{instructions, pdas};
+
+        let program_test: ProgramTest = ProgramTest::new(
+            "escrow",
+            crate::ID,
+            processor!(super::entrypoint::process_instruction)
+        );
+
+        // 1.1 init Client, payer, get latest blockhash
+        let (banks_client, payer, latest_blockhash) = program_test.start().await;
+        let payer_pkey: Pubkey = payer.pubkey();
+
+        // 1.2 init recipient & derive escrow and note PDAs (with the generated client, which must agree with the program)
+        let recipient: Keypair = Keypair::new();
+        let recipient_pkey: Pubkey = recipient.pubkey();
+
+        let (escrow_pda, _bump) = pdas::find_escrow_pda(&payer_pkey, &recipient_pkey);
+        let (note_pda, _bump) = pdas::find_note_pda(&escrow_pda);
+        assert_eq!(escrow_client::ESCROW_ID, crate::ID);
+        assert_eq!(escrow_pda, derive_escrow_pda(&payer_pkey, &recipient_pkey).0);
+
+        // 2. init escrow with a note (the program doesn't care what the ciphertext is),
+        // which the IDL leaves out: it's appended to the data, its account to the accounts
+        let ciphertext: &[u8] = b"encrypted-to-recipient";
+
+        let initialize_escrow_ix: Instruction = instructions::Initialize {
+            payer: payer_pkey,
+            recipient: recipient_pkey,
+            escrow: escrow_pda,
+            system_program: system_program::ID,
+            counter: pdas::find_counter_pda().0,
+            config: pdas::find_config_pda().0
+        }.instruction_with_remaining_accounts(
+            &instructions::InitializeInstructionArgs { amount: 101101101 },
+            ciphertext,
+            &[AccountMeta::new(note_pda, false)]
+        );
+        let message: Message = Message::new(&[initialize_escrow_ix], Some(&payer_pkey));
+        let mut initialize_escrow_tx: Transaction = Transaction::new_unsigned(message);
+        initialize_escrow_tx.sign(&[&payer], latest_blockhash);
+        banks_client.process_transaction(initialize_escrow_tx).await?;
+
+        // 3. check the generated decoder reads the escrow as the program packed it, and the stored note
+        let escrow_account = banks_client.get_account(escrow_pda).await?.ok_or("escrow account not found")?;
+        let escrow_instance: EscrowAccount = EscrowAccount::unpack(&escrow_account.data)?;
+        let decoded = escrow_client::accounts::EscrowAccount::from_bytes(&escrow_account.data)?;
+        assert_eq!(decoded.id, escrow_instance.id);
+        assert_eq!(decoded.created_at, escrow_instance.created_at);
+        assert_eq!(decoded.mint, escrow_instance.mint);
+        assert_eq!(decoded.max_payout, escrow_instance.max_payout);
+        assert!(escrow_client::accounts::EscrowAccount::from_bytes(&escrow_account.data[1..]).is_err());
+
+        let note_account = banks_client.get_account(note_pda).await?.ok_or("note account not found")?;
+        let note: EscrowNote = EscrowNote::unpack(&note_account.data)?;
+        assert_eq!(note.ciphertext, ciphertext);
+
+        // 4. close note (the default mint: a SOL escrow's)
+        let close_note_ix: Instruction = instructions::CloseNote {
+            authority: payer_pkey,
+            payer: payer_pkey,
+            recipient: recipient_pkey,
+            note: note_pda
+        }.instruction(&instructions::CloseNoteInstructionArgs { mint: Pubkey::default() });
+        let message: Message = Message::new(&[close_note_ix], Some(&payer_pkey));
+        let mut close_note_tx: Transaction = Transaction::new_unsigned(message);
+        close_note_tx.sign(&[&payer], latest_blockhash);
+        banks_client.process_transaction(close_note_tx).await?;
+
+        assert!(banks_client.get_account(note_pda).await?.is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_deadline_gates_complete_and_close() -> Result<(), Box<dyn std::error::Error>> {
+        let mut context = test_utils::program_test().start_with_context().await;
+
+        let payer: Keypair = test_utils::funded_keypair(&mut context, 10_000_000_000).await?;
+        let recipient_pkey: Pubkey = Keypair::new().pubkey();
+        let (escrow_pda, _bump) = derive_escrow_pda(&payer.pubkey(), &recipient_pkey);
+
+        // 1. init escrow with a deadline an hour from now
+        let mut clock: Clock = context.banks_client.get_sysvar::<Clock>().await?;
+        let deadline: i64 = clock.unix_timestamp + 3600;
+
+        let mut initialize_escrow_ix: Instruction = crate::instruction::initialize(&payer.pubkey(), &recipient_pkey, 101101101);
+        initialize_escrow_ix.data[0] = 18;
+        initialize_escrow_ix.data.extend_from_slice(&deadline.to_le_bytes());
+
+        test_utils::send(&mut context, &[initialize_escrow_ix], &[&payer]).await?;
+
+        let escrow_account = context.banks_client.get_account(escrow_pda).await?.ok_or("escrow wasn't created")?;
+        assert_eq!(EscrowAccount::unpack(&escrow_account.data)?.deadline, deadline);
+
+        // 2. the payer can't reclaim before the deadline
+        let close_ix: Instruction = crate::instruction::close(&payer.pubkey(), &recipient_pkey);
+        assert!(test_utils::send(&mut context, &[close_ix.clone()], &[&payer]).await.is_err());
+
+        // 3. once it has passed, the escrow can't be completed anymore, only closed
+        clock.unix_timestamp = deadline;
+        context.set_sysvar(&clock);
+
+        let complete_ix: Instruction = crate::instruction::complete(&payer.pubkey(), &recipient_pkey);
+        assert!(test_utils::send(&mut context, &[complete_ix], &[&payer]).await.is_err());
+
+        test_utils::send(&mut context, &[close_ix], &[&payer]).await?;
+        assert!(context.banks_client.get_account(escrow_pda).await?.is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_escrow_stores_external_reference() -> Result<(), Box<dyn std::error::Error>> {
+        let mut context = test_utils::program_test().start_with_context().await;
+
+        let payer: Keypair = test_utils::funded_keypair(&mut context, 10_000_000_000).await?;
+        let recipient_pkey: Pubkey = Keypair::new().pubkey();
+        let (escrow_pda, _bump) = derive_escrow_pda(&payer.pubkey(), &recipient_pkey);
+        let reference: [u8; 16] = *b"order-0000004217";
+
+        // init escrow without a deadline, tagged with the merchant's order id
+        let mut initialize_escrow_ix: Instruction = crate::instruction::initialize(&payer.pubkey(), &recipient_pkey, 101101101);
+        initialize_escrow_ix.data[0] = 31;
+        initialize_escrow_ix.data.extend_from_slice(&0i64.to_le_bytes());
+        initialize_escrow_ix.data.extend_from_slice(&reference);
+
+        test_utils::send(&mut context, &[initialize_escrow_ix], &[&payer]).await?;
+
+        // the reference sits at a fixed offset, so a memcmp filter finds the escrow
+        let escrow_account = context.banks_client.get_account(escrow_pda).await?.ok_or("escrow wasn't created")?;
+        assert_eq!(&escrow_account.data[EscrowAccount::REFERENCE_OFFSET..EscrowAccount::REFERENCE_OFFSET + 16], &reference);
+
+        let escrow: EscrowAccount = EscrowAccount::unpack(&escrow_account.data)?;
+        assert_eq!(escrow.reference, reference);
+        assert_eq!(escrow.deadline, 0);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_htlc_releases_only_with_preimage() -> Result<(), Box<dyn std::error::Error>> {
+        let mut context = test_utils::program_test().start_with_context().await;
+
+        let payer: Keypair = test_utils::funded_keypair(&mut context, 10_000_000_000).await?;
+        let recipient: Keypair = Keypair::new();
+        let (escrow_pda, _bump) = derive_escrow_pda(&payer.pubkey(), &recipient.pubkey());
+        let amount: u64 = 101101101;
+        let secret: [u8; 32] = [42; 32];
+
+        // 1. init a hash time-locked escrow, refundable after an hour
+        let clock: Clock = context.banks_client.get_sysvar::<Clock>().await?;
+        let mut initialize_escrow_ix: Instruction = crate::instruction::initialize(&payer.pubkey(), &recipient.pubkey(), amount);
+        initialize_escrow_ix.data[0] = 33;
+        initialize_escrow_ix.data.extend_from_slice(&(clock.unix_timestamp + 3600).to_le_bytes());
+        initialize_escrow_ix.data.extend_from_slice(&solana_program::hash::hashv(&[&secret]).to_bytes());
+
+        test_utils::send(&mut context, &[initialize_escrow_ix], &[&payer]).await?;
+
+        // 2. without the secret, or with a wrong one, it can't be released
+        let complete_ix: Instruction = crate::instruction::complete(&payer.pubkey(), &recipient.pubkey());
+        assert!(test_utils::send(&mut context, &[complete_ix.clone()], &[&payer]).await.is_err());
+
+        let mut wrong_preimage_data: Vec<u8> = vec![34];
+        wrong_preimage_data.extend_from_slice(&[7; 32]);
+        let wrong_complete_ix: Instruction = Instruction { data: wrong_preimage_data, ..complete_ix };
+        assert!(test_utils::send(&mut context, &[wrong_complete_ix], &[&payer]).await.is_err());
+
+        // 3. the recipient claims it by revealing the secret
+        let mut claim_ix: Instruction = crate::instruction::claim(&recipient.pubkey(), &payer.pubkey());
+        claim_ix.data.extend_from_slice(&secret);
+        test_utils::send(&mut context, &[claim_ix], &[&recipient]).await?;
+
+        assert!(context.banks_client.get_account(escrow_pda).await?.is_none());
+        assert_eq!(context.banks_client.get_balance(recipient.pubkey()).await?, amount);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_settle_swaps_linked_escrows() -> Result<(), Box<dyn std::error::Error>> {
+        let mut context = test_utils::program_test().start_with_context().await;
+
+        let alice: Keypair = test_utils::funded_keypair(&mut context, 10_000_000_000).await?;
+        let bob: Keypair = test_utils::funded_keypair(&mut context, 10_000_000_000).await?;
+        let (leg_a_pda, _bump) = derive_escrow_pda(&alice.pubkey(), &bob.pubkey());
+        let (leg_b_pda, _bump) = derive_escrow_pda(&bob.pubkey(), &alice.pubkey());
+        let (swap_pda, _bump) = Pubkey::find_program_address(&[b"swap", leg_a_pda.as_ref()], &crate::ID);
+        let (leg_a_amount, leg_b_amount): (u64, u64) = (100_000_000, 250_000_000);
+
+        // 1. alice funds leg A & offers it against bob's (not yet funded) leg B
+        let clock: Clock = context.banks_client.get_sysvar::<Clock>().await?;
+        let mut open_swap_ix_payload: Vec<u8> = vec![35];
+        open_swap_ix_payload.extend_from_slice(&(clock.unix_timestamp + 3600).to_le_bytes());
+        let open_swap_ix: Instruction = Instruction::new_with_bytes(
+            crate::ID,
+            &open_swap_ix_payload,
+            vec![
+                AccountMeta::new(alice.pubkey(), true),
+                AccountMeta::new_readonly(bob.pubkey(), false),
+                AccountMeta::new(leg_a_pda, false),
+                AccountMeta::new_readonly(leg_b_pda, false),
+                AccountMeta::new(swap_pda, false),
+                AccountMeta::new_readonly(system_program::ID, false),
+                AccountMeta::new_readonly(derive_config_pda(), false)
+            ]
+        );
+
+        test_utils::send(
+            &mut context,
+            &[crate::instruction::initialize(&alice.pubkey(), &bob.pubkey(), leg_a_amount), open_swap_ix],
+            &[&alice]
+        ).await?;
+
+        // 2. the locked leg can't be settled on its own, nor refunded before the deadline
+        let complete_ix: Instruction = crate::instruction::complete(&alice.pubkey(), &bob.pubkey());
+        assert!(test_utils::send(&mut context, &[complete_ix], &[&alice]).await.is_err());
+
+        let settle_ix: Instruction = Instruction::new_with_bytes(
+            crate::ID,
+            &[37],
+            vec![
+                AccountMeta::new_readonly(bob.pubkey(), true),
+                AccountMeta::new(alice.pubkey(), false),
+                AccountMeta::new(bob.pubkey(), true),
+                AccountMeta::new(leg_a_pda, false),
+                AccountMeta::new(leg_b_pda, false),
+                AccountMeta::new(swap_pda, false),
+                AccountMeta::new_readonly(derive_config_pda(), false)
+            ]
+        );
+        assert!(test_utils::send(&mut context, &[settle_ix.clone()], &[&bob]).await.is_err());
+
+        // 3. bob funds leg B & joins
+        let join_swap_ix: Instruction = Instruction::new_with_bytes(
+            crate::ID,
+            &[36],
+            vec![
+                AccountMeta::new_readonly(bob.pubkey(), true),
+                AccountMeta::new_readonly(alice.pubkey(), false),
+                AccountMeta::new(leg_b_pda, false),
+                AccountMeta::new_readonly(leg_a_pda, false),
+                AccountMeta::new_readonly(swap_pda, false)
+            ]
+        );
+
+        test_utils::send(
+            &mut context,
+            &[crate::instruction::initialize(&bob.pubkey(), &alice.pubkey(), leg_b_amount), join_swap_ix],
+            &[&bob]
+        ).await?;
+
+        // 4. settling swaps both legs, the rents going back to their payers
+        let rent = context.banks_client.get_rent().await?;
+        let alice_balance: u64 = context.banks_client.get_balance(alice.pubkey()).await?;
+        let bob_balance: u64 = context.banks_client.get_balance(bob.pubkey()).await?;
+
+        test_utils::send(&mut context, &[settle_ix], &[&bob]).await?;
+
+        for pda in [leg_a_pda, leg_b_pda, swap_pda] {
+            assert!(context.banks_client.get_account(pda).await?.is_none());
+        }
+        assert_eq!(
+            context.banks_client.get_balance(alice.pubkey()).await?,
+            alice_balance + leg_b_amount + rent.minimum_balance(EscrowAccount::LEN) + rent.minimum_balance(EscrowSwap::LEN)
+        );
+        assert_eq!(
+            context.banks_client.get_balance(bob.pubkey()).await?,
+            bob_balance + leg_a_amount + rent.minimum_balance(EscrowAccount::LEN)
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_settle_swap_of_mirrored_escrows() -> Result<(), Box<dyn std::error::Error>> {
+        let mut context = test_utils::program_test().start_with_context().await;
+
+        let alice: Keypair = test_utils::funded_keypair(&mut context, 10_000_000_000).await?;
+        let bob: Keypair = test_utils::funded_keypair(&mut context, 10_000_000_000).await?;
+        let (leg_a_pda, _bump) = derive_escrow_pda(&alice.pubkey(), &bob.pubkey());
+        let (leg_b_pda, _bump) = derive_escrow_pda(&bob.pubkey(), &alice.pubkey());
+        let (leg_a_amount, leg_b_amount): (u64, u64) = (100_000_000, 250_000_000);
+
+        // 1. two ordinary escrows, alice → bob & bob → alice
+        test_utils::send(&mut context, &[crate::instruction::initialize(&alice.pubkey(), &bob.pubkey(), leg_a_amount)], &[&alice]).await?;
+
+        // 2. nothing to swap against yet
+        let settle_ix: Instruction = crate::instruction::settle_swap(&alice.pubkey(), &bob.pubkey(), leg_a_amount, leg_b_amount);
+        assert!(test_utils::send(&mut context, &[settle_ix.clone()], &[&alice, &bob]).await.is_err());
+
+        test_utils::send(&mut context, &[crate::instruction::initialize(&bob.pubkey(), &alice.pubkey(), leg_b_amount)], &[&bob]).await?;
+
+        // 3. mismatching terms, or a missing signature, settle nothing
+        let mismatching_ix: Instruction = crate::instruction::settle_swap(&alice.pubkey(), &bob.pubkey(), leg_a_amount, leg_b_amount + 1);
+        assert!(test_utils::send(&mut context, &[mismatching_ix], &[&alice, &bob]).await.is_err());
+
+        let mut unsigned_ix: Instruction = settle_ix.clone();
+        unsigned_ix.accounts[1].is_signer = false;
+        assert!(test_utils::send(&mut context, &[unsigned_ix], &[&alice]).await.is_err());
+
+        // 4. both legs complete at once, the rent going back to their payers
+        let alice_balance: u64 = context.banks_client.get_balance(alice.pubkey()).await?;
+        let bob_balance: u64 = context.banks_client.get_balance(bob.pubkey()).await?;
+        let rent_exempt: u64 = context.banks_client.get_rent().await?.minimum_balance(EscrowAccount::LEN);
+
+        test_utils::send(&mut context, &[settle_ix], &[&alice, &bob]).await?;
+
+        assert!(context.banks_client.get_account(leg_a_pda).await?.is_none());
+        assert!(context.banks_client.get_account(leg_b_pda).await?.is_none());
+        assert_eq!(context.banks_client.get_balance(alice.pubkey()).await?, alice_balance + leg_b_amount + rent_exempt);
+        assert_eq!(context.banks_client.get_balance(bob.pubkey()).await?, bob_balance + leg_a_amount + rent_exempt);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_deposit_tops_up_escrow() -> Result<(), Box<dyn std::error::Error>> {
+        let mut context = test_utils::program_test().start_with_context().await;
+
+        let payer: Keypair = test_utils::funded_keypair(&mut context, 10_000_000_000).await?;
+        let recipient_pkey: Pubkey = Keypair::new().pubkey();
+        let (escrow_pda, _bump) = derive_escrow_pda(&payer.pubkey(), &recipient_pkey);
+        let amount: u64 = 101101101;
+        let top_up: u64 = 50_000_000;
+
+        // 1. init escrow
+        let initialize_escrow_ix: Instruction = crate::instruction::initialize(&payer.pubkey(), &recipient_pkey, amount);
+        test_utils::send(&mut context, &[initialize_escrow_ix], &[&payer]).await?;
+
+        // 2. top it up, an empty deposit is rejected
+        assert!(test_utils::send(&mut context, &[crate::instruction::deposit(&payer.pubkey(), &recipient_pkey, 0)], &[&payer]).await.is_err());
+        test_utils::send(&mut context, &[crate::instruction::deposit(&payer.pubkey(), &recipient_pkey, top_up)], &[&payer]).await?;
+
+        let escrow_account = context.banks_client.get_account(escrow_pda).await?.ok_or("escrow wasn't created")?;
+        let rent_exempt: u64 = context.banks_client.get_rent().await?.minimum_balance(EscrowAccount::LEN);
+        assert_eq!(escrow_account.lamports, rent_exempt + amount + top_up);
+
+        let escrow: EscrowAccount = EscrowAccount::unpack(&escrow_account.data)?;
+        assert_eq!((escrow.payer, escrow.recipient, escrow.amount), (payer.pubkey(), recipient_pkey, amount + top_up));
+
+        // 3. the recipient gets both
+        let complete_ix: Instruction = crate::instruction::complete(&payer.pubkey(), &recipient_pkey);
+        test_utils::send(&mut context, &[complete_ix], &[&payer]).await?;
+
+        assert!(context.banks_client.get_account(escrow_pda).await?.is_none());
+        assert_eq!(context.banks_client.get_balance(recipient_pkey).await?, amount + top_up);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_escrow_records_its_parties_and_rejects_others() -> Result<(), Box<dyn std::error::Error>> {
+        let mut context = test_utils::program_test().start_with_context().await;
+
+        let payer: Keypair = test_utils::funded_keypair(&mut context, 10_000_000_000).await?;
+        let recipient_pkey: Pubkey = Keypair::new().pubkey();
+        let (escrow_pda, _bump) = derive_escrow_pda(&payer.pubkey(), &recipient_pkey);
+
+        // 1. the parties and the locked amount are recorded at `Initialize`
+        test_utils::send(&mut context, &[crate::instruction::initialize(&payer.pubkey(), &recipient_pkey, 1_000_000_000)], &[&payer]).await?;
+
+        let mut escrow_account: Account = context.banks_client.get_account(escrow_pda).await?.ok_or("escrow wasn't created")?;
+        let mut escrow: EscrowAccount = EscrowAccount::unpack(&escrow_account.data)?;
+        assert_eq!((escrow.payer, escrow.recipient, escrow.amount), (payer.pubkey(), recipient_pkey, 1_000_000_000));
+
+        // 2. an escrow recording another recipient than its address was derived for is refused
+        let original_data: Vec<u8> = escrow_account.data.clone();
+        escrow.recipient = Pubkey::new_unique();
+        escrow.pack_into_slice(&mut escrow_account.data);
+        context.set_account(&escrow_pda, &solana_sdk::account::AccountSharedData::from(escrow_account.clone()));
+
+        let complete_ix: Instruction = crate::instruction::complete(&payer.pubkey(), &recipient_pkey);
+        assert_eq!(instruction_error(&context.banks_client, &[complete_ix.clone()], &[&payer]).await?, InstructionError::InvalidArgument);
+
+        // 3. restored, it completes to the recorded recipient
+        escrow_account.data = original_data;
+        context.set_account(&escrow_pda, &solana_sdk::account::AccountSharedData::from(escrow_account));
+        test_utils::send(&mut context, &[complete_ix], &[&payer]).await?;
+
+        assert_eq!(context.banks_client.get_balance(recipient_pkey).await?, 1_000_000_000);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_reserve_then_fund_escrow() -> Result<(), Box<dyn std::error::Error>> {
+        let mut context = test_utils::program_test().start_with_context().await;
+
+        let payer: Keypair = test_utils::funded_keypair(&mut context, 10_000_000_000).await?;
+        let recipient_pkey: Pubkey = Keypair::new().pubkey();
+        let other_recipient_pkey: Pubkey = Keypair::new().pubkey();
+        let (escrow_pda, _bump) = derive_escrow_pda(&payer.pubkey(), &recipient_pkey);
+        let (other_escrow_pda, _bump) = derive_escrow_pda(&payer.pubkey(), &other_recipient_pkey);
+        let amount: u64 = 101101101;
+
+        let mut clock: Clock = context.banks_client.get_sysvar::<Clock>().await?;
+        let reserved_until: i64 = clock.unix_timestamp + 3600;
+        let rent_exempt: u64 = context.banks_client.get_rent().await?.minimum_balance(EscrowAccount::LEN);
+
+        // 1. reserve two escrows, they only hold their rent (and lock no less than it, like `Initialize`)
+        let too_small_ix: Instruction = crate::instruction::reserve(&payer.pubkey(), &recipient_pkey, rent_exempt - 1, reserved_until);
+        let error = instruction_error(&context.banks_client, &[too_small_ix], &[&payer]).await?;
+        assert_eq!(error, InstructionError::Custom(EscrowError::AmountTooSmall as u32));
+
+        test_utils::send(&mut context, &[
+            crate::instruction::reserve(&payer.pubkey(), &recipient_pkey, amount, reserved_until),
+            crate::instruction::reserve(&payer.pubkey(), &other_recipient_pkey, amount, reserved_until)
+        ], &[&payer]).await?;
+
+        let escrow_account = context.banks_client.get_account(escrow_pda).await?.ok_or("escrow wasn't reserved")?;
+        assert_eq!(escrow_account.lamports, rent_exempt);
+
+        let escrow: EscrowAccount = EscrowAccount::unpack(&escrow_account.data)?;
+        assert_eq!((escrow.status, escrow.amount, escrow.reserved_until), (EscrowStatus::Reserved, amount, reserved_until));
+
+        // 2. nothing to release or top up before funding, and nothing to garbage-collect before expiry
+        let complete_ix: Instruction = crate::instruction::complete(&payer.pubkey(), &recipient_pkey);
+        assert!(test_utils::send(&mut context, &[complete_ix.clone()], &[&payer]).await.is_err());
+        assert!(test_utils::send(&mut context, &[crate::instruction::deposit(&payer.pubkey(), &recipient_pkey, amount)], &[&payer]).await.is_err());
+        assert!(test_utils::send(&mut context, &[crate::instruction::close_expired_reservation(&payer.pubkey(), &recipient_pkey)], &[]).await.is_err());
+
+        // 3. fund it, it's now a regular escrow
+        test_utils::send(&mut context, &[crate::instruction::fund(&payer.pubkey(), &recipient_pkey)], &[&payer]).await?;
+
+        let escrow_account = context.banks_client.get_account(escrow_pda).await?.ok_or("escrow was closed")?;
+        assert_eq!(escrow_account.lamports, rent_exempt + amount);
+        assert_eq!(EscrowAccount::unpack(&escrow_account.data)?.status, EscrowStatus::Pending);
+        assert!(test_utils::send(&mut context, &[crate::instruction::fund(&payer.pubkey(), &recipient_pkey)], &[&payer]).await.is_err());
+
+        test_utils::send(&mut context, &[complete_ix], &[&payer]).await?;
+        assert_eq!(context.banks_client.get_balance(recipient_pkey).await?, amount);
+
+        // 4. the other reservation expires unfunded, anyone can close it & the payer gets the rent back
+        clock.unix_timestamp = reserved_until;
+        context.set_sysvar(&clock);
+
+        assert!(test_utils::send(&mut context, &[crate::instruction::fund(&payer.pubkey(), &other_recipient_pkey)], &[&payer]).await.is_err());
+
+        let payer_balance: u64 = context.banks_client.get_balance(payer.pubkey()).await?;
+        test_utils::send(&mut context, &[crate::instruction::close_expired_reservation(&payer.pubkey(), &other_recipient_pkey)], &[]).await?;
+
+        assert!(context.banks_client.get_account(other_escrow_pda).await?.is_none());
+        assert_eq!(context.banks_client.get_balance(payer.pubkey()).await?, payer_balance + rent_exempt);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_withdraw_vested_releases_linearly() -> Result<(), Box<dyn std::error::Error>> {
+        let mut context = test_utils::program_test().start_with_context().await;
+
+        let payer: Keypair = test_utils::funded_keypair(&mut context, 10_000_000_000).await?;
+        let recipient: Keypair = test_utils::funded_keypair(&mut context, 1_000_000_000).await?;
+        let (escrow_pda, _bump) = derive_escrow_pda(&payer.pubkey(), &recipient.pubkey());
+        let amount: u64 = 1_000_000_000;
+
+        // 1. vest `amount` over 1000s, starting in 100s
+        let mut clock: Clock = context.banks_client.get_sysvar::<Clock>().await?;
+        let start: i64 = clock.unix_timestamp + 100;
+        let end: i64 = start + 1000;
+
+        let initialize_escrow_ix: Instruction = crate::instruction::initialize_vesting(&payer.pubkey(), &recipient.pubkey(), amount, start, end);
+        test_utils::send(&mut context, &[initialize_escrow_ix], &[&payer]).await?;
+
+        // 2. nothing has vested yet
+        let withdraw_ix: Instruction = crate::instruction::withdraw_vested(&payer.pubkey(), &recipient.pubkey());
+        assert!(test_utils::send(&mut context, &[withdraw_ix.clone()], &[&recipient]).await.is_err());
+
+        // 3. a quarter in, the recipient gets a quarter & the payer can't back out anymore
+        clock.unix_timestamp = start + 250;
+        context.set_sysvar(&clock);
+
+        let recipient_balance: u64 = context.banks_client.get_balance(recipient.pubkey()).await?;
+        test_utils::send(&mut context, &[withdraw_ix.clone()], &[&recipient]).await?;
+        assert_eq!(context.banks_client.get_balance(recipient.pubkey()).await?, recipient_balance + amount / 4);
+
+        let escrow: EscrowAccount = EscrowAccount::unpack(&context.banks_client.get_account(escrow_pda).await?.ok_or("escrow was closed")?.data)?;
+        assert_eq!(escrow.withdrawn, amount / 4);
+        assert!(test_utils::send(&mut context, &[crate::instruction::close(&payer.pubkey(), &recipient.pubkey())], &[&payer]).await.is_err());
+
+        // 4. halfway, only the second quarter is left to withdraw
+        clock.unix_timestamp = start + 500;
+        context.set_sysvar(&clock);
+
+        test_utils::send(&mut context, &[withdraw_ix.clone()], &[&recipient]).await?;
+        assert_eq!(context.banks_client.get_balance(recipient.pubkey()).await?, recipient_balance + amount / 2);
+
+        // 5. past the end, the rest is withdrawn and the escrow closed, its rent going back to the payer
+        clock.unix_timestamp = end + 1;
+        context.set_sysvar(&clock);
+
+        let payer_balance: u64 = context.banks_client.get_balance(payer.pubkey()).await?;
+        let rent_exempt: u64 = context.banks_client.get_rent().await?.minimum_balance(EscrowAccount::LEN);
+
+        test_utils::send(&mut context, &[withdraw_ix], &[&recipient]).await?;
+
+        assert!(context.banks_client.get_account(escrow_pda).await?.is_none());
+        assert_eq!(context.banks_client.get_balance(recipient.pubkey()).await?, recipient_balance + amount);
+        assert_eq!(context.banks_client.get_balance(payer.pubkey()).await?, payer_balance + rent_exempt);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_claim_rejects_vesting_escrow() -> Result<(), Box<dyn std::error::Error>> {
+        let mut context = test_utils::program_test().start_with_context().await;
+
+        let payer: Keypair = test_utils::funded_keypair(&mut context, 10_000_000_000).await?;
+        let recipient: Keypair = test_utils::funded_keypair(&mut context, 1_000_000_000).await?;
+        let (escrow_pda, _bump) = derive_escrow_pda(&payer.pubkey(), &recipient.pubkey());
+        let amount: u64 = 1_000_000_000;
+
+        let mut clock: Clock = context.banks_client.get_sysvar::<Clock>().await?;
+        let start: i64 = clock.unix_timestamp;
+        let end: i64 = start + 1000;
+
+        let initialize_escrow_ix: Instruction = crate::instruction::initialize_vesting(&payer.pubkey(), &recipient.pubkey(), amount, start, end);
+        test_utils::send(&mut context, &[initialize_escrow_ix], &[&payer]).await?;
+
+        // neither mid-schedule nor past its end can the recipient take the escrow in one go, it's only withdrawn as it vests
+        let claim_ix: Instruction = crate::instruction::claim(&recipient.pubkey(), &payer.pubkey());
+        for now in [start + 250, end + 1] {
+            clock.unix_timestamp = now;
+            context.set_sysvar(&clock);
+
+            let error = instruction_error(&context.banks_client, &[claim_ix.clone()], &[&recipient]).await?;
+            assert_eq!(error, InstructionError::InvalidArgument);
+        }
+
+        let escrow: EscrowAccount = EscrowAccount::unpack(&context.banks_client.get_account(escrow_pda).await?.ok_or("escrow was closed")?.data)?;
+        assert_eq!(escrow.withdrawn, 0);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_team_vesting_withdraws_per_beneficiary() -> Result<(), Box<dyn std::error::Error>> {
+        let mut context = test_utils::program_test().start_with_context().await;
+
+        let payer: Keypair = test_utils::funded_keypair(&mut context, 10_000_000_000).await?;
+        let recipient: Keypair = test_utils::funded_keypair(&mut context, 1_000_000_000).await?;
+        let member_a: Keypair = test_utils::funded_keypair(&mut context, 1_000_000_000).await?;
+        let member_b: Keypair = test_utils::funded_keypair(&mut context, 1_000_000_000).await?;
+        let (escrow_pda, _bump) = derive_escrow_pda(&payer.pubkey(), &recipient.pubkey());
+        let amount: u64 = 1_000_000_000;
+
+        // 1. vest `amount` over 1000s: 30% to a, 20% to b, the recipient keeping 50%
+        let mut clock: Clock = context.banks_client.get_sysvar::<Clock>().await?;
+        let start: i64 = clock.unix_timestamp + 100;
+        let end: i64 = start + 1000;
+        let splits: [(Pubkey, u16); 2] = [(member_a.pubkey(), 3000), (member_b.pubkey(), 2000)];
+
+        let all_to_splits_ix: Instruction = crate::instruction::initialize_team_vesting(
+            &payer.pubkey(),
+            &recipient.pubkey(),
+            amount,
+            start,
+            end,
+            &[(member_a.pubkey(), 6000), (member_b.pubkey(), 4000)]
+        );
+        assert!(test_utils::send(&mut context, &[all_to_splits_ix], &[&payer]).await.is_err());
+
+        let initialize_escrow_ix: Instruction = crate::instruction::initialize_team_vesting(
+            &payer.pubkey(),
+            &recipient.pubkey(),
+            amount,
+            start,
+            end,
+            &splits
+        );
+        let share_a_pda: Pubkey = initialize_escrow_ix.accounts[8].pubkey;
+        test_utils::send(&mut context, &[initialize_escrow_ix], &[&payer]).await?;
+
+        let share_a: VestingShare = VestingShare::unpack(&context.banks_client.get_account(share_a_pda).await?.ok_or("share wasn't created")?.data)?;
+        assert_eq!((share_a.beneficiary, share_a.allocation, share_a.withdrawn), (member_a.pubkey(), amount * 3 / 10, 0));
+
+        // 2. a quarter in, a gets a quarter of its 30%, and can't withdraw it twice
+        clock.unix_timestamp = start + 250;
+        context.set_sysvar(&clock);
+
+        let withdraw_a_ix: Instruction = crate::instruction::withdraw_vested_share(&payer.pubkey(), &recipient.pubkey(), &member_a.pubkey());
+        let member_a_balance: u64 = context.banks_client.get_balance(member_a.pubkey()).await?;
+        test_utils::send(&mut context, &[withdraw_a_ix.clone()], &[&member_a]).await?;
+        assert_eq!(context.banks_client.get_balance(member_a.pubkey()).await?, member_a_balance + amount * 3 / 40);
+        assert!(test_utils::send(&mut context, &[withdraw_a_ix.clone()], &[&member_a]).await.is_err());
+
+        let escrow: EscrowAccount = EscrowAccount::unpack(&context.banks_client.get_account(escrow_pda).await?.ok_or("escrow was closed")?.data)?;
+        assert_eq!(escrow.withdrawn, amount * 3 / 40);
+
+        // the escrow settles only through the beneficiaries' withdrawals, nor can anyone else withdraw
+        assert!(test_utils::send(&mut context, &[EscrowInstruction::complete(&payer.pubkey(), &recipient.pubkey())], &[&payer]).await.is_err());
+        assert!(test_utils::send(&mut context, &[crate::instruction::withdraw_vested(&payer.pubkey(), &recipient.pubkey())], &[&recipient]).await.is_err());
+        let outsider: Keypair = test_utils::funded_keypair(&mut context, 1_000_000_000).await?;
+        let withdraw_outsider_ix: Instruction = crate::instruction::withdraw_vested_share(&payer.pubkey(), &recipient.pubkey(), &outsider.pubkey());
+        assert!(test_utils::send(&mut context, &[withdraw_outsider_ix], &[&outsider]).await.is_err());
+
+        // 3. past the end, every beneficiary withdraws the rest of its part, a's share is closed
+        clock.unix_timestamp = end + 1;
+        context.set_sysvar(&clock);
+
+        test_utils::send(&mut context, &[withdraw_a_ix], &[&member_a]).await?;
+        assert_eq!(context.banks_client.get_balance(member_a.pubkey()).await?, member_a_balance + amount * 3 / 10);
+        assert!(context.banks_client.get_account(share_a_pda).await?.is_none());
+
+        let recipient_balance: u64 = context.banks_client.get_balance(recipient.pubkey()).await?;
+        let withdraw_recipient_ix: Instruction = crate::instruction::withdraw_vested_share(&payer.pubkey(), &recipient.pubkey(), &recipient.pubkey());
+        test_utils::send(&mut context, &[withdraw_recipient_ix], &[&recipient]).await?;
+        assert_eq!(context.banks_client.get_balance(recipient.pubkey()).await?, recipient_balance + amount / 2);
+
+        // 4. the last one closes the escrow, every rent going back to the payer
+        let payer_balance: u64 = context.banks_client.get_balance(payer.pubkey()).await?;
+        let rent = context.banks_client.get_rent().await?;
+        let member_b_balance: u64 = context.banks_client.get_balance(member_b.pubkey()).await?;
+
+        let withdraw_b_ix: Instruction = crate::instruction::withdraw_vested_share(&payer.pubkey(), &recipient.pubkey(), &member_b.pubkey());
+        test_utils::send(&mut context, &[withdraw_b_ix], &[&member_b]).await?;
+
+        assert!(context.banks_client.get_account(escrow_pda).await?.is_none());
+        assert_eq!(context.banks_client.get_balance(member_b.pubkey()).await?, member_b_balance + amount / 5);
+        assert_eq!(
+            context.banks_client.get_balance(payer.pubkey()).await?,
+            payer_balance + crate::rent::escrow(&rent) + crate::rent::assignments(&rent, 2) + crate::rent::vesting_share(&rent)
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_late_completion_pays_capped_late_fee() -> Result<(), Box<dyn std::error::Error>> {
+        let mut context = test_utils::program_test().start_with_context().await;
+
+        let payer: Keypair = test_utils::funded_keypair(&mut context, 10_000_000_000).await?;
+        let recipient_pkey: Pubkey = Keypair::new().pubkey();
+        let (escrow_pda, _bump) = derive_escrow_pda(&payer.pubkey(), &recipient_pkey);
+
+        // 1. init escrow with a deadline & agree to 1% per day late, at most 5%
+        let mut clock: Clock = context.banks_client.get_sysvar::<Clock>().await?;
+        let deadline: i64 = clock.unix_timestamp + 3600;
+        let amount: u64 = 101101101;
+
+        let mut initialize_escrow_ix: Instruction = crate::instruction::initialize(&payer.pubkey(), &recipient_pkey, amount);
+        initialize_escrow_ix.data[0] = 18;
+        initialize_escrow_ix.data.extend_from_slice(&deadline.to_le_bytes());
+
+        let mut late_fee_ix_payload: Vec<u8> = vec![19];
+        late_fee_ix_payload.extend_from_slice(&100u16.to_le_bytes());
+        late_fee_ix_payload.extend_from_slice(&500u16.to_le_bytes());
+        let set_late_fee_ix: Instruction = Instruction::new_with_bytes(
+            crate::ID,
+            &late_fee_ix_payload,
+            vec![
+                AccountMeta::new_readonly(payer.pubkey(), true),
+                AccountMeta::new_readonly(recipient_pkey, false),
+                AccountMeta::new(escrow_pda, false)
+            ]
+        );
+
+        test_utils::send(&mut context, &[initialize_escrow_ix, set_late_fee_ix.clone()], &[&payer]).await?;
+
+        // 2. the fee can't be renegotiated
+        assert!(test_utils::send(&mut context, &[set_late_fee_ix], &[&payer]).await.is_err());
+
+        // 3. complete 3 days (and a bit) late, the recipient gets 3% on top
+        clock.unix_timestamp = deadline + 3 * 24 * 60 * 60 + 100;
+        context.set_sysvar(&clock);
+
+        let mut complete_ix: Instruction = crate::instruction::complete(&payer.pubkey(), &recipient_pkey);
+        complete_ix.accounts.push(AccountMeta::new_readonly(system_program::ID, false));
+        test_utils::send(&mut context, &[complete_ix], &[&payer]).await?;
+
+        assert_eq!(context.banks_client.get_balance(recipient_pkey).await?, amount + amount * 300 / 10_000);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_complete_fails_until_unlocked() -> Result<(), Box<dyn std::error::Error>> {
+        let mut context = test_utils::program_test().start_with_context().await;
+
+        let payer: Keypair = test_utils::funded_keypair(&mut context, 10_000_000_000).await?;
+        let recipient_pkey: Pubkey = Keypair::new().pubkey();
+        let (escrow_pda, _bump) = derive_escrow_pda(&payer.pubkey(), &recipient_pkey);
+
+        // 1. init escrow & lock it for an hour
+        let mut clock: Clock = context.banks_client.get_sysvar::<Clock>().await?;
+        let unlock_at: i64 = clock.unix_timestamp + 3600;
+
+        let mut unlock_at_ix_payload: Vec<u8> = vec![20];
+        unlock_at_ix_payload.extend_from_slice(&unlock_at.to_le_bytes());
+        let set_unlock_at_ix: Instruction = Instruction::new_with_bytes(
+            crate::ID,
+            &unlock_at_ix_payload,
+            vec![
+                AccountMeta::new_readonly(payer.pubkey(), true),
+                AccountMeta::new_readonly(recipient_pkey, false),
+                AccountMeta::new(escrow_pda, false)
+            ]
+        );
+
+        test_utils::send(
+            &mut context,
+            &[crate::instruction::initialize(&payer.pubkey(), &recipient_pkey, 101101101), set_unlock_at_ix],
+            &[&payer]
+        ).await?;
+
+        // 2. completing early fails with `EscrowError::StillLocked`
+        let complete_ix: Instruction = crate::instruction::complete(&payer.pubkey(), &recipient_pkey);
+        let err = test_utils::send(&mut context, &[complete_ix.clone()], &[&payer]).await.unwrap_err();
+        assert!(err.to_string().contains(&format!("custom program error: {:#x}", crate::error::EscrowError::StillLocked as u32)));
+
+        // 3. and succeeds once the lock has expired
+        clock.unix_timestamp = unlock_at;
+        context.set_sysvar(&clock);
+
+        test_utils::send(&mut context, &[complete_ix], &[&payer]).await?;
+        assert!(context.banks_client.get_account(escrow_pda).await?.is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_assigned_claim_pays_out_to_assignee() -> Result<(), Box<dyn std::error::Error>> {
+        let mut context = test_utils::program_test().start_with_context().await;
+
+        let payer: Keypair = test_utils::funded_keypair(&mut context, 10_000_000_000).await?;
+        let recipient: Keypair = Keypair::new();
+        let factor_pkey: Pubkey = Keypair::new().pubkey();
+        let (escrow_pda, _bump) = derive_escrow_pda(&payer.pubkey(), &recipient.pubkey());
+
+        // 1. init escrow & let the recipient sell the claim to a factor
+        let assign_claim_ix: Instruction = Instruction::new_with_bytes(
+            crate::ID,
+            &[21],
+            vec![
+                AccountMeta::new_readonly(recipient.pubkey(), true),
+                AccountMeta::new_readonly(payer.pubkey(), false),
+                AccountMeta::new_readonly(recipient.pubkey(), false),
+                AccountMeta::new(escrow_pda, false),
+                AccountMeta::new_readonly(factor_pkey, false)
+            ]
+        );
+
+        test_utils::send(
+            &mut context,
+            &[crate::instruction::initialize(&payer.pubkey(), &recipient.pubkey(), 101101101), assign_claim_ix],
+            &[&payer, &recipient]
+        ).await?;
+
+        let escrow_account = context.banks_client.get_account(escrow_pda).await?.ok_or("escrow wasn't created")?;
+        assert_eq!(EscrowAccount::unpack(&escrow_account.data)?.assignee, factor_pkey);
+
+        // 2. completing without the assignee fails, with it the factor gets paid
+        let mut complete_ix: Instruction = crate::instruction::complete(&payer.pubkey(), &recipient.pubkey());
+        assert!(test_utils::send(&mut context, &[complete_ix.clone()], &[&payer]).await.is_err());
+
+        complete_ix.accounts.push(AccountMeta::new(factor_pkey, false));
+        test_utils::send(&mut context, &[complete_ix], &[&payer]).await?;
+
+        assert_eq!(context.banks_client.get_balance(factor_pkey).await?, 101101101);
+        assert_eq!(context.banks_client.get_balance(recipient.pubkey()).await?, 0);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_escrow_to_domain_resolves_owner() -> Result<(), Box<dyn std::error::Error>> {
+        let name_account = |parent_name: &Pubkey, owner_pkey: &Pubkey| -> Account {
+            let mut data: Vec<u8> = Vec::with_capacity(name_service::NameRecord::HEADER_LEN);
+            data.extend_from_slice(parent_name.as_ref());
+            data.extend_from_slice(owner_pkey.as_ref());
+            data.extend_from_slice(Pubkey::default().as_ref());
+
+            Account { lamports: 10_000_000, data, owner: NAME_SERVICE_PROGRAM_ID, executable: false, rent_epoch: 0 }
+        };
+
+        let alice_domain: Pubkey = name_service::domain_address("alice.sol");
+        let bob_domain: Pubkey = name_service::domain_address("bob");
+        let subdomain: Pubkey = Pubkey::new_unique();
+        let (alice, bob, bob_buyer) = (Keypair::new(), Keypair::new(), Keypair::new());
+
+        // 1. alice.sol & bob.sol, plus a name outside `.sol`
+        let mut program_test: ProgramTest = test_utils::program_test();
+        program_test.add_account(alice_domain, name_account(&SOL_TLD_AUTHORITY, &alice.pubkey()));
+        program_test.add_account(bob_domain, name_account(&SOL_TLD_AUTHORITY, &bob.pubkey()));
+        program_test.add_account(subdomain, name_account(&alice_domain, &alice.pubkey()));
+        let mut context = program_test.start_with_context().await;
+
+        let payer: Keypair = test_utils::funded_keypair(&mut context, 10_000_000_000).await?;
+        let amount: u64 = 1_000_000_000;
+
+        let subdomain_ix: Instruction = crate::instruction::initialize_to_domain(&payer.pubkey(), &subdomain, amount, RecipientResolution::Pinned);
+        assert!(test_utils::send(&mut context, &[subdomain_ix], &[&payer]).await.is_err());
+
+        // 2. alice's ownership is pinned at creation, so she's paid even once she sold the domain
+        let pinned_ix: Instruction = crate::instruction::initialize_to_domain(&payer.pubkey(), &alice_domain, amount, RecipientResolution::Pinned);
+        test_utils::send(&mut context, &[pinned_ix], &[&payer]).await?;
+
+        let (alice_escrow_pda, _bump) = derive_escrow_pda(&payer.pubkey(), &alice_domain);
+        let escrow: EscrowAccount = EscrowAccount::unpack(&context.banks_client.get_account(alice_escrow_pda).await?.ok_or("escrow wasn't created")?.data)?;
+        assert_eq!((escrow.recipient, escrow.assignee, escrow.resolve_recipient), (alice_domain, alice.pubkey(), false));
+
+        context.set_account(&alice_domain, &solana_sdk::account::AccountSharedData::from(name_account(&SOL_TLD_AUTHORITY, &bob_buyer.pubkey())));
+
+        let to_buyer_ix: Instruction = crate::instruction::complete_to_domain(&payer.pubkey(), &alice_domain, &bob_buyer.pubkey());
+        assert!(test_utils::send(&mut context, &[to_buyer_ix], &[&payer]).await.is_err());
+        test_utils::send(&mut context, &[crate::instruction::complete_to_domain(&payer.pubkey(), &alice_domain, &alice.pubkey())], &[&payer]).await?;
+        assert_eq!(context.banks_client.get_balance(alice.pubkey()).await?, amount);
+
+        // 3. bob.sol is resolved at settlement: once sold, only the buyer can claim it
+        let resolved_ix: Instruction = crate::instruction::initialize_to_domain(&payer.pubkey(), &bob_domain, amount, RecipientResolution::AtSettlement);
+        test_utils::send(&mut context, &[resolved_ix], &[&payer]).await?;
+
+        context.set_account(&bob_domain, &solana_sdk::account::AccountSharedData::from(name_account(&SOL_TLD_AUTHORITY, &bob_buyer.pubkey())));
+
+        let bob_claim_ix: Instruction = crate::instruction::claim_to_domain(&bob.pubkey(), &payer.pubkey(), &bob_domain);
+        assert!(test_utils::send(&mut context, &[bob_claim_ix], &[&bob]).await.is_err());
+
+        let buyer_claim_ix: Instruction = crate::instruction::claim_to_domain(&bob_buyer.pubkey(), &payer.pubkey(), &bob_domain);
+        test_utils::send(&mut context, &[buyer_claim_ix], &[&bob_buyer]).await?;
+
+        assert!(context.banks_client.get_account(derive_escrow_pda(&payer.pubkey(), &bob_domain).0).await?.is_none());
+        assert_eq!(context.banks_client.get_balance(bob_buyer.pubkey()).await?, amount);
+        assert_eq!(context.banks_client.get_balance(bob.pubkey()).await?, 0);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_recipient_claims_without_payer() -> Result<(), Box<dyn std::error::Error>> {
+        let mut context = test_utils::program_test().start_with_context().await;
+
+        let payer: Keypair = test_utils::funded_keypair(&mut context, 10_000_000_000).await?;
+        let recipient: Keypair = Keypair::new();
+        let (escrow_pda, _bump) = derive_escrow_pda(&payer.pubkey(), &recipient.pubkey());
+
+        test_utils::send(&mut context, &[crate::instruction::initialize(&payer.pubkey(), &recipient.pubkey(), 101101101)], &[&payer]).await?;
+
+        let payer_balance: u64 = context.banks_client.get_balance(payer.pubkey()).await?;
+
+        // only the recipient signs, the payer gets the escrow's rent back
+        test_utils::send(&mut context, &[crate::instruction::claim(&recipient.pubkey(), &payer.pubkey())], &[&recipient]).await?;
+
+        assert!(context.banks_client.get_account(escrow_pda).await?.is_none());
+        assert_eq!(context.banks_client.get_balance(recipient.pubkey()).await?, 101101101);
+        assert!(context.banks_client.get_balance(payer.pubkey()).await? > payer_balance);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_accepted_escrow_needs_mutual_cancellation() -> Result<(), Box<dyn std::error::Error>> {
+        let mut context = test_utils::program_test().start_with_context().await;
+
+        let payer: Keypair = test_utils::funded_keypair(&mut context, 10_000_000_000).await?;
+        let recipient: Keypair = Keypair::new();
+        let (escrow_pda, _bump) = derive_escrow_pda(&payer.pubkey(), &recipient.pubkey());
+
+        // 1. init escrow & let the recipient accept it
+        let accept_ix: Instruction = Instruction::new_with_bytes(
+            crate::ID,
+            &[23],
+            vec![
+                AccountMeta::new_readonly(recipient.pubkey(), true),
+                AccountMeta::new_readonly(payer.pubkey(), false),
+                AccountMeta::new(escrow_pda, false)
+            ]
+        );
+
+        test_utils::send(
+            &mut context,
+            &[crate::instruction::initialize(&payer.pubkey(), &recipient.pubkey(), 100_000_000), accept_ix.clone()],
+            &[&payer, &recipient]
+        ).await?;
+
+        let escrow_account = context.banks_client.get_account(escrow_pda).await?.ok_or("escrow wasn't created")?;
+        assert_eq!(EscrowAccount::unpack(&escrow_account.data)?.status, EscrowStatus::Accepted);
+
+        // Accepted -> Accepted isn't a transition
+        assert!(test_utils::send(&mut context, &[accept_ix], &[&recipient]).await.is_err());
+
+        // 2. the payer can't close it alone anymore
+        let close_ix: Instruction = crate::instruction::close(&payer.pubkey(), &recipient.pubkey());
+        assert!(test_utils::send(&mut context, &[close_ix], &[&payer]).await.is_err());
+
+        // 3. but both can cancel it, 30% back to the payer & 70% to the recipient
+        let mut cancel_ix_payload: Vec<u8> = vec![24];
+        cancel_ix_payload.extend_from_slice(&3_000u16.to_le_bytes());
+        cancel_ix_payload.extend_from_slice(&7_000u16.to_le_bytes());
+        let cancel_mutual_ix: Instruction = Instruction::new_with_bytes(
+            crate::ID,
+            &cancel_ix_payload,
+            vec![
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new(recipient.pubkey(), true),
+                AccountMeta::new(escrow_pda, false),
+                AccountMeta::new_readonly(derive_config_pda(), false)
+            ]
+        );
+
+        test_utils::send(&mut context, &[cancel_mutual_ix], &[&payer, &recipient]).await?;
+
+        assert!(context.banks_client.get_account(escrow_pda).await?.is_none());
+        assert_eq!(context.banks_client.get_balance(recipient.pubkey()).await?, 70_000_000);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_complete_splits_payout_among_claim_shares() -> Result<(), Box<dyn std::error::Error>> {
+        let mut context = test_utils::program_test().start_with_context().await;
+
+        let payer: Keypair = test_utils::funded_keypair(&mut context, 10_000_000_000).await?;
+        let recipient: Keypair = test_utils::funded_keypair(&mut context, 1_000_000_000).await?;
+        let assignee_pkeys: [Pubkey; 2] = [Keypair::new().pubkey(), Keypair::new().pubkey()];
+        let (escrow_pda, _bump) = derive_escrow_pda(&payer.pubkey(), &recipient.pubkey());
+        let (assignments_pda, _bump) = Pubkey::find_program_address(&[b"assignments", escrow_pda.as_ref()], &crate::ID);
+
+        test_utils::send(&mut context, &[crate::instruction::initialize(&payer.pubkey(), &recipient.pubkey(), 1_000_000_000)], &[&payer]).await?;
+
+        // 1. the recipient sells 25% and 10% of the payout
+        for (assignee_pkey, bps) in assignee_pkeys.iter().zip([2_500u16, 1_000]) {
+            let mut assign_ix_payload: Vec<u8> = vec![25];
+            assign_ix_payload.extend_from_slice(&bps.to_le_bytes());
+            let assign_claim_share_ix: Instruction = Instruction::new_with_bytes(
+                crate::ID,
+                &assign_ix_payload,
+                vec![
+                    AccountMeta::new(recipient.pubkey(), true),
+                    AccountMeta::new_readonly(payer.pubkey(), false),
+                    AccountMeta::new(escrow_pda, false),
+                    AccountMeta::new(assignments_pda, false),
+                    AccountMeta::new_readonly(*assignee_pkey, false),
+                    AccountMeta::new_readonly(system_program::ID, false)
+                ]
+            );
+            test_utils::send(&mut context, &[assign_claim_share_ix], &[&recipient]).await?;
+        }
+
+        let recipient_balance: u64 = context.banks_client.get_balance(recipient.pubkey()).await?;
+        let assignments_rent: u64 = context.banks_client.get_balance(assignments_pda).await?;
+
+        // 2. complete pays every share, the recipient keeps 65% and gets the assignments' rent back
+        let mut complete_ix: Instruction = crate::instruction::complete(&payer.pubkey(), &recipient.pubkey());
+        complete_ix.accounts.push(AccountMeta::new(assignments_pda, false));
+        complete_ix.accounts.extend(assignee_pkeys.iter().map(|assignee_pkey| AccountMeta::new(*assignee_pkey, false)));
+        test_utils::send(&mut context, &[complete_ix], &[&payer]).await?;
+
+        assert_eq!(context.banks_client.get_balance(assignee_pkeys[0]).await?, 250_000_000);
+        assert_eq!(context.banks_client.get_balance(assignee_pkeys[1]).await?, 100_000_000);
+        assert_eq!(context.banks_client.get_balance(recipient.pubkey()).await?, recipient_balance + 650_000_000 + assignments_rent);
+        assert!(context.banks_client.get_account(assignments_pda).await?.is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_claim_share_rent_taken_from_payout() -> Result<(), Box<dyn std::error::Error>> {
+        let mut context = test_utils::program_test().start_with_context().await;
+
+        let payer: Keypair = test_utils::funded_keypair(&mut context, 10_000_000_000).await?;
+        // holds no SOL at all
+        let recipient: Keypair = Keypair::new();
+        let assignee_pkey: Pubkey = Keypair::new().pubkey();
+        let (escrow_pda, _bump) = derive_escrow_pda(&payer.pubkey(), &recipient.pubkey());
+        let (assignments_pda, _bump) = Pubkey::find_program_address(&[b"assignments", escrow_pda.as_ref()], &crate::ID);
+
+        test_utils::send(&mut context, &[crate::instruction::initialize(&payer.pubkey(), &recipient.pubkey(), 1_000_000_000)], &[&payer]).await?;
+
+        // 1. the recipient can't front the rent, the escrow does
+        let assign_ix = |from_payout: bool| crate::instruction::assign_claim_share(&recipient.pubkey(), &payer.pubkey(), &assignee_pkey, 2_500, from_payout);
+        assert!(test_utils::send(&mut context, &[assign_ix(false)], &[&recipient]).await.is_err());
+        test_utils::send(&mut context, &[assign_ix(true)], &[&recipient]).await?;
+
+        let assignments_rent: u64 = context.banks_client.get_balance(assignments_pda).await?;
+        let escrow: EscrowAccount = EscrowAccount::unpack(&context.banks_client.get_account(escrow_pda).await?.ok_or("escrow wasn't created")?.data)?;
+        assert_eq!(escrow.rent_advanced, assignments_rent);
+
+        // 2. complete still splits the full amount, the recipient's part including the rent
+        let mut complete_ix: Instruction = crate::instruction::complete(&payer.pubkey(), &recipient.pubkey());
+        complete_ix.accounts.push(AccountMeta::new(assignments_pda, false));
+        complete_ix.accounts.push(AccountMeta::new(assignee_pkey, false));
+        test_utils::send(&mut context, &[complete_ix], &[&payer]).await?;
+
+        assert_eq!(context.banks_client.get_balance(assignee_pkey).await?, 250_000_000);
+        assert_eq!(context.banks_client.get_balance(recipient.pubkey()).await?, 750_000_000);
+        assert!(context.banks_client.get_account(assignments_pda).await?.is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_complete_pays_split_recipients() -> Result<(), Box<dyn std::error::Error>> {
+        let mut context = test_utils::program_test().start_with_context().await;
+
+        let payer: Keypair = test_utils::funded_keypair(&mut context, 10_000_000_000).await?;
+        let seller: Keypair = test_utils::funded_keypair(&mut context, 1_000_000_000).await?;
+        let (platform_pkey, referrer_pkey): (Pubkey, Pubkey) = (Keypair::new().pubkey(), Keypair::new().pubkey());
+        let (escrow_pda, _bump) = derive_escrow_pda(&payer.pubkey(), &seller.pubkey());
+        let (assignments_pda, _bump) = Pubkey::find_program_address(&[b"assignments", escrow_pda.as_ref()], &crate::ID);
+
+        // 1. more than 100% can't be split off
+        let overflowing_ix: Instruction = crate::instruction::initialize_split(
+            &payer.pubkey(),
+            &seller.pubkey(),
+            1_000_000_000,
+            &[(platform_pkey, 9_000), (referrer_pkey, 1_001)]
+        );
+        assert!(test_utils::send(&mut context, &[overflowing_ix], &[&payer]).await.is_err());
+
+        // 2. the platform takes 5% & the referrer 1%, the seller gets the rest
+        let initialize_escrow_ix: Instruction = crate::instruction::initialize_split(
+            &payer.pubkey(),
+            &seller.pubkey(),
+            1_000_000_000,
+            &[(platform_pkey, 500), (referrer_pkey, 100)]
+        );
+        test_utils::send(&mut context, &[initialize_escrow_ix], &[&payer]).await?;
+
+        let escrow: EscrowAccount = EscrowAccount::unpack(&context.banks_client.get_account(escrow_pda).await?.ok_or("escrow wasn't created")?.data)?;
+        assert!(escrow.has_assignments && escrow.payer_splits);
+
+        // 3. the seller can't sell shares of a split payout
+        let mut assign_ix_payload: Vec<u8> = vec![25];
+        assign_ix_payload.extend_from_slice(&1_000u16.to_le_bytes());
+        let assign_claim_share_ix: Instruction = Instruction::new_with_bytes(
+            crate::ID,
+            &assign_ix_payload,
+            vec![
+                AccountMeta::new(seller.pubkey(), true),
+                AccountMeta::new_readonly(payer.pubkey(), false),
+                AccountMeta::new(escrow_pda, false),
+                AccountMeta::new(assignments_pda, false),
+                AccountMeta::new_readonly(Keypair::new().pubkey(), false),
+                AccountMeta::new_readonly(system_program::ID, false)
+            ]
+        );
+        assert!(test_utils::send(&mut context, &[assign_claim_share_ix], &[&seller]).await.is_err());
+
+        // 4. a single complete pays everyone, the payer gets the assignments' rent back
+        let seller_balance: u64 = context.banks_client.get_balance(seller.pubkey()).await?;
+        let payer_balance: u64 = context.banks_client.get_balance(payer.pubkey()).await?;
+        let assignments_rent: u64 = context.banks_client.get_balance(assignments_pda).await?;
+        let escrow_rent: u64 = context.banks_client.get_rent().await?.minimum_balance(EscrowAccount::LEN);
+
+        let mut complete_ix: Instruction = crate::instruction::complete(&payer.pubkey(), &seller.pubkey());
+        complete_ix.accounts.push(AccountMeta::new(assignments_pda, false));
+        complete_ix.accounts.push(AccountMeta::new(platform_pkey, false));
+        complete_ix.accounts.push(AccountMeta::new(referrer_pkey, false));
+        test_utils::send(&mut context, &[complete_ix], &[&payer]).await?;
+
+        assert_eq!(context.banks_client.get_balance(platform_pkey).await?, 50_000_000);
+        assert_eq!(context.banks_client.get_balance(referrer_pkey).await?, 10_000_000);
+        assert_eq!(context.banks_client.get_balance(seller.pubkey()).await?, seller_balance + 940_000_000);
+        assert_eq!(context.banks_client.get_balance(payer.pubkey()).await?, payer_balance + escrow_rent + assignments_rent);
+        assert!(context.banks_client.get_account(assignments_pda).await?.is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_passed_governance_proposal_vetoes_escrow() -> Result<(), Box<dyn std::error::Error>> {
+        let realm_pkey: Pubkey = Pubkey::new_unique();
+        let governance: Keypair = Keypair::new();
+        let (passed_proposal_pkey, voting_proposal_pkey): (Pubkey, Pubkey) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let governance_account = |data: Vec<u8>| Account {
+            lamports: 10_000_000,
+            data,
+            owner: SPL_GOVERNANCE_PROGRAM_ID,
+            executable: false,
+            rent_epoch: 0
+        };
+        let proposal_data = |state: u8| {
+            let mut data: Vec<u8> = vec![14];
+            data.extend_from_slice(governance.pubkey().as_ref());
+            data.extend_from_slice(&[0; 32]);
+            data.push(state);
+            data
+        };
+
+        // 1. a realm (RealmV2), one of its governances (GovernanceV2), a passed & a still voting proposal
+        let mut program_test: ProgramTest = test_utils::program_test();
+        program_test.add_account(realm_pkey, governance_account(vec![16; 64]));
+        program_test.add_account(governance.pubkey(), governance_account([&[18], realm_pkey.as_ref()].concat()));
+        program_test.add_account(passed_proposal_pkey, governance_account(proposal_data(Proposal::SUCCEEDED)));
+        program_test.add_account(voting_proposal_pkey, governance_account(proposal_data(2)));
+        let mut context = program_test.start_with_context().await;
+
+        let payer: Keypair = test_utils::funded_keypair(&mut context, 10_000_000_000).await?;
+        let recipient_pkey: Pubkey = Keypair::new().pubkey();
+        let (escrow_pda, _bump) = derive_escrow_pda(&payer.pubkey(), &recipient_pkey);
+        let amount: u64 = 1_000_000_000;
+
+        // 2. the DAO funds a grant it can recall for a day
+        let veto_until: i64 = context.banks_client.get_sysvar::<Clock>().await?.unix_timestamp + 24 * 60 * 60;
+        let mut set_veto_ix_payload: Vec<u8> = vec![46];
+        set_veto_ix_payload.extend_from_slice(&veto_until.to_le_bytes());
+        let set_veto_ix: Instruction = Instruction::new_with_bytes(
+            crate::ID,
+            &set_veto_ix_payload,
+            vec![
+                AccountMeta::new_readonly(payer.pubkey(), true),
+                AccountMeta::new_readonly(recipient_pkey, false),
+                AccountMeta::new(escrow_pda, false),
+                AccountMeta::new_readonly(realm_pkey, false)
+            ]
+        );
+
+        test_utils::send(
+            &mut context,
+            &[crate::instruction::initialize(&payer.pubkey(), &recipient_pkey, amount), set_veto_ix],
+            &[&payer]
+        ).await?;
+
+        let escrow: EscrowAccount = EscrowAccount::unpack(&context.banks_client.get_account(escrow_pda).await?.ok_or("escrow wasn't created")?.data)?;
+        assert_eq!((escrow.veto_realm, escrow.veto_until), (realm_pkey, veto_until));
+
+        // 3. a proposal still being voted on can't veto, a passed one can
+        let veto_ix = |proposal_pkey: Pubkey| Instruction::new_with_bytes(
+            crate::ID,
+            &[47],
+            vec![
+                AccountMeta::new_readonly(governance.pubkey(), true),
+                AccountMeta::new_readonly(proposal_pkey, false),
+                AccountMeta::new(payer.pubkey(), false),
+                AccountMeta::new_readonly(recipient_pkey, false),
+                AccountMeta::new(escrow_pda, false),
+                AccountMeta::new_readonly(derive_config_pda(), false)
+            ]
+        );
+
+        assert!(test_utils::send(&mut context, &[veto_ix(voting_proposal_pkey)], &[&governance]).await.is_err());
+
+        let payer_balance: u64 = context.banks_client.get_balance(payer.pubkey()).await?;
+        let rent_exempt: u64 = context.banks_client.get_rent().await?.minimum_balance(EscrowAccount::LEN);
+
+        test_utils::send(&mut context, &[veto_ix(passed_proposal_pkey)], &[&governance]).await?;
+
+        assert!(context.banks_client.get_account(escrow_pda).await?.is_none());
+        assert_eq!(context.banks_client.get_balance(payer.pubkey()).await?, payer_balance + amount + rent_exempt);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_arbiter_resolves_dispute() -> Result<(), Box<dyn std::error::Error>> {
+        let mut context = test_utils::program_test().start_with_context().await;
+
+        let payer: Keypair = test_utils::funded_keypair(&mut context, 10_000_000_000).await?;
+        let recipient: Keypair = Keypair::new();
+        let arbiter: Keypair = Keypair::new();
+        let (escrow_pda, _bump) = derive_escrow_pda(&payer.pubkey(), &recipient.pubkey());
+
+        // 1. init escrow with an arbiter & let the recipient raise a dispute
+        let mut initialize_escrow_ix: Instruction = crate::instruction::initialize(&payer.pubkey(), &recipient.pubkey(), 100_000_000);
+        initialize_escrow_ix.accounts.push(AccountMeta::new_readonly(arbiter.pubkey(), false));
+
+        let raise_dispute_ix: Instruction = Instruction::new_with_bytes(
+            crate::ID,
+            &[26],
+            vec![
+                AccountMeta::new_readonly(recipient.pubkey(), true),
+                AccountMeta::new_readonly(payer.pubkey(), false),
+                AccountMeta::new_readonly(recipient.pubkey(), false),
+                AccountMeta::new(escrow_pda, false)
+            ]
+        );
+
+        test_utils::send(&mut context, &[initialize_escrow_ix, raise_dispute_ix], &[&payer, &recipient]).await?;
+
+        // 2. the parties can't settle on their own anymore
+        let complete_ix: Instruction = crate::instruction::complete(&payer.pubkey(), &recipient.pubkey());
+        assert!(test_utils::send(&mut context, &[complete_ix], &[&payer]).await.is_err());
+
+        // 3. the arbiter awards 60% to the recipient
+        let mut resolve_ix_payload: Vec<u8> = vec![27];
+        resolve_ix_payload.extend_from_slice(&4_000u16.to_le_bytes());
+        resolve_ix_payload.extend_from_slice(&6_000u16.to_le_bytes());
+        let resolve_dispute_ix: Instruction = Instruction::new_with_bytes(
+            crate::ID,
+            &resolve_ix_payload,
+            vec![
+                AccountMeta::new_readonly(arbiter.pubkey(), true),
+                AccountMeta::new(payer.pubkey(), false),
+                AccountMeta::new(recipient.pubkey(), false),
+                AccountMeta::new(escrow_pda, false)
+            ]
+        );
+
+        test_utils::send(&mut context, &[resolve_dispute_ix], &[&arbiter]).await?;
+
+        assert!(context.banks_client.get_account(escrow_pda).await?.is_none());
+        assert_eq!(context.banks_client.get_balance(recipient.pubkey()).await?, 60_000_000);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_disputed_escrow_rejects_other_transitions() -> Result<(), Box<dyn std::error::Error>> {
+        let mut context = test_utils::program_test().start_with_context().await;
+
+        let payer: Keypair = test_utils::funded_keypair(&mut context, 10_000_000_000).await?;
+        let recipient: Keypair = test_utils::funded_keypair(&mut context, 1_000_000_000).await?;
+        let (escrow_pda, _bump) = derive_escrow_pda(&payer.pubkey(), &recipient.pubkey());
+
+        let mut initialize_escrow_ix: Instruction = crate::instruction::initialize(&payer.pubkey(), &recipient.pubkey(), 100_000_000);
+        initialize_escrow_ix.accounts.push(AccountMeta::new_readonly(Keypair::new().pubkey(), false));
+
+        let raise_dispute_ix: Instruction = Instruction::new_with_bytes(
+            crate::ID,
+            &[26],
+            vec![
+                AccountMeta::new_readonly(recipient.pubkey(), true),
+                AccountMeta::new_readonly(payer.pubkey(), false),
+                AccountMeta::new_readonly(recipient.pubkey(), false),
+                AccountMeta::new(escrow_pda, false)
+            ]
+        );
+        let accept_ix: Instruction = Instruction::new_with_bytes(
+            crate::ID,
+            &[23],
+            vec![
+                AccountMeta::new_readonly(recipient.pubkey(), true),
+                AccountMeta::new_readonly(payer.pubkey(), false),
+                AccountMeta::new(escrow_pda, false)
+            ]
+        );
+
+        // 1. Pending -> Disputed
+        test_utils::send(&mut context, &[initialize_escrow_ix, raise_dispute_ix.clone()], &[&payer, &recipient]).await?;
+
+        let escrow_account = context.banks_client.get_account(escrow_pda).await?.ok_or("escrow wasn't created")?;
+        assert_eq!(EscrowAccount::unpack(&escrow_account.data)?.status, EscrowStatus::Disputed);
+
+        // 2. neither Disputed -> Accepted nor Disputed -> Disputed are transitions
+        for ix in [accept_ix, raise_dispute_ix] {
+            assert_eq!(
+                instruction_error(&context.banks_client, &[ix], &[&recipient]).await?,
+                InstructionError::Custom(EscrowError::InvalidTransition as u32)
+            );
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_relayer_pays_fees_for_recipient_signed_close_note() -> Result<(), Box<dyn std::error::Error>> {
+        let mut context = test_utils::program_test().start_with_context().await;
+
+        // 1. the recipient holds no SOL at all, the relayer pays every fee
+        let payer: Keypair = test_utils::funded_keypair(&mut context, 10_000_000_000).await?;
+        let relayer: Keypair = test_utils::funded_keypair(&mut context, 1_000_000_000).await?;
+        let recipient: Keypair = Keypair::new();
+
+        let (escrow_pda, _bump) = derive_escrow_pda(&payer.pubkey(), &recipient.pubkey());
+        let (note_pda, _bump) = Pubkey::find_program_address(&[b"note", escrow_pda.as_ref()], &crate::ID);
+
+        // 2. init escrow with a note
+        let mut initialize_escrow_ix: Instruction = crate::instruction::initialize(&payer.pubkey(), &recipient.pubkey(), 101101101);
+        initialize_escrow_ix.data.extend_from_slice(b"encrypted-to-recipient");
+        initialize_escrow_ix.accounts.push(AccountMeta::new(note_pda, false));
+
+        test_utils::send(&mut context, &[initialize_escrow_ix], &[&payer]).await?;
+
+        // 3. the recipient closes the note in a transaction the relayer submits
+        let latest_blockhash: Hash = context.get_new_latest_blockhash().await?;
+        let message: Message = crate::instruction::relayed_message(
+            &[crate::instruction::close_note(&recipient.pubkey(), &payer.pubkey(), &recipient.pubkey())],
+            &relayer.pubkey(),
+            &latest_blockhash
+        );
+
+        let mut close_note_tx: Transaction = Transaction::new_unsigned(message);
+        close_note_tx.partial_sign(&[&recipient], latest_blockhash);
+        close_note_tx.partial_sign(&[&relayer], latest_blockhash);
+        context.banks_client.process_transaction(close_note_tx).await?;
+
+        assert!(context.banks_client.get_account(note_pda).await?.is_none());
+        assert_eq!(context.banks_client.get_balance(recipient.pubkey()).await?, 0);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_register_callback() -> Result<(), Box<dyn std::error::Error>> {
+        let program_test: ProgramTest = ProgramTest::new(
+            "escrow",
+            crate::ID,
+            processor!(super::entrypoint::process_instruction)
+        );
+
+        // 1.1 init Client, payer, get latest blockhash
+        let (banks_client, payer, latest_blockhash) = program_test.start().await;
+        let payer_pkey: Pubkey = payer.pubkey();
+
+        // 1.2 init recipient & derive escrow PDA
+        let recipient_pkey: Pubkey = Keypair::new().pubkey();
+        let (escrow_pda, _bump) = derive_escrow_pda(&payer_pkey, &recipient_pkey);
+
+        // 2. init escrow
+        init_escrow(&banks_client, &payer, &payer_pkey, &recipient_pkey, &escrow_pda, latest_blockhash).await?;
+
+        // 3. register callback
+        let callback: [u8; 32] = [7; 32];
+
+        let mut register_ix_payload: Vec<u8> = Vec::with_capacity(33);
+        register_ix_payload.push(5);
+        register_ix_payload.extend_from_slice(&callback);
+
+        let register_callback_ix: Instruction = Instruction::new_with_bytes(
+            crate::ID,
+            &register_ix_payload,
+            vec![
+                AccountMeta::new_readonly(payer_pkey, true),
+                AccountMeta::new_readonly(recipient_pkey, false),
+                AccountMeta::new(escrow_pda, false)
+            ]
+        );
+        let message: Message = Message::new(&[register_callback_ix], Some(&payer_pkey));
+        let mut register_callback_tx: Transaction = Transaction::new_unsigned(message);
+        register_callback_tx.sign(&[&payer], latest_blockhash);
+        banks_client.process_transaction(register_callback_tx).await?;
+
+        let escrow_account = banks_client.get_account(escrow_pda).await?.ok_or("escrow account not found")?;
+        let escrow: EscrowAccount = EscrowAccount::unpack(&escrow_account.data)?;
+        assert_eq!(escrow.callback, callback);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_escrow_ids_are_sequential() -> Result<(), Box<dyn std::error::Error>> {
+        let program_test: ProgramTest = ProgramTest::new(
+            "escrow",
+            crate::ID,
+            processor!(super::entrypoint::process_instruction)
+        );
+
+        // 1. init Client, payer, get latest blockhash
+        let (banks_client, payer, latest_blockhash) = program_test.start().await;
+        let payer_pkey: Pubkey = payer.pubkey();
+
+        // 2. init two escrows to different recipients
+        for expected_id in 0..2u64 {
+            let recipient_pkey: Pubkey = Keypair::new().pubkey();
+            let (escrow_pda, _bump) = derive_escrow_pda(&payer_pkey, &recipient_pkey);
+
+            init_escrow(&banks_client, &payer, &payer_pkey, &recipient_pkey, &escrow_pda, latest_blockhash).await?;
+
+            let escrow_account = banks_client.get_account(escrow_pda).await?.ok_or("escrow account not found")?;
+            let escrow: EscrowAccount = EscrowAccount::unpack(&escrow_account.data)?;
+            assert_eq!(escrow.id, expected_id);
+        }
+
+        // 3. the counter points at the next free id
+        let counter_account = banks_client.get_account(derive_counter_pda()).await?.ok_or("counter account not found")?;
+        let counter: EscrowCounter = EscrowCounter::unpack(&counter_account.data)?;
+        assert_eq!(counter.next_id, 2);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_batch_close_skips_foreign_escrows_with_an_event() -> Result<(), Box<dyn std::error::Error>> {
+        let program_test: ProgramTest = ProgramTest::new(
+            "escrow",
+            crate::ID,
+            processor!(super::entrypoint::process_instruction)
+        );
+
+        // 1. init Client, payer, get latest blockhash
+        let (banks_client, payer, latest_blockhash) = program_test.start().await;
+        let payer_pkey: Pubkey = payer.pubkey();
+
+        // 2. init two escrows
+        let mut batch_accounts: Vec<AccountMeta> = vec![
+            AccountMeta::new(payer_pkey, true),
+            AccountMeta::new_readonly(derive_config_pda(), false)
+        ];
+        let mut escrow_pdas: Vec<Pubkey> = Vec::new();
+
+        for _ in 0..2 {
+            let recipient_pkey: Pubkey = Keypair::new().pubkey();
+            let (escrow_pda, _bump) = derive_escrow_pda(&payer_pkey, &recipient_pkey);
+
+            init_escrow(&banks_client, &payer, &payer_pkey, &recipient_pkey, &escrow_pda, latest_blockhash).await?;
+
+            batch_accounts.push(AccountMeta::new_readonly(recipient_pkey, false));
+            batch_accounts.push(AccountMeta::new(escrow_pda, false));
+            escrow_pdas.push(escrow_pda);
+        }
+
+        // 3. add a pair that was never initialized, it must be skipped instead of failing the batch
+        let stranger_pkey: Pubkey = Keypair::new().pubkey();
+        let (stranger_escrow_pda, _bump) = derive_escrow_pda(&payer_pkey, &stranger_pkey);
+        batch_accounts.push(AccountMeta::new_readonly(stranger_pkey, false));
+        batch_accounts.push(AccountMeta::new(stranger_escrow_pda, false));
+
+        // 4. batch close
+        let batch_close_ix: Instruction = Instruction::new_with_bytes(crate::ID, &[4], batch_accounts);
+        let message: Message = Message::new(&[batch_close_ix], Some(&payer_pkey));
+        let mut batch_close_tx: Transaction = Transaction::new_unsigned(message);
+        batch_close_tx.sign(&[&payer], latest_blockhash);
+        let result = banks_client.process_transaction_with_metadata(batch_close_tx).await?;
+        result.result?;
+
+        for escrow_pda in escrow_pdas {
+            assert!(banks_client.get_account(escrow_pda).await?.is_none());
+        }
+
+        // 5. only the skipped pair is reported, with the reason it failed `Close`
+        let skipped: Vec<(Pubkey, u64)> = result.metadata.ok_or("missing metadata")?.log_messages.iter()
+            .filter_map(|log| log.strip_prefix("Program data: "))
+            .filter_map(|data| base64::engine::general_purpose::STANDARD.decode(data).ok())
+            .filter_map(|data| match EscrowEvent::unpack(&data) {
+                Ok(EscrowEvent::CloseSkipped { escrow, error }) => Some((escrow, error)),
+                _ => None
+            })
+            .collect();
+
+        assert_eq!(skipped, vec![(stranger_escrow_pda, u64::from(ProgramError::IncorrectProgramId))]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_batch_close_leaves_token_and_milestone_escrows_to_close() -> Result<(), Box<dyn std::error::Error>> {
+        let mut context = test_utils::program_test().start_with_context().await;
+
+        let payer: Keypair = test_utils::funded_keypair(&mut context, 10_000_000_000).await?;
+        let payer_pkey: Pubkey = payer.pubkey();
+        let (sol_recipient_pkey, milestone_recipient_pkey, token_recipient_pkey) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let now: i64 = context.banks_client.get_sysvar::<Clock>().await?.unix_timestamp;
+
+        // 1. a plain SOL escrow & a milestone escrow
+        test_utils::send(&mut context, &[crate::instruction::initialize(&payer_pkey, &sol_recipient_pkey, 1_000_000_000)], &[&payer]).await?;
+        let initialize_milestones_ix: Instruction = crate::instruction::initialize_milestones(&payer_pkey, &milestone_recipient_pkey, &[(1_000_000_000, now + 86_400)]);
+        test_utils::send(&mut context, &[initialize_milestones_ix], &[&payer]).await?;
+
+        // 2. a token escrow of 400_000 tokens
+        let mint: Keypair = Keypair::new();
+        let payer_token: Keypair = Keypair::new();
+        let rent: Rent = context.banks_client.get_rent().await?;
+
+        let setup_ixs: Vec<Instruction> = vec![
+            system_instruction::create_account(&payer_pkey, &mint.pubkey(), rent.minimum_balance(spl_token::state::Mint::LEN), spl_token::state::Mint::LEN as u64, &spl_token::ID),
+            spl_token::instruction::initialize_mint2(&spl_token::ID, &mint.pubkey(), &payer_pkey, None, 6)?,
+            system_instruction::create_account(
+                &payer_pkey,
+                &payer_token.pubkey(),
+                rent.minimum_balance(spl_token::state::Account::LEN),
+                spl_token::state::Account::LEN as u64,
+                &spl_token::ID
+            ),
+            spl_token::instruction::initialize_account3(&spl_token::ID, &payer_token.pubkey(), &mint.pubkey(), &payer_pkey)?,
+            spl_token::instruction::mint_to(&spl_token::ID, &mint.pubkey(), &payer_token.pubkey(), &payer_pkey, &[], 1_000_000)?
+        ];
+        test_utils::send(&mut context, &setup_ixs, &[&payer, &mint, &payer_token]).await?;
+
+        let (token_escrow_pda, _bump) = Pubkey::find_program_address(
+            &[b"escrow", payer_pkey.as_ref(), token_recipient_pkey.as_ref(), mint.pubkey().as_ref()],
+            &crate::ID
+        );
+        let (vault_pda, _bump) = Pubkey::find_program_address(&[b"vault", token_escrow_pda.as_ref()], &crate::ID);
+        let (blocked_mint_pda, _bump) = Pubkey::find_program_address(&[b"blocked_mint", mint.pubkey().as_ref()], &crate::ID);
+
+        let initialize_token_escrow_ix: Instruction = Instruction::new_with_bytes(
+            crate::ID,
+            &[&[14][..], &400_000u64.to_le_bytes()].concat(),
+            vec![
+                AccountMeta::new(payer_pkey, true),
+                AccountMeta::new_readonly(token_recipient_pkey, false),
+                AccountMeta::new(token_escrow_pda, false),
+                AccountMeta::new_readonly(mint.pubkey(), false),
+                AccountMeta::new(vault_pda, false),
+                AccountMeta::new(payer_token.pubkey(), false),
+                AccountMeta::new_readonly(spl_token::ID, false),
+                AccountMeta::new_readonly(system_program::ID, false),
+                AccountMeta::new(derive_counter_pda(), false),
+                AccountMeta::new_readonly(derive_config_pda(), false),
+                AccountMeta::new_readonly(blocked_mint_pda, false)
+            ]
+        );
+        test_utils::send(&mut context, &[initialize_token_escrow_ix], &[&payer]).await?;
+
+        // 3. batch close all three
+        let (sol_escrow_pda, _bump) = derive_escrow_pda(&payer_pkey, &sol_recipient_pkey);
+        let (milestone_escrow_pda, _bump) = derive_escrow_pda(&payer_pkey, &milestone_recipient_pkey);
+
+        let batch_close_ix: Instruction = Instruction::new_with_bytes(
+            crate::ID,
+            &[4],
+            vec![
+                AccountMeta::new(payer_pkey, true),
+                AccountMeta::new_readonly(derive_config_pda(), false),
+                AccountMeta::new_readonly(sol_recipient_pkey, false),
+                AccountMeta::new(sol_escrow_pda, false),
+                AccountMeta::new_readonly(milestone_recipient_pkey, false),
+                AccountMeta::new(milestone_escrow_pda, false),
+                AccountMeta::new_readonly(token_recipient_pkey, false),
+                AccountMeta::new(token_escrow_pda, false)
+            ]
+        );
+        let latest_blockhash: Hash = context.get_new_latest_blockhash().await?;
+        let batch_close_tx: Transaction = Transaction::new_signed_with_payer(&[batch_close_ix], Some(&payer_pkey), &[&payer], latest_blockhash);
+        let result = context.banks_client.process_transaction_with_metadata(batch_close_tx).await?;
+        result.result?;
+
+        // 4. only the SOL escrow is refunded, the others are skipped as needing `Close`'s extra accounts
+        assert!(context.banks_client.get_account(sol_escrow_pda).await?.is_none());
+        assert!(context.banks_client.get_account(milestone_escrow_pda).await?.is_some());
+        assert!(context.banks_client.get_account(token_escrow_pda).await?.is_some());
+
+        let skipped: Vec<(Pubkey, u64)> = result.metadata.ok_or("missing metadata")?.log_messages.iter()
+            .filter_map(|log| log.strip_prefix("Program data: "))
+            .filter_map(|data| base64::engine::general_purpose::STANDARD.decode(data).ok())
+            .filter_map(|data| match EscrowEvent::unpack(&data) {
+                Ok(EscrowEvent::CloseSkipped { escrow, error }) => Some((escrow, error)),
+                _ => None
+            })
+            .collect();
+
+        assert_eq!(skipped, vec![
+            (milestone_escrow_pda, u64::from(ProgramError::InvalidArgument)),
+            (token_escrow_pda, u64::from(ProgramError::InvalidArgument))
+        ]);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_display_metadata_set_and_close() -> Result<(), Box<dyn std::error::Error>> {
+        let mut context = test_utils::program_test().start_with_context().await;
+
+        let payer: Keypair = test_utils::funded_keypair(&mut context, 10_000_000_000).await?;
+        let stranger: Keypair = test_utils::funded_keypair(&mut context, 1_000_000_000).await?;
+        let recipient_pkey: Pubkey = Keypair::new().pubkey();
+        let (escrow_pda, _bump) = derive_escrow_pda(&payer.pubkey(), &recipient_pkey);
+        let (display_pda, _bump) = Pubkey::find_program_address(&[b"display", escrow_pda.as_ref()], &crate::ID);
+
+        let set_display_metadata = |label: &[u8]| -> Instruction {
+            let mut data: Vec<u8> = vec![11];
+            data.extend_from_slice(&[7; 32]);
+            data.extend_from_slice(label);
+
+            Instruction::new_with_bytes(
+                crate::ID,
+                &data,
+                vec![
+                    AccountMeta::new(payer.pubkey(), true),
+                    AccountMeta::new_readonly(recipient_pkey, false),
+                    AccountMeta::new_readonly(escrow_pda, false),
+                    AccountMeta::new(display_pda, false),
+                    AccountMeta::new_readonly(system_program::ID, false)
+                ]
+            )
+        };
+        let close_display_metadata = |payer_pkey: &Pubkey| -> Instruction {
+            Instruction::new_with_bytes(
+                crate::ID,
+                &[12],
+                vec![
+                    AccountMeta::new(*payer_pkey, true),
+                    AccountMeta::new_readonly(recipient_pkey, false),
+                    AccountMeta::new(display_pda, false)
+                ]
+            )
+        };
+
+        test_utils::send(&mut context, &[crate::instruction::initialize(&payer.pubkey(), &recipient_pkey, 1_000_000_000)], &[&payer]).await?;
+
+        // 1. labels are capped at `MAX_LABEL_LEN` bytes
+        assert_eq!(
+            instruction_error(&context.banks_client, &[set_display_metadata(&[b'a'; EscrowDisplay::MAX_LABEL_LEN + 1])], &[&payer]).await?,
+            InstructionError::InvalidInstructionData
+        );
+
+        // 2. created on first use, updated in place afterwards
+        test_utils::send(&mut context, &[set_display_metadata(b"Invoice #42")], &[&payer]).await?;
+        test_utils::send(&mut context, &[set_display_metadata(b"Invoice #42 (paid)")], &[&payer]).await?;
+
+        let display: EscrowDisplay = EscrowDisplay::unpack(&context.banks_client.get_account(display_pda).await?.ok_or("display wasn't created")?.data)?;
+        assert_eq!((display.label.as_str(), display.icon_hash), ("Invoice #42 (paid)", [7; 32]));
+
+        // 3. only the escrow's payer gets the rent back
+        assert!(test_utils::send(&mut context, &[close_display_metadata(&stranger.pubkey())], &[&stranger]).await.is_err());
+
+        let display_rent: u64 = context.banks_client.get_balance(display_pda).await?;
+        let payer_balance: u64 = context.banks_client.get_balance(payer.pubkey()).await?;
+        test_utils::send(&mut context, &[close_display_metadata(&payer.pubkey())], &[&payer]).await?;
+
+        assert!(context.banks_client.get_account(display_pda).await?.is_none());
+        assert_eq!(context.banks_client.get_balance(payer.pubkey()).await?, payer_balance + display_rent);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_token_escrow_initialize_and_complete() -> Result<(), Box<dyn std::error::Error>> {
+        for token_program_id in [spl_token::ID, spl_token_2022::ID] {
+            token_escrow_initialize_and_complete(token_program_id).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn token_escrow_initialize_and_complete(token_program_id: Pubkey) -> Result<(), Box<dyn std::error::Error>> {
+        let program_test: ProgramTest = ProgramTest::new(
+            "escrow",
+            crate::ID,
+            processor!(super::entrypoint::process_instruction)
+        );
+
+        // 1. init Client, payer, get latest blockhash
+        let (banks_client, payer, latest_blockhash) = program_test.start().await;
+        let payer_pkey: Pubkey = payer.pubkey();
+        let recipient: Keypair = Keypair::new();
+        let recipient_pkey: Pubkey = recipient.pubkey();
+
+        // 2. create a mint and token accounts of both parties, funding the payer's one
+        let mint: Keypair = Keypair::new();
+        let payer_token: Keypair = Keypair::new();
+        let recipient_token: Keypair = Keypair::new();
+        let rent = banks_client.get_rent().await?;
+
+        let mut setup_ixs: Vec<Instruction> = vec![
+            system_instruction::create_account(
+                &payer_pkey,
+                &mint.pubkey(),
+                rent.minimum_balance(spl_token::state::Mint::LEN),
+                spl_token::state::Mint::LEN as u64,
+                &token_program_id
+            ),
+            spl_token_2022::instruction::initialize_mint2(&token_program_id, &mint.pubkey(), &payer_pkey, None, 6)?
+        ];
+        for (token_account, owner) in [(&payer_token, &payer_pkey), (&recipient_token, &recipient_pkey)] {
+            setup_ixs.push(system_instruction::create_account(
+                &payer_pkey,
+                &token_account.pubkey(),
+                rent.minimum_balance(spl_token::state::Account::LEN),
+                spl_token::state::Account::LEN as u64,
+                &token_program_id
+            ));
+            setup_ixs.push(spl_token_2022::instruction::initialize_account3(&token_program_id, &token_account.pubkey(), &mint.pubkey(), owner)?);
+        }
+        setup_ixs.push(spl_token_2022::instruction::mint_to(&token_program_id, &mint.pubkey(), &payer_token.pubkey(), &payer_pkey, &[], 1_000_000)?);
+
+        let message: Message = Message::new(&setup_ixs, Some(&payer_pkey));
+        let mut setup_tx: Transaction = Transaction::new_unsigned(message);
+        setup_tx.sign(&[&payer, &mint, &payer_token, &recipient_token], latest_blockhash);
+        banks_client.process_transaction(setup_tx).await?;
+
+        // 3. init token escrow
+        let (escrow_pda, _bump) = Pubkey::find_program_address(
+            &[b"escrow", payer_pkey.as_ref(), recipient_pkey.as_ref(), mint.pubkey().as_ref()],
+            &crate::ID
+        );
+        let (vault_pda, _bump) = Pubkey::find_program_address(&[b"vault", escrow_pda.as_ref()], &crate::ID);
+        let (blocked_mint_pda, _bump) = Pubkey::find_program_address(&[b"blocked_mint", mint.pubkey().as_ref()], &crate::ID);
+
+        let mut init_ix_payload: Vec<u8> = Vec::with_capacity(9);
+        init_ix_payload.push(14);
+        init_ix_payload.extend_from_slice(&u64::to_le_bytes(400_000));
+
+        let initialize_token_escrow_ix: Instruction = Instruction::new_with_bytes(
+            crate::ID,
+            &init_ix_payload,
+            vec![
+                AccountMeta::new(payer_pkey, true),
+                AccountMeta::new_readonly(recipient_pkey, false),
+                AccountMeta::new(escrow_pda, false),
+                AccountMeta::new_readonly(mint.pubkey(), false),
+                AccountMeta::new(vault_pda, false),
+                AccountMeta::new(payer_token.pubkey(), false),
+                AccountMeta::new_readonly(token_program_id, false),
+                AccountMeta::new_readonly(system_program::ID, false),
+                AccountMeta::new(derive_counter_pda(), false),
+                AccountMeta::new_readonly(derive_config_pda(), false),
+                AccountMeta::new_readonly(blocked_mint_pda, false)
+            ]
+        );
+        // the token program has to be the mint's owner, the other one is rejected
+        let mut wrong_program_ix: Instruction = initialize_token_escrow_ix.clone();
+        wrong_program_ix.accounts[6].pubkey = if token_program_id == spl_token::ID { spl_token_2022::ID } else { spl_token::ID };
+        assert_eq!(instruction_error(&banks_client, &[wrong_program_ix], &[&payer]).await?, InstructionError::IncorrectProgramId);
+
+        let message: Message = Message::new(&[initialize_token_escrow_ix], Some(&payer_pkey));
+        let mut initialize_token_escrow_tx: Transaction = Transaction::new_unsigned(message);
+        initialize_token_escrow_tx.sign(&[&payer], latest_blockhash);
+        banks_client.process_transaction(initialize_token_escrow_tx).await?;
+
+        let vault_account = banks_client.get_account(vault_pda).await?.ok_or("vault wasn't created")?;
+        assert_eq!(spl_token::state::Account::unpack(&vault_account.data)?.amount, 400_000);
+
+        let escrow_account = banks_client.get_account(escrow_pda).await?.ok_or("escrow wasn't created")?;
+        assert_eq!(EscrowAccount::unpack(&escrow_account.data)?.token_amount, 400_000);
+
+        // 3.1 tokens sent to the vault directly are picked up by `Reconcile`
+        let reconcile_ix: Instruction = Instruction::new_with_bytes(
+            crate::ID,
+            &[30],
+            vec![
+                AccountMeta::new(escrow_pda, false),
+                AccountMeta::new_readonly(vault_pda, false)
+            ]
+        );
+        let message: Message = Message::new(
+            &[
+                spl_token_2022::instruction::mint_to(&token_program_id, &mint.pubkey(), &vault_pda, &payer_pkey, &[], 5_000)?,
+                reconcile_ix
+            ],
+            Some(&payer_pkey)
+        );
+        let mut reconcile_tx: Transaction = Transaction::new_unsigned(message);
+        reconcile_tx.sign(&[&payer], latest_blockhash);
+        banks_client.process_transaction(reconcile_tx).await?;
+
+        let escrow_account = banks_client.get_account(escrow_pda).await?.ok_or("escrow is gone")?;
+        assert_eq!(EscrowAccount::unpack(&escrow_account.data)?.token_amount, 405_000);
+
+        // 4. complete token escrow, leaving the recipient a note
+        let (note_pda, _bump) = Pubkey::find_program_address(&[b"note", escrow_pda.as_ref()], &crate::ID);
+        let ciphertext: &[u8] = b"encrypted-to-recipient";
+
+        let mut complete_ix_payload: Vec<u8> = Vec::with_capacity(1 + ciphertext.len());
+        complete_ix_payload.push(1);
+        complete_ix_payload.extend_from_slice(ciphertext);
+
+        let complete_escrow_ix: Instruction = Instruction::new_with_bytes(
+            crate::ID,
+            &complete_ix_payload,
+            vec![
+                AccountMeta::new(payer_pkey, true),
+                AccountMeta::new_readonly(recipient_pkey, false),
+                AccountMeta::new(escrow_pda, false),
+                AccountMeta::new_readonly(derive_config_pda(), false),
+                AccountMeta::new_readonly(mint.pubkey(), false),
+                AccountMeta::new(vault_pda, false),
+                AccountMeta::new(recipient_token.pubkey(), false),
+                AccountMeta::new_readonly(token_program_id, false),
+                AccountMeta::new(note_pda, false),
+                AccountMeta::new_readonly(system_program::ID, false)
+            ]
+        );
+        let message: Message = Message::new(&[complete_escrow_ix], Some(&payer_pkey));
+        let mut complete_escrow_tx: Transaction = Transaction::new_unsigned(message);
+        complete_escrow_tx.sign(&[&payer], latest_blockhash);
+        banks_client.process_transaction(complete_escrow_tx).await?;
+
+        let recipient_token_account = banks_client.get_account(recipient_token.pubkey()).await?.ok_or("recipient token account is gone")?;
+        assert_eq!(spl_token::state::Account::unpack(&recipient_token_account.data)?.amount, 405_000);
+        assert!(banks_client.get_account(vault_pda).await?.is_none());
+        assert!(banks_client.get_account(escrow_pda).await?.is_none());
+
+        // 5. the note outlives the escrow and is closed given its mint, not as a SOL escrow's note
+        let close_note_ix: Instruction = crate::instruction::close_token_note(&recipient_pkey, &payer_pkey, &recipient_pkey, &mint.pubkey());
+        assert_eq!(close_note_ix.accounts[3].pubkey, note_pda);
+
+        let mut sol_close_note_ix: Instruction = close_note_ix.clone();
+        sol_close_note_ix.data = vec![3];
+        assert!(banks_client.process_transaction(Transaction::new_signed_with_payer(
+            &[sol_close_note_ix],
+            Some(&payer_pkey),
+            &[&payer, &recipient],
+            latest_blockhash
+        )).await.is_err());
+
+        let note_rent: u64 = banks_client.get_balance(note_pda).await?;
+        let payer_balance: u64 = banks_client.get_balance(payer_pkey).await?;
+        let close_note_tx: Transaction = Transaction::new_signed_with_payer(&[close_note_ix], Some(&payer_pkey), &[&payer, &recipient], latest_blockhash);
+        let fee: u64 = banks_client.get_fee_for_message(close_note_tx.message.clone()).await?.ok_or("blockhash expired")?;
+        banks_client.process_transaction(close_note_tx).await?;
+
+        assert!(banks_client.get_account(note_pda).await?.is_none());
+        assert_eq!(banks_client.get_balance(payer_pkey).await?, payer_balance + note_rent - fee);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_writes_shortfalls_off_only_when_authorized() -> Result<(), Box<dyn std::error::Error>> {
+        let mut context = test_utils::program_test().start_with_context().await;
+
+        let payer: Keypair = test_utils::funded_keypair(&mut context, 10_000_000_000).await?;
+        let stranger: Keypair = test_utils::funded_keypair(&mut context, 1_000_000_000).await?;
+        let recipient_pkey: Pubkey = Keypair::new().pubkey();
+        let mint_authority_pkey: Pubkey = context.payer.pubkey();
+        let (mint, payer_token): (Keypair, Keypair) = (Keypair::new(), Keypair::new());
+        let rent: Rent = context.banks_client.get_rent().await?;
+
+        // 1. a token escrow of 400_000
+        test_utils::send(
+            &mut context,
+            &[
+                system_instruction::create_account(
+                    &mint_authority_pkey,
+                    &mint.pubkey(),
+                    rent.minimum_balance(spl_token::state::Mint::LEN),
+                    spl_token::state::Mint::LEN as u64,
+                    &spl_token::ID
+                ),
+                spl_token_2022::instruction::initialize_mint2(&spl_token::ID, &mint.pubkey(), &mint_authority_pkey, None, 6)?,
+                system_instruction::create_account(
+                    &mint_authority_pkey,
+                    &payer_token.pubkey(),
+                    rent.minimum_balance(spl_token::state::Account::LEN),
+                    spl_token::state::Account::LEN as u64,
+                    &spl_token::ID
+                ),
+                spl_token_2022::instruction::initialize_account3(&spl_token::ID, &payer_token.pubkey(), &mint.pubkey(), &payer.pubkey())?,
+                spl_token_2022::instruction::mint_to(&spl_token::ID, &mint.pubkey(), &payer_token.pubkey(), &mint_authority_pkey, &[], 1_000_000)?
+            ],
+            &[&mint, &payer_token]
+        ).await?;
+
+        let (escrow_pda, _bump) = Pubkey::find_program_address(
+            &[b"escrow", payer.pubkey().as_ref(), recipient_pkey.as_ref(), mint.pubkey().as_ref()],
+            &crate::ID
+        );
+        let (vault_pda, _bump) = Pubkey::find_program_address(&[b"vault", escrow_pda.as_ref()], &crate::ID);
+        let (blocked_mint_pda, _bump) = Pubkey::find_program_address(&[b"blocked_mint", mint.pubkey().as_ref()], &crate::ID);
+
+        let mut init_ix_payload: Vec<u8> = vec![14];
+        init_ix_payload.extend_from_slice(&400_000u64.to_le_bytes());
+        let initialize_token_escrow_ix: Instruction = Instruction::new_with_bytes(
+            crate::ID,
+            &init_ix_payload,
+            vec![
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new_readonly(recipient_pkey, false),
+                AccountMeta::new(escrow_pda, false),
+                AccountMeta::new_readonly(mint.pubkey(), false),
+                AccountMeta::new(vault_pda, false),
+                AccountMeta::new(payer_token.pubkey(), false),
+                AccountMeta::new_readonly(spl_token::ID, false),
+                AccountMeta::new_readonly(system_program::ID, false),
+                AccountMeta::new(derive_counter_pda(), false),
+                AccountMeta::new_readonly(derive_config_pda(), false),
+                AccountMeta::new_readonly(blocked_mint_pda, false)
+            ]
+        );
+        test_utils::send(&mut context, &[initialize_token_escrow_ix], &[&payer]).await?;
+
+        let reconcile_ix = |authority_pkey: Option<Pubkey>| {
+            let mut accounts: Vec<AccountMeta> = vec![AccountMeta::new(escrow_pda, false), AccountMeta::new_readonly(vault_pda, false)];
+            if let Some(authority_pkey) = authority_pkey {
+                accounts.push(AccountMeta::new_readonly(authority_pkey, true));
+                accounts.push(AccountMeta::new_readonly(derive_config_pda(), false));
+            }
+            Instruction::new_with_bytes(crate::ID, &[30], accounts)
+        };
+        // 2. only the escrow's own vault is compared with its books, and a surplus is adopted by anyone
+        let mut spoofed_vault_ix: Instruction = reconcile_ix(None);
+        spoofed_vault_ix.accounts[1].pubkey = payer_token.pubkey();
+        let error = instruction_error(&context.banks_client, &[spoofed_vault_ix], &[&stranger]).await?;
+        assert_eq!(error, InstructionError::Custom(EscrowError::PdaMismatch as u32));
+
+        test_utils::send(
+            &mut context,
+            &[spl_token_2022::instruction::mint_to(&spl_token::ID, &mint.pubkey(), &vault_pda, &mint_authority_pkey, &[], 5_000)?, reconcile_ix(None)],
+            &[]
+        ).await?;
+        assert_eq!(EscrowAccount::unpack(&context.banks_client.get_account(escrow_pda).await?.ok_or("escrow is gone")?.data)?.token_amount, 405_000);
+
+        // 3. tokens leave the vault behind the program's back (e.g. clawed back by the mint)
+        let mut vault_account: Account = context.banks_client.get_account(vault_pda).await?.ok_or("vault is gone")?;
+        vault_account.data[64..72].copy_from_slice(&300_000u64.to_le_bytes());
+        context.set_account(&vault_pda, &vault_account.into());
+
+        // 4. the shortfall can't be written off by anyone, nor without a signature
+        let error = instruction_error(&context.banks_client, &[reconcile_ix(None)], &[&stranger]).await?;
+        assert_eq!(error, InstructionError::NotEnoughAccountKeys);
+        let error = instruction_error(&context.banks_client, &[reconcile_ix(Some(stranger.pubkey()))], &[&stranger]).await?;
+        assert_eq!(error, InstructionError::MissingRequiredSignature);
+        assert_eq!(EscrowAccount::unpack(&context.banks_client.get_account(escrow_pda).await?.ok_or("escrow is gone")?.data)?.token_amount, 405_000);
+
+        // 5. the payer writes it off
+        test_utils::send(&mut context, &[reconcile_ix(Some(payer.pubkey()))], &[&payer]).await?;
+        assert_eq!(EscrowAccount::unpack(&context.banks_client.get_account(escrow_pda).await?.ok_or("escrow is gone")?.data)?.token_amount, 300_000);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_nft_escrow_releases_to_ata_only() -> Result<(), Box<dyn std::error::Error>> {
+        let program_test: ProgramTest = ProgramTest::new(
+            "escrow",
+            crate::ID,
+            processor!(super::entrypoint::process_instruction)
+        );
+
+        // 1. init Client, payer, get latest blockhash
+        let (banks_client, payer, latest_blockhash) = program_test.start().await;
+        let payer_pkey: Pubkey = payer.pubkey();
+        let recipient_pkey: Pubkey = Keypair::new().pubkey();
+
+        // 2. mint an NFT into the payer's ATA; the recipient gets an ATA and a plain token account
+        let mint: Keypair = Keypair::new();
+        let recipient_token: Keypair = Keypair::new();
+        let rent = banks_client.get_rent().await?;
+
+        let payer_ata: Pubkey = spl_associated_token_account::get_associated_token_address(&payer_pkey, &mint.pubkey());
+        let recipient_ata: Pubkey = spl_associated_token_account::get_associated_token_address(&recipient_pkey, &mint.pubkey());
+
+        let setup_ixs: Vec<Instruction> = vec![
+            system_instruction::create_account(
+                &payer_pkey,
+                &mint.pubkey(),
+                rent.minimum_balance(spl_token::state::Mint::LEN),
+                spl_token::state::Mint::LEN as u64,
+                &spl_token::ID
+            ),
+            spl_token::instruction::initialize_mint2(&spl_token::ID, &mint.pubkey(), &payer_pkey, None, 0)?,
+            spl_associated_token_account::instruction::create_associated_token_account(&payer_pkey, &payer_pkey, &mint.pubkey(), &spl_token::ID),
+            spl_associated_token_account::instruction::create_associated_token_account(&payer_pkey, &recipient_pkey, &mint.pubkey(), &spl_token::ID),
+            system_instruction::create_account(
+                &payer_pkey,
+                &recipient_token.pubkey(),
+                rent.minimum_balance(spl_token::state::Account::LEN),
+                spl_token::state::Account::LEN as u64,
+                &spl_token::ID
+            ),
+            spl_token::instruction::initialize_account3(&spl_token::ID, &recipient_token.pubkey(), &mint.pubkey(), &recipient_pkey)?,
+            spl_token::instruction::mint_to(&spl_token::ID, &mint.pubkey(), &payer_ata, &payer_pkey, &[], 1)?
+        ];
+        let message: Message = Message::new(&setup_ixs, Some(&payer_pkey));
+        let mut setup_tx: Transaction = Transaction::new_unsigned(message);
+        setup_tx.sign(&[&payer, &mint, &recipient_token], latest_blockhash);
+        banks_client.process_transaction(setup_tx).await?;
+
+        // 3. init NFT escrow
+        let (escrow_pda, _bump) = Pubkey::find_program_address(
+            &[b"escrow", payer_pkey.as_ref(), recipient_pkey.as_ref(), mint.pubkey().as_ref()],
+            &crate::ID
+        );
+        let (vault_pda, _bump) = Pubkey::find_program_address(&[b"vault", escrow_pda.as_ref()], &crate::ID);
+        let (blocked_mint_pda, _bump) = Pubkey::find_program_address(&[b"blocked_mint", mint.pubkey().as_ref()], &crate::ID);
+
+        let initialize_nft_escrow_ix: Instruction = Instruction::new_with_bytes(
+            crate::ID,
+            &[15],
+            vec![
+                AccountMeta::new(payer_pkey, true),
+                AccountMeta::new_readonly(recipient_pkey, false),
+                AccountMeta::new(escrow_pda, false),
+                AccountMeta::new_readonly(mint.pubkey(), false),
+                AccountMeta::new(vault_pda, false),
+                AccountMeta::new(payer_ata, false),
+                AccountMeta::new_readonly(spl_token::ID, false),
+                AccountMeta::new_readonly(system_program::ID, false),
+                AccountMeta::new(derive_counter_pda(), false),
+                AccountMeta::new_readonly(derive_config_pda(), false),
+                AccountMeta::new_readonly(blocked_mint_pda, false)
+            ]
+        );
+        let message: Message = Message::new(&[initialize_nft_escrow_ix], Some(&payer_pkey));
+        let mut initialize_nft_escrow_tx: Transaction = Transaction::new_unsigned(message);
+        initialize_nft_escrow_tx.sign(&[&payer], latest_blockhash);
+        banks_client.process_transaction(initialize_nft_escrow_tx).await?;
+
+        // 4. complete: a non-ATA destination is rejected, the recipient's ATA is not
+        for (destination, should_succeed) in [(recipient_token.pubkey(), false), (recipient_ata, true)] {
+            let complete_escrow_ix: Instruction = Instruction::new_with_bytes(
+                crate::ID,
+                &[1],
+                vec![
+                    AccountMeta::new(payer_pkey, true),
+                    AccountMeta::new_readonly(recipient_pkey, false),
+                    AccountMeta::new(escrow_pda, false),
+                    AccountMeta::new_readonly(derive_config_pda(), false),
+                    AccountMeta::new_readonly(mint.pubkey(), false),
+                    AccountMeta::new(vault_pda, false),
+                    AccountMeta::new(destination, false),
+                    AccountMeta::new_readonly(spl_token::ID, false)
+                ]
+            );
+            let message: Message = Message::new(&[complete_escrow_ix], Some(&payer_pkey));
+            let mut complete_escrow_tx: Transaction = Transaction::new_unsigned(message);
+            complete_escrow_tx.sign(&[&payer], latest_blockhash);
+
+            assert_eq!(banks_client.process_transaction(complete_escrow_tx).await.is_ok(), should_succeed);
+        }
+
+        let recipient_ata_account = banks_client.get_account(recipient_ata).await?.ok_or("recipient ATA is gone")?;
+        assert_eq!(spl_token::state::Account::unpack(&recipient_ata_account.data)?.amount, 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_block_and_unblock_mint() -> Result<(), Box<dyn std::error::Error>> {
+        let program_test: ProgramTest = ProgramTest::new(
+            "escrow",
+            crate::ID,
+            processor!(super::entrypoint::process_instruction)
+        );
+
+        // 1. init Client, payer (who becomes the admin as the upgrade authority), get latest blockhash
+        let mut context = program_test.start_with_context().await;
+        let payer_pkey: Pubkey = context.payer.pubkey();
+        test_utils::set_upgrade_authority(&mut context, &payer_pkey);
+        let ProgramTestContext { banks_client, payer, last_blockhash: latest_blockhash, .. } = context;
+
+        init_config(&banks_client, &payer, latest_blockhash).await?;
+
+        // 2. block mint
+        let mint_pkey: Pubkey = Keypair::new().pubkey();
+        let (blocked_mint_pda, _bump) = Pubkey::find_program_address(&[b"blocked_mint", mint_pkey.as_ref()], &crate::ID);
+
+        let block_mint_ix: Instruction = Instruction::new_with_bytes(
+            crate::ID,
+            &[7],
+            vec![
+                AccountMeta::new(payer_pkey, true),
+                AccountMeta::new_readonly(derive_config_pda(), false),
+                AccountMeta::new_readonly(mint_pkey, false),
+                AccountMeta::new(blocked_mint_pda, false),
+                AccountMeta::new_readonly(system_program::ID, false)
+            ]
+        );
+        let message: Message = Message::new(&[block_mint_ix], Some(&payer_pkey));
+        let mut block_mint_tx: Transaction = Transaction::new_unsigned(message);
+        block_mint_tx.sign(&[&payer], latest_blockhash);
+        banks_client.process_transaction(block_mint_tx).await?;
+
+        assert!(banks_client.get_account(blocked_mint_pda).await?.is_some());
+
+        // 3. unblock mint
+        let unblock_mint_ix: Instruction = Instruction::new_with_bytes(
+            crate::ID,
+            &[8],
+            vec![
+                AccountMeta::new(payer_pkey, true),
+                AccountMeta::new_readonly(derive_config_pda(), false),
+                AccountMeta::new_readonly(mint_pkey, false),
+                AccountMeta::new(blocked_mint_pda, false)
+            ]
+        );
+        let message: Message = Message::new(&[unblock_mint_ix], Some(&payer_pkey));
+        let mut unblock_mint_tx: Transaction = Transaction::new_unsigned(message);
+        unblock_mint_tx.sign(&[&payer], latest_blockhash);
+        banks_client.process_transaction(unblock_mint_tx).await?;
+
+        assert!(banks_client.get_account(blocked_mint_pda).await?.is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_wind_down_pauses_initialize_only() -> Result<(), Box<dyn std::error::Error>> {
+        let program_test: ProgramTest = ProgramTest::new(
+            "escrow",
+            crate::ID,
+            processor!(super::entrypoint::process_instruction)
+        );
+
+        // 1. init Client, payer (who becomes the admin as the upgrade authority), get latest blockhash
+        let mut context = program_test.start_with_context().await;
+        let payer_pkey: Pubkey = context.payer.pubkey();
+        test_utils::set_upgrade_authority(&mut context, &payer_pkey);
+        let ProgramTestContext { banks_client, payer, last_blockhash: latest_blockhash, .. } = context;
+
+        init_config(&banks_client, &payer, latest_blockhash).await?;
+
+        // 2. init an escrow before winding down
+        let recipient_pkey: Pubkey = Keypair::new().pubkey();
+        let (escrow_pda, _bump) = derive_escrow_pda(&payer_pkey, &recipient_pkey);
+
+        init_escrow(&banks_client, &payer, &payer_pkey, &recipient_pkey, &escrow_pda, latest_blockhash).await?;
+
+        // 3. pause Initialize only
+        let set_paused_ix: Instruction = Instruction::new_with_bytes(
+            crate::ID,
+            &[13, Config::PAUSE_INITIALIZE],
+            vec![
+                AccountMeta::new(payer_pkey, true),
+                AccountMeta::new(derive_config_pda(), false)
+            ]
+        );
+        let message: Message = Message::new(&[set_paused_ix], Some(&payer_pkey));
+        let mut set_paused_tx: Transaction = Transaction::new_unsigned(message);
+        set_paused_tx.sign(&[&payer], latest_blockhash);
+        banks_client.process_transaction(set_paused_tx).await?;
+
+        // 4. new escrows are rejected
+        let other_recipient_pkey: Pubkey = Keypair::new().pubkey();
+        let (other_escrow_pda, _bump) = derive_escrow_pda(&payer_pkey, &other_recipient_pkey);
+
+        assert!(init_escrow(&banks_client, &payer, &payer_pkey, &other_recipient_pkey, &other_escrow_pda, latest_blockhash).await.is_err());
+
+        // 5. the existing one can still be closed
+        let close_escrow_ix: Instruction = Instruction::new_with_bytes(
+            crate::ID,
+            &[2],
+            vec![
+                AccountMeta::new(payer_pkey, true),
+                AccountMeta::new_readonly(recipient_pkey, false),
+                AccountMeta::new(escrow_pda, false),
+                AccountMeta::new_readonly(derive_config_pda(), false)
+            ]
+        );
+        let message: Message = Message::new(&[close_escrow_ix], Some(&payer_pkey));
+        let mut close_escrow_tx: Transaction = Transaction::new_unsigned(message);
+        close_escrow_tx.sign(&[&payer], latest_blockhash);
+        banks_client.process_transaction(close_escrow_tx).await?;
+
+        assert!(banks_client.get_account(escrow_pda).await?.is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_pause_all_halts_every_settling_instruction() -> Result<(), Box<dyn std::error::Error>> {
+        let mut context = test_utils::program_test().start_with_context().await;
+
+        let admin: Keypair = test_utils::funded_keypair(&mut context, 1_000_000_000).await?;
+        test_utils::set_upgrade_authority(&mut context, &admin.pubkey());
+        let payer: Keypair = test_utils::funded_keypair(&mut context, 10_000_000_000).await?;
+        let recipient: Keypair = test_utils::funded_keypair(&mut context, 1_000_000_000).await?;
+        let (payer_pkey, recipient_pkey) = (payer.pubkey(), recipient.pubkey());
+        let (escrow_pda, _bump) = derive_escrow_pda(&payer_pkey, &recipient_pkey);
+        let amount: u64 = 1_000_000_000;
+
+        // 1. an escrow from before the incident, then the admin flips the whole switch
+        test_utils::send(&mut context, &[crate::instruction::initialize_config(&admin.pubkey(), &Pubkey::default())], &[&admin]).await?;
+        test_utils::send(&mut context, &[crate::instruction::initialize(&payer_pkey, &recipient_pkey, amount)], &[&payer]).await?;
+
+        let set_paused_ix = |paused: u8| Instruction::new_with_bytes(
+            crate::ID,
+            &[13, paused],
+            vec![
+                AccountMeta::new(admin.pubkey(), true),
+                AccountMeta::new(derive_config_pda(), false)
+            ]
+        );
+        test_utils::send(&mut context, &[set_paused_ix(Config::PAUSE_ALL)], &[&admin]).await?;
+
+        // 2. every instruction creating an escrow or moving escrowed funds fails before looking at the escrow
+        let other_recipient_pkey: Pubkey = Keypair::new().pubkey();
+        let initialize_token_ix: Instruction = Instruction::new_with_bytes(
+            crate::ID,
+            &[&[14][..], &amount.to_le_bytes()].concat(),
+            [
+                AccountMeta::new(payer_pkey, true),
+                AccountMeta::new_readonly(other_recipient_pkey, false),
+                AccountMeta::new(Keypair::new().pubkey(), false),
+                AccountMeta::new_readonly(Keypair::new().pubkey(), false),
+                AccountMeta::new(Keypair::new().pubkey(), false),
+                AccountMeta::new(Keypair::new().pubkey(), false),
+                AccountMeta::new_readonly(spl_token::ID, false),
+                AccountMeta::new_readonly(system_program::ID, false),
+                AccountMeta::new(derive_counter_pda(), false),
+                AccountMeta::new_readonly(derive_config_pda(), false),
+                AccountMeta::new_readonly(Keypair::new().pubkey(), false)
+            ].to_vec()
+        );
+        let cancel_mutual_ix: Instruction = Instruction::new_with_bytes(
+            crate::ID,
+            &[&[24][..], &5_000u16.to_le_bytes(), &5_000u16.to_le_bytes()].concat(),
+            vec![
+                AccountMeta::new(payer_pkey, true),
+                AccountMeta::new(recipient_pkey, true),
+                AccountMeta::new(escrow_pda, false),
+                AccountMeta::new_readonly(derive_config_pda(), false)
+            ]
+        );
+        let batch_close_ix: Instruction = Instruction::new_with_bytes(
+            crate::ID,
+            &[4],
+            vec![
+                AccountMeta::new(payer_pkey, true),
+                AccountMeta::new_readonly(derive_config_pda(), false),
+                AccountMeta::new_readonly(recipient_pkey, false),
+                AccountMeta::new(escrow_pda, false)
+            ]
+        );
+        let governance_veto_ix: Instruction = Instruction::new_with_bytes(
+            crate::ID,
+            &[47],
+            vec![
+                AccountMeta::new_readonly(Keypair::new().pubkey(), false),
+                AccountMeta::new_readonly(Keypair::new().pubkey(), false),
+                AccountMeta::new(payer_pkey, false),
+                AccountMeta::new_readonly(recipient_pkey, false),
+                AccountMeta::new(escrow_pda, false),
+                AccountMeta::new_readonly(derive_config_pda(), false)
+            ]
+        );
+        let complete_usd_ix: Instruction = Instruction::new_with_bytes(
+            crate::ID,
+            &[&[10][..], &0u64.to_le_bytes(), &u64::MAX.to_le_bytes()].concat(),
+            vec![
+                AccountMeta::new(payer_pkey, true),
+                AccountMeta::new(recipient_pkey, false),
+                AccountMeta::new(escrow_pda, false),
+                AccountMeta::new_readonly(derive_config_pda(), false)
+            ]
+        );
+
+        let paused_instructions: Vec<(&str, Instruction, Vec<&Keypair>)> = vec![
+            ("Initialize", crate::instruction::initialize(&payer_pkey, &other_recipient_pkey, amount), vec![&payer]),
+            ("Initialize (payer pays fee)", crate::instruction::initialize_payer_pays_fee(&payer_pkey, &other_recipient_pkey, amount), vec![&payer]),
+            ("InitializeMilestones", crate::instruction::initialize_milestones(&payer_pkey, &other_recipient_pkey, &[(amount, i64::MAX)]), vec![&payer]),
+            ("InitializeToken", initialize_token_ix, vec![&payer]),
+            ("Reserve", crate::instruction::reserve(&payer_pkey, &other_recipient_pkey, amount, i64::MAX), vec![&payer]),
+            ("Fund", crate::instruction::fund(&payer_pkey, &recipient_pkey), vec![&payer]),
+            ("Complete", crate::instruction::complete(&payer_pkey, &recipient_pkey), vec![&payer]),
+            ("BatchSettle", crate::instruction::batch_settle(&payer_pkey, &[(payer_pkey, recipient_pkey)]), vec![&payer]),
+            ("Claim", crate::instruction::claim(&recipient_pkey, &payer_pkey), vec![&recipient]),
+            ("CompleteUsd", complete_usd_ix, vec![&payer]),
+            ("ApproveMilestone", crate::instruction::approve_milestone(&payer_pkey, &recipient_pkey, 0), vec![&payer]),
+            ("WithdrawVested", crate::instruction::withdraw_vested(&payer_pkey, &recipient_pkey), vec![&recipient]),
+            ("WithdrawVestedShare", crate::instruction::withdraw_vested_share(&payer_pkey, &recipient_pkey, &recipient_pkey), vec![&recipient]),
+            ("SettleSwap", crate::instruction::settle_swap(&payer_pkey, &recipient_pkey, amount, amount), vec![&payer, &recipient]),
+            ("Close", crate::instruction::close(&payer_pkey, &recipient_pkey), vec![&payer]),
+            ("BatchClose", batch_close_ix, vec![&payer]),
+            ("CancelMutual", cancel_mutual_ix, vec![&payer, &recipient]),
+            ("GovernanceVeto", governance_veto_ix, vec![&payer])
+        ];
+
+        for (name, ix, signers) in paused_instructions {
+            assert_eq!(
+                instruction_error(&context.banks_client, &[ix], &signers).await?,
+                InstructionError::Custom(EscrowError::Paused as u32),
+                "{} went through while paused",
+                name
+            );
+        }
+
+        // 3. unpausing resumes them, the escrow having been left untouched
+        test_utils::send(&mut context, &[set_paused_ix(0)], &[&admin]).await?;
+        test_utils::send(&mut context, &[crate::instruction::complete(&payer_pkey, &recipient_pkey)], &[&payer]).await?;
+
+        assert!(context.banks_client.get_account(escrow_pda).await?.is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_complete_charges_protocol_fee() -> Result<(), Box<dyn std::error::Error>> {
+        let mut context = test_utils::program_test().start_with_context().await;
+
+        let admin: Keypair = test_utils::funded_keypair(&mut context, 1_000_000_000).await?;
+        test_utils::set_upgrade_authority(&mut context, &admin.pubkey());
+        let payer: Keypair = test_utils::funded_keypair(&mut context, 10_000_000_000).await?;
+        let recipient_pkey: Pubkey = Keypair::new().pubkey();
+        let treasury_pkey: Pubkey = Keypair::new().pubkey();
+        let fee_vault_pda: Pubkey = Pubkey::find_program_address(&[b"fee_vault"], &crate::ID).0;
+        let amount: u64 = 1_000_000_000;
+
+        // 1. the admin sets a 2.5% fee, which can't exceed the cap
+        let initialize_config_ix: Instruction = crate::instruction::initialize_config(&admin.pubkey(), &Pubkey::default());
+        test_utils::send(&mut context, &[initialize_config_ix], &[&admin]).await?;
+
+        let capped_fee_ix: Instruction = crate::instruction::set_protocol_fee(&admin.pubkey(), Config::MAX_FEE_BPS + 1);
+        assert!(test_utils::send(&mut context, &[capped_fee_ix], &[&admin]).await.is_err());
+        assert!(test_utils::send(&mut context, &[crate::instruction::set_protocol_fee(&payer.pubkey(), 250)], &[&payer]).await.is_err());
+        test_utils::send(&mut context, &[crate::instruction::set_protocol_fee(&admin.pubkey(), 250)], &[&admin]).await?;
+
+        let config: Config = Config::unpack(&context.banks_client.get_account(derive_config_pda()).await?.ok_or("config wasn't created")?.data)?;
+        assert_eq!(config.fee_bps, 250);
+
+        // 2. completing now takes the fee vault, and the fee off the payout
+        test_utils::send(&mut context, &[crate::instruction::initialize(&payer.pubkey(), &recipient_pkey, amount)], &[&payer]).await?;
+
+        let mut complete_ix: Instruction = crate::instruction::complete(&payer.pubkey(), &recipient_pkey);
+        assert!(test_utils::send(&mut context, &[complete_ix.clone()], &[&payer]).await.is_err());
+
+        let vault_balance: u64 = context.banks_client.get_balance(fee_vault_pda).await?;
+        complete_ix.accounts.push(AccountMeta::new(fee_vault_pda, false));
+        test_utils::send(&mut context, &[complete_ix], &[&payer]).await?;
+
+        assert_eq!(context.banks_client.get_balance(recipient_pkey).await?, 975_000_000);
+        assert_eq!(context.banks_client.get_balance(fee_vault_pda).await?, vault_balance + 25_000_000);
+
+        // 3. only the admin withdraws the fees, and never the vault's rent
+        assert!(test_utils::send(&mut context, &[crate::instruction::withdraw_fees(&payer.pubkey(), &payer.pubkey(), 25_000_000)], &[&payer]).await.is_err());
+        assert!(test_utils::send(&mut context, &[crate::instruction::withdraw_fees(&admin.pubkey(), &treasury_pkey, 25_000_001)], &[&admin]).await.is_err());
+        test_utils::send(&mut context, &[crate::instruction::withdraw_fees(&admin.pubkey(), &treasury_pkey, 25_000_000)], &[&admin]).await?;
+
+        assert_eq!(context.banks_client.get_balance(treasury_pkey).await?, 25_000_000);
+        assert_eq!(context.banks_client.get_balance(fee_vault_pda).await?, vault_balance);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_complete_requires_recipient_attestation() -> Result<(), Box<dyn std::error::Error>> {
+        let recipient_pkey: Pubkey = Keypair::new().pubkey();
+        let (issuer_pkey, other_issuer_pkey): (Pubkey, Pubkey) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (attestation_pkey, foreign_attestation_pkey): (Pubkey, Pubkey) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let attestation_account = |credential: &Pubkey| {
+            let mut data: Vec<u8> = vec![2];
+            data.extend_from_slice(recipient_pkey.as_ref());
+            data.extend_from_slice(credential.as_ref());
+            data.extend_from_slice(Pubkey::new_unique().as_ref());
+            data.extend_from_slice(&0u32.to_le_bytes());
+            data.extend_from_slice(Pubkey::new_unique().as_ref());
+            data.extend_from_slice(&0i64.to_le_bytes());
+
+            Account { lamports: 10_000_000, data, owner: ATTESTATION_SERVICE_PROGRAM_ID, executable: false, rent_epoch: 0 }
+        };
+
+        // 1. the recipient holds an attestation from the accepted issuer, and one from another issuer
+        let mut program_test: ProgramTest = test_utils::program_test();
+        program_test.add_account(attestation_pkey, attestation_account(&issuer_pkey));
+        program_test.add_account(foreign_attestation_pkey, attestation_account(&other_issuer_pkey));
+        let mut context = program_test.start_with_context().await;
+
+        let admin: Keypair = test_utils::funded_keypair(&mut context, 1_000_000_000).await?;
+        test_utils::set_upgrade_authority(&mut context, &admin.pubkey());
+        let payer: Keypair = test_utils::funded_keypair(&mut context, 10_000_000_000).await?;
+        let amount: u64 = 1_000_000_000;
+
+        let initialize_config_ix: Instruction = crate::instruction::initialize_config(&admin.pubkey(), &Pubkey::default());
+        test_utils::send(&mut context, &[initialize_config_ix, crate::instruction::set_attestation_issuer(&admin.pubkey(), &issuer_pkey)], &[&admin]).await?;
+
+        // 2. the payer requires the recipient to be attested
+        test_utils::send(
+            &mut context,
+            &[
+                crate::instruction::initialize(&payer.pubkey(), &recipient_pkey, amount),
+                crate::instruction::require_attestation(&payer.pubkey(), &recipient_pkey)
+            ],
+            &[&payer]
+        ).await?;
+
+        // 3. completing fails without the attestation or with another issuer's, and goes through with the accepted one
+        let complete_ix = |attestation_pkey: Option<Pubkey>| {
+            let mut complete_ix: Instruction = crate::instruction::complete(&payer.pubkey(), &recipient_pkey);
+            complete_ix.accounts.extend(attestation_pkey.map(|pkey| AccountMeta::new_readonly(pkey, false)));
+            complete_ix
+        };
+
+        assert!(test_utils::send(&mut context, &[complete_ix(None)], &[&payer]).await.is_err());
+        assert!(test_utils::send(&mut context, &[complete_ix(Some(foreign_attestation_pkey))], &[&payer]).await.is_err());
+        test_utils::send(&mut context, &[complete_ix(Some(attestation_pkey))], &[&payer]).await?;
+
+        assert_eq!(context.banks_client.get_balance(recipient_pkey).await?, amount);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_isolated_settlement_rejects_foreign_instructions() -> Result<(), Box<dyn std::error::Error>> {
+        let mut context = test_utils::program_test().start_with_context().await;
+
+        let payer: Keypair = test_utils::funded_keypair(&mut context, 10_000_000_000).await?;
+        let recipient_pkey: Pubkey = Keypair::new().pubkey();
+        let (escrow_pda, _bump) = derive_escrow_pda(&payer.pubkey(), &recipient_pkey);
+
+        test_utils::send(
+            &mut context,
+            &[
+                crate::instruction::initialize(&payer.pubkey(), &recipient_pkey, 1_000_000_000),
+                crate::instruction::require_isolated_settlement(&payer.pubkey(), &recipient_pkey)
+            ],
+            &[&payer]
+        ).await?;
+
+        let mut complete_ix: Instruction = crate::instruction::complete(&payer.pubkey(), &recipient_pkey);
+        complete_ix.accounts.push(AccountMeta::new_readonly(solana_program::sysvar::instructions::ID, false));
+
+        // 1. settling next to another program's instruction fails with `EscrowError::ForeignInstruction`
+        let foreign_ix: Instruction = Instruction::new_with_bytes(spl_token::ID, &[], vec![]);
+        let err = test_utils::send(&mut context, &[complete_ix.clone(), foreign_ix], &[&payer]).await.unwrap_err();
+        assert!(err.to_string().contains(&format!("custom program error: {:#x}", EscrowError::ForeignInstruction as u32)));
+
+        // 2. the Instructions sysvar can't be left out
+        assert!(test_utils::send(&mut context, &[crate::instruction::complete(&payer.pubkey(), &recipient_pkey)], &[&payer]).await.is_err());
+
+        // 3. a settlement with a priority fee goes through
+        test_utils::send(&mut context, &[priority_fee::set_compute_unit_price(1), complete_ix], &[&payer]).await?;
+        assert!(context.banks_client.get_account(escrow_pda).await?.is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_export_and_import_state() -> Result<(), Box<dyn std::error::Error>> {
+        let mut context = test_utils::program_test().start_with_context().await;
+
+        let admin: Keypair = test_utils::funded_keypair(&mut context, 10_000_000_000).await?;
+        test_utils::set_upgrade_authority(&mut context, &admin.pubkey());
+        let payer: Keypair = test_utils::funded_keypair(&mut context, 10_000_000_000).await?;
+        let recipient_pkey: Pubkey = Keypair::new().pubkey();
+        let (escrow_pda, _bump) = derive_escrow_pda(&payer.pubkey(), &recipient_pkey);
+        let amount: u64 = 1_000_000_000;
+
+        let latest_blockhash: Hash = context.get_new_latest_blockhash().await?;
+        init_config(&context.banks_client, &admin, latest_blockhash).await?;
+        test_utils::send(&mut context, &[crate::instruction::initialize(&payer.pubkey(), &recipient_pkey, amount)], &[&payer]).await?;
+
+        // 1. only the admin exports, which closes the escrow
+        let data: Vec<u8> = context.banks_client.get_account(escrow_pda).await?.ok_or("escrow wasn't created")?.data;
+        assert!(test_utils::send(&mut context, &[crate::instruction::export_state(&payer.pubkey(), &escrow_pda)], &[&payer]).await.is_err());
+        test_utils::send(&mut context, &[crate::instruction::export_state(&admin.pubkey(), &escrow_pda)], &[&admin]).await?;
+        assert!(context.banks_client.get_account(escrow_pda).await?.is_none());
+
+        // 2. tampered data doesn't match the export
+        let import_ix = |data: &[u8]| crate::instruction::import_state(&admin.pubkey(), &crate::ID, &escrow_pda, &payer.pubkey(), &recipient_pkey, data);
+        let mut tampered_data: Vec<u8> = data.clone();
+        tampered_data[376..384].copy_from_slice(&(amount * 2).to_le_bytes());
+        assert!(test_utils::send(&mut context, &[import_ix(&tampered_data)], &[&admin]).await.is_err());
+
+        // 3. the exported data recreates the escrow with its lamports, once
+        test_utils::send(&mut context, &[import_ix(&data)], &[&admin]).await?;
+        let escrow_account: Account = context.banks_client.get_account(escrow_pda).await?.ok_or("escrow wasn't imported")?;
+        assert_eq!(escrow_account.data, data);
+        assert_eq!(escrow_account.lamports, context.banks_client.get_rent().await?.minimum_balance(EscrowAccount::LEN) + amount);
+
+        assert!(test_utils::send(&mut context, &[import_ix(&data)], &[&admin]).await.is_err());
+
+        // 4. and it settles as usual
+        test_utils::send(&mut context, &[crate::instruction::complete(&payer.pubkey(), &recipient_pkey)], &[&payer]).await?;
+        assert_eq!(context.banks_client.get_balance(recipient_pkey).await?, amount);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_quarantine_inconsistent_escrow() -> Result<(), Box<dyn std::error::Error>> {
+        let mut context = test_utils::program_test().start_with_context().await;
+
+        let admin: Keypair = test_utils::funded_keypair(&mut context, 10_000_000_000).await?;
+        test_utils::set_upgrade_authority(&mut context, &admin.pubkey());
+        let payer: Keypair = test_utils::funded_keypair(&mut context, 10_000_000_000).await?;
+        let recipient_pkey: Pubkey = Keypair::new().pubkey();
+        let (escrow_pda, _bump) = derive_escrow_pda(&payer.pubkey(), &recipient_pkey);
+
+        let latest_blockhash: Hash = context.get_new_latest_blockhash().await?;
+        init_config(&context.banks_client, &admin, latest_blockhash).await?;
+        test_utils::send(&mut context, &[crate::instruction::initialize(&payer.pubkey(), &recipient_pkey, 1_000_000_000)], &[&payer]).await?;
+
+        // 1. consistent escrows can't be quarantined
+        assert!(test_utils::send(&mut context, &[crate::instruction::quarantine(&escrow_pda)], &[]).await.is_err());
+
+        // 2. a corrupted discriminator fails every flow until anyone quarantines the escrow
+        let mut escrow_account: Account = context.banks_client.get_account(escrow_pda).await?.ok_or("escrow wasn't created")?;
+        escrow_account.data[..8].copy_from_slice(&[0; 8]);
+        context.set_account(&escrow_pda, &solana_sdk::account::AccountSharedData::from(escrow_account));
+
+        assert!(test_utils::send(&mut context, &[crate::instruction::complete(&payer.pubkey(), &recipient_pkey)], &[&payer]).await.is_err());
+        test_utils::send(&mut context, &[crate::instruction::quarantine(&escrow_pda)], &[]).await?;
+
+        let escrow_data: Vec<u8> = context.banks_client.get_account(escrow_pda).await?.ok_or("escrow was closed")?.data;
+        let escrow: EscrowAccount = EscrowAccount::unpack(&escrow_data)?;
+        assert_eq!(escrow.status, EscrowStatus::Quarantined);
+        assert_eq!(EscrowStatus::Quarantined.transition(EscrowStatus::Completed), Err(EscrowError::Quarantined.into()));
+
+        // 3. it stays out of the normal flows, is quarantined only once, and the admin recovers it
+        assert!(test_utils::send(&mut context, &[crate::instruction::complete(&payer.pubkey(), &recipient_pkey)], &[&payer]).await.is_err());
+        assert!(test_utils::send(&mut context, &[crate::instruction::close(&payer.pubkey(), &recipient_pkey)], &[&payer]).await.is_err());
+        assert!(test_utils::send(&mut context, &[crate::instruction::quarantine(&escrow_pda)], &[]).await.is_err());
+
+        test_utils::send(&mut context, &[crate::instruction::export_state(&admin.pubkey(), &escrow_pda)], &[&admin]).await?;
+        assert!(context.banks_client.get_account(escrow_pda).await?.is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_namespace_config_is_isolated() -> Result<(), Box<dyn std::error::Error>> {
+        let mut context = test_utils::program_test().start_with_context().await;
+
+        let operator: Keypair = test_utils::funded_keypair(&mut context, 1_000_000_000).await?;
+        let payer: Keypair = test_utils::funded_keypair(&mut context, 10_000_000_000).await?;
+        let (recipient_pkey, global_recipient_pkey): (Pubkey, Pubkey) = (Keypair::new().pubkey(), Keypair::new().pubkey());
+        let namespace: Pubkey = Pubkey::new_unique();
+        let namespace_config_pda: Pubkey = Pubkey::find_program_address(&[b"config", namespace.as_ref()], &crate::ID).0;
+        let namespace_fee_vault_pda: Pubkey = Pubkey::find_program_address(&[b"fee_vault", namespace.as_ref()], &crate::ID).0;
+        let amount: u64 = 1_000_000_000;
+
+        // 1. an operator runs its own namespace with a 1% fee, the global config doesn't exist
+        test_utils::send(
+            &mut context,
+            &[
+                crate::instruction::initialize_config(&operator.pubkey(), &namespace),
+                crate::instruction::in_namespace(crate::instruction::set_protocol_fee(&operator.pubkey(), 100), &namespace)
+            ],
+            &[&operator]
+        ).await?;
+
+        let config: Config = Config::unpack(&context.banks_client.get_account(namespace_config_pda).await?.ok_or("config wasn't created")?.data)?;
+        assert_eq!((config.admin, config.namespace, config.fee_bps), (operator.pubkey(), namespace, 100));
+        assert!(context.banks_client.get_account(derive_config_pda()).await?.is_none());
+
+        // 2. escrows are bound to the config they're created with
+        test_utils::send(
+            &mut context,
+            &[
+                crate::instruction::in_namespace(crate::instruction::initialize(&payer.pubkey(), &recipient_pkey, amount), &namespace),
+                crate::instruction::initialize(&payer.pubkey(), &global_recipient_pkey, amount)
+            ],
+            &[&payer]
+        ).await?;
+
+        let (escrow_pda, _bump) = derive_escrow_pda(&payer.pubkey(), &recipient_pkey);
+        let escrow: EscrowAccount = EscrowAccount::unpack(&context.banks_client.get_account(escrow_pda).await?.ok_or("escrow wasn't created")?.data)?;
+        assert_eq!(escrow.config, namespace_config_pda);
+
+        // 3. the namespaced escrow can't dodge its fee under the global config, and pays it into the namespace's vault
+        let complete_ix: Instruction = crate::instruction::complete(&payer.pubkey(), &recipient_pkey);
+        assert!(test_utils::send(&mut context, &[complete_ix.clone()], &[&payer]).await.is_err());
+
+        let mut namespace_complete_ix: Instruction = crate::instruction::in_namespace(complete_ix, &namespace);
+        namespace_complete_ix.accounts.push(AccountMeta::new(namespace_fee_vault_pda, false));
+        let vault_balance: u64 = context.banks_client.get_balance(namespace_fee_vault_pda).await?;
+
+        test_utils::send(&mut context, &[namespace_complete_ix], &[&payer]).await?;
+
+        assert_eq!(context.banks_client.get_balance(recipient_pkey).await?, 990_000_000);
+        assert_eq!(context.banks_client.get_balance(namespace_fee_vault_pda).await?, vault_balance + 10_000_000);
+
+        // 4. escrows of the global namespace are untouched by the operator's settings
+        let global_complete_ix: Instruction = crate::instruction::complete(&payer.pubkey(), &global_recipient_pkey);
+        assert!(test_utils::send(&mut context, &[crate::instruction::in_namespace(global_complete_ix.clone(), &namespace)], &[&payer]).await.is_err());
+        test_utils::send(&mut context, &[global_complete_ix], &[&payer]).await?;
+
+        assert_eq!(context.banks_client.get_balance(global_recipient_pkey).await?, amount);
+
+        // 5. the mint blocklist belongs to the whole deployment, the operator has no say over it
+        let mint_pkey: Pubkey = Pubkey::new_unique();
+        let (blocked_mint_pda, _bump) = Pubkey::find_program_address(&[b"blocked_mint", mint_pkey.as_ref()], &crate::ID);
+        let block_mint_ix: Instruction = Instruction::new_with_bytes(
+            crate::ID,
+            &[7],
+            vec![
+                AccountMeta::new(operator.pubkey(), true),
+                AccountMeta::new_readonly(namespace_config_pda, false),
+                AccountMeta::new_readonly(mint_pkey, false),
+                AccountMeta::new(blocked_mint_pda, false),
+                AccountMeta::new_readonly(system_program::ID, false)
+            ]
+        );
+
+        let error = instruction_error(&context.banks_client, &[block_mint_ix], &[&operator]).await?;
+        assert_eq!(error, InstructionError::Custom(EscrowError::NotAdmin as u32));
+        assert!(context.banks_client.get_account(blocked_mint_pda).await?.is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_global_config_is_created_by_upgrade_authority() -> Result<(), Box<dyn std::error::Error>> {
+        let mut context = test_utils::program_test().start_with_context().await;
+
+        let authority: Keypair = test_utils::funded_keypair(&mut context, 1_000_000_000).await?;
+        let front_runner: Keypair = test_utils::funded_keypair(&mut context, 1_000_000_000).await?;
+        test_utils::set_upgrade_authority(&mut context, &authority.pubkey());
+
+        // 1. anyone racing the deployer is rejected, with the program's `ProgramData` or an account of their own making
+        let front_run_ix: Instruction = crate::instruction::initialize_config(&front_runner.pubkey(), &Pubkey::default());
+        let error = instruction_error(&context.banks_client, &[front_run_ix.clone()], &[&front_runner]).await?;
+        assert_eq!(error, InstructionError::Custom(EscrowError::NotAdmin as u32));
+
+        let mut spoofed_ix: Instruction = front_run_ix;
+        spoofed_ix.accounts[3].pubkey = Pubkey::new_unique();
+        let error = instruction_error(&context.banks_client, &[spoofed_ix], &[&front_runner]).await?;
+        assert_eq!(error, InstructionError::Custom(EscrowError::PdaMismatch as u32));
+
+        assert!(context.banks_client.get_account(derive_config_pda()).await?.is_none());
+
+        // 2. the upgrade authority creates it and becomes its admin
+        test_utils::send(&mut context, &[crate::instruction::initialize_config(&authority.pubkey(), &Pubkey::default())], &[&authority]).await?;
+
+        let config: Config = Config::unpack(&context.banks_client.get_account(derive_config_pda()).await?.ok_or("config wasn't created")?.data)?;
+        assert_eq!(config.admin, authority.pubkey());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_update_config_limits_amounts() -> Result<(), Box<dyn std::error::Error>> {
+        let mut context = test_utils::program_test().start_with_context().await;
+
+        let admin: Keypair = test_utils::funded_keypair(&mut context, 1_000_000_000).await?;
+        test_utils::set_upgrade_authority(&mut context, &admin.pubkey());
+        let payer: Keypair = test_utils::funded_keypair(&mut context, 10_000_000_000).await?;
+        let recipient_pkey: Pubkey = Keypair::new().pubkey();
+
+        // 1. the admin limits escrows to 0.1..=1 SOL
+        let update_ix: Instruction = crate::instruction::update_config(&admin.pubkey(), 100_000_000, 1_000_000_000);
+        test_utils::send(&mut context, &[crate::instruction::initialize_config(&admin.pubkey(), &Pubkey::default()), update_ix], &[&admin]).await?;
+
+        let config: Config = Config::unpack(&context.banks_client.get_account(derive_config_pda()).await?.ok_or("config wasn't created")?.data)?;
+        assert_eq!((config.min_amount, config.max_amount), (100_000_000, 1_000_000_000));
+
+        // 2. only the admin updates the config, inverted limits are rejected
+        assert!(test_utils::send(&mut context, &[crate::instruction::update_config(&payer.pubkey(), 0, 0)], &[&payer]).await.is_err());
+        assert!(test_utils::send(&mut context, &[crate::instruction::update_config(&admin.pubkey(), 2, 1)], &[&admin]).await.is_err());
+
+        // 3. escrows must lock an amount within the limits
+        for amount in [99_999_999, 1_000_000_001] {
+            assert!(test_utils::send(&mut context, &[crate::instruction::initialize(&payer.pubkey(), &recipient_pkey, amount)], &[&payer]).await.is_err());
+        }
+        test_utils::send(&mut context, &[crate::instruction::initialize(&payer.pubkey(), &recipient_pkey, 1_000_000_000)], &[&payer]).await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_features_gate_instruction_families() -> Result<(), Box<dyn std::error::Error>> {
+        let mut context = test_utils::program_test().start_with_context().await;
+
+        let admin: Keypair = test_utils::funded_keypair(&mut context, 1_000_000_000).await?;
+        test_utils::set_upgrade_authority(&mut context, &admin.pubkey());
+        let alice: Keypair = test_utils::funded_keypair(&mut context, 10_000_000_000).await?;
+        let bob: Keypair = test_utils::funded_keypair(&mut context, 10_000_000_000).await?;
+
+        // 1. new configs enable every family, the admin then holds swaps back
+        test_utils::send(&mut context, &[crate::instruction::initialize_config(&admin.pubkey(), &Pubkey::default())], &[&admin]).await?;
+
+        let config: Config = Config::unpack(&context.banks_client.get_account(derive_config_pda()).await?.ok_or("config wasn't created")?.data)?;
+        assert_eq!(config.features, Config::FEATURES_ALL);
+
+        let features: u8 = Config::FEATURES_ALL & !Config::FEATURE_SWAPS;
+        assert!(test_utils::send(&mut context, &[crate::instruction::set_features(&alice.pubkey(), features)], &[&alice]).await.is_err());
+        test_utils::send(&mut context, &[crate::instruction::set_features(&admin.pubkey(), features)], &[&admin]).await?;
+
+        // 2. plain escrows still work, swaps fail with `EscrowError::FeatureDisabled`
+        test_utils::send(
+            &mut context,
+            &[
+                crate::instruction::initialize(&alice.pubkey(), &bob.pubkey(), 100_000_000),
+                crate::instruction::initialize(&bob.pubkey(), &alice.pubkey(), 250_000_000)
+            ],
+            &[&alice, &bob]
+        ).await?;
+
+        let settle_ix: Instruction = crate::instruction::settle_swap(&alice.pubkey(), &bob.pubkey(), 100_000_000, 250_000_000);
+        let err = test_utils::send(&mut context, &[settle_ix.clone()], &[&alice, &bob]).await.unwrap_err();
+        assert!(err.to_string().contains(&format!("custom program error: {:#x}", EscrowError::FeatureDisabled as u32)));
+
+        // 3. once rolled out, they go through
+        test_utils::send(&mut context, &[crate::instruction::set_features(&admin.pubkey(), Config::FEATURES_ALL)], &[&admin]).await?;
+        test_utils::send(&mut context, &[settle_ix], &[&alice, &bob]).await?;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_admin_handover_takes_nominee_acceptance() -> Result<(), Box<dyn std::error::Error>> {
+        let mut context = test_utils::program_test().start_with_context().await;
+
+        let admin: Keypair = test_utils::funded_keypair(&mut context, 1_000_000_000).await?;
+        test_utils::set_upgrade_authority(&mut context, &admin.pubkey());
+        let nominee: Keypair = test_utils::funded_keypair(&mut context, 1_000_000_000).await?;
+        let stranger: Keypair = test_utils::funded_keypair(&mut context, 1_000_000_000).await?;
+
+        // 1. nominating doesn't hand anything over yet
+        test_utils::send(
+            &mut context,
+            &[
+                crate::instruction::initialize_config(&admin.pubkey(), &Pubkey::default()),
+                crate::instruction::nominate_admin(&admin.pubkey(), &nominee.pubkey())
+            ],
+            &[&admin]
+        ).await?;
+
+        let config: Config = Config::unpack(&context.banks_client.get_account(derive_config_pda()).await?.ok_or("config wasn't created")?.data)?;
+        assert_eq!((config.admin, config.pending_admin), (admin.pubkey(), nominee.pubkey()));
+
+        // 2. only the nominee accepts
+        assert!(test_utils::send(&mut context, &[crate::instruction::accept_admin(&stranger.pubkey())], &[&stranger]).await.is_err());
+        test_utils::send(&mut context, &[crate::instruction::accept_admin(&nominee.pubkey())], &[&nominee]).await?;
+
+        let config: Config = Config::unpack(&context.banks_client.get_account(derive_config_pda()).await?.ok_or("config was closed")?.data)?;
+        assert_eq!((config.admin, config.pending_admin), (nominee.pubkey(), Pubkey::default()));
+
+        // 3. the former admin is out
+        assert!(test_utils::send(&mut context, &[crate::instruction::update_config(&admin.pubkey(), 0, 0)], &[&admin]).await.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_batch_settle_with_payer_authorizations() -> Result<(), Box<dyn std::error::Error>> {
+        let mut context = test_utils::program_test().start_with_context().await;
+
+        let relayer: Keypair = test_utils::funded_keypair(&mut context, 1_000_000_000).await?;
+        let alice: Keypair = test_utils::funded_keypair(&mut context, 10_000_000_000).await?;
+        let bob: Keypair = test_utils::funded_keypair(&mut context, 10_000_000_000).await?;
+        let (carol_pkey, dave_pkey): (Pubkey, Pubkey) = (Keypair::new().pubkey(), Keypair::new().pubkey());
+
+        test_utils::send(
+            &mut context,
+            &[
+                crate::instruction::initialize(&alice.pubkey(), &carol_pkey, 100_000_000),
+                crate::instruction::initialize(&bob.pubkey(), &dave_pkey, 200_000_000)
+            ],
+            &[&alice, &bob]
+        ).await?;
+
+        // 1. each payer signs its escrow's settlement message off-chain
+        let mut authorizations: Vec<(Pubkey, [u8; 64], Vec<u8>)> = Vec::new();
+        for (payer, recipient_pkey) in [(&alice, &carol_pkey), (&bob, &dave_pkey)] {
+            let (escrow_pda, _bump) = derive_escrow_pda(&payer.pubkey(), recipient_pkey);
+            let escrow: EscrowAccount = EscrowAccount::unpack(&context.banks_client.get_account(escrow_pda).await?.ok_or("escrow wasn't created")?.data)?;
+            let message: Vec<u8> = authorization::settlement_message(&escrow_pda, escrow.id).to_vec();
+
+            authorizations.push((payer.pubkey(), payer.sign_message(&message).as_ref().try_into()?, message));
+        }
+
+        // 2. without bob's authorization the whole batch fails
+        let settle_ix: Instruction = crate::instruction::batch_settle(&relayer.pubkey(), &[(alice.pubkey(), carol_pkey), (bob.pubkey(), dave_pkey)]);
+        assert!(test_utils::send(&mut context, &[authorization::ed25519_instruction(&authorizations[..1]), settle_ix.clone()], &[&relayer]).await.is_err());
+
+        // nor does alice's authorization settle bob's escrow
+        let mut forged: Vec<(Pubkey, [u8; 64], Vec<u8>)> = authorizations.clone();
+        forged[1] = (alice.pubkey(), alice.sign_message(&forged[1].2).as_ref().try_into()?, forged[1].2.clone());
+        assert!(test_utils::send(&mut context, &[authorization::ed25519_instruction(&forged), settle_ix.clone()], &[&relayer]).await.is_err());
+
+        // 3. the relayer settles both with a single signature of its own
+        test_utils::send(&mut context, &[authorization::ed25519_instruction(&authorizations), settle_ix], &[&relayer]).await?;
+
+        assert_eq!(context.banks_client.get_balance(carol_pkey).await?, 100_000_000);
+        assert_eq!(context.banks_client.get_balance(dave_pkey).await?, 200_000_000);
+
+        // 4. an authorization doesn't carry over to a later escrow at the same address
+        test_utils::send(&mut context, &[crate::instruction::initialize(&alice.pubkey(), &carol_pkey, 100_000_000)], &[&alice]).await?;
+
+        let replay_ix: Instruction = crate::instruction::batch_settle(&relayer.pubkey(), &[(alice.pubkey(), carol_pkey)]);
+        assert!(test_utils::send(&mut context, &[authorization::ed25519_instruction(&authorizations[..1]), replay_ix], &[&relayer]).await.is_err());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_batch_settle_rejects_escrows_charging_the_payer() -> Result<(), Box<dyn std::error::Error>> {
+        let mut context = test_utils::program_test().start_with_context().await;
+
+        let relayer: Keypair = test_utils::funded_keypair(&mut context, 1_000_000_000).await?;
+        let payer: Keypair = test_utils::funded_keypair(&mut context, 10_000_000_000).await?;
+        let recipient_pkey: Pubkey = Keypair::new().pubkey();
+        let (escrow_pda, _bump) = derive_escrow_pda(&payer.pubkey(), &recipient_pkey);
+
+        test_utils::send(
+            &mut context,
+            &[crate::instruction::initialize_payer_pays_fee(&payer.pubkey(), &recipient_pkey, 100_000_000)],
+            &[&payer]
+        ).await?;
+
+        let escrow: EscrowAccount = EscrowAccount::unpack(&context.banks_client.get_account(escrow_pda).await?.ok_or("escrow wasn't created")?.data)?;
+        let message: Vec<u8> = authorization::settlement_message(&escrow_pda, escrow.id).to_vec();
+        let authorizations: Vec<(Pubkey, [u8; 64], Vec<u8>)> = vec![(payer.pubkey(), payer.sign_message(&message).as_ref().try_into()?, message)];
+
+        // the payer's authorization is valid, but can't pay the protocol fee from its wallet
+        let latest_blockhash: Hash = context.get_new_latest_blockhash().await?;
+        let tx: Transaction = Transaction::new_signed_with_payer(
+            &[
+                authorization::ed25519_instruction(&authorizations),
+                crate::instruction::batch_settle(&relayer.pubkey(), &[(payer.pubkey(), recipient_pkey)])
+            ],
+            Some(&relayer.pubkey()),
+            &[&relayer],
+            latest_blockhash
+        );
+
+        assert_eq!(
+            context.banks_client.process_transaction(tx).await.err().ok_or("batch settled a payer-paid fee escrow")?.unwrap(),
+            TransactionError::InstructionError(1, InstructionError::InvalidArgument)
+        );
+        assert_eq!(context.banks_client.get_balance(recipient_pkey).await?, 0);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_max_payout_draws_escrow_down() -> Result<(), Box<dyn std::error::Error>> {
+        let mut context = test_utils::program_test().start_with_context().await;
+
+        let payer: Keypair = test_utils::funded_keypair(&mut context, 10_000_000_000).await?;
+        let recipient_pkey: Pubkey = Keypair::new().pubkey();
+        let (escrow_pda, _bump) = derive_escrow_pda(&payer.pubkey(), &recipient_pkey);
+
+        // 1. 1 SOL, released at most 0.4 SOL at a time
+        test_utils::send(
+            &mut context,
+            &[
+                crate::instruction::initialize(&payer.pubkey(), &recipient_pkey, 1_000_000_000),
+                crate::instruction::set_max_payout(&payer.pubkey(), &recipient_pkey, 400_000_000)
+            ],
+            &[&payer]
+        ).await?;
+
+        // 2. the first two draws leave the escrow open
+        for drawn in [400_000_000, 800_000_000] {
+            test_utils::send(&mut context, &[crate::instruction::complete(&payer.pubkey(), &recipient_pkey)], &[&payer]).await?;
+
+            assert_eq!(context.banks_client.get_balance(recipient_pkey).await?, drawn);
+            let escrow: EscrowAccount = EscrowAccount::unpack(&context.banks_client.get_account(escrow_pda).await?.ok_or("escrow was closed early")?.data)?;
+            assert_eq!((escrow.withdrawn, escrow.status), (drawn, EscrowStatus::Pending));
+        }
+
+        // 3. the last one settles the rest
+        test_utils::send(&mut context, &[crate::instruction::complete(&payer.pubkey(), &recipient_pkey)], &[&payer]).await?;
+
+        assert_eq!(context.banks_client.get_balance(recipient_pkey).await?, 1_000_000_000);
+        assert!(context.banks_client.get_account(escrow_pda).await?.is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_payer_pays_fee_on_top() -> Result<(), Box<dyn std::error::Error>> {
+        let mut context = test_utils::program_test().start_with_context().await;
+
+        let admin: Keypair = test_utils::funded_keypair(&mut context, 1_000_000_000).await?;
+        test_utils::set_upgrade_authority(&mut context, &admin.pubkey());
+        let payer: Keypair = test_utils::funded_keypair(&mut context, 10_000_000_000).await?;
+        let recipient_pkey: Pubkey = Keypair::new().pubkey();
+        let fee_vault_pda: Pubkey = Pubkey::find_program_address(&[b"fee_vault"], &crate::ID).0;
+
+        // 1. a 2.5% fee, borne by the payer of a 1 SOL escrow
+        test_utils::send(
+            &mut context,
+            &[
+                crate::instruction::initialize_config(&admin.pubkey(), &Pubkey::default()),
+                crate::instruction::set_protocol_fee(&admin.pubkey(), 250)
+            ],
+            &[&admin]
+        ).await?;
+        test_utils::send(&mut context, &[crate::instruction::initialize_payer_pays_fee(&payer.pubkey(), &recipient_pkey, 1_000_000_000)], &[&payer]).await?;
+
+        // 2. completing takes the system program after the fee vault, and pays the fee on top
+        let mut complete_ix: Instruction = crate::instruction::complete(&payer.pubkey(), &recipient_pkey);
+        complete_ix.accounts.push(AccountMeta::new(fee_vault_pda, false));
+        assert!(test_utils::send(&mut context, &[complete_ix.clone()], &[&payer]).await.is_err());
+
+        let vault_balance: u64 = context.banks_client.get_balance(fee_vault_pda).await?;
+        complete_ix.accounts.push(AccountMeta::new_readonly(system_program::ID, false));
+        test_utils::send(&mut context, &[complete_ix], &[&payer]).await?;
+
+        assert_eq!(context.banks_client.get_balance(recipient_pkey).await?, 1_000_000_000);
+        assert_eq!(context.banks_client.get_balance(fee_vault_pda).await?, vault_balance + 25_000_000);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_every_payout_is_charged_protocol_fee() -> Result<(), Box<dyn std::error::Error>> {
+        let mut context = test_utils::program_test().start_with_context().await;
+
+        let admin: Keypair = test_utils::funded_keypair(&mut context, 1_000_000_000).await?;
+        test_utils::set_upgrade_authority(&mut context, &admin.pubkey());
+        let payer: Keypair = test_utils::funded_keypair(&mut context, 10_000_000_000).await?;
+        let mut recipients: Vec<Keypair> = Vec::with_capacity(5);
+        for _ in 0..5 {
+            recipients.push(test_utils::funded_keypair(&mut context, 1_000_000_000).await?);
+        }
+        let [claimant, beneficiary, contractor, counterparty, fee_free_claimant] = &recipients[..] else { unreachable!() };
+        let fee_vault_pda: Pubkey = Pubkey::find_program_address(&[b"fee_vault"], &crate::ID).0;
+        let amount: u64 = 1_000_000_000;
+
+        // 1. a 2.5% fee, and one escrow per way of paying out
+        test_utils::send(
+            &mut context,
+            &[
+                crate::instruction::initialize_config(&admin.pubkey(), &Pubkey::default()),
+                crate::instruction::set_protocol_fee(&admin.pubkey(), 250)
+            ],
+            &[&admin]
+        ).await?;
+
+        let mut clock: Clock = context.banks_client.get_sysvar::<Clock>().await?;
+        test_utils::send(
+            &mut context,
+            &[
+                crate::instruction::initialize(&payer.pubkey(), &claimant.pubkey(), amount),
+                crate::instruction::initialize_vesting(&payer.pubkey(), &beneficiary.pubkey(), amount, clock.unix_timestamp, clock.unix_timestamp + 1000),
+                crate::instruction::initialize_milestones(&payer.pubkey(), &contractor.pubkey(), &[(amount, clock.unix_timestamp + 10_000)]),
+                crate::instruction::initialize(&payer.pubkey(), &counterparty.pubkey(), amount),
+                crate::instruction::initialize_payer_pays_fee(&payer.pubkey(), &fee_free_claimant.pubkey(), amount)
+            ],
+            &[&payer]
+        ).await?;
+
+        let with_fee_vault = |mut ix: Instruction| {
+            ix.accounts.push(AccountMeta::new(fee_vault_pda, false));
+            ix
+        };
+
+        // 2. `Claim`, `ApproveMilestone` and `WithdrawVested` can't skip the fee vault, and pay the fee into it
+        clock.unix_timestamp += 1001;
+        context.set_sysvar(&clock);
+
+        let payouts: [(Instruction, &Keypair, &Keypair); 3] = [
+            (crate::instruction::claim(&claimant.pubkey(), &payer.pubkey()), claimant, claimant),
+            (crate::instruction::withdraw_vested(&payer.pubkey(), &beneficiary.pubkey()), beneficiary, beneficiary),
+            (crate::instruction::approve_milestone(&payer.pubkey(), &contractor.pubkey(), 0), &payer, contractor)
+        ];
+        for (payout_ix, signer, receiver) in payouts {
+            let error = instruction_error(&context.banks_client, &[payout_ix.clone()], &[signer]).await?;
+            assert_eq!(error, InstructionError::NotEnoughAccountKeys);
+
+            let (receiver_balance, vault_balance): (u64, u64) = (
+                context.banks_client.get_balance(receiver.pubkey()).await?,
+                context.banks_client.get_balance(fee_vault_pda).await?
+            );
+            test_utils::send(&mut context, &[with_fee_vault(payout_ix)], &[signer]).await?;
+
+            assert_eq!(context.banks_client.get_balance(receiver.pubkey()).await?, receiver_balance + 975_000_000);
+            assert_eq!(context.banks_client.get_balance(fee_vault_pda).await?, vault_balance + 25_000_000);
+        }
+
+        // 3. cancellations are exempt, both parties get their share in full
+        let vault_balance: u64 = context.banks_client.get_balance(fee_vault_pda).await?;
+        let counterparty_balance: u64 = context.banks_client.get_balance(counterparty.pubkey()).await?;
+        let mut cancel_ix_payload: Vec<u8> = vec![24];
+        cancel_ix_payload.extend_from_slice(&5_000u16.to_le_bytes());
+        cancel_ix_payload.extend_from_slice(&5_000u16.to_le_bytes());
+        let cancel_mutual_ix: Instruction = Instruction::new_with_bytes(
+            crate::ID,
+            &cancel_ix_payload,
+            vec![
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new(counterparty.pubkey(), true),
+                AccountMeta::new(derive_escrow_pda(&payer.pubkey(), &counterparty.pubkey()).0, false),
+                AccountMeta::new_readonly(derive_config_pda(), false)
+            ]
+        );
+        test_utils::send(&mut context, &[cancel_mutual_ix], &[&payer, counterparty]).await?;
+
+        assert_eq!(context.banks_client.get_balance(counterparty.pubkey()).await?, counterparty_balance + amount / 2);
+        assert_eq!(context.banks_client.get_balance(fee_vault_pda).await?, vault_balance);
+
+        // 4. a payer bearing the fee has to co-sign the claim, the claimant then gets the escrow in full
+        let mut claim_ix: Instruction = with_fee_vault(crate::instruction::claim(&fee_free_claimant.pubkey(), &payer.pubkey()));
+        claim_ix.accounts.push(AccountMeta::new_readonly(system_program::ID, false));
+
+        let error = instruction_error(&context.banks_client, &[claim_ix.clone()], &[fee_free_claimant]).await?;
+        assert_eq!(error, InstructionError::MissingRequiredSignature);
+
+        claim_ix.accounts[1].is_signer = true;
+        let claimant_balance: u64 = context.banks_client.get_balance(fee_free_claimant.pubkey()).await?;
+        test_utils::send(&mut context, &[claim_ix], &[fee_free_claimant, &payer]).await?;
+
+        assert_eq!(context.banks_client.get_balance(fee_free_claimant.pubkey()).await?, claimant_balance + amount);
+        assert_eq!(context.banks_client.get_balance(fee_vault_pda).await?, vault_balance + 25_000_000);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_events_are_numbered_per_escrow() -> Result<(), Box<dyn std::error::Error>> {
+        let mut context = test_utils::program_test().start_with_context().await;
+
+        let payer: Keypair = test_utils::funded_keypair(&mut context, 10_000_000_000).await?;
+        let recipient_pkey: Pubkey = Keypair::new().pubkey();
+        let (escrow_pda, _bump) = derive_escrow_pda(&payer.pubkey(), &recipient_pkey);
+
+        // `Initialized`, then a `Deposited` per top-up
+        test_utils::send(
+            &mut context,
+            &[
+                crate::instruction::initialize(&payer.pubkey(), &recipient_pkey, 1_000_000_000),
+                crate::instruction::deposit(&payer.pubkey(), &recipient_pkey, 100_000_000),
+                crate::instruction::deposit(&payer.pubkey(), &recipient_pkey, 200_000_000)
+            ],
+            &[&payer]
+        ).await?;
+
+        let escrow: EscrowAccount = EscrowAccount::unpack(&context.banks_client.get_account(escrow_pda).await?.ok_or("escrow wasn't created")?.data)?;
+        assert_eq!(escrow.event_seq, 3);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_touch_reemits_escrow_state() -> Result<(), Box<dyn std::error::Error>> {
+        let mut context = test_utils::program_test().start_with_context().await;
+
+        let payer: Keypair = test_utils::funded_keypair(&mut context, 10_000_000_000).await?;
+        let recipient_pkey: Pubkey = Keypair::new().pubkey();
+        let (escrow_pda, _bump) = derive_escrow_pda(&payer.pubkey(), &recipient_pkey);
+
+        test_utils::send(&mut context, &[crate::instruction::initialize(&payer.pubkey(), &recipient_pkey, 1_000_000_000)], &[&payer]).await?;
+        let escrow_data: Vec<u8> = context.banks_client.get_account(escrow_pda).await?.ok_or("escrow wasn't created")?.data;
+
+        // anyone may touch the escrow: the state is logged, not changed
+        let touch_tx: Transaction = Transaction::new_signed_with_payer(
+            &[crate::instruction::touch(&escrow_pda)],
+            Some(&context.payer.pubkey()),
+            &[&context.payer],
+            context.get_new_latest_blockhash().await?
+        );
+        let result = context.banks_client.process_transaction_with_metadata(touch_tx).await?;
+        result.result?;
+
+        let event: EscrowEvent = result.metadata.ok_or("missing metadata")?.log_messages.iter()
+            .filter_map(|log| log.strip_prefix("Program data: "))
+            .filter_map(|data| base64::engine::general_purpose::STANDARD.decode(data).ok())
+            .find_map(|data| EscrowEvent::unpack(&data).ok())
+            .ok_or("no event was emitted")?;
+
+        match event {
+            EscrowEvent::Touched { escrow, seq, status, amount, .. } => {
+                assert_eq!((escrow, seq, status, amount), (escrow_pda, 1, EscrowStatus::Pending, 1_000_000_000));
+            },
+            event => return Err(format!("unexpected event: {:?}", event).into())
+        };
+        assert_eq!(context.banks_client.get_account(escrow_pda).await?.ok_or("escrow was closed")?.data, escrow_data);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_complete_emits_settlement_breakdown() -> Result<(), Box<dyn std::error::Error>> {
+        let mut context = test_utils::program_test().start_with_context().await;
+
+        let payer: Keypair = test_utils::funded_keypair(&mut context, 10_000_000_000).await?;
+        let recipient_pkey: Pubkey = Keypair::new().pubkey();
+        let (escrow_pda, _bump) = derive_escrow_pda(&payer.pubkey(), &recipient_pkey);
+
+        // `Settled` event of `escrow` among the logs of a processed transaction
+        let settled_breakdown = |log_messages: Vec<String>, escrow_pda: Pubkey| log_messages.iter()
+            .filter_map(|log| log.strip_prefix("Program data: "))
+            .filter_map(|data| base64::engine::general_purpose::STANDARD.decode(data).ok())
+            .find_map(|data| match EscrowEvent::unpack(&data) {
+                Ok(EscrowEvent::Settled { escrow, breakdown, .. }) if escrow == escrow_pda => Some(breakdown),
+                _ => None
+            });
+
+        // 1. a plain escrow: everything goes to the recipient
+        test_utils::send(&mut context, &[crate::instruction::initialize(&payer.pubkey(), &recipient_pkey, 1_000_000_000)], &[&payer]).await?;
+
+        let complete_tx: Transaction = Transaction::new_signed_with_payer(
+            &[crate::instruction::complete(&payer.pubkey(), &recipient_pkey)],
+            Some(&payer.pubkey()),
+            &[&payer],
+            context.get_new_latest_blockhash().await?
+        );
+        let result = context.banks_client.process_transaction_with_metadata(complete_tx).await?;
+        result.result?;
+
+        let breakdown: SettlementBreakdown = settled_breakdown(result.metadata.ok_or("missing metadata")?.log_messages, escrow_pda)
+            .ok_or("no Settled event was emitted")?;
+
+        assert_eq!(breakdown.principal, 1_000_000_000);
+        assert_eq!(breakdown.late_fee, 0);
+        assert_eq!(breakdown.payout, breakdown.principal - breakdown.protocol_fee);
+        assert_eq!(breakdown.shares, 0);
+        assert_eq!(breakdown.arbiter, 0);
+        assert_eq!(breakdown.rent_refund, crate::rent::escrow(&Rent::default()));
+
+        // 2. a split escrow: the referrer's 1% is broken out of the recipient's payout
+        let seller_pkey: Pubkey = Keypair::new().pubkey();
+        let referrer_pkey: Pubkey = Keypair::new().pubkey();
+        let (split_escrow_pda, _bump) = derive_escrow_pda(&payer.pubkey(), &seller_pkey);
+        let (assignments_pda, _bump) = Pubkey::find_program_address(&[b"assignments", split_escrow_pda.as_ref()], &crate::ID);
+
+        let initialize_split_ix: Instruction = crate::instruction::initialize_split(
+            &payer.pubkey(),
+            &seller_pkey,
+            1_000_000_000,
+            &[(referrer_pkey, 100)]
+        );
+        test_utils::send(&mut context, &[initialize_split_ix], &[&payer]).await?;
+
+        let mut complete_ix: Instruction = crate::instruction::complete(&payer.pubkey(), &seller_pkey);
+        complete_ix.accounts.push(AccountMeta::new(assignments_pda, false));
+        complete_ix.accounts.push(AccountMeta::new(referrer_pkey, false));
+        let complete_tx: Transaction = Transaction::new_signed_with_payer(
+            &[complete_ix],
+            Some(&payer.pubkey()),
+            &[&payer],
+            context.get_new_latest_blockhash().await?
+        );
+        let result = context.banks_client.process_transaction_with_metadata(complete_tx).await?;
+        result.result?;
+
+        let breakdown: SettlementBreakdown = settled_breakdown(result.metadata.ok_or("missing metadata")?.log_messages, split_escrow_pda)
+            .ok_or("no Settled event was emitted")?;
+
+        assert_eq!(breakdown.principal, 1_000_000_000);
+        assert_eq!(breakdown.protocol_fee, 0);
+        assert_eq!(breakdown.shares, 10_000_000);
+        assert_eq!(breakdown.payout, 990_000_000);
+        assert_eq!(breakdown.arbiter, 0);
+        assert_eq!(context.banks_client.get_balance(referrer_pkey).await?, breakdown.shares);
+        assert_eq!(context.banks_client.get_balance(seller_pkey).await?, breakdown.payout);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_discriminators()-> Result<(), Box<dyn std::error::Error>> {
+        let mut context = test_utils::program_test().start_with_context().await;
+
+        let payer: Keypair = test_utils::funded_keypair(&mut context, 10_000_000_000).await?;
+        let recipient_pkey: Pubkey = Keypair::new().pubkey();
+        let (escrow_pda, _bump) = derive_escrow_pda(&payer.pubkey(), &recipient_pkey);
+
+        test_utils::send(&mut context, &[crate::instruction::with_discriminator(crate::instruction::initialize(&payer.pubkey(), &recipient_pkey, 1_000_000_000))], &[&payer]).await?;
+        let escrow_data: Vec<u8> = context.banks_client.get_account(escrow_pda).await?.ok_or("escrow wasn't created")?.data;
+        assert_eq!(&escrow_data[..8], &EscrowAccount::DISCRIMINATOR);
+        // parties sit at fixed offsets, for `getProgramAccounts` memcmp filters
+        assert_eq!(&escrow_data[EscrowAccount::PAYER_OFFSET..EscrowAccount::PAYER_OFFSET + 32], payer.pubkey().as_ref());
+        assert_eq!(&escrow_data[EscrowAccount::RECIPIENT_OFFSET..EscrowAccount::RECIPIENT_OFFSET + 32], recipient_pkey.as_ref());
+
+        // a foreign account (any other discriminator) isn't mistaken for an escrow
+        let mut foreign_data: Vec<u8> = escrow_data.clone();
+        foreign_data[..8].copy_from_slice(&[0; 8]);
+        assert_eq!(EscrowAccount::unpack(&foreign_data).err(), Some(ProgramError::InvalidAccountData));
+
+        // 1-byte tags keep working next to discriminators
+        test_utils::send(&mut context, &[EscrowInstruction::complete(&payer.pubkey(), &recipient_pkey)], &[&payer]).await?;
+        assert!(context.banks_client.get_account(escrow_pda).await?.is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_deadline_buckets_index_upcoming_deadlines() -> Result<(), Box<dyn std::error::Error>> {
+        let mut context = test_utils::program_test().start_with_context().await;
+
+        let payer: Keypair = test_utils::funded_keypair(&mut context, 10_000_000_000).await?;
+        let notifier: Keypair = test_utils::funded_keypair(&mut context, 1_000_000_000).await?;
+        let recipient_pkey: Pubkey = Keypair::new().pubkey();
+        let (escrow_pda, _bump) = derive_escrow_pda(&payer.pubkey(), &recipient_pkey);
+
+        let now: i64 = context.banks_client.get_sysvar::<solana_program::clock::Clock>().await?.unix_timestamp;
+        let deadline: i64 = now + 24 * 60 * 60;
+        let day: i64 = DeadlineBucket::day_of(deadline);
+        let (bucket_pda, _bump) = Pubkey::find_program_address(&[b"deadlines", &day.to_le_bytes()], &crate::ID);
+
+        // 1. an escrow due tomorrow is listed in tomorrow's bucket, once
+        let mut initialize_ix: Instruction = crate::instruction::initialize(&payer.pubkey(), &recipient_pkey, 1_000_000_000);
+        initialize_ix.data[0] = 18;
+        initialize_ix.data.extend_from_slice(&deadline.to_le_bytes());
+        test_utils::send(&mut context, &[initialize_ix], &[&payer]).await?;
+
+        let index_ix: Instruction = crate::instruction::index_deadline(&notifier.pubkey(), &escrow_pda, deadline);
+        test_utils::send(&mut context, &[index_ix.clone()], &[&notifier]).await?;
+        assert!(test_utils::send(&mut context, &[index_ix], &[&notifier]).await.is_err());
+
+        let bucket: DeadlineBucket = DeadlineBucket::unpack(&context.banks_client.get_account(bucket_pda).await?.ok_or("bucket wasn't created")?.data)?;
+        assert_eq!((bucket.day, bucket.escrows), (day, vec![escrow_pda]));
+
+        // 2. it can't be pruned while it's still due, only once it's settled, which closes the emptied bucket
+        let prune_ix: Instruction = crate::instruction::prune_deadlines(&notifier.pubkey(), day, &[escrow_pda]);
+        assert!(test_utils::send(&mut context, &[prune_ix.clone()], &[&notifier]).await.is_err());
+
+        test_utils::send(&mut context, &[crate::instruction::complete(&payer.pubkey(), &recipient_pkey)], &[&payer]).await?;
+        test_utils::send(&mut context, &[prune_ix], &[&notifier]).await?;
+
+        assert!(context.banks_client.get_account(bucket_pda).await?.is_none());
+
+        Ok(())
+    }
+
+    /// Stand-in for an integrator's program, opening & completing escrows through `cpi`.
+    /// Accounts: escrow program, payer, recipient, escrow, system program, counter, config.
+    fn cpi_caller_process_instruction(
+        _program_id: &Pubkey,
+        accounts: &[solana_program::account_info::AccountInfo],
+        data: &[u8]
+    ) -> solana_program::entrypoint::ProgramResult {
+        let [escrow_program, payer, recipient, escrow, system_program, counter, config] = accounts else {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+
+        match data.split_first() {
+            Some((&0, amount)) => crate::cpi::initialize(
+                crate::cpi::InitializeAccounts { escrow_program, payer, recipient, escrow, system_program, counter, config },
+                u64::from_le_bytes(amount.try_into().map_err(|_| ProgramError::InvalidInstructionData)?),
+                &[]
+            ),
+            Some((&1, [])) => crate::cpi::complete(crate::cpi::SettleAccounts { escrow_program, payer, recipient, escrow, config }, &[]),
+            _ => Err(ProgramError::InvalidInstructionData)
+        }
+    }
+
+    /// Stand-in for an SPL stake pool, minting a pool token per deposited lamport and paying 1.1 lamports per
+    /// pool token withdrawn, out of its reserve. Its pool mint's authority is the withdraw authority,
+    /// a PDA (seeds: pool, `"withdraw"`). Accounts: those of `treasury::deposit_sol` / `treasury::withdraw_sol`.
+    fn stub_stake_pool_process_instruction(
+        program_id: &Pubkey,
+        accounts: &[solana_program::account_info::AccountInfo],
+        data: &[u8]
+    ) -> solana_program::entrypoint::ProgramResult {
+        let amount: u64 = data.get(1..9)
+            .and_then(|amount| amount.try_into().ok())
+            .map(u64::from_le_bytes)
+            .ok_or(ProgramError::InvalidInstructionData)?;
+
+        match (data[0], accounts) {
+            (14, [pool, withdraw_authority, reserve, depositor, pool_tokens, _manager_fee, _referral, pool_mint, system_program, token_program]) => {
+                solana_program::program::invoke(
+                    &system_instruction::transfer(depositor.key, reserve.key, amount),
+                    &[depositor.clone(), reserve.clone(), system_program.clone()]
+                )?;
+
+                let (_withdraw_authority, bump) = Pubkey::find_program_address(&[pool.key.as_ref(), b"withdraw"], program_id);
+                solana_program::program::invoke_signed(
+                    &spl_token::instruction::mint_to(token_program.key, pool_mint.key, pool_tokens.key, withdraw_authority.key, &[], amount)?,
+                    &[pool_mint.clone(), pool_tokens.clone(), withdraw_authority.clone(), token_program.clone()],
+                    &[&[pool.key.as_ref(), b"withdraw", &[bump]]]
+                )
+            },
+            (16, [_pool, _withdraw_authority, owner, pool_tokens, reserve, destination, _manager_fee, pool_mint, _clock, _stake_history, _stake_program, token_program]) => {
+                solana_program::program::invoke(
+                    &spl_token::instruction::burn(token_program.key, pool_tokens.key, pool_mint.key, owner.key, &[], amount)?,
+                    &[pool_tokens.clone(), pool_mint.clone(), owner.clone(), token_program.clone()]
+                )?;
+
+                let lamports: u64 = amount + amount / 10;
+                **reserve.lamports.borrow_mut() -= lamports;
+                **destination.lamports.borrow_mut() += lamports;
+
+                Ok(())
+            },
+            _ => Err(ProgramError::InvalidInstructionData)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sweep_fees_into_yield_venue_and_redeem_treasury() -> Result<(), Box<dyn std::error::Error>> {
+        let mut program_test: ProgramTest = test_utils::program_test();
+        program_test.add_program("stub_stake_pool", SPL_STAKE_POOL_PROGRAM_ID, processor!(stub_stake_pool_process_instruction));
+        let mut context = program_test.start_with_context().await;
+
+        let admin: Keypair = test_utils::funded_keypair(&mut context, 1_000_000_000).await?;
+        test_utils::set_upgrade_authority(&mut context, &admin.pubkey());
+        let intruder: Keypair = test_utils::funded_keypair(&mut context, 1_000_000_000).await?;
+        let fee_vault_pda: Pubkey = Pubkey::find_program_address(&[b"fee_vault"], &crate::ID).0;
+        let treasury_pda: Pubkey = Pubkey::find_program_address(&[b"treasury"], &crate::ID).0;
+
+        // 1. a config with a fee vault holding 1 SOL of collected fees
+        test_utils::send(
+            &mut context,
+            &[
+                crate::instruction::initialize_config(&admin.pubkey(), &Pubkey::default()),
+                crate::instruction::set_protocol_fee(&admin.pubkey(), 100)
+            ],
+            &[&admin]
+        ).await?;
+
+        let mut fee_vault: Account = context.banks_client.get_account(fee_vault_pda).await?.ok_or("fee vault wasn't created")?;
+        let vault_rent: u64 = fee_vault.lamports;
+        fee_vault.lamports += 1_000_000_000;
+        context.set_account(&fee_vault_pda, &fee_vault.into());
+
+        // 2. a stake pool of the stub, with 1 SOL of reserve to pay yield from, and the treasury's pool tokens
+        let stake_pool = |pool_pkey: Pubkey, pool_mint_pkey: Pubkey, reserve_pkey: Pubkey| StakePool {
+            program: SPL_STAKE_POOL_PROGRAM_ID,
+            pool: pool_pkey,
+            withdraw_authority: Pubkey::find_program_address(&[pool_pkey.as_ref(), b"withdraw"], &SPL_STAKE_POOL_PROGRAM_ID).0,
+            reserve_stake: reserve_pkey,
+            manager_fee: Pubkey::new_unique(),
+            pool_mint: pool_mint_pkey,
+            token_program: spl_token::ID
+        };
+        let pool_mint: Keypair = Keypair::new();
+        let (pool_pkey, reserve_pkey): (Pubkey, Pubkey) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let pool: StakePool = stake_pool(pool_pkey, pool_mint.pubkey(), reserve_pkey);
+        let stub_account = |lamports: u64| -> solana_sdk::account::AccountSharedData {
+            Account { lamports, data: vec![0; 8], owner: SPL_STAKE_POOL_PROGRAM_ID, executable: false, rent_epoch: 0 }.into()
+        };
+        context.set_account(&pool_pkey, &stub_account(1_000_000_000));
+        context.set_account(&reserve_pkey, &stub_account(1_000_000_000));
+
+        let rent: Rent = context.banks_client.get_rent().await?;
+        let pool_tokens_pkey: Pubkey = spl_associated_token_account::get_associated_token_address(&treasury_pda, &pool_mint.pubkey());
+        test_utils::send(
+            &mut context,
+            &[
+                system_instruction::create_account(
+                    &admin.pubkey(),
+                    &pool_mint.pubkey(),
+                    rent.minimum_balance(spl_token::state::Mint::LEN),
+                    spl_token::state::Mint::LEN as u64,
+                    &spl_token::ID
+                ),
+                spl_token::instruction::initialize_mint2(&spl_token::ID, &pool_mint.pubkey(), &pool.withdraw_authority, None, 9)?,
+                spl_associated_token_account::instruction::create_associated_token_account(&admin.pubkey(), &treasury_pda, &pool_mint.pubkey(), &spl_token::ID)
+            ],
+            &[&admin, &pool_mint]
+        ).await?;
+
+        // 3. nothing is swept before the admin picks the venue, which must belong to an allowlisted program
+        assert_eq!(
+            instruction_error(&context.banks_client, &[crate::instruction::sweep_fees(&admin.pubkey(), &pool, 100_000_000)], &[&admin]).await?,
+            InstructionError::InvalidArgument
+        );
+
+        let foreign_pool_pkey: Pubkey = Pubkey::new_unique();
+        context.set_account(&foreign_pool_pkey, &Account { lamports: 1_000_000_000, data: vec![0; 8], owner: Pubkey::new_unique(), executable: false, rent_epoch: 0 }.into());
+        assert_eq!(
+            instruction_error(&context.banks_client, &[crate::instruction::set_yield_venue(&admin.pubkey(), &foreign_pool_pkey)], &[&admin]).await?,
+            InstructionError::IncorrectProgramId
+        );
+        test_utils::send(&mut context, &[crate::instruction::set_yield_venue(&admin.pubkey(), &pool_pkey)], &[&admin]).await?;
+
+        // 4. only the admin sweeps, only into the venue, and only what the vault collected (above its rent)
+        assert_eq!(
+            instruction_error(&context.banks_client, &[crate::instruction::sweep_fees(&intruder.pubkey(), &pool, 100_000_000)], &[&intruder]).await?,
+            InstructionError::Custom(EscrowError::NotAdmin as u32)
+        );
+
+        let other_pool: StakePool = stake_pool(Pubkey::new_unique(), pool_mint.pubkey(), reserve_pkey);
+        assert_eq!(
+            instruction_error(&context.banks_client, &[crate::instruction::sweep_fees(&admin.pubkey(), &other_pool, 100_000_000)], &[&admin]).await?,
+            InstructionError::InvalidArgument
+        );
+
+        for amount in [0, 1_000_000_001] {
+            assert_eq!(
+                instruction_error(&context.banks_client, &[crate::instruction::sweep_fees(&admin.pubkey(), &pool, amount)], &[&admin]).await?,
+                InstructionError::InsufficientFunds
+            );
+        }
+
+        // 5. sweeping 0.5 SOL turns it into pool tokens held by the treasury
+        test_utils::send(&mut context, &[crate::instruction::sweep_fees(&admin.pubkey(), &pool, 500_000_000)], &[&admin]).await?;
+
+        let pool_tokens_account: Account = context.banks_client.get_account(pool_tokens_pkey).await?.ok_or("treasury's pool tokens are gone")?;
+        assert_eq!(spl_token::state::Account::unpack(&pool_tokens_account.data)?.amount, 500_000_000);
+        assert_eq!(context.banks_client.get_balance(fee_vault_pda).await?, vault_rent + 500_000_000);
+        assert_eq!(context.banks_client.get_balance(reserve_pkey).await?, 1_500_000_000);
+
+        let config: Config = Config::unpack(&context.banks_client.get_account(derive_config_pda()).await?.ok_or("config is gone")?.data)?;
+        assert_eq!(config.swept_principal, 500_000_000);
+
+        // the venue can't change while principal is swept into it
+        assert_eq!(
+            instruction_error(&context.banks_client, &[crate::instruction::set_yield_venue(&admin.pubkey(), &Pubkey::default())], &[&admin]).await?,
+            InstructionError::Custom(EscrowError::PrincipalOutstanding as u32)
+        );
+
+        // 6. redeeming takes the admin and some pool tokens
+        assert_eq!(
+            instruction_error(&context.banks_client, &[crate::instruction::redeem_treasury(&intruder.pubkey(), &pool, 500_000_000)], &[&intruder]).await?,
+            InstructionError::Custom(EscrowError::NotAdmin as u32)
+        );
+        assert_eq!(
+            instruction_error(&context.banks_client, &[crate::instruction::redeem_treasury(&admin.pubkey(), &pool, 0)], &[&admin]).await?,
+            InstructionError::InvalidInstructionData
+        );
+        assert_eq!(
+            instruction_error(&context.banks_client, &[crate::instruction::redeem_treasury(&admin.pubkey(), &other_pool, 500_000_000)], &[&admin]).await?,
+            InstructionError::InvalidArgument
+        );
+
+        // 7. redeeming every pool token brings the principal and 10% of yield back into the fee vault
+        test_utils::send(&mut context, &[crate::instruction::redeem_treasury(&admin.pubkey(), &pool, 500_000_000)], &[&admin]).await?;
+
+        assert_eq!(context.banks_client.get_balance(fee_vault_pda).await?, vault_rent + 1_050_000_000);
+        assert_eq!(context.banks_client.get_balance(treasury_pda).await?, 0);
+
+        let config: Config = Config::unpack(&context.banks_client.get_account(derive_config_pda()).await?.ok_or("config is gone")?.data)?;
+        assert_eq!((config.swept_principal, config.realized_yield), (0, 50_000_000));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_cpi_initialize_and_complete() -> Result<(), Box<dyn std::error::Error>> {
+        let caller_program_id: Pubkey = Pubkey::new_unique();
+        let mut program_test: ProgramTest = test_utils::program_test();
+        program_test.add_program("cpi_caller", caller_program_id, processor!(cpi_caller_process_instruction));
+        let mut context = program_test.start_with_context().await;
+
+        let payer: Keypair = test_utils::funded_keypair(&mut context, 10_000_000_000).await?;
+        let recipient_pkey: Pubkey = Keypair::new().pubkey();
+        let (escrow_pda, _bump) = derive_escrow_pda(&payer.pubkey(), &recipient_pkey);
+
+        // the caller passes the accounts of `instruction::initialize` on, behind the escrow program
+        let caller_ix = |data: Vec<u8>| -> Instruction {
+            let mut accounts: Vec<AccountMeta> = vec![AccountMeta::new_readonly(crate::ID, false)];
+            accounts.extend(crate::instruction::initialize(&payer.pubkey(), &recipient_pkey, 0).accounts);
+            accounts[2].is_writable = true;
+            Instruction { program_id: caller_program_id, accounts, data }
+        };
+
+        test_utils::send(&mut context, &[caller_ix([&[0][..], &1_000_000_000u64.to_le_bytes()].concat())], &[&payer]).await?;
+        let escrow: EscrowAccount = EscrowAccount::unpack(&context.banks_client.get_account(escrow_pda).await?.ok_or("escrow wasn't created")?.data)?;
+        assert_eq!((escrow.payer, escrow.amount), (payer.pubkey(), 1_000_000_000));
+
+        test_utils::send(&mut context, &[caller_ix(vec![1])], &[&payer]).await?;
+        assert_eq!(context.banks_client.get_balance(recipient_pkey).await?, 1_000_000_000);
+        assert!(context.banks_client.get_account(escrow_pda).await?.is_none());
+
+        Ok(())
+    }
+
+    async fn init_config(
+        banks_client: &BanksClient,
+        admin: &Keypair,
+        latest_blockhash: Hash
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let initialize_config_ix: Instruction = Instruction::new_with_bytes(
+            crate::ID,
+            &[6],
+            vec![
+                AccountMeta::new(admin.pubkey(), true),
+                AccountMeta::new(derive_config_pda(), false),
+                AccountMeta::new_readonly(system_program::ID, false),
+                AccountMeta::new_readonly(crate::get_program_data_address(&crate::ID), false)
+            ]
+        );
+        let message: Message = Message::new(&[initialize_config_ix], Some(&admin.pubkey()));
+        let mut initialize_config_tx: Transaction = Transaction::new_unsigned(message);
+        initialize_config_tx.sign(&[admin], latest_blockhash);
+        banks_client.process_transaction(initialize_config_tx).await?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_escrow_error_codes_round_trip() {
+        for code in 0..=18 {
+            let error: EscrowError = EscrowError::try_from(code).unwrap();
+            assert_eq!(ProgramError::from(error), ProgramError::Custom(code));
+        }
+
+        assert_eq!(EscrowError::try_from(19), Err(ProgramError::Custom(19)));
+    }
+
+    #[test]
+    fn test_treasury_redemptions_repay_principal_before_yield() {
+        let mut config: Config = Config::new(255, Pubkey::new_unique());
+        config.swept_principal = 1_000_000_000;
+
+        // 1. a partial redemption only repays principal
+        assert_eq!(config.record_redemption(600_000_000, false), (600_000_000, 0, 0));
+
+        // 2. the rest of the principal, then yield on top of it
+        assert_eq!(config.record_redemption(450_000_000, false), (400_000_000, 50_000_000, 0));
+        assert_eq!((config.swept_principal, config.realized_yield), (0, 50_000_000));
+
+        // 3. exhausting the pool tokens writes off the principal a loss left unpaid
+        config.swept_principal = 1_000_000_000;
+        assert_eq!(config.record_redemption(900_000_000, true), (900_000_000, 0, 100_000_000));
+        assert_eq!((config.swept_principal, config.realized_yield), (0, 50_000_000));
+    }
+
+    #[test]
+    fn test_simulation_message_keeps_signers() {
+        let payer_pkey: Pubkey = Pubkey::new_unique();
+        let recipient_pkey: Pubkey = Pubkey::new_unique();
+
+        let message: Message = crate::instruction::simulation_message(
+            &[EscrowInstruction::complete(&payer_pkey, &recipient_pkey)],
+            &payer_pkey
+        );
+
+        assert_eq!(message.header.num_required_signatures, 1);
+        assert_eq!(message.account_keys[0], payer_pkey);
+        assert_eq!(message.recent_blockhash, Hash::default());
+    }
+
+    #[test]
+    fn test_rent_helpers_match_account_sizes() {
+        let rent: Rent = Rent::default();
+
+        assert_eq!(crate::rent::escrow(&rent), rent.minimum_balance(EscrowAccount::LEN));
+        assert_eq!(crate::rent::deadline_bucket(&rent, 3), rent.minimum_balance(DeadlineBucket::HEADER_LEN + 3 * 32));
+        // SPL Token mints require no extensions, so their vaults are plain token accounts
+        assert_eq!(crate::rent::vault(&rent, &[]), Ok(rent.minimum_balance(spl_token::state::Account::LEN)));
+    }
+
+    #[test]
+    fn test_escrow_layouts_convert_across_versions() {
+        let payer_pkey: Pubkey = Pubkey::new_unique();
+        let recipient_pkey: Pubkey = Pubkey::new_unique();
+        let implied: ImpliedFields = ImpliedFields { program_id: &crate::ID, payer: &payer_pkey, recipient: &recipient_pkey };
+        let (global_config_pda, _bump) = Pubkey::find_program_address(&[crate::get_config_seeds()], &crate::ID);
+        let rich_bytes = |escrow: &EscrowAccount| -> Vec<u8> {
+            let mut data: Vec<u8> = vec![0; EscrowAccount::LEN];
+            escrow.store(LayoutVersion::Rich, &mut data).unwrap();
+            data
+        };
+
+        // 1. only the two layouts' lengths are told apart
+        for len in 0..=EscrowAccount::LEN + 1 {
+            let expected: Option<LayoutVersion> = match len {
+                2 => Some(LayoutVersion::Legacy),
+                EscrowAccount::LEN => Some(LayoutVersion::Rich),
+                _ => None
+            };
+            assert_eq!(LayoutVersion::detect(&vec![0; len]).ok(), expected);
+        }
+
+        // 2. every legacy account: initialized ones read as plain pending SOL escrows of the implied parties,
+        // written back byte for byte, and read the same once moved to the rich layout
+        for is_initialized in 0..=u8::MAX {
+            for bump in 0..=u8::MAX {
+                let legacy_data: [u8; 2] = [is_initialized, bump];
+                let loaded = EscrowAccount::load(&legacy_data, &implied);
+
+                if is_initialized != 1 {
+                    assert_eq!(loaded.err(), Some(ProgramError::UninitializedAccount));
+                    continue;
+                }
+
+                let (escrow, layout) = loaded.unwrap();
+                assert_eq!(layout, LayoutVersion::Legacy);
+                assert_eq!((escrow.bump, escrow.payer, escrow.recipient), (bump, payer_pkey, recipient_pkey));
+                assert_eq!((escrow.status, escrow.config, escrow.amount), (EscrowStatus::Pending, global_config_pda, 0));
+
+                let mut stored: [u8; 2] = [0; 2];
+                escrow.store(LayoutVersion::Legacy, &mut stored).unwrap();
+                assert_eq!(stored, legacy_data);
+
+                let rich_data: Vec<u8> = rich_bytes(&escrow);
+                let (migrated, layout) = EscrowAccount::load(&rich_data, &implied).unwrap();
+                assert_eq!(layout, LayoutVersion::Rich);
+                assert_eq!(rich_bytes(&migrated), rich_data);
+
+                // and back, since it holds nothing the legacy layout can't
+                migrated.store(LayoutVersion::Legacy, &mut stored).unwrap();
+                assert_eq!(stored, legacy_data);
+            }
+        }
+
+        // 3. rich escrows go back to the legacy layout only without state it has no room for
+        let mut legacy: [u8; 2] = [0; 2];
+        let rich = || EscrowAccount::new(7, 0, 0, payer_pkey, recipient_pkey, 1_000_000);
+        assert!(rich().store(LayoutVersion::Legacy, &mut legacy).is_ok());
+
+        let mutations: [fn(&mut EscrowAccount); 8] = [
+            |escrow| escrow.id = 1,
+            |escrow| escrow.created_at = 1,
+            |escrow| escrow.deadline = 1,
+            |escrow| escrow.mint = Pubkey::new_unique(),
+            |escrow| escrow.status = EscrowStatus::Accepted,
+            |escrow| escrow.hashlock = [1; 32],
+            |escrow| escrow.reference = [1; 16],
+            |escrow| escrow.resolve_recipient = true
+        ];
+
+        for mutate in mutations {
+            let mut escrow: EscrowAccount = rich();
+            mutate(&mut escrow);
+            assert_eq!(escrow.store(LayoutVersion::Legacy, &mut legacy), Err(ProgramError::InvalidAccountData));
+        }
+
+        // 4. neither layout is written into the other's length, and foreign rich data is still rejected
+        assert_eq!(rich().store(LayoutVersion::Rich, &mut legacy), Err(ProgramError::InvalidAccountData));
+        assert_eq!(rich().store(LayoutVersion::Legacy, &mut [0; EscrowAccount::LEN]), Err(ProgramError::InvalidAccountData));
+
+        let mut foreign_data: Vec<u8> = rich_bytes(&rich());
+        foreign_data[0] ^= 1;
+        assert_eq!(EscrowAccount::load(&foreign_data, &implied).err(), Some(ProgramError::InvalidAccountData));
+    }
+
+    fn any_pubkey() -> impl Strategy<Value = Pubkey> {
+        any::<[u8; 32]>().prop_map(Pubkey::new_from_array)
+    }
+
+    prop_compose! {
+        fn any_escrow_account()(
+            [is_initialized, nft, has_milestones, has_assignments, payer_splits, depeg_override, attestation_required,
+                payer_pays_fee, isolated_settlement, resolve_recipient] in any::<[bool; 10]>(),
+            bump in any::<u8>(),
+            status in (0..=u8::MAX).prop_filter_map("not a status", |status| EscrowStatus::try_from(status).ok()),
+            [price_feed, secondary_price_feed, mint, assignee, arbiter, depeg_price_feed, payer, recipient, swap, veto_realm,
+                config, stake_vote] in any::<[[u8; 32]; 12]>().prop_map(|pubkeys| pubkeys.map(Pubkey::new_from_array)),
+            [callback, hashlock] in any::<[[u8; 32]; 2]>(),
+            reference in any::<[u8; 16]>(),
+            [id, usd_amount, token_amount, amount, withdrawn, max_payout, rent_advanced, event_seq] in any::<[u64; 8]>(),
+            [created_at, deadline, unlock_at, reserved_until, vesting_start, vesting_end, veto_until] in any::<[i64; 7]>(),
+            [max_divergence_bps, late_fee_bps_per_day, late_fee_cap_bps, max_depeg_bps] in any::<[u16; 4]>()
+        ) -> EscrowAccount {
+            EscrowAccount {
+                is_initialized, bump, id, callback, usd_amount, price_feed, secondary_price_feed, max_divergence_bps, created_at,
+                mint, nft, has_milestones, deadline, late_fee_bps_per_day, late_fee_cap_bps, unlock_at, assignee, status,
+                has_assignments, arbiter, payer_splits, depeg_price_feed, max_depeg_bps, depeg_override, token_amount, reference,
+                payer, recipient, amount, hashlock, swap, reserved_until, vesting_start, vesting_end, withdrawn, veto_realm,
+                veto_until, attestation_required, config, max_payout, rent_advanced, payer_pays_fee, event_seq,
+                isolated_settlement, resolve_recipient, stake_vote
+            }
+        }
+    }
+
+    prop_compose! {
+        fn any_config()(
+            is_initialized in any::<bool>(),
+            [bump, paused, features] in any::<[u8; 3]>(),
+            fee_bps in any::<u16>(),
+            [admin, attestation_issuer, namespace, pending_admin, yield_venue] in any::<[[u8; 32]; 5]>()
+                .prop_map(|pubkeys| pubkeys.map(Pubkey::new_from_array)),
+            [min_amount, max_amount, swept_principal, realized_yield] in any::<[u64; 4]>()
+        ) -> Config {
+            Config {
+                is_initialized, bump, admin, paused, fee_bps, attestation_issuer, namespace, min_amount, max_amount,
+                pending_admin, features, yield_venue, swept_principal, realized_yield
+            }
+        }
+    }
+
+    prop_compose! {
+        fn any_archived_escrow()(
+            state_hash in any::<[u8; 32]>(),
+            [created_at, closed_at, acknowledged_at] in any::<[u32; 3]>(),
+            outcome in any::<u8>()
+        ) -> ArchivedEscrow {
+            ArchivedEscrow { state_hash, created_at, closed_at, outcome, acknowledged_at }
+        }
+    }
+
+    /// `unpack(pack(value)) == value`, `pack` writing every byte of the layout so no stale data survives in a reused buffer.
+    fn check_pack_round_trip<T: Pack + PartialEq + std::fmt::Debug>(value: T) -> Result<(), TestCaseError> {
+        let mut data: Vec<u8> = vec![0; T::LEN];
+        value.pack_into_slice(&mut data);
+        let mut dirty_data: Vec<u8> = vec![u8::MAX; T::LEN];
+        value.pack_into_slice(&mut dirty_data);
+
+        prop_assert_eq!(&dirty_data, &data);
+        prop_assert_eq!(T::unpack_unchecked(&data), Ok(value));
+        Ok(())
+    }
+
+    /// Asserts that the data of the instruction `$ix` unpacks to `$pattern`, led by its tag, its discriminator or the
+    /// length-prefixed header.
+    macro_rules! prop_assert_unpacks {
+        ($ix:expr, $pattern:pat $(if $guard:expr)?) => {
+            let ix: Instruction = $ix;
+            let layouts: [Vec<u8>; 3] = [
+                ix.data.clone(),
+                crate::instruction::with_discriminator(ix.clone()).data,
+                crate::instruction::with_length_prefix(ix).data
+            ];
+
+            for data in layouts {
+                prop_assert!(matches!(EscrowInstruction::unpack(&data), Ok($pattern) $(if $guard)?), "{:?} doesn't round-trip", data);
+            }
+        };
+    }
+
+    proptest! {
+        #[test]
+        fn test_escrow_account_pack_round_trip(escrow in any_escrow_account()) {
+            check_pack_round_trip(escrow)?;
+        }
+
+        #[test]
+        fn test_fixed_size_state_pack_round_trips(
+            config in any_config(),
+            record in any_archived_escrow(),
+            is_initialized in any::<bool>(),
+            bump in any::<u8>(),
+            [first_pkey, second_pkey] in any::<[[u8; 32]; 2]>().prop_map(|pubkeys| pubkeys.map(Pubkey::new_from_array)),
+            hash in any::<[u8; 32]>(),
+            [first_amount, second_amount] in any::<[u64; 2]>(),
+            deadline in any::<i64>(),
+            label in "\\PC{0,8}"
+        ) {
+            check_pack_round_trip(config)?;
+            check_pack_round_trip(record)?;
+            check_pack_round_trip(EscrowCounter { is_initialized, bump, next_id: first_amount })?;
+            check_pack_round_trip(EscrowExport { is_initialized, bump, escrow: first_pkey, lamports: first_amount, hash })?;
+            check_pack_round_trip(EscrowSwap { is_initialized, bump, leg_a: first_pkey, leg_b: second_pkey, deadline })?;
+            check_pack_round_trip(VestingShare {
+                is_initialized,
+                bump,
+                escrow: first_pkey,
+                beneficiary: second_pkey,
+                allocation: first_amount,
+                withdrawn: second_amount
+            })?;
+            check_pack_round_trip(BlockedMint { is_initialized, bump, mint: first_pkey })?;
+            check_pack_round_trip(EscrowDisplay { is_initialized, bump, label, icon_hash: hash })?;
+        }
+
+        #[test]
+        fn test_variable_size_state_pack_round_trips(
+            bump in any::<u8>(),
+            ciphertext in prop::collection::vec(any::<u8>(), 0..=EscrowNote::MAX_CIPHERTEXT_LEN),
+            milestones in prop::collection::vec(
+                (any::<u64>(), any::<i64>(), any::<bool>()).prop_map(|(amount, deadline, released)| Milestone { amount, deadline, released }),
+                0..=EscrowMilestones::MAX_MILESTONES
+            ),
+            shares in prop::collection::vec(
+                (any_pubkey(), any::<u16>()).prop_map(|(assignee, bps)| ClaimShare { assignee, bps }),
+                0..=EscrowAssignments::MAX_SHARES
+            ),
+            day in any::<i64>(),
+            escrows in prop::collection::vec(any_pubkey(), 0..=16),
+            records in prop::collection::vec(any_archived_escrow(), 0..=8)
+        ) {
+            let note: EscrowNote = EscrowNote::new(bump, ciphertext);
+            let mut data: Vec<u8> = vec![0; EscrowNote::space(note.ciphertext.len())];
+            note.pack_into_slice(&mut data);
+            prop_assert_eq!(EscrowNote::unpack(&data), Ok(note));
+
+            let milestones: EscrowMilestones = EscrowMilestones::new(bump, milestones);
+            let mut data: Vec<u8> = vec![0; EscrowMilestones::space(milestones.milestones.len())];
+            milestones.pack_into_slice(&mut data);
+            prop_assert_eq!(EscrowMilestones::unpack(&data), Ok(milestones));
+
+            let assignments: EscrowAssignments = EscrowAssignments { shares, ..EscrowAssignments::new(bump) };
+            let mut data: Vec<u8> = vec![0; EscrowAssignments::space(assignments.shares.len())];
+            assignments.pack_into_slice(&mut data);
+            prop_assert_eq!(EscrowAssignments::unpack(&data), Ok(assignments));
+
+            let bucket: DeadlineBucket = DeadlineBucket { escrows, ..DeadlineBucket::new(bump, day) };
+            let mut data: Vec<u8> = vec![0; DeadlineBucket::space(bucket.escrows.len())];
+            bucket.pack_into_slice(&mut data);
+            prop_assert_eq!(DeadlineBucket::unpack(&data), Ok(bucket));
+
+            // the archive's records are appended after its header
+            let archive: EscrowArchive = EscrowArchive::new(bump);
+            let mut data: Vec<u8> = vec![0; EscrowArchive::space(records.len())];
+            archive.pack_into_slice(&mut data);
+            for (record, chunk) in records.iter().zip(data[EscrowArchive::HEADER_LEN..].chunks_exact_mut(ArchivedEscrow::LEN)) {
+                record.pack_into_slice(chunk);
+            }
+            prop_assert_eq!(EscrowArchive::unpack(&data), Ok(archive));
+            prop_assert_eq!(EscrowArchive::records(&data), Ok(records));
+        }
+
+        #[test]
+        fn test_instruction_data_round_trips(
+            [first_amount, second_amount] in any::<[u64; 2]>(),
+            timestamp in any::<i64>(),
+            (start, end) in (any::<i64>(), any::<i64>()).prop_filter_map("empty schedule", |(start, end)| (start < end).then_some((start, end))),
+            bps in any::<u16>(),
+            byte in any::<u8>(),
+            pubkey in any_pubkey(),
+            milestones in prop::collection::vec((any::<u64>(), any::<i64>()), 0..=EscrowMilestones::MAX_MILESTONES),
+            splits in prop::collection::vec((any_pubkey(), any::<u16>()), 0..=EscrowAssignments::MAX_SHARES)
+        ) {
+            let payer_pkey: Pubkey = Pubkey::new_unique();
+            let recipient_pkey: Pubkey = Pubkey::new_unique();
+
+            prop_assert_unpacks!(
+                crate::instruction::initialize(&payer_pkey, &recipient_pkey, first_amount),
+                EscrowInstruction::Initialize { amount, deadline: None, vesting: None, splits: None, payer_pays_fee: false, note: None, .. }
+                    if amount == first_amount
+            );
+            prop_assert_unpacks!(
+                crate::instruction::initialize_payer_pays_fee(&payer_pkey, &recipient_pkey, first_amount),
+                EscrowInstruction::Initialize { amount, payer_pays_fee: true, note: None, .. } if amount == first_amount
+            );
+            prop_assert_unpacks!(
+                crate::instruction::initialize_vesting(&payer_pkey, &recipient_pkey, first_amount, start, end),
+                EscrowInstruction::Initialize { amount, vesting: Some(vesting), note: None, .. } if (amount, vesting) == (first_amount, (start, end))
+            );
+            prop_assert_unpacks!(
+                crate::instruction::initialize_split(&payer_pkey, &recipient_pkey, first_amount, &splits),
+                EscrowInstruction::Initialize { amount, splits: Some(unpacked_splits), note: None, .. }
+                    if amount == first_amount
+                        && unpacked_splits.iter().map(|share| (share.assignee, share.bps)).eq(splits.iter().copied())
+            );
+            prop_assert_unpacks!(
+                crate::instruction::initialize_milestones(&payer_pkey, &recipient_pkey, &milestones),
+                EscrowInstruction::InitializeMilestones { milestones: unpacked_milestones }
+                    if unpacked_milestones.iter().all(|milestone| !milestone.released)
+                        && unpacked_milestones.iter().map(|milestone| (milestone.amount, milestone.deadline)).eq(milestones.iter().copied())
+            );
+            prop_assert_unpacks!(
+                crate::instruction::reserve(&payer_pkey, &recipient_pkey, first_amount, timestamp),
+                EscrowInstruction::Reserve { amount, reserved_until } if (amount, reserved_until) == (first_amount, timestamp)
+            );
+            prop_assert_unpacks!(
+                crate::instruction::deposit(&payer_pkey, &recipient_pkey, first_amount),
+                EscrowInstruction::Deposit { amount } if amount == first_amount
+            );
+            prop_assert_unpacks!(
+                crate::instruction::approve_milestone(&payer_pkey, &recipient_pkey, byte),
+                EscrowInstruction::ApproveMilestone { index } if index == byte
+            );
+            prop_assert_unpacks!(
+                crate::instruction::set_max_payout(&payer_pkey, &recipient_pkey, first_amount),
+                EscrowInstruction::SetMaxPayout { max_payout } if max_payout == first_amount
+            );
+            prop_assert_unpacks!(
+                crate::instruction::settle_swap(&payer_pkey, &recipient_pkey, first_amount, second_amount),
+                EscrowInstruction::SettleSwap { amount_a, amount_b } if (amount_a, amount_b) == (first_amount, second_amount)
+            );
+            prop_assert_unpacks!(
+                crate::instruction::set_stake_settlement(&recipient_pkey, &payer_pkey, &recipient_pkey, &pubkey),
+                EscrowInstruction::SetStakeSettlement { vote } if vote == pubkey
+            );
+            prop_assert_unpacks!(
+                crate::instruction::set_protocol_fee(&payer_pkey, bps),
+                EscrowInstruction::SetProtocolFee { fee_bps } if fee_bps == bps
+            );
+            prop_assert_unpacks!(
+                crate::instruction::withdraw_fees(&payer_pkey, &recipient_pkey, first_amount),
+                EscrowInstruction::WithdrawFees { amount } if amount == first_amount
+            );
+            prop_assert_unpacks!(
+                crate::instruction::update_config(&payer_pkey, first_amount, second_amount),
+                EscrowInstruction::UpdateConfig { min_amount, max_amount } if (min_amount, max_amount) == (first_amount, second_amount)
+            );
+            prop_assert_unpacks!(
+                crate::instruction::nominate_admin(&payer_pkey, &pubkey),
+                EscrowInstruction::NominateAdmin { admin } if admin == pubkey
+            );
+            prop_assert_unpacks!(
+                crate::instruction::set_features(&payer_pkey, byte),
+                EscrowInstruction::SetFeatures { features } if features == byte
+            );
+        }
+    }
+
+    #[test]
+    fn test_instruction_data_lengths_are_checked() {
+        let too_short: Option<ProgramError> = Some(EscrowError::InstructionDataTooShort.into());
+        let too_long: Option<ProgramError> = Some(EscrowError::InstructionDataTooLong.into());
+        let deposit_data: Vec<u8> = crate::instruction::deposit(&Pubkey::new_unique(), &Pubkey::new_unique(), 7).data;
+        let framed_deposit_data: Vec<u8> = [&[crate::instruction::EXTENDED_TAG, 32, 0, 8, 0][..], &deposit_data[1..]].concat();
+
+        // 1. empty data, a header cut short, and frames shorter or longer than their announced length
+        assert_eq!(EscrowInstruction::unpack(&[]).err(), too_short);
+        assert_eq!(EscrowInstruction::unpack(&framed_deposit_data[..3]).err(), too_short);
+        assert_eq!(EscrowInstruction::unpack(&framed_deposit_data[..12]).err(), too_short);
+        assert_eq!(EscrowInstruction::unpack(&[&framed_deposit_data[..], &[0]].concat()).err(), too_long);
+        assert!(matches!(EscrowInstruction::unpack(&framed_deposit_data), Ok(EscrowInstruction::Deposit { amount: 7 })));
+
+        // 2. payloads shorter or longer than their instruction's fields, whichever the layout
+        let mut long_frame: Vec<u8> = [&framed_deposit_data[..], &[0]].concat();
+        long_frame[3] = 9;
+        assert_eq!(EscrowInstruction::unpack(&long_frame).err(), too_long);
+        assert_eq!(EscrowInstruction::unpack(&deposit_data[..8]).err(), too_short);
+        assert_eq!(EscrowInstruction::unpack(&[&deposit_data[..], &[0]].concat()).err(), too_long);
+        assert_eq!(EscrowInstruction::unpack(&[57, 0]).err(), too_long);
+
+        // 3. optional last fields are either left out or complete, variable-length ones take whatever follows
+        assert!(matches!(EscrowInstruction::unpack(&[22]), Ok(EscrowInstruction::Claim { preimage: None })));
+        assert_eq!(EscrowInstruction::unpack(&[22, 0]).err(), too_short);
+        assert_eq!(EscrowInstruction::unpack(&[[22].as_slice(), &[0; 33]].concat()).err(), too_long);
+        assert!(matches!(EscrowInstruction::unpack(&[1, 0, 0]), Ok(EscrowInstruction::Complete { note: Some(note), .. }) if note == [0, 0]));
+
+        // 4. unknown tags, in either layout
+        assert_eq!(EscrowInstruction::unpack(&[74]).err(), Some(EscrowError::UnknownInstruction.into()));
+        assert_eq!(EscrowInstruction::unpack(&[crate::instruction::EXTENDED_TAG, 0, 1, 0, 0]).err(), Some(EscrowError::UnknownInstruction.into()));
+        assert_eq!(EscrowInstruction::unpack(&[crate::instruction::DISCRIMINATOR_TAG, 0, 0, 0, 0, 0, 0, 0, 0]).err(), Some(EscrowError::UnknownInstruction.into()));
+
+        // 5. flags are either 0 or 1
+        assert!(matches!(EscrowInstruction::unpack(&[25, 0xc4, 0x09, 1]), Ok(EscrowInstruction::AssignClaimShare { bps: 2_500, from_payout: true })));
+        assert!(matches!(EscrowInstruction::unpack(&[25, 0xc4, 0x09, 0]), Ok(EscrowInstruction::AssignClaimShare { bps: 2_500, from_payout: false })));
+        assert_eq!(EscrowInstruction::unpack(&[25, 0xc4, 0x09, 2]).err(), Some(ProgramError::InvalidInstructionData));
+
+        // 6. a 1-byte tag is never read as a discriminator, even when its payload makes the data start like one
+        // (`Complete`'s discriminator starts with `Initialize`'s tag)
+        let complete_discriminator: Vec<u8> = crate::instruction::with_discriminator(crate::instruction::complete(&Pubkey::new_unique(), &Pubkey::new_unique())).data;
+        let initialize_data: Vec<u8> = [&complete_discriminator[1..], &[0]].concat();
+        let amount: u64 = u64::from_le_bytes(initialize_data[1..9].try_into().unwrap());
+        assert!(matches!(EscrowInstruction::unpack(&initialize_data), Ok(EscrowInstruction::Initialize { amount: unpacked, .. }) if unpacked == amount));
+    }
+
+    #[test]
+    fn test_idl_layout_round_trips_through_unpack() {
+        use crate::idl::IdlInstruction;
+
+        let pubkey: Pubkey = Pubkey::new_unique();
+        let idl_data = |instruction: IdlInstruction, trailing: &[u8]| -> Vec<u8> {
+            [borsh::to_vec(&instruction).unwrap(), trailing.to_vec()].concat()
+        };
+
+        // 1. the IDL's fields are exactly what the tag reads, variable-length fields being appended to them
+        assert!(matches!(
+            EscrowInstruction::unpack(&idl_data(IdlInstruction::Initialize { amount: 7 }, &[])),
+            Ok(EscrowInstruction::Initialize { amount: 7, deadline: None, note: None, .. })
+        ));
+        assert!(matches!(
+            EscrowInstruction::unpack(&idl_data(IdlInstruction::Initialize { amount: 7 }, b"note")),
+            Ok(EscrowInstruction::Initialize { amount: 7, note: Some(note), .. }) if note == b"note"
+        ));
+        assert!(matches!(
+            EscrowInstruction::unpack(&idl_data(IdlInstruction::Complete, &[])),
+            Ok(EscrowInstruction::Complete { preimage: None, note: None })
+        ));
+        assert!(matches!(
+            EscrowInstruction::unpack(&idl_data(IdlInstruction::CompleteWithPreimage { preimage: [3; 32] }, &[])),
+            Ok(EscrowInstruction::Complete { preimage: Some(preimage), note: None }) if preimage == [3; 32]
+        ));
+        assert!(matches!(
+            EscrowInstruction::unpack(&idl_data(IdlInstruction::InitializeWithReference { amount: 7, deadline: 0, reference: [1; 16] }, &[])),
+            Ok(EscrowInstruction::Initialize { amount: 7, deadline: None, reference: Some(reference), note: None, .. }) if reference == [1; 16]
+        ));
+        assert!(matches!(
+            EscrowInstruction::unpack(&idl_data(IdlInstruction::InitializeVesting { amount: 7, start: 1, end: 2 }, &[])),
+            Ok(EscrowInstruction::Initialize { amount: 7, vesting: Some((1, 2)), note: None, .. })
+        ));
+        assert!(matches!(
+            EscrowInstruction::unpack(&idl_data(IdlInstruction::InitializeSplit { amount: 7, count: 1 }, &[pubkey.as_ref(), &[0x10, 0x27]].concat())),
+            Ok(EscrowInstruction::Initialize { amount: 7, splits: Some(splits), note: None, .. })
+                if splits == [ClaimShare { assignee: pubkey, bps: 10_000 }]
+        ));
+        assert!(matches!(
+            EscrowInstruction::unpack(&idl_data(IdlInstruction::InitializeToDomain { amount: 7, resolution: RecipientResolution::AtSettlement }, &[])),
+            Ok(EscrowInstruction::Initialize { amount: 7, domain: Some(RecipientResolution::AtSettlement), note: None, .. })
+        ));
+        assert!(matches!(
+            EscrowInstruction::unpack(&idl_data(IdlInstruction::InitializeFromTemplate { amount: 7, template: MilestoneTemplate::Halves }, &[])),
+            Ok(EscrowInstruction::InitializeFromTemplate { amount: 7, template: MilestoneTemplate::Halves })
+        ));
+        assert!(matches!(
+            EscrowInstruction::unpack(&idl_data(IdlInstruction::InitializeMilestones { count: 1 }, &[7u64.to_le_bytes(), 9i64.to_le_bytes()].concat())),
+            Ok(EscrowInstruction::InitializeMilestones { milestones }) if milestones == [Milestone { amount: 7, deadline: 9, released: false }]
+        ));
+        assert!(matches!(
+            EscrowInstruction::unpack(&idl_data(IdlInstruction::SetDisplayMetadata { icon_hash: [2; 32] }, b"label")),
+            Ok(EscrowInstruction::SetDisplayMetadata { label, icon_hash: [2, ..] }) if label == "label"
+        ));
+        assert!(matches!(
+            EscrowInstruction::unpack(&idl_data(IdlInstruction::AcknowledgeReceipt { record: 1, lamports: 7 }, b"state")),
+            Ok(EscrowInstruction::AcknowledgeReceipt { record: 1, lamports: 7, state }) if state == b"state"
+        ));
+        assert!(matches!(
+            EscrowInstruction::unpack(&idl_data(IdlInstruction::AssignClaimShare { bps: 2_500, from_payout: true }, &[])),
+            Ok(EscrowInstruction::AssignClaimShare { bps: 2_500, from_payout: true })
+        ));
+
+        // 2. optional fields are sent zeroed when unused
+        assert!(matches!(
+            EscrowInstruction::unpack(&idl_data(IdlInstruction::CloseNote { mint: Pubkey::default() }, &[])),
+            Ok(EscrowInstruction::CloseNote { mint: None })
+        ));
+        assert!(matches!(
+            EscrowInstruction::unpack(&idl_data(IdlInstruction::CloseNote { mint: pubkey }, &[])),
+            Ok(EscrowInstruction::CloseNote { mint: Some(mint) }) if mint == pubkey
+        ));
+        assert!(matches!(
+            EscrowInstruction::unpack(&idl_data(IdlInstruction::SetUsdTerms { usd_amount: 7, max_divergence_bps: 0 }, &[])),
+            Ok(EscrowInstruction::SetUsdTerms { usd_amount: 7, max_divergence_bps: None })
+        ));
+        assert!(matches!(
+            EscrowInstruction::unpack(&idl_data(IdlInstruction::SetUsdTerms { usd_amount: 7, max_divergence_bps: 50 }, &[])),
+            Ok(EscrowInstruction::SetUsdTerms { usd_amount: 7, max_divergence_bps: Some(50) })
+        ));
+        assert!(matches!(
+            EscrowInstruction::unpack(&idl_data(IdlInstruction::InitializeToken { amount: 7, reference: [0; 16] }, &[])),
+            Ok(EscrowInstruction::InitializeToken { amount: 7, reference: Some(reference) }) if reference == [0; 16]
+        ));
+        assert!(matches!(
+            EscrowInstruction::unpack(&idl_data(IdlInstruction::Claim { preimage: [0; 32] }, &[])),
+            Ok(EscrowInstruction::Claim { preimage: Some(preimage) }) if preimage == [0; 32]
+        ));
+
+        // 3. the IDL's variants are numbered by tag, the builders' data being what the IDL describes
+        assert_eq!(idl_data(IdlInstruction::SetStakeSettlement { vote: pubkey }, &[]), crate::instruction::set_stake_settlement(&pubkey, &pubkey, &pubkey, &pubkey).data);
+        assert_eq!(idl_data(IdlInstruction::Touch, &[]), crate::instruction::touch(&pubkey).data);
+        assert_eq!(idl_data(IdlInstruction::Initialize { amount: 7 }, &[]), EscrowInstruction::initialize(&pubkey, &pubkey, 7).data);
+        assert_eq!(
+            idl_data(IdlInstruction::CloseNote { mint: pubkey }, &[]),
+            crate::instruction::close_token_note(&pubkey, &pubkey, &pubkey, &pubkey).data
+        );
+    }
+
+    #[tokio::test]
+    async fn test_complete_and_close_legacy_escrows() -> Result<(), Box<dyn std::error::Error>> {
+        let mut program_test: ProgramTest = ProgramTest::new(
+            "escrow",
+            crate::ID,
+            processor!(super::entrypoint::process_instruction)
+        );
+
+        // 1. three escrows of the original program: 2 bytes, their amount being their lamports above rent.
+        // dave's holds less than the rich layout's rent, which legacy escrows never paid.
+        let payer: Keypair = Keypair::new();
+        let bob_pkey: Pubkey = Pubkey::new_unique();
+        let carol_pkey: Pubkey = Pubkey::new_unique();
+        let dave_pkey: Pubkey = Pubkey::new_unique();
+        let amount: u64 = 1_000_000_000;
+        let small_amount: u64 = 1_000;
+        let legacy_rent: u64 = crate::rent::escrow_in(&Rent::default(), LayoutVersion::Legacy);
+
+        program_test.add_account(payer.pubkey(), Account { lamports: 10_000_000_000, data: Vec::new(), owner: system_program::ID, executable: false, rent_epoch: 0 });
+        program_test.add_account(dave_pkey, Account { lamports: 1_000_000_000, data: Vec::new(), owner: system_program::ID, executable: false, rent_epoch: 0 });
+
+        for (recipient_pkey, amount) in [(&bob_pkey, amount), (&carol_pkey, amount), (&dave_pkey, small_amount)] {
+            let (escrow_pda, bump) = derive_escrow_pda(&payer.pubkey(), recipient_pkey);
+            program_test.add_account(escrow_pda, Account { lamports: legacy_rent + amount, data: vec![1, bump], owner: crate::ID, executable: false, rent_epoch: 0 });
+        }
+
+        let (banks_client, fee_payer, latest_blockhash) = program_test.start().await;
+
+        // 2. Complete pays bob & dave their amounts, Close refunds carol's escrow to the payer
+        let instructions: [Instruction; 3] = [
+            EscrowInstruction::complete(&payer.pubkey(), &bob_pkey),
+            EscrowInstruction::close(&payer.pubkey(), &carol_pkey),
+            EscrowInstruction::complete(&payer.pubkey(), &dave_pkey)
+        ];
+        let tx: Transaction = Transaction::new_signed_with_payer(&instructions, Some(&fee_payer.pubkey()), &[&fee_payer, &payer], latest_blockhash);
+        banks_client.process_transaction(tx).await?;
+
+        assert_eq!(banks_client.get_balance(bob_pkey).await?, amount);
+        assert_eq!(banks_client.get_balance(dave_pkey).await?, 1_000_000_000 + small_amount);
+        assert_eq!(banks_client.get_balance(payer.pubkey()).await?, 10_000_000_000 + 3 * legacy_rent + amount);
+
+        for recipient_pkey in [&bob_pkey, &carol_pkey, &dave_pkey] {
+            assert!(banks_client.get_account(derive_escrow_pda(&payer.pubkey(), recipient_pkey).0).await?.is_none());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_account_lists_parse_in_builder_order() {
+        let payer_pkey: Pubkey = Pubkey::new_unique();
+        let recipient_pkey: Pubkey = Pubkey::new_unique();
+        let ix: Instruction = crate::instruction::complete(&payer_pkey, &recipient_pkey);
+
+        let owner: Pubkey = Pubkey::default();
+        let mut lamports: Vec<u64> = vec![0; ix.accounts.len()];
+        let mut data: Vec<Vec<u8>> = vec![Vec::new(); ix.accounts.len()];
+        let account_infos: Vec<AccountInfo> = ix.accounts.iter()
+            .zip(lamports.iter_mut())
+            .zip(data.iter_mut())
+            .map(|((meta, lamports), data)| AccountInfo::new(&meta.pubkey, meta.is_signer, meta.is_writable, lamports, data, &owner, false, 0))
+            .collect();
+
+        let accounts: crate::accounts::CompleteAccounts<&AccountInfo> = crate::accounts::CompleteAccounts::parse(&mut account_infos.iter()).unwrap();
+        assert_eq!(accounts.payer.key, &payer_pkey);
+        assert!(accounts.payer.is_signer && accounts.recipient.is_writable);
+        assert_eq!(accounts.recipient.key, &recipient_pkey);
+        assert_eq!(accounts.escrow.key, &derive_escrow_pda(&payer_pkey, &recipient_pkey).0);
+
+        // missing accounts fail instead of shifting the others
+        assert_eq!(
+            crate::accounts::CompleteAccounts::parse(&mut account_infos[..3].iter()).err(),
+            Some(ProgramError::NotEnoughAccountKeys)
+        );
+    }
+
+    #[test]
+    fn test_token_escrow_pda_is_derived_per_mint() {
+        let (payer_pkey, recipient_pkey): (Pubkey, Pubkey) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (usdc_mint, usdt_mint): (Pubkey, Pubkey) = (Pubkey::new_unique(), Pubkey::new_unique());
+
+        let derive = |mint_pkey: &Pubkey| -> (Pubkey, u8) {
+            let (seed1, seed2, seed3, seed4) = crate::get_token_escrow_seeds(&payer_pkey, &recipient_pkey, mint_pkey);
+            Pubkey::find_program_address(&[seed1, seed2, seed3, seed4], &crate::ID)
+        };
+        let (usdc_escrow_pda, usdc_bump) = derive(&usdc_mint);
+        let (usdt_escrow_pda, _bump) = derive(&usdt_mint);
+
+        // the same parties hold one escrow per mint, next to their SOL escrow
+        assert_ne!(usdc_escrow_pda, usdt_escrow_pda);
+        assert_ne!(usdc_escrow_pda, derive_escrow_pda(&payer_pkey, &recipient_pkey).0);
+
+        assert!(crate::check_provided_token_pda(&payer_pkey, &recipient_pkey, &usdc_mint, &usdc_escrow_pda, usdc_bump).is_ok());
+        assert!(crate::check_provided_token_pda(&payer_pkey, &recipient_pkey, &usdt_mint, &usdc_escrow_pda, usdc_bump).is_err());
+        assert!(crate::check_provided_token_pda(&recipient_pkey, &payer_pkey, &usdc_mint, &usdc_escrow_pda, usdc_bump).is_err());
+    }
+
+    #[test]
+    fn test_oracle_usd_to_lamports_and_bounds() {
+        // $150.00000000 per SOL
+        let oracle_price: OraclePrice = OraclePrice { price: 15_000_000_000, exponent: -8, publish_time: 0 };
+
+        // $75 => 0.5 SOL
+        let lamports: u64 = oracle_price.usd_to_lamports(75_000_000).unwrap();
+        assert_eq!(lamports, 500_000_000);
+
+        assert!(SettlementBounds { min_amount: 490_000_000, max_amount: 510_000_000 }.check(lamports).is_ok());
+        assert!(SettlementBounds { min_amount: 0, max_amount: 499_999_999 }.check(lamports).is_err());
+        assert!(SettlementBounds { min_amount: 500_000_001, max_amount: u64::MAX }.check(lamports).is_err());
+
+        // $151.5 with a different exponent => 1% (100 bps) apart
+        let secondary_price: OraclePrice = OraclePrice { price: 1_515_000, exponent: -4, publish_time: 0 };
+        assert_eq!(oracle_price.divergence_bps(&secondary_price).unwrap(), 100);
+    }
+
+    #[test]
+    fn test_oracle_accepts_only_fully_verified_sol_usd_updates() {
+        let now: i64 = 1_700_000_000;
+        let key: Pubkey = Pubkey::new_unique();
+
+        // discriminator, write authority, verification level, then the message: feed id, price, conf, exponent, publish time, ...
+        let price_update = |verification_level: &[u8], feed_id: [u8; 32]| -> Vec<u8> {
+            let mut data: Vec<u8> = vec![34, 241, 35, 99, 157, 126, 244, 205];
+            data.extend_from_slice(&[0; 32]);
+            data.extend_from_slice(verification_level);
+            data.extend_from_slice(&feed_id);
+            data.extend_from_slice(&15_000_000_000i64.to_le_bytes());
+            data.extend_from_slice(&0u64.to_le_bytes());
+            data.extend_from_slice(&(-8i32).to_le_bytes());
+            data.extend_from_slice(&now.to_le_bytes());
+            data.resize(data.len() + 32, 0);
+            data
+        };
+        let load = |mut data: Vec<u8>| -> Result<OraclePrice, ProgramError> {
+            let mut lamports: u64 = 0;
+            let account: AccountInfo = AccountInfo::new(&key, false, false, &mut lamports, &mut data, &PYTH_RECEIVER_PROGRAM_ID, false, 0);
+            OraclePrice::load_sol_usd(&account, now)
+        };
+
+        assert_eq!(load(price_update(&[1], SOL_USD_FEED_ID)).unwrap().price, 15_000_000_000);
+
+        // another feed priced in the same units, and a partially verified update of the right one
+        assert_eq!(load(price_update(&[1], [7; 32])).err(), Some(ProgramError::InvalidAccountData));
+        assert_eq!(load(price_update(&[0, 13], SOL_USD_FEED_ID)).err(), Some(ProgramError::InvalidAccountData));
+    }
+
+    #[tokio::test]
+    async fn test_usd_settlement_cross_checks_secondary_price() -> Result<(), Box<dyn std::error::Error>> {
+        let mut context = test_utils::program_test().start_with_context().await;
+
+        let payer: Keypair = test_utils::funded_keypair(&mut context, 10_000_000_000).await?;
+        let recipient_pkey: Pubkey = Keypair::new().pubkey();
+        let (escrow_pda, _bump) = derive_escrow_pda(&payer.pubkey(), &recipient_pkey);
+        let (primary_feed, secondary_feed): (Pubkey, Pubkey) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let now: i64 = context.banks_client.get_sysvar::<Clock>().await?.unix_timestamp;
+
+        // fully verified SOL/USD updates, priced with 8 decimals
+        let price_update = |price: i64| -> solana_sdk::account::AccountSharedData {
+            let mut data: Vec<u8> = vec![34, 241, 35, 99, 157, 126, 244, 205];
+            data.extend_from_slice(&[0; 32]);
+            data.push(1);
+            data.extend_from_slice(&SOL_USD_FEED_ID);
+            data.extend_from_slice(&price.to_le_bytes());
+            data.extend_from_slice(&0u64.to_le_bytes());
+            data.extend_from_slice(&(-8i32).to_le_bytes());
+            data.extend_from_slice(&now.to_le_bytes());
+            data.resize(data.len() + 32, 0);
+
+            Account { lamports: 1_000_000_000, data, owner: PYTH_RECEIVER_PROGRAM_ID, executable: false, rent_epoch: 0 }.into()
+        };
+        let set_usd_terms = |secondary_feed: &Pubkey| -> Instruction {
+            let mut data: Vec<u8> = vec![9];
+            data.extend_from_slice(&75_000_000u64.to_le_bytes());
+            data.extend_from_slice(&200u16.to_le_bytes());
+
+            Instruction::new_with_bytes(
+                crate::ID,
+                &data,
+                vec![
+                    AccountMeta::new_readonly(payer.pubkey(), true),
+                    AccountMeta::new_readonly(recipient_pkey, false),
+                    AccountMeta::new(escrow_pda, false),
+                    AccountMeta::new_readonly(primary_feed, false),
+                    AccountMeta::new_readonly(*secondary_feed, false),
+                    AccountMeta::new_readonly(derive_config_pda(), false)
+                ]
+            )
+        };
+
+        let mut complete_usd_data: Vec<u8> = vec![10];
+        complete_usd_data.extend_from_slice(&0u64.to_le_bytes());
+        complete_usd_data.extend_from_slice(&u64::MAX.to_le_bytes());
+        let complete_usd_ix: Instruction = Instruction::new_with_bytes(
+            crate::ID,
+            &complete_usd_data,
+            vec![
+                AccountMeta::new(payer.pubkey(), true),
+                AccountMeta::new(recipient_pkey, false),
+                AccountMeta::new(escrow_pda, false),
+                AccountMeta::new_readonly(derive_config_pda(), false),
+                AccountMeta::new_readonly(primary_feed, false),
+                AccountMeta::new_readonly(secondary_feed, false)
+            ]
+        );
+
+        // 1. $150 and $151.5 per SOL, 1% apart
+        context.set_account(&primary_feed, &price_update(15_000_000_000));
+        context.set_account(&secondary_feed, &price_update(15_150_000_000));
+        test_utils::send(&mut context, &[crate::instruction::initialize(&payer.pubkey(), &recipient_pkey, 1_000_000_000)], &[&payer]).await?;
+
+        // a source can't cross-check itself
+        assert_eq!(instruction_error(&context.banks_client, &[set_usd_terms(&primary_feed)], &[&payer]).await?, InstructionError::InvalidArgument);
+
+        // 2. $75 within 2% of both sources
+        test_utils::send(&mut context, &[set_usd_terms(&secondary_feed)], &[&payer]).await?;
+
+        let escrow: EscrowAccount = EscrowAccount::unpack(&context.banks_client.get_account(escrow_pda).await?.ok_or("escrow wasn't created")?.data)?;
+        assert_eq!((escrow.secondary_price_feed, escrow.max_divergence_bps), (secondary_feed, 200));
+
+        // 3. once the secondary source moves 10% away, settlement is refused
+        context.set_account(&secondary_feed, &price_update(16_500_000_000));
+        assert_eq!(instruction_error(&context.banks_client, &[complete_usd_ix.clone()], &[&payer]).await?, InstructionError::InvalidAccountData);
+
+        // 4. back within bounds, the recipient is paid $75 at the primary price
+        context.set_account(&secondary_feed, &price_update(15_150_000_000));
+        test_utils::send(&mut context, &[complete_usd_ix], &[&payer]).await?;
+
+        assert_eq!(context.banks_client.get_balance(recipient_pkey).await?, 500_000_000);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_oracle_peg_deviation() {
+        // $0.97000000 => 3 cents below the peg, in bps of the lower price
+        let depegged: OraclePrice = OraclePrice { price: 97_000_000, exponent: -8, publish_time: 0 };
+        assert_eq!(depegged.peg_deviation_bps().unwrap(), 309);
+
+        let pegged: OraclePrice = OraclePrice { price: 10_001, exponent: -4, publish_time: 0 };
+        assert_eq!(pegged.peg_deviation_bps().unwrap(), 1);
+
+        let broken: OraclePrice = OraclePrice { price: 0, exponent: -8, publish_time: 0 };
+        assert_eq!(broken.peg_deviation_bps().unwrap(), u64::MAX);
+    }
+
+    #[tokio::test]
+    async fn test_depeg_guard_freezes_settlement_until_overridden() -> Result<(), Box<dyn std::error::Error>> {
+        let mut context = test_utils::program_test().start_with_context().await;
+
+        let payer: Keypair = test_utils::funded_keypair(&mut context, 10_000_000_000).await?;
+        let recipient: Keypair = Keypair::new();
+        let (payer_pkey, recipient_pkey): (Pubkey, Pubkey) = (payer.pubkey(), recipient.pubkey());
+        let price_feed: Pubkey = Pubkey::new_unique();
+        let now: i64 = context.banks_client.get_sysvar::<Clock>().await?.unix_timestamp;
+
+        // fully verified stablecoin/USD updates, priced with 8 decimals
+        let price_update = |price: i64| -> solana_sdk::account::AccountSharedData {
+            let mut data: Vec<u8> = vec![34, 241, 35, 99, 157, 126, 244, 205];
+            data.extend_from_slice(&[0; 32]);
+            data.push(1);
+            data.extend_from_slice(&[9; 32]);
+            data.extend_from_slice(&price.to_le_bytes());
+            data.extend_from_slice(&0u64.to_le_bytes());
+            data.extend_from_slice(&(-8i32).to_le_bytes());
+            data.extend_from_slice(&now.to_le_bytes());
+            data.resize(data.len() + 32, 0);
+
+            Account { lamports: 1_000_000_000, data, owner: PYTH_RECEIVER_PROGRAM_ID, executable: false, rent_epoch: 0 }.into()
+        };
+
+        // 1. a token escrow of 400_000 stablecoins
+        let mint: Keypair = Keypair::new();
+        let payer_token: Keypair = Keypair::new();
+        let recipient_token: Keypair = Keypair::new();
+        let rent: Rent = context.banks_client.get_rent().await?;
+
+        let mut setup_ixs: Vec<Instruction> = vec![
+            system_instruction::create_account(&payer_pkey, &mint.pubkey(), rent.minimum_balance(spl_token::state::Mint::LEN), spl_token::state::Mint::LEN as u64, &spl_token::ID),
+            spl_token::instruction::initialize_mint2(&spl_token::ID, &mint.pubkey(), &payer_pkey, None, 6)?
+        ];
+        for (token_account, owner) in [(&payer_token, &payer_pkey), (&recipient_token, &recipient_pkey)] {
+            setup_ixs.push(system_instruction::create_account(
+                &payer_pkey,
+                &token_account.pubkey(),
+                rent.minimum_balance(spl_token::state::Account::LEN),
+                spl_token::state::Account::LEN as u64,
+                &spl_token::ID
+            ));
+            setup_ixs.push(spl_token::instruction::initialize_account3(&spl_token::ID, &token_account.pubkey(), &mint.pubkey(), owner)?);
+        }
+        setup_ixs.push(spl_token::instruction::mint_to(&spl_token::ID, &mint.pubkey(), &payer_token.pubkey(), &payer_pkey, &[], 1_000_000)?);
+        test_utils::send(&mut context, &setup_ixs, &[&payer, &mint, &payer_token, &recipient_token]).await?;
+
+        let (escrow_pda, _bump) = Pubkey::find_program_address(
+            &[b"escrow", payer_pkey.as_ref(), recipient_pkey.as_ref(), mint.pubkey().as_ref()],
+            &crate::ID
+        );
+        let (vault_pda, _bump) = Pubkey::find_program_address(&[b"vault", escrow_pda.as_ref()], &crate::ID);
+        let (blocked_mint_pda, _bump) = Pubkey::find_program_address(&[b"blocked_mint", mint.pubkey().as_ref()], &crate::ID);
+
+        let mut init_ix_payload: Vec<u8> = vec![14];
+        init_ix_payload.extend_from_slice(&400_000u64.to_le_bytes());
+        let initialize_token_escrow_ix: Instruction = Instruction::new_with_bytes(
+            crate::ID,
+            &init_ix_payload,
+            vec![
+                AccountMeta::new(payer_pkey, true),
+                AccountMeta::new_readonly(recipient_pkey, false),
+                AccountMeta::new(escrow_pda, false),
+                AccountMeta::new_readonly(mint.pubkey(), false),
+                AccountMeta::new(vault_pda, false),
+                AccountMeta::new(payer_token.pubkey(), false),
+                AccountMeta::new_readonly(spl_token::ID, false),
+                AccountMeta::new_readonly(system_program::ID, false),
+                AccountMeta::new(derive_counter_pda(), false),
+                AccountMeta::new_readonly(derive_config_pda(), false),
+                AccountMeta::new_readonly(blocked_mint_pda, false)
+            ]
+        );
+        test_utils::send(&mut context, &[initialize_token_escrow_ix], &[&payer]).await?;
+
+        // 2. guard it at 1% off the peg, while the token trades at $1.00
+        context.set_account(&price_feed, &price_update(100_000_000));
+
+        let mut set_depeg_guard_payload: Vec<u8> = vec![28];
+        set_depeg_guard_payload.extend_from_slice(&100u16.to_le_bytes());
+        let set_depeg_guard_ix: Instruction = Instruction::new_with_bytes(
+            crate::ID,
+            &set_depeg_guard_payload,
+            vec![
+                AccountMeta::new_readonly(payer_pkey, true),
+                AccountMeta::new_readonly(recipient_pkey, false),
+                AccountMeta::new(escrow_pda, false),
+                AccountMeta::new_readonly(price_feed, false),
+                AccountMeta::new_readonly(derive_config_pda(), false)
+            ]
+        );
+        test_utils::send(&mut context, &[set_depeg_guard_ix.clone()], &[&payer]).await?;
+
+        let escrow: EscrowAccount = EscrowAccount::unpack(&context.banks_client.get_account(escrow_pda).await?.ok_or("escrow is gone")?.data)?;
+        assert_eq!((escrow.depeg_price_feed, escrow.max_depeg_bps, escrow.depeg_override), (price_feed, 100, false));
+
+        // the guard is set once
+        assert_eq!(instruction_error(&context.banks_client, &[set_depeg_guard_ix], &[&payer]).await?, InstructionError::InvalidArgument);
+
+        // 3. at $0.97 (309 bps off) settlement is frozen
+        context.set_account(&price_feed, &price_update(97_000_000));
+
+        let complete_ix = |with_price_feed: bool| -> Instruction {
+            let mut accounts: Vec<AccountMeta> = vec![
+                AccountMeta::new(payer_pkey, true),
+                AccountMeta::new_readonly(recipient_pkey, false),
+                AccountMeta::new(escrow_pda, false),
+                AccountMeta::new_readonly(derive_config_pda(), false),
+                AccountMeta::new_readonly(mint.pubkey(), false),
+                AccountMeta::new(vault_pda, false),
+                AccountMeta::new(recipient_token.pubkey(), false),
+                AccountMeta::new_readonly(spl_token::ID, false)
+            ];
+            if with_price_feed {
+                accounts.push(AccountMeta::new_readonly(price_feed, false));
+            }
+            Instruction::new_with_bytes(crate::ID, &[1], accounts)
+        };
+        assert_eq!(
+            instruction_error(&context.banks_client, &[complete_ix(true)], &[&payer]).await?,
+            InstructionError::Custom(EscrowError::Depegged as u32)
+        );
+
+        // 4. lifting the freeze takes the arbiter or both parties, not the payer alone
+        let override_depeg_freeze_ix = |recipient_signs: bool| Instruction::new_with_bytes(
+            crate::ID,
+            &[29],
+            vec![
+                AccountMeta::new_readonly(payer_pkey, true),
+                AccountMeta::new_readonly(recipient_pkey, recipient_signs),
+                AccountMeta::new(escrow_pda, false)
+            ]
+        );
+        assert_eq!(
+            instruction_error(&context.banks_client, &[override_depeg_freeze_ix(false)], &[&payer]).await?,
+            InstructionError::MissingRequiredSignature
+        );
+        test_utils::send(&mut context, &[override_depeg_freeze_ix(true)], &[&payer, &recipient]).await?;
+
+        let escrow: EscrowAccount = EscrowAccount::unpack(&context.banks_client.get_account(escrow_pda).await?.ok_or("escrow is gone")?.data)?;
+        assert!(escrow.depeg_override);
+
+        // 5. once overridden the oracle is no longer consulted, and the recipient is paid despite the depeg
+        test_utils::send(&mut context, &[complete_ix(false)], &[&payer]).await?;
+
+        let recipient_token_account = context.banks_client.get_account(recipient_token.pubkey()).await?.ok_or("recipient token account is gone")?;
+        assert_eq!(spl_token::state::Account::unpack(&recipient_token_account.data)?.amount, 400_000);
+        assert!(context.banks_client.get_account(escrow_pda).await?.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_claim_request_round_trip() {
+        let with_secret: ClaimRequest = ClaimRequest {
+            escrow: Pubkey::new_unique(),
+            secret: Some([7; 32]),
+            cluster: Cluster::Devnet
+        };
+        let without_secret: ClaimRequest = ClaimRequest { secret: None, cluster: Cluster::Mainnet, ..with_secret.clone() };
+
+        assert_eq!(ClaimRequest::from_uri(&with_secret.to_uri()), Ok(with_secret));
+        assert_eq!(ClaimRequest::decode(&without_secret.encode()), Ok(without_secret));
+        assert!(ClaimRequest::decode("AQ").is_err());
+        assert!(ClaimRequest::from_uri("https://example.com").is_err());
+    }
+
+    #[test]
+    fn test_priority_fee_policy() {
+        let policy: PriorityFeePolicy = PriorityFeePolicy { percentile: 50, min_micro_lamports: 10, max_micro_lamports: 5_000 };
+
+        assert_eq!(policy.compute_unit_price(&[]), 10);
+        assert_eq!(policy.compute_unit_price(&[0, 300, 100, 200, 400]), 200);
+        assert_eq!(policy.compute_unit_price(&[1_000_000; 3]), 5_000);
+
+        // only write-locked accounts compete for priority
+        let payer_pkey: Pubkey = Pubkey::new_unique();
+        let recipient_pkey: Pubkey = Pubkey::new_unique();
+        let ix: Instruction = crate::instruction::complete(&payer_pkey, &recipient_pkey);
+        let fee_accounts: Vec<Pubkey> = priority_fee::fee_accounts(&[ix.clone(), ix.clone()]);
+        assert_eq!(fee_accounts.len(), 3);
+        assert!(!fee_accounts.contains(&derive_config_pda()));
+
+        let prioritized: Vec<Instruction> = priority_fee::with_priority_fee(&[ix], Some(50_000), 200);
+        assert_eq!(prioritized.len(), 3);
+        assert_eq!(prioritized[0].data, [&[2u8][..], &50_000u32.to_le_bytes()].concat());
+        assert_eq!(prioritized[1].data, [&[3u8][..], &200u64.to_le_bytes()].concat());
+
+        // simulated consumption plus the margin, up to the transaction maximum
+        assert_eq!(priority_fee::compute_unit_limit(20_000), 22_000);
+        assert_eq!(priority_fee::compute_unit_limit(1_300_000), priority_fee::MAX_COMPUTE_UNIT_LIMIT);
+    }
+
+    #[test]
+    fn test_explorer_tx_url() {
+        assert_eq!(Cluster::Mainnet.explorer_tx_url("sig"), "https://explorer.solana.com/tx/sig");
+        assert_eq!(Cluster::Devnet.explorer_tx_url("sig"), "https://explorer.solana.com/tx/sig?cluster=devnet");
+        assert!(Cluster::Localnet.explorer_tx_url("sig").contains("customUrl=http%3A%2F%2F127.0.0.1%3A8899"));
+    }
+
+    #[test]
+    fn test_vested_amount() {
+        let mut escrow: EscrowAccount = EscrowAccount::new(255, 0, 0, Pubkey::new_unique(), Pubkey::new_unique(), 1_000);
+        (escrow.vesting_start, escrow.vesting_end) = (100, 400);
+
+        assert!(escrow.is_vesting());
+        assert_eq!(escrow.vested(50), 0);
+        assert_eq!(escrow.vested(100), 0);
+        assert_eq!(escrow.vested(200), 333);
+        assert_eq!(escrow.vested(399), 996);
+        assert_eq!(escrow.vested(400), 1_000);
+        assert_eq!(escrow.vested(i64::MAX), 1_000);
+    }
+
+    #[test]
+    fn test_solana_pay_transaction_request() {
+        let link: &str = "https://shop.example/escrow?order=42";
+        let url: String = solana_pay::transaction_request_url(link);
+
+        assert!(!url["solana:".len()..].contains('?'));
+        assert_eq!(solana_pay::parse_transaction_request_url(&url), Ok(link.to_string()));
+
+        // the unsigned transaction is one empty signature followed by the message
+        let payer_pkey: Pubkey = Pubkey::new_unique();
+        let ix: Instruction = crate::instruction::initialize(&payer_pkey, &Pubkey::new_unique(), 101101101);
+        let message: Message = Message::new(&[ix], Some(&payer_pkey));
+        let transaction: Vec<u8> = base64::engine::general_purpose::STANDARD
+            .decode(solana_pay::encode_unsigned_transaction(&message))
+            .unwrap();
+
+        assert_eq!(transaction[0], 1);
+        assert!(transaction[1..65].iter().all(|byte| *byte == 0));
+        assert_eq!(transaction[65..], message.serialize());
+    }
+
+    #[tokio::test]
+    async fn test_adversarial_scenarios() -> Result<(), Box<dyn std::error::Error>> {
+        let mut context = test_utils::program_test().start_with_context().await;
+
+        // 1. the payer is distinct from the fee payer, so demoting it to a non-signer is observable
+        let payer: Keypair = test_utils::funded_keypair(&mut context, 10_000_000_000).await?;
+        let payer_pkey: Pubkey = payer.pubkey();
+        let recipient_pkey: Pubkey = Keypair::new().pubkey();
+
+        let (escrow_pda, _bump) = derive_escrow_pda(&payer_pkey, &recipient_pkey);
+
+        // 2. initialize
+        let mut init_ix_payload: Vec<u8> = Vec::with_capacity(9);
+        init_ix_payload.push(0);
+        init_ix_payload.extend_from_slice(&u64::to_le_bytes(101101101));
+
+        let initialize_escrow_ix: Instruction = Instruction::new_with_bytes(
+            crate::ID,
+            &init_ix_payload,
+            vec![
+                AccountMeta::new(payer_pkey, true),
+                AccountMeta::new_readonly(recipient_pkey, false),
+                AccountMeta::new(escrow_pda, false),
+                AccountMeta::new_readonly(system_program::ID, false),
+                AccountMeta::new(derive_counter_pda(), false),
+                AccountMeta::new_readonly(derive_config_pda(), false)
+            ]
+        );
+        test_utils::assert_attacks_rejected(
+            &mut context,
+            &initialize_escrow_ix,
+            &[&payer],
+            &[
+                Attack::WrongSigner,
+                Attack::WrongPda { account_index: 2 },
+                Attack::Replay,
+                Attack::DrainSigner { account_index: 0 }
+            ]
+        ).await?;
+        test_utils::send(&mut context, &[initialize_escrow_ix], &[&payer]).await?;
+
+        // 3. complete & close share the same account list
+        let escrow_accounts: Vec<AccountMeta> = vec![
+            AccountMeta::new(payer_pkey, true),
+            AccountMeta::new(recipient_pkey, false),
+            AccountMeta::new(escrow_pda, false),
+            AccountMeta::new_readonly(derive_config_pda(), false)
+        ];
+        let settlement_attacks: &[Attack] = &[
+            Attack::WrongSigner,
+            Attack::WrongPda { account_index: 1 },
+            Attack::WrongPda { account_index: 2 },
+            Attack::WrongOwner { account_index: 2 },
+            Attack::WrongPda { account_index: 3 },
+            Attack::Replay
+        ];
+
+        for tag in [1u8, 2] {
+            let ix: Instruction = Instruction::new_with_bytes(crate::ID, &[tag], escrow_accounts.clone());
+            test_utils::assert_attacks_rejected(&mut context, &ix, &[&payer], settlement_attacks).await?;
+        }
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_differential_against_reference_model() -> Result<(), Box<dyn std::error::Error>> {
+        for seed in [1, 0xdead_beef, 20250601] {
+            test_utils::run_differential(seed, 24).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn init_escrow(
+        banks_client: &BanksClient,
+        payer: &Keypair,
+        payer_pkey: &Pubkey,
+        recipient_pkey: &Pubkey,
+        escrow_pda: &Pubkey,
+        latest_blockhash: Hash
+    ) -> Result<(), Box<dyn std::error::Error>> {        
+        // craft init ix & init tx
+        let mut init_ix_payload: Vec<u8> = Vec::with_capacity(9);
+        init_ix_payload.push(0);
+        init_ix_payload.extend_from_slice(&u64::to_le_bytes(101101101));
+
+        let initialize_escrow_ix: Instruction = Instruction::new_with_bytes(
+            crate::ID, 
+            &init_ix_payload, 
+            vec![
+                AccountMeta::new(*payer_pkey, true),
+                AccountMeta::new_readonly(*recipient_pkey, false),
+                AccountMeta::new(*escrow_pda, false),
+                AccountMeta::new_readonly(system_program::ID, false),
+                AccountMeta::new(derive_counter_pda(), false),
+                AccountMeta::new_readonly(derive_config_pda(), false)
+            ]
+        );
+        let message: Message = Message::new(&[initialize_escrow_ix], Some(&payer_pkey));
+        let mut initialize_escrow_tx: Transaction = Transaction::new_unsigned(message);
+
+        // sign init escrow tx & send it
+        initialize_escrow_tx.sign(&[&payer], latest_blockhash);
+        banks_client.process_transaction(initialize_escrow_tx).await?;
+
+        Ok(())
+    }
+    
+    /// Starts the program with a funded payer, distinct from the fee payer so it can be left out of the signers,
+    /// and its escrow to a fresh recipient.
+    async fn start_with_escrow() -> Result<(BanksClient, Keypair, Keypair, Pubkey), Box<dyn std::error::Error>> {
+        let mut program_test: ProgramTest = ProgramTest::new(
+            "escrow",
+            crate::ID,
+            processor!(super::entrypoint::process_instruction)
+        );
+
+        let payer: Keypair = Keypair::new();
+        let recipient_pkey: Pubkey = Pubkey::new_unique();
+        program_test.add_account(payer.pubkey(), Account { lamports: 10_000_000_000, data: Vec::new(), owner: system_program::ID, executable: false, rent_epoch: 0 });
+
+        let (banks_client, fee_payer, _latest_blockhash) = program_test.start().await;
+        send_signed(&banks_client, &[EscrowInstruction::initialize(&payer.pubkey(), &recipient_pkey, 101101101)], &[&fee_payer, &payer]).await?;
+
+        Ok((banks_client, fee_payer, payer, recipient_pkey))
+    }
+
+    /// Sends `instructions` signed by `signers`, the first one paying the fees.
+    async fn send_signed(banks_client: &BanksClient, instructions: &[Instruction], signers: &[&Keypair]) -> Result<(), Box<dyn std::error::Error>> {
+        let latest_blockhash: Hash = banks_client.get_latest_blockhash().await?;
+        let tx: Transaction = Transaction::new_signed_with_payer(instructions, Some(&signers[0].pubkey()), signers, latest_blockhash);
+        banks_client.process_transaction(tx).await?;
+
+        Ok(())
+    }
+
+    /// Error the first of `instructions` fails with, failing the test if the transaction goes through or fails otherwise.
+    async fn instruction_error(
+        banks_client: &BanksClient,
+        instructions: &[Instruction],
+        signers: &[&Keypair]
+    ) -> Result<InstructionError, Box<dyn std::error::Error>> {
+        let latest_blockhash: Hash = banks_client.get_latest_blockhash().await?;
+        let tx: Transaction = Transaction::new_signed_with_payer(instructions, Some(&signers[0].pubkey()), signers, latest_blockhash);
+
+        match banks_client.process_transaction(tx).await.err().ok_or("transaction succeeded")?.unwrap() {
+            TransactionError::InstructionError(0, error) => Ok(error),
+            error => Err(format!("expected the first instruction to fail, got {:?}", error).into())
+        }
+    }
+
+    fn derive_config_pda() -> Pubkey {
+        Pubkey::find_program_address(&[b"config"], &crate::ID).0
+    }
+
+    fn derive_counter_pda() -> Pubkey {
+        Pubkey::find_program_address(&[b"counter"], &crate::ID).0
+    }
+
     fn derive_escrow_pda(payer_pkey: &Pubkey, recipient_pkey: &Pubkey) -> (Pubkey, u8) {
         Pubkey::find_program_address(
             &[
@@ -0,0 +1,59 @@
+use std::collections::HashMap;
+
+
+/// Pure-Rust reference model of the SOL escrow state machine, used by the differential harness in `test_utils`.
+/// Parties are identified by index, escrows by their `(payer, recipient)` pair, exactly like the PDA seeds.
+pub struct EscrowModel {
+    pub rent_exempt: u64,
+    pub next_id: u64,
+    pub escrows: HashMap<(usize, usize), ModelEscrow>,
+    /// lamports received by each recipient through `Complete`
+    pub received: HashMap<usize, u64>
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ModelEscrow {
+    pub id: u64,
+    pub locked: u64
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum ModelOp {
+    Initialize { payer: usize, recipient: usize, amount: u64 },
+    Complete { payer: usize, recipient: usize },
+    Close { payer: usize, recipient: usize }
+}
+
+impl EscrowModel {
+    pub fn new(rent_exempt: u64) -> Self {
+        Self {
+            rent_exempt,
+            next_id: 0,
+            escrows: HashMap::new(),
+            received: HashMap::new()
+        }
+    }
+
+    /// Applies `op`, returning whether the program is expected to accept it. Rejected ops leave the model untouched.
+    pub fn apply(&mut self, op: ModelOp) -> bool {
+        match op {
+            ModelOp::Initialize { payer, recipient, amount } => {
+                if amount < self.rent_exempt || self.escrows.contains_key(&(payer, recipient)) {
+                    return false;
+                }
+
+                self.escrows.insert((payer, recipient), ModelEscrow { id: self.next_id, locked: amount });
+                self.next_id += 1;
+                true
+            },
+            ModelOp::Complete { payer, recipient } => match self.escrows.remove(&(payer, recipient)) {
+                Some(escrow) => {
+                    *self.received.entry(recipient).or_default() += escrow.locked;
+                    true
+                },
+                None => false
+            },
+            ModelOp::Close { payer, recipient } => self.escrows.remove(&(payer, recipient)).is_some()
+        }
+    }
+}
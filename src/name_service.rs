@@ -0,0 +1,91 @@
+use solana_program::{
+    pubkey,
+    pubkey::Pubkey,
+    hash::hashv,
+    account_info::AccountInfo,
+    program_error::ProgramError
+};
+
+
+/// SPL Name Service program, owner of the Solana Name Service (SNS) name accounts.
+pub const NAME_SERVICE_PROGRAM_ID: Pubkey = pubkey!("namesLPneVptA9Z5rqUDD9tMTWEJwofgaYwp8cawRkX");
+
+/// Parent of every `.sol` domain.
+pub const SOL_TLD_AUTHORITY: Pubkey = pubkey!("58PwtjSDuFHuUkYjH9BYnnQKHfwo9reZhC2zMJv9JJ5x");
+
+const HASH_PREFIX: &str = "SPL Name Service";
+
+
+/// When the owner of the recipient domain of an escrow (tag 66 `Initialize`) is looked up.
+#[cfg_attr(feature = "idl", derive(shank::ShankType))]
+#[cfg_attr(test, derive(borsh::BorshSerialize), borsh(use_discriminant = true))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RecipientResolution {
+    /// at `Initialize`, the owner then being the escrow's assignee whoever owns the domain later
+    Pinned = 0,
+    /// at settlement, paying whoever owns the domain by then
+    AtSettlement = 1
+}
+
+impl TryFrom<u8> for RecipientResolution {
+    type Error = ProgramError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        Ok(match value {
+            0 => Self::Pinned,
+            1 => Self::AtSettlement,
+            _ => return Err(ProgramError::InvalidInstructionData)
+        })
+    }
+}
+
+
+/// The header of an SNS name account, which the domain's data follows.
+pub struct NameRecord {
+    pub parent_name: Pubkey,
+    pub owner: Pubkey,
+    pub class: Pubkey
+}
+
+impl NameRecord {
+    /// parent_name (32) + owner (32) + class (32)
+    pub const HEADER_LEN: usize = 96;
+
+    /// Reads a `.sol` domain's name account, rejecting other names (subdomains, reverse lookups...) and unowned ones.
+    pub fn load(domain_account: &AccountInfo) -> Result<Self, ProgramError> {
+        if domain_account.owner != &NAME_SERVICE_PROGRAM_ID {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        let record: Self = Self::unpack(&domain_account.data.borrow())?;
+
+        if record.parent_name != SOL_TLD_AUTHORITY || record.owner == Pubkey::default() {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        Ok(record)
+    }
+
+    fn unpack(src: &[u8]) -> Result<Self, ProgramError> {
+        let read_pubkey = |offset: usize| -> Result<Pubkey, ProgramError> {
+            Ok(Pubkey::new_from_array(src.get(offset..offset + 32).and_then(|slice| slice.try_into().ok()).ok_or(ProgramError::InvalidAccountData)?))
+        };
+
+        Ok(Self {
+            parent_name: read_pubkey(0)?,
+            owner: read_pubkey(32)?,
+            class: read_pubkey(64)?
+        })
+    }
+}
+
+/// Name account of the `.sol` domain `name` (e.g. `"alice"` for alice.sol), the recipient to escrow to.
+pub fn domain_address(name: &str) -> Pubkey {
+    let name: &str = name.strip_suffix(".sol").unwrap_or(name);
+    let hashed_name: [u8; 32] = hashv(&[HASH_PREFIX.as_bytes(), name.as_bytes()]).to_bytes();
+
+    Pubkey::find_program_address(
+        &[&hashed_name, Pubkey::default().as_ref(), SOL_TLD_AUTHORITY.as_ref()],
+        &NAME_SERVICE_PROGRAM_ID
+    ).0
+}
@@ -0,0 +1,154 @@
+use solana_program::{
+    pubkey,
+    pubkey::Pubkey,
+    account_info::AccountInfo,
+    entrypoint::ProgramResult,
+    program_error::ProgramError
+};
+
+
+/// Pyth pull-oracle receiver program, owner of `PriceUpdateV2` accounts.
+pub const PYTH_RECEIVER_PROGRAM_ID: Pubkey = pubkey!("rec5EKMGg6MxZYaMdyBfgwp4d5rB9T1VQH5pJv5LtFJ");
+
+/// Prices older than this (in seconds) are rejected.
+pub const MAX_PRICE_AGE: i64 = 60;
+
+/// USD amounts are stored with 6 decimals (same as USDC).
+pub const USD_DECIMALS: u32 = 6;
+
+/// Pyth feed id of SOL/USD, the only feed USD-denominated escrows are priced by.
+pub const SOL_USD_FEED_ID: [u8; 32] = [
+    0xef, 0x0d, 0x8b, 0x6f, 0xda, 0x2c, 0xeb, 0xa4, 0x1d, 0xa1, 0x5d, 0x40, 0x95, 0xd1, 0xda, 0x39,
+    0x2a, 0x0d, 0x2f, 0x8e, 0xd0, 0xc6, 0xc7, 0xbc, 0x0f, 0x4c, 0xfa, 0xc8, 0xc2, 0x80, 0xb5, 0x6d
+];
+
+const LAMPORTS_DECIMALS: u32 = 9;
+const PRICE_UPDATE_V2_DISCRIMINATOR: [u8; 8] = [34, 241, 35, 99, 157, 126, 244, 205];
+
+
+/// SOL/USD price read from an oracle account: `price * 10^exponent` USD per SOL.
+pub struct OraclePrice {
+    pub price: i64,
+    pub exponent: i32,
+    pub publish_time: i64
+}
+
+impl OraclePrice {
+    /// Reads a fully verified Pyth `PriceUpdateV2` account, rejecting it if it's older than `MAX_PRICE_AGE`.
+    pub fn load(price_feed_account: &AccountInfo, now: i64) -> Result<Self, ProgramError> {
+        Ok(Self::load_with_feed_id(price_feed_account, now)?.0)
+    }
+
+    /// Like `load()`, additionally rejecting any price update that isn't for `SOL_USD_FEED_ID`: a price update account
+    /// is just an address, and one written by anyone for another SOL-priced feed would misprice the escrow.
+    pub fn load_sol_usd(price_feed_account: &AccountInfo, now: i64) -> Result<Self, ProgramError> {
+        let (oracle_price, feed_id): (Self, [u8; 32]) = Self::load_with_feed_id(price_feed_account, now)?;
+
+        if feed_id != SOL_USD_FEED_ID {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        Ok(oracle_price)
+    }
+
+    fn load_with_feed_id(price_feed_account: &AccountInfo, now: i64) -> Result<(Self, [u8; 32]), ProgramError> {
+        if price_feed_account.owner != &PYTH_RECEIVER_PROGRAM_ID {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        let data = price_feed_account.data.borrow();
+        let (oracle_price, feed_id): (Self, [u8; 32]) = Self::unpack_price_update_v2(&data)?;
+
+        if now.saturating_sub(oracle_price.publish_time) > MAX_PRICE_AGE || oracle_price.price <= 0 {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        Ok((oracle_price, feed_id))
+    }
+
+    /// Layout: discriminator (8) + write_authority (32) + verification_level (borsh enum, 1 byte for `Full`)
+    /// + feed_id (32) + price (8) + conf (8) + exponent (4) + publish_time (8) + ...
+    fn unpack_price_update_v2(src: &[u8]) -> Result<(Self, [u8; 32]), ProgramError> {
+        if src.get(..8) != Some(&PRICE_UPDATE_V2_DISCRIMINATOR[..]) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        // only `Full` (variant 1) updates are accepted, `Partial { num_signatures: u8 }` ones are checked by too few guardians
+        if src.get(40) != Some(&1) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let message: &[u8] = src.get(41..41 + 92).ok_or(ProgramError::InvalidAccountData)?;
+        let feed_id: [u8; 32] = message[..32].try_into().map_err(|_| ProgramError::InvalidAccountData)?;
+
+        Ok((Self {
+            price: i64::from_le_bytes(message[32..40].try_into().map_err(|_| ProgramError::InvalidAccountData)?),
+            exponent: i32::from_le_bytes(message[48..52].try_into().map_err(|_| ProgramError::InvalidAccountData)?),
+            publish_time: i64::from_le_bytes(message[52..60].try_into().map_err(|_| ProgramError::InvalidAccountData)?)
+        }, feed_id))
+    }
+
+    /// Relative difference between two prices in basis points of the lower one.
+    pub fn divergence_bps(&self, other: &Self) -> Result<u64, ProgramError> {
+        // bring both prices to the smaller exponent before comparing
+        let exponent: i32 = self.exponent.min(other.exponent);
+        let a: u128 = Self::rescale(self.price, self.exponent - exponent)?;
+        let b: u128 = Self::rescale(other.price, other.exponent - exponent)?;
+
+        let diff: u128 = a.abs_diff(b);
+        let bps: u128 = diff.checked_mul(10_000).ok_or(ProgramError::ArithmeticOverflow)? / a.min(b);
+
+        Ok(u64::try_from(bps).unwrap_or(u64::MAX))
+    }
+
+    /// Distance from a $1 peg in basis points, for stablecoin feeds. Non-positive prices count as fully depegged.
+    pub fn peg_deviation_bps(&self) -> Result<u64, ProgramError> {
+        if self.price <= 0 {
+            return Ok(u64::MAX);
+        }
+
+        self.divergence_bps(&Self { price: 1, exponent: 0, publish_time: self.publish_time })
+    }
+
+    fn rescale(price: i64, shift: i32) -> Result<u128, ProgramError> {
+        (price as u128).checked_mul(10u128.checked_pow(shift as u32).ok_or(ProgramError::ArithmeticOverflow)?)
+            .ok_or(ProgramError::ArithmeticOverflow)
+    }
+
+    /// Converts a USD amount (`USD_DECIMALS`) into lamports at this price.
+    pub fn usd_to_lamports(&self, usd_amount: u64) -> Result<u64, ProgramError> {
+        // lamports = usd_amount * 10^(9 - 6) / (price * 10^exponent)
+        let scale: i64 = (LAMPORTS_DECIMALS - USD_DECIMALS) as i64 - self.exponent as i64;
+        let (numerator, denominator): (u128, u128) = if scale >= 0 {
+            (
+                (usd_amount as u128).checked_mul(10u128.checked_pow(scale as u32).ok_or(ProgramError::ArithmeticOverflow)?)
+                    .ok_or(ProgramError::ArithmeticOverflow)?,
+                self.price as u128
+            )
+        } else {
+            (
+                usd_amount as u128,
+                (self.price as u128).checked_mul(10u128.checked_pow(scale.unsigned_abs() as u32).ok_or(ProgramError::ArithmeticOverflow)?)
+                    .ok_or(ProgramError::ArithmeticOverflow)?
+            )
+        };
+
+        u64::try_from(numerator / denominator).map_err(|_| ProgramError::ArithmeticOverflow)
+    }
+}
+
+
+/// Caller-supplied bounds on an oracle-derived settlement amount, protecting both parties from stale or manipulated prices.
+pub struct SettlementBounds {
+    pub min_amount: u64,
+    pub max_amount: u64
+}
+
+impl SettlementBounds {
+    pub fn check(&self, amount: u64) -> ProgramResult {
+        if amount < self.min_amount || amount > self.max_amount {
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        Ok(())
+    }
+}
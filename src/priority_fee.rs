@@ -0,0 +1,112 @@
+//! Client-side priority fees, so settlements land promptly during congestion without manual tuning.
+//!
+//! Feed the per-slot fees returned by `getRecentPrioritizationFees` (scoped to `fee_accounts()` of the transaction)
+//! into `PriorityFeePolicy::compute_unit_price()`, then prepend the compute budget instructions with `with_priority_fee()`,
+//! limited to `compute_unit_limit()` of the units a simulation of the transaction consumed.
+use solana_program::{
+    pubkey,
+    pubkey::Pubkey,
+    instruction::Instruction
+};
+
+
+pub const COMPUTE_BUDGET_PROGRAM_ID: Pubkey = pubkey!("ComputeBudget111111111111111111111111111111");
+
+/// `getRecentPrioritizationFees` accepts at most this many accounts.
+pub const MAX_FEE_ACCOUNTS: usize = 128;
+
+/// Most compute units a transaction can request, which is what simulations run with.
+pub const MAX_COMPUTE_UNIT_LIMIT: u32 = 1_400_000;
+
+/// Headroom over the simulated consumption, in bps, for the state changing between the simulation and the execution.
+pub const COMPUTE_UNIT_MARGIN_BPS: u64 = 1_000;
+
+
+/// How the compute unit price (in micro-lamports) is derived from recent fees.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PriorityFeePolicy {
+    /// percentile of the recent per-slot fees to pay, 0..=100
+    pub percentile: u8,
+    pub min_micro_lamports: u64,
+    /// hard cap, whatever the network currently pays
+    pub max_micro_lamports: u64
+}
+
+impl Default for PriorityFeePolicy {
+    fn default() -> Self {
+        Self {
+            percentile: 75,
+            min_micro_lamports: 0,
+            max_micro_lamports: 1_000_000
+        }
+    }
+}
+
+impl PriorityFeePolicy {
+    /// Price to set for a transaction, given the recent per-slot prioritization fees. `min_micro_lamports` if there are none.
+    pub fn compute_unit_price(&self, recent_fees: &[u64]) -> u64 {
+        let mut fees: Vec<u64> = recent_fees.to_vec();
+        fees.sort_unstable();
+
+        let price: u64 = match fees.len() {
+            0 => 0,
+            len => fees[(len - 1) * self.percentile.min(100) as usize / 100]
+        };
+
+        price.clamp(self.min_micro_lamports, self.max_micro_lamports.max(self.min_micro_lamports))
+    }
+}
+
+/// Accounts the transaction write-locks, which are the ones its priority competes on.
+pub fn fee_accounts(instructions: &[Instruction]) -> Vec<Pubkey> {
+    let mut accounts: Vec<Pubkey> = Vec::new();
+
+    for account in instructions.iter().flat_map(|ix| &ix.accounts).filter(|account| account.is_writable) {
+        if !accounts.contains(&account.pubkey) && accounts.len() < MAX_FEE_ACCOUNTS {
+            accounts.push(account.pubkey);
+        }
+    }
+
+    accounts
+}
+
+/// Limit to request for a transaction whose simulation consumed `units_consumed`, `COMPUTE_UNIT_MARGIN_BPS` above it.
+/// A tighter limit than the default makes the same price cheaper, and the transaction easier to schedule.
+pub fn compute_unit_limit(units_consumed: u64) -> u32 {
+    let units: u64 = units_consumed.saturating_add(units_consumed.saturating_mul(COMPUTE_UNIT_MARGIN_BPS) / 10_000);
+
+    units.min(MAX_COMPUTE_UNIT_LIMIT as u64) as u32
+}
+
+pub fn set_compute_unit_limit(units: u32) -> Instruction {
+    let mut data: Vec<u8> = Vec::with_capacity(5);
+    data.push(2);
+    data.extend_from_slice(&units.to_le_bytes());
+
+    Instruction::new_with_bytes(COMPUTE_BUDGET_PROGRAM_ID, &data, vec![])
+}
+
+pub fn set_compute_unit_price(micro_lamports: u64) -> Instruction {
+    let mut data: Vec<u8> = Vec::with_capacity(9);
+    data.push(3);
+    data.extend_from_slice(&micro_lamports.to_le_bytes());
+
+    Instruction::new_with_bytes(COMPUTE_BUDGET_PROGRAM_ID, &data, vec![])
+}
+
+/// `instructions` preceded by the compute budget instructions. Without a `compute_unit_limit`,
+/// the runtime default (200k CUs per instruction) is what gets priced.
+pub fn with_priority_fee(instructions: &[Instruction], compute_unit_limit: Option<u32>, micro_lamports: u64) -> Vec<Instruction> {
+    let mut prioritized: Vec<Instruction> = Vec::with_capacity(instructions.len() + 2);
+
+    if let Some(units) = compute_unit_limit {
+        prioritized.push(set_compute_unit_limit(units));
+    }
+
+    if micro_lamports > 0 {
+        prioritized.push(set_compute_unit_price(micro_lamports));
+    }
+
+    prioritized.extend_from_slice(instructions);
+    prioritized
+}
@@ -1,50 +1,241 @@
 use solana_program::{
     rent::Rent,
-    sysvar::Sysvar,
+    clock::Clock,
+    hash::hashv,
+    sysvar::{self, Sysvar},
     pubkey::Pubkey,
     system_program,
     system_instruction,
     instruction::Instruction,
     entrypoint::ProgramResult,
-    program::invoke_signed,
+    program::{invoke, invoke_signed},
     program_pack::Pack,
     program_error::ProgramError,
     account_info::{AccountInfo, next_account_info},
+    msg
+};
+use spl_token_2022::{
+    extension::{ExtensionType, StateWithExtensions, BaseStateWithExtensions, transfer_fee::TransferFeeAmount},
+    state::{Account as TokenAccount, Mint}
 };
 use super::{
-    state::EscrowAccount,
+    state::{
+        EscrowAccount, EscrowStatus, EscrowSwap, EscrowCounter, EscrowNote, EscrowArchive, ArchivedEscrow, EscrowDisplay, EscrowMilestones, Milestone,
+        MilestoneTemplate, EscrowAssignments, ClaimShare, Config, BlockedMint, DeadlineBucket, EscrowExport, Inconsistency, VestingShare,
+        LayoutVersion, ImpliedFields
+    },
+    error::EscrowError,
+    accounts::{
+        InitializeAccounts, CompleteAccounts, ClaimAccounts, CloseAccounts, DepositAccounts, FundAccounts, CloseExpiredReservationAccounts,
+        WithdrawVestedAccounts, WithdrawVestedShareAccounts, TouchAccounts, QuarantineAccounts, IndexDeadlineAccounts, ExportStateAccounts, ImportStateAccounts,
+        NominateAdminAccounts, AcceptAdminAccounts, SetFeaturesAccounts, SetYieldVenueAccounts, SweepFeesAccounts, RedeemTreasuryAccounts,
+        AcknowledgeReceiptAccounts, SetStakeSettlementAccounts
+    },
+    events::{EscrowEvent, SettlementBreakdown, SettlementReceipt},
+    oracle::{OraclePrice, SettlementBounds},
+    governance::{self, Proposal},
+    attestation::Attestation,
+    name_service::{NameRecord, RecipientResolution},
+    treasury::{self, StakePool},
+    staking,
     instruction::EscrowInstruction
 };
 
 
 pub struct Processor;
 
+/// Extra accounts of token escrows in `Complete` and `Close`.
+#[derive(Clone, Copy)]
+struct TokenAccounts<'a, 'b> {
+    mint: &'b AccountInfo<'a>,
+    vault: &'b AccountInfo<'a>,
+    destination: &'b AccountInfo<'a>,
+    token_program: &'b AccountInfo<'a>
+}
+
+/// Extra accounts of escrows settled into a stake account in `Complete`, see `SetStakeSettlement`.
+struct StakeAccounts<'a, 'b> {
+    stake: &'b AccountInfo<'a>,
+    vote: &'b AccountInfo<'a>,
+    clock: &'b AccountInfo<'a>,
+    stake_history: &'b AccountInfo<'a>,
+    rent: &'b AccountInfo<'a>,
+    stake_config: &'b AccountInfo<'a>,
+    stake_program: &'b AccountInfo<'a>,
+    system_program: &'b AccountInfo<'a>
+}
+
+/// `EscrowAssignments` and the accounts of its assignees (with their shares) in `Complete`.
+struct ClaimShareAccounts<'a, 'b> {
+    assignments: &'b AccountInfo<'a>,
+    assignees: Vec<(&'b AccountInfo<'a>, u16)>
+}
+
+/// Optional terms of a SOL `Initialize`, see `EscrowInstruction::Initialize`.
+#[derive(Default)]
+struct EscrowTerms {
+    deadline: Option<i64>,
+    reference: Option<[u8; 16]>,
+    hashlock: Option<[u8; 32]>,
+    vesting: Option<(i64, i64)>,
+    splits: Option<Vec<ClaimShare>>,
+    payer_pays_fee: bool,
+    domain: Option<RecipientResolution>
+}
+
 impl Processor {
     pub fn process(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
         let instruction: EscrowInstruction = EscrowInstruction::unpack(data)?;
 
         match instruction {
-            EscrowInstruction::Initialize { amount } => Self::process_initialize_escrow(program_id, accounts, amount)?,
-            EscrowInstruction::Complete => Self::process_complete_escrow(program_id, accounts)?,
-            EscrowInstruction::Close => Self::process_close_escrow(program_id, accounts)?
+            EscrowInstruction::Initialize { amount, deadline, reference, hashlock, vesting, splits, payer_pays_fee, domain, note } => {
+                let terms: EscrowTerms = EscrowTerms { deadline, reference, hashlock, vesting, splits, payer_pays_fee, domain };
+                Self::process_initialize_escrow(program_id, accounts, amount, terms, note)?
+            },
+            EscrowInstruction::Complete { preimage, note } => Self::process_complete_escrow(program_id, accounts, preimage, note)?,
+            EscrowInstruction::Close => Self::process_close_escrow(program_id, accounts)?,
+            EscrowInstruction::CloseNote { mint } => Self::process_close_note(program_id, accounts, mint)?,
+            EscrowInstruction::BatchClose => Self::process_batch_close_escrows(program_id, accounts)?,
+            EscrowInstruction::RegisterCallback { callback } => Self::process_register_callback(program_id, accounts, callback)?,
+            EscrowInstruction::InitializeConfig { namespace } => Self::process_initialize_config(program_id, accounts, namespace)?,
+            EscrowInstruction::BlockMint => Self::process_block_mint(program_id, accounts)?,
+            EscrowInstruction::UnblockMint => Self::process_unblock_mint(program_id, accounts)?,
+            EscrowInstruction::SetUsdTerms { usd_amount, max_divergence_bps } => {
+                Self::process_set_usd_terms(program_id, accounts, usd_amount, max_divergence_bps)?
+            },
+            EscrowInstruction::CompleteUsd { min_amount, max_amount } => {
+                Self::process_complete_usd_escrow(program_id, accounts, SettlementBounds { min_amount, max_amount })?
+            },
+            EscrowInstruction::SetDisplayMetadata { label, icon_hash } => {
+                Self::process_set_display_metadata(program_id, accounts, label, icon_hash)?
+            },
+            EscrowInstruction::CloseDisplayMetadata => Self::process_close_display_metadata(program_id, accounts)?,
+            EscrowInstruction::SetPaused { paused } => Self::process_set_paused(program_id, accounts, paused)?,
+            EscrowInstruction::InitializeToken { amount, reference } => {
+                Self::process_initialize_token_escrow(program_id, accounts, amount, false, reference)?
+            },
+            EscrowInstruction::InitializeNft { reference } => Self::process_initialize_token_escrow(program_id, accounts, 1, true, reference)?,
+            EscrowInstruction::InitializeFromTemplate { amount, template } => {
+                Self::process_initialize_from_template(program_id, accounts, amount, template)?
+            },
+            EscrowInstruction::ApproveMilestone { index } => Self::process_approve_milestone(program_id, accounts, index)?,
+            EscrowInstruction::SetLateFee { bps_per_day, cap_bps } => {
+                Self::process_set_late_fee(program_id, accounts, bps_per_day, cap_bps)?
+            },
+            EscrowInstruction::SetUnlockAt { unlock_at } => Self::process_set_unlock_at(program_id, accounts, unlock_at)?,
+            EscrowInstruction::AssignClaim => Self::process_assign_claim(program_id, accounts)?,
+            EscrowInstruction::Claim { preimage } => Self::process_claim_escrow(program_id, accounts, preimage)?,
+            EscrowInstruction::Accept => Self::process_accept_escrow(program_id, accounts)?,
+            EscrowInstruction::CancelMutual { payer_bps, recipient_bps } => {
+                Self::process_cancel_mutual(program_id, accounts, payer_bps, recipient_bps)?
+            },
+            EscrowInstruction::AssignClaimShare { bps, from_payout } => Self::process_assign_claim_share(program_id, accounts, bps, from_payout)?,
+            EscrowInstruction::RaiseDispute => Self::process_raise_dispute(program_id, accounts)?,
+            EscrowInstruction::ResolveDispute { payer_bps, recipient_bps } => {
+                Self::process_resolve_dispute(program_id, accounts, payer_bps, recipient_bps)?
+            },
+            EscrowInstruction::SetDepegGuard { max_depeg_bps } => Self::process_set_depeg_guard(program_id, accounts, max_depeg_bps)?,
+            EscrowInstruction::OverrideDepegFreeze => Self::process_override_depeg_freeze(program_id, accounts)?,
+            EscrowInstruction::Reconcile => Self::process_reconcile(program_id, accounts)?,
+            EscrowInstruction::Deposit { amount } => Self::process_deposit(program_id, accounts, amount)?,
+            EscrowInstruction::OpenSwap { deadline } => Self::process_open_swap(program_id, accounts, deadline)?,
+            EscrowInstruction::JoinSwap => Self::process_join_swap(program_id, accounts)?,
+            EscrowInstruction::Settle => Self::process_settle_swap(program_id, accounts)?,
+            EscrowInstruction::Reserve { amount, reserved_until } => {
+                Self::process_reserve_escrow(program_id, accounts, amount, reserved_until)?
+            },
+            EscrowInstruction::Fund => Self::process_fund_escrow(program_id, accounts)?,
+            EscrowInstruction::CloseExpiredReservation => Self::process_close_expired_reservation(program_id, accounts)?,
+            EscrowInstruction::WithdrawVested => Self::process_withdraw_vested(program_id, accounts)?,
+            EscrowInstruction::InitializeMilestones { milestones } => {
+                Self::process_initialize_milestones(program_id, accounts, milestones)?
+            },
+            EscrowInstruction::SettleSwap { amount_a, amount_b } => Self::process_settle_mirrored_swap(program_id, accounts, amount_a, amount_b)?,
+            EscrowInstruction::SetGovernanceVeto { veto_until } => Self::process_set_governance_veto(program_id, accounts, veto_until)?,
+            EscrowInstruction::GovernanceVeto => Self::process_governance_veto(program_id, accounts)?,
+            EscrowInstruction::SetProtocolFee { fee_bps } => Self::process_set_protocol_fee(program_id, accounts, fee_bps)?,
+            EscrowInstruction::WithdrawFees { amount } => Self::process_withdraw_fees(program_id, accounts, amount)?,
+            EscrowInstruction::SetAttestationIssuer { issuer } => Self::process_set_attestation_issuer(program_id, accounts, issuer)?,
+            EscrowInstruction::RequireAttestation => Self::process_require_attestation(program_id, accounts)?,
+            EscrowInstruction::UpdateConfig { min_amount, max_amount } => Self::process_update_config(program_id, accounts, min_amount, max_amount)?,
+            EscrowInstruction::SetMaxPayout { max_payout } => Self::process_set_max_payout(program_id, accounts, max_payout)?,
+            EscrowInstruction::NominateAdmin { admin } => Self::process_nominate_admin(program_id, accounts, admin)?,
+            EscrowInstruction::AcceptAdmin => Self::process_accept_admin(program_id, accounts)?,
+            EscrowInstruction::Touch => Self::process_touch(program_id, accounts)?,
+            EscrowInstruction::IndexDeadline => Self::process_index_deadline(program_id, accounts)?,
+            EscrowInstruction::PruneDeadlines => Self::process_prune_deadlines(program_id, accounts)?,
+            EscrowInstruction::RequireIsolatedSettlement => Self::process_require_isolated_settlement(program_id, accounts)?,
+            EscrowInstruction::ExportState => Self::process_export_state(program_id, accounts)?,
+            EscrowInstruction::ImportState { data } => Self::process_import_state(program_id, accounts, &data)?,
+            EscrowInstruction::Quarantine => Self::process_quarantine(program_id, accounts)?,
+            EscrowInstruction::WithdrawVestedShare => Self::process_withdraw_vested_share(program_id, accounts)?,
+            EscrowInstruction::SetFeatures { features } => Self::process_set_features(program_id, accounts, features)?,
+            EscrowInstruction::BatchSettle => Self::process_batch_settle(program_id, accounts)?,
+            EscrowInstruction::SetYieldVenue { venue } => Self::process_set_yield_venue(program_id, accounts, venue)?,
+            EscrowInstruction::SweepFees { amount } => Self::process_sweep_fees(program_id, accounts, amount)?,
+            EscrowInstruction::RedeemTreasury { pool_tokens } => Self::process_redeem_treasury(program_id, accounts, pool_tokens)?,
+            EscrowInstruction::AcknowledgeReceipt { record, lamports, state } => {
+                Self::process_acknowledge_receipt(program_id, accounts, record, lamports, &state)?
+            },
+            EscrowInstruction::SetStakeSettlement { vote } => Self::process_set_stake_settlement(program_id, accounts, vote)?
         };
 
         Ok(())
     }
 
-    fn process_initialize_escrow(program_id: &Pubkey, accounts: &[AccountInfo], amount: u64) -> ProgramResult {
-        let rent_exemp: u64 = Rent::get()?.minimum_balance(EscrowAccount::LEN);
+    fn process_initialize_escrow(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        amount: u64,
+        terms: EscrowTerms,
+        note: Option<Vec<u8>>
+    ) -> ProgramResult {
+        let rent_exemp: u64 = crate::rent::escrow(&Rent::get()?);
         
         if amount < rent_exemp {
-            return Err(ProgramError::InsufficientFunds);
+            return Err(EscrowError::AmountTooSmall.into());
+        }
+
+        let now: i64 = Clock::get()?.unix_timestamp;
+
+        if terms.deadline.is_some_and(|deadline| deadline <= now) {
+            msg!("Deadline is in the past");
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        if terms.vesting.is_some_and(|(_start, end)| end <= now) {
+            msg!("Vesting would already be over");
+            return Err(ProgramError::InvalidArgument);
         }
         
         let accounts_iter = &mut accounts.iter();
         
-        let payer_account: &AccountInfo = next_account_info(accounts_iter)?;
-        let recipient_account: &AccountInfo = next_account_info(accounts_iter)?;
-        let escrow_account: &AccountInfo = next_account_info(accounts_iter)?;  // pda
-        let system_program_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let InitializeAccounts {
+            payer: payer_account,
+            recipient: recipient_account,
+            escrow: escrow_account,
+            system_program: system_program_account,
+            counter: counter_account,
+            config: config_account
+        } = InitializeAccounts::parse(accounts_iter)?;
+
+        Self::_check_not_paused(program_id, config_account, Config::PAUSE_INITIALIZE)?;
+        Self::_check_amount_limits(program_id, config_account, amount)?;
+
+        if let Some(splits) = &terms.splits {
+            if splits.is_empty()
+                || splits.len() > EscrowAssignments::MAX_SHARES
+                || splits.iter().any(|split| split.bps == 0 || &split.assignee == recipient_account.key)
+                || splits.iter().map(|split| split.bps as u32).sum::<u32>() > 10_000
+            {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+
+            // the recipient vests alongside the splits, so it has to keep a part
+            if terms.vesting.is_some() && splits.iter().map(|split| split.bps as u32).sum::<u32>() == 10_000 {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+        }
         
         let (seed1, seed2, seed3) = crate::get_escrow_seeds(payer_account.key, recipient_account.key);
         let (expected_pda, bump) = Pubkey::find_program_address(
@@ -53,84 +244,736 @@ impl Processor {
         );
 
         if &expected_pda != escrow_account.key {
-            return Err(ProgramError::InvalidInstructionData);
+            return Err(EscrowError::PdaMismatch.into());
         }
 
         let signers_seeds: &[&[u8]] = &[seed1, seed2, seed3, &[bump]];
         let total_amount: u64 = rent_exemp + amount;
 
         // 1. create pda account
-        let create_ix: Instruction = system_instruction::create_account(
-            payer_account.key, 
-            escrow_account.key, 
-            total_amount, 
-            EscrowAccount::LEN as u64, 
-            program_id
-        );
-        invoke_signed(
-            &create_ix, 
-            &[
-                payer_account.clone(),
-                escrow_account.clone(),
-                system_program_account.clone()
-            ],
-            &[signers_seeds]
+        Self::_process_create_pda(
+            payer_account,
+            escrow_account,
+            system_program_account,
+            total_amount,
+            EscrowAccount::LEN,
+            program_id,
+            signers_seeds
         )?;
 
-        // 2. init pda account
-        let escrow_instance: EscrowAccount = EscrowAccount::new(bump);
-        let escrow_data: &mut [u8] = &mut **escrow_account.data.borrow_mut();
+        // 2. take the next escrow id
+        let id: u64 = Self::_process_next_escrow_id(
+            program_id,
+            payer_account,
+            counter_account,
+            system_program_account
+        )?;
+
+        // 3. init pda account
+        let mut escrow_instance: EscrowAccount = EscrowAccount::new(bump, id, now, *payer_account.key, *recipient_account.key, amount);
+        escrow_instance.config = *config_account.key;
+        escrow_instance.deadline = terms.deadline.unwrap_or(0);
+        escrow_instance.reference = terms.reference.unwrap_or_default();
+        escrow_instance.hashlock = terms.hashlock.unwrap_or_default();
+        (escrow_instance.vesting_start, escrow_instance.vesting_end) = terms.vesting.unwrap_or_default();
+        escrow_instance.has_assignments = terms.splits.is_some();
+        escrow_instance.payer_pays_fee = terms.payer_pays_fee;
+        escrow_instance.payer_splits = terms.splits.is_some();
+
+        // (optional) the recipient is a domain, its owner is either pinned as the assignee or looked up at settlement
+        match terms.domain {
+            Some(RecipientResolution::Pinned) => escrow_instance.assignee = NameRecord::load(recipient_account)?.owner,
+            Some(RecipientResolution::AtSettlement) => {
+                NameRecord::load(recipient_account)?;
+                escrow_instance.resolve_recipient = true;
+            },
+            None => {}
+        }
+
+        // (optional) arbiter, right after the `EscrowAssignments` PDA, the `VestingShare` PDAs & the note account if there are any
+        let vesting_shares: usize = match (&terms.vesting, &terms.splits) {
+            (Some(_), Some(splits)) => 1 + splits.len(),
+            _ => 0
+        };
+        let arbiter_index: usize = 6 + terms.splits.is_some() as usize + vesting_shares + note.is_some() as usize;
+        if let Some(arbiter_account) = accounts.get(arbiter_index) {
+            escrow_instance.arbiter = *arbiter_account.key;
+        }
+
+        let seq: u64 = escrow_instance.next_event_seq();
+        let escrow_data: &mut [u8] = &mut escrow_account.data.borrow_mut();
         escrow_instance.pack_into_slice(escrow_data);
 
+        // (optional) the payer's split of the payout, settled by `Complete` like sold shares
+        if let Some(splits) = terms.splits {
+            let assignments_account: &AccountInfo = next_account_info(accounts_iter)?;
+            Self::_process_create_splits(
+                program_id,
+                payer_account,
+                escrow_account,
+                assignments_account,
+                system_program_account,
+                splits
+            )?;
+
+            // (team vesting) the `VestingShare` of every beneficiary, the recipient's first
+            if escrow_instance.is_vesting() {
+                let assignments_instance: EscrowAssignments = EscrowAssignments::unpack(&assignments_account.data.borrow())?;
+                let beneficiaries = std::iter::once(recipient_account.key).chain(assignments_instance.shares.iter().map(|share| &share.assignee));
+
+                for beneficiary_pkey in beneficiaries {
+                    let allocation: u64 = assignments_instance.allocation(amount, recipient_account.key, beneficiary_pkey)
+                        .ok_or(ProgramError::InvalidInstructionData)?;
+                    Self::_process_create_vesting_share(
+                        program_id,
+                        payer_account,
+                        escrow_account,
+                        next_account_info(accounts_iter)?,
+                        system_program_account,
+                        beneficiary_pkey,
+                        allocation
+                    )?;
+                }
+            }
+        }
+
+        // 4. (optional) attach an encrypted note for the recipient
+        if let Some(ciphertext) = note {
+            let note_account: &AccountInfo = next_account_info(accounts_iter)?;
+            Self::_process_attach_note(
+                program_id,
+                payer_account,
+                escrow_account,
+                note_account,
+                system_program_account,
+                ciphertext
+            )?;
+        }
+
+        EscrowEvent::Initialized { id, escrow: *escrow_account.key, callback: escrow_instance.callback, seq, amount }.emit();
+
         Ok(())
     }
 
-    fn process_complete_escrow(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    fn process_reserve_escrow(program_id: &Pubkey, accounts: &[AccountInfo], amount: u64, reserved_until: i64) -> ProgramResult {
+        let rent_exemp: u64 = crate::rent::escrow(&Rent::get()?);
+        let now: i64 = Clock::get()?.unix_timestamp;
+
+        // same lower bound as `Initialize`
+        if amount < rent_exemp {
+            return Err(EscrowError::AmountTooSmall.into());
+        }
+
+        if reserved_until <= now {
+            msg!("Reservation would already be expired");
+            return Err(ProgramError::InvalidArgument);
+        }
+
         let accounts_iter = &mut accounts.iter();
 
         let payer_account: &AccountInfo = next_account_info(accounts_iter)?;
         let recipient_account: &AccountInfo = next_account_info(accounts_iter)?;
-        let escrow_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let escrow_account: &AccountInfo = next_account_info(accounts_iter)?;  // pda
+        let system_program_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let counter_account: &AccountInfo = next_account_info(accounts_iter)?;  // pda
+        let config_account: &AccountInfo = next_account_info(accounts_iter)?;  // pda
+
+        Self::_check_not_paused(program_id, config_account, Config::PAUSE_INITIALIZE)?;
+        Self::_check_amount_limits(program_id, config_account, amount)?;
+
+        let (seed1, seed2, seed3) = crate::get_escrow_seeds(payer_account.key, recipient_account.key);
+        let (expected_pda, bump) = Pubkey::find_program_address(&[seed1, seed2, seed3], program_id);
+
+        if &expected_pda != escrow_account.key {
+            return Err(EscrowError::PdaMismatch.into());
+        }
+
+        // 1. create pda account, holding nothing but its rent
+        Self::_process_create_pda(
+            payer_account,
+            escrow_account,
+            system_program_account,
+            rent_exemp,
+            EscrowAccount::LEN,
+            program_id,
+            &[seed1, seed2, seed3, &[bump]]
+        )?;
+
+        // 2. take the next escrow id, so the escrow is fully addressable before it's funded
+        let id: u64 = Self::_process_next_escrow_id(
+            program_id,
+            payer_account,
+            counter_account,
+            system_program_account
+        )?;
+
+        let mut escrow_instance: EscrowAccount = EscrowAccount::new(bump, id, now, *payer_account.key, *recipient_account.key, amount);
+        escrow_instance.config = *config_account.key;
+        escrow_instance.status = EscrowStatus::Reserved;
+        escrow_instance.reserved_until = reserved_until;
+        escrow_instance.pack_into_slice(&mut escrow_account.data.borrow_mut());
+
+        Ok(())
+    }
+
+    fn process_fund_escrow(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let FundAccounts {
+            payer: payer_account,
+            recipient: recipient_account,
+            escrow: escrow_account,
+            system_program: system_program_account,
+            config: config_account
+        } = FundAccounts::parse(accounts_iter)?;
+
+        Self::_check_not_paused(program_id, config_account, Config::PAUSE_INITIALIZE)?;
+
+        if !payer_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
 
         if escrow_account.owner != program_id {
             return Err(ProgramError::IncorrectProgramId);
         }
 
-        // 1. unpack EscrowAccount (check if it's initialized & extract bump)
-        let escrow_data = escrow_account.data.borrow(); 
-        let escrow_instance: EscrowAccount = EscrowAccount::unpack(&**escrow_data)?;
-        std::mem::drop(escrow_data);  // explicitly dropping ref, because we call escrow_account.data.borrow_mut() in close_account()
+        let mut escrow_instance: EscrowAccount = EscrowAccount::unpack(&escrow_account.data.borrow())?;
 
-        // 2. create `expected_pda` and check the match with provided pda
-        crate::check_provided_pda(
-            payer_account.key,
-            recipient_account.key,
-            escrow_account.key,
-            escrow_instance.bump
+        Self::_check_sol_escrow_pda(payer_account, recipient_account, escrow_account, &escrow_instance)?;
+        Self::_check_escrow_config(config_account, &escrow_instance)?;
+
+        if escrow_instance.status == EscrowStatus::Reserved && Clock::get()?.unix_timestamp >= escrow_instance.reserved_until {
+            msg!("Reservation expired");
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        escrow_instance.status = escrow_instance.status.transition(EscrowStatus::Pending)?;
+
+        invoke(
+            &system_instruction::transfer(payer_account.key, escrow_account.key, escrow_instance.amount),
+            &[payer_account.clone(), escrow_account.clone(), system_program_account.clone()]
         )?;
 
-        // 3. transfer locked lamports in the contract to the recipient & close `EscrowAccount`.
-        // Note, that we MUST NOT subtract the balance of `EscrowAccount`, because `EscrowInstruction::close()` already handles it.
-        let rent_exemp: u64 = Rent::get()?.minimum_balance(EscrowAccount::LEN);
-        let locked_amount: u64 = escrow_account.lamports() - rent_exemp;
+        let seq: u64 = escrow_instance.next_event_seq();
+        escrow_instance.pack_into_slice(&mut escrow_account.data.borrow_mut());
+
+        EscrowEvent::Initialized {
+            id: escrow_instance.id,
+            escrow: *escrow_account.key,
+            callback: escrow_instance.callback,
+            seq,
+            amount: escrow_instance.amount
+        }.emit();
+
+        Ok(())
+    }
+
+    fn process_close_expired_reservation(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let CloseExpiredReservationAccounts {
+            payer: payer_account,
+            recipient: recipient_account,
+            escrow: escrow_account
+        } = CloseExpiredReservationAccounts::parse(accounts_iter)?;
+
+        if escrow_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        let mut escrow_instance: EscrowAccount = EscrowAccount::unpack(&escrow_account.data.borrow())?;
+
+        Self::_check_sol_escrow_pda(payer_account, recipient_account, escrow_account, &escrow_instance)?;
+
+        if escrow_instance.status != EscrowStatus::Reserved || Clock::get()?.unix_timestamp < escrow_instance.reserved_until {
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        escrow_instance.status.transition(EscrowStatus::Cancelled)?;
+
+        Self::_process_close_escrow(payer_account, escrow_account, escrow_account.lamports())?;
+
+        EscrowEvent::Closed {
+            id: escrow_instance.id,
+            escrow: *escrow_account.key,
+            callback: escrow_instance.callback,
+            seq: escrow_instance.next_event_seq()
+        }.emit();
+
+        Ok(())
+    }
+
+    fn process_withdraw_vested(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let WithdrawVestedAccounts {
+            recipient: recipient_account,
+            payer: payer_account,
+            escrow: escrow_account,
+            config: config_account
+        } = WithdrawVestedAccounts::parse(accounts_iter)?;
+
+        Self::_check_not_paused(program_id, config_account, Config::PAUSE_COMPLETE)?;
+
+        if !recipient_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        if escrow_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        let mut escrow_instance: EscrowAccount = EscrowAccount::unpack(&escrow_account.data.borrow())?;
+
+        Self::_check_sol_escrow_pda(payer_account, recipient_account, escrow_account, &escrow_instance)?;
+        Self::_check_escrow_config(config_account, &escrow_instance)?;
+        Self::_check_not_disputed(&escrow_instance)?;
+        Self::_check_not_quarantined(&escrow_instance)?;
+
+        // an assigned claim would have to be withdrawn by its holder
+        if !escrow_instance.is_vesting() || escrow_instance.has_assignments || escrow_instance.assignee != Pubkey::default() {
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        let config_instance: Option<Config> = Self::_load_config(program_id, config_account)?;
+        let (fee_vault_account, fee_system_program_account) = Self::_next_fee_vault(
+            program_id,
+            accounts_iter,
+            config_instance.as_ref(),
+            &[&escrow_instance]
+        )?;
+
+        // 1. pay out what vested since the last withdrawal, less the protocol fee on it
+        let vested: u64 = escrow_instance.vested(Clock::get()?.unix_timestamp);
+        let withdrawable: u64 = vested - escrow_instance.withdrawn;
+
+        if withdrawable == 0 {
+            msg!("Nothing vested since the last withdrawal");
+            return Err(ProgramError::InsufficientFunds);
+        }
+
+        let protocol_fee: u64 = Self::_process_protocol_fee(
+            payer_account,
+            escrow_account,
+            &mut escrow_instance,
+            fee_vault_account.zip(config_instance.as_ref()),
+            fee_system_program_account,
+            withdrawable
+        )?;
+        let deducted_fee: u64 = if escrow_instance.payer_pays_fee { 0 } else { protocol_fee };
 
+        **escrow_account.lamports.borrow_mut() = escrow_account.lamports()
+            .checked_sub(withdrawable)
+            .ok_or(ProgramError::InsufficientFunds)?;
         **recipient_account.lamports.borrow_mut() = recipient_account.lamports()
-            .checked_add(locked_amount)
+            .checked_add(withdrawable - deducted_fee)
             .ok_or(ProgramError::ArithmeticOverflow)?;
 
-        Self::_process_close_escrow(payer_account, escrow_account, rent_exemp)?;
+        escrow_instance.withdrawn = vested;
+
+        EscrowEvent::VestedWithdrawn {
+            id: escrow_instance.id,
+            escrow: *escrow_account.key,
+            callback: escrow_instance.callback,
+            seq: escrow_instance.next_event_seq(),
+            amount: withdrawable,
+            withdrawn: vested
+        }.emit();
+
+        // 2. fully vested, the rent goes back to the payer
+        if vested == escrow_instance.amount {
+            escrow_instance.status.transition(EscrowStatus::Completed)?;
+
+            Self::_process_close_escrow(payer_account, escrow_account, escrow_account.lamports())?;
+
+            EscrowEvent::Completed {
+                id: escrow_instance.id,
+                escrow: *escrow_account.key,
+                callback: escrow_instance.callback,
+                seq: escrow_instance.next_event_seq(),
+                amount: escrow_instance.amount
+            }.emit();
+
+            return Ok(());
+        }
+
+        escrow_instance.pack_into_slice(&mut escrow_account.data.borrow_mut());
 
         Ok(())
     }
 
-    fn process_close_escrow(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+    fn process_withdraw_vested_share(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
         let accounts_iter = &mut accounts.iter();
 
-        let payer_account: &AccountInfo = next_account_info(accounts_iter)?;
-        // this field is only used for pda check, namely `recipient_account.key`. 
-        // (we could teoretically store `recipient_pkey` in the `escrow_account.data`, but since it's used only here, it's an overkill)
-        let recipient_account: &AccountInfo = next_account_info(accounts_iter)?;
-        let escrow_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let WithdrawVestedShareAccounts {
+            beneficiary: beneficiary_account,
+            payer: payer_account,
+            recipient: recipient_account,
+            escrow: escrow_account,
+            config: config_account,
+            assignments: assignments_account,
+            vesting_share: vesting_share_account
+        } = WithdrawVestedShareAccounts::parse(accounts_iter)?;
+
+        Self::_check_not_paused(program_id, config_account, Config::PAUSE_COMPLETE)?;
+
+        if !beneficiary_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        if escrow_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        let mut escrow_instance: EscrowAccount = EscrowAccount::unpack(&escrow_account.data.borrow())?;
+
+        Self::_check_sol_escrow_pda(payer_account, recipient_account, escrow_account, &escrow_instance)?;
+        Self::_check_escrow_config(config_account, &escrow_instance)?;
+        Self::_check_not_disputed(&escrow_instance)?;
+        Self::_check_not_quarantined(&escrow_instance)?;
+
+        if !escrow_instance.is_team_vesting() {
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        Self::_check_assignments_pda(program_id, escrow_account, assignments_account)?;
+        let mut share_instance: VestingShare = Self::_check_vesting_share_pda(
+            program_id,
+            escrow_account,
+            beneficiary_account.key,
+            vesting_share_account
+        )?;
+
+        let config_instance: Option<Config> = Self::_load_config(program_id, config_account)?;
+        let (fee_vault_account, fee_system_program_account) = Self::_next_fee_vault(
+            program_id,
+            accounts_iter,
+            config_instance.as_ref(),
+            &[&escrow_instance]
+        )?;
+
+        // 1. pay out what vested of the beneficiary's allocation since its last withdrawal, less the protocol fee on it
+        let vested: u64 = share_instance.vested(&escrow_instance, Clock::get()?.unix_timestamp);
+        let withdrawable: u64 = vested - share_instance.withdrawn;
+
+        if withdrawable == 0 {
+            msg!("Nothing vested since the last withdrawal");
+            return Err(ProgramError::InsufficientFunds);
+        }
+
+        let protocol_fee: u64 = Self::_process_protocol_fee(
+            payer_account,
+            escrow_account,
+            &mut escrow_instance,
+            fee_vault_account.zip(config_instance.as_ref()),
+            fee_system_program_account,
+            withdrawable
+        )?;
+        let deducted_fee: u64 = if escrow_instance.payer_pays_fee { 0 } else { protocol_fee };
+
+        **escrow_account.lamports.borrow_mut() = escrow_account.lamports()
+            .checked_sub(withdrawable)
+            .ok_or(ProgramError::InsufficientFunds)?;
+        **beneficiary_account.lamports.borrow_mut() = beneficiary_account.lamports()
+            .checked_add(withdrawable - deducted_fee)
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+
+        share_instance.withdrawn = vested;
+        escrow_instance.withdrawn = escrow_instance.withdrawn
+            .checked_add(withdrawable)
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+
+        EscrowEvent::VestedShareWithdrawn {
+            id: escrow_instance.id,
+            escrow: *escrow_account.key,
+            callback: escrow_instance.callback,
+            seq: escrow_instance.next_event_seq(),
+            beneficiary: *beneficiary_account.key,
+            amount: withdrawable,
+            withdrawn: vested
+        }.emit();
+
+        // 2. the share is fully withdrawn, its rent goes back to the payer
+        if vested == share_instance.allocation {
+            Self::_process_close_escrow(payer_account, vesting_share_account, vesting_share_account.lamports())?;
+        } else {
+            share_instance.pack_into_slice(&mut vesting_share_account.data.borrow_mut());
+        }
+
+        // 3. every share is, so is the escrow
+        if escrow_instance.withdrawn == escrow_instance.amount {
+            escrow_instance.status.transition(EscrowStatus::Completed)?;
+
+            Self::_process_close_escrow(payer_account, assignments_account, assignments_account.lamports())?;
+            Self::_process_close_escrow(payer_account, escrow_account, escrow_account.lamports())?;
+
+            EscrowEvent::Completed {
+                id: escrow_instance.id,
+                escrow: *escrow_account.key,
+                callback: escrow_instance.callback,
+                seq: escrow_instance.next_event_seq(),
+                amount: escrow_instance.amount
+            }.emit();
+
+            return Ok(());
+        }
+
+        escrow_instance.pack_into_slice(&mut escrow_account.data.borrow_mut());
+
+        Ok(())
+    }
+
+    fn process_complete_escrow(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        preimage: Option<[u8; 32]>,
+        note: Option<Vec<u8>>
+    ) -> ProgramResult {
+        Self::_process_complete_escrow(program_id, accounts, preimage, note, false)
+    }
+
+    /// Settles an escrow to its recipient. `payer_authorized` is set by callers that verified the payer's consent
+    /// some other way than its signature, e.g. `BatchSettle` through an Ed25519 authorization.
+    fn _process_complete_escrow(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        preimage: Option<[u8; 32]>,
+        note: Option<Vec<u8>>,
+        payer_authorized: bool
+    ) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let CompleteAccounts {
+            payer: payer_account,
+            recipient: recipient_account,
+            escrow: escrow_account,
+            config: config_account
+        } = CompleteAccounts::parse(accounts_iter)?;
+
+        Self::_check_not_paused(program_id, config_account, Config::PAUSE_COMPLETE)?;
+        let config_instance: Option<Config> = Self::_load_config(program_id, config_account)?;
+
+        if !payer_account.is_signer && !payer_authorized {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        if escrow_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        // 1. unpack EscrowAccount (check if it's initialized & extract bump), legacy escrows included
+        let (mut escrow_instance, layout) = Self::_load_escrow(program_id, payer_account, recipient_account, escrow_account)?;
+
+        // 2. create `expected_pda` and check the match with provided pda
+        Self::_check_escrow_pda(payer_account, recipient_account, escrow_account, &escrow_instance)?;
+        Self::_check_escrow_config(config_account, &escrow_instance)?;
+
+        // with a late fee the payer may still complete after the deadline, paying for the delay
+        if escrow_instance.late_fee_bps_per_day == 0 {
+            Self::_check_deadline(&escrow_instance, true)?;
+        }
+
+        Self::_check_unlocked(&escrow_instance)?;
+        Self::_check_not_disputed(&escrow_instance)?;
+        Self::_check_not_quarantined(&escrow_instance)?;
+        Self::_check_not_swapped(&escrow_instance)?;
+        Self::_check_preimage(&escrow_instance, preimage)?;
+        escrow_instance.status.transition(EscrowStatus::Completed)?;
+
+        let payout_account: &AccountInfo = Self::_next_payout_account(accounts_iter, recipient_account, &escrow_instance)?;
+        let claim_shares: Option<ClaimShareAccounts> = Self::_next_claim_shares(program_id, accounts_iter, escrow_account, &escrow_instance)?;
+
+        // token escrows carry their token accounts right after `config` (and the assignee)
+        let token_accounts: Option<TokenAccounts> = Self::_next_token_accounts(accounts_iter, &escrow_instance)?;
+        Self::_check_peg(accounts_iter, &escrow_instance)?;
+
+        // escrows with a late fee carry the system program right after `config`, the fee is paid from the payer's wallet
+        let late_fee_system_program_account: Option<&AccountInfo> = if escrow_instance.late_fee_bps_per_day != 0 {
+            Some(next_account_info(accounts_iter)?)
+        } else {
+            None
+        };
+
+        // while a protocol fee is set, SOL escrows carry the fee vault next (and the system program if their payer bears it)
+        let (fee_vault_account, fee_system_program_account) = Self::_next_fee_vault(
+            program_id,
+            accounts_iter,
+            config_instance.as_ref(),
+            &[&escrow_instance]
+        )?;
+
+        Self::_check_attestation(accounts_iter, config_instance.as_ref(), &escrow_instance, recipient_account)?;
+        Self::_check_isolated_settlement(accounts_iter, &escrow_instance)?;
+
+        // escrows settled into a stake account carry its accounts next, the payout then going into the stake account
+        let stake_accounts: Option<StakeAccounts> = Self::_next_stake_accounts(program_id, accounts_iter, &escrow_instance)?;
+        let settlement_account: &AccountInfo = stake_accounts.as_ref().map_or(payout_account, |stake_accounts| stake_accounts.stake);
+
+        // USD-denominated escrows settle only through `CompleteUsd`, milestone escrows through `ApproveMilestone`,
+        // team vesting escrows through every beneficiary's `WithdrawVestedShare`
+        if escrow_instance.usd_amount != 0 || escrow_instance.has_milestones || escrow_instance.is_team_vesting() {
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        // capped escrows are drawn down, all but the last draw leaving the escrow open
+        if token_accounts.is_none() && Self::_is_partial_draw(escrow_account, &escrow_instance, layout)? {
+            if stake_accounts.is_some() {
+                msg!("Capped escrows can't be settled into a stake account");
+                return Err(ProgramError::InvalidArgument);
+            }
+
+            return Self::_process_draw(
+                payer_account,
+                payout_account,
+                escrow_account,
+                escrow_instance,
+                late_fee_system_program_account.or(fee_system_program_account),
+                fee_vault_account.zip(config_instance.as_ref())
+            );
+        }
+
+        // 3. (optional) attach an encrypted note for the recipient.
+        // The note outlives `EscrowAccount`, so it has to be created before the escrow is closed.
+        if let Some(ciphertext) = note {
+            let note_account: &AccountInfo = next_account_info(accounts_iter)?;
+            let system_program_account: &AccountInfo = next_account_info(accounts_iter)?;
+            Self::_process_attach_note(
+                program_id,
+                payer_account,
+                escrow_account,
+                note_account,
+                system_program_account,
+                ciphertext
+            )?;
+        }
+
+        // (optional) archive the escrow while its final state is still there
+        if let Some((archive_account, system_program_account)) = Self::_next_archive_accounts(program_id, accounts_iter, payer_account, true)? {
+            Self::_process_archive_escrow(
+                program_id,
+                payer_account,
+                escrow_account,
+                archive_account,
+                system_program_account,
+                escrow_instance.created_at,
+                ArchivedEscrow::OUTCOME_COMPLETED
+            )?;
+        }
+
+        // 4. transfer locked lamports (or tokens) in the contract to the recipient & close `EscrowAccount`.
+        // Note, that we MUST NOT subtract the balance of `EscrowAccount`, because `EscrowInstruction::close()` already handles it.
+        let breakdown: SettlementBreakdown = match token_accounts {
+            Some(token_accounts) => {
+                let locked_amount: u64 = Self::_process_release_vault(
+                    payer_account,
+                    recipient_account,
+                    escrow_account,
+                    &escrow_instance,
+                    &token_accounts,
+                    payout_account.key
+                )?;
+                let rent_refund: u64 = escrow_account.lamports();
+                Self::_process_close_escrow(payer_account, escrow_account, rent_refund)?;
+                SettlementBreakdown { principal: locked_amount, protocol_fee: 0, late_fee: 0, payout: locked_amount, shares: 0, arbiter: 0, rent_refund }
+            },
+            None => {
+                let rent_exemp: u64 = crate::rent::escrow_in(&Rent::get()?, layout);
+                let locked_amount: u64 = escrow_account.lamports() - rent_exemp;
+                let late_fee: u64 = escrow_instance.late_fee(locked_amount, Clock::get()?.unix_timestamp)?;
+
+                // the late fee comes out of the payer's wallet, so it has to be transferred before any lamports are moved by hand
+                if late_fee != 0 {
+                    let system_program_account: &AccountInfo = late_fee_system_program_account.ok_or(ProgramError::NotEnoughAccountKeys)?;
+                    invoke(
+                        &system_instruction::transfer(payer_account.key, settlement_account.key, late_fee),
+                        &[
+                            payer_account.clone(),
+                            settlement_account.clone(),
+                            system_program_account.clone()
+                        ]
+                    )?;
+                }
+
+                let protocol_fee: u64 = Self::_process_protocol_fee(
+                    payer_account,
+                    escrow_account,
+                    &mut escrow_instance,
+                    fee_vault_account.zip(config_instance.as_ref()),
+                    fee_system_program_account,
+                    locked_amount
+                )?;
+                let deducted_fee: u64 = if escrow_instance.payer_pays_fee { 0 } else { protocol_fee };
+
+                let rent_receiver_account: &AccountInfo = Self::_assignments_rent_receiver(&escrow_instance, payer_account, recipient_account);
+                let shares: u64 = Self::_process_payout(rent_receiver_account, settlement_account, claim_shares, locked_amount - deducted_fee)?;
+
+                Self::_process_close_escrow(payer_account, escrow_account, rent_exemp)?;
+
+                if let Some(stake_accounts) = &stake_accounts {
+                    Self::_process_stake_payout(program_id, payout_account, stake_accounts, escrow_instance.id)?;
+                }
+
+                SettlementBreakdown {
+                    principal: locked_amount,
+                    protocol_fee,
+                    late_fee,
+                    payout: locked_amount - deducted_fee + late_fee - shares,
+                    shares,
+                    arbiter: 0,
+                    rent_refund: rent_exemp
+                }
+            }
+        };
+        let locked_amount: u64 = breakdown.payout + breakdown.shares;
+
+        // 5. (optional) write a settlement receipt memo
+        if let Ok(memo_program_account) = next_account_info(accounts_iter) {
+            let receipt: SettlementReceipt = SettlementReceipt {
+                id: escrow_instance.id,
+                escrow: escrow_account.key,
+                payer: payer_account.key,
+                recipient: payout_account.key,
+                amount: locked_amount
+            };
+            Self::_process_memo_receipt(memo_program_account, &receipt)?;
+
+            // the memo comes last, an archive after it is misordered rather than left out
+            if accounts_iter.next().is_some() {
+                msg!("The memo program must be the last account");
+                return Err(ProgramError::InvalidArgument);
+            }
+        }
+
+        EscrowEvent::Settled {
+            id: escrow_instance.id,
+            escrow: *escrow_account.key,
+            callback: escrow_instance.callback,
+            seq: escrow_instance.next_event_seq(),
+            breakdown
+        }.emit();
+
+        EscrowEvent::Completed {
+            id: escrow_instance.id,
+            escrow: *escrow_account.key,
+            callback: escrow_instance.callback,
+            seq: escrow_instance.next_event_seq(),
+            amount: locked_amount
+        }.emit();
+
+        Ok(())
+    }
+
+    fn process_claim_escrow(program_id: &Pubkey, accounts: &[AccountInfo], preimage: Option<[u8; 32]>) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let ClaimAccounts {
+            recipient: recipient_account,
+            payer: payer_account,
+            escrow: escrow_account,
+            config: config_account
+        } = ClaimAccounts::parse(accounts_iter)?;
+
+        Self::_check_not_paused(program_id, config_account, Config::PAUSE_COMPLETE)?;
 
         if escrow_account.owner != program_id {
             return Err(ProgramError::IncorrectProgramId);
@@ -138,21 +981,4584 @@ impl Processor {
 
         // 1. unpack EscrowAccount (check if it's initialized & extract bump)
         let escrow_data = escrow_account.data.borrow();
-        let escrow_instance: EscrowAccount = EscrowAccount::unpack(&**escrow_data)?;
-        std::mem::drop(escrow_data);  // explicitly dropping ref, because we call escrow_account.data.borrow_mut() in close_account()
-        
+        let mut escrow_instance: EscrowAccount = EscrowAccount::unpack(&escrow_data)?;
+        std::mem::drop(escrow_data);
+
         // 2. create `expected_pda` and check the match with provided pda
-        crate::check_provided_pda(
-            payer_account.key,
-            recipient_account.key,
-            escrow_account.key,
-            escrow_instance.bump
+        Self::_check_escrow_pda(payer_account, recipient_account, escrow_account, &escrow_instance)?;
+        Self::_check_escrow_config(config_account, &escrow_instance)?;
+
+        // 3. the holder of the claim (the recipient, or the assignee) pulls the funds
+        let payout_account: &AccountInfo = Self::_next_payout_account(accounts_iter, recipient_account, &escrow_instance)?;
+
+        if !payout_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // USD-denominated, milestone & vesting escrows have their own settlement instructions, shares are settled by `Complete`
+        if escrow_instance.usd_amount != 0 || escrow_instance.has_milestones || escrow_instance.has_assignments || escrow_instance.is_vesting() {
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        // late fees are paid out of the payer's wallet, so a late escrow can only be completed by the payer
+        Self::_check_deadline(&escrow_instance, true)?;
+        Self::_check_unlocked(&escrow_instance)?;
+        Self::_check_not_disputed(&escrow_instance)?;
+        Self::_check_not_quarantined(&escrow_instance)?;
+        Self::_check_not_swapped(&escrow_instance)?;
+        Self::_check_preimage(&escrow_instance, preimage)?;
+        escrow_instance.status.transition(EscrowStatus::Completed)?;
+
+        let config_instance: Option<Config> = Self::_load_config(program_id, config_account)?;
+        let token_accounts: Option<TokenAccounts> = Self::_next_token_accounts(accounts_iter, &escrow_instance)?;
+        Self::_check_peg(accounts_iter, &escrow_instance)?;
+        let (fee_vault_account, fee_system_program_account) = Self::_next_fee_vault(
+            program_id,
+            accounts_iter,
+            config_instance.as_ref(),
+            &[&escrow_instance]
         )?;
+        Self::_check_attestation(accounts_iter, config_instance.as_ref(), &escrow_instance, recipient_account)?;
+        Self::_check_isolated_settlement(accounts_iter, &escrow_instance)?;
 
-        // 3. close `EscrowAccount`
-        let total_amount: u64 = escrow_account.lamports();
+        if token_accounts.is_none() && Self::_is_partial_draw(escrow_account, &escrow_instance, LayoutVersion::Rich)? {
+            return Self::_process_draw(
+                payer_account,
+                payout_account,
+                escrow_account,
+                escrow_instance,
+                fee_system_program_account,
+                fee_vault_account.zip(config_instance.as_ref())
+            );
+        }
+
+        // 4. transfer locked lamports (or tokens) to the claimant & close `EscrowAccount`, returning the rent to the payer
+        let locked_amount: u64 = match token_accounts {
+            Some(token_accounts) => {
+                let locked_amount: u64 = Self::_process_release_vault(
+                    payer_account,
+                    recipient_account,
+                    escrow_account,
+                    &escrow_instance,
+                    &token_accounts,
+                    payout_account.key
+                )?;
+                Self::_process_close_escrow(payer_account, escrow_account, escrow_account.lamports())?;
+                locked_amount
+            },
+            None => {
+                let rent_exemp: u64 = crate::rent::escrow(&Rent::get()?);
+                let locked_amount: u64 = escrow_account.lamports() - rent_exemp;
 
-        Self::_process_close_escrow(payer_account, escrow_account, total_amount)
+                let protocol_fee: u64 = Self::_process_protocol_fee(
+                    payer_account,
+                    escrow_account,
+                    &mut escrow_instance,
+                    fee_vault_account.zip(config_instance.as_ref()),
+                    fee_system_program_account,
+                    locked_amount
+                )?;
+                let payout: u64 = if escrow_instance.payer_pays_fee { locked_amount } else { locked_amount - protocol_fee };
+
+                **payout_account.lamports.borrow_mut() = payout_account.lamports()
+                    .checked_add(payout)
+                    .ok_or(ProgramError::ArithmeticOverflow)?;
+
+                Self::_process_close_escrow(payer_account, escrow_account, rent_exemp)?;
+                payout
+            }
+        };
+
+        EscrowEvent::Completed {
+            id: escrow_instance.id,
+            escrow: *escrow_account.key,
+            callback: escrow_instance.callback,
+            seq: escrow_instance.next_event_seq(),
+            amount: locked_amount
+        }.emit();
+
+        Ok(())
+    }
+
+    fn process_accept_escrow(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let recipient_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let payer_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let escrow_account: &AccountInfo = next_account_info(accounts_iter)?;
+
+        if !recipient_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        if escrow_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        let mut escrow_data = escrow_account.data.borrow_mut();
+        let mut escrow_instance: EscrowAccount = EscrowAccount::unpack(&escrow_data)?;
+
+        // SOL escrows only, `CancelMutual` doesn't split vaults
+        Self::_check_sol_escrow_pda(payer_account, recipient_account, escrow_account, &escrow_instance)?;
+
+        escrow_instance.status = escrow_instance.status.transition(EscrowStatus::Accepted)?;
+        escrow_instance.pack_into_slice(&mut escrow_data);
+
+        Ok(())
+    }
+
+    fn process_cancel_mutual(program_id: &Pubkey, accounts: &[AccountInfo], payer_bps: u16, recipient_bps: u16) -> ProgramResult {
+        if payer_bps as u32 + recipient_bps as u32 != 10_000 {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        let accounts_iter = &mut accounts.iter();
+
+        let payer_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let recipient_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let escrow_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let config_account: &AccountInfo = next_account_info(accounts_iter)?;
+
+        Self::_check_not_paused(program_id, config_account, Config::PAUSE_CLOSE)?;
+
+        if !payer_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        if escrow_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        // 1. unpack EscrowAccount (check if it's initialized & extract bump)
+        let escrow_data = escrow_account.data.borrow();
+        let mut escrow_instance: EscrowAccount = EscrowAccount::unpack(&escrow_data)?;
+        std::mem::drop(escrow_data);
+
+        // 2. create `expected_pda` and check the match with provided pda (SOL escrows only)
+        Self::_check_sol_escrow_pda(payer_account, recipient_account, escrow_account, &escrow_instance)?;
+        Self::_check_escrow_config(config_account, &escrow_instance)?;
+
+        if escrow_instance.has_assignments {
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        Self::_check_not_swapped(&escrow_instance)?;
+        escrow_instance.status.transition(EscrowStatus::Cancelled)?;
+
+        // 3. the other side of the agreement is whoever holds the claim, and gets its share
+        let payout_account: &AccountInfo = Self::_next_payout_account(accounts_iter, recipient_account, &escrow_instance)?;
+
+        if !payout_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        if escrow_instance.has_milestones {
+            let milestones_account: &AccountInfo = next_account_info(accounts_iter)?;
+            Self::_check_milestones_pda(program_id, escrow_account, milestones_account)?;
+            Self::_process_close_escrow(payer_account, milestones_account, milestones_account.lamports())?;
+        }
+
+        // 4. split what's left (unreleased milestones included), the rent goes back to the payer
+        let rent_exemp: u64 = crate::rent::escrow(&Rent::get()?);
+        let locked_amount: u64 = escrow_account.lamports() - rent_exemp;
+        let recipient_amount: u64 = u64::try_from(locked_amount as u128 * recipient_bps as u128 / 10_000)
+            .map_err(|_| ProgramError::ArithmeticOverflow)?;
+
+        **payout_account.lamports.borrow_mut() = payout_account.lamports()
+            .checked_add(recipient_amount)
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+
+        Self::_process_close_escrow(payer_account, escrow_account, escrow_account.lamports() - recipient_amount)?;
+
+        EscrowEvent::Closed {
+            id: escrow_instance.id,
+            escrow: *escrow_account.key,
+            callback: escrow_instance.callback,
+            seq: escrow_instance.next_event_seq()
+        }.emit();
+
+        Ok(())
+    }
+
+    fn process_close_escrow(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let CloseAccounts {
+            payer: payer_account,
+            recipient: recipient_account,
+            escrow: escrow_account,
+            config: config_account
+        } = CloseAccounts::parse(accounts_iter)?;
+
+        Self::_check_not_paused(program_id, config_account, Config::PAUSE_CLOSE)?;
+
+        if !payer_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        if escrow_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        // 1. unpack EscrowAccount (check if it's initialized & extract bump), legacy escrows included
+        let (mut escrow_instance, _layout) = Self::_load_escrow(program_id, payer_account, recipient_account, escrow_account)?;
+
+        // 2. create `expected_pda` and check the match with provided pda
+        Self::_check_escrow_pda(payer_account, recipient_account, escrow_account, &escrow_instance)?;
+        Self::_check_escrow_config(config_account, &escrow_instance)?;
+
+        Self::_check_deadline(&escrow_instance, false)?;
+        Self::_check_not_disputed(&escrow_instance)?;
+        Self::_check_not_quarantined(&escrow_instance)?;
+        Self::_check_not_swapped(&escrow_instance)?;
+
+        // whatever has started vesting is the recipient's, the payer may still release it all with `Complete`
+        if escrow_instance.is_vesting() && Clock::get()?.unix_timestamp >= escrow_instance.vesting_start {
+            msg!("Escrow started vesting, use Complete or CancelMutual");
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        // once accepted, only an expired deadline lets the payer back out alone
+        if escrow_instance.status == EscrowStatus::Accepted && escrow_instance.deadline == 0 {
+            msg!("Escrow was accepted by the recipient, use CancelMutual");
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        escrow_instance.status.transition(EscrowStatus::Cancelled)?;
+
+        // token escrows carry their token accounts right after `config`
+        let token_accounts: Option<TokenAccounts> = Self::_next_token_accounts(accounts_iter, &escrow_instance)?;
+        Self::_check_peg(accounts_iter, &escrow_instance)?;
+
+        // milestone escrows refund whatever hasn't been released yet, their schedule is closed along with them
+        if escrow_instance.has_milestones {
+            let milestones_account: &AccountInfo = next_account_info(accounts_iter)?;
+            Self::_check_milestones_pda(program_id, escrow_account, milestones_account)?;
+            Self::_process_close_escrow(payer_account, milestones_account, milestones_account.lamports())?;
+        }
+
+        // shares lapse with the escrow, their rent goes back to whoever paid it
+        if escrow_instance.has_assignments {
+            let assignments_account: &AccountInfo = next_account_info(accounts_iter)?;
+            let assignments_instance: EscrowAssignments = Self::_check_assignments_pda(program_id, escrow_account, assignments_account)?;
+            Self::_process_repay_advanced_rent(escrow_account, assignments_account, &escrow_instance)?;
+            let rent_receiver_account: &AccountInfo = Self::_assignments_rent_receiver(&escrow_instance, payer_account, recipient_account);
+            Self::_process_close_escrow(rent_receiver_account, assignments_account, assignments_account.lamports())?;
+
+            // team vesting escrows (only closed before vesting starts) carry every beneficiary's `VestingShare` next
+            if escrow_instance.is_team_vesting() {
+                let beneficiaries = std::iter::once(recipient_account.key).chain(assignments_instance.shares.iter().map(|share| &share.assignee));
+
+                for beneficiary_pkey in beneficiaries {
+                    let vesting_share_account: &AccountInfo = next_account_info(accounts_iter)?;
+                    Self::_check_vesting_share_pda(program_id, escrow_account, beneficiary_pkey, vesting_share_account)?;
+                    Self::_process_close_escrow(payer_account, vesting_share_account, vesting_share_account.lamports())?;
+                }
+            }
+        }
+
+        // 3. (optional) archive the escrow before its data is zeroed
+        if let Some((archive_account, system_program_account)) = Self::_next_archive_accounts(program_id, accounts_iter, payer_account, false)? {
+            Self::_process_archive_escrow(
+                program_id,
+                payer_account,
+                escrow_account,
+                archive_account,
+                system_program_account,
+                escrow_instance.created_at,
+                ArchivedEscrow::OUTCOME_CLOSED
+            )?;
+        }
+
+        // 4. refund the tokens (if any) & close `EscrowAccount`
+        if let Some(token_accounts) = token_accounts {
+            Self::_process_release_vault(
+                payer_account,
+                recipient_account,
+                escrow_account,
+                &escrow_instance,
+                &token_accounts,
+                payer_account.key
+            )?;
+        }
+
+        let total_amount: u64 = escrow_account.lamports();
+
+        Self::_process_close_escrow(payer_account, escrow_account, total_amount)?;
+
+        EscrowEvent::Closed {
+            id: escrow_instance.id,
+            escrow: *escrow_account.key,
+            callback: escrow_instance.callback,
+            seq: escrow_instance.next_event_seq()
+        }.emit();
+
+        Ok(())
+    }
+
+    fn process_initialize_from_template(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        amount: u64,
+        template: MilestoneTemplate
+    ) -> ProgramResult {
+        let milestones: Vec<Milestone> = template.schedule(amount, Clock::get()?.unix_timestamp)?;
+
+        Self::_process_initialize_milestones(program_id, accounts, amount, milestones)
+    }
+
+    fn process_initialize_milestones(program_id: &Pubkey, accounts: &[AccountInfo], milestones: Vec<Milestone>) -> ProgramResult {
+        if milestones.is_empty()
+            || milestones.len() > EscrowMilestones::MAX_MILESTONES
+            || milestones.iter().any(|milestone| milestone.amount == 0)
+        {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        let now: i64 = Clock::get()?.unix_timestamp;
+
+        if milestones.iter().any(|milestone| milestone.deadline <= now) {
+            msg!("Milestone deadline is in the past");
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        let amount: u64 = milestones.iter()
+            .try_fold(0u64, |total, milestone| total.checked_add(milestone.amount))
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+
+        Self::_process_initialize_milestones(program_id, accounts, amount, milestones)
+    }
+
+    /// `Initialize` locking `amount`, split into `milestones`: accounts are `Initialize`'s, then the `EscrowMilestones` PDA.
+    fn _process_initialize_milestones(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        amount: u64,
+        milestones: Vec<Milestone>
+    ) -> ProgramResult {
+        // 1. regular SOL escrow, taking the first 6 accounts
+        Self::process_initialize_escrow(program_id, accounts.get(..6).ok_or(ProgramError::NotEnoughAccountKeys)?, amount, EscrowTerms::default(), None)?;
+
+        let payer_account: &AccountInfo = &accounts[0];
+        let escrow_account: &AccountInfo = &accounts[2];
+        let system_program_account: &AccountInfo = &accounts[3];
+        let milestones_account: &AccountInfo = accounts.get(6).ok_or(ProgramError::NotEnoughAccountKeys)?;  // pda
+
+        let (seed1, seed2) = crate::get_milestones_seeds(escrow_account.key);
+        let (expected_pda, bump) = Pubkey::find_program_address(&[seed1, seed2], program_id);
+
+        if &expected_pda != milestones_account.key {
+            return Err(EscrowError::PdaMismatch.into());
+        }
+
+        // 2. create the milestone schedule
+        let space: usize = EscrowMilestones::space(milestones.len());
+        Self::_process_create_pda(
+            payer_account,
+            milestones_account,
+            system_program_account,
+            crate::rent::milestones(&Rent::get()?, milestones.len()),
+            space,
+            program_id,
+            &[seed1, seed2, &[bump]]
+        )?;
+
+        let milestones_instance: EscrowMilestones = EscrowMilestones::new(bump, milestones);
+        milestones_instance.pack_into_slice(&mut milestones_account.data.borrow_mut());
+
+        // 3. mark the escrow, so it can't be released at once through `Complete`
+        let mut escrow_data = escrow_account.data.borrow_mut();
+        let mut escrow_instance: EscrowAccount = EscrowAccount::unpack(&escrow_data)?;
+        escrow_instance.has_milestones = true;
+        escrow_instance.pack_into_slice(&mut escrow_data);
+
+        Ok(())
+    }
+
+    fn process_approve_milestone(program_id: &Pubkey, accounts: &[AccountInfo], index: u8) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let payer_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let recipient_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let escrow_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let config_account: &AccountInfo = next_account_info(accounts_iter)?;
+
+        Self::_check_not_paused(program_id, config_account, Config::PAUSE_COMPLETE)?;
+
+        if !payer_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        if escrow_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        let escrow_data = escrow_account.data.borrow();
+        let mut escrow_instance: EscrowAccount = EscrowAccount::unpack(&escrow_data)?;
+        std::mem::drop(escrow_data);
+
+        Self::_check_sol_escrow_pda(payer_account, recipient_account, escrow_account, &escrow_instance)?;
+        Self::_check_escrow_config(config_account, &escrow_instance)?;
+
+        if !escrow_instance.has_milestones {
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        Self::_check_unlocked(&escrow_instance)?;
+        Self::_check_not_disputed(&escrow_instance)?;
+        Self::_check_not_quarantined(&escrow_instance)?;
+        Self::_check_not_swapped(&escrow_instance)?;
+
+        let payout_account: &AccountInfo = Self::_next_payout_account(accounts_iter, recipient_account, &escrow_instance)?;
+        let milestones_account: &AccountInfo = next_account_info(accounts_iter)?;
+
+        let mut milestones_instance: EscrowMilestones = Self::_check_milestones_pda(program_id, escrow_account, milestones_account)?;
+
+        let config_instance: Option<Config> = Self::_load_config(program_id, config_account)?;
+        let (fee_vault_account, fee_system_program_account) = Self::_next_fee_vault(
+            program_id,
+            accounts_iter,
+            config_instance.as_ref(),
+            &[&escrow_instance]
+        )?;
+
+        let milestone: &mut Milestone = milestones_instance.milestones.get_mut(index as usize)
+            .ok_or(ProgramError::InvalidInstructionData)?;
+
+        if milestone.released {
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        milestone.released = true;
+        let amount: u64 = milestone.amount;
+
+        let protocol_fee: u64 = Self::_process_protocol_fee(
+            payer_account,
+            escrow_account,
+            &mut escrow_instance,
+            fee_vault_account.zip(config_instance.as_ref()),
+            fee_system_program_account,
+            amount
+        )?;
+        let deducted_fee: u64 = if escrow_instance.payer_pays_fee { 0 } else { protocol_fee };
+
+        **escrow_account.lamports.borrow_mut() = escrow_account.lamports()
+            .checked_sub(amount)
+            .ok_or(ProgramError::InsufficientFunds)?;
+        **payout_account.lamports.borrow_mut() = payout_account.lamports()
+            .checked_add(amount - deducted_fee)
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+
+        if !milestones_instance.milestones.iter().all(|milestone| milestone.released) {
+            milestones_instance.pack_into_slice(&mut milestones_account.data.borrow_mut());
+            // keeps the event sequence of a charged fee
+            escrow_instance.pack_into_slice(&mut escrow_account.data.borrow_mut());
+            return Ok(());
+        }
+
+        // last milestone released: only rent is left in both accounts
+        escrow_instance.status.transition(EscrowStatus::Completed)?;
+        Self::_process_close_escrow(payer_account, milestones_account, milestones_account.lamports())?;
+        Self::_process_close_escrow(payer_account, escrow_account, escrow_account.lamports())?;
+
+        EscrowEvent::Completed {
+            id: escrow_instance.id,
+            escrow: *escrow_account.key,
+            callback: escrow_instance.callback,
+            seq: escrow_instance.next_event_seq(),
+            amount: milestones_instance.milestones.iter().map(|milestone| milestone.amount).sum()
+        }.emit();
+
+        Ok(())
+    }
+
+    fn process_initialize_token_escrow(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        amount: u64,
+        nft: bool,
+        reference: Option<[u8; 16]>
+    ) -> ProgramResult {
+        if amount == 0 {
+            return Err(EscrowError::AmountTooSmall.into());
+        }
+
+        let accounts_iter = &mut accounts.iter();
+
+        let payer_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let recipient_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let escrow_account: &AccountInfo = next_account_info(accounts_iter)?;  // pda
+        let mint_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let vault_account: &AccountInfo = next_account_info(accounts_iter)?;  // pda
+        let payer_token_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let token_program_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let system_program_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let counter_account: &AccountInfo = next_account_info(accounts_iter)?;  // pda
+        let config_account: &AccountInfo = next_account_info(accounts_iter)?;  // pda
+        let blocked_mint_account: &AccountInfo = next_account_info(accounts_iter)?;  // pda
+
+        Self::_check_not_paused(program_id, config_account, Config::PAUSE_INITIALIZE)?;
+        Self::_check_feature_enabled(program_id, config_account, Config::FEATURE_TOKENS)?;
+
+        crate::check_mint_not_blocked(mint_account.key, blocked_mint_account)?;
+
+        Self::_check_token_program(token_program_account, mint_account)?;
+
+        // Token-2022 mints may require extensions on every token account (e.g. `TransferFeeAmount`), growing the vault
+        let mint_data = mint_account.data.borrow();
+        let mint_instance: StateWithExtensions<Mint> = StateWithExtensions::<Mint>::unpack(&mint_data)?;
+        let decimals: u8 = mint_instance.base.decimals;
+
+        if nft && (decimals != 0 || mint_instance.base.supply != 1) {
+            msg!("Mint {} is not an NFT", mint_account.key);
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let mint_extensions: Vec<ExtensionType> = mint_instance.get_extension_types()?;
+        let vault_space: usize = crate::rent::vault_space(&mint_extensions)?;
+        std::mem::drop(mint_data);
+
+        let (seed1, seed2, seed3, seed4) = crate::get_token_escrow_seeds(payer_account.key, recipient_account.key, mint_account.key);
+        let (expected_pda, bump) = Pubkey::find_program_address(&[seed1, seed2, seed3, seed4], program_id);
+
+        if &expected_pda != escrow_account.key {
+            return Err(EscrowError::PdaMismatch.into());
+        }
+
+        let (vault_seed1, vault_seed2) = crate::get_vault_seeds(escrow_account.key);
+        let (expected_vault_pda, vault_bump) = Pubkey::find_program_address(&[vault_seed1, vault_seed2], program_id);
+
+        if &expected_vault_pda != vault_account.key {
+            return Err(EscrowError::PdaMismatch.into());
+        }
+
+        let rent: Rent = Rent::get()?;
+
+        // 1. create pda account, it only has to be rent exempt since the tokens live in the vault
+        Self::_process_create_pda(
+            payer_account,
+            escrow_account,
+            system_program_account,
+            crate::rent::escrow(&rent),
+            EscrowAccount::LEN,
+            program_id,
+            &[seed1, seed2, seed3, seed4, &[bump]]
+        )?;
+
+        // 2. create the vault, owned by the escrow pda
+        Self::_process_create_pda(
+            payer_account,
+            vault_account,
+            system_program_account,
+            crate::rent::vault(&rent, &mint_extensions)?,
+            vault_space,
+            token_program_account.key,
+            &[vault_seed1, vault_seed2, &[vault_bump]]
+        )?;
+
+        let init_vault_ix: Instruction = spl_token_2022::instruction::initialize_account3(
+            token_program_account.key,
+            vault_account.key,
+            mint_account.key,
+            escrow_account.key
+        )?;
+        invoke(&init_vault_ix, &[vault_account.clone(), mint_account.clone()])?;
+
+        // 3. lock the tokens
+        let transfer_ix: Instruction = spl_token_2022::instruction::transfer_checked(
+            token_program_account.key,
+            payer_token_account.key,
+            mint_account.key,
+            vault_account.key,
+            payer_account.key,
+            &[],
+            amount,
+            decimals
+        )?;
+        invoke(
+            &transfer_ix,
+            &[
+                payer_token_account.clone(),
+                mint_account.clone(),
+                vault_account.clone(),
+                payer_account.clone()
+            ]
+        )?;
+
+        // 4. take the next escrow id & init pda account
+        let id: u64 = Self::_process_next_escrow_id(
+            program_id,
+            payer_account,
+            counter_account,
+            system_program_account
+        )?;
+
+        let mut escrow_instance: EscrowAccount = EscrowAccount::new(
+            bump,
+            id,
+            Clock::get()?.unix_timestamp,
+            *payer_account.key,
+            *recipient_account.key,
+            amount
+        );
+        escrow_instance.config = *config_account.key;
+        escrow_instance.mint = *mint_account.key;
+        escrow_instance.nft = nft;
+        escrow_instance.reference = reference.unwrap_or_default();
+        // net of Token-2022 transfer fees
+        escrow_instance.token_amount = StateWithExtensions::<TokenAccount>::unpack(&vault_account.data.borrow())?.base.amount;
+        let seq: u64 = escrow_instance.next_event_seq();
+        escrow_instance.pack_into_slice(&mut escrow_account.data.borrow_mut());
+
+        EscrowEvent::Initialized { id, escrow: *escrow_account.key, callback: escrow_instance.callback, seq, amount }.emit();
+
+        Ok(())
+    }
+
+    fn process_register_callback(program_id: &Pubkey, accounts: &[AccountInfo], callback: [u8; 32]) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let payer_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let recipient_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let escrow_account: &AccountInfo = next_account_info(accounts_iter)?;
+
+        if !payer_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        if escrow_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        // 1. unpack EscrowAccount (check if it's initialized & extract bump)
+        let mut escrow_data = escrow_account.data.borrow_mut();
+        let mut escrow_instance: EscrowAccount = EscrowAccount::unpack(&escrow_data)?;
+
+        // 2. create `expected_pda` and check the match with provided pda
+        Self::_check_sol_escrow_pda(payer_account, recipient_account, escrow_account, &escrow_instance)?;
+
+        // 3. store the callback
+        escrow_instance.callback = callback;
+        let seq: u64 = escrow_instance.next_event_seq();
+        escrow_instance.pack_into_slice(&mut escrow_data);
+
+        EscrowEvent::CallbackRegistered { id: escrow_instance.id, escrow: *escrow_account.key, callback, seq }.emit();
+
+        Ok(())
+    }
+
+    fn process_set_usd_terms(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        usd_amount: u64,
+        max_divergence_bps: Option<u16>
+    ) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let payer_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let recipient_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let escrow_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let price_feed_account: &AccountInfo = next_account_info(accounts_iter)?;
+
+        if !payer_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        if escrow_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        // 1. make sure the oracles are readable right now, so a bad feed fails here rather than at settlement
+        let now: i64 = Clock::get()?.unix_timestamp;
+        OraclePrice::load_sol_usd(price_feed_account, now)?;
+
+        let secondary_price_feed: Pubkey = match max_divergence_bps {
+            Some(_) => {
+                let secondary_price_feed_account: &AccountInfo = next_account_info(accounts_iter)?;
+
+                if secondary_price_feed_account.key == price_feed_account.key {
+                    return Err(ProgramError::InvalidArgument);
+                }
+
+                OraclePrice::load_sol_usd(secondary_price_feed_account, now)?;
+                *secondary_price_feed_account.key
+            },
+            None => Pubkey::default()
+        };
+        let config_account: &AccountInfo = next_account_info(accounts_iter)?;
+
+        // 2. unpack EscrowAccount (check if it's initialized & extract bump)
+        let mut escrow_data = escrow_account.data.borrow_mut();
+        let mut escrow_instance: EscrowAccount = EscrowAccount::unpack(&escrow_data)?;
+
+        // 3. create `expected_pda` and check the match with provided pda
+        Self::_check_sol_escrow_pda(payer_account, recipient_account, escrow_account, &escrow_instance)?;
+        Self::_check_escrow_config(config_account, &escrow_instance)?;
+        Self::_check_feature_enabled(program_id, config_account, Config::FEATURE_ORACLES)?;
+
+        // the recipient accepts the escrow knowing what it pays out, so its terms can't be repriced after that
+        if escrow_instance.status != EscrowStatus::Pending {
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        // 4. store the terms
+        escrow_instance.usd_amount = usd_amount;
+        escrow_instance.price_feed = *price_feed_account.key;
+        escrow_instance.secondary_price_feed = secondary_price_feed;
+        escrow_instance.max_divergence_bps = max_divergence_bps.unwrap_or(0);
+        escrow_instance.pack_into_slice(&mut escrow_data);
+
+        Ok(())
+    }
+
+    fn process_set_late_fee(program_id: &Pubkey, accounts: &[AccountInfo], bps_per_day: u16, cap_bps: u16) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let payer_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let recipient_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let escrow_account: &AccountInfo = next_account_info(accounts_iter)?;
+
+        if !payer_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        if escrow_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        if bps_per_day == 0 || cap_bps == 0 || cap_bps > 10_000 {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        // 1. unpack EscrowAccount (check if it's initialized & extract bump)
+        let mut escrow_data = escrow_account.data.borrow_mut();
+        let mut escrow_instance: EscrowAccount = EscrowAccount::unpack(&escrow_data)?;
+
+        // 2. create `expected_pda` and check the match with provided pda (late fees are paid in SOL, so SOL escrows only)
+        Self::_check_sol_escrow_pda(payer_account, recipient_account, escrow_account, &escrow_instance)?;
+
+        // 3. there is nothing to be late for without a deadline, and the payer can't back out of an agreed fee
+        if escrow_instance.deadline == 0 || escrow_instance.late_fee_bps_per_day != 0 {
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        escrow_instance.late_fee_bps_per_day = bps_per_day;
+        escrow_instance.late_fee_cap_bps = cap_bps;
+        escrow_instance.pack_into_slice(&mut escrow_data);
+
+        Ok(())
+    }
+
+    fn process_set_max_payout(program_id: &Pubkey, accounts: &[AccountInfo], max_payout: u64) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let payer_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let recipient_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let escrow_account: &AccountInfo = next_account_info(accounts_iter)?;
+
+        if !payer_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        if escrow_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        if max_payout == 0 {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        let mut escrow_instance: EscrowAccount = EscrowAccount::unpack(&escrow_account.data.borrow())?;
+
+        Self::_check_sol_escrow_pda(payer_account, recipient_account, escrow_account, &escrow_instance)?;
+
+        // shares are paid out (and their PDA closed) in one go, vesting escrows have their own schedule
+        if escrow_instance.status != EscrowStatus::Pending || escrow_instance.has_assignments || escrow_instance.is_vesting() {
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        escrow_instance.max_payout = max_payout;
+        escrow_instance.pack_into_slice(&mut escrow_account.data.borrow_mut());
+
+        Ok(())
+    }
+
+    fn process_deposit(program_id: &Pubkey, accounts: &[AccountInfo], amount: u64) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let DepositAccounts {
+            payer: payer_account,
+            recipient: recipient_account,
+            escrow: escrow_account,
+            system_program: system_program_account
+        } = DepositAccounts::parse(accounts_iter)?;
+
+        if !payer_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        if escrow_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        if amount == 0 {
+            return Err(EscrowError::AmountTooSmall.into());
+        }
+
+        // 1. unpack EscrowAccount (check if it's initialized & extract bump)
+        let mut escrow_instance: EscrowAccount = EscrowAccount::unpack(&escrow_account.data.borrow())?;
+
+        // 2. create `expected_pda` and check the match with provided pda (token escrows are funded through their vault)
+        Self::_check_sol_escrow_pda(payer_account, recipient_account, escrow_account, &escrow_instance)?;
+
+        // 3. the last milestone would hand the top-up back to the payer, and a disputed escrow is up to the arbiter
+        // (a reservation is funded through `Fund`, and a vesting schedule is fixed)
+        if escrow_instance.has_milestones || escrow_instance.status == EscrowStatus::Reserved || escrow_instance.is_vesting() {
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        Self::_check_not_disputed(&escrow_instance)?;
+
+        Self::_check_not_quarantined(&escrow_instance)?;
+
+        // 4. transfer & keep the locked amount in sync
+        invoke(
+            &system_instruction::transfer(payer_account.key, escrow_account.key, amount),
+            &[payer_account.clone(), escrow_account.clone(), system_program_account.clone()]
+        )?;
+
+        escrow_instance.amount = escrow_instance.amount.checked_add(amount).ok_or(ProgramError::ArithmeticOverflow)?;
+        let seq: u64 = escrow_instance.next_event_seq();
+        escrow_instance.pack_into_slice(&mut escrow_account.data.borrow_mut());
+
+        EscrowEvent::Deposited {
+            id: escrow_instance.id,
+            escrow: *escrow_account.key,
+            callback: escrow_instance.callback,
+            seq,
+            amount
+        }.emit();
+
+        Ok(())
+    }
+
+    fn process_open_swap(program_id: &Pubkey, accounts: &[AccountInfo], deadline: i64) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let payer_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let recipient_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let escrow_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let leg_b_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let swap_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let system_program_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let config_account: &AccountInfo = next_account_info(accounts_iter)?;
+
+        if !payer_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        if escrow_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        if deadline <= Clock::get()?.unix_timestamp || leg_b_account.key == escrow_account.key {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        // 1. unpack EscrowAccount (check if it's initialized & extract bump)
+        let mut escrow_instance: EscrowAccount = EscrowAccount::unpack(&escrow_account.data.borrow())?;
+
+        // 2. create `expected_pda` and check the match with provided pda
+        Self::_check_escrow_pda(payer_account, recipient_account, escrow_account, &escrow_instance)?;
+        Self::_check_escrow_config(config_account, &escrow_instance)?;
+        Self::_check_feature_enabled(program_id, config_account, Config::FEATURE_SWAPS)?;
+        Self::_check_swap_leg(&escrow_instance)?;
+
+        // 3. create the `EscrowSwap` & lock the leg
+        let (seed1, seed2) = crate::get_swap_seeds(escrow_account.key);
+        let (expected_pda, bump) = Pubkey::find_program_address(&[seed1, seed2], program_id);
+
+        if &expected_pda != swap_account.key {
+            return Err(EscrowError::PdaMismatch.into());
+        }
+
+        Self::_process_create_pda(
+            payer_account,
+            swap_account,
+            system_program_account,
+            crate::rent::swap(&Rent::get()?),
+            EscrowSwap::LEN,
+            program_id,
+            &[seed1, seed2, &[bump]]
+        )?;
+
+        let swap_instance: EscrowSwap = EscrowSwap::new(bump, *escrow_account.key, *leg_b_account.key, deadline);
+        swap_instance.pack_into_slice(&mut swap_account.data.borrow_mut());
+
+        escrow_instance.swap = *swap_account.key;
+        escrow_instance.pack_into_slice(&mut escrow_account.data.borrow_mut());
+
+        Ok(())
+    }
+
+    fn process_join_swap(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let payer_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let recipient_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let escrow_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let leg_a_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let swap_account: &AccountInfo = next_account_info(accounts_iter)?;
+
+        if !payer_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        if escrow_account.owner != program_id || leg_a_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        // 1. unpack both legs & check the joining one
+        let mut escrow_instance: EscrowAccount = EscrowAccount::unpack(&escrow_account.data.borrow())?;
+        let leg_a_instance: EscrowAccount = EscrowAccount::unpack(&leg_a_account.data.borrow())?;
+
+        Self::_check_escrow_pda(payer_account, recipient_account, escrow_account, &escrow_instance)?;
+        Self::_check_swap_leg(&escrow_instance)?;
+
+        // 2. this is the leg the swap was offered against, going the other way
+        let swap_instance: EscrowSwap = Self::_check_swap_pda(program_id, leg_a_account, swap_account)?;
+
+        if &swap_instance.leg_b != escrow_account.key
+            || &leg_a_instance.swap != swap_account.key
+            || leg_a_instance.payer != escrow_instance.recipient
+            || leg_a_instance.recipient != escrow_instance.payer
+        {
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        escrow_instance.swap = *swap_account.key;
+        escrow_instance.pack_into_slice(&mut escrow_account.data.borrow_mut());
+
+        Ok(())
+    }
+
+    fn process_settle_swap(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let signer_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let payer_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let recipient_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let escrow_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let leg_b_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let swap_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let config_account: &AccountInfo = next_account_info(accounts_iter)?;
+
+        if !signer_account.is_signer || (signer_account.key != payer_account.key && signer_account.key != recipient_account.key) {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        if escrow_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        // 1. unpack leg A & its swap
+        let mut escrow_instance: EscrowAccount = EscrowAccount::unpack(&escrow_account.data.borrow())?;
+
+        Self::_check_escrow_pda(payer_account, recipient_account, escrow_account, &escrow_instance)?;
+        Self::_check_escrow_config(config_account, &escrow_instance)?;
+
+        let swap_instance: EscrowSwap = Self::_check_swap_pda(program_id, escrow_account, swap_account)?;
+
+        if &escrow_instance.swap != swap_account.key || &swap_instance.leg_b != leg_b_account.key {
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        // 2. leg B counts once it's locked into this swap (it may not even exist yet)
+        let leg_b_instance: Option<EscrowAccount> = if leg_b_account.owner == program_id {
+            Some(EscrowAccount::unpack(&leg_b_account.data.borrow())?).filter(|leg_b| &leg_b.swap == swap_account.key)
+        } else {
+            None
+        };
+
+        match leg_b_instance {
+            Some(mut leg_b_instance) => {
+                Self::_check_not_paused(program_id, config_account, Config::PAUSE_COMPLETE)?;
+
+                // leg B goes from leg A's recipient to leg A's payer
+                Self::_check_escrow_pda(recipient_account, payer_account, leg_b_account, &leg_b_instance)?;
+                Self::_check_escrow_config(config_account, &leg_b_instance)?;
+
+                for leg in [&escrow_instance, &leg_b_instance] {
+                    Self::_check_swap_leg(leg)?;
+                    leg.status.transition(EscrowStatus::Completed)?;
+                }
+
+                let leg_a_token_accounts: Option<TokenAccounts> = Self::_next_token_accounts(accounts_iter, &escrow_instance)?;
+                let leg_b_token_accounts: Option<TokenAccounts> = Self::_next_token_accounts(accounts_iter, &leg_b_instance)?;
+
+                let config_instance: Option<Config> = Self::_load_config(program_id, config_account)?;
+                let (fee_vault_account, _) = Self::_next_fee_vault(
+                    program_id,
+                    accounts_iter,
+                    config_instance.as_ref(),
+                    &[&escrow_instance, &leg_b_instance]
+                )?;
+
+                // 3. swap: both legs are paid out to their recipients (less the protocol fee) within this instruction, or neither is
+                let leg_a_amount: u64 = Self::_process_settle_swap_leg(
+                    payer_account,
+                    recipient_account,
+                    escrow_account,
+                    &mut escrow_instance,
+                    leg_a_token_accounts,
+                    recipient_account,
+                    fee_vault_account.zip(config_instance.as_ref())
+                )?;
+                let leg_b_amount: u64 = Self::_process_settle_swap_leg(
+                    recipient_account,
+                    payer_account,
+                    leg_b_account,
+                    &mut leg_b_instance,
+                    leg_b_token_accounts,
+                    payer_account,
+                    fee_vault_account.zip(config_instance.as_ref())
+                )?;
+
+                EscrowEvent::Completed {
+                    id: escrow_instance.id,
+                    escrow: *escrow_account.key,
+                    callback: escrow_instance.callback,
+                    seq: escrow_instance.next_event_seq(),
+                    amount: leg_a_amount
+                }.emit();
+                EscrowEvent::Completed {
+                    id: leg_b_instance.id,
+                    escrow: *leg_b_account.key,
+                    callback: leg_b_instance.callback,
+                    seq: leg_b_instance.next_event_seq(),
+                    amount: leg_b_amount
+                }.emit();
+            },
+            None => {
+                Self::_check_not_paused(program_id, config_account, Config::PAUSE_CLOSE)?;
+
+                if Clock::get()?.unix_timestamp < swap_instance.deadline {
+                    msg!("Leg B hasn't joined yet, leg A can only be refunded after the deadline");
+                    return Err(ProgramError::InvalidArgument);
+                }
+
+                escrow_instance.status.transition(EscrowStatus::Cancelled)?;
+
+                // 3. refund leg A, refunds carry no protocol fee
+                let leg_a_token_accounts: Option<TokenAccounts> = Self::_next_token_accounts(accounts_iter, &escrow_instance)?;
+                Self::_process_settle_swap_leg(
+                    payer_account,
+                    recipient_account,
+                    escrow_account,
+                    &mut escrow_instance,
+                    leg_a_token_accounts,
+                    payer_account,
+                    None
+                )?;
+
+                EscrowEvent::Closed {
+                    id: escrow_instance.id,
+                    escrow: *escrow_account.key,
+                    callback: escrow_instance.callback,
+                    seq: escrow_instance.next_event_seq()
+                }.emit();
+            }
+        };
+
+        // 4. the swap is over either way, its rent goes back to whoever offered it
+        Self::_process_close_escrow(payer_account, swap_account, swap_account.lamports())?;
+
+        Ok(())
+    }
+
+    fn process_settle_mirrored_swap(program_id: &Pubkey, accounts: &[AccountInfo], amount_a: u64, amount_b: u64) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let party_a_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let party_b_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let leg_a_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let leg_b_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let config_account: &AccountInfo = next_account_info(accounts_iter)?;
+
+        Self::_check_not_paused(program_id, config_account, Config::PAUSE_COMPLETE)?;
+        Self::_check_feature_enabled(program_id, config_account, Config::FEATURE_SWAPS)?;
+
+        // each party releases their own leg
+        if !party_a_account.is_signer || !party_b_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        if leg_a_account.owner != program_id || leg_b_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        // 1. both legs exist & mirror each other: A→B and B→A
+        let mut leg_a_instance: EscrowAccount = EscrowAccount::unpack(&leg_a_account.data.borrow())?;
+        let mut leg_b_instance: EscrowAccount = EscrowAccount::unpack(&leg_b_account.data.borrow())?;
+
+        Self::_check_escrow_pda(party_a_account, party_b_account, leg_a_account, &leg_a_instance)?;
+        Self::_check_escrow_pda(party_b_account, party_a_account, leg_b_account, &leg_b_instance)?;
+
+        // 2. and still lock what both parties signed for
+        for (leg, expected_amount) in [(&leg_a_instance, amount_a), (&leg_b_instance, amount_b)] {
+            Self::_check_escrow_config(config_account, leg)?;
+            Self::_check_not_swapped(leg)?;
+            Self::_check_swap_leg(leg)?;
+            leg.status.transition(EscrowStatus::Completed)?;
+
+            let locked_amount: u64 = if leg.is_token_escrow() { leg.token_amount } else { leg.amount };
+
+            if locked_amount != expected_amount {
+                msg!("Swap terms don't match: {} locked, {} expected", locked_amount, expected_amount);
+                return Err(ProgramError::InvalidArgument);
+            }
+        }
+
+        let leg_a_token_accounts: Option<TokenAccounts> = Self::_next_token_accounts(accounts_iter, &leg_a_instance)?;
+        let leg_b_token_accounts: Option<TokenAccounts> = Self::_next_token_accounts(accounts_iter, &leg_b_instance)?;
+
+        let config_instance: Option<Config> = Self::_load_config(program_id, config_account)?;
+        let (fee_vault_account, _) = Self::_next_fee_vault(
+            program_id,
+            accounts_iter,
+            config_instance.as_ref(),
+            &[&leg_a_instance, &leg_b_instance]
+        )?;
+
+        // 3. both legs are paid out to their recipients (less the protocol fee) within this instruction, or neither is
+        let leg_a_amount: u64 = Self::_process_settle_swap_leg(
+            party_a_account,
+            party_b_account,
+            leg_a_account,
+            &mut leg_a_instance,
+            leg_a_token_accounts,
+            party_b_account,
+            fee_vault_account.zip(config_instance.as_ref())
+        )?;
+        let leg_b_amount: u64 = Self::_process_settle_swap_leg(
+            party_b_account,
+            party_a_account,
+            leg_b_account,
+            &mut leg_b_instance,
+            leg_b_token_accounts,
+            party_a_account,
+            fee_vault_account.zip(config_instance.as_ref())
+        )?;
+
+        EscrowEvent::Completed {
+            id: leg_a_instance.id,
+            escrow: *leg_a_account.key,
+            callback: leg_a_instance.callback,
+            seq: leg_a_instance.next_event_seq(),
+            amount: leg_a_amount
+        }.emit();
+        EscrowEvent::Completed {
+            id: leg_b_instance.id,
+            escrow: *leg_b_account.key,
+            callback: leg_b_instance.callback,
+            seq: leg_b_instance.next_event_seq(),
+            amount: leg_b_amount
+        }.emit();
+
+        Ok(())
+    }
+
+    fn process_set_governance_veto(program_id: &Pubkey, accounts: &[AccountInfo], veto_until: i64) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let payer_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let recipient_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let escrow_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let realm_account: &AccountInfo = next_account_info(accounts_iter)?;
+
+        if !payer_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        if escrow_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        let mut escrow_instance: EscrowAccount = EscrowAccount::unpack(&escrow_account.data.borrow())?;
+
+        Self::_check_sol_escrow_pda(payer_account, recipient_account, escrow_account, &escrow_instance)?;
+        Self::_check_not_swapped(&escrow_instance)?;
+
+        // the recipient accepts the escrow knowing it can be recalled, and a recall refunds it in one go
+        if escrow_instance.status != EscrowStatus::Pending
+            || escrow_instance.veto_realm != Pubkey::default()
+            || escrow_instance.has_milestones
+            || escrow_instance.has_assignments
+        {
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        if veto_until <= Clock::get()?.unix_timestamp {
+            msg!("Veto window would already be over");
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        governance::check_realm(realm_account)?;
+
+        escrow_instance.veto_realm = *realm_account.key;
+        escrow_instance.veto_until = veto_until;
+        escrow_instance.pack_into_slice(&mut escrow_account.data.borrow_mut());
+
+        Ok(())
+    }
+
+    fn process_governance_veto(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let governance_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let proposal_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let payer_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let recipient_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let escrow_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let config_account: &AccountInfo = next_account_info(accounts_iter)?;
+
+        Self::_check_not_paused(program_id, config_account, Config::PAUSE_CLOSE)?;
+
+        // only a proposal's transaction can sign for its governance
+        if !governance_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        if escrow_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        let mut escrow_instance: EscrowAccount = EscrowAccount::unpack(&escrow_account.data.borrow())?;
+
+        Self::_check_sol_escrow_pda(payer_account, recipient_account, escrow_account, &escrow_instance)?;
+        Self::_check_escrow_config(config_account, &escrow_instance)?;
+        Self::_check_not_swapped(&escrow_instance)?;
+
+        if escrow_instance.veto_realm == Pubkey::default() || Clock::get()?.unix_timestamp >= escrow_instance.veto_until {
+            msg!("Escrow can't be vetoed (anymore)");
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        // 1. the governance belongs to the escrow's realm, and the proposal to that governance & passed
+        if governance::governance_realm(governance_account)? != escrow_instance.veto_realm {
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        let proposal: Proposal = Proposal::load(proposal_account)?;
+
+        if &proposal.governance != governance_account.key || !proposal.is_passed() {
+            msg!("Proposal didn't pass");
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        escrow_instance.status.transition(EscrowStatus::Cancelled)?;
+
+        // 2. refund whatever is left (a vesting escrow may have been partly withdrawn already)
+        Self::_process_close_escrow(payer_account, escrow_account, escrow_account.lamports())?;
+
+        EscrowEvent::Closed {
+            id: escrow_instance.id,
+            escrow: *escrow_account.key,
+            callback: escrow_instance.callback,
+            seq: escrow_instance.next_event_seq()
+        }.emit();
+
+        Ok(())
+    }
+
+    fn process_require_attestation(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let payer_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let recipient_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let escrow_account: &AccountInfo = next_account_info(accounts_iter)?;
+
+        if !payer_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        if escrow_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        let mut escrow_instance: EscrowAccount = EscrowAccount::unpack(&escrow_account.data.borrow())?;
+
+        Self::_check_escrow_pda(payer_account, recipient_account, escrow_account, &escrow_instance)?;
+
+        // the recipient accepts the escrow knowing what it takes to get paid
+        if escrow_instance.status != EscrowStatus::Pending {
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        escrow_instance.attestation_required = true;
+        escrow_instance.pack_into_slice(&mut escrow_account.data.borrow_mut());
+
+        Ok(())
+    }
+
+    fn process_require_isolated_settlement(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let payer_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let recipient_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let escrow_account: &AccountInfo = next_account_info(accounts_iter)?;
+
+        if !payer_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        if escrow_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        let mut escrow_instance: EscrowAccount = EscrowAccount::unpack(&escrow_account.data.borrow())?;
+
+        Self::_check_escrow_pda(payer_account, recipient_account, escrow_account, &escrow_instance)?;
+
+        // the recipient accepts the escrow knowing how it has to be settled
+        if escrow_instance.status != EscrowStatus::Pending {
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        escrow_instance.isolated_settlement = true;
+        escrow_instance.pack_into_slice(&mut escrow_account.data.borrow_mut());
+
+        Ok(())
+    }
+
+    fn process_set_unlock_at(program_id: &Pubkey, accounts: &[AccountInfo], unlock_at: i64) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let payer_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let recipient_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let escrow_account: &AccountInfo = next_account_info(accounts_iter)?;
+
+        if !payer_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        if escrow_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        // 1. unpack EscrowAccount (check if it's initialized & extract bump)
+        let mut escrow_data = escrow_account.data.borrow_mut();
+        let mut escrow_instance: EscrowAccount = EscrowAccount::unpack(&escrow_data)?;
+
+        // 2. create `expected_pda` and check the match with provided pda
+        Self::_check_escrow_pda(payer_account, recipient_account, escrow_account, &escrow_instance)?;
+
+        // 3. the lock can't be lifted once set, and must leave time to complete before the deadline
+        if escrow_instance.unlock_at != 0 || unlock_at <= 0 {
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        if escrow_instance.deadline != 0 && unlock_at >= escrow_instance.deadline {
+            msg!("Unlock time must come before the deadline {}", escrow_instance.deadline);
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        escrow_instance.unlock_at = unlock_at;
+        escrow_instance.pack_into_slice(&mut escrow_data);
+
+        Ok(())
+    }
+
+    fn process_assign_claim(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let holder_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let payer_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let recipient_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let escrow_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let assignee_account: &AccountInfo = next_account_info(accounts_iter)?;
+
+        if !holder_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        if escrow_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        // 1. unpack EscrowAccount (check if it's initialized & extract bump)
+        let mut escrow_data = escrow_account.data.borrow_mut();
+        let mut escrow_instance: EscrowAccount = EscrowAccount::unpack(&escrow_data)?;
+
+        // 2. create `expected_pda` and check the match with provided pda
+        Self::_check_escrow_pda(payer_account, recipient_account, escrow_account, &escrow_instance)?;
+
+        // 3. only the current holder of the claim can sell it, and not after selling shares of it
+        if escrow_instance.has_assignments {
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        let holder: &Pubkey = if escrow_instance.assignee == Pubkey::default() {
+            recipient_account.key
+        } else {
+            &escrow_instance.assignee
+        };
+
+        if holder != holder_account.key {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        if assignee_account.key == holder_account.key || assignee_account.key == &Pubkey::default() {
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        escrow_instance.assignee = *assignee_account.key;
+        // the stake account would be the new holder's, who picks their validator for themselves
+        escrow_instance.stake_vote = Pubkey::default();
+        let seq: u64 = escrow_instance.next_event_seq();
+        escrow_instance.pack_into_slice(&mut escrow_data);
+
+        EscrowEvent::ClaimAssigned {
+            id: escrow_instance.id,
+            escrow: *escrow_account.key,
+            callback: escrow_instance.callback,
+            seq,
+            from: *holder_account.key,
+            assignee: escrow_instance.assignee
+        }.emit();
+
+        Ok(())
+    }
+
+    fn process_assign_claim_share(program_id: &Pubkey, accounts: &[AccountInfo], bps: u16, from_payout: bool) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let recipient_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let payer_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let escrow_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let assignments_account: &AccountInfo = next_account_info(accounts_iter)?;  // pda
+        let assignee_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let system_program_account: &AccountInfo = next_account_info(accounts_iter)?;
+
+        if !recipient_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        if escrow_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        // 1. unpack EscrowAccount (check if it's initialized & extract bump)
+        let escrow_data = escrow_account.data.borrow();
+        let mut escrow_instance: EscrowAccount = EscrowAccount::unpack(&escrow_data)?;
+        std::mem::drop(escrow_data);
+
+        // 2. create `expected_pda` and check the match with provided pda (SOL escrows only)
+        Self::_check_sol_escrow_pda(payer_account, recipient_account, escrow_account, &escrow_instance)?;
+
+        // 3. shares are settled by `Complete`, so escrows settling elsewhere (or sold as a whole) can't have them
+        // (nor can the payer's fixed split be extended)
+        if escrow_instance.assignee != Pubkey::default()
+            || escrow_instance.usd_amount != 0
+            || escrow_instance.has_milestones
+            || escrow_instance.payer_splits
+            || escrow_instance.max_payout != 0
+        {
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        if bps == 0 || assignee_account.key == recipient_account.key {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        let (seed1, seed2) = crate::get_assignments_seeds(escrow_account.key);
+
+        // 4. create the assignments on the first share, grow them afterwards
+        let mut assignments_instance: EscrowAssignments = if escrow_instance.has_assignments {
+            Self::_check_assignments_pda(program_id, escrow_account, assignments_account)?
+        } else {
+            let (expected_pda, bump) = Pubkey::find_program_address(&[seed1, seed2], program_id);
+
+            if &expected_pda != assignments_account.key {
+                return Err(EscrowError::PdaMismatch.into());
+            }
+
+            EscrowAssignments::new(bump)
+        };
+
+        if assignments_instance.shares.len() == EscrowAssignments::MAX_SHARES
+            || assignments_instance.assigned_bps() + bps as u32 > 10_000
+        {
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        assignments_instance.shares.push(ClaimShare { assignee: *assignee_account.key, bps });
+
+        let space: usize = EscrowAssignments::space(assignments_instance.shares.len());
+        let rent: Rent = Rent::get()?;
+
+        // the rent comes off the escrowed amount: the account is set up first (CPIs before lamports are moved by hand)
+        if from_payout {
+            let top_up: u64 = crate::rent::assignments(&rent, assignments_instance.shares.len()).saturating_sub(assignments_account.lamports());
+            let locked_amount: u64 = escrow_account.lamports() - crate::rent::escrow(&rent);
+
+            if top_up >= locked_amount {
+                return Err(ProgramError::InsufficientFunds);
+            }
+
+            if escrow_instance.has_assignments {
+                assignments_account.realloc(space, false)?;
+            } else {
+                let signers_seeds: &[&[u8]] = &[seed1, seed2, &[assignments_instance.bump]];
+                invoke_signed(
+                    &system_instruction::allocate(assignments_account.key, space as u64),
+                    &[assignments_account.clone(), system_program_account.clone()],
+                    &[signers_seeds]
+                )?;
+                invoke_signed(
+                    &system_instruction::assign(assignments_account.key, program_id),
+                    &[assignments_account.clone(), system_program_account.clone()],
+                    &[signers_seeds]
+                )?;
+
+                escrow_instance.has_assignments = true;
+            }
+
+            **escrow_account.lamports.borrow_mut() = escrow_account.lamports() - top_up;
+            **assignments_account.lamports.borrow_mut() = assignments_account.lamports()
+                .checked_add(top_up)
+                .ok_or(ProgramError::ArithmeticOverflow)?;
+
+            escrow_instance.rent_advanced += top_up;
+        } else if escrow_instance.has_assignments {
+            let top_up: u64 = crate::rent::assignments(&rent, assignments_instance.shares.len()).saturating_sub(assignments_account.lamports());
+
+            if top_up > 0 {
+                invoke(
+                    &system_instruction::transfer(recipient_account.key, assignments_account.key, top_up),
+                    &[
+                        recipient_account.clone(),
+                        assignments_account.clone(),
+                        system_program_account.clone()
+                    ]
+                )?;
+            }
+
+            assignments_account.realloc(space, false)?;
+        } else {
+            Self::_process_create_pda(
+                recipient_account,
+                assignments_account,
+                system_program_account,
+                crate::rent::assignments(&rent, assignments_instance.shares.len()),
+                space,
+                program_id,
+                &[seed1, seed2, &[assignments_instance.bump]]
+            )?;
+
+            escrow_instance.has_assignments = true;
+        }
+
+        let seq: u64 = escrow_instance.next_event_seq();
+        escrow_instance.pack_into_slice(&mut escrow_account.data.borrow_mut());
+        assignments_instance.pack_into_slice(&mut assignments_account.data.borrow_mut());
+
+        EscrowEvent::ClaimShareAssigned {
+            id: escrow_instance.id,
+            escrow: *escrow_account.key,
+            callback: escrow_instance.callback,
+            seq,
+            assignee: *assignee_account.key,
+            bps
+        }.emit();
+
+        Ok(())
+    }
+
+    fn process_raise_dispute(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let signer_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let payer_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let recipient_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let escrow_account: &AccountInfo = next_account_info(accounts_iter)?;
+
+        if !signer_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        if signer_account.key != payer_account.key && signer_account.key != recipient_account.key {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        if escrow_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        let mut escrow_data = escrow_account.data.borrow_mut();
+        let mut escrow_instance: EscrowAccount = EscrowAccount::unpack(&escrow_data)?;
+
+        Self::_check_escrow_pda(payer_account, recipient_account, escrow_account, &escrow_instance)?;
+
+        // without an arbiter there is nobody to settle it
+        if escrow_instance.arbiter == Pubkey::default() {
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        Self::_check_not_swapped(&escrow_instance)?;
+
+        escrow_instance.status = escrow_instance.status.transition(EscrowStatus::Disputed)?;
+        let seq: u64 = escrow_instance.next_event_seq();
+        escrow_instance.pack_into_slice(&mut escrow_data);
+
+        EscrowEvent::DisputeRaised {
+            id: escrow_instance.id,
+            escrow: *escrow_account.key,
+            callback: escrow_instance.callback,
+            seq,
+            by: *signer_account.key
+        }.emit();
+
+        Ok(())
+    }
+
+    fn process_resolve_dispute(program_id: &Pubkey, accounts: &[AccountInfo], payer_bps: u16, recipient_bps: u16) -> ProgramResult {
+        if payer_bps as u32 + recipient_bps as u32 != 10_000 {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        let accounts_iter = &mut accounts.iter();
+
+        let arbiter_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let payer_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let recipient_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let escrow_account: &AccountInfo = next_account_info(accounts_iter)?;
+
+        if !arbiter_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        if escrow_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        // 1. unpack EscrowAccount (check if it's initialized & extract bump)
+        let escrow_data = escrow_account.data.borrow();
+        let mut escrow_instance: EscrowAccount = EscrowAccount::unpack(&escrow_data)?;
+        std::mem::drop(escrow_data);
+
+        // 2. create `expected_pda` and check the match with provided pda (SOL escrows only)
+        Self::_check_sol_escrow_pda(payer_account, recipient_account, escrow_account, &escrow_instance)?;
+
+        if &escrow_instance.arbiter != arbiter_account.key {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        if escrow_instance.status != EscrowStatus::Disputed {
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        escrow_instance.status.transition(EscrowStatus::Completed)?;
+
+        // 3. the recipient's part is paid out like in `Complete`, to the assignees if the claim was sold
+        let payout_account: &AccountInfo = Self::_next_payout_account(accounts_iter, recipient_account, &escrow_instance)?;
+        let claim_shares: Option<ClaimShareAccounts> = Self::_next_claim_shares(program_id, accounts_iter, escrow_account, &escrow_instance)?;
+
+        if escrow_instance.has_milestones {
+            let milestones_account: &AccountInfo = next_account_info(accounts_iter)?;
+            Self::_check_milestones_pda(program_id, escrow_account, milestones_account)?;
+            Self::_process_close_escrow(payer_account, milestones_account, milestones_account.lamports())?;
+        }
+
+        // 4. split what's left, the rent goes back to the payer
+        let rent_exemp: u64 = crate::rent::escrow(&Rent::get()?);
+        let locked_amount: u64 = escrow_account.lamports() - rent_exemp;
+        let recipient_amount: u64 = u64::try_from(locked_amount as u128 * recipient_bps as u128 / 10_000)
+            .map_err(|_| ProgramError::ArithmeticOverflow)?;
+
+        **escrow_account.lamports.borrow_mut() = escrow_account.lamports() - recipient_amount;
+        let rent_receiver_account: &AccountInfo = Self::_assignments_rent_receiver(&escrow_instance, payer_account, recipient_account);
+        Self::_process_payout(rent_receiver_account, payout_account, claim_shares, recipient_amount)?;
+        Self::_process_close_escrow(payer_account, escrow_account, escrow_account.lamports())?;
+
+        EscrowEvent::DisputeResolved {
+            id: escrow_instance.id,
+            escrow: *escrow_account.key,
+            callback: escrow_instance.callback,
+            seq: escrow_instance.next_event_seq(),
+            payer_amount: locked_amount - recipient_amount,
+            recipient_amount
+        }.emit();
+
+        Ok(())
+    }
+
+    fn process_set_depeg_guard(program_id: &Pubkey, accounts: &[AccountInfo], max_depeg_bps: u16) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let payer_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let recipient_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let escrow_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let price_feed_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let config_account: &AccountInfo = next_account_info(accounts_iter)?;
+
+        if !payer_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        if escrow_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        // 1. make sure the oracle is readable right now, so a bad feed fails here rather than at settlement
+        OraclePrice::load(price_feed_account, Clock::get()?.unix_timestamp)?;
+
+        // 2. unpack EscrowAccount (check if it's initialized & extract bump)
+        let mut escrow_data = escrow_account.data.borrow_mut();
+        let mut escrow_instance: EscrowAccount = EscrowAccount::unpack(&escrow_data)?;
+
+        // 3. create `expected_pda` and check the match with provided pda
+        Self::_check_escrow_pda(payer_account, recipient_account, escrow_account, &escrow_instance)?;
+        Self::_check_escrow_config(config_account, &escrow_instance)?;
+        Self::_check_feature_enabled(program_id, config_account, Config::FEATURE_ORACLES)?;
+
+        if !escrow_instance.is_token_escrow() || escrow_instance.depeg_price_feed != Pubkey::default() {
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        // 4. store the guard
+        escrow_instance.depeg_price_feed = *price_feed_account.key;
+        escrow_instance.max_depeg_bps = max_depeg_bps;
+        escrow_instance.pack_into_slice(&mut escrow_data);
+
+        Ok(())
+    }
+
+    fn process_override_depeg_freeze(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let payer_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let recipient_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let escrow_account: &AccountInfo = next_account_info(accounts_iter)?;
+
+        if escrow_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        let mut escrow_data = escrow_account.data.borrow_mut();
+        let mut escrow_instance: EscrowAccount = EscrowAccount::unpack(&escrow_data)?;
+
+        Self::_check_escrow_pda(payer_account, recipient_account, escrow_account, &escrow_instance)?;
+
+        // either the arbiter or both parties
+        let arbiter_signed: bool = next_account_info(accounts_iter).is_ok_and(|arbiter_account| {
+            arbiter_account.is_signer
+                && escrow_instance.arbiter != Pubkey::default()
+                && arbiter_account.key == &escrow_instance.arbiter
+        });
+
+        let parties_signed: bool = payer_account.is_signer && recipient_account.is_signer;
+
+        if !arbiter_signed && !parties_signed {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        if escrow_instance.depeg_price_feed == Pubkey::default() {
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        escrow_instance.depeg_override = true;
+        escrow_instance.pack_into_slice(&mut escrow_data);
+
+        Ok(())
+    }
+
+    fn process_reconcile(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let escrow_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let vault_account: &AccountInfo = next_account_info(accounts_iter)?;
+
+        if escrow_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        let mut escrow_data = escrow_account.data.borrow_mut();
+        let mut escrow_instance: EscrowAccount = EscrowAccount::unpack(&escrow_data)?;
+
+        if !escrow_instance.is_token_escrow() {
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        // 1. the vault is derived from the escrow, which is enough to trust it's the escrow's own
+        let (vault_seed1, vault_seed2) = crate::get_vault_seeds(escrow_account.key);
+        let (expected_vault_pda, _bump) = Pubkey::find_program_address(&[vault_seed1, vault_seed2], program_id);
+
+        if &expected_vault_pda != vault_account.key {
+            return Err(EscrowError::PdaMismatch.into());
+        }
+
+        if vault_account.owner != &spl_token::ID && vault_account.owner != &spl_token_2022::ID {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        // 2. compare with the books
+        let actual: u64 = StateWithExtensions::<TokenAccount>::unpack(&vault_account.data.borrow())?.base.amount;
+
+        if actual == escrow_instance.token_amount {
+            return Ok(());
+        }
+
+        // 3. anyone can adopt a surplus (e.g. tokens sent to the vault directly), but a shortfall shrinks what the recipient
+        // is owed, so only the payer or the admin of the escrow's config can write it off
+        if actual < escrow_instance.token_amount {
+            let authority_account: &AccountInfo = next_account_info(accounts_iter)?;
+            let config_account: &AccountInfo = next_account_info(accounts_iter)?;
+
+            Self::_check_escrow_config(config_account, &escrow_instance)?;
+            let is_admin: bool = Self::_load_config(program_id, config_account)?
+                .is_some_and(|config_instance| &config_instance.admin == authority_account.key);
+
+            if !authority_account.is_signer || (authority_account.key != &escrow_instance.payer && !is_admin) {
+                msg!("Only the payer or the admin can write a shortfall off");
+                return Err(ProgramError::MissingRequiredSignature);
+            }
+        }
+
+        EscrowEvent::VaultDiscrepancy {
+            id: escrow_instance.id,
+            escrow: *escrow_account.key,
+            callback: escrow_instance.callback,
+            seq: escrow_instance.next_event_seq(),
+            expected: escrow_instance.token_amount,
+            actual
+        }.emit();
+
+        escrow_instance.token_amount = actual;
+        escrow_instance.pack_into_slice(&mut escrow_data);
+
+        Ok(())
+    }
+
+    fn process_complete_usd_escrow(program_id: &Pubkey, accounts: &[AccountInfo], bounds: SettlementBounds) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let payer_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let recipient_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let escrow_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let config_account: &AccountInfo = next_account_info(accounts_iter)?;
+
+        Self::_check_not_paused(program_id, config_account, Config::PAUSE_COMPLETE)?;
+
+        if !payer_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        if escrow_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        // 1. unpack EscrowAccount (check if it's initialized & extract bump)
+        let escrow_data = escrow_account.data.borrow();
+        let mut escrow_instance: EscrowAccount = EscrowAccount::unpack(&escrow_data)?;
+        std::mem::drop(escrow_data);  // explicitly dropping ref, because we call escrow_account.data.borrow_mut() in close_account()
+
+        // 2. create `expected_pda` and check the match with provided pda
+        Self::_check_sol_escrow_pda(payer_account, recipient_account, escrow_account, &escrow_instance)?;
+        Self::_check_escrow_config(config_account, &escrow_instance)?;
+
+        if escrow_instance.usd_amount == 0
+            || escrow_instance.has_milestones
+            || escrow_instance.has_assignments
+            || escrow_instance.is_hashlocked()
+            || escrow_instance.is_vesting()
+        {
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        Self::_check_deadline(&escrow_instance, true)?;
+        Self::_check_unlocked(&escrow_instance)?;
+        Self::_check_not_disputed(&escrow_instance)?;
+        Self::_check_not_quarantined(&escrow_instance)?;
+        Self::_check_not_swapped(&escrow_instance)?;
+        escrow_instance.status.transition(EscrowStatus::Completed)?;
+
+        let payout_account: &AccountInfo = Self::_next_payout_account(accounts_iter, recipient_account, &escrow_instance)?;
+        let price_feed_account: &AccountInfo = next_account_info(accounts_iter)?;
+
+        if &escrow_instance.price_feed != price_feed_account.key {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        // 3. price the payout (cross-checked against the secondary source, if any) and enforce the caller's slippage bounds
+        let now: i64 = Clock::get()?.unix_timestamp;
+        let oracle_price: OraclePrice = OraclePrice::load_sol_usd(price_feed_account, now)?;
+
+        if escrow_instance.secondary_price_feed != Pubkey::default() {
+            let secondary_price_feed_account: &AccountInfo = next_account_info(accounts_iter)?;
+
+            if &escrow_instance.secondary_price_feed != secondary_price_feed_account.key {
+                return Err(ProgramError::InvalidAccountData);
+            }
+
+            let secondary_price: OraclePrice = OraclePrice::load_sol_usd(secondary_price_feed_account, now)?;
+
+            if oracle_price.divergence_bps(&secondary_price)? > escrow_instance.max_divergence_bps as u64 {
+                msg!("Price sources diverge beyond {} bps", escrow_instance.max_divergence_bps);
+                return Err(ProgramError::InvalidAccountData);
+            }
+        }
+
+        let config_instance: Option<Config> = Self::_load_config(program_id, config_account)?;
+        let (fee_vault_account, fee_system_program_account) = Self::_next_fee_vault(
+            program_id,
+            accounts_iter,
+            config_instance.as_ref(),
+            &[&escrow_instance]
+        )?;
+
+        let settled_amount: u64 = oracle_price.usd_to_lamports(escrow_instance.usd_amount)?;
+
+        bounds.check(settled_amount)?;
+
+        let rent_exemp: u64 = crate::rent::escrow(&Rent::get()?);
+        let locked_amount: u64 = escrow_account.lamports() - rent_exemp;
+
+        if settled_amount > locked_amount {
+            return Err(ProgramError::InsufficientFunds);
+        }
+
+        // 4. pay the recipient (or assignee) less the protocol fee, refund the rest (including rent) to the payer & close `EscrowAccount`
+        let protocol_fee: u64 = Self::_process_protocol_fee(
+            payer_account,
+            escrow_account,
+            &mut escrow_instance,
+            fee_vault_account.zip(config_instance.as_ref()),
+            fee_system_program_account,
+            settled_amount
+        )?;
+        let payout: u64 = if escrow_instance.payer_pays_fee { settled_amount } else { settled_amount - protocol_fee };
+
+        **payout_account.lamports.borrow_mut() = payout_account.lamports()
+            .checked_add(payout)
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+
+        Self::_process_close_escrow(payer_account, escrow_account, escrow_account.lamports() - settled_amount)?;
+
+        EscrowEvent::Completed {
+            id: escrow_instance.id,
+            escrow: *escrow_account.key,
+            callback: escrow_instance.callback,
+            seq: escrow_instance.next_event_seq(),
+            amount: payout
+        }.emit();
+
+        Ok(())
+    }
+
+    fn process_set_display_metadata(
+        program_id: &Pubkey,
+        accounts: &[AccountInfo],
+        label: String,
+        icon_hash: [u8; 32]
+    ) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let payer_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let recipient_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let escrow_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let display_account: &AccountInfo = next_account_info(accounts_iter)?;  // pda
+        let system_program_account: &AccountInfo = next_account_info(accounts_iter)?;
+
+        if !payer_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        if label.len() > EscrowDisplay::MAX_LABEL_LEN {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        if escrow_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        // 1. unpack EscrowAccount (check if it's initialized & extract bump)
+        let escrow_instance: EscrowAccount = EscrowAccount::unpack(&escrow_account.data.borrow())?;
+
+        // 2. create `expected_pda` and check the match with provided pda
+        Self::_check_sol_escrow_pda(payer_account, recipient_account, escrow_account, &escrow_instance)?;
+
+        let (seed1, seed2) = crate::get_display_seeds(escrow_account.key);
+        let (expected_pda, bump) = Pubkey::find_program_address(&[seed1, seed2], program_id);
+
+        if &expected_pda != display_account.key {
+            return Err(EscrowError::PdaMismatch.into());
+        }
+
+        // 3. create the display account on first use
+        if display_account.owner == &system_program::ID {
+            Self::_process_create_pda(
+                payer_account,
+                display_account,
+                system_program_account,
+                crate::rent::display(&Rent::get()?),
+                EscrowDisplay::LEN,
+                program_id,
+                &[seed1, seed2, &[bump]]
+            )?;
+        } else if display_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        // 4. store the hints
+        EscrowDisplay::new(bump, label, icon_hash).pack_into_slice(&mut display_account.data.borrow_mut());
+
+        Ok(())
+    }
+
+    fn process_close_display_metadata(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let payer_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let recipient_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let display_account: &AccountInfo = next_account_info(accounts_iter)?;
+
+        if !payer_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        if display_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        // 1. unpack EscrowDisplay (check if it's initialized & extract bump)
+        let display_instance: EscrowDisplay = EscrowDisplay::unpack(&display_account.data.borrow())?;
+
+        // 2. the escrow itself may already be closed, so re-derive its address from the parties
+        let (seed1, seed2, seed3) = crate::get_escrow_seeds(payer_account.key, recipient_account.key);
+        let (escrow_pda, _bump) = Pubkey::find_program_address(&[seed1, seed2, seed3], program_id);
+
+        let (seed1, seed2) = crate::get_display_seeds(&escrow_pda);
+        let expected_pda: Pubkey = Pubkey::create_program_address(&[seed1, seed2, &[display_instance.bump]], program_id)?;
+
+        if &expected_pda != display_account.key {
+            return Err(EscrowError::PdaMismatch.into());
+        }
+
+        // 3. close `EscrowDisplay`
+        let total_amount: u64 = display_account.lamports();
+
+        Self::_process_close_escrow(payer_account, display_account, total_amount)
+    }
+
+    fn process_initialize_config(program_id: &Pubkey, accounts: &[AccountInfo], namespace: Pubkey) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let admin_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let config_account: &AccountInfo = next_account_info(accounts_iter)?;  // pda
+        let system_program_account: &AccountInfo = next_account_info(accounts_iter)?;
+
+        if !admin_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        // the global config is created by the program's upgrade authority, so no one can front-run it after a (re)deploy.
+        // Namespaced configs stay open to anyone, they only govern the escrows created under them.
+        if namespace == Pubkey::default() {
+            let program_data_account: &AccountInfo = next_account_info(accounts_iter)?;
+            crate::check_upgrade_authority(program_id, program_data_account, admin_account.key)?;
+        }
+
+        if config_account.owner == program_id {
+            return Err(EscrowError::AlreadyInitialized.into());
+        }
+
+        let seeds: Vec<&[u8]> = crate::get_namespace_config_seeds(&namespace);
+        let (expected_pda, bump) = Pubkey::find_program_address(&seeds, program_id);
+
+        if &expected_pda != config_account.key {
+            return Err(EscrowError::PdaMismatch.into());
+        }
+
+        // 1. create pda account
+        Self::_process_create_pda(
+            admin_account,
+            config_account,
+            system_program_account,
+            crate::rent::config(&Rent::get()?),
+            Config::LEN,
+            program_id,
+            &[&seeds[..], &[&[bump]]].concat()
+        )?;
+
+        // 2. init pda account
+        let mut config_instance: Config = Config::new(bump, *admin_account.key);
+        config_instance.namespace = namespace;
+        config_instance.pack_into_slice(&mut config_account.data.borrow_mut());
+
+        Ok(())
+    }
+
+    fn process_block_mint(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let admin_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let config_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let mint_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let blocked_mint_account: &AccountInfo = next_account_info(accounts_iter)?;  // pda
+        let system_program_account: &AccountInfo = next_account_info(accounts_iter)?;
+
+        Self::_check_global_admin(program_id, admin_account, config_account)?;
+
+        if blocked_mint_account.owner == program_id {
+            return Err(EscrowError::AlreadyInitialized.into());
+        }
+
+        let (seed1, seed2) = crate::get_blocked_mint_seeds(mint_account.key);
+        let (expected_pda, bump) = Pubkey::find_program_address(&[seed1, seed2], program_id);
+
+        if &expected_pda != blocked_mint_account.key {
+            return Err(EscrowError::PdaMismatch.into());
+        }
+
+        // 1. create pda account
+        Self::_process_create_pda(
+            admin_account,
+            blocked_mint_account,
+            system_program_account,
+            crate::rent::blocked_mint(&Rent::get()?),
+            BlockedMint::LEN,
+            program_id,
+            &[seed1, seed2, &[bump]]
+        )?;
+
+        // 2. init pda account
+        BlockedMint::new(bump, *mint_account.key).pack_into_slice(&mut blocked_mint_account.data.borrow_mut());
+
+        EscrowEvent::MintBlocked { mint: *mint_account.key }.emit();
+
+        Ok(())
+    }
+
+    fn process_unblock_mint(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let admin_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let config_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let mint_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let blocked_mint_account: &AccountInfo = next_account_info(accounts_iter)?;
+
+        Self::_check_global_admin(program_id, admin_account, config_account)?;
+
+        if blocked_mint_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        let blocked_mint_data = blocked_mint_account.data.borrow();
+        let blocked_mint_instance: BlockedMint = BlockedMint::unpack(&blocked_mint_data)?;
+        std::mem::drop(blocked_mint_data);
+
+        if &blocked_mint_instance.mint != mint_account.key {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let total_amount: u64 = blocked_mint_account.lamports();
+
+        Self::_process_close_escrow(admin_account, blocked_mint_account, total_amount)?;
+
+        EscrowEvent::MintUnblocked { mint: *mint_account.key }.emit();
+
+        Ok(())
+    }
+
+    fn process_set_protocol_fee(program_id: &Pubkey, accounts: &[AccountInfo], fee_bps: u16) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let admin_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let config_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let fee_vault_account: &AccountInfo = next_account_info(accounts_iter)?;  // pda
+        let system_program_account: &AccountInfo = next_account_info(accounts_iter)?;
+
+        let mut config_instance: Config = Self::_check_admin(program_id, admin_account, config_account)?;
+
+        if fee_bps > Config::MAX_FEE_BPS {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        // 1. create the (data-less) fee vault on first use
+        if fee_vault_account.owner == &system_program::ID {
+            let seeds: Vec<&[u8]> = crate::get_namespace_fee_vault_seeds(&config_instance.namespace);
+            let (expected_pda, bump) = Pubkey::find_program_address(&seeds, program_id);
+
+            if &expected_pda != fee_vault_account.key {
+                return Err(EscrowError::PdaMismatch.into());
+            }
+
+            Self::_process_create_pda(
+                admin_account,
+                fee_vault_account,
+                system_program_account,
+                crate::rent::marker(&Rent::get()?),
+                0,
+                program_id,
+                &[&seeds[..], &[&[bump]]].concat()
+            )?;
+        }
+
+        Self::_check_fee_vault_pda(program_id, fee_vault_account, &config_instance)?;
+
+        // 2. update the fee
+        config_instance.fee_bps = fee_bps;
+        config_instance.pack_into_slice(&mut config_account.data.borrow_mut());
+
+        EscrowEvent::ProtocolFeeUpdated { fee_bps }.emit();
+
+        Ok(())
+    }
+
+    fn process_withdraw_fees(program_id: &Pubkey, accounts: &[AccountInfo], amount: u64) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let admin_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let config_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let fee_vault_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let destination_account: &AccountInfo = next_account_info(accounts_iter)?;
+
+        let config_instance: Config = Self::_check_admin(program_id, admin_account, config_account)?;
+        Self::_check_fee_vault_pda(program_id, fee_vault_account, &config_instance)?;
+
+        // the vault keeps its rent
+        let collected: u64 = fee_vault_account.lamports().saturating_sub(crate::rent::marker(&Rent::get()?));
+
+        if amount == 0 || amount > collected {
+            return Err(ProgramError::InsufficientFunds);
+        }
+
+        **fee_vault_account.lamports.borrow_mut() = fee_vault_account.lamports() - amount;
+        **destination_account.lamports.borrow_mut() = destination_account.lamports()
+            .checked_add(amount)
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+
+        Ok(())
+    }
+
+    fn process_set_attestation_issuer(program_id: &Pubkey, accounts: &[AccountInfo], issuer: Pubkey) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let admin_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let config_account: &AccountInfo = next_account_info(accounts_iter)?;
+
+        let mut config_instance: Config = Self::_check_admin(program_id, admin_account, config_account)?;
+
+        config_instance.attestation_issuer = issuer;
+        config_instance.pack_into_slice(&mut config_account.data.borrow_mut());
+
+        Ok(())
+    }
+
+    fn process_update_config(program_id: &Pubkey, accounts: &[AccountInfo], min_amount: u64, max_amount: u64) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let admin_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let config_account: &AccountInfo = next_account_info(accounts_iter)?;
+
+        let mut config_instance: Config = Self::_check_admin(program_id, admin_account, config_account)?;
+
+        if max_amount != 0 && min_amount > max_amount {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        config_instance.min_amount = min_amount;
+        config_instance.max_amount = max_amount;
+        config_instance.pack_into_slice(&mut config_account.data.borrow_mut());
+
+        Ok(())
+    }
+
+    fn process_nominate_admin(program_id: &Pubkey, accounts: &[AccountInfo], admin: Pubkey) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let NominateAdminAccounts {
+            admin: admin_account,
+            config: config_account
+        } = NominateAdminAccounts::parse(accounts_iter)?;
+
+        let mut config_instance: Config = Self::_check_admin(program_id, admin_account, config_account)?;
+
+        config_instance.pending_admin = admin;
+        config_instance.pack_into_slice(&mut config_account.data.borrow_mut());
+
+        Ok(())
+    }
+
+    fn process_accept_admin(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let AcceptAdminAccounts {
+            nominee: nominee_account,
+            config: config_account
+        } = AcceptAdminAccounts::parse(accounts_iter)?;
+
+        if !nominee_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        if config_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        let mut config_instance: Config = Config::unpack(&config_account.data.borrow())?;
+
+        crate::check_provided_config_pda(config_account.key, &config_instance.namespace, config_instance.bump)?;
+
+        if config_instance.pending_admin == Pubkey::default() || &config_instance.pending_admin != nominee_account.key {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        config_instance.admin = config_instance.pending_admin;
+        config_instance.pending_admin = Pubkey::default();
+        config_instance.pack_into_slice(&mut config_account.data.borrow_mut());
+
+        Ok(())
+    }
+
+    fn process_touch(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let TouchAccounts { escrow: escrow_account } = TouchAccounts::parse(accounts_iter)?;
+
+        if escrow_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        let escrow_instance: EscrowAccount = EscrowAccount::unpack(&escrow_account.data.borrow())?;
+
+        EscrowEvent::Touched {
+            id: escrow_instance.id,
+            escrow: *escrow_account.key,
+            callback: escrow_instance.callback,
+            seq: escrow_instance.event_seq,
+            status: escrow_instance.status,
+            amount: escrow_instance.amount,
+            token_amount: escrow_instance.token_amount,
+            withdrawn: escrow_instance.withdrawn,
+            deadline: escrow_instance.deadline
+        }.emit();
+
+        Ok(())
+    }
+
+    fn process_index_deadline(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let IndexDeadlineAccounts {
+            signer: signer_account,
+            escrow: escrow_account,
+            deadline_bucket: bucket_account,
+            system_program: system_program_account
+        } = IndexDeadlineAccounts::parse(accounts_iter)?;
+
+        if !signer_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        if escrow_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        let escrow_instance: EscrowAccount = EscrowAccount::unpack(&escrow_account.data.borrow())?;
+
+        if escrow_instance.deadline == 0 {
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        // 1. the bucket of the deadline's day, if someone created it already
+        let day: i64 = DeadlineBucket::day_of(escrow_instance.deadline);
+        let day_bytes: [u8; 8] = day.to_le_bytes();
+        let (seed1, seed2) = crate::get_deadline_bucket_seeds(&day_bytes);
+        let bucket_exists: bool = bucket_account.owner == program_id;
+
+        let mut bucket_instance: DeadlineBucket = if bucket_exists {
+            let bucket_instance: DeadlineBucket = DeadlineBucket::unpack(&bucket_account.data.borrow())?;
+
+            if &Pubkey::create_program_address(&[seed1, seed2, &[bucket_instance.bump]], program_id)? != bucket_account.key {
+                return Err(EscrowError::PdaMismatch.into());
+            }
+
+            bucket_instance
+        } else {
+            let (expected_pda, bump) = Pubkey::find_program_address(&[seed1, seed2], program_id);
+
+            if &expected_pda != bucket_account.key {
+                return Err(EscrowError::PdaMismatch.into());
+            }
+
+            DeadlineBucket::new(bump, day)
+        };
+
+        if bucket_instance.escrows.contains(escrow_account.key) || bucket_instance.escrows.len() == DeadlineBucket::MAX_ESCROWS {
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        bucket_instance.escrows.push(*escrow_account.key);
+
+        // 2. create or grow it, the signer paying the rent
+        let space: usize = DeadlineBucket::space(bucket_instance.escrows.len());
+        let rent: u64 = crate::rent::deadline_bucket(&Rent::get()?, bucket_instance.escrows.len());
+
+        if bucket_exists {
+            let top_up: u64 = rent.saturating_sub(bucket_account.lamports());
+
+            if top_up > 0 {
+                invoke(
+                    &system_instruction::transfer(signer_account.key, bucket_account.key, top_up),
+                    &[signer_account.clone(), bucket_account.clone(), system_program_account.clone()]
+                )?;
+            }
+
+            bucket_account.realloc(space, false)?;
+        } else {
+            Self::_process_create_pda(
+                signer_account,
+                bucket_account,
+                system_program_account,
+                rent,
+                space,
+                program_id,
+                &[seed1, seed2, &[bucket_instance.bump]]
+            )?;
+        }
+
+        bucket_instance.pack_into_slice(&mut bucket_account.data.borrow_mut());
+
+        Ok(())
+    }
+
+    fn process_prune_deadlines(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let signer_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let bucket_account: &AccountInfo = next_account_info(accounts_iter)?;
+
+        if !signer_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        if bucket_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        let mut bucket_instance: DeadlineBucket = DeadlineBucket::unpack(&bucket_account.data.borrow())?;
+
+        let day_bytes: [u8; 8] = bucket_instance.day.to_le_bytes();
+        let (seed1, seed2) = crate::get_deadline_bucket_seeds(&day_bytes);
+
+        if &Pubkey::create_program_address(&[seed1, seed2, &[bucket_instance.bump]], program_id)? != bucket_account.key {
+            return Err(EscrowError::PdaMismatch.into());
+        }
+
+        // 1. drop the escrows that were settled or moved to another day, anything else is still due that day
+        for escrow_account in accounts_iter {
+            let stale: bool = escrow_account.owner != program_id
+                || EscrowAccount::unpack(&escrow_account.data.borrow())
+                    .map_or(true, |escrow_instance| DeadlineBucket::day_of(escrow_instance.deadline) != bucket_instance.day);
+
+            let index: usize = bucket_instance.escrows.iter()
+                .position(|escrow_pkey| escrow_pkey == escrow_account.key)
+                .ok_or(ProgramError::InvalidArgument)?;
+
+            if !stale {
+                return Err(ProgramError::InvalidArgument);
+            }
+
+            bucket_instance.escrows.swap_remove(index);
+        }
+
+        // 2. hand the freed rent to the signer
+        if bucket_instance.escrows.is_empty() {
+            return Self::_process_close_escrow(signer_account, bucket_account, bucket_account.lamports());
+        }
+
+        let space: usize = DeadlineBucket::space(bucket_instance.escrows.len());
+        let freed: u64 = bucket_account.lamports().saturating_sub(crate::rent::deadline_bucket(&Rent::get()?, bucket_instance.escrows.len()));
+
+        bucket_instance.pack_into_slice(&mut bucket_account.data.borrow_mut());
+        bucket_account.realloc(space, false)?;
+
+        **bucket_account.lamports.borrow_mut() = bucket_account.lamports() - freed;
+        **signer_account.lamports.borrow_mut() = signer_account.lamports()
+            .checked_add(freed)
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+
+        Ok(())
+    }
+
+    fn process_export_state(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let ExportStateAccounts {
+            admin: admin_account,
+            config: config_account,
+            escrow: escrow_account,
+            export: export_account,
+            system_program: system_program_account
+        } = ExportStateAccounts::parse(accounts_iter)?;
+
+        Self::_check_admin(program_id, admin_account, config_account)?;
+
+        if escrow_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        let escrow_instance: EscrowAccount = EscrowAccount::unpack(&escrow_account.data.borrow())?;
+
+        crate::check_provided_pda(&escrow_instance.payer, &escrow_instance.recipient, escrow_account.key, escrow_instance.bump)?;
+
+        if config_account.key != &escrow_instance.config {
+            msg!("Escrow belongs to another namespace");
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        // the escrow's own account is all that's carried over
+        if escrow_instance.is_token_escrow() || escrow_instance.has_milestones || escrow_instance.has_assignments {
+            return Err(ProgramError::InvalidArgument);
+        }
+        Self::_check_not_swapped(&escrow_instance)?;
+
+        // 1. record the hash of the data & the escrowed lamports, the admin paying the rent
+        let (seed1, seed2) = crate::get_export_seeds(escrow_account.key);
+        let (expected_pda, bump) = Pubkey::find_program_address(&[seed1, seed2], program_id);
+
+        if &expected_pda != export_account.key {
+            return Err(EscrowError::PdaMismatch.into());
+        }
+
+        let export_instance: EscrowExport = EscrowExport {
+            is_initialized: true,
+            bump,
+            escrow: *escrow_account.key,
+            lamports: escrow_account.lamports().saturating_sub(Rent::get()?.minimum_balance(escrow_account.data_len())),
+            hash: EscrowExport::hash(escrow_account.key, &escrow_account.data.borrow())
+        };
+
+        Self::_process_create_pda(
+            admin_account,
+            export_account,
+            system_program_account,
+            crate::rent::export(&Rent::get()?),
+            EscrowExport::LEN,
+            program_id,
+            &[seed1, seed2, &[bump]]
+        )?;
+
+        export_instance.pack_into_slice(&mut export_account.data.borrow_mut());
+
+        // 2. close the escrow, the admin carrying its lamports over to the import
+        Self::_process_close_escrow(admin_account, escrow_account, escrow_account.lamports())
+    }
+
+    fn process_import_state(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let ImportStateAccounts {
+            admin: admin_account,
+            config: config_account,
+            export: export_account,
+            import_marker: import_account,
+            escrow: escrow_account,
+            system_program: system_program_account
+        } = ImportStateAccounts::parse(accounts_iter)?;
+
+        Self::_check_admin(program_id, admin_account, config_account)?;
+
+        // 1. the export is the PDA of the program that made it (this one, or the one being migrated from)
+        let export_instance: EscrowExport = EscrowExport::unpack(&export_account.data.borrow())?;
+        let (export_seed1, export_seed2) = crate::get_export_seeds(&export_instance.escrow);
+
+        if &Pubkey::create_program_address(&[export_seed1, export_seed2, &[export_instance.bump]], export_account.owner)? != export_account.key {
+            return Err(EscrowError::PdaMismatch.into());
+        }
+
+        if EscrowExport::hash(&export_instance.escrow, data) != export_instance.hash {
+            msg!("Data doesn't match the export");
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        let mut escrow_instance: EscrowAccount = EscrowAccount::unpack_exported(data)?;
+
+        // 2. mark the export as imported, failing if it already was
+        let (import_seed1, import_seed2) = crate::get_import_seeds(&export_instance.escrow);
+        let (expected_import_pda, import_bump) = Pubkey::find_program_address(&[import_seed1, import_seed2], program_id);
+
+        if &expected_import_pda != import_account.key {
+            return Err(EscrowError::PdaMismatch.into());
+        }
+
+        Self::_process_create_pda(
+            admin_account,
+            import_account,
+            system_program_account,
+            crate::rent::marker(&Rent::get()?),
+            0,
+            program_id,
+            &[import_seed1, import_seed2, &[import_bump]]
+        )?;
+
+        // 3. recreate the escrow at its PDA of this program, the admin locking the exported lamports again
+        let (seed1, seed2, seed3) = crate::get_escrow_seeds(&escrow_instance.payer, &escrow_instance.recipient);
+        let (expected_pda, bump) = Pubkey::find_program_address(&[seed1, seed2, seed3], program_id);
+
+        if &expected_pda != escrow_account.key {
+            return Err(EscrowError::PdaMismatch.into());
+        }
+
+        let lamports: u64 = crate::rent::escrow(&Rent::get()?)
+            .checked_add(export_instance.lamports)
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+
+        Self::_process_create_pda(
+            admin_account,
+            escrow_account,
+            system_program_account,
+            lamports,
+            EscrowAccount::LEN,
+            program_id,
+            &[seed1, seed2, seed3, &[bump]]
+        )?;
+
+        escrow_instance.bump = bump;
+        escrow_instance.config = *config_account.key;
+        escrow_instance.pack_into_slice(&mut escrow_account.data.borrow_mut());
+
+        Ok(())
+    }
+
+    fn process_quarantine(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let QuarantineAccounts { escrow: escrow_account } = QuarantineAccounts::parse(accounts_iter)?;
+
+        if escrow_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        if escrow_account.data.borrow().get(EscrowAccount::STATUS_OFFSET) == Some(&(EscrowStatus::Quarantined as u8)) {
+            return Err(EscrowError::Quarantined.into());
+        }
+
+        // 1. read the escrow past what `unpack()` rejects, then check the balances it can't see
+        let (mut escrow_instance, inconsistency) = EscrowAccount::unpack_inconsistent(&escrow_account.data.borrow())?;
+
+        let underfunded: bool = !escrow_instance.is_token_escrow() && escrow_account.lamports() < crate::rent::escrow(&Rent::get()?);
+
+        let Some(reason) = inconsistency.or(underfunded.then_some(Inconsistency::Balance)) else {
+            msg!("Escrow is consistent");
+            return Err(ProgramError::InvalidArgument);
+        };
+
+        // 2. repack it (with its discriminator) out of the normal flows
+        escrow_instance.status = EscrowStatus::Quarantined;
+
+        EscrowEvent::Quarantined {
+            id: escrow_instance.id,
+            escrow: *escrow_account.key,
+            callback: escrow_instance.callback,
+            seq: escrow_instance.next_event_seq(),
+            reason
+        }.emit();
+
+        escrow_instance.pack_into_slice(&mut escrow_account.data.borrow_mut());
+
+        Ok(())
+    }
+
+    fn process_set_paused(program_id: &Pubkey, accounts: &[AccountInfo], paused: u8) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let admin_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let config_account: &AccountInfo = next_account_info(accounts_iter)?;
+
+        let mut config_instance: Config = Self::_check_admin(program_id, admin_account, config_account)?;
+
+        config_instance.paused = paused;
+        config_instance.pack_into_slice(&mut config_account.data.borrow_mut());
+
+        EscrowEvent::PauseUpdated { paused }.emit();
+
+        Ok(())
+    }
+
+    fn process_set_features(program_id: &Pubkey, accounts: &[AccountInfo], features: u8) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let SetFeaturesAccounts {
+            admin: admin_account,
+            config: config_account
+        } = SetFeaturesAccounts::parse(accounts_iter)?;
+
+        let mut config_instance: Config = Self::_check_admin(program_id, admin_account, config_account)?;
+
+        config_instance.features = features;
+        config_instance.pack_into_slice(&mut config_account.data.borrow_mut());
+
+        EscrowEvent::FeaturesUpdated { features }.emit();
+
+        Ok(())
+    }
+
+    fn process_set_yield_venue(program_id: &Pubkey, accounts: &[AccountInfo], venue: Pubkey) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let SetYieldVenueAccounts {
+            admin: admin_account,
+            config: config_account
+        } = SetYieldVenueAccounts::parse(accounts_iter)?;
+
+        let mut config_instance: Config = Self::_check_admin(program_id, admin_account, config_account)?;
+
+        // the treasury's pool tokens of the current venue would be stranded
+        if config_instance.swept_principal != 0 && venue != config_instance.yield_venue {
+            return Err(EscrowError::PrincipalOutstanding.into());
+        }
+
+        if venue != Pubkey::default() {
+            let pool_account: &AccountInfo = next_account_info(accounts_iter)?;
+
+            if pool_account.key != &venue || !treasury::YIELD_VENUE_PROGRAMS.contains(pool_account.owner) {
+                return Err(ProgramError::IncorrectProgramId);
+            }
+        }
+
+        config_instance.yield_venue = venue;
+        config_instance.pack_into_slice(&mut config_account.data.borrow_mut());
+
+        Ok(())
+    }
+
+    fn process_sweep_fees(program_id: &Pubkey, accounts: &[AccountInfo], amount: u64) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let SweepFeesAccounts {
+            admin: admin_account,
+            config: config_account,
+            fee_vault: fee_vault_account,
+            treasury: treasury_account,
+            pool_tokens: pool_tokens_account,
+            pool_program: pool_program_account,
+            pool: pool_account,
+            withdraw_authority: withdraw_authority_account,
+            reserve_stake: reserve_stake_account,
+            manager_fee: manager_fee_account,
+            pool_mint: pool_mint_account,
+            system_program: system_program_account,
+            token_program: token_program_account
+        } = SweepFeesAccounts::parse(accounts_iter)?;
+
+        let mut config_instance: Config = Self::_check_admin(program_id, admin_account, config_account)?;
+        Self::_check_fee_vault_pda(program_id, fee_vault_account, &config_instance)?;
+        Self::_check_yield_venue(&config_instance, pool_program_account, pool_account)?;
+        let treasury_bump: u8 = Self::_check_treasury(program_id, treasury_account, pool_tokens_account, pool_mint_account, &config_instance)?;
+
+        if system_program_account.key != &system_program::ID {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        // the vault keeps its rent
+        let collected: u64 = fee_vault_account.lamports().saturating_sub(crate::rent::marker(&Rent::get()?));
+
+        if amount == 0 || amount > collected {
+            return Err(ProgramError::InsufficientFunds);
+        }
+
+        // the pool takes deposits through a system transfer, which the program-owned vault can't be the source of
+        **fee_vault_account.lamports.borrow_mut() = fee_vault_account.lamports() - amount;
+        **treasury_account.lamports.borrow_mut() = treasury_account.lamports()
+            .checked_add(amount)
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+
+        let pool: StakePool = Self::_stake_pool(
+            pool_program_account, pool_account, withdraw_authority_account, reserve_stake_account, manager_fee_account, pool_mint_account, token_program_account
+        );
+        let treasury_seeds: Vec<&[u8]> = crate::get_namespace_treasury_seeds(&config_instance.namespace);
+
+        invoke_signed(
+            &treasury::deposit_sol(&pool, treasury_account.key, pool_tokens_account.key, amount),
+            &[
+                pool_account.clone(),
+                withdraw_authority_account.clone(),
+                reserve_stake_account.clone(),
+                treasury_account.clone(),
+                pool_tokens_account.clone(),
+                manager_fee_account.clone(),
+                pool_mint_account.clone(),
+                system_program_account.clone(),
+                token_program_account.clone(),
+                pool_program_account.clone()
+            ],
+            &[&[&treasury_seeds[..], &[&[treasury_bump]]].concat()]
+        )?;
+
+        config_instance.swept_principal = config_instance.swept_principal
+            .checked_add(amount)
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+        config_instance.pack_into_slice(&mut config_account.data.borrow_mut());
+
+        EscrowEvent::TreasurySwept { amount, principal: config_instance.swept_principal }.emit();
+
+        Ok(())
+    }
+
+    fn process_redeem_treasury(program_id: &Pubkey, accounts: &[AccountInfo], pool_tokens: u64) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let RedeemTreasuryAccounts {
+            admin: admin_account,
+            config: config_account,
+            fee_vault: fee_vault_account,
+            treasury: treasury_account,
+            pool_tokens: pool_tokens_account,
+            pool_program: pool_program_account,
+            pool: pool_account,
+            withdraw_authority: withdraw_authority_account,
+            reserve_stake: reserve_stake_account,
+            manager_fee: manager_fee_account,
+            pool_mint: pool_mint_account,
+            clock: clock_account,
+            stake_history: stake_history_account,
+            stake_program: stake_program_account,
+            system_program: system_program_account,
+            token_program: token_program_account
+        } = RedeemTreasuryAccounts::parse(accounts_iter)?;
+
+        let mut config_instance: Config = Self::_check_admin(program_id, admin_account, config_account)?;
+        Self::_check_fee_vault_pda(program_id, fee_vault_account, &config_instance)?;
+        Self::_check_yield_venue(&config_instance, pool_program_account, pool_account)?;
+        let treasury_bump: u8 = Self::_check_treasury(program_id, treasury_account, pool_tokens_account, pool_mint_account, &config_instance)?;
+
+        if system_program_account.key != &system_program::ID {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        if pool_tokens == 0 {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        let pool: StakePool = Self::_stake_pool(
+            pool_program_account, pool_account, withdraw_authority_account, reserve_stake_account, manager_fee_account, pool_mint_account, token_program_account
+        );
+        let treasury_seeds: Vec<&[u8]> = crate::get_namespace_treasury_seeds(&config_instance.namespace);
+        let treasury_bump_seed: [u8; 1] = [treasury_bump];
+        let treasury_signer_seeds: Vec<&[u8]> = [&treasury_seeds[..], &[&treasury_bump_seed]].concat();
+
+        let lamports_before: u64 = treasury_account.lamports();
+
+        invoke_signed(
+            &treasury::withdraw_sol(&pool, treasury_account.key, pool_tokens_account.key, pool_tokens),
+            &[
+                pool_account.clone(),
+                withdraw_authority_account.clone(),
+                treasury_account.clone(),
+                pool_tokens_account.clone(),
+                reserve_stake_account.clone(),
+                manager_fee_account.clone(),
+                pool_mint_account.clone(),
+                clock_account.clone(),
+                stake_history_account.clone(),
+                stake_program_account.clone(),
+                token_program_account.clone(),
+                pool_program_account.clone()
+            ],
+            &[&treasury_signer_seeds]
+        )?;
+
+        let received: u64 = treasury_account.lamports().saturating_sub(lamports_before);
+
+        if received != 0 {
+            invoke_signed(
+                &system_instruction::transfer(treasury_account.key, fee_vault_account.key, received),
+                &[treasury_account.clone(), fee_vault_account.clone(), system_program_account.clone()],
+                &[&treasury_signer_seeds]
+            )?;
+        }
+
+        let exhausted: bool = StateWithExtensions::<TokenAccount>::unpack(&pool_tokens_account.data.borrow())?.base.amount == 0;
+        let (principal, yield_amount, written_off) = config_instance.record_redemption(received, exhausted);
+        config_instance.pack_into_slice(&mut config_account.data.borrow_mut());
+
+        EscrowEvent::TreasuryRedeemed { lamports: received, principal, yield_amount, written_off }.emit();
+
+        Ok(())
+    }
+
+    fn process_acknowledge_receipt(program_id: &Pubkey, accounts: &[AccountInfo], record: u32, lamports: u64, state: &[u8]) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let AcknowledgeReceiptAccounts {
+            recipient: recipient_account,
+            payer: payer_account,
+            escrow: escrow_account,
+            archive: archive_account
+        } = AcknowledgeReceiptAccounts::parse(accounts_iter)?;
+
+        if !recipient_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        if archive_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        let archive_instance: EscrowArchive = EscrowArchive::unpack(&archive_account.data.borrow())?;
+        let (seed1, seed2) = crate::get_archive_seeds(payer_account.key);
+
+        if &Pubkey::create_program_address(&[seed1, seed2, &[archive_instance.bump]], program_id)? != archive_account.key {
+            return Err(EscrowError::PdaMismatch.into());
+        }
+
+        // 1. the record is the payer's completed escrow, archived holding `state`
+        let offset: usize = EscrowArchive::space(record as usize);
+        let mut archive_data = archive_account.data.borrow_mut();
+        let record_data: &mut [u8] = archive_data.get_mut(offset..offset + ArchivedEscrow::LEN).ok_or(ProgramError::InvalidArgument)?;
+        let mut record_instance: ArchivedEscrow = ArchivedEscrow::unpack_from_slice(record_data)?;
+
+        if ArchivedEscrow::state_hash(escrow_account.key, lamports, state) != record_instance.state_hash {
+            msg!("State doesn't match the archived escrow");
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        if record_instance.outcome != ArchivedEscrow::OUTCOME_COMPLETED {
+            msg!("Only completed escrows can be acknowledged");
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        if record_instance.acknowledged_at != 0 {
+            msg!("Receipt already acknowledged");
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        // 2. ...and the signer the one it paid out to: legacy escrows only imply their parties, so the PDA check
+        // holds the signer as their recipient
+        let implied: ImpliedFields = ImpliedFields { program_id, payer: payer_account.key, recipient: recipient_account.key };
+        let (escrow_instance, _layout) = EscrowAccount::load(state, &implied)?;
+
+        let paid_to: &Pubkey = if escrow_instance.assignee != Pubkey::default() { &escrow_instance.assignee } else { &escrow_instance.recipient };
+
+        if paid_to != recipient_account.key || &escrow_instance.payer != payer_account.key {
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        if escrow_instance.is_token_escrow() {
+            crate::check_provided_token_pda(
+                &escrow_instance.payer,
+                &escrow_instance.recipient,
+                &escrow_instance.mint,
+                escrow_account.key,
+                escrow_instance.bump
+            )?;
+        } else {
+            crate::check_provided_pda(&escrow_instance.payer, &escrow_instance.recipient, escrow_account.key, escrow_instance.bump)?;
+        }
+
+        // 3. stamp the record
+        let now: i64 = Clock::get()?.unix_timestamp;
+        record_instance.acknowledged_at = u32::try_from(now).map_err(|_| ProgramError::InvalidAccountData)?;
+        record_instance.pack_into_slice(record_data);
+
+        EscrowEvent::ReceiptAcknowledged {
+            id: escrow_instance.id,
+            escrow: *escrow_account.key,
+            callback: escrow_instance.callback,
+            seq: 0,
+            by: *recipient_account.key,
+            acknowledged_at: now
+        }.emit();
+
+        Ok(())
+    }
+
+    /// Checks that `pool_account` is the `Config`'s yield venue, run by `pool_program_account`.
+    fn process_set_stake_settlement(program_id: &Pubkey, accounts: &[AccountInfo], vote: Pubkey) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let SetStakeSettlementAccounts {
+            holder: holder_account,
+            payer: payer_account,
+            recipient: recipient_account,
+            escrow: escrow_account
+        } = SetStakeSettlementAccounts::parse(accounts_iter)?;
+
+        if !holder_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        if escrow_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        let mut escrow_instance: EscrowAccount = EscrowAccount::unpack(&escrow_account.data.borrow())?;
+
+        Self::_check_escrow_pda(payer_account, recipient_account, escrow_account, &escrow_instance)?;
+        Self::_check_not_quarantined(&escrow_instance)?;
+
+        // only the holder of the claim decides where the payout goes
+        let holder: &Pubkey = if escrow_instance.assignee == Pubkey::default() {
+            recipient_account.key
+        } else {
+            &escrow_instance.assignee
+        };
+
+        if holder != holder_account.key || escrow_instance.resolve_recipient {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        if !matches!(escrow_instance.status, EscrowStatus::Pending | EscrowStatus::Accepted) {
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        // only escrows `Complete` settles in one go, in lamports
+        if escrow_instance.is_token_escrow()
+            || escrow_instance.usd_amount != 0
+            || escrow_instance.has_milestones
+            || escrow_instance.is_team_vesting()
+            || escrow_instance.max_payout != 0
+            || escrow_instance.swap != Pubkey::default()
+        {
+            msg!("Only SOL escrows settled at once can be staked");
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        if vote != Pubkey::default() {
+            let vote_account: &AccountInfo = next_account_info(accounts_iter)?;
+
+            if vote_account.key != &vote {
+                return Err(ProgramError::InvalidArgument);
+            }
+            staking::check_vote_account(vote_account)?;
+        }
+
+        escrow_instance.stake_vote = vote;
+        escrow_instance.pack_into_slice(&mut escrow_account.data.borrow_mut());
+
+        Ok(())
+    }
+
+    fn _check_yield_venue(config_instance: &Config, pool_program_account: &AccountInfo, pool_account: &AccountInfo) -> ProgramResult {
+        if config_instance.yield_venue == Pubkey::default() || pool_account.key != &config_instance.yield_venue {
+            msg!("Stake pool isn't the configured yield venue");
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        treasury::check_venue(pool_account, pool_program_account)
+    }
+
+    /// Checks that `treasury_account` is the treasury PDA of `config_instance`'s namespace and `pool_tokens_account` its
+    /// token account of `pool_mint_account`, returning the treasury's bump.
+    fn _check_treasury(
+        program_id: &Pubkey,
+        treasury_account: &AccountInfo,
+        pool_tokens_account: &AccountInfo,
+        pool_mint_account: &AccountInfo,
+        config_instance: &Config
+    ) -> Result<u8, ProgramError> {
+        let (expected_pda, bump) = Pubkey::find_program_address(&crate::get_namespace_treasury_seeds(&config_instance.namespace), program_id);
+
+        if &expected_pda != treasury_account.key {
+            return Err(EscrowError::PdaMismatch.into());
+        }
+
+        let pool_tokens_data = pool_tokens_account.data.borrow();
+        let pool_tokens_instance: StateWithExtensions<TokenAccount> = StateWithExtensions::<TokenAccount>::unpack(&pool_tokens_data)?;
+
+        if &pool_tokens_instance.base.owner != treasury_account.key || &pool_tokens_instance.base.mint != pool_mint_account.key {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        Ok(bump)
+    }
+
+    fn _stake_pool(
+        pool_program_account: &AccountInfo,
+        pool_account: &AccountInfo,
+        withdraw_authority_account: &AccountInfo,
+        reserve_stake_account: &AccountInfo,
+        manager_fee_account: &AccountInfo,
+        pool_mint_account: &AccountInfo,
+        token_program_account: &AccountInfo
+    ) -> StakePool {
+        StakePool {
+            program: *pool_program_account.key,
+            pool: *pool_account.key,
+            withdraw_authority: *withdraw_authority_account.key,
+            reserve_stake: *reserve_stake_account.key,
+            manager_fee: *manager_fee_account.key,
+            pool_mint: *pool_mint_account.key,
+            token_program: *token_program_account.key
+        }
+    }
+
+    /// Fails if `flag` is paused in the given (global or namespace) `Config`. A global `Config` that wasn't created yet
+    /// pauses nothing, but `config_account` must still be its PDA, so callers can't dodge the check by passing another account.
+    fn _check_not_paused(program_id: &Pubkey, config_account: &AccountInfo, flag: u8) -> ProgramResult {
+        let Some(config_instance) = Self::_load_config(program_id, config_account)? else {
+            return Ok(());
+        };
+
+        if config_instance.is_paused(flag) {
+            msg!("Instruction is paused by the admin");
+            return Err(EscrowError::Paused.into());
+        }
+
+        Ok(())
+    }
+
+    /// Fails if `feature` isn't enabled in the given (global or namespace) `Config`. Like pauses, a global `Config`
+    /// that wasn't created yet restricts nothing.
+    fn _check_feature_enabled(program_id: &Pubkey, config_account: &AccountInfo, feature: u8) -> ProgramResult {
+        let Some(config_instance) = Self::_load_config(program_id, config_account)? else {
+            return Ok(());
+        };
+
+        if !config_instance.is_enabled(feature) {
+            msg!("Instruction family isn't enabled by the admin");
+            return Err(EscrowError::FeatureDisabled.into());
+        }
+
+        Ok(())
+    }
+
+    /// Fails if the `Config` (if any) doesn't let SOL escrows lock `amount`.
+    fn _check_amount_limits(program_id: &Pubkey, config_account: &AccountInfo, amount: u64) -> ProgramResult {
+        match Self::_load_config(program_id, config_account)? {
+            Some(config_instance) => config_instance.check_amount(amount),
+            None => Ok(())
+        }
+    }
+
+    /// Unpacks the global or a namespace's `Config`, `None` if the global one wasn't created yet
+    /// (`config_account` must still be its PDA).
+    fn _load_config(program_id: &Pubkey, config_account: &AccountInfo) -> Result<Option<Config>, ProgramError> {
+        if config_account.owner != program_id {
+            let (expected_pda, _bump) = Pubkey::find_program_address(&[crate::get_config_seeds()], program_id);
+
+            // not created yet, whatever lamports were sent to its address
+            if &expected_pda != config_account.key {
+                return Err(EscrowError::PdaMismatch.into());
+            }
+
+            return Ok(None);
+        }
+
+        let config_instance: Config = Config::unpack(&config_account.data.borrow())?;
+
+        crate::check_provided_config_pda(config_account.key, &config_instance.namespace, config_instance.bump)?;
+
+        Ok(Some(config_instance))
+    }
+
+    /// Checks that `config_account` is the `Config` the escrow was bound to at creation,
+    /// so an escrow can't dodge its namespace's pause flags & fees by being settled under another one.
+    fn _check_escrow_config(config_account: &AccountInfo, escrow_instance: &EscrowAccount) -> ProgramResult {
+        if config_account.key != &escrow_instance.config {
+            msg!("Escrow belongs to another namespace");
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        Ok(())
+    }
+
+    /// Takes the recipient's attestation off `accounts_iter` for escrows requiring one, and checks it was issued
+    /// by `Config::attestation_issuer` for the recipient & hasn't expired.
+    fn _check_attestation(
+        accounts_iter: &mut std::slice::Iter<AccountInfo>,
+        config_instance: Option<&Config>,
+        escrow_instance: &EscrowAccount,
+        recipient_account: &AccountInfo
+    ) -> ProgramResult {
+        if !escrow_instance.attestation_required {
+            return Ok(());
+        }
+
+        let attestation_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let attestation: Attestation = Attestation::load(attestation_account, Clock::get()?.unix_timestamp)?;
+
+        let issuer: Pubkey = config_instance.map(|config_instance| config_instance.attestation_issuer).unwrap_or_default();
+
+        if issuer == Pubkey::default() || attestation.credential != issuer || &attestation.nonce != recipient_account.key {
+            msg!("Recipient lacks a valid attestation");
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        Ok(())
+    }
+
+    /// Takes the Instructions sysvar off `accounts_iter` for escrows requiring an isolated settlement, and checks
+    /// every instruction of the transaction belongs to one of `ISOLATED_SETTLEMENT_PROGRAMS`.
+    fn _check_isolated_settlement(accounts_iter: &mut std::slice::Iter<AccountInfo>, escrow_instance: &EscrowAccount) -> ProgramResult {
+        if !escrow_instance.isolated_settlement {
+            return Ok(());
+        }
+
+        let instructions_sysvar_account: &AccountInfo = next_account_info(accounts_iter)?;
+
+        if !sysvar::instructions::check_id(instructions_sysvar_account.key) {
+            return Err(ProgramError::UnsupportedSysvar);
+        }
+
+        let mut index: usize = 0;
+        while let Ok(instruction) = sysvar::instructions::load_instruction_at_checked(index, instructions_sysvar_account) {
+            if !crate::ISOLATED_SETTLEMENT_PROGRAMS.contains(&instruction.program_id) {
+                msg!("Escrow only settles alongside its own, system, compute budget & memo instructions");
+                return Err(EscrowError::ForeignInstruction.into());
+            }
+            index += 1;
+        }
+
+        Ok(())
+    }
+
+    /// Checks that `fee_vault_account` is the fee vault of `config_instance`'s namespace.
+    fn _check_fee_vault_pda(program_id: &Pubkey, fee_vault_account: &AccountInfo, config_instance: &Config) -> ProgramResult {
+        let (expected_pda, _bump) = Pubkey::find_program_address(&crate::get_namespace_fee_vault_seeds(&config_instance.namespace), program_id);
+
+        if &expected_pda != fee_vault_account.key {
+            return Err(EscrowError::PdaMismatch.into());
+        }
+
+        if fee_vault_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        Ok(())
+    }
+
+    /// While a protocol fee is set, settlements paying out a SOL escrow carry the fee vault, followed by the system program
+    /// when the payer of one of them bears the fee. Returns both, if expected.
+    fn _next_fee_vault<'a, 'b>(
+        program_id: &Pubkey,
+        accounts_iter: &mut std::slice::Iter<'b, AccountInfo<'a>>,
+        config_instance: Option<&Config>,
+        escrow_instances: &[&EscrowAccount]
+    ) -> Result<(Option<&'b AccountInfo<'a>>, Option<&'b AccountInfo<'a>>), ProgramError> {
+        let fee_vault_account: &AccountInfo = match config_instance {
+            Some(config_instance) if config_instance.fee_bps != 0 && escrow_instances.iter().any(|escrow| !escrow.is_token_escrow()) => {
+                let fee_vault_account: &AccountInfo = next_account_info(accounts_iter)?;
+                Self::_check_fee_vault_pda(program_id, fee_vault_account, config_instance)?;
+                fee_vault_account
+            },
+            _ => return Ok((None, None))
+        };
+
+        let system_program_account: Option<&AccountInfo> = if escrow_instances.iter().any(|escrow| !escrow.is_token_escrow() && escrow.payer_pays_fee) {
+            Some(next_account_info(accounts_iter)?)
+        } else {
+            None
+        };
+
+        Ok((Some(fee_vault_account), system_program_account))
+    }
+
+    /// Checks that `admin_account` signed and is the admin stored in the given (global or namespace) `Config`.
+    fn _check_admin(program_id: &Pubkey, admin_account: &AccountInfo, config_account: &AccountInfo) -> Result<Config, ProgramError> {
+        if !admin_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        if config_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        let config_instance: Config = Config::unpack(&config_account.data.borrow())?;
+
+        crate::check_provided_config_pda(config_account.key, &config_instance.namespace, config_instance.bump)?;
+
+        if &config_instance.admin != admin_account.key {
+            return Err(EscrowError::NotAdmin.into());
+        }
+
+        Ok(config_instance)
+    }
+
+    /// `_check_admin()` for settings of the whole deployment rather than of one namespace (the mint blocklist),
+    /// which only the global `Config`'s admin controls: anyone can create a namespace and administer it.
+    fn _check_global_admin(program_id: &Pubkey, admin_account: &AccountInfo, config_account: &AccountInfo) -> Result<Config, ProgramError> {
+        let config_instance: Config = Self::_check_admin(program_id, admin_account, config_account)?;
+
+        if config_instance.namespace != Pubkey::default() {
+            return Err(EscrowError::NotAdmin.into());
+        }
+
+        Ok(config_instance)
+    }
+
+    /// Closes every `(recipient, escrow)` pair that passes the same checks as `Close`, skipping the rest,
+    /// so a single stale or foreign escrow doesn't fail the whole cleanup.
+    fn process_batch_close_escrows(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let [payer_account, config_account, pairs @ ..] = accounts else {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+
+        Self::_check_not_paused(program_id, config_account, Config::PAUSE_CLOSE)?;
+
+        if !payer_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        if pairs.is_empty() || pairs.len() % 2 != 0 {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        }
+
+        let mut closed: usize = 0;
+
+        for pair in pairs.chunks_exact(2) {
+            let (recipient_account, escrow_account) = (&pair[0], &pair[1]);
+
+            // every check in `process_close_escrow()` runs before any lamports are moved, so a failed pair is left untouched
+            let result: ProgramResult = Self::_check_batch_closable(program_id, escrow_account).and_then(|()| Self::process_close_escrow(
+                program_id,
+                &[payer_account.clone(), recipient_account.clone(), escrow_account.clone(), config_account.clone()]
+            ));
+
+            match result {
+                Ok(()) => closed += 1,
+                Err(e) => {
+                    msg!("Skipping escrow {}: {}", escrow_account.key, e);
+                    EscrowEvent::CloseSkipped { escrow: *escrow_account.key, error: u64::from(e) }.emit();
+                }
+            };
+        }
+
+        msg!("Closed {} of {} escrows", closed, pairs.len() / 2);
+
+        Ok(())
+    }
+
+    /// `BatchClose` passes `Close` no accounts past `config`, so it only refunds plain SOL escrows. Token, milestone and
+    /// assigned escrows are turned away up front; anything that isn't an escrow of ours is left to `Close` to reject.
+    fn _check_batch_closable(program_id: &Pubkey, escrow_account: &AccountInfo) -> ProgramResult {
+        if escrow_account.owner != program_id {
+            return Ok(());
+        }
+
+        let Ok(escrow_instance) = EscrowAccount::unpack(&escrow_account.data.borrow()) else {
+            return Ok(());
+        };
+
+        if escrow_instance.is_token_escrow() || escrow_instance.has_milestones || escrow_instance.has_assignments {
+            msg!("Escrow {} takes more accounts than BatchClose passes, use Close", escrow_account.key);
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        Ok(())
+    }
+
+    fn process_batch_settle(program_id: &Pubkey, accounts: &[AccountInfo]) -> ProgramResult {
+        let [relayer_account, config_account, instructions_sysvar_account, rest @ ..] = accounts else {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        };
+
+        if !relayer_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        Self::_check_not_paused(program_id, config_account, Config::PAUSE_COMPLETE)?;
+
+        // while a protocol fee is set, the fee vault shared by every escrow comes last
+        let (triples, fee_vault_account): (&[AccountInfo], Option<&AccountInfo>) = match Self::_load_config(program_id, config_account)? {
+            Some(config_instance) if config_instance.fee_bps != 0 => {
+                let (fee_vault_account, triples) = rest.split_last().ok_or(ProgramError::NotEnoughAccountKeys)?;
+                (triples, Some(fee_vault_account))
+            },
+            _ => (rest, None)
+        };
+
+        if triples.is_empty() || triples.len() % 3 != 0 {
+            return Err(ProgramError::NotEnoughAccountKeys);
+        }
+
+        let authorizations: Vec<(Pubkey, Vec<u8>)> = crate::authorization::verified_messages(instructions_sysvar_account)?;
+
+        for triple in triples.chunks_exact(3) {
+            let (payer_account, recipient_account, escrow_account) = (&triple[0], &triple[1], &triple[2]);
+
+            if escrow_account.owner != program_id {
+                return Err(ProgramError::IncorrectProgramId);
+            }
+
+            let escrow_instance: EscrowAccount = EscrowAccount::unpack(&escrow_account.data.borrow())?;
+
+            // 1. the payer's authorization can't sign transfers out of its wallet, so escrows charging it are settled
+            // through `Complete` only
+            if escrow_instance.late_fee_bps_per_day != 0 || escrow_instance.payer_pays_fee {
+                msg!("Escrow {} charges its payer's wallet on settlement", escrow_account.key);
+                return Err(ProgramError::InvalidArgument);
+            }
+
+            // 2. the payer's verified authorization of this very escrow stands in for its signature
+            let message: [u8; crate::authorization::SETTLEMENT_MESSAGE_LEN] = crate::authorization::settlement_message(escrow_account.key, escrow_instance.id);
+
+            if !authorizations.iter().any(|(signer, signed)| signer == payer_account.key && signed[..] == message[..]) {
+                msg!("Escrow {} lacks its payer's authorization", escrow_account.key);
+                return Err(ProgramError::MissingRequiredSignature);
+            }
+
+            // 3. settle it as a plain `Complete`, which checks the escrow belongs to the payer
+            let mut complete_accounts: Vec<AccountInfo> = vec![
+                payer_account.clone(),
+                recipient_account.clone(),
+                escrow_account.clone(),
+                config_account.clone()
+            ];
+            complete_accounts.extend(fee_vault_account.cloned());
+
+            Self::_process_complete_escrow(program_id, &complete_accounts, None, None, true)?;
+        }
+
+        msg!("Settled {} escrows", triples.len() / 3);
+
+        Ok(())
+    }
+
+    /// Closes an `EscrowNote`, returning its rent to the payer.
+    /// Can be signed by either party, since the note is only useful to them.
+    fn process_close_note(program_id: &Pubkey, accounts: &[AccountInfo], mint: Option<Pubkey>) -> ProgramResult {
+        let accounts_iter = &mut accounts.iter();
+
+        let authority_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let payer_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let recipient_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let note_account: &AccountInfo = next_account_info(accounts_iter)?;
+
+        if !authority_account.is_signer {
+            return Err(ProgramError::MissingRequiredSignature);
+        }
+
+        if authority_account.key != payer_account.key && authority_account.key != recipient_account.key {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        if note_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        // 1. unpack EscrowNote (check if it's initialized & extract bump)
+        let note_data = note_account.data.borrow();
+        let note_instance: EscrowNote = EscrowNote::unpack(&note_data)?;
+        std::mem::drop(note_data);
+
+        // 2. the escrow itself may already be closed, so re-derive its address from the parties (and the mint of token escrows)
+        let escrow_pda: Pubkey = match mint {
+            Some(mint) => {
+                let (seed1, seed2, seed3, seed4) = crate::get_token_escrow_seeds(payer_account.key, recipient_account.key, &mint);
+                Pubkey::find_program_address(&[seed1, seed2, seed3, seed4], program_id).0
+            },
+            None => {
+                let (seed1, seed2, seed3) = crate::get_escrow_seeds(payer_account.key, recipient_account.key);
+                Pubkey::find_program_address(&[seed1, seed2, seed3], program_id).0
+            }
+        };
+
+        crate::check_provided_note_pda(&escrow_pda, note_account.key, note_instance.bump)?;
+
+        // 3. close `EscrowNote`
+        let total_amount: u64 = note_account.lamports();
+
+        Self::_process_close_escrow(payer_account, note_account, total_amount)
+    }
+
+    fn _process_memo_receipt(memo_program_account: &AccountInfo, receipt: &SettlementReceipt) -> ProgramResult {
+        if memo_program_account.key != &crate::MEMO_PROGRAM_ID {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        let memo_ix: Instruction = Instruction::new_with_bytes(
+            crate::MEMO_PROGRAM_ID,
+            receipt.to_json().as_bytes(),
+            vec![]
+        );
+
+        invoke(&memo_ix, std::slice::from_ref(memo_program_account))
+    }
+
+    /// Creates the PDA `pda_account` (signed for with `signer_seeds`) with `space` bytes, owned by `owner`, `funder_account`
+    /// paying its `lamports`. Anyone can send lamports to the address beforehand, which would fail a plain `create_account`
+    /// and lock the PDA out for good, so a funded address is topped up, allocated and assigned instead.
+    fn _process_create_pda<'a>(
+        funder_account: &AccountInfo<'a>,
+        pda_account: &AccountInfo<'a>,
+        system_program_account: &AccountInfo<'a>,
+        lamports: u64,
+        space: usize,
+        owner: &Pubkey,
+        signer_seeds: &[&[u8]]
+    ) -> ProgramResult {
+        if pda_account.lamports() == 0 {
+            return invoke_signed(
+                &system_instruction::create_account(funder_account.key, pda_account.key, lamports, space as u64, owner),
+                &[funder_account.clone(), pda_account.clone(), system_program_account.clone()],
+                &[signer_seeds]
+            );
+        }
+
+        let top_up: u64 = lamports.saturating_sub(pda_account.lamports());
+
+        if top_up > 0 {
+            invoke(
+                &system_instruction::transfer(funder_account.key, pda_account.key, top_up),
+                &[funder_account.clone(), pda_account.clone(), system_program_account.clone()]
+            )?;
+        }
+
+        invoke_signed(
+            &system_instruction::allocate(pda_account.key, space as u64),
+            &[pda_account.clone(), system_program_account.clone()],
+            &[signer_seeds]
+        )?;
+        invoke_signed(
+            &system_instruction::assign(pda_account.key, owner),
+            &[pda_account.clone(), system_program_account.clone()],
+            &[signer_seeds]
+        )
+    }
+
+    /// Returns the next escrow id and bumps `EscrowCounter`, creating the counter on first use.
+    fn _process_next_escrow_id<'a>(
+        program_id: &Pubkey,
+        payer_account: &AccountInfo<'a>,
+        counter_account: &AccountInfo<'a>,
+        system_program_account: &AccountInfo<'a>
+    ) -> Result<u64, ProgramError> {
+        let seed: &[u8] = crate::get_counter_seeds();
+        let (expected_pda, bump) = Pubkey::find_program_address(&[seed], program_id);
+
+        if &expected_pda != counter_account.key {
+            return Err(EscrowError::PdaMismatch.into());
+        }
+
+        if counter_account.owner == &system_program::ID {
+            Self::_process_create_pda(
+                payer_account,
+                counter_account,
+                system_program_account,
+                crate::rent::counter(&Rent::get()?),
+                EscrowCounter::LEN,
+                program_id,
+                &[seed, &[bump]]
+            )?;
+
+            EscrowCounter::new(bump).pack_into_slice(&mut counter_account.data.borrow_mut());
+        }
+
+        if counter_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        let mut counter_data = counter_account.data.borrow_mut();
+        let mut counter_instance: EscrowCounter = EscrowCounter::unpack(&counter_data)?;
+
+        let id: u64 = counter_instance.next_id;
+        counter_instance.next_id = id.checked_add(1).ok_or(ProgramError::ArithmeticOverflow)?;
+        counter_instance.pack_into_slice(&mut counter_data);
+
+        Ok(id)
+    }
+
+    /// Creates the `EscrowNote` PDA of `escrow_account`, funded by the payer and sized to the ciphertext.
+    fn _process_attach_note<'a>(
+        program_id: &Pubkey,
+        payer_account: &AccountInfo<'a>,
+        escrow_account: &AccountInfo<'a>,
+        note_account: &AccountInfo<'a>,
+        system_program_account: &AccountInfo<'a>,
+        ciphertext: Vec<u8>
+    ) -> ProgramResult {
+        if ciphertext.len() > EscrowNote::MAX_CIPHERTEXT_LEN {
+            return Err(ProgramError::InvalidInstructionData);
+        }
+
+        if note_account.owner == program_id {
+            return Err(EscrowError::AlreadyInitialized.into());
+        }
+
+        let (seed1, seed2) = crate::get_note_seeds(escrow_account.key);
+        let (expected_pda, bump) = Pubkey::find_program_address(&[seed1, seed2], program_id);
+
+        if &expected_pda != note_account.key {
+            return Err(EscrowError::PdaMismatch.into());
+        }
+
+        let space: usize = EscrowNote::space(ciphertext.len());
+        Self::_process_create_pda(
+            payer_account,
+            note_account,
+            system_program_account,
+            crate::rent::note(&Rent::get()?, ciphertext.len()),
+            space,
+            program_id,
+            &[seed1, seed2, &[bump]]
+        )?;
+
+        let note_instance: EscrowNote = EscrowNote::new(bump, ciphertext);
+        note_instance.pack_into_slice(&mut note_account.data.borrow_mut());
+
+        Ok(())
+    }
+
+    /// SOL and token escrows are derived from different seeds.
+    /// Reads the escrow in whichever layout it's stored in, the legacy one's parties being the provided ones
+    /// (`_check_escrow_pda()` then holds them against the bump).
+    fn _load_escrow(
+        program_id: &Pubkey,
+        payer_account: &AccountInfo,
+        recipient_account: &AccountInfo,
+        escrow_account: &AccountInfo
+    ) -> Result<(EscrowAccount, LayoutVersion), ProgramError> {
+        let implied: ImpliedFields = ImpliedFields {
+            program_id,
+            payer: payer_account.key,
+            recipient: recipient_account.key
+        };
+
+        EscrowAccount::load(&escrow_account.data.borrow(), &implied)
+    }
+
+    fn _check_escrow_pda(
+        payer_account: &AccountInfo,
+        recipient_account: &AccountInfo,
+        escrow_account: &AccountInfo,
+        escrow_instance: &EscrowAccount
+    ) -> ProgramResult {
+        escrow_instance.check_parties(payer_account.key, recipient_account.key)?;
+
+        if escrow_instance.is_token_escrow() {
+            crate::check_provided_token_pda(
+                payer_account.key,
+                recipient_account.key,
+                &escrow_instance.mint,
+                escrow_account.key,
+                escrow_instance.bump
+            )
+        } else {
+            crate::check_provided_pda(
+                payer_account.key,
+                recipient_account.key,
+                escrow_account.key,
+                escrow_instance.bump
+            )
+        }
+    }
+
+    /// Same as `_check_escrow_pda()`, for instructions only supported by SOL escrows (token escrows fail the seeds check).
+    fn _check_sol_escrow_pda(
+        payer_account: &AccountInfo,
+        recipient_account: &AccountInfo,
+        escrow_account: &AccountInfo,
+        escrow_instance: &EscrowAccount
+    ) -> ProgramResult {
+        escrow_instance.check_parties(payer_account.key, recipient_account.key)?;
+
+        crate::check_provided_pda(
+            payer_account.key,
+            recipient_account.key,
+            escrow_account.key,
+            escrow_instance.bump
+        )
+    }
+
+    /// Escrows with a deadline can only be completed before it and closed after it.
+    fn _check_deadline(escrow_instance: &EscrowAccount, completing: bool) -> ProgramResult {
+        if escrow_instance.deadline == 0 {
+            return Ok(());
+        }
+
+        let expired: bool = Clock::get()?.unix_timestamp >= escrow_instance.deadline;
+
+        if completing == expired {
+            msg!("Escrow deadline: {}", escrow_instance.deadline);
+            return Err(if expired { EscrowError::Expired } else { EscrowError::NotExpired }.into());
+        }
+
+        Ok(())
+    }
+
+    /// Escrows whose claim was assigned pay out to the assignee, passed right after `config`, and so do escrows to a domain
+    /// resolved at settlement to the domain's current owner.
+    fn _next_payout_account<'a, 'b>(
+        accounts_iter: &mut std::slice::Iter<'b, AccountInfo<'a>>,
+        recipient_account: &'b AccountInfo<'a>,
+        escrow_instance: &EscrowAccount
+    ) -> Result<&'b AccountInfo<'a>, ProgramError> {
+        if escrow_instance.resolve_recipient {
+            let owner_account: &AccountInfo = next_account_info(accounts_iter)?;
+
+            if owner_account.key != &NameRecord::load(recipient_account)?.owner {
+                msg!("Not the domain's owner");
+                return Err(ProgramError::InvalidArgument);
+            }
+
+            return Ok(owner_account);
+        }
+
+        if escrow_instance.assignee == Pubkey::default() {
+            return Ok(recipient_account);
+        }
+
+        let assignee_account: &AccountInfo = next_account_info(accounts_iter)?;
+
+        if assignee_account.key != &escrow_instance.assignee {
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        Ok(assignee_account)
+    }
+
+    /// Creates the `EscrowAssignments` PDA of `escrow_account` holding the payer's split of the payout, funded by the payer.
+    fn _process_create_splits<'a>(
+        program_id: &Pubkey,
+        payer_account: &AccountInfo<'a>,
+        escrow_account: &AccountInfo<'a>,
+        assignments_account: &AccountInfo<'a>,
+        system_program_account: &AccountInfo<'a>,
+        splits: Vec<ClaimShare>
+    ) -> ProgramResult {
+        let (seed1, seed2) = crate::get_assignments_seeds(escrow_account.key);
+        let (expected_pda, bump) = Pubkey::find_program_address(&[seed1, seed2], program_id);
+
+        if &expected_pda != assignments_account.key {
+            return Err(EscrowError::PdaMismatch.into());
+        }
+
+        let space: usize = EscrowAssignments::space(splits.len());
+        Self::_process_create_pda(
+            payer_account,
+            assignments_account,
+            system_program_account,
+            crate::rent::assignments(&Rent::get()?, splits.len()),
+            space,
+            program_id,
+            &[seed1, seed2, &[bump]]
+        )?;
+
+        let mut assignments_instance: EscrowAssignments = EscrowAssignments::new(bump);
+        assignments_instance.shares = splits;
+        assignments_instance.pack_into_slice(&mut assignments_account.data.borrow_mut());
+
+        Ok(())
+    }
+
+    /// Creates the `VestingShare` of `beneficiary_pkey` in a team vesting escrow, paid by the payer.
+    fn _process_create_vesting_share<'a>(
+        program_id: &Pubkey,
+        payer_account: &AccountInfo<'a>,
+        escrow_account: &AccountInfo<'a>,
+        vesting_share_account: &AccountInfo<'a>,
+        system_program_account: &AccountInfo<'a>,
+        beneficiary_pkey: &Pubkey,
+        allocation: u64
+    ) -> ProgramResult {
+        let (seed1, seed2, seed3) = crate::get_vesting_share_seeds(escrow_account.key, beneficiary_pkey);
+        let (expected_pda, bump) = Pubkey::find_program_address(&[seed1, seed2, seed3], program_id);
+
+        if &expected_pda != vesting_share_account.key {
+            return Err(EscrowError::PdaMismatch.into());
+        }
+
+        Self::_process_create_pda(
+            payer_account,
+            vesting_share_account,
+            system_program_account,
+            crate::rent::vesting_share(&Rent::get()?),
+            VestingShare::LEN,
+            program_id,
+            &[seed1, seed2, seed3, &[bump]]
+        )?;
+
+        let share_instance: VestingShare = VestingShare {
+            is_initialized: true,
+            bump,
+            escrow: *escrow_account.key,
+            beneficiary: *beneficiary_pkey,
+            allocation,
+            withdrawn: 0
+        };
+        share_instance.pack_into_slice(&mut vesting_share_account.data.borrow_mut());
+
+        Ok(())
+    }
+
+    /// Whoever paid the `EscrowAssignments` rent, and gets it back once the escrow is settled.
+    fn _assignments_rent_receiver<'c, 'a>(
+        escrow_instance: &EscrowAccount,
+        payer_account: &'c AccountInfo<'a>,
+        recipient_account: &'c AccountInfo<'a>
+    ) -> &'c AccountInfo<'a> {
+        if escrow_instance.payer_splits { payer_account } else { recipient_account }
+    }
+
+    /// Whether a SOL escrow holds more than its `max_payout`, so releasing it takes more than one draw.
+    fn _is_partial_draw(escrow_account: &AccountInfo, escrow_instance: &EscrowAccount, layout: LayoutVersion) -> Result<bool, ProgramError> {
+        if escrow_instance.max_payout == 0 {
+            return Ok(false);
+        }
+
+        let locked_amount: u64 = escrow_account.lamports()
+            .checked_sub(crate::rent::escrow_in(&Rent::get()?, layout))
+            .ok_or(ProgramError::InsufficientFunds)?;
+
+        Ok(locked_amount > escrow_instance.max_payout)
+    }
+
+    /// Releases `max_payout` lamports of a capped escrow to `payout_account`, charging the late fee (out of the payer's
+    /// wallet) and the protocol fee (out of the draw) on that amount, and leaves the escrow open.
+    fn _process_draw<'a>(
+        payer_account: &AccountInfo<'a>,
+        payout_account: &AccountInfo<'a>,
+        escrow_account: &AccountInfo<'a>,
+        mut escrow_instance: EscrowAccount,
+        late_fee_system_program_account: Option<&AccountInfo<'a>>,
+        fee_vault: Option<(&AccountInfo<'a>, &Config)>
+    ) -> ProgramResult {
+        let amount: u64 = escrow_instance.max_payout;
+        let late_fee: u64 = escrow_instance.late_fee(amount, Clock::get()?.unix_timestamp)?;
+
+        // CPI first, lamports are moved by hand below
+        if late_fee != 0 {
+            let system_program_account: &AccountInfo = late_fee_system_program_account.ok_or(ProgramError::NotEnoughAccountKeys)?;
+            invoke(
+                &system_instruction::transfer(payer_account.key, payout_account.key, late_fee),
+                &[
+                    payer_account.clone(),
+                    payout_account.clone(),
+                    system_program_account.clone()
+                ]
+            )?;
+        }
+
+        let protocol_fee: u64 = Self::_process_protocol_fee(
+            payer_account,
+            escrow_account,
+            &mut escrow_instance,
+            fee_vault,
+            late_fee_system_program_account,
+            amount
+        )?;
+        let deducted_fee: u64 = if escrow_instance.payer_pays_fee { 0 } else { protocol_fee };
+
+        **escrow_account.lamports.borrow_mut() = escrow_account.lamports()
+            .checked_sub(amount)
+            .ok_or(ProgramError::InsufficientFunds)?;
+        **payout_account.lamports.borrow_mut() = payout_account.lamports()
+            .checked_add(amount - deducted_fee)
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+
+        escrow_instance.withdrawn = escrow_instance.withdrawn
+            .checked_add(amount)
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+        let seq: u64 = escrow_instance.next_event_seq();
+        escrow_instance.pack_into_slice(&mut escrow_account.data.borrow_mut());
+
+        EscrowEvent::Drawn {
+            id: escrow_instance.id,
+            escrow: *escrow_account.key,
+            callback: escrow_instance.callback,
+            seq,
+            amount: amount - deducted_fee + late_fee,
+            withdrawn: escrow_instance.withdrawn
+        }.emit();
+
+        Ok(())
+    }
+
+    /// Charges the protocol fee on `amount` into the fee vault and returns it. Escrows whose payer bears the fee have it
+    /// transferred from the payer's wallet on top (a CPI, so this has to run before any lamports are moved by hand),
+    /// the others have it taken off the payout by the caller.
+    fn _process_protocol_fee<'a>(
+        payer_account: &AccountInfo<'a>,
+        escrow_account: &AccountInfo<'a>,
+        escrow_instance: &mut EscrowAccount,
+        fee_vault: Option<(&AccountInfo<'a>, &Config)>,
+        system_program_account: Option<&AccountInfo<'a>>,
+        amount: u64
+    ) -> Result<u64, ProgramError> {
+        let (fee_vault_account, config_instance) = match fee_vault {
+            Some(fee_vault) => fee_vault,
+            None => return Ok(0)
+        };
+        let protocol_fee: u64 = config_instance.protocol_fee(amount)?;
+
+        if protocol_fee == 0 {
+            return Ok(0);
+        }
+
+        if escrow_instance.payer_pays_fee {
+            if !payer_account.is_signer {
+                msg!("The payer bears the protocol fee, so has to sign");
+                return Err(ProgramError::MissingRequiredSignature);
+            }
+
+            let system_program_account: &AccountInfo = system_program_account.ok_or(ProgramError::NotEnoughAccountKeys)?;
+            invoke(
+                &system_instruction::transfer(payer_account.key, fee_vault_account.key, protocol_fee),
+                &[
+                    payer_account.clone(),
+                    fee_vault_account.clone(),
+                    system_program_account.clone()
+                ]
+            )?;
+        } else {
+            **fee_vault_account.lamports.borrow_mut() = fee_vault_account.lamports()
+                .checked_add(protocol_fee)
+                .ok_or(ProgramError::ArithmeticOverflow)?;
+        }
+
+        EscrowEvent::FeeCharged {
+            id: escrow_instance.id,
+            escrow: *escrow_account.key,
+            callback: escrow_instance.callback,
+            seq: escrow_instance.next_event_seq(),
+            fee: protocol_fee,
+            by_payer: escrow_instance.payer_pays_fee
+        }.emit();
+
+        Ok(protocol_fee)
+    }
+
+    /// Pays `amount` lamports (already taken off the escrow) to the holder of the claim: assignees get their shares,
+    /// the recipient (or the assignee of the whole claim) the rest. The `EscrowAssignments` rent goes back to `rent_receiver_account`.
+    /// Returns what the assignees got.
+    fn _process_payout(
+        rent_receiver_account: &AccountInfo,
+        payout_account: &AccountInfo,
+        claim_shares: Option<ClaimShareAccounts>,
+        amount: u64
+    ) -> Result<u64, ProgramError> {
+        let mut payout_amount: u64 = amount;
+
+        if let Some(claim_shares) = claim_shares {
+            for (assignee_account, bps) in claim_shares.assignees {
+                let share_amount: u64 = u64::try_from(amount as u128 * bps as u128 / 10_000)
+                    .map_err(|_| ProgramError::ArithmeticOverflow)?;
+
+                **assignee_account.lamports.borrow_mut() = assignee_account.lamports()
+                    .checked_add(share_amount)
+                    .ok_or(ProgramError::ArithmeticOverflow)?;
+                payout_amount -= share_amount;
+            }
+
+            Self::_process_close_escrow(rent_receiver_account, claim_shares.assignments, claim_shares.assignments.lamports())?;
+        }
+
+        **payout_account.lamports.borrow_mut() = payout_account.lamports()
+            .checked_add(payout_amount)
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+
+        Ok(amount - payout_amount)
+    }
+
+    /// Guarded stablecoin escrows carry their oracle right after the token accounts.
+    fn _check_peg<'a, 'b>(
+        accounts_iter: &mut std::slice::Iter<'b, AccountInfo<'a>>,
+        escrow_instance: &EscrowAccount
+    ) -> ProgramResult {
+        if escrow_instance.depeg_price_feed == Pubkey::default() || escrow_instance.depeg_override {
+            return Ok(());
+        }
+
+        let price_feed_account: &AccountInfo = next_account_info(accounts_iter)?;
+
+        if &escrow_instance.depeg_price_feed != price_feed_account.key {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let deviation_bps: u64 = OraclePrice::load(price_feed_account, Clock::get()?.unix_timestamp)?.peg_deviation_bps()?;
+
+        if deviation_bps > escrow_instance.max_depeg_bps as u64 {
+            msg!("Token is {} bps off its peg", deviation_bps);
+            return Err(EscrowError::Depegged.into());
+        }
+
+        Ok(())
+    }
+
+    /// Hash time-locked escrows are only released with the secret hashing to their `hashlock`.
+    fn _check_preimage(escrow_instance: &EscrowAccount, preimage: Option<[u8; 32]>) -> ProgramResult {
+        if !escrow_instance.is_hashlocked() {
+            return Ok(());
+        }
+
+        match preimage {
+            Some(preimage) if hashv(&[&preimage]).to_bytes() == escrow_instance.hashlock => Ok(()),
+            _ => Err(EscrowError::InvalidPreimage.into())
+        }
+    }
+
+    fn _check_not_swapped(escrow_instance: &EscrowAccount) -> ProgramResult {
+        if escrow_instance.swap != Pubkey::default() {
+            msg!("Escrow is a swap leg, use Settle");
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        Ok(())
+    }
+
+    /// Swap legs are plain escrows that aren't part of another swap: `Settle` pays them out in full (less the protocol fee),
+    /// with no extra accounts, so their payer can't bear the fee either.
+    fn _check_swap_leg(escrow_instance: &EscrowAccount) -> ProgramResult {
+        Self::_check_not_disputed(escrow_instance)?;
+        Self::_check_not_quarantined(escrow_instance)?;
+
+        if escrow_instance.usd_amount != 0
+            || escrow_instance.has_milestones
+            || escrow_instance.has_assignments
+            || escrow_instance.assignee != Pubkey::default()
+            || escrow_instance.is_hashlocked()
+            || escrow_instance.is_vesting()
+            || escrow_instance.depeg_price_feed != Pubkey::default()
+            || escrow_instance.payer_pays_fee
+        {
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        Ok(())
+    }
+
+    fn _check_swap_pda(program_id: &Pubkey, leg_a_account: &AccountInfo, swap_account: &AccountInfo) -> Result<EscrowSwap, ProgramError> {
+        if swap_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        let swap_instance: EscrowSwap = EscrowSwap::unpack(&swap_account.data.borrow())?;
+
+        let (seed1, seed2) = crate::get_swap_seeds(leg_a_account.key);
+        let expected_pda: Pubkey = Pubkey::create_program_address(&[seed1, seed2, &[swap_instance.bump]], program_id)?;
+
+        if &expected_pda != swap_account.key {
+            return Err(EscrowError::PdaMismatch.into());
+        }
+
+        Ok(swap_instance)
+    }
+
+    /// Pays a swap leg out in full to `destination_account` (its recipient when swapping, its payer when refunding)
+    /// and closes it, the rent going back to the leg's payer. SOL legs are charged the protocol fee when given the fee vault.
+    /// Returns the amount paid out.
+    fn _process_settle_swap_leg<'a>(
+        payer_account: &AccountInfo<'a>,
+        recipient_account: &AccountInfo<'a>,
+        escrow_account: &AccountInfo<'a>,
+        escrow_instance: &mut EscrowAccount,
+        token_accounts: Option<TokenAccounts<'a, '_>>,
+        destination_account: &AccountInfo<'a>,
+        fee_vault: Option<(&AccountInfo<'a>, &Config)>
+    ) -> Result<u64, ProgramError> {
+        if let Some(token_accounts) = token_accounts {
+            let amount: u64 = Self::_process_release_vault(
+                payer_account,
+                recipient_account,
+                escrow_account,
+                escrow_instance,
+                &token_accounts,
+                destination_account.key
+            )?;
+            Self::_process_close_escrow(payer_account, escrow_account, escrow_account.lamports())?;
+            return Ok(amount);
+        }
+
+        let rent_exemp: u64 = crate::rent::escrow(&Rent::get()?);
+        let locked_amount: u64 = escrow_account.lamports() - rent_exemp;
+        let protocol_fee: u64 = Self::_process_protocol_fee(payer_account, escrow_account, escrow_instance, fee_vault, None, locked_amount)?;
+        let amount: u64 = locked_amount - protocol_fee;
+
+        **escrow_account.lamports.borrow_mut() = rent_exemp;
+        **destination_account.lamports.borrow_mut() = destination_account.lamports()
+            .checked_add(amount)
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+
+        Self::_process_close_escrow(payer_account, escrow_account, rent_exemp)?;
+
+        Ok(amount)
+    }
+
+    fn _check_not_disputed(escrow_instance: &EscrowAccount) -> ProgramResult {
+        if escrow_instance.status == EscrowStatus::Disputed {
+            msg!("Escrow is disputed, waiting for the arbiter");
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        Ok(())
+    }
+
+    /// Quarantined escrows (see `Quarantine`) are left to the admin, through `ExportState`.
+    fn _check_not_quarantined(escrow_instance: &EscrowAccount) -> ProgramResult {
+        if escrow_instance.status == EscrowStatus::Quarantined {
+            return Err(EscrowError::Quarantined.into());
+        }
+
+        Ok(())
+    }
+
+    /// Takes the `EscrowAssignments` PDA and every assignee off `accounts_iter` for escrows with sold shares,
+    /// moving any rent advanced out of the escrowed amount back into the escrow.
+    fn _next_claim_shares<'a, 'b>(
+        program_id: &Pubkey,
+        accounts_iter: &mut std::slice::Iter<'b, AccountInfo<'a>>,
+        escrow_account: &AccountInfo,
+        escrow_instance: &EscrowAccount
+    ) -> Result<Option<ClaimShareAccounts<'a, 'b>>, ProgramError> {
+        if !escrow_instance.has_assignments {
+            return Ok(None);
+        }
+
+        let assignments_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let assignments_instance: EscrowAssignments = Self::_check_assignments_pda(program_id, escrow_account, assignments_account)?;
+        Self::_process_repay_advanced_rent(escrow_account, assignments_account, escrow_instance)?;
+
+        let assignees: Vec<(&AccountInfo, u16)> = assignments_instance.shares.iter()
+            .map(|share| {
+                let assignee_account: &AccountInfo = next_account_info(accounts_iter)?;
+
+                if assignee_account.key != &share.assignee {
+                    return Err(ProgramError::InvalidArgument);
+                }
+
+                Ok((assignee_account, share.bps))
+            })
+            .collect::<Result<_, ProgramError>>()?;
+
+        Ok(Some(ClaimShareAccounts { assignments: assignments_account, assignees }))
+    }
+
+    /// Moves the rent `AssignClaimShare` took off the escrowed amount from the `EscrowAssignments` back into the escrow,
+    /// which restores the full amount before the assignments are closed along with it.
+    fn _process_repay_advanced_rent(
+        escrow_account: &AccountInfo,
+        assignments_account: &AccountInfo,
+        escrow_instance: &EscrowAccount
+    ) -> ProgramResult {
+        if escrow_instance.rent_advanced == 0 {
+            return Ok(());
+        }
+
+        **assignments_account.lamports.borrow_mut() = assignments_account.lamports()
+            .checked_sub(escrow_instance.rent_advanced)
+            .ok_or(ProgramError::InsufficientFunds)?;
+        **escrow_account.lamports.borrow_mut() = escrow_account.lamports()
+            .checked_add(escrow_instance.rent_advanced)
+            .ok_or(ProgramError::ArithmeticOverflow)?;
+
+        Ok(())
+    }
+
+    fn _check_assignments_pda(
+        program_id: &Pubkey,
+        escrow_account: &AccountInfo,
+        assignments_account: &AccountInfo
+    ) -> Result<EscrowAssignments, ProgramError> {
+        if assignments_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        let assignments_instance: EscrowAssignments = EscrowAssignments::unpack(&assignments_account.data.borrow())?;
+
+        let (seed1, seed2) = crate::get_assignments_seeds(escrow_account.key);
+        let expected_pda: Pubkey = Pubkey::create_program_address(&[seed1, seed2, &[assignments_instance.bump]], program_id)?;
+
+        if &expected_pda != assignments_account.key {
+            return Err(EscrowError::PdaMismatch.into());
+        }
+
+        Ok(assignments_instance)
+    }
+
+    fn _check_unlocked(escrow_instance: &EscrowAccount) -> ProgramResult {
+        if Clock::get()?.unix_timestamp < escrow_instance.unlock_at {
+            msg!("Escrow is locked until {}", escrow_instance.unlock_at);
+            return Err(EscrowError::StillLocked.into());
+        }
+
+        Ok(())
+    }
+
+    fn _check_vesting_share_pda(
+        program_id: &Pubkey,
+        escrow_account: &AccountInfo,
+        beneficiary_pkey: &Pubkey,
+        vesting_share_account: &AccountInfo
+    ) -> Result<VestingShare, ProgramError> {
+        if vesting_share_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        let share_instance: VestingShare = VestingShare::unpack(&vesting_share_account.data.borrow())?;
+
+        let (seed1, seed2, seed3) = crate::get_vesting_share_seeds(escrow_account.key, beneficiary_pkey);
+        let expected_pda: Pubkey = Pubkey::create_program_address(&[seed1, seed2, seed3, &[share_instance.bump]], program_id)?;
+
+        if &expected_pda != vesting_share_account.key {
+            return Err(EscrowError::PdaMismatch.into());
+        }
+
+        Ok(share_instance)
+    }
+
+    fn _check_milestones_pda(
+        program_id: &Pubkey,
+        escrow_account: &AccountInfo,
+        milestones_account: &AccountInfo
+    ) -> Result<EscrowMilestones, ProgramError> {
+        if milestones_account.owner != program_id {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        let milestones_instance: EscrowMilestones = EscrowMilestones::unpack(&milestones_account.data.borrow())?;
+
+        let (seed1, seed2) = crate::get_milestones_seeds(escrow_account.key);
+        let expected_pda: Pubkey = Pubkey::create_program_address(&[seed1, seed2, &[milestones_instance.bump]], program_id)?;
+
+        if &expected_pda != milestones_account.key {
+            return Err(EscrowError::PdaMismatch.into());
+        }
+
+        Ok(milestones_instance)
+    }
+
+    /// Takes `[mint, vault, destination token account, token program]` off `accounts_iter` for token escrows.
+    fn _next_token_accounts<'a, 'b>(
+        accounts_iter: &mut std::slice::Iter<'b, AccountInfo<'a>>,
+        escrow_instance: &EscrowAccount
+    ) -> Result<Option<TokenAccounts<'a, 'b>>, ProgramError> {
+        if !escrow_instance.is_token_escrow() {
+            return Ok(None);
+        }
+
+        Ok(Some(TokenAccounts {
+            mint: next_account_info(accounts_iter)?,
+            vault: next_account_info(accounts_iter)?,
+            destination: next_account_info(accounts_iter)?,
+            token_program: next_account_info(accounts_iter)?
+        }))
+    }
+
+    fn _next_stake_accounts<'a, 'b>(
+        program_id: &Pubkey,
+        accounts_iter: &mut std::slice::Iter<'b, AccountInfo<'a>>,
+        escrow_instance: &EscrowAccount
+    ) -> Result<Option<StakeAccounts<'a, 'b>>, ProgramError> {
+        if escrow_instance.stake_vote == Pubkey::default() {
+            return Ok(None);
+        }
+
+        let stake_accounts: StakeAccounts = StakeAccounts {
+            stake: next_account_info(accounts_iter)?,
+            vote: next_account_info(accounts_iter)?,
+            clock: next_account_info(accounts_iter)?,
+            stake_history: next_account_info(accounts_iter)?,
+            rent: next_account_info(accounts_iter)?,
+            stake_config: next_account_info(accounts_iter)?,
+            stake_program: next_account_info(accounts_iter)?,
+            system_program: next_account_info(accounts_iter)?
+        };
+
+        let escrow_id: [u8; 8] = escrow_instance.id.to_le_bytes();
+        let (seed1, seed2) = crate::get_stake_seeds(&escrow_id);
+        let (expected_pda, _bump) = Pubkey::find_program_address(&[seed1, seed2], program_id);
+
+        if stake_accounts.stake.key != &expected_pda {
+            return Err(EscrowError::PdaMismatch.into());
+        }
+
+        if !stake_accounts.stake.data_is_empty() || stake_accounts.stake.owner != &system_program::ID {
+            return Err(EscrowError::AlreadyInitialized.into());
+        }
+
+        if stake_accounts.vote.key != &escrow_instance.stake_vote {
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        Ok(Some(stake_accounts))
+    }
+
+    /// Turns the stake PDA, holding the payout by now, into a stake account delegated to the chosen vote account and
+    /// controlled by `holder_account` alone (see `staking`).
+    fn _process_stake_payout(
+        program_id: &Pubkey,
+        holder_account: &AccountInfo,
+        stake_accounts: &StakeAccounts,
+        escrow_id: u64
+    ) -> ProgramResult {
+        let escrow_id: [u8; 8] = escrow_id.to_le_bytes();
+        let (seed1, seed2) = crate::get_stake_seeds(&escrow_id);
+        let (_pda, bump) = Pubkey::find_program_address(&[seed1, seed2], program_id);
+        let signer_seeds: &[&[u8]] = &[seed1, seed2, &[bump]];
+
+        let stake_account: &AccountInfo = stake_accounts.stake;
+
+        invoke_signed(
+            &system_instruction::allocate(stake_account.key, staking::STAKE_ACCOUNT_LEN),
+            &[stake_account.clone(), stake_accounts.system_program.clone()],
+            &[signer_seeds]
+        )?;
+        invoke_signed(
+            &system_instruction::assign(stake_account.key, &staking::STAKE_PROGRAM_ID),
+            &[stake_account.clone(), stake_accounts.system_program.clone()],
+            &[signer_seeds]
+        )?;
+
+        // the stake account is its own staker until it's delegated, the holder withdraws it from the start
+        invoke(
+            &staking::initialize(stake_account.key, stake_account.key, holder_account.key),
+            &[stake_account.clone(), stake_accounts.rent.clone(), stake_accounts.stake_program.clone()]
+        )?;
+        invoke_signed(
+            &staking::delegate_stake(stake_account.key, stake_account.key, stake_accounts.vote.key),
+            &[
+                stake_account.clone(),
+                stake_accounts.vote.clone(),
+                stake_accounts.clock.clone(),
+                stake_accounts.stake_history.clone(),
+                stake_accounts.stake_config.clone(),
+                stake_accounts.stake_program.clone()
+            ],
+            &[signer_seeds]
+        )?;
+        invoke_signed(
+            &staking::authorize_staker(stake_account.key, stake_account.key, holder_account.key),
+            &[stake_account.clone(), stake_accounts.clock.clone(), stake_accounts.stake_program.clone()],
+            &[signer_seeds]
+        )?;
+
+        msg!("Payout staked in {} with {}", stake_account.key, stake_accounts.vote.key);
+
+        Ok(())
+    }
+
+    /// Token escrows work with both SPL Token and Token-2022, as long as the passed program is the mint's owner.
+    fn _check_token_program(token_program_account: &AccountInfo, mint_account: &AccountInfo) -> ProgramResult {
+        if token_program_account.key != &spl_token::ID && token_program_account.key != &spl_token_2022::ID {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        if mint_account.owner != token_program_account.key {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        Ok(())
+    }
+
+    /// Moves the whole vault balance to `token_accounts.destination`, which must be a token account of `destination_owner`,
+    /// then closes the vault, returning its rent to the payer. Returns the released amount.
+    fn _process_release_vault<'a>(
+        payer_account: &AccountInfo<'a>,
+        recipient_account: &AccountInfo<'a>,
+        escrow_account: &AccountInfo<'a>,
+        escrow_instance: &EscrowAccount,
+        token_accounts: &TokenAccounts<'a, '_>,
+        destination_owner: &Pubkey
+    ) -> Result<u64, ProgramError> {
+        let TokenAccounts { mint, vault, destination, token_program } = *token_accounts;
+
+        if mint.key != &escrow_instance.mint {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        Self::_check_token_program(token_program, mint)?;
+
+        let (vault_seed1, vault_seed2) = crate::get_vault_seeds(escrow_account.key);
+        let (expected_vault_pda, _bump) = Pubkey::find_program_address(&[vault_seed1, vault_seed2], &crate::ID);
+
+        if &expected_vault_pda != vault.key {
+            return Err(EscrowError::PdaMismatch.into());
+        }
+
+        // otherwise the payer could complete an escrow into a token account of their own
+        let destination_data = destination.data.borrow();
+        let destination_instance: StateWithExtensions<TokenAccount> = StateWithExtensions::<TokenAccount>::unpack(&destination_data)?;
+
+        if &destination_instance.base.owner != destination_owner || destination_instance.base.mint != escrow_instance.mint {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        std::mem::drop(destination_data);
+
+        if escrow_instance.nft {
+            let expected_ata: Pubkey = spl_associated_token_account::get_associated_token_address_with_program_id(
+                destination_owner,
+                mint.key,
+                token_program.key
+            );
+
+            if &expected_ata != destination.key {
+                return Err(ProgramError::InvalidAccountData);
+            }
+        }
+
+        let amount: u64 = StateWithExtensions::<TokenAccount>::unpack(&vault.data.borrow())?.base.amount;
+        let decimals: u8 = StateWithExtensions::<Mint>::unpack(&mint.data.borrow())?.base.decimals;
+
+        let (seed1, seed2, seed3, seed4) = crate::get_token_escrow_seeds(payer_account.key, recipient_account.key, mint.key);
+        let signers_seeds: &[&[u8]] = &[seed1, seed2, seed3, seed4, &[escrow_instance.bump]];
+
+        let transfer_ix: Instruction = spl_token_2022::instruction::transfer_checked(
+            token_program.key,
+            vault.key,
+            mint.key,
+            destination.key,
+            escrow_account.key,
+            &[],
+            amount,
+            decimals
+        )?;
+        invoke_signed(
+            &transfer_ix,
+            &[
+                vault.clone(),
+                mint.clone(),
+                destination.clone(),
+                escrow_account.clone()
+            ],
+            &[signers_seeds]
+        )?;
+
+        // transfer fees withheld on the vault block closing it, so they're swept to the mint first (permissionless)
+        let withheld_amount: u64 = StateWithExtensions::<TokenAccount>::unpack(&vault.data.borrow())?
+            .get_extension::<TransferFeeAmount>()
+            .map_or(0, |transfer_fee_amount| u64::from(transfer_fee_amount.withheld_amount));
+
+        if withheld_amount > 0 {
+            let harvest_ix: Instruction = spl_token_2022::extension::transfer_fee::instruction::harvest_withheld_tokens_to_mint(
+                token_program.key,
+                mint.key,
+                &[vault.key]
+            )?;
+            invoke(&harvest_ix, &[mint.clone(), vault.clone()])?;
+        }
+
+        let close_vault_ix: Instruction = spl_token_2022::instruction::close_account(
+            token_program.key,
+            vault.key,
+            payer_account.key,
+            escrow_account.key,
+            &[]
+        )?;
+        invoke_signed(
+            &close_vault_ix,
+            &[
+                vault.clone(),
+                payer_account.clone(),
+                escrow_account.clone()
+            ],
+            &[signers_seeds]
+        )?;
+
+        Ok(amount)
+    }
+
+    /// The payer's `EscrowArchive` and the system program, if the next account is that archive. Both the archive and the
+    /// settlement memo (`memo_may_follow`) are optional, so the next account has to be one of them, or none at all:
+    /// anything else is a misordered account list and fails rather than being skipped.
+    fn _next_archive_accounts<'a, 'b>(
+        program_id: &Pubkey,
+        accounts_iter: &mut std::slice::Iter<'b, AccountInfo<'a>>,
+        payer_account: &AccountInfo<'a>,
+        memo_may_follow: bool
+    ) -> Result<Option<(&'b AccountInfo<'a>, &'b AccountInfo<'a>)>, ProgramError> {
+        let Some(next_account) = accounts_iter.as_slice().first() else {
+            return Ok(None);
+        };
+
+        if memo_may_follow && next_account.key == &crate::MEMO_PROGRAM_ID {
+            return Ok(None);
+        }
+
+        let (seed1, seed2) = crate::get_archive_seeds(payer_account.key);
+
+        if next_account.key != &Pubkey::find_program_address(&[seed1, seed2], program_id).0 {
+            msg!("Expected the payer's archive{}", if memo_may_follow { " or the memo program" } else { "" });
+            return Err(EscrowError::PdaMismatch.into());
+        }
+
+        let archive_account: &AccountInfo = next_account_info(accounts_iter)?;
+        let system_program_account: &AccountInfo = next_account_info(accounts_iter)?;
+
+        if system_program_account.key != &system_program::ID {
+            return Err(ProgramError::IncorrectProgramId);
+        }
+
+        Ok(Some((archive_account, system_program_account)))
+    }
+
+    /// Appends an `ArchivedEscrow` to the payer's `EscrowArchive`, creating the archive on first use
+    /// and growing it by one record otherwise. The payer funds the extra rent.
+    ///
+    /// `state_hash` commits to the escrow address, its final lamports and its final data,
+    /// so anyone holding a copy of the escrow (e.g. from an indexer) can verify it against the record.
+    fn _process_archive_escrow<'a>(
+        program_id: &Pubkey,
+        payer_account: &AccountInfo<'a>,
+        escrow_account: &AccountInfo<'a>,
+        archive_account: &AccountInfo<'a>,
+        system_program_account: &AccountInfo<'a>,
+        created_at: i64,
+        outcome: u8
+    ) -> ProgramResult {
+        let (seed1, seed2) = crate::get_archive_seeds(payer_account.key);
+        let (expected_pda, bump) = Pubkey::find_program_address(&[seed1, seed2], program_id);
+
+        if &expected_pda != archive_account.key {
+            return Err(EscrowError::PdaMismatch.into());
+        }
+
+        let state_hash: [u8; 32] = ArchivedEscrow::state_hash(escrow_account.key, escrow_account.lamports(), &escrow_account.data.borrow());
+        let record: ArchivedEscrow = ArchivedEscrow {
+            state_hash,
+            created_at: u32::try_from(created_at).map_err(|_| ProgramError::InvalidAccountData)?,
+            closed_at: u32::try_from(Clock::get()?.unix_timestamp).map_err(|_| ProgramError::InvalidAccountData)?,
+            outcome,
+            acknowledged_at: 0
+        };
+
+        let rent: Rent = Rent::get()?;
+
+        if archive_account.owner == &system_program::ID {
+            let space: usize = EscrowArchive::space(1);
+            Self::_process_create_pda(
+                payer_account,
+                archive_account,
+                system_program_account,
+                crate::rent::archive(&rent, 1),
+                space,
+                program_id,
+                &[seed1, seed2, &[bump]]
+            )?;
+
+            EscrowArchive::new(bump).pack_into_slice(&mut archive_account.data.borrow_mut());
+        } else {
+            if archive_account.owner != program_id {
+                return Err(ProgramError::IncorrectProgramId);
+            }
+
+            EscrowArchive::unpack(&archive_account.data.borrow())?;
+
+            let space: usize = archive_account.data_len() + ArchivedEscrow::LEN;
+            let top_up: u64 = rent.minimum_balance(space).saturating_sub(archive_account.lamports());
+
+            if top_up > 0 {
+                invoke(
+                    &system_instruction::transfer(payer_account.key, archive_account.key, top_up),
+                    &[
+                        payer_account.clone(),
+                        archive_account.clone(),
+                        system_program_account.clone()
+                    ]
+                )?;
+            }
+
+            archive_account.realloc(space, false)?;
+        }
+
+        let mut archive_data = archive_account.data.borrow_mut();
+        let offset: usize = archive_data.len() - ArchivedEscrow::LEN;
+        record.pack_into_slice(&mut archive_data[offset..]);
+
+        Ok(())
     }
 
     /// This method does the following:
@@ -0,0 +1,104 @@
+//! Rent-exempt minimums of every account the program creates, given the cluster's `Rent` (`Rent::get()` on-chain,
+//! `BanksClient::get_rent()` or the `Rent` sysvar account off-chain).
+//!
+//! The processor funds accounts through these too, so what a client budgets for is exactly what the program charges.
+
+use solana_program::{
+    rent::Rent,
+    program_pack::Pack,
+    program_error::ProgramError
+};
+use spl_token_2022::{
+    extension::ExtensionType,
+    state::Account as TokenAccount
+};
+use super::state::{
+    EscrowAccount, EscrowCounter, EscrowSwap, EscrowNote, EscrowArchive, EscrowDisplay, EscrowMilestones, EscrowAssignments,
+    EscrowExport, Config, BlockedMint, DeadlineBucket, VestingShare, LayoutVersion
+};
+
+
+// Rent-exempt minimums of every account the program creates, given the cluster's `Rent` (`Rent::get()` on-chain,
+// `getMinimumBalanceForRentExemption`'s parameters or `BanksClient::get_rent()` off-chain). The processor funds
+// accounts through these too, so what a client budgets for is exactly what the program charges.
+
+/// `EscrowAccount`, SOL and token escrows alike.
+pub fn escrow(rent: &Rent) -> u64 {
+    rent.minimum_balance(EscrowAccount::LEN)
+}
+
+/// `EscrowAccount` stored in `layout`, legacy escrows being smaller than the current ones.
+pub fn escrow_in(rent: &Rent, layout: LayoutVersion) -> u64 {
+    rent.minimum_balance(layout.data_len())
+}
+
+/// `EscrowCounter`, paid by the first `Initialize`.
+pub fn counter(rent: &Rent) -> u64 {
+    rent.minimum_balance(EscrowCounter::LEN)
+}
+
+/// `Config`, global or of a namespace.
+pub fn config(rent: &Rent) -> u64 {
+    rent.minimum_balance(Config::LEN)
+}
+
+pub fn blocked_mint(rent: &Rent) -> u64 {
+    rent.minimum_balance(BlockedMint::LEN)
+}
+
+pub fn swap(rent: &Rent) -> u64 {
+    rent.minimum_balance(EscrowSwap::LEN)
+}
+
+pub fn display(rent: &Rent) -> u64 {
+    rent.minimum_balance(EscrowDisplay::LEN)
+}
+
+pub fn export(rent: &Rent) -> u64 {
+    rent.minimum_balance(EscrowExport::LEN)
+}
+
+/// Data-less PDAs: fee vaults and `ImportState` markers.
+pub fn marker(rent: &Rent) -> u64 {
+    rent.minimum_balance(0)
+}
+
+/// Note of `ciphertext_len` bytes.
+pub fn note(rent: &Rent, ciphertext_len: usize) -> u64 {
+    rent.minimum_balance(EscrowNote::space(ciphertext_len))
+}
+
+pub fn milestones(rent: &Rent, milestones: usize) -> u64 {
+    rent.minimum_balance(EscrowMilestones::space(milestones))
+}
+
+/// `EscrowAssignments` holding `shares` claim shares (or payer splits).
+pub fn assignments(rent: &Rent, shares: usize) -> u64 {
+    rent.minimum_balance(EscrowAssignments::space(shares))
+}
+
+/// `VestingShare` of one beneficiary of a team vesting escrow.
+pub fn vesting_share(rent: &Rent) -> u64 {
+    rent.minimum_balance(VestingShare::LEN)
+}
+
+/// `EscrowArchive` holding `records` archived escrows.
+pub fn archive(rent: &Rent, records: usize) -> u64 {
+    rent.minimum_balance(EscrowArchive::space(records))
+}
+
+/// `DeadlineBucket` listing `escrows` escrows.
+pub fn deadline_bucket(rent: &Rent, escrows: usize) -> u64 {
+    rent.minimum_balance(DeadlineBucket::space(escrows))
+}
+
+/// Size of the vault of a token escrow, which grows with the extensions its mint requires on token accounts
+/// (e.g. `TransferFeeAmount`, an empty list for SPL Token mints).
+pub fn vault_space(mint_extensions: &[ExtensionType]) -> Result<usize, ProgramError> {
+    ExtensionType::try_calculate_account_len::<TokenAccount>(&ExtensionType::get_required_init_account_extensions(mint_extensions))
+}
+
+/// Vault of a token escrow, see `vault_space`.
+pub fn vault(rent: &Rent, mint_extensions: &[ExtensionType]) -> Result<u64, ProgramError> {
+    Ok(rent.minimum_balance(vault_space(mint_extensions)?))
+}
@@ -0,0 +1,390 @@
+//! Native replay of transactions against this crate's `Processor`, for incident analysis (feature `replay`, see the
+//! `escrow-replay` binary).
+//!
+//! Every instruction gets its accounts serialized the way the runtime hands them to a program, runs on them and has
+//! them read back, so reallocations and owner changes behave as on-chain. While replaying, the syscall stubs serve the
+//! clock & rent of the replayed slot, print events (decoded as `EscrowEvent`s) and CPIs like the runtime logs them,
+//! and carry the CPIs out: to the System program (`CreateAccount`, `Assign`, `Transfer` and `Allocate`, emulated),
+//! the SPL Memo program (emulated), and the SPL Token, Token-2022 and Associated Token Account programs (run
+//! natively). A CPI to any other program fails the replay, saying so in the trace.
+//!
+//! `msg!` prints straight to stdout off-chain, so the trace is stdout, in execution order.
+use std::{collections::HashMap, sync::Mutex};
+use base64::Engine;
+use solana_program::{
+    rent::Rent,
+    clock::Clock,
+    pubkey::Pubkey,
+    system_program,
+    sysvar::{self, instructions::{BorrowedInstruction, BorrowedAccountMeta}},
+    instruction::Instruction,
+    entrypoint::{ProgramResult, NON_DUP_MARKER, BPF_ALIGN_OF_U128, MAX_PERMITTED_DATA_INCREASE, SUCCESS},
+    program_stubs::{self, SyscallStubs},
+    program_error::ProgramError,
+    account_info::AccountInfo
+};
+use super::{
+    events::EscrowEvent,
+    processor::Processor
+};
+
+
+/// `SystemError` codes the emulated System program fails with, as on-chain.
+const ACCOUNT_ALREADY_IN_USE: u32 = 0;
+const RESULT_WITH_NEGATIVE_LAMPORTS: u32 = 1;
+
+/// Programs whose top-level instructions are skipped, their effects (e.g. verified signatures) being taken as given.
+const SKIPPED_PROGRAMS: [Pubkey; 3] = [
+    crate::priority_fee::COMPUTE_BUDGET_PROGRAM_ID,
+    solana_program::ed25519_program::ID,
+    solana_program::secp256k1_program::ID
+];
+
+
+/// An account as the replay sees it, before or after an instruction.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ReplayAccount {
+    pub lamports: u64,
+    pub data: Vec<u8>,
+    pub owner: Pubkey,
+    pub executable: bool
+}
+
+/// Replays transactions over `accounts`, which they update as they succeed.
+pub struct Replayer {
+    pub accounts: HashMap<Pubkey, ReplayAccount>,
+    pub clock: Clock,
+    pub rent: Rent
+}
+
+impl Replayer {
+    pub fn new(accounts: HashMap<Pubkey, ReplayAccount>, clock: Clock) -> Self {
+        Self { accounts, clock, rent: Rent::default() }
+    }
+
+    /// Runs the instructions of a transaction in order, returning the index & error of the one failing it, in which
+    /// case `accounts` are left as they were (transactions are atomic). Accounts missing from `accounts` start out
+    /// empty & system-owned, like accounts that don't exist on-chain.
+    pub fn replay(&mut self, instructions: &[Instruction]) -> Result<(), (usize, ProgramError)> {
+        let snapshot: HashMap<Pubkey, ReplayAccount> = self.accounts.clone();
+        let mut instructions_sysvar_data: Vec<u8> = sysvar::instructions::construct_instructions_data(
+            &instructions.iter().map(|ix| BorrowedInstruction {
+                program_id: &ix.program_id,
+                accounts: ix.accounts.iter().map(|meta| BorrowedAccountMeta {
+                    pubkey: &meta.pubkey,
+                    is_signer: meta.is_signer,
+                    is_writable: meta.is_writable
+                }).collect(),
+                data: &ix.data
+            }).collect::<Vec<BorrowedInstruction>>()
+        );
+
+        for (index, instruction) in instructions.iter().enumerate() {
+            if SKIPPED_PROGRAMS.contains(&instruction.program_id) {
+                println!("Program {} skipped", instruction.program_id);
+                continue;
+            }
+
+            sysvar::instructions::store_current_index(&mut instructions_sysvar_data, index as u16);
+            self.accounts.insert(sysvar::instructions::ID, ReplayAccount { data: instructions_sysvar_data.clone(), owner: sysvar::ID, ..Default::default() });
+            self.accounts.insert(sysvar::clock::ID, ReplayAccount { data: clock_data(&self.clock), owner: sysvar::ID, ..Default::default() });
+
+            if let Err(err) = self.execute(instruction) {
+                self.accounts = snapshot;
+                return Err((index, err));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Serializes `instruction`'s accounts as the runtime does (aligned layout), runs the program over them and reads
+    /// them back.
+    fn execute(&mut self, instruction: &Instruction) -> ProgramResult {
+        let mut keys: Vec<(Pubkey, bool, bool)> = Vec::new();
+        for meta in &instruction.accounts {
+            match keys.iter_mut().find(|(key, _, _)| key == &meta.pubkey) {
+                Some((_, is_signer, is_writable)) => {
+                    *is_signer |= meta.is_signer;
+                    *is_writable |= meta.is_writable;
+                },
+                None => keys.push((meta.pubkey, meta.is_signer, meta.is_writable))
+            }
+        }
+
+        let mut input: Vec<u8> = Vec::new();
+        let mut offsets: Vec<(Pubkey, usize)> = Vec::new();
+
+        input.extend_from_slice(&(instruction.accounts.len() as u64).to_le_bytes());
+
+        for (position, meta) in instruction.accounts.iter().enumerate() {
+            if let Some(original) = instruction.accounts[..position].iter().position(|earlier| earlier.pubkey == meta.pubkey) {
+                input.push(original as u8);
+                input.extend_from_slice(&[0; 7]);
+                continue;
+            }
+
+            let (key, is_signer, is_writable) = keys.iter().find(|(key, _, _)| key == &meta.pubkey).copied().unwrap_or_default();
+            let account: ReplayAccount = self.accounts.get(&key).cloned().unwrap_or_default();
+
+            input.push(NON_DUP_MARKER);
+            input.push(is_signer as u8);
+            input.push(is_writable as u8);
+            input.push(account.executable as u8);
+            input.extend_from_slice(&[0; 4]);
+            offsets.push((key, input.len()));
+            input.extend_from_slice(key.as_ref());
+            input.extend_from_slice(account.owner.as_ref());
+            input.extend_from_slice(&account.lamports.to_le_bytes());
+            input.extend_from_slice(&(account.data.len() as u64).to_le_bytes());
+            input.extend_from_slice(&account.data);
+            input.resize(input.len() + MAX_PERMITTED_DATA_INCREASE, 0);
+            input.resize(input.len().next_multiple_of(BPF_ALIGN_OF_U128), 0);
+            input.extend_from_slice(&u64::MAX.to_le_bytes());
+        }
+
+        input.extend_from_slice(&(instruction.data.len() as u64).to_le_bytes());
+        input.extend_from_slice(&instruction.data);
+        input.extend_from_slice(instruction.program_id.as_ref());
+
+        // u64-aligned, as the runtime's input region
+        let mut aligned: Vec<u64> = vec![0; input.len().div_ceil(8)];
+        let aligned_input: &mut [u8] = unsafe { std::slice::from_raw_parts_mut(aligned.as_mut_ptr() as *mut u8, aligned.len() * 8) };
+        aligned_input[..input.len()].copy_from_slice(&input);
+
+        program_stubs::set_syscall_stubs(Box::new(Tracer {
+            clock: self.clock.clone(),
+            rent: self.rent.clone(),
+            callers: Mutex::new(vec![instruction.program_id])
+        }));
+
+        println!("Program {} invoke [1]", instruction.program_id);
+
+        let result: ProgramResult = {
+            let (program_id, account_infos, data) = unsafe { solana_program::entrypoint::deserialize(aligned_input.as_mut_ptr()) };
+            Tracer::run(program_id, &account_infos, data)
+        };
+
+        log_result(&instruction.program_id, &result);
+        result?;
+
+        for (key, offset) in offsets {
+            let read_u64 = |at: usize| u64::from_le_bytes(aligned_input[at..at + 8].try_into().unwrap_or_default());
+            let data_len: usize = read_u64(offset + 72) as usize;
+
+            self.accounts.insert(key, ReplayAccount {
+                owner: Pubkey::new_from_array(aligned_input[offset + 32..offset + 64].try_into().unwrap_or_default()),
+                lamports: read_u64(offset + 64),
+                data: aligned_input[offset + 80..offset + 80 + data_len].to_vec(),
+                executable: aligned_input[offset - 5] != 0
+            });
+        }
+
+        Ok(())
+    }
+}
+
+
+/// Syscall stubs of a replay. `callers` is the stack of programs running, whose top signs for the PDAs of a CPI.
+struct Tracer {
+    clock: Clock,
+    rent: Rent,
+    callers: Mutex<Vec<Pubkey>>
+}
+
+impl Tracer {
+    fn run(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+        if program_id == &crate::ID {
+            Processor::process(program_id, accounts, data)
+        } else if program_id == &system_program::ID {
+            process_system_instruction(accounts, data)
+        } else if program_id == &crate::MEMO_PROGRAM_ID {
+            process_memo_instruction(accounts, data)
+        } else if program_id == &spl_token::ID {
+            spl_token::processor::Processor::process(program_id, accounts, data)
+        } else if program_id == &spl_token_2022::ID {
+            spl_token_2022::processor::Processor::process(program_id, accounts, data)
+        } else if program_id == &spl_associated_token_account::ID {
+            spl_associated_token_account::processor::process_instruction(program_id, accounts, data)
+        } else {
+            println!("Program {} isn't emulated by the replay", program_id);
+            Err(ProgramError::IncorrectProgramId)
+        }
+    }
+
+    fn with_callers<T>(&self, f: impl FnOnce(&mut Vec<Pubkey>) -> T) -> T {
+        f(&mut self.callers.lock().unwrap_or_else(|poisoned| poisoned.into_inner()))
+    }
+}
+
+impl SyscallStubs for Tracer {
+    fn sol_invoke_signed(&self, instruction: &Instruction, account_infos: &[AccountInfo], signers_seeds: &[&[&[u8]]]) -> ProgramResult {
+        let caller: Pubkey = self.with_callers(|callers| callers.last().copied()).ok_or(ProgramError::InvalidArgument)?;
+        let signers: Vec<Pubkey> = signers_seeds.iter()
+            .map(|seeds| Pubkey::create_program_address(seeds, &caller))
+            .collect::<Result<_, _>>()?;
+
+        let mut callee_infos: Vec<AccountInfo> = Vec::with_capacity(instruction.accounts.len());
+        for meta in &instruction.accounts {
+            let mut account_info: AccountInfo = account_infos.iter()
+                .find(|account_info| account_info.key == &meta.pubkey)
+                .ok_or(ProgramError::NotEnoughAccountKeys)?
+                .clone();
+
+            if meta.is_signer && !account_info.is_signer && !signers.contains(&meta.pubkey) {
+                println!("Cross-program invocation with unauthorized signer {}", meta.pubkey);
+                return Err(ProgramError::MissingRequiredSignature);
+            }
+            if meta.is_writable && !account_info.is_writable {
+                println!("Cross-program invocation with unauthorized writable account {}", meta.pubkey);
+                return Err(ProgramError::InvalidArgument);
+            }
+
+            account_info.is_signer = meta.is_signer;
+            account_info.is_writable = meta.is_writable;
+            callee_infos.push(account_info);
+        }
+
+        let depth: usize = self.with_callers(|callers| {
+            callers.push(instruction.program_id);
+            callers.len()
+        });
+        println!("Program {} invoke [{}]", instruction.program_id, depth);
+
+        let result: ProgramResult = Self::run(&instruction.program_id, &callee_infos, &instruction.data);
+
+        self.with_callers(|callers| callers.pop());
+        log_result(&instruction.program_id, &result);
+
+        result
+    }
+
+    fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+        unsafe { *(var_addr as *mut Clock) = self.clock.clone() };
+        SUCCESS
+    }
+
+    fn sol_get_rent_sysvar(&self, var_addr: *mut u8) -> u64 {
+        unsafe { *(var_addr as *mut Rent) = self.rent.clone() };
+        SUCCESS
+    }
+
+    fn sol_log_data(&self, fields: &[&[u8]]) {
+        let encoded: Vec<String> = fields.iter().map(|field| base64::engine::general_purpose::STANDARD.encode(field)).collect();
+        println!("Program data: {}", encoded.join(" "));
+
+        if let [data] = fields {
+            if let Ok(event) = EscrowEvent::unpack(data) {
+                println!("  {:?}", event);
+            }
+        }
+    }
+}
+
+/// Runs the System program instructions the escrow program invokes, failing like the System program would.
+fn process_system_instruction(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let read_u64 = |offset: usize| -> Result<u64, ProgramError> {
+        data.get(offset..offset + 8).and_then(|slice| slice.try_into().ok()).map(u64::from_le_bytes).ok_or(ProgramError::InvalidInstructionData)
+    };
+    let read_pubkey = |offset: usize| -> Result<Pubkey, ProgramError> {
+        data.get(offset..offset + 32).and_then(|slice| slice.try_into().ok()).map(Pubkey::new_from_array).ok_or(ProgramError::InvalidInstructionData)
+    };
+    let tag: u32 = data.get(..4).and_then(|slice| slice.try_into().ok()).map(u32::from_le_bytes).ok_or(ProgramError::InvalidInstructionData)?;
+
+    match (tag, accounts) {
+        // CreateAccount { lamports, space, owner }
+        (0, [from, to, ..]) => {
+            if to.lamports() != 0 || !to.data_is_empty() || to.owner != &system_program::ID {
+                println!("Create Account: account {} already in use", to.key);
+                return Err(ProgramError::Custom(ACCOUNT_ALREADY_IN_USE));
+            }
+
+            transfer(from, to, read_u64(4)?)?;
+            allocate(to, read_u64(12)?)?;
+            to.assign(&read_pubkey(20)?);
+        },
+        // Assign { owner }
+        (1, [account, ..]) => {
+            if !account.is_signer {
+                return Err(ProgramError::MissingRequiredSignature);
+            }
+            account.assign(&read_pubkey(4)?);
+        },
+        // Transfer { lamports }
+        (2, [from, to, ..]) => transfer(from, to, read_u64(4)?)?,
+        // Allocate { space }
+        (8, [account, ..]) => allocate(account, read_u64(4)?)?,
+        (0..=2 | 8, _) => return Err(ProgramError::NotEnoughAccountKeys),
+        _ => {
+            println!("System instruction {} isn't emulated by the replay", tag);
+            return Err(ProgramError::InvalidInstructionData);
+        }
+    }
+
+    Ok(())
+}
+
+/// Logs the memo like the SPL Memo program, which also requires every account passed to have signed.
+fn process_memo_instruction(accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    if let Some(account) = accounts.iter().find(|account| !account.is_signer) {
+        println!("Memo: missing required signature {}", account.key);
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    let memo: &str = std::str::from_utf8(data).map_err(|_| ProgramError::InvalidInstructionData)?;
+    println!("Memo (len {}): {:?}", memo.len(), memo);
+
+    Ok(())
+}
+
+fn transfer(from: &AccountInfo, to: &AccountInfo, lamports: u64) -> ProgramResult {
+    if !from.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if !from.data_is_empty() {
+        println!("Transfer: `from` must not carry data");
+        return Err(ProgramError::InvalidArgument);
+    }
+
+    if from.lamports() < lamports {
+        println!("Transfer: insufficient lamports {}, need {}", from.lamports(), lamports);
+        return Err(ProgramError::Custom(RESULT_WITH_NEGATIVE_LAMPORTS));
+    }
+
+    **from.lamports.borrow_mut() -= lamports;
+    **to.lamports.borrow_mut() = to.lamports().checked_add(lamports).ok_or(ProgramError::ArithmeticOverflow)?;
+
+    Ok(())
+}
+
+fn allocate(account: &AccountInfo, space: u64) -> ProgramResult {
+    if !account.is_signer {
+        return Err(ProgramError::MissingRequiredSignature);
+    }
+
+    if !account.data_is_empty() || account.owner != &system_program::ID {
+        println!("Allocate: account {} already in use", account.key);
+        return Err(ProgramError::Custom(ACCOUNT_ALREADY_IN_USE));
+    }
+
+    account.realloc(space as usize, true)
+}
+
+fn log_result(program_id: &Pubkey, result: &ProgramResult) {
+    match result {
+        Ok(()) => println!("Program {} success", program_id),
+        Err(err) => println!("Program {} failed: {}", program_id, err)
+    }
+}
+
+/// `Clock` as stored in its sysvar account.
+fn clock_data(clock: &Clock) -> Vec<u8> {
+    [
+        clock.slot.to_le_bytes(),
+        clock.epoch_start_timestamp.to_le_bytes(),
+        clock.epoch.to_le_bytes(),
+        clock.leader_schedule_epoch.to_le_bytes(),
+        clock.unix_timestamp.to_le_bytes()
+    ].concat()
+}
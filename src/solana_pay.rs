@@ -0,0 +1,176 @@
+//! [Solana Pay transaction requests](https://docs.solanapay.com/spec#specification-transaction-request) for escrow actions.
+//!
+//! The link and transaction helpers are always available; the axum handler lives in `server`, behind the `solana-pay` feature.
+use base64::{Engine, engine::general_purpose::STANDARD};
+use solana_program::message::Message;
+
+
+/// Wraps an https `link` (the merchant's transaction request endpoint) into a `solana:` URL, ready to be shown as a QR code.
+/// As required by the spec, links with query parameters are percent-encoded.
+pub fn transaction_request_url(link: &str) -> String {
+    if !link.contains('?') {
+        return format!("solana:{}", link);
+    }
+
+    let mut url: String = String::from("solana:");
+
+    for byte in link.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => url.push(byte as char),
+            _ => url.push_str(&format!("%{:02X}", byte))
+        }
+    }
+
+    url
+}
+
+/// Inverse of `transaction_request_url()`, returns the https link of the endpoint.
+pub fn parse_transaction_request_url(url: &str) -> Result<String, String> {
+    let link: &str = url.strip_prefix("solana:").ok_or("not a solana pay url")?;
+
+    let mut bytes: Vec<u8> = Vec::with_capacity(link.len());
+    let mut rest: &[u8] = link.as_bytes();
+
+    while let Some((&byte, tail)) = rest.split_first() {
+        if byte == b'%' {
+            let hex: &str = tail.get(..2)
+                .and_then(|hex| std::str::from_utf8(hex).ok())
+                .ok_or("truncated percent-encoding")?;
+            bytes.push(u8::from_str_radix(hex, 16).map_err(|_| "invalid percent-encoding")?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(byte);
+            rest = tail;
+        }
+    }
+
+    let link: String = String::from_utf8(bytes).map_err(|_| "link is not utf-8")?;
+
+    if !link.starts_with("https://") {
+        return Err("transaction request links must be https".into());
+    }
+
+    Ok(link)
+}
+
+/// Serializes `message` as a transaction with empty signatures, base64-encoded, which is what
+/// a transaction request endpoint returns for the wallet to sign.
+pub fn encode_unsigned_transaction(message: &Message) -> String {
+    let signatures: usize = message.header.num_required_signatures as usize;
+
+    // signature count is a compact-u16, a single byte below 128
+    let mut data: Vec<u8> = Vec::with_capacity(1 + signatures * 64);
+    data.push(signatures as u8);
+    data.resize(1 + signatures * 64, 0);
+    data.extend_from_slice(&message.serialize());
+
+    STANDARD.encode(data)
+}
+
+
+#[cfg(feature = "solana-pay")]
+pub mod server {
+    use std::{future::Future, str::FromStr, sync::Arc};
+    use axum::{
+        Json,
+        Router,
+        extract::State,
+        http::StatusCode,
+        routing::get
+    };
+    use serde::{Deserialize, Serialize};
+    use solana_program::{
+        hash::Hash,
+        instruction::Instruction,
+        message::Message,
+        pubkey::Pubkey
+    };
+    use crate::instruction;
+
+
+    /// Where the handler gets a recent blockhash from, typically an RPC client.
+    pub trait BlockhashSource: Send + Sync + 'static {
+        fn latest_blockhash(&self) -> impl Future<Output = Result<Hash, String>> + Send;
+    }
+
+    /// Action the wallet's account (the `account` of the POST request) performs on its escrow with `counterparty`.
+    #[derive(Clone, Copy, Debug)]
+    pub enum EscrowAction {
+        /// escrow-funded checkout: the wallet locks `amount` lamports for `counterparty` (the merchant)
+        Initialize { counterparty: Pubkey, amount: u64 },
+        /// the wallet releases its escrow to `counterparty`
+        Complete { counterparty: Pubkey },
+        /// the wallet takes its escrow for `counterparty` back
+        Close { counterparty: Pubkey }
+    }
+
+    impl EscrowAction {
+        fn instruction(&self, account: &Pubkey) -> Instruction {
+            match *self {
+                Self::Initialize { counterparty, amount } => instruction::initialize(account, &counterparty, amount),
+                Self::Complete { counterparty } => instruction::complete(account, &counterparty),
+                Self::Close { counterparty } => instruction::close(account, &counterparty)
+            }
+        }
+    }
+
+    pub struct EscrowCheckout<B: BlockhashSource> {
+        pub label: String,
+        pub icon: String,
+        pub action: EscrowAction,
+        /// shown by the wallet next to the transaction
+        pub message: Option<String>,
+        pub blockhash_source: B
+    }
+
+    #[derive(Serialize)]
+    struct MetadataResponse {
+        label: String,
+        icon: String
+    }
+
+    #[derive(Deserialize)]
+    struct TransactionRequest {
+        account: String
+    }
+
+    #[derive(Serialize)]
+    struct TransactionResponse {
+        transaction: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        message: Option<String>
+    }
+
+    /// Serves the transaction request endpoint at `/`: GET returns the label and icon, POST the escrow transaction.
+    pub fn router<B: BlockhashSource>(checkout: EscrowCheckout<B>) -> Router {
+        Router::new()
+            .route("/", get(metadata::<B>).post(transaction::<B>))
+            .with_state(Arc::new(checkout))
+    }
+
+    async fn metadata<B: BlockhashSource>(State(checkout): State<Arc<EscrowCheckout<B>>>) -> Json<MetadataResponse> {
+        Json(MetadataResponse {
+            label: checkout.label.clone(),
+            icon: checkout.icon.clone()
+        })
+    }
+
+    async fn transaction<B: BlockhashSource>(
+        State(checkout): State<Arc<EscrowCheckout<B>>>,
+        Json(request): Json<TransactionRequest>
+    ) -> Result<Json<TransactionResponse>, (StatusCode, String)> {
+        let account: Pubkey = Pubkey::from_str(&request.account)
+            .map_err(|e| (StatusCode::BAD_REQUEST, format!("invalid account: {}", e)))?;
+
+        let blockhash: Hash = checkout.blockhash_source.latest_blockhash().await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e))?;
+
+        // the wallet's account is both the fee payer and the escrow payer
+        let message: Message = Message::new_with_blockhash(&[checkout.action.instruction(&account)], Some(&account), &blockhash);
+
+        Ok(Json(TransactionResponse {
+            transaction: super::encode_unsigned_transaction(&message),
+            message: checkout.message.clone()
+        }))
+    }
+}
@@ -0,0 +1,98 @@
+//! Settlement into a stake account (`SetStakeSettlement`): `Complete` pays the holder of the claim into a new stake
+//! account delegated to the vote account they picked, rather than into their wallet.
+//!
+//! The stake account is a PDA (seeds: `"stake"`, escrow id) the program creates within `Complete`: it's initialized with
+//! the holder as withdrawer and itself as staker, so the program can sign the delegation, then its staker authority is
+//! handed over to the holder, who controls it alone from then on. Holders who already stake with that validator can
+//! `Merge` the new stake account into theirs once both are active, their voter and authorities being the same.
+use solana_program::{
+    pubkey,
+    pubkey::Pubkey,
+    sysvar,
+    account_info::AccountInfo,
+    instruction::{Instruction, AccountMeta},
+    program_error::ProgramError
+};
+
+
+pub const STAKE_PROGRAM_ID: Pubkey = solana_program::stake::program::ID;
+
+/// Vote program, owner of the vote accounts stake is delegated to.
+pub const VOTE_PROGRAM_ID: Pubkey = pubkey!("Vote111111111111111111111111111111111111111");
+
+/// Stake config account `DelegateStake` still expects, although unused.
+pub const STAKE_CONFIG_ID: Pubkey = pubkey!("StakeConfig11111111111111111111111111111111");
+
+/// Data length of a stake account (`StakeStateV2`).
+pub const STAKE_ACCOUNT_LEN: u64 = 200;
+
+// `StakeInstruction` tags (bincode, u32)
+const INITIALIZE: u32 = 0;
+const AUTHORIZE: u32 = 1;
+const DELEGATE_STAKE: u32 = 2;
+
+// `StakeAuthorize::Staker`
+const STAKER: u32 = 0;
+
+
+/// Checks that `vote_account` is a vote account, the only kind stake can be delegated to.
+pub fn check_vote_account(vote_account: &AccountInfo) -> Result<(), ProgramError> {
+    if vote_account.owner != &VOTE_PROGRAM_ID {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    Ok(())
+}
+
+/// `Initialize` of the stake account at `stake_pkey` with `staker_pkey` & `withdrawer_pkey`, without lockup.
+pub fn initialize(stake_pkey: &Pubkey, staker_pkey: &Pubkey, withdrawer_pkey: &Pubkey) -> Instruction {
+    let mut data: Vec<u8> = Vec::with_capacity(116);
+    data.extend_from_slice(&INITIALIZE.to_le_bytes());
+    data.extend_from_slice(staker_pkey.as_ref());
+    data.extend_from_slice(withdrawer_pkey.as_ref());
+    // `Lockup { unix_timestamp: 0, epoch: 0, custodian: Pubkey::default() }`
+    data.extend_from_slice(&[0; 48]);
+
+    Instruction::new_with_bytes(
+        STAKE_PROGRAM_ID,
+        &data,
+        vec![
+            AccountMeta::new(*stake_pkey, false),
+            AccountMeta::new_readonly(sysvar::rent::ID, false)
+        ]
+    )
+}
+
+/// `DelegateStake` of the stake account at `stake_pkey` to `vote_pkey`, signed by its staker `staker_pkey`.
+pub fn delegate_stake(stake_pkey: &Pubkey, staker_pkey: &Pubkey, vote_pkey: &Pubkey) -> Instruction {
+    Instruction::new_with_bytes(
+        STAKE_PROGRAM_ID,
+        &DELEGATE_STAKE.to_le_bytes(),
+        vec![
+            AccountMeta::new(*stake_pkey, false),
+            AccountMeta::new_readonly(*vote_pkey, false),
+            AccountMeta::new_readonly(sysvar::clock::ID, false),
+            AccountMeta::new_readonly(sysvar::stake_history::ID, false),
+            AccountMeta::new_readonly(STAKE_CONFIG_ID, false),
+            AccountMeta::new_readonly(*staker_pkey, true)
+        ]
+    )
+}
+
+/// `Authorize` of `new_staker_pkey` as the staker of the stake account at `stake_pkey`, signed by its staker `staker_pkey`.
+pub fn authorize_staker(stake_pkey: &Pubkey, staker_pkey: &Pubkey, new_staker_pkey: &Pubkey) -> Instruction {
+    let mut data: Vec<u8> = Vec::with_capacity(40);
+    data.extend_from_slice(&AUTHORIZE.to_le_bytes());
+    data.extend_from_slice(new_staker_pkey.as_ref());
+    data.extend_from_slice(&STAKER.to_le_bytes());
+
+    Instruction::new_with_bytes(
+        STAKE_PROGRAM_ID,
+        &data,
+        vec![
+            AccountMeta::new(*stake_pkey, false),
+            AccountMeta::new_readonly(sysvar::clock::ID, false),
+            AccountMeta::new_readonly(*staker_pkey, true)
+        ]
+    )
+}
@@ -1,21 +1,380 @@
 use solana_program::{
+    pubkey::Pubkey,
+    hash::hashv,
     program_error::ProgramError,
     program_pack::{Pack, Sealed, IsInitialized}
 };
+use super::error::EscrowError;
 
 
+/// Lifecycle of an escrow. Only `Reserved`, `Pending`, `Accepted`, `Disputed` and `Quarantined` are ever stored:
+/// reaching `Completed` or `Cancelled` closes the escrow account.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "idl", derive(shank::ShankType))]
+pub enum EscrowStatus {
+    /// funded, waiting for settlement
+    Pending = 0,
+    /// the recipient accepted it (`Accept`), so the payer can no longer `Close` it alone before the deadline
+    Accepted = 1,
+    /// a party raised a dispute, the escrow can only be settled by the arbiter (or `CancelMutual`) until it's resolved
+    Disputed = 2,
+    /// released to the recipient (`Complete`, `Claim`, `CompleteUsd`, last milestone, `ResolveDispute`)
+    Completed = 3,
+    /// returned to the payer, wholly or partly (`Close`, `CancelMutual`)
+    Cancelled = 4,
+    /// address and terms reserved (`Reserve`) but not funded yet, becomes `Pending` on `Fund`
+    Reserved = 5,
+    /// found inconsistent by `Quarantine`, out of every normal flow until the admin exports it (`ExportState`)
+    Quarantined = 6
+}
+
+impl EscrowStatus {
+    /// Returns `next` if the escrow may move there from `self`, fails with `EscrowError::InvalidTransition` otherwise.
+    pub fn transition(self, next: Self) -> Result<Self, ProgramError> {
+        if self == Self::Quarantined {
+            return Err(EscrowError::Quarantined.into());
+        }
+
+        let legal: bool = matches!(
+            (self, next),
+            (Self::Reserved, Self::Pending | Self::Cancelled)
+                | (Self::Pending, Self::Accepted | Self::Disputed | Self::Completed | Self::Cancelled)
+                | (Self::Accepted, Self::Disputed | Self::Completed | Self::Cancelled)
+                | (Self::Disputed, Self::Completed | Self::Cancelled)
+        );
+
+        if !legal {
+            return Err(EscrowError::InvalidTransition.into());
+        }
+
+        Ok(next)
+    }
+}
+
+impl TryFrom<u8> for EscrowStatus {
+    type Error = ProgramError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        Ok(match value {
+            0 => Self::Pending,
+            1 => Self::Accepted,
+            2 => Self::Disputed,
+            3 => Self::Completed,
+            4 => Self::Cancelled,
+            5 => Self::Reserved,
+            6 => Self::Quarantined,
+            _ => return Err(ProgramError::InvalidAccountData)
+        })
+    }
+}
+
+/// What `Quarantine` found wrong with an escrow, carried by the `Quarantined` event.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Inconsistency {
+    /// initialized, but not starting with `EscrowAccount::DISCRIMINATOR`
+    Discriminator = 0,
+    /// a status that's never stored (unknown, `Completed` or `Cancelled`)
+    Status = 1,
+    /// a SOL escrow holding less than its rent-exempt minimum
+    Balance = 2,
+    /// more withdrawn from a vesting escrow than was escrowed
+    Withdrawn = 3
+}
+
+impl TryFrom<u8> for Inconsistency {
+    type Error = ProgramError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        Ok(match value {
+            0 => Self::Discriminator,
+            1 => Self::Status,
+            2 => Self::Balance,
+            3 => Self::Withdrawn,
+            _ => return Err(ProgramError::InvalidAccountData)
+        })
+    }
+}
+
+
+/// Fields are declared in the order they're packed in, after the 8-byte `DISCRIMINATOR`, so the IDL matches the layout.
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "idl", derive(shank::ShankAccount))]
 pub struct EscrowAccount {
     pub is_initialized: bool,
-    pub bump: u8
-    // there is no need to store amount , because we can calculate the transfer amount by subtracting account.lamports - rent_exempt
+    pub bump: u8,
+    /// sequential id assigned by `EscrowCounter` at initialization
+    pub id: u64,
+    /// integrator's webhook digest (zeroed if none), included in every event of this escrow
+    pub callback: [u8; 32],
+    /// USD value (6 decimals) the recipient gets in SOL at settlement time, 0 if the escrow isn't USD-denominated
+    pub usd_amount: u64,
+    /// SOL/USD oracle account used to price a USD-denominated escrow
+    pub price_feed: Pubkey,
+    /// optional independent SOL/USD source (default pubkey if none), settlement fails when it disagrees with `price_feed`
+    pub secondary_price_feed: Pubkey,
+    /// tolerated divergence between the two price sources, in basis points
+    pub max_divergence_bps: u16,
+    /// unix timestamp of `Initialize`
+    pub created_at: i64,
+    /// escrowed SPL token mint, default pubkey for native SOL escrows.
+    /// Tokens are held in the escrow's vault (seeds: `"vault"`, escrow_pda), not on the escrow itself.
+    pub mint: Pubkey,
+    /// the vault holds a single NFT, which is only ever released to associated token accounts
+    pub nft: bool,
+    /// the escrow is released tranche by tranche according to its `EscrowMilestones` (seeds: `"milestones"`, escrow_pda)
+    pub has_milestones: bool,
+    /// unix timestamp before which only `Complete` and after which only `Close` succeeds, 0 if none
+    pub deadline: i64,
+    /// late fee the payer adds on top of the payout per full day `Complete` comes after `deadline`, in basis points, 0 if none
+    pub late_fee_bps_per_day: u16,
+    /// upper bound of the accrued late fee, in basis points of the payout
+    pub late_fee_cap_bps: u16,
+    /// unix timestamp before which the funds can't be released to the recipient, 0 if none
+    pub unlock_at: i64,
+    /// holder of the claim the recipient sold through `AssignClaim`, who then gets the payout (default pubkey if not assigned)
+    pub assignee: Pubkey,
+    pub status: EscrowStatus,
+    /// the recipient sold shares of the payout (`AssignClaimShare`), or the payer split it at `Initialize`,
+    /// tracked in its `EscrowAssignments` (seeds: `"assignments"`, escrow_pda)
+    pub has_assignments: bool,
+    /// third party settling disputes through `ResolveDispute` (default pubkey if none)
+    pub arbiter: Pubkey,
+    /// the `EscrowAssignments` shares are the payer's split of the payout (set at `Initialize`, fixed, their rent paid by the payer)
+    /// rather than shares the recipient sold
+    pub payer_splits: bool,
+    /// stablecoin/USD oracle of a token escrow, settlement freezes while it's off the $1 peg (default pubkey if none)
+    pub depeg_price_feed: Pubkey,
+    /// tolerated distance from the peg, in basis points
+    pub max_depeg_bps: u16,
+    /// the arbiter or both parties lifted the depeg freeze (`OverrideDepegFreeze`)
+    pub depeg_override: bool,
+    /// tokens the vault of a token escrow should hold (what it received at `InitializeToken`, net of transfer fees),
+    /// kept in sync with the vault by `Reconcile`
+    pub token_amount: u64,
+    /// integrator's own id of the escrow (e.g. a merchant order id), zeroed if none.
+    /// Lives at `EscrowAccount::REFERENCE_OFFSET`, so escrows can be looked up with a single memcmp filter.
+    pub reference: [u8; 16],
+    /// parties the escrow was created for, every instruction checks the provided accounts against them.
+    /// They live at `EscrowAccount::PAYER_OFFSET` & `RECIPIENT_OFFSET`, for listing an account's escrows
+    pub payer: Pubkey,
+    pub recipient: Pubkey,
+    /// amount locked at `Initialize` plus any `Deposit`: lamports (excluding rent) for SOL escrows,
+    /// the requested token amount for token escrows (see `token_amount` for what the vault actually received)
+    pub amount: u64,
+    /// SHA-256 of the secret releasing a hash time-locked escrow (zeroed if none), see `Initialize`
+    pub hashlock: [u8; 32],
+    /// `EscrowSwap` this escrow is a leg of (default pubkey if none), it's then only settled through `Settle`
+    pub swap: Pubkey,
+    /// unix timestamp until which a `Reserved` escrow can be funded, anyone can close it afterwards
+    pub reserved_until: i64,
+    /// linear vesting schedule of `amount` (both zeroed if none), see `vested()`
+    pub vesting_start: i64,
+    pub vesting_end: i64,
+    /// lamports already withdrawn by the recipient of a vesting escrow (`WithdrawVested`), by the beneficiaries of a team
+    /// vesting one (`WithdrawVestedShare`) or drawn from a capped one
+    pub withdrawn: u64,
+    /// SPL Governance realm whose passed proposal can force-refund the escrow before `veto_until` (default pubkey if none),
+    /// see `SetGovernanceVeto`
+    pub veto_realm: Pubkey,
+    pub veto_until: i64,
+    /// `Complete` & `Claim` require an unexpired attestation of the recipient by `Config::attestation_issuer`,
+    /// see `RequireAttestation`
+    pub attestation_required: bool,
+    /// `Config` PDA of the namespace the escrow was created in (the global one's by default),
+    /// the only one instructions settling or closing it accept
+    pub config: Pubkey,
+    /// most lamports a single `Complete` or `Claim` releases, 0 if uncapped (see `SetMaxPayout`)
+    pub max_payout: u64,
+    /// lamports taken off the escrowed amount to pay the rent of the escrow's `EscrowAssignments`
+    /// (`AssignClaimShare` with `from_payout`), moved back into the escrow before it's settled or refunded
+    pub rent_advanced: u64,
+    /// the protocol fee is charged to the payer on top of the escrowed amount rather than taken off the payout
+    pub payer_pays_fee: bool,
+    /// `seq` of the last event emitted for the escrow
+    pub event_seq: u64,
+    /// settlement is rejected in transactions calling programs outside `ISOLATED_SETTLEMENT_PROGRAMS`
+    /// (see `RequireIsolatedSettlement`)
+    pub isolated_settlement: bool,
+    /// `recipient` is an SNS domain whose owner, looked up at settlement, gets the payout (see `RecipientResolution`)
+    pub resolve_recipient: bool,
+    /// vote account the holder of the claim picked for `Complete` to stake the payout with, default pubkey to pay
+    /// their wallet (see `SetStakeSettlement`)
+    pub stake_vote: Pubkey
 }
 
 impl EscrowAccount {
-    pub fn new(bump: u8) -> Self {
+    /// Anchor-style account discriminator (`sha256("account:EscrowAccount")[..8]`) the data starts with,
+    /// telling escrows apart from the program's other accounts in `getProgramAccounts` memcmp filters (offset 0)
+    pub const DISCRIMINATOR: [u8; 8] = [0x24, 0x45, 0x30, 0x12, 0x80, 0xe1, 0x7d, 0x87];
+    /// offset of `reference` in the account data, for `getProgramAccounts` memcmp filters
+    pub const REFERENCE_OFFSET: usize = 296;
+    /// offset of `status`, e.g. to list quarantined escrows
+    pub const STATUS_OFFSET: usize = 218;
+    /// offsets of `payer` & `recipient`, fixed since fields are only ever appended (see `client::list_escrows_by_payer`)
+    pub const PAYER_OFFSET: usize = 312;
+    pub const RECIPIENT_OFFSET: usize = 344;
+
+    const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+    pub fn new(bump: u8, id: u64, created_at: i64, payer: Pubkey, recipient: Pubkey, amount: u64) -> Self {
         Self {
             is_initialized: true,
-            bump
+            bump,
+            id,
+            callback: [0; 32],
+            usd_amount: 0,
+            price_feed: Pubkey::default(),
+            secondary_price_feed: Pubkey::default(),
+            max_divergence_bps: 0,
+            created_at,
+            mint: Pubkey::default(),
+            nft: false,
+            has_milestones: false,
+            deadline: 0,
+            late_fee_bps_per_day: 0,
+            late_fee_cap_bps: 0,
+            unlock_at: 0,
+            assignee: Pubkey::default(),
+            status: EscrowStatus::Pending,
+            has_assignments: false,
+            payer_splits: false,
+            arbiter: Pubkey::default(),
+            depeg_price_feed: Pubkey::default(),
+            max_depeg_bps: 0,
+            depeg_override: false,
+            token_amount: 0,
+            reference: [0; 16],
+            payer,
+            recipient,
+            amount,
+            hashlock: [0; 32],
+            swap: Pubkey::default(),
+            reserved_until: 0,
+            vesting_start: 0,
+            vesting_end: 0,
+            withdrawn: 0,
+            veto_realm: Pubkey::default(),
+            veto_until: 0,
+            attestation_required: false,
+            config: Pubkey::default(),
+            max_payout: 0,
+            rent_advanced: 0,
+            payer_pays_fee: false,
+            event_seq: 0,
+            isolated_settlement: false,
+            resolve_recipient: false,
+            stake_vote: Pubkey::default()
+        }
+    }
+
+    /// The provided payer and recipient are the ones the escrow was created for.
+    pub fn check_parties(&self, payer: &Pubkey, recipient: &Pubkey) -> Result<(), ProgramError> {
+        if &self.payer != payer || &self.recipient != recipient {
+            return Err(ProgramError::InvalidArgument);
+        }
+
+        Ok(())
+    }
+
+    pub fn is_hashlocked(&self) -> bool {
+        self.hashlock != [0; 32]
+    }
+
+    pub fn is_vesting(&self) -> bool {
+        self.vesting_end != 0
+    }
+
+    /// Part of `amount` vested at `now`: `elapsed / total * amount`, linearly from `vesting_start` to `vesting_end`.
+    pub fn vested(&self, now: i64) -> u64 {
+        if now <= self.vesting_start {
+            return 0;
+        }
+
+        if now >= self.vesting_end {
+            return self.amount;
+        }
+
+        let elapsed: u128 = (now - self.vesting_start) as u128;
+        let total: u128 = (self.vesting_end - self.vesting_start) as u128;
+
+        // elapsed < total, so this is below `amount`
+        (self.amount as u128 * elapsed / total) as u64
+    }
+
+    /// Vesting to weighted beneficiaries (tag 64 `Initialize`), each withdrawing through its `VestingShare`.
+    pub fn is_team_vesting(&self) -> bool {
+        self.is_vesting() && self.has_assignments
+    }
+
+    pub fn is_token_escrow(&self) -> bool {
+        self.mint != Pubkey::default()
+    }
+
+    /// Reads an escrow `unpack()` may reject, along with the first inconsistency of its data: a foreign discriminator
+    /// or a status that's never stored (read as `Quarantined`), or more withdrawn than escrowed. Balances are up to the caller.
+    pub fn unpack_inconsistent(data: &[u8]) -> Result<(Self, Option<Inconsistency>), ProgramError> {
+        if data.len() != Self::LEN || data[8] != 1 {
+            return Err(ProgramError::UninitializedAccount);
+        }
+
+        let mut src: Vec<u8> = data.to_vec();
+        let mut inconsistency: Option<Inconsistency> = None;
+
+        if src[0..8] != Self::DISCRIMINATOR {
+            src[0..8].copy_from_slice(&Self::DISCRIMINATOR);
+            inconsistency = Some(Inconsistency::Discriminator);
+        }
+
+        let stored: bool = EscrowStatus::try_from(src[Self::STATUS_OFFSET])
+            .is_ok_and(|status| status != EscrowStatus::Completed && status != EscrowStatus::Cancelled);
+
+        if !stored {
+            src[Self::STATUS_OFFSET] = EscrowStatus::Quarantined as u8;
+            inconsistency = inconsistency.or(Some(Inconsistency::Status));
+        }
+
+        let escrow: Self = Self::unpack(&src)?;
+
+        if escrow.withdrawn > escrow.amount {
+            inconsistency = inconsistency.or(Some(Inconsistency::Withdrawn));
+        }
+
+        Ok((escrow, inconsistency))
+    }
+
+    /// Reads escrow data exported by `ExportState`, which may come from an older layout: fields are only ever appended,
+    /// so the ones the export predates are left zeroed.
+    pub fn unpack_exported(data: &[u8]) -> Result<Self, ProgramError> {
+        if data.len() > Self::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let mut src: Vec<u8> = vec![0; Self::LEN];
+        src[..data.len()].copy_from_slice(data);
+
+        Self::unpack(&src)
+    }
+
+    /// Bumps `event_seq` for an event about to be emitted and returns it. The escrow has to be packed afterwards
+    /// (unless it's being closed) for the next event to carry the following number.
+    pub fn next_event_seq(&mut self) -> u64 {
+        self.event_seq += 1;
+        self.event_seq
+    }
+
+    /// Late fee owed on top of `payout` when completing at `now`: `late_fee_bps_per_day` for every full day
+    /// past `deadline`, capped at `late_fee_cap_bps`.
+    pub fn late_fee(&self, payout: u64, now: i64) -> Result<u64, ProgramError> {
+        if self.late_fee_bps_per_day == 0 || now < self.deadline {
+            return Ok(0);
         }
+
+        let days_late: u64 = ((now - self.deadline) / Self::SECONDS_PER_DAY) as u64;
+        let fee_bps: u64 = days_late
+            .saturating_mul(self.late_fee_bps_per_day as u64)
+            .min(self.late_fee_cap_bps as u64);
+
+        u64::try_from(payout as u128 * fee_bps as u128 / 10_000).map_err(|_| ProgramError::ArithmeticOverflow)
     }
 }
 
@@ -28,20 +387,1179 @@ impl IsInitialized for EscrowAccount {
 impl Sealed for EscrowAccount {}
 
 impl Pack for EscrowAccount {
-    const LEN: usize = 2;
+    const LEN: usize = 612;
 
-    fn pack_into_slice(&self, dst: &mut [u8]) -> () {
-        dst.copy_from_slice(&[
-            self.is_initialized as u8,
-            self.bump
-        ]);
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        dst[0..8].copy_from_slice(&Self::DISCRIMINATOR);
+        dst[8] = self.is_initialized as u8;
+        dst[9] = self.bump;
+        dst[10..18].copy_from_slice(&self.id.to_le_bytes());
+        dst[18..50].copy_from_slice(&self.callback);
+        dst[50..58].copy_from_slice(&self.usd_amount.to_le_bytes());
+        dst[58..90].copy_from_slice(self.price_feed.as_ref());
+        dst[90..122].copy_from_slice(self.secondary_price_feed.as_ref());
+        dst[122..124].copy_from_slice(&self.max_divergence_bps.to_le_bytes());
+        dst[124..132].copy_from_slice(&self.created_at.to_le_bytes());
+        dst[132..164].copy_from_slice(self.mint.as_ref());
+        dst[164] = self.nft as u8;
+        dst[165] = self.has_milestones as u8;
+        dst[166..174].copy_from_slice(&self.deadline.to_le_bytes());
+        dst[174..176].copy_from_slice(&self.late_fee_bps_per_day.to_le_bytes());
+        dst[176..178].copy_from_slice(&self.late_fee_cap_bps.to_le_bytes());
+        dst[178..186].copy_from_slice(&self.unlock_at.to_le_bytes());
+        dst[186..218].copy_from_slice(self.assignee.as_ref());
+        dst[Self::STATUS_OFFSET] = self.status as u8;
+        dst[219] = self.has_assignments as u8;
+        dst[220..252].copy_from_slice(self.arbiter.as_ref());
+        dst[252] = self.payer_splits as u8;
+        dst[253..285].copy_from_slice(self.depeg_price_feed.as_ref());
+        dst[285..287].copy_from_slice(&self.max_depeg_bps.to_le_bytes());
+        dst[287] = self.depeg_override as u8;
+        dst[288..296].copy_from_slice(&self.token_amount.to_le_bytes());
+        dst[Self::REFERENCE_OFFSET..Self::REFERENCE_OFFSET + 16].copy_from_slice(&self.reference);
+        dst[Self::PAYER_OFFSET..Self::PAYER_OFFSET + 32].copy_from_slice(self.payer.as_ref());
+        dst[Self::RECIPIENT_OFFSET..Self::RECIPIENT_OFFSET + 32].copy_from_slice(self.recipient.as_ref());
+        dst[376..384].copy_from_slice(&self.amount.to_le_bytes());
+        dst[384..416].copy_from_slice(&self.hashlock);
+        dst[416..448].copy_from_slice(self.swap.as_ref());
+        dst[448..456].copy_from_slice(&self.reserved_until.to_le_bytes());
+        dst[456..464].copy_from_slice(&self.vesting_start.to_le_bytes());
+        dst[464..472].copy_from_slice(&self.vesting_end.to_le_bytes());
+        dst[472..480].copy_from_slice(&self.withdrawn.to_le_bytes());
+        dst[480..512].copy_from_slice(self.veto_realm.as_ref());
+        dst[512..520].copy_from_slice(&self.veto_until.to_le_bytes());
+        dst[520] = self.attestation_required as u8;
+        dst[521..553].copy_from_slice(self.config.as_ref());
+        dst[553..561].copy_from_slice(&self.max_payout.to_le_bytes());
+        dst[561..569].copy_from_slice(&self.rent_advanced.to_le_bytes());
+        dst[569] = self.payer_pays_fee as u8;
+        dst[570..578].copy_from_slice(&self.event_seq.to_le_bytes());
+        dst[578] = self.isolated_settlement as u8;
+        dst[579] = self.resolve_recipient as u8;
+        dst[580..612].copy_from_slice(self.stake_vote.as_ref());
     }
 
     // no need to perform LEN check, because calling Self::unpack() || Self::unpack_unchecked() already does it!
     fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        // zeroed (not yet initialized) accounts carry no discriminator
+        if src[8] == 1 && src[0..8] != Self::DISCRIMINATOR {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
         Ok(Self { 
-            is_initialized: if src[0] == 1 { true } else { false },
+            is_initialized: src[8] == 1,
+            bump: src[9],
+            id: u64::from_le_bytes(src[10..18].try_into().map_err(|_| ProgramError::InvalidAccountData)?),
+            callback: src[18..50].try_into().map_err(|_| ProgramError::InvalidAccountData)?,
+            usd_amount: u64::from_le_bytes(src[50..58].try_into().map_err(|_| ProgramError::InvalidAccountData)?),
+            price_feed: Pubkey::new_from_array(src[58..90].try_into().map_err(|_| ProgramError::InvalidAccountData)?),
+            secondary_price_feed: Pubkey::new_from_array(src[90..122].try_into().map_err(|_| ProgramError::InvalidAccountData)?),
+            max_divergence_bps: u16::from_le_bytes(src[122..124].try_into().map_err(|_| ProgramError::InvalidAccountData)?),
+            created_at: i64::from_le_bytes(src[124..132].try_into().map_err(|_| ProgramError::InvalidAccountData)?),
+            mint: Pubkey::new_from_array(src[132..164].try_into().map_err(|_| ProgramError::InvalidAccountData)?),
+            nft: src[164] == 1,
+            has_milestones: src[165] == 1,
+            deadline: i64::from_le_bytes(src[166..174].try_into().map_err(|_| ProgramError::InvalidAccountData)?),
+            late_fee_bps_per_day: u16::from_le_bytes(src[174..176].try_into().map_err(|_| ProgramError::InvalidAccountData)?),
+            late_fee_cap_bps: u16::from_le_bytes(src[176..178].try_into().map_err(|_| ProgramError::InvalidAccountData)?),
+            unlock_at: i64::from_le_bytes(src[178..186].try_into().map_err(|_| ProgramError::InvalidAccountData)?),
+            assignee: Pubkey::new_from_array(src[186..218].try_into().map_err(|_| ProgramError::InvalidAccountData)?),
+            status: EscrowStatus::try_from(src[Self::STATUS_OFFSET])?,
+            has_assignments: src[219] == 1,
+            payer_splits: src[252] == 1,
+            arbiter: Pubkey::new_from_array(src[220..252].try_into().map_err(|_| ProgramError::InvalidAccountData)?),
+            depeg_price_feed: Pubkey::new_from_array(src[253..285].try_into().map_err(|_| ProgramError::InvalidAccountData)?),
+            max_depeg_bps: u16::from_le_bytes(src[285..287].try_into().map_err(|_| ProgramError::InvalidAccountData)?),
+            depeg_override: src[287] == 1,
+            token_amount: u64::from_le_bytes(src[288..296].try_into().map_err(|_| ProgramError::InvalidAccountData)?),
+            reference: src[Self::REFERENCE_OFFSET..Self::REFERENCE_OFFSET + 16].try_into().map_err(|_| ProgramError::InvalidAccountData)?,
+            payer: Pubkey::new_from_array(src[Self::PAYER_OFFSET..Self::PAYER_OFFSET + 32].try_into().map_err(|_| ProgramError::InvalidAccountData)?),
+            recipient: Pubkey::new_from_array(src[Self::RECIPIENT_OFFSET..Self::RECIPIENT_OFFSET + 32].try_into().map_err(|_| ProgramError::InvalidAccountData)?),
+            amount: u64::from_le_bytes(src[376..384].try_into().map_err(|_| ProgramError::InvalidAccountData)?),
+            hashlock: src[384..416].try_into().map_err(|_| ProgramError::InvalidAccountData)?,
+            swap: Pubkey::new_from_array(src[416..448].try_into().map_err(|_| ProgramError::InvalidAccountData)?),
+            reserved_until: i64::from_le_bytes(src[448..456].try_into().map_err(|_| ProgramError::InvalidAccountData)?),
+            vesting_start: i64::from_le_bytes(src[456..464].try_into().map_err(|_| ProgramError::InvalidAccountData)?),
+            vesting_end: i64::from_le_bytes(src[464..472].try_into().map_err(|_| ProgramError::InvalidAccountData)?),
+            withdrawn: u64::from_le_bytes(src[472..480].try_into().map_err(|_| ProgramError::InvalidAccountData)?),
+            veto_realm: Pubkey::new_from_array(src[480..512].try_into().map_err(|_| ProgramError::InvalidAccountData)?),
+            veto_until: i64::from_le_bytes(src[512..520].try_into().map_err(|_| ProgramError::InvalidAccountData)?),
+            attestation_required: src[520] == 1,
+            config: Pubkey::new_from_array(src[521..553].try_into().map_err(|_| ProgramError::InvalidAccountData)?),
+            max_payout: u64::from_le_bytes(src[553..561].try_into().map_err(|_| ProgramError::InvalidAccountData)?),
+            rent_advanced: u64::from_le_bytes(src[561..569].try_into().map_err(|_| ProgramError::InvalidAccountData)?),
+            payer_pays_fee: src[569] == 1,
+            event_seq: u64::from_le_bytes(src[570..578].try_into().map_err(|_| ProgramError::InvalidAccountData)?),
+            isolated_settlement: src[578] == 1,
+            resolve_recipient: src[579] == 1,
+            stake_vote: Pubkey::new_from_array(src[580..612].try_into().map_err(|_| ProgramError::InvalidAccountData)?)
+        })
+    }
+}
+
+
+/// Layouts `EscrowAccount`s are stored in. Escrows created by the original program are 2 bytes (`LegacyLayout`),
+/// every escrow since starts with `EscrowAccount::DISCRIMINATOR` (`RichLayout`). Both are read during the transition,
+/// until the last legacy escrow is settled: `Complete` & `Close` accept either, every other instruction the rich one.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LayoutVersion {
+    Legacy,
+    Rich
+}
+
+impl LayoutVersion {
+    /// Layout `data` is stored in. Legacy escrows carry no discriminator and are told apart by their length,
+    /// the rich layout's discriminator is checked when unpacking (zeroed, uninitialized accounts have none).
+    pub fn detect(data: &[u8]) -> Result<Self, ProgramError> {
+        match data.len() {
+            RichLayout::LEN => Ok(Self::Rich),
+            LegacyLayout::LEN => Ok(Self::Legacy),
+            _ => Err(ProgramError::InvalidAccountData)
+        }
+    }
+
+    pub fn data_len(self) -> usize {
+        match self {
+            Self::Legacy => LegacyLayout::LEN,
+            Self::Rich => RichLayout::LEN
+        }
+    }
+}
+
+/// What a layout may leave out because the escrow's address implies it: the parties are the PDA's seeds
+/// (checked against the bump afterwards, as for every escrow) and escrows predating namespaces belong to the global `Config`.
+pub struct ImpliedFields<'a> {
+    pub program_id: &'a Pubkey,
+    pub payer: &'a Pubkey,
+    pub recipient: &'a Pubkey
+}
+
+/// (De)serialization of an `EscrowAccount` in one of the `LayoutVersion`s.
+pub trait EscrowLayout {
+    const VERSION: LayoutVersion;
+    const LEN: usize;
+
+    fn unpack(src: &[u8], implied: &ImpliedFields) -> Result<EscrowAccount, ProgramError>;
+
+    /// Fails if `escrow` holds state the layout has no room for.
+    fn pack(escrow: &EscrowAccount, dst: &mut [u8]) -> Result<(), ProgramError>;
+}
+
+/// `[is_initialized, bump]`: a plain SOL escrow whose amount is its lamports above the rent-exempt minimum
+/// (`rent::escrow_in()`), the rest of its fields being implied or defaults.
+pub struct LegacyLayout;
+
+impl EscrowLayout for LegacyLayout {
+    const VERSION: LayoutVersion = LayoutVersion::Legacy;
+    const LEN: usize = 2;
+
+    fn unpack(src: &[u8], implied: &ImpliedFields) -> Result<EscrowAccount, ProgramError> {
+        if src.len() != Self::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        if src[0] != 1 {
+            return Err(ProgramError::UninitializedAccount);
+        }
+
+        let mut escrow: EscrowAccount = EscrowAccount::new(src[1], 0, 0, *implied.payer, *implied.recipient, 0);
+        escrow.config = Pubkey::find_program_address(&[crate::get_config_seeds()], implied.program_id).0;
+
+        Ok(escrow)
+    }
+
+    fn pack(escrow: &EscrowAccount, dst: &mut [u8]) -> Result<(), ProgramError> {
+        if dst.len() != Self::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        // what the legacy layout implies, with the fields it doesn't store (nor imply) taken from `escrow`:
+        // anything else set on `escrow` would be lost
+        let mut legacy: EscrowAccount = EscrowAccount::new(escrow.bump, 0, 0, escrow.payer, escrow.recipient, escrow.amount);
+        legacy.is_initialized = escrow.is_initialized;
+        legacy.config = escrow.config;
+        legacy.event_seq = escrow.event_seq;
+
+        let mut expected: [u8; RichLayout::LEN] = [0; RichLayout::LEN];
+        let mut actual: [u8; RichLayout::LEN] = [0; RichLayout::LEN];
+        legacy.pack_into_slice(&mut expected);
+        escrow.pack_into_slice(&mut actual);
+
+        if expected != actual {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        dst[0] = escrow.is_initialized as u8;
+        dst[1] = escrow.bump;
+
+        Ok(())
+    }
+}
+
+/// Discriminator-prefixed layout of every field, `EscrowAccount`'s `Pack` implementation.
+pub struct RichLayout;
+
+impl EscrowLayout for RichLayout {
+    const VERSION: LayoutVersion = LayoutVersion::Rich;
+    const LEN: usize = EscrowAccount::LEN;
+
+    fn unpack(src: &[u8], _implied: &ImpliedFields) -> Result<EscrowAccount, ProgramError> {
+        EscrowAccount::unpack(src)
+    }
+
+    fn pack(escrow: &EscrowAccount, dst: &mut [u8]) -> Result<(), ProgramError> {
+        if dst.len() != Self::LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        escrow.pack_into_slice(dst);
+
+        Ok(())
+    }
+}
+
+impl EscrowAccount {
+    /// Reads an escrow in whichever layout `data` is stored in (see `LayoutVersion::detect()`).
+    pub fn load(data: &[u8], implied: &ImpliedFields) -> Result<(Self, LayoutVersion), ProgramError> {
+        let version: LayoutVersion = LayoutVersion::detect(data)?;
+
+        let escrow: Self = match version {
+            LayoutVersion::Legacy => LegacyLayout::unpack(data, implied)?,
+            LayoutVersion::Rich => RichLayout::unpack(data, implied)?
+        };
+
+        Ok((escrow, version))
+    }
+
+    /// Writes the escrow back in `version`'s layout, the one it was `load()`ed from.
+    pub fn store(&self, version: LayoutVersion, dst: &mut [u8]) -> Result<(), ProgramError> {
+        match version {
+            LayoutVersion::Legacy => LegacyLayout::pack(self, dst),
+            LayoutVersion::Rich => RichLayout::pack(self, dst)
+        }
+    }
+}
+
+
+/// Global singleton (seeds: `"counter"`) handing out monotonically increasing escrow ids.
+/// It's created lazily by the first `Initialize`.
+#[derive(Debug, PartialEq)]
+pub struct EscrowCounter {
+    pub is_initialized: bool,
+    pub bump: u8,
+    pub next_id: u64
+}
+
+impl EscrowCounter {
+    pub fn new(bump: u8) -> Self {
+        Self {
+            is_initialized: true,
+            bump,
+            next_id: 0
+        }
+    }
+}
+
+impl IsInitialized for EscrowCounter {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Sealed for EscrowCounter {}
+
+impl Pack for EscrowCounter {
+    const LEN: usize = 10;
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        dst[0] = self.is_initialized as u8;
+        dst[1] = self.bump;
+        dst[2..10].copy_from_slice(&self.next_id.to_le_bytes());
+    }
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        Ok(Self {
+            is_initialized: src[0] == 1,
+            bump: src[1],
+            next_id: u64::from_le_bytes(src[2..10].try_into().map_err(|_| ProgramError::InvalidAccountData)?)
+        })
+    }
+}
+
+/// Record of an escrow moved out by `ExportState` (seeds: `"export"`, escrow), vouching for the exported data
+/// so `ImportState` (of this program or of a re-deployed one) recreates exactly what was exported.
+#[derive(Debug, PartialEq)]
+pub struct EscrowExport {
+    pub is_initialized: bool,
+    pub bump: u8,
+    pub escrow: Pubkey,
+    /// escrowed lamports handed to the admin, to be locked again on import
+    pub lamports: u64,
+    /// `EscrowExport::hash` of the escrow & its data
+    pub hash: [u8; 32]
+}
+
+impl EscrowExport {
+    pub fn hash(escrow_pkey: &Pubkey, data: &[u8]) -> [u8; 32] {
+        hashv(&[escrow_pkey.as_ref(), data]).to_bytes()
+    }
+}
+
+impl IsInitialized for EscrowExport {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Sealed for EscrowExport {}
+
+impl Pack for EscrowExport {
+    const LEN: usize = 74;
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        dst[0] = self.is_initialized as u8;
+        dst[1] = self.bump;
+        dst[2..34].copy_from_slice(self.escrow.as_ref());
+        dst[34..42].copy_from_slice(&self.lamports.to_le_bytes());
+        dst[42..74].copy_from_slice(&self.hash);
+    }
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        Ok(Self {
+            is_initialized: src[0] == 1,
+            bump: src[1],
+            escrow: Pubkey::new_from_array(src[2..34].try_into().map_err(|_| ProgramError::InvalidAccountData)?),
+            lamports: u64::from_le_bytes(src[34..42].try_into().map_err(|_| ProgramError::InvalidAccountData)?),
+            hash: src[42..74].try_into().map_err(|_| ProgramError::InvalidAccountData)?
+        })
+    }
+}
+
+/// Links two escrows of opposite direction into an atomic swap (seeds: `"swap"`, leg_a).
+/// `leg_a` is locked by `OpenSwap`, `leg_b` (funded by `leg_a`'s recipient, for `leg_a`'s payer) by `JoinSwap`.
+/// `Settle` then swaps both legs at once, or refunds `leg_a` once `deadline` passed without `leg_b` joining.
+#[derive(Debug, PartialEq)]
+pub struct EscrowSwap {
+    pub is_initialized: bool,
+    pub bump: u8,
+    pub leg_a: Pubkey,
+    pub leg_b: Pubkey,
+    pub deadline: i64
+}
+
+impl EscrowSwap {
+    pub fn new(bump: u8, leg_a: Pubkey, leg_b: Pubkey, deadline: i64) -> Self {
+        Self {
+            is_initialized: true,
+            bump,
+            leg_a,
+            leg_b,
+            deadline
+        }
+    }
+}
+
+impl IsInitialized for EscrowSwap {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Sealed for EscrowSwap {}
+
+impl Pack for EscrowSwap {
+    const LEN: usize = 74;
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        dst[0] = self.is_initialized as u8;
+        dst[1] = self.bump;
+        dst[2..34].copy_from_slice(self.leg_a.as_ref());
+        dst[34..66].copy_from_slice(self.leg_b.as_ref());
+        dst[66..74].copy_from_slice(&self.deadline.to_le_bytes());
+    }
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        Ok(Self {
+            is_initialized: src[0] == 1,
+            bump: src[1],
+            leg_a: Pubkey::new_from_array(src[2..34].try_into().map_err(|_| ProgramError::InvalidAccountData)?),
+            leg_b: Pubkey::new_from_array(src[34..66].try_into().map_err(|_| ProgramError::InvalidAccountData)?),
+            deadline: i64::from_le_bytes(src[66..74].try_into().map_err(|_| ProgramError::InvalidAccountData)?)
+        })
+    }
+}
+
+/// Child account of an escrow (seeds: `"note"`, escrow_pda) holding a small ciphertext blob,
+/// encrypted off-chain to the counterparty's key. The program never inspects the ciphertext.
+#[derive(Debug, PartialEq)]
+pub struct EscrowNote {
+    pub is_initialized: bool,
+    pub bump: u8,
+    pub ciphertext: Vec<u8>
+}
+
+impl EscrowNote {
+    /// is_initialized (1) + bump (1) + ciphertext length (2)
+    pub const HEADER_LEN: usize = 4;
+    pub const MAX_CIPHERTEXT_LEN: usize = 512;
+
+    pub fn new(bump: u8, ciphertext: Vec<u8>) -> Self {
+        Self {
+            is_initialized: true,
+            bump,
+            ciphertext
+        }
+    }
+
+    /// Account size for a note carrying `ciphertext_len` bytes, so rent scales with the blob.
+    pub fn space(ciphertext_len: usize) -> usize {
+        Self::HEADER_LEN + ciphertext_len
+    }
+
+    pub fn pack_into_slice(&self, dst: &mut [u8]) {
+        dst[0] = self.is_initialized as u8;
+        dst[1] = self.bump;
+        dst[2..Self::HEADER_LEN].copy_from_slice(&(self.ciphertext.len() as u16).to_le_bytes());
+        dst[Self::HEADER_LEN..].copy_from_slice(&self.ciphertext);
+    }
+
+    pub fn unpack(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() < Self::HEADER_LEN || src[0] != 1 {
+            return Err(ProgramError::UninitializedAccount);
+        }
+
+        let len: usize = u16::from_le_bytes([src[2], src[3]]) as usize;
+        let ciphertext: &[u8] = src.get(Self::HEADER_LEN..Self::HEADER_LEN + len)
+            .ok_or(ProgramError::InvalidAccountData)?;
+
+        Ok(Self {
+            is_initialized: true,
+            bump: src[1],
+            ciphertext: ciphertext.to_vec()
+        })
+    }
+}
+
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "idl", derive(shank::ShankType))]
+pub struct Milestone {
+    pub amount: u64,
+    /// unix timestamp by which the milestone is due
+    pub deadline: i64,
+    pub released: bool
+}
+
+impl Milestone {
+    /// amount (8) + deadline (8) + released (1)
+    pub const LEN: usize = 17;
+}
+
+/// Child account of an escrow (seeds: `"milestones"`, escrow_pda) splitting its lamports into tranches,
+/// released one at a time by `ApproveMilestone`.
+#[derive(Debug, PartialEq)]
+pub struct EscrowMilestones {
+    pub is_initialized: bool,
+    pub bump: u8,
+    pub milestones: Vec<Milestone>
+}
+
+impl EscrowMilestones {
+    /// is_initialized (1) + bump (1) + milestone count (1)
+    pub const HEADER_LEN: usize = 3;
+    pub const MAX_MILESTONES: usize = 16;
+
+    pub fn new(bump: u8, milestones: Vec<Milestone>) -> Self {
+        Self {
+            is_initialized: true,
+            bump,
+            milestones
+        }
+    }
+
+    pub fn space(milestones: usize) -> usize {
+        Self::HEADER_LEN + milestones * Milestone::LEN
+    }
+
+    pub fn pack_into_slice(&self, dst: &mut [u8]) {
+        dst[0] = self.is_initialized as u8;
+        dst[1] = self.bump;
+        dst[2] = self.milestones.len() as u8;
+
+        for (milestone, chunk) in self.milestones.iter().zip(dst[Self::HEADER_LEN..].chunks_exact_mut(Milestone::LEN)) {
+            chunk[0..8].copy_from_slice(&milestone.amount.to_le_bytes());
+            chunk[8..16].copy_from_slice(&milestone.deadline.to_le_bytes());
+            chunk[16] = milestone.released as u8;
+        }
+    }
+
+    pub fn unpack(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() < Self::HEADER_LEN || src[0] != 1 {
+            return Err(ProgramError::UninitializedAccount);
+        }
+
+        let count: usize = src[2] as usize;
+        let records: &[u8] = src.get(Self::HEADER_LEN..Self::space(count)).ok_or(ProgramError::InvalidAccountData)?;
+
+        let milestones: Vec<Milestone> = records.chunks_exact(Milestone::LEN)
+            .map(|chunk| Ok(Milestone {
+                amount: u64::from_le_bytes(chunk[0..8].try_into().map_err(|_| ProgramError::InvalidAccountData)?),
+                deadline: i64::from_le_bytes(chunk[8..16].try_into().map_err(|_| ProgramError::InvalidAccountData)?),
+                released: chunk[16] == 1
+            }))
+            .collect::<Result<_, ProgramError>>()?;
+
+        Ok(Self {
+            is_initialized: true,
+            bump: src[1],
+            milestones
+        })
+    }
+}
+
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "idl", derive(shank::ShankType))]
+pub struct ClaimShare {
+    pub assignee: Pubkey,
+    /// share of the payout, in basis points
+    pub bps: u16
+}
+
+impl ClaimShare {
+    /// assignee (32) + bps (2)
+    pub const LEN: usize = 34;
+}
+
+/// Child account of an escrow (seeds: `"assignments"`, escrow_pda) listing the shares of the payout the recipient sold.
+/// `Complete` pays every assignee its share, the recipient keeps the rest.
+#[derive(Debug, PartialEq)]
+pub struct EscrowAssignments {
+    pub is_initialized: bool,
+    pub bump: u8,
+    pub shares: Vec<ClaimShare>
+}
+
+impl EscrowAssignments {
+    /// is_initialized (1) + bump (1) + share count (1)
+    pub const HEADER_LEN: usize = 3;
+    pub const MAX_SHARES: usize = 8;
+
+    pub fn new(bump: u8) -> Self {
+        Self {
+            is_initialized: true,
+            bump,
+            shares: Vec::new()
+        }
+    }
+
+    pub fn space(shares: usize) -> usize {
+        Self::HEADER_LEN + shares * ClaimShare::LEN
+    }
+
+    pub fn assigned_bps(&self) -> u32 {
+        self.shares.iter().map(|share| share.bps as u32).sum()
+    }
+
+    /// Part of `amount` `beneficiary` gets: its shares' bps of it, rounded down, or the rest for the `recipient`
+    /// (rounding included), so the parts add up to `amount`. `None` for anyone else.
+    pub fn allocation(&self, amount: u64, recipient: &Pubkey, beneficiary: &Pubkey) -> Option<u64> {
+        let part = |bps: u16| (amount as u128 * bps as u128 / 10_000) as u64;
+
+        if beneficiary == recipient {
+            return Some(amount - self.shares.iter().map(|share| part(share.bps)).sum::<u64>());
+        }
+
+        self.shares.iter()
+            .filter(|share| &share.assignee == beneficiary)
+            .map(|share| part(share.bps))
+            .reduce(|total, allocation| total + allocation)
+    }
+
+    pub fn pack_into_slice(&self, dst: &mut [u8]) {
+        dst[0] = self.is_initialized as u8;
+        dst[1] = self.bump;
+        dst[2] = self.shares.len() as u8;
+
+        for (share, chunk) in self.shares.iter().zip(dst[Self::HEADER_LEN..].chunks_exact_mut(ClaimShare::LEN)) {
+            chunk[0..32].copy_from_slice(share.assignee.as_ref());
+            chunk[32..34].copy_from_slice(&share.bps.to_le_bytes());
+        }
+    }
+
+    pub fn unpack(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() < Self::HEADER_LEN || src[0] != 1 {
+            return Err(ProgramError::UninitializedAccount);
+        }
+
+        let count: usize = src[2] as usize;
+        let records: &[u8] = src.get(Self::HEADER_LEN..Self::space(count)).ok_or(ProgramError::InvalidAccountData)?;
+
+        let shares: Vec<ClaimShare> = records.chunks_exact(ClaimShare::LEN)
+            .map(|chunk| Ok(ClaimShare {
+                assignee: Pubkey::new_from_array(chunk[0..32].try_into().map_err(|_| ProgramError::InvalidAccountData)?),
+                bps: u16::from_le_bytes(chunk[32..34].try_into().map_err(|_| ProgramError::InvalidAccountData)?)
+            }))
+            .collect::<Result<_, ProgramError>>()?;
+
+        Ok(Self {
+            is_initialized: true,
+            bump: src[1],
+            shares
+        })
+    }
+}
+
+/// Child account of a team vesting escrow (seeds: `"vesting_share"`, escrow_pda, beneficiary), tracking what one
+/// beneficiary withdrew of its `allocation` (see `EscrowAssignments::allocation`).
+#[derive(Debug, PartialEq)]
+pub struct VestingShare {
+    pub is_initialized: bool,
+    pub bump: u8,
+    pub escrow: Pubkey,
+    pub beneficiary: Pubkey,
+    pub allocation: u64,
+    pub withdrawn: u64
+}
+
+impl VestingShare {
+    /// Part of `allocation` vested at `now`, on the schedule of `escrow_instance`.
+    pub fn vested(&self, escrow_instance: &EscrowAccount, now: i64) -> u64 {
+        // `vested()` is at most `amount`, so this is at most `allocation`
+        (self.allocation as u128 * escrow_instance.vested(now) as u128 / escrow_instance.amount.max(1) as u128) as u64
+    }
+}
+
+impl IsInitialized for VestingShare {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Sealed for VestingShare {}
+
+impl Pack for VestingShare {
+    const LEN: usize = 82;
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        dst[0] = self.is_initialized as u8;
+        dst[1] = self.bump;
+        dst[2..34].copy_from_slice(self.escrow.as_ref());
+        dst[34..66].copy_from_slice(self.beneficiary.as_ref());
+        dst[66..74].copy_from_slice(&self.allocation.to_le_bytes());
+        dst[74..82].copy_from_slice(&self.withdrawn.to_le_bytes());
+    }
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        Ok(Self {
+            is_initialized: src[0] == 1,
+            bump: src[1],
+            escrow: Pubkey::new_from_array(src[2..34].try_into().map_err(|_| ProgramError::InvalidAccountData)?),
+            beneficiary: Pubkey::new_from_array(src[34..66].try_into().map_err(|_| ProgramError::InvalidAccountData)?),
+            allocation: u64::from_le_bytes(src[66..74].try_into().map_err(|_| ProgramError::InvalidAccountData)?),
+            withdrawn: u64::from_le_bytes(src[74..82].try_into().map_err(|_| ProgramError::InvalidAccountData)?)
+        })
+    }
+}
+
+/// Index of the escrows whose deadline falls on one UTC day (seeds: `"deadlines"`, day as i64 LE bytes),
+/// so notifiers can look up "what expires tomorrow" instead of scanning every escrow. Escrows are listed by
+/// `IndexDeadline` and dropped by `PruneDeadlines` once settled, so entries may be stale until pruned.
+#[derive(Debug, PartialEq)]
+pub struct DeadlineBucket {
+    pub is_initialized: bool,
+    pub bump: u8,
+    /// days since the unix epoch, see `day_of`
+    pub day: i64,
+    pub escrows: Vec<Pubkey>
+}
+
+impl DeadlineBucket {
+    /// is_initialized (1) + bump (1) + day (8) + escrow count (2)
+    pub const HEADER_LEN: usize = 12;
+    pub const MAX_ESCROWS: usize = 256;
+    const SECONDS_PER_DAY: i64 = 24 * 60 * 60;
+
+    pub fn new(bump: u8, day: i64) -> Self {
+        Self {
+            is_initialized: true,
+            bump,
+            day,
+            escrows: Vec::new()
+        }
+    }
+
+    /// Bucket (UTC day) of a unix timestamp.
+    pub fn day_of(timestamp: i64) -> i64 {
+        timestamp.div_euclid(Self::SECONDS_PER_DAY)
+    }
+
+    pub fn space(escrows: usize) -> usize {
+        Self::HEADER_LEN + escrows * 32
+    }
+
+    pub fn pack_into_slice(&self, dst: &mut [u8]) {
+        dst[0] = self.is_initialized as u8;
+        dst[1] = self.bump;
+        dst[2..10].copy_from_slice(&self.day.to_le_bytes());
+        dst[10..12].copy_from_slice(&(self.escrows.len() as u16).to_le_bytes());
+
+        for (escrow, chunk) in self.escrows.iter().zip(dst[Self::HEADER_LEN..].chunks_exact_mut(32)) {
+            chunk.copy_from_slice(escrow.as_ref());
+        }
+    }
+
+    pub fn unpack(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() < Self::HEADER_LEN || src[0] != 1 {
+            return Err(ProgramError::UninitializedAccount);
+        }
+
+        let count: usize = u16::from_le_bytes(src[10..12].try_into().map_err(|_| ProgramError::InvalidAccountData)?) as usize;
+        let records: &[u8] = src.get(Self::HEADER_LEN..Self::space(count)).ok_or(ProgramError::InvalidAccountData)?;
+
+        let escrows: Vec<Pubkey> = records.chunks_exact(32)
+            .map(|chunk| Ok(Pubkey::new_from_array(chunk.try_into().map_err(|_| ProgramError::InvalidAccountData)?)))
+            .collect::<Result<_, ProgramError>>()?;
+
+        Ok(Self {
+            is_initialized: true,
+            bump: src[1],
+            day: i64::from_le_bytes(src[2..10].try_into().map_err(|_| ProgramError::InvalidAccountData)?),
+            escrows
+        })
+    }
+}
+
+/// Named milestone presets for `InitializeFromTemplate`, so clients don't have to assemble the common schedules themselves.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "idl", derive(shank::ShankType))]
+#[cfg_attr(test, derive(borsh::BorshSerialize))]
+pub enum MilestoneTemplate {
+    /// the usual freelance split: 50% / 30% / 20%, each due 30 days after the previous one
+    Freelance50_30_20,
+    /// 50% / 50%, each due 30 days after the previous one
+    Halves
+}
+
+impl MilestoneTemplate {
+    const MILESTONE_PERIOD: i64 = 30 * 24 * 60 * 60;
+
+    fn shares_bps(&self) -> &'static [u64] {
+        match self {
+            Self::Freelance50_30_20 => &[5_000, 3_000, 2_000],
+            Self::Halves => &[5_000, 5_000]
+        }
+    }
+
+    /// Splits `amount` by the template's shares (the last milestone takes the rounding remainder),
+    /// with deadlines counted from `start`.
+    pub fn schedule(&self, amount: u64, start: i64) -> Result<Vec<Milestone>, ProgramError> {
+        let shares: &[u64] = self.shares_bps();
+        let mut remaining: u64 = amount;
+        let mut milestones: Vec<Milestone> = Vec::with_capacity(shares.len());
+
+        for (index, share) in shares.iter().enumerate() {
+            let milestone_amount: u64 = if index + 1 == shares.len() {
+                remaining
+            } else {
+                u64::try_from(amount as u128 * *share as u128 / 10_000).map_err(|_| ProgramError::ArithmeticOverflow)?
+            };
+            remaining -= milestone_amount;
+
+            milestones.push(Milestone {
+                amount: milestone_amount,
+                deadline: start.checked_add(Self::MILESTONE_PERIOD * (index as i64 + 1)).ok_or(ProgramError::ArithmeticOverflow)?,
+                released: false
+            });
+        }
+
+        Ok(milestones)
+    }
+}
+
+impl TryFrom<u8> for MilestoneTemplate {
+    type Error = ProgramError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        Ok(match value {
+            0 => Self::Freelance50_30_20,
+            1 => Self::Halves,
+            _ => return Err(ProgramError::InvalidInstructionData)
+        })
+    }
+}
+
+
+/// Compact audit record of a closed escrow, appended to the payer's `EscrowArchive`.
+#[derive(Debug, PartialEq)]
+pub struct ArchivedEscrow {
+    /// sha256 of the escrow's final state, see `state_hash()`
+    pub state_hash: [u8; 32],
+    /// unix timestamps, in seconds (they fit into u32 until 2106)
+    pub created_at: u32,
+    pub closed_at: u32,
+    /// `ArchivedEscrow::OUTCOME_*`
+    pub outcome: u8,
+    /// unix timestamp the recipient acknowledged receiving the payout at (`AcknowledgeReceipt`), 0 if they haven't
+    pub acknowledged_at: u32
+    // 3 reserved bytes round the record up to 48
+}
+
+impl ArchivedEscrow {
+    pub const OUTCOME_COMPLETED: u8 = 0;
+    pub const OUTCOME_CLOSED: u8 = 1;
+
+    /// `state_hash` of the escrow at `escrow_pkey` holding `lamports` and `data` when it was archived.
+    pub fn state_hash(escrow_pkey: &Pubkey, lamports: u64, data: &[u8]) -> [u8; 32] {
+        hashv(&[escrow_pkey.as_ref(), &lamports.to_le_bytes(), data]).to_bytes()
+    }
+}
+
+impl Sealed for ArchivedEscrow {}
+
+impl Pack for ArchivedEscrow {
+    const LEN: usize = 48;
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        dst[0..32].copy_from_slice(&self.state_hash);
+        dst[32..36].copy_from_slice(&self.created_at.to_le_bytes());
+        dst[36..40].copy_from_slice(&self.closed_at.to_le_bytes());
+        dst[40] = self.outcome;
+        dst[41..45].copy_from_slice(&self.acknowledged_at.to_le_bytes());
+        dst[45..48].fill(0);
+    }
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        Ok(Self {
+            state_hash: src[0..32].try_into().map_err(|_| ProgramError::InvalidAccountData)?,
+            created_at: u32::from_le_bytes(src[32..36].try_into().map_err(|_| ProgramError::InvalidAccountData)?),
+            closed_at: u32::from_le_bytes(src[36..40].try_into().map_err(|_| ProgramError::InvalidAccountData)?),
+            outcome: src[40],
+            acknowledged_at: u32::from_le_bytes(src[41..45].try_into().map_err(|_| ProgramError::InvalidAccountData)?)
+        })
+    }
+}
+
+/// Per-payer account (seeds: `"archive"`, payer) holding a header followed by `ArchivedEscrow` records.
+/// It grows by `ArchivedEscrow::LEN` bytes with every archived escrow, so only the header is stored here.
+#[derive(Debug, PartialEq)]
+pub struct EscrowArchive {
+    pub is_initialized: bool,
+    pub bump: u8
+}
+
+impl EscrowArchive {
+    /// is_initialized (1) + bump (1)
+    pub const HEADER_LEN: usize = 2;
+
+    pub fn new(bump: u8) -> Self {
+        Self {
+            is_initialized: true,
+            bump
+        }
+    }
+
+    /// Account size of an archive holding `records` records.
+    pub fn space(records: usize) -> usize {
+        Self::HEADER_LEN + records * ArchivedEscrow::LEN
+    }
+
+    pub fn pack_into_slice(&self, dst: &mut [u8]) {
+        dst[0] = self.is_initialized as u8;
+        dst[1] = self.bump;
+    }
+
+    pub fn unpack(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() < Self::HEADER_LEN || src[0] != 1 || !(src.len() - Self::HEADER_LEN).is_multiple_of(ArchivedEscrow::LEN) {
+            return Err(ProgramError::UninitializedAccount);
+        }
+
+        Ok(Self {
+            is_initialized: true,
             bump: src[1]
         })
     }
-}
\ No newline at end of file
+
+    /// Records stored in the archive's data, oldest first.
+    pub fn records(src: &[u8]) -> Result<Vec<ArchivedEscrow>, ProgramError> {
+        Self::unpack(src)?;
+
+        src[Self::HEADER_LEN..].chunks_exact(ArchivedEscrow::LEN)
+            .map(ArchivedEscrow::unpack_from_slice)
+            .collect()
+    }
+}
+
+
+/// Program configuration: the global one (seeds: `"config"`) or a namespace's (seeds: `"config"`, namespace),
+/// see `InitializeConfig`.
+#[derive(Debug, PartialEq)]
+pub struct Config {
+    pub is_initialized: bool,
+    pub bump: u8,
+    pub admin: Pubkey,
+    /// bitmask of `Config::PAUSE_*` flags, e.g. pausing only `PAUSE_INITIALIZE` winds the program down
+    /// while existing escrows can still be settled
+    pub paused: u8,
+    /// protocol fee taken off the recipient's payout of SOL escrows whenever they pay out (see `SetProtocolFee` for
+    /// the exemptions), sent to the fee vault (seeds: `"fee_vault"`)
+    pub fee_bps: u16,
+    /// Solana Attestation Service credential whose attestations escrows requiring one accept (default pubkey if none)
+    pub attestation_issuer: Pubkey,
+    /// operator-chosen namespace, the default pubkey for the global `Config`
+    pub namespace: Pubkey,
+    /// bounds on the lamports a SOL escrow locks at initialization, `max_amount` 0 meaning unlimited
+    pub min_amount: u64,
+    pub max_amount: u64,
+    /// admin nominated by `NominateAdmin`, default pubkey if none
+    pub pending_admin: Pubkey,
+    /// bitmask of the `Config::FEATURE_*` instruction families enabled in this `Config`, see `SetFeatures`
+    pub features: u8,
+    /// stake pool idle fees are swept into (`SweepFees`), default pubkey if none
+    pub yield_venue: Pubkey,
+    /// lamports swept into the yield venue and not redeemed yet
+    pub swept_principal: u64,
+    /// lamports redeemed from the yield venue on top of the principal, over the venue's lifetime
+    pub realized_yield: u64
+}
+
+impl Config {
+    /// halts every instruction creating an escrow (`Initialize` & its variants, `InitializeToken`, `Reserve`, `Fund`)
+    pub const PAUSE_INITIALIZE: u8 = 1 << 0;
+    /// halts the instructions releasing funds to recipients (`Complete`, `BatchSettle`, `Claim`, `CompleteUsd`,
+    /// `ApproveMilestone`, `WithdrawVested`, `WithdrawVestedShare`, swap settlements)
+    pub const PAUSE_COMPLETE: u8 = 1 << 1;
+    /// halts the instructions refunding payers (`Close`, `BatchClose`, `CancelMutual`, `GovernanceVeto`, swap cancellations)
+    pub const PAUSE_CLOSE: u8 = 1 << 2;
+    /// the whole switch, halting every instruction that creates an escrow or moves escrowed funds
+    pub const PAUSE_ALL: u8 = Self::PAUSE_INITIALIZE | Self::PAUSE_COMPLETE | Self::PAUSE_CLOSE;
+
+    /// token & NFT escrows (`InitializeToken`, `InitializeNft`)
+    pub const FEATURE_TOKENS: u8 = 1 << 0;
+    /// oracle-priced terms (`SetUsdTerms`, `SetDepegGuard`)
+    pub const FEATURE_ORACLES: u8 = 1 << 1;
+    /// swaps between escrows (`OpenSwap`, `SettleSwap`)
+    pub const FEATURE_SWAPS: u8 = 1 << 2;
+    /// every family this build knows of, which new configs start with. A family added later is off
+    /// in existing configs until the admin enables it.
+    pub const FEATURES_ALL: u8 = Self::FEATURE_TOKENS | Self::FEATURE_ORACLES | Self::FEATURE_SWAPS;
+
+    pub const MAX_FEE_BPS: u16 = 1_000;
+
+    pub fn new(bump: u8, admin: Pubkey) -> Self {
+        Self {
+            is_initialized: true,
+            bump,
+            admin,
+            paused: 0,
+            fee_bps: 0,
+            attestation_issuer: Pubkey::default(),
+            namespace: Pubkey::default(),
+            min_amount: 0,
+            max_amount: 0,
+            pending_admin: Pubkey::default(),
+            features: Self::FEATURES_ALL,
+            yield_venue: Pubkey::default(),
+            swept_principal: 0,
+            realized_yield: 0
+        }
+    }
+
+    pub fn is_paused(&self, flag: u8) -> bool {
+        self.paused & flag != 0
+    }
+
+    pub fn is_enabled(&self, feature: u8) -> bool {
+        self.features & feature == feature
+    }
+
+    pub fn check_amount(&self, amount: u64) -> Result<(), ProgramError> {
+        if amount < self.min_amount {
+            return Err(EscrowError::AmountTooSmall.into());
+        }
+
+        if self.max_amount != 0 && amount > self.max_amount {
+            return Err(EscrowError::AmountTooLarge.into());
+        }
+
+        Ok(())
+    }
+
+    pub fn protocol_fee(&self, amount: u64) -> Result<u64, ProgramError> {
+        u64::try_from(amount as u128 * self.fee_bps as u128 / 10_000).map_err(|_| ProgramError::ArithmeticOverflow)
+    }
+
+    /// Accounts for `received` lamports redeemed from the yield venue: they repay the swept principal first, the rest
+    /// being yield. Once the treasury's pool tokens are `exhausted`, whatever principal they didn't repay is written off
+    /// as the venue's loss. Returns the principal repaid, the yield and the principal written off.
+    pub fn record_redemption(&mut self, received: u64, exhausted: bool) -> (u64, u64, u64) {
+        let principal: u64 = received.min(self.swept_principal);
+        let yield_amount: u64 = received - principal;
+
+        self.swept_principal -= principal;
+        self.realized_yield = self.realized_yield.saturating_add(yield_amount);
+
+        let written_off: u64 = match exhausted {
+            true => std::mem::take(&mut self.swept_principal),
+            false => 0
+        };
+
+        (principal, yield_amount, written_off)
+    }
+}
+
+impl IsInitialized for Config {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Sealed for Config {}
+
+impl Pack for Config {
+    const LEN: usize = 198;
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        dst[0] = self.is_initialized as u8;
+        dst[1] = self.bump;
+        dst[2..34].copy_from_slice(self.admin.as_ref());
+        dst[34] = self.paused;
+        dst[35..37].copy_from_slice(&self.fee_bps.to_le_bytes());
+        dst[37..69].copy_from_slice(self.attestation_issuer.as_ref());
+        dst[69..101].copy_from_slice(self.namespace.as_ref());
+        dst[101..109].copy_from_slice(&self.min_amount.to_le_bytes());
+        dst[109..117].copy_from_slice(&self.max_amount.to_le_bytes());
+        dst[117..149].copy_from_slice(self.pending_admin.as_ref());
+        dst[149] = self.features;
+        dst[150..182].copy_from_slice(self.yield_venue.as_ref());
+        dst[182..190].copy_from_slice(&self.swept_principal.to_le_bytes());
+        dst[190..198].copy_from_slice(&self.realized_yield.to_le_bytes());
+    }
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        Ok(Self {
+            is_initialized: src[0] == 1,
+            bump: src[1],
+            admin: Pubkey::new_from_array(src[2..34].try_into().map_err(|_| ProgramError::InvalidAccountData)?),
+            paused: src[34],
+            fee_bps: u16::from_le_bytes(src[35..37].try_into().map_err(|_| ProgramError::InvalidAccountData)?),
+            attestation_issuer: Pubkey::new_from_array(src[37..69].try_into().map_err(|_| ProgramError::InvalidAccountData)?),
+            namespace: Pubkey::new_from_array(src[69..101].try_into().map_err(|_| ProgramError::InvalidAccountData)?),
+            min_amount: u64::from_le_bytes(src[101..109].try_into().map_err(|_| ProgramError::InvalidAccountData)?),
+            max_amount: u64::from_le_bytes(src[109..117].try_into().map_err(|_| ProgramError::InvalidAccountData)?),
+            pending_admin: Pubkey::new_from_array(src[117..149].try_into().map_err(|_| ProgramError::InvalidAccountData)?),
+            features: src[149],
+            yield_venue: Pubkey::new_from_array(src[150..182].try_into().map_err(|_| ProgramError::InvalidAccountData)?),
+            swept_principal: u64::from_le_bytes(src[182..190].try_into().map_err(|_| ProgramError::InvalidAccountData)?),
+            realized_yield: u64::from_le_bytes(src[190..198].try_into().map_err(|_| ProgramError::InvalidAccountData)?)
+        })
+    }
+}
+
+
+/// Marker account (seeds: `"blocked_mint"`, mint) managed by the admin.
+/// Its existence means the mint cannot be escrowed.
+#[derive(Debug, PartialEq)]
+pub struct BlockedMint {
+    pub is_initialized: bool,
+    pub bump: u8,
+    pub mint: Pubkey
+}
+
+impl BlockedMint {
+    pub fn new(bump: u8, mint: Pubkey) -> Self {
+        Self {
+            is_initialized: true,
+            bump,
+            mint
+        }
+    }
+}
+
+impl IsInitialized for BlockedMint {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Sealed for BlockedMint {}
+
+impl Pack for BlockedMint {
+    const LEN: usize = 34;
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        dst[0] = self.is_initialized as u8;
+        dst[1] = self.bump;
+        dst[2..34].copy_from_slice(self.mint.as_ref());
+    }
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        Ok(Self {
+            is_initialized: src[0] == 1,
+            bump: src[1],
+            mint: Pubkey::new_from_array(src[2..34].try_into().map_err(|_| ProgramError::InvalidAccountData)?)
+        })
+    }
+}
+
+
+/// Child account of an escrow (seeds: `"display"`, escrow_pda) with optional hints for wallets and explorers,
+/// so an escrow from "Acme Payroll" shows up as such in an activity feed.
+#[derive(Debug, PartialEq)]
+pub struct EscrowDisplay {
+    pub is_initialized: bool,
+    pub bump: u8,
+    /// utf-8, at most `MAX_LABEL_LEN` bytes
+    pub label: String,
+    /// hash of the icon image, zeroed if none
+    pub icon_hash: [u8; 32]
+}
+
+impl EscrowDisplay {
+    pub const MAX_LABEL_LEN: usize = 32;
+
+    pub fn new(bump: u8, label: String, icon_hash: [u8; 32]) -> Self {
+        Self {
+            is_initialized: true,
+            bump,
+            label,
+            icon_hash
+        }
+    }
+}
+
+impl IsInitialized for EscrowDisplay {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Sealed for EscrowDisplay {}
+
+impl Pack for EscrowDisplay {
+    /// is_initialized (1) + bump (1) + label length (1) + label (32) + icon_hash (32)
+    const LEN: usize = 67;
+
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        dst[0] = self.is_initialized as u8;
+        dst[1] = self.bump;
+        dst[2] = self.label.len() as u8;
+        dst[3..35].fill(0);
+        dst[3..3 + self.label.len()].copy_from_slice(self.label.as_bytes());
+        dst[35..67].copy_from_slice(&self.icon_hash);
+    }
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let label_len: usize = src[2] as usize;
+
+        if label_len > Self::MAX_LABEL_LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        Ok(Self {
+            is_initialized: src[0] == 1,
+            bump: src[1],
+            label: String::from_utf8(src[3..3 + label_len].to_vec()).map_err(|_| ProgramError::InvalidAccountData)?,
+            icon_hash: src[35..67].try_into().map_err(|_| ProgramError::InvalidAccountData)?
+        })
+    }
+}
@@ -0,0 +1,298 @@
+//! Reusable adversarial scenarios for `solana-program-test`, shipped under the `test-utils` feature.
+//!
+//! Every scenario takes an instruction that is known to succeed, mutates it (or its surroundings) into an attack,
+//! and asserts that the program rejects it. New instructions get the same coverage by passing their happy-path
+//! instruction to `assert_attacks_rejected()`.
+use solana_program::program_pack::Pack;
+use solana_program_test::{ProgramTest, ProgramTestContext, processor};
+use solana_sdk::{
+    account::{AccountSharedData, WritableAccount},
+    instruction::{Instruction, AccountMeta},
+    pubkey::Pubkey,
+    signature::Keypair,
+    signer::Signer,
+    system_instruction,
+    system_program,
+    transaction::Transaction
+};
+use super::{
+    state::EscrowAccount,
+    model::{EscrowModel, ModelEscrow, ModelOp}
+};
+
+
+pub type ScenarioResult = Result<(), Box<dyn std::error::Error>>;
+
+#[derive(Clone, Copy, Debug)]
+pub enum Attack {
+    /// Every signer except the fee payer is demoted to a non-signer.
+    WrongSigner,
+    /// The account at `account_index` is replaced with an unrelated address.
+    WrongPda { account_index: usize },
+    /// The account at `account_index` keeps its data but is re-owned by another program.
+    WrongOwner { account_index: usize },
+    /// The instruction is executed twice in the same transaction.
+    Replay,
+    /// A preceding instruction in the same transaction drains every lamport of the signer at `account_index`.
+    DrainSigner { account_index: usize }
+}
+
+pub fn program_test() -> ProgramTest {
+    ProgramTest::new(
+        "escrow",
+        crate::ID,
+        processor!(crate::entrypoint::process_instruction)
+    )
+}
+
+/// Stores the program's `ProgramData` account with `authority` as its upgrade authority, as a deployment through
+/// the upgradeable loader would have it, so `authority` can create the global `Config`.
+pub fn set_upgrade_authority(context: &mut ProgramTestContext, authority: &Pubkey) {
+    let mut data: Vec<u8> = Vec::with_capacity(45);
+    data.extend_from_slice(&3u32.to_le_bytes());
+    data.extend_from_slice(&0u64.to_le_bytes());
+    data.push(1);
+    data.extend_from_slice(authority.as_ref());
+
+    let mut program_data: AccountSharedData = AccountSharedData::new(1_000_000_000, data.len(), &solana_program::bpf_loader_upgradeable::ID);
+    program_data.set_data_from_slice(&data);
+    context.set_account(&crate::get_program_data_address(&crate::ID), &program_data);
+}
+
+/// Creates a keypair funded by the context's fee payer, so it can act as a party distinct from the fee payer.
+pub async fn funded_keypair(context: &mut ProgramTestContext, lamports: u64) -> Result<Keypair, Box<dyn std::error::Error>> {
+    let keypair: Keypair = Keypair::new();
+    let transfer_ix: Instruction = system_instruction::transfer(&context.payer.pubkey(), &keypair.pubkey(), lamports);
+
+    send(context, &[transfer_ix], &[]).await?;
+
+    Ok(keypair)
+}
+
+/// Sends `instructions` with the context's payer as fee payer, plus `signers`.
+pub async fn send(context: &mut ProgramTestContext, instructions: &[Instruction], signers: &[&Keypair]) -> ScenarioResult {
+    let latest_blockhash = context.get_new_latest_blockhash().await?;
+
+    let mut all_signers: Vec<&Keypair> = vec![&context.payer];
+    all_signers.extend_from_slice(signers);
+
+    let tx: Transaction = Transaction::new_signed_with_payer(
+        instructions,
+        Some(&context.payer.pubkey()),
+        &all_signers,
+        latest_blockhash
+    );
+    context.banks_client.process_transaction(tx).await?;
+
+    Ok(())
+}
+
+/// Runs every attack against `instruction` and fails on the first one the program accepts.
+pub async fn assert_attacks_rejected(
+    context: &mut ProgramTestContext,
+    instruction: &Instruction,
+    signers: &[&Keypair],
+    attacks: &[Attack]
+) -> ScenarioResult {
+    for attack in attacks {
+        assert_attack_rejected(context, instruction, signers, *attack).await?;
+    }
+
+    Ok(())
+}
+
+pub async fn assert_attack_rejected(
+    context: &mut ProgramTestContext,
+    instruction: &Instruction,
+    signers: &[&Keypair],
+    attack: Attack
+) -> ScenarioResult {
+    let result: ScenarioResult = match attack {
+        Attack::WrongSigner => {
+            let mut ix: Instruction = instruction.clone();
+            let fee_payer: Pubkey = context.payer.pubkey();
+
+            ix.accounts.iter_mut()
+                .filter(|meta| meta.pubkey != fee_payer)
+                .for_each(|meta| meta.is_signer = false);
+
+            send(context, &[ix], &[]).await
+        },
+        Attack::WrongPda { account_index } => {
+            let mut ix: Instruction = instruction.clone();
+            ix.accounts.get_mut(account_index).ok_or("account index out of bounds")?.pubkey = Pubkey::new_unique();
+
+            send(context, &[ix], signers).await
+        },
+        Attack::WrongOwner { account_index } => {
+            let address: Pubkey = instruction.accounts.get(account_index).ok_or("account index out of bounds")?.pubkey;
+            let original = context.banks_client.get_account(address).await?.ok_or("account to re-own doesn't exist")?;
+
+            let mut spoofed: AccountSharedData = AccountSharedData::from(original.clone());
+            spoofed.set_owner(Pubkey::new_unique());
+            context.set_account(&address, &spoofed);
+
+            let result: ScenarioResult = send(context, &[instruction.clone()], signers).await;
+
+            context.set_account(&address, &AccountSharedData::from(original));
+            result
+        },
+        Attack::Replay => send(context, &[instruction.clone(), instruction.clone()], signers).await,
+        Attack::DrainSigner { account_index } => {
+            let meta = instruction.accounts.get(account_index).ok_or("account index out of bounds")?;
+            let victim: &Keypair = signers.iter()
+                .copied()
+                .find(|signer| signer.pubkey() == meta.pubkey)
+                .ok_or("account to drain must be one of the signers")?;
+
+            let balance: u64 = context.banks_client.get_balance(victim.pubkey()).await?;
+            let drain_ix: Instruction = system_instruction::transfer(&victim.pubkey(), &Pubkey::new_unique(), balance);
+
+            send(context, &[drain_ix, instruction.clone()], signers).await
+        }
+    };
+
+    if result.is_ok() {
+        return Err(format!("attack {:?} was accepted", attack).into());
+    }
+
+    Ok(())
+}
+
+
+/// Deterministic xorshift generator, so a failing differential seed can be replayed.
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Self(seed.max(1))
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    pub fn below(&mut self, n: u64) -> u64 {
+        self.next_u64() % n
+    }
+}
+
+/// Applies `steps` random instructions to both `EscrowModel` and the program, asserting after every step that
+/// both accepted/rejected the instruction alike and ended up in the same state.
+pub async fn run_differential(seed: u64, steps: usize) -> ScenarioResult {
+    const PARTIES: usize = 2;
+
+    let mut context: ProgramTestContext = program_test().start_with_context().await;
+    let mut rng: Rng = Rng::new(seed);
+
+    let rent_exempt: u64 = crate::rent::escrow(&context.banks_client.get_rent().await?);
+    let mut model: EscrowModel = EscrowModel::new(rent_exempt);
+
+    let mut payers: Vec<Keypair> = Vec::with_capacity(PARTIES);
+    for _ in 0..PARTIES {
+        payers.push(funded_keypair(&mut context, 10_000_000_000).await?);
+    }
+    let recipients: Vec<Pubkey> = (0..PARTIES).map(|_| Pubkey::new_unique()).collect();
+
+    for step in 0..steps {
+        let payer: usize = rng.below(PARTIES as u64) as usize;
+        let recipient: usize = rng.below(PARTIES as u64) as usize;
+
+        // amounts straddle the minimum, so rejected initializations are covered too
+        let op: ModelOp = match rng.below(3) {
+            0 => ModelOp::Initialize { payer, recipient, amount: rent_exempt - 1 + rng.below(3 * rent_exempt) },
+            1 => ModelOp::Complete { payer, recipient },
+            _ => ModelOp::Close { payer, recipient }
+        };
+
+        let ix: Instruction = model_op_instruction(op, &payers[payer].pubkey(), &recipients[recipient]);
+        let accepted: bool = send(&mut context, &[ix], &[&payers[payer]]).await.is_ok();
+        let expected: bool = model.apply(op);
+
+        if accepted != expected {
+            return Err(format!("seed {} step {}: {:?} accepted = {}, model expected {}", seed, step, op, accepted, expected).into());
+        }
+
+        // compare every escrow and every recipient balance
+        for (payer_index, payer) in payers.iter().enumerate() {
+            for (recipient_index, recipient_pkey) in recipients.iter().enumerate() {
+                let (seed1, seed2, seed3) = crate::get_escrow_seeds(&payer.pubkey(), recipient_pkey);
+                let (escrow_pda, _bump) = Pubkey::find_program_address(&[seed1, seed2, seed3], &crate::ID);
+
+                let actual: Option<ModelEscrow> = match context.banks_client.get_account(escrow_pda).await? {
+                    Some(account) => Some(ModelEscrow {
+                        id: EscrowAccount::unpack(&account.data)?.id,
+                        locked: account.lamports - rent_exempt
+                    }),
+                    None => None
+                };
+
+                if actual != model.escrows.get(&(payer_index, recipient_index)).copied() {
+                    return Err(format!("seed {} step {}: escrow ({}, {}) diverged after {:?}", seed, step, payer_index, recipient_index, op).into());
+                }
+            }
+        }
+
+        for (recipient_index, recipient_pkey) in recipients.iter().enumerate() {
+            let balance: u64 = context.banks_client.get_balance(*recipient_pkey).await?;
+
+            if balance != model.received.get(&recipient_index).copied().unwrap_or(0) {
+                return Err(format!("seed {} step {}: recipient {} balance diverged after {:?}", seed, step, recipient_index, op).into());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn model_op_instruction(op: ModelOp, payer_pkey: &Pubkey, recipient_pkey: &Pubkey) -> Instruction {
+    let (seed1, seed2, seed3) = crate::get_escrow_seeds(payer_pkey, recipient_pkey);
+    let (escrow_pda, _bump) = Pubkey::find_program_address(&[seed1, seed2, seed3], &crate::ID);
+    let (config_pda, _bump) = Pubkey::find_program_address(&[crate::get_config_seeds()], &crate::ID);
+
+    match op {
+        ModelOp::Initialize { amount, .. } => {
+            let mut data: Vec<u8> = Vec::with_capacity(9);
+            data.push(0);
+            data.extend_from_slice(&amount.to_le_bytes());
+
+            let (counter_pda, _bump) = Pubkey::find_program_address(&[crate::get_counter_seeds()], &crate::ID);
+
+            Instruction::new_with_bytes(
+                crate::ID,
+                &data,
+                vec![
+                    AccountMeta::new(*payer_pkey, true),
+                    AccountMeta::new_readonly(*recipient_pkey, false),
+                    AccountMeta::new(escrow_pda, false),
+                    AccountMeta::new_readonly(system_program::ID, false),
+                    AccountMeta::new(counter_pda, false),
+                    AccountMeta::new_readonly(config_pda, false)
+                ]
+            )
+        },
+        ModelOp::Complete { .. } => Instruction::new_with_bytes(
+            crate::ID,
+            &[1],
+            vec![
+                AccountMeta::new(*payer_pkey, true),
+                AccountMeta::new(*recipient_pkey, false),
+                AccountMeta::new(escrow_pda, false),
+                AccountMeta::new_readonly(config_pda, false)
+            ]
+        ),
+        ModelOp::Close { .. } => Instruction::new_with_bytes(
+            crate::ID,
+            &[2],
+            vec![
+                AccountMeta::new(*payer_pkey, true),
+                AccountMeta::new_readonly(*recipient_pkey, false),
+                AccountMeta::new(escrow_pda, false),
+                AccountMeta::new_readonly(config_pda, false)
+            ]
+        )
+    }
+}
@@ -0,0 +1,97 @@
+//! Yield venues idle protocol fees can be swept into (`SweepFees`) and redeemed from (`RedeemTreasury`).
+//!
+//! A venue is a stake pool of an allowlisted program speaking the SPL stake pool's `DepositSol` / `WithdrawSol`.
+//! The treasury PDA (seeds: `"treasury"`) is a data-less system account the program signs for: it passes swept
+//! lamports on to the pool and holds the pool tokens received for them.
+use solana_program::{
+    pubkey,
+    pubkey::Pubkey,
+    system_program,
+    sysvar,
+    account_info::AccountInfo,
+    instruction::{Instruction, AccountMeta},
+    program_error::ProgramError
+};
+
+
+/// SPL Stake Pool program, owner of the stake pools fees are swept into.
+pub const SPL_STAKE_POOL_PROGRAM_ID: Pubkey = pubkey!("SPoo1Ku8WFXoNDMHPsrGSTSG1Y47rzgn41SLUNakuHy");
+
+/// Programs whose pools `SetYieldVenue` accepts.
+pub const YIELD_VENUE_PROGRAMS: [Pubkey; 1] = [SPL_STAKE_POOL_PROGRAM_ID];
+
+// `StakePoolInstruction` tags
+const DEPOSIT_SOL: u8 = 14;
+const WITHDRAW_SOL: u8 = 16;
+
+
+/// Checks that `pool_account` is owned by an allowlisted venue program, which `pool_program_account` must be.
+pub fn check_venue(pool_account: &AccountInfo, pool_program_account: &AccountInfo) -> Result<(), ProgramError> {
+    if !YIELD_VENUE_PROGRAMS.contains(pool_account.owner) || pool_program_account.key != pool_account.owner {
+        return Err(ProgramError::IncorrectProgramId);
+    }
+
+    Ok(())
+}
+
+/// The pool's accounts `DepositSol` and `WithdrawSol` both take.
+pub struct StakePool {
+    pub program: Pubkey,
+    pub pool: Pubkey,
+    pub withdraw_authority: Pubkey,
+    pub reserve_stake: Pubkey,
+    pub manager_fee: Pubkey,
+    pub pool_mint: Pubkey,
+    pub token_program: Pubkey
+}
+
+/// `DepositSol` of `lamports` from `treasury_pkey`, minting the pool tokens to `pool_tokens_pkey` (the treasury's).
+pub fn deposit_sol(pool: &StakePool, treasury_pkey: &Pubkey, pool_tokens_pkey: &Pubkey, lamports: u64) -> Instruction {
+    let mut data: Vec<u8> = Vec::with_capacity(9);
+    data.push(DEPOSIT_SOL);
+    data.extend_from_slice(&lamports.to_le_bytes());
+
+    Instruction::new_with_bytes(
+        pool.program,
+        &data,
+        vec![
+            AccountMeta::new(pool.pool, false),
+            AccountMeta::new_readonly(pool.withdraw_authority, false),
+            AccountMeta::new(pool.reserve_stake, false),
+            AccountMeta::new(*treasury_pkey, true),
+            AccountMeta::new(*pool_tokens_pkey, false),
+            AccountMeta::new(pool.manager_fee, false),
+            // referral fees go back to the treasury's own pool tokens
+            AccountMeta::new(*pool_tokens_pkey, false),
+            AccountMeta::new(pool.pool_mint, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+            AccountMeta::new_readonly(pool.token_program, false)
+        ]
+    )
+}
+
+/// `WithdrawSol` of `pool_tokens` burnt from `pool_tokens_pkey`, the lamports going to `treasury_pkey`.
+pub fn withdraw_sol(pool: &StakePool, treasury_pkey: &Pubkey, pool_tokens_pkey: &Pubkey, pool_tokens: u64) -> Instruction {
+    let mut data: Vec<u8> = Vec::with_capacity(9);
+    data.push(WITHDRAW_SOL);
+    data.extend_from_slice(&pool_tokens.to_le_bytes());
+
+    Instruction::new_with_bytes(
+        pool.program,
+        &data,
+        vec![
+            AccountMeta::new(pool.pool, false),
+            AccountMeta::new_readonly(pool.withdraw_authority, false),
+            AccountMeta::new_readonly(*treasury_pkey, true),
+            AccountMeta::new(*pool_tokens_pkey, false),
+            AccountMeta::new(pool.reserve_stake, false),
+            AccountMeta::new(*treasury_pkey, false),
+            AccountMeta::new(pool.manager_fee, false),
+            AccountMeta::new(pool.pool_mint, false),
+            AccountMeta::new_readonly(sysvar::clock::ID, false),
+            AccountMeta::new_readonly(sysvar::stake_history::ID, false),
+            AccountMeta::new_readonly(solana_program::stake::program::ID, false),
+            AccountMeta::new_readonly(pool.token_program, false)
+        ]
+    )
+}
@@ -0,0 +1,267 @@
+//! Webhook schema of the escrow lifecycle (behind the `types` feature), shared by every off-chain component:
+//! the indexer decodes `EscrowEvent`s into these payloads (`WebhookPayload::from_event()`), the REST service serves
+//! them and the notifier posts them, all as the same JSON. Integrators that can't receive webhooks poll the REST
+//! service's `GET /events` instead, paging through an escrow's lifecycle with `EventsQuery` & `EventsPage`.
+//!
+//! Keys are base58 strings and `callback` is hex, so the payloads don't depend on how `Pubkey` serializes.
+//! Fields are only ever added; a breaking change bumps `SCHEMA_VERSION`.
+use serde::{Deserialize, Serialize};
+use crate::{events::EscrowEvent, state::Inconsistency};
+
+
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Payloads an `EventsPage` holds at most.
+pub const MAX_EVENTS_PER_PAGE: usize = 100;
+
+/// What every payload carries about its escrow, see `EscrowEvent`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EscrowRef {
+    pub id: u64,
+    pub escrow: String,
+    /// the integrator's webhook digest, hex-encoded (zeroes if none)
+    pub callback: String,
+    pub seq: u64
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EscrowCreatedPayload {
+    #[serde(flatten)]
+    pub escrow: EscrowRef,
+    pub amount: u64
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EscrowDepositedPayload {
+    #[serde(flatten)]
+    pub escrow: EscrowRef,
+    pub amount: u64
+}
+
+/// Breakdown of a `Complete`, sent right before the matching `EscrowCompletedPayload`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EscrowSettledPayload {
+    #[serde(flatten)]
+    pub escrow: EscrowRef,
+    pub principal: u64,
+    pub protocol_fee: u64,
+    pub late_fee: u64,
+    pub payout: u64,
+    pub shares: u64,
+    pub arbiter: u64,
+    pub rent_refund: u64
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EscrowCompletedPayload {
+    #[serde(flatten)]
+    pub escrow: EscrowRef,
+    pub amount: u64
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EscrowClosedPayload {
+    #[serde(flatten)]
+    pub escrow: EscrowRef
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EscrowDisputedPayload {
+    #[serde(flatten)]
+    pub escrow: EscrowRef,
+    /// the party that raised the dispute
+    pub by: String
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EscrowDisputeResolvedPayload {
+    #[serde(flatten)]
+    pub escrow: EscrowRef,
+    pub payer_amount: u64,
+    pub recipient_amount: u64
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EscrowQuarantinedPayload {
+    #[serde(flatten)]
+    pub escrow: EscrowRef,
+    /// `discriminator`, `status`, `balance` or `withdrawn`, see `Inconsistency`
+    pub reason: String
+}
+
+/// The recipient's on-chain delivery confirmation of a completed escrow, for the payer's accounting.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EscrowReceiptAcknowledgedPayload {
+    #[serde(flatten)]
+    pub escrow: EscrowRef,
+    /// the recipient, or the assignee of the claim
+    pub by: String,
+    pub acknowledged_at: i64
+}
+
+/// A lifecycle payload, tagged with its `event` name: `{"event": "escrow_created", "data": {...}}`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "event", content = "data", rename_all = "snake_case")]
+pub enum WebhookPayload {
+    EscrowCreated(EscrowCreatedPayload),
+    EscrowDeposited(EscrowDepositedPayload),
+    EscrowSettled(EscrowSettledPayload),
+    EscrowCompleted(EscrowCompletedPayload),
+    EscrowClosed(EscrowClosedPayload),
+    EscrowDisputed(EscrowDisputedPayload),
+    EscrowDisputeResolved(EscrowDisputeResolvedPayload),
+    EscrowQuarantined(EscrowQuarantinedPayload),
+    EscrowReceiptAcknowledged(EscrowReceiptAcknowledgedPayload)
+}
+
+impl WebhookPayload {
+    /// Payload of a decoded event, `None` for events outside the lifecycle (admin events, claim assignments, fees...).
+    pub fn from_event(event: &EscrowEvent) -> Option<Self> {
+        let escrow_ref = |id: &u64, escrow: &solana_program::pubkey::Pubkey, callback: &[u8; 32], seq: &u64| EscrowRef {
+            id: *id,
+            escrow: escrow.to_string(),
+            callback: callback.iter().map(|byte| format!("{:02x}", byte)).collect(),
+            seq: *seq
+        };
+
+        Some(match event {
+            EscrowEvent::Initialized { id, escrow, callback, seq, amount } => {
+                Self::EscrowCreated(EscrowCreatedPayload { escrow: escrow_ref(id, escrow, callback, seq), amount: *amount })
+            },
+            EscrowEvent::Deposited { id, escrow, callback, seq, amount } => {
+                Self::EscrowDeposited(EscrowDepositedPayload { escrow: escrow_ref(id, escrow, callback, seq), amount: *amount })
+            },
+            EscrowEvent::Settled { id, escrow, callback, seq, breakdown } => Self::EscrowSettled(EscrowSettledPayload {
+                escrow: escrow_ref(id, escrow, callback, seq),
+                principal: breakdown.principal,
+                protocol_fee: breakdown.protocol_fee,
+                late_fee: breakdown.late_fee,
+                payout: breakdown.payout,
+                shares: breakdown.shares,
+                arbiter: breakdown.arbiter,
+                rent_refund: breakdown.rent_refund
+            }),
+            EscrowEvent::Completed { id, escrow, callback, seq, amount } => {
+                Self::EscrowCompleted(EscrowCompletedPayload { escrow: escrow_ref(id, escrow, callback, seq), amount: *amount })
+            },
+            EscrowEvent::Closed { id, escrow, callback, seq } => {
+                Self::EscrowClosed(EscrowClosedPayload { escrow: escrow_ref(id, escrow, callback, seq) })
+            },
+            EscrowEvent::DisputeRaised { id, escrow, callback, seq, by } => {
+                Self::EscrowDisputed(EscrowDisputedPayload { escrow: escrow_ref(id, escrow, callback, seq), by: by.to_string() })
+            },
+            EscrowEvent::DisputeResolved { id, escrow, callback, seq, payer_amount, recipient_amount } => {
+                Self::EscrowDisputeResolved(EscrowDisputeResolvedPayload {
+                    escrow: escrow_ref(id, escrow, callback, seq),
+                    payer_amount: *payer_amount,
+                    recipient_amount: *recipient_amount
+                })
+            },
+            EscrowEvent::Quarantined { id, escrow, callback, seq, reason } => Self::EscrowQuarantined(EscrowQuarantinedPayload {
+                escrow: escrow_ref(id, escrow, callback, seq),
+                reason: String::from(match reason {
+                    Inconsistency::Discriminator => "discriminator",
+                    Inconsistency::Status => "status",
+                    Inconsistency::Balance => "balance",
+                    Inconsistency::Withdrawn => "withdrawn"
+                })
+            }),
+            EscrowEvent::ReceiptAcknowledged { id, escrow, callback, seq, by, acknowledged_at } => {
+                Self::EscrowReceiptAcknowledged(EscrowReceiptAcknowledgedPayload {
+                    escrow: escrow_ref(id, escrow, callback, seq),
+                    by: by.to_string(),
+                    acknowledged_at: *acknowledged_at
+                })
+            },
+            _ => return None
+        })
+    }
+}
+
+/// Body of a webhook request: the schema version next to the payload.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Webhook {
+    pub version: u32,
+    #[serde(flatten)]
+    pub payload: WebhookPayload
+}
+
+impl From<WebhookPayload> for Webhook {
+    fn from(payload: WebhookPayload) -> Self {
+        Self { version: SCHEMA_VERSION, payload }
+    }
+}
+
+
+/// Query of `GET /events?id=<id>&since=<seq>&limit=<limit>`: the lifecycle of escrow `id` after its event `since`
+/// (0 for all of it). Cursors are the escrow's own event sequence (see `EscrowEvent`), so a poll resumes exactly where
+/// the previous one stopped, whichever indexer instance answers it.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EventsQuery {
+    pub id: u64,
+    #[serde(default)]
+    pub since: u64,
+    /// payloads wanted at most, capped to `MAX_EVENTS_PER_PAGE`
+    #[serde(default = "default_events_limit")]
+    pub limit: usize
+}
+
+fn default_events_limit() -> usize {
+    MAX_EVENTS_PER_PAGE
+}
+
+/// Answer to an `EventsQuery`: the lifecycle payloads of an unbroken run of the escrow's events after `since`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EventsPage {
+    pub version: u32,
+    pub id: u64,
+    pub events: Vec<WebhookPayload>,
+    /// `since` of the next poll: the last event this page covers, lifecycle payload or not
+    pub next: u64,
+    /// events past `next` are indexed already, poll again right away
+    pub more: bool,
+    /// the event after `next` is missing while later ones are known: the page stops short of it, so nothing is
+    /// skipped, and `next` won't move until the indexer backfills it
+    pub missing: Option<u64>
+}
+
+impl EventsPage {
+    /// Page answering `query` from the events indexed for escrow `query.id`, in any order, duplicates included.
+    ///
+    /// Events are served in `seq` order and only as far as the sequence is unbroken. `Touched` heartbeats only tell
+    /// how far the sequence goes, and the receipt acknowledgement (`seq` 0, emitted once the escrow is over) comes
+    /// right after the escrow's last event.
+    pub fn new(query: &EventsQuery, events: &[EscrowEvent]) -> Self {
+        let limit: usize = query.limit.clamp(1, MAX_EVENTS_PER_PAGE);
+
+        let sequenced = || events.iter().filter_map(|event| match event.sequence() {
+            Some((id, seq)) if id == query.id => Some((seq, event)),
+            _ => None
+        });
+        let last_seq: u64 = sequenced().map(|(seq, _)| seq).max().unwrap_or(0);
+
+        let mut positioned: Vec<(u64, &EscrowEvent)> = sequenced()
+            .filter(|(seq, event)| !matches!(event, EscrowEvent::Touched { .. }) && (*seq != 0 || last_seq != 0))
+            .map(|(seq, event)| (if seq == 0 { last_seq + 1 } else { seq }, event))
+            .collect();
+        positioned.sort_by_key(|(position, _)| *position);
+        positioned.dedup_by_key(|(position, _)| *position);
+
+        let tail: u64 = positioned.last().map_or(last_seq, |(position, _)| last_seq.max(*position));
+        let mut next: u64 = query.since;
+        let mut payloads: Vec<WebhookPayload> = Vec::new();
+
+        for (position, event) in positioned.iter().filter(|(position, _)| *position > query.since) {
+            if *position != next + 1 || payloads.len() == limit {
+                break;
+            }
+
+            next = *position;
+            payloads.extend(WebhookPayload::from_event(event));
+        }
+
+        let missing: Option<u64> = (next < tail && !positioned.iter().any(|(position, _)| *position == next + 1)).then_some(next + 1);
+
+        Self { version: SCHEMA_VERSION, id: query.id, events: payloads, next, more: next < tail && missing.is_none(), missing }
+    }
+}